@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::ab::{self, MatchTo};
+use mastering_core::cache;
+
+#[derive(Args)]
+pub struct AbArgs {
+    /// Original (unmastered) audio file
+    pub original: PathBuf,
+
+    /// Mastered audio file
+    pub master: PathBuf,
+
+    /// Directory for the two renders and the report [default: next to the master]
+    #[arg(short, long)]
+    pub output_dir: Option<PathBuf>,
+
+    /// File whose loudness the other is brought to: master, original
+    #[arg(long, default_value = "master")]
+    pub match_to: String,
+
+    /// Print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Ignore cached analysis results and re-analyze
+    #[arg(long)]
+    pub no_cache: bool,
+}
+
+pub async fn run(args: AbArgs) -> Result<()> {
+    for path in [&args.original, &args.master] {
+        super::ensure_input(path)?;
+    }
+    let match_to: MatchTo = args.match_to.parse()?;
+    let output_dir = match args.output_dir {
+        Some(dir) => dir,
+        None => args.master.parent().map(PathBuf::from).unwrap_or_default(),
+    };
+
+    let spinner = indicatif::ProgressBar::new_spinner();
+    spinner.set_style(
+        indicatif::ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.set_message("Rendering loudness-matched files...");
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let cache = (!args.no_cache).then(cache::global_cache);
+    let export = ab::export(&args.original, &args.master, &output_dir, match_to, cache)
+        .await
+        .context("A/B export failed")?;
+
+    spinner.finish_and_clear();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&export)?);
+        return Ok(());
+    }
+
+    let d = &export.comparison.delta;
+    println!(
+        "\n{}  matched to the {} at {:.1} LUFS",
+        "A/B".bold().cyan(),
+        export.matched_to,
+        export.lufs_integrated
+    );
+    println!(
+        "  A: {} ({:+.1} dB)",
+        export.original_path.display().to_string().white(),
+        export.original_gain_db
+    );
+    println!(
+        "  B: {} ({:+.1} dB)",
+        export.master_path.display().to_string().white(),
+        export.master_gain_db
+    );
+    println!("  Report: {}", export.report_path.display());
+
+    println!("\n{}", "Master - Original".bold().yellow());
+    println!("  True Peak:         {:+.1} dB", d.true_peak_db);
+    println!("  Dynamic Range:     {:+.1} dB", d.dynamic_range_db);
+    println!("  Stereo Width:      {:+.2}", d.stereo_width);
+    let bands = &d.frequency_bands;
+    println!(
+        "  Bands (dB):        sub {:+.1}  bass {:+.1}  low-mid {:+.1}  mid {:+.1}  upper-mid {:+.1}  presence {:+.1}  brilliance {:+.1}",
+        bands.sub_bass, bands.bass, bands.low_mid, bands.mid, bands.upper_mid, bands.presence, bands.brilliance
+    );
+
+    println!();
+    Ok(())
+}
@@ -3,7 +3,12 @@ use clap::Args;
 use colored::Colorize;
 use std::path::PathBuf;
 
-use mastering_core::analysis;
+use mastering_core::analysis::diagnosis::{self, Severity};
+use mastering_core::analysis::match_eq::SpectralCurve;
+use mastering_core::analysis::tempo;
+use mastering_core::curves::CurveStore;
+use mastering_core::types::TimeRange;
+use mastering_core::{analysis, cache};
 
 #[derive(Args)]
 pub struct AnalyzeArgs {
@@ -13,14 +18,31 @@ pub struct AnalyzeArgs {
     /// Output analysis as JSON
     #[arg(long)]
     pub json: bool,
+
+    /// Ignore cached analysis results and re-analyze
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Analyze from this many seconds into the file
+    #[arg(long, value_name = "SECS")]
+    pub start: Option<f64>,
+
+    /// Analyze up to this many seconds into the file
+    #[arg(long, value_name = "SECS")]
+    pub end: Option<f64>,
+
+    /// Save the track's tonal balance as a curve for `master --match-eq`
+    #[arg(long, value_name = "FILE")]
+    pub save_curve: Option<PathBuf>,
+
+    /// Compare the tonal balance with a target curve from the config's
+    /// curves folder
+    #[arg(long, value_name = "NAME")]
+    pub curve: Option<String>,
 }
 
 pub async fn run(args: AnalyzeArgs) -> Result<()> {
-    anyhow::ensure!(
-        args.input.exists(),
-        "Input file not found: {}",
-        args.input.display()
-    );
+    super::ensure_input(&args.input)?;
 
     let spinner = indicatif::ProgressBar::new_spinner();
     spinner.set_style(
@@ -31,14 +53,50 @@ pub async fn run(args: AnalyzeArgs) -> Result<()> {
     spinner.set_message("Analyzing audio...");
     spinner.enable_steady_tick(std::time::Duration::from_millis(100));
 
-    let analysis = analysis::analyze_file(&args.input)
-        .await
-        .context("Audio analysis failed")?;
+    let range = (args.start.is_some() || args.end.is_some()).then(|| TimeRange {
+        start_secs: args.start.unwrap_or(0.0),
+        end_secs: args.end,
+    });
+    // Only whole-file results are cached
+    let analysis = match range {
+        Some(ref range) => analysis::analyze_file_range(&args.input, range).await,
+        None => {
+            let cache = (!args.no_cache).then(cache::global_cache);
+            analysis::analyze_file_cached(&args.input, cache).await
+        }
+    }
+    .context("Audio analysis failed")?;
+
+    if let Some(ref path) = args.save_curve {
+        spinner.set_message("Measuring tonal balance...");
+        let input = args.input.clone();
+        let curve = tokio::task::spawn_blocking(move || SpectralCurve::measure(&input))
+            .await
+            .context("Tonal balance task failed")??;
+        curve.save(path)?;
+    }
+
+    let comparison = match args.curve {
+        Some(ref name) => {
+            spinner.set_message("Comparing with the target curve...");
+            let target = CurveStore::open_default()?.load(name)?;
+            let comparison = analysis::compare_to_curve(&args.input, &target)
+                .await
+                .context("Comparing with the target curve failed")?;
+            Some(comparison)
+        }
+        None => None,
+    };
 
     spinner.finish_and_clear();
 
     if args.json {
-        println!("{}", serde_json::to_string_pretty(&analysis)?);
+        let mut json = serde_json::to_value(&analysis)?;
+        if let Some(ref comparison) = comparison {
+            json["curve_comparison"] = serde_json::to_value(comparison)?;
+        }
+        json["diagnosis"] = serde_json::to_value(diagnosis::diagnose(&analysis))?;
+        println!("{}", serde_json::to_string_pretty(&json)?);
         return Ok(());
     }
 
@@ -47,23 +105,72 @@ pub async fn run(args: AnalyzeArgs) -> Result<()> {
         "ANALYSIS".bold().cyan(),
         args.input.display().to_string().white()
     );
+    if let Some(range) = range {
+        println!("  Range:        {range}");
+    }
 
     println!("\n{}", "Metadata".bold().yellow());
     println!("  Format:       {}", analysis.metadata.format);
     println!("  Sample Rate:  {} Hz", analysis.metadata.sample_rate);
     println!("  Channels:     {}", analysis.metadata.channels);
     println!("  Duration:     {:.1}s", analysis.metadata.duration_secs);
+    if let Some(bpm) = analysis.tempo_bpm {
+        println!(
+            "  Tempo:        {bpm:.1} BPM (1/4 note {:.0} ms, 1/16 note {:.0} ms)",
+            tempo::note_ms(bpm, 4),
+            tempo::note_ms(bpm, 16)
+        );
+    }
 
-    println!("\n{}", "Loudness".bold().yellow());
-    println!("  Integrated LUFS:   {:.1}", analysis.lufs_integrated);
-    println!("  Short-term Max:    {:.1} LUFS", analysis.lufs_short_term_max);
-    println!("  RMS:               {:.1} dB", analysis.rms_db);
+    // In the order an EBU Mode meter shows them
+    let ebu = &analysis.ebu_mode;
+    println!("\n{}", "Loudness (EBU Mode)".bold().yellow());
+    println!("  Integrated (I):     {:.1} LUFS", ebu.integrated_lufs);
+    match (ebu.loudness_range_low_lufs, ebu.loudness_range_high_lufs) {
+        (Some(low), Some(high)) => println!(
+            "  Range (LRA):        {:.1} LU ({low:.1} to {high:.1} LUFS)",
+            ebu.loudness_range_lu
+        ),
+        _ => println!("  Range (LRA):        {:.1} LU", ebu.loudness_range_lu),
+    }
+    println!("  Momentary Max (M):  {:.1} LUFS", ebu.momentary_max_lufs);
+    println!("  Short-term Max (S): {:.1} LUFS", ebu.short_term_max_lufs);
+    println!("  True Peak Max (TP): {:.1} dBTP", ebu.true_peak_max_dbtp);
+    println!("  RMS:                {:.1} dB", analysis.rms_db);
 
     println!("\n{}", "Dynamics".bold().yellow());
     println!("  Peak:              {:.1} dB", analysis.peak_db);
     println!("  True Peak:         {:.1} dB", analysis.true_peak_db);
     println!("  Dynamic Range:     {:.1} dB", analysis.dynamic_range_db);
 
+    let clipping = &analysis.clipping;
+    if clipping.regions > 0 {
+        println!("\n{}", "Clipping".bold().yellow());
+        println!("  Regions:           {}", clipping.regions);
+        println!("  Clipped Samples:   {}", clipping.clipped_samples);
+        println!("  Clipped Time:      {:.3}s", clipping.clipped_secs);
+        for region in &clipping.worst {
+            println!(
+                "  - at {:.2}s: {:.2} ms in channel {}",
+                region.start_secs,
+                region.duration_secs * 1000.0,
+                region.channel + 1
+            );
+        }
+        if let Some(warning) = analysis::clipping_warning(&analysis) {
+            println!("  {} {warning}", "WARNING:".bold().red());
+        }
+    }
+
+    if let (Some(floor), Some(snr)) = (analysis.noise_floor_db, analysis.signal_to_noise_db) {
+        println!("\n{}", "Noise".bold().yellow());
+        println!("  Noise Floor:       {:.1} dBFS", floor);
+        println!("  Signal-to-Noise:   {:.1} dB", snr);
+        if let Some(warning) = analysis::noise_floor_warning(&analysis) {
+            println!("  {} {warning}", "WARNING:".bold().red());
+        }
+    }
+
     println!("\n{}", "Stereo".bold().yellow());
     let width_desc = if analysis.stereo_width < 0.1 {
         "Mono"
@@ -80,6 +187,72 @@ pub async fn run(args: AnalyzeArgs) -> Result<()> {
         "  Stereo Width:      {:.2} ({})",
         analysis.stereo_width, width_desc
     );
+    let balance_desc = if analysis.stereo_balance_db.abs() < 1.0 {
+        "Centered"
+    } else if analysis.stereo_balance_db > 0.0 {
+        "Left heavy"
+    } else {
+        "Right heavy"
+    };
+    println!(
+        "  Balance (L/R):     {:+.1} dB ({})",
+        analysis.stereo_balance_db, balance_desc
+    );
+    if analysis.channel_stats.len() > 1 {
+        println!(
+            "  {:<17}  {:>8}  {:>8}  {:>8}",
+            "Channel", "Peak", "RMS", "Loudness"
+        );
+        for channel in &analysis.channel_stats {
+            println!(
+                "  {:<17}  {:>5.1} dB  {:>5.1} dB  {:>7.0}%",
+                channel.name,
+                channel.peak_db,
+                channel.rms_db,
+                channel.loudness_share * 100.0
+            );
+        }
+    }
+    for warning in analysis::channel_warnings(&analysis) {
+        println!("  {} {warning}", "WARNING:".bold().red());
+    }
+
+    let silence = &analysis.silence;
+    println!("\n{}", "Silence".bold().yellow());
+    println!("  Leading:           {:.2}s", silence.leading_secs);
+    println!("  Trailing:          {:.2}s", silence.trailing_secs);
+    for gap in &silence.gaps {
+        println!(
+            "  - gap at {:.2}s: {:.2}s",
+            gap.start_secs, gap.duration_secs
+        );
+    }
+
+    if let Some(ref mono) = analysis.mono_compatibility {
+        println!("\n{}", "Mono Compatibility".bold().yellow());
+        println!("  Phase Correlation: {:+.2}", analysis.phase_correlation);
+        println!(
+            "  Lowest Windowed:   {:+.2} ({:.1}s windows)",
+            analysis.phase_correlation_min, mono.correlation_window_secs
+        );
+        if analysis.out_of_phase_secs > 0.0 {
+            println!("  Out of Phase:      {:.1}s", analysis.out_of_phase_secs);
+        }
+        let losses = &mono.band_loss_db;
+        println!(
+            "  Fold-down Loss:    sub {:.1} | bass {:.1} | low-mid {:.1} | mid {:.1} | upper-mid {:.1} | presence {:.1} | brilliance {:.1} dB",
+            losses.sub_bass,
+            losses.bass,
+            losses.low_mid,
+            losses.mid,
+            losses.upper_mid,
+            losses.presence,
+            losses.brilliance
+        );
+        for warning in analysis::mono_compatibility_warnings(&analysis) {
+            println!("  {} {warning}", "WARNING:".bold().red());
+        }
+    }
 
     println!("\n{}", "Frequency Balance".bold().yellow());
     let bands = &analysis.frequency_bands;
@@ -90,13 +263,80 @@ pub async fn run(args: AnalyzeArgs) -> Result<()> {
     print_band("Upper-mid (2k-4k Hz)   ", bands.upper_mid);
     print_band("Presence  (4k-6k Hz)   ", bands.presence);
     print_band("Brilliance(6k-20k Hz)  ", bands.brilliance);
+    let spectral = &analysis.spectral;
+    println!("\n{}", "Spectrum".bold().yellow());
+    println!("  Centroid:          {:.0} Hz", spectral.centroid_hz);
+    println!("  Rolloff (85%):     {:.0} Hz", spectral.rolloff_hz);
+    println!("  Flatness:          {:.3}", spectral.flatness);
+    println!(
+        "  Tilt:              {:+.1} dB/octave",
+        spectral.tilt_db_per_octave
+    );
+
+    if let (Some(name), Some(comparison)) = (&args.curve, &comparison) {
+        println!("\n{}", format!("Target Curve: {name}").bold().yellow());
+        for (label, low, high) in CURVE_RANGES {
+            print_deviation(label, comparison.range_deviation_db(low, high));
+        }
+        println!(
+            "  RMS deviation:          {:.1} dB",
+            comparison.rms_deviation_db
+        );
+    }
+
+    let issues = diagnosis::diagnose(&analysis);
+    if !issues.is_empty() {
+        println!("\n{}", "Diagnosis".bold().yellow());
+        for issue in &issues {
+            let severity = match issue.severity {
+                Severity::Critical => "CRITICAL".bold().red(),
+                Severity::Warning => "WARNING".bold().yellow(),
+                Severity::Info => "INFO".bold().cyan(),
+            };
+            println!(
+                "  {severity:<8} {}: {}",
+                issue.problem.to_string().bold(),
+                issue.detail
+            );
+            println!("           {}", issue.suggestion.dimmed());
+        }
+    }
+
+    if let Some(ref path) = args.save_curve {
+        println!(
+            "\n  Tonal balance curve saved to {}",
+            path.display().to_string().cyan()
+        );
+    }
 
     println!();
     Ok(())
 }
 
+/// Ranges a curve comparison is summarized over, as in the band listing.
+const CURVE_RANGES: [(&str, f64, f64); 7] = [
+    ("Sub-bass  (20-60 Hz)   ", 20.0, 60.0),
+    ("Bass      (60-250 Hz)  ", 60.0, 250.0),
+    ("Low-mid   (250-500 Hz) ", 250.0, 500.0),
+    ("Mid       (500-2k Hz)  ", 500.0, 2000.0),
+    ("Upper-mid (2k-4k Hz)   ", 2000.0, 4000.0),
+    ("Presence  (4k-6k Hz)   ", 4000.0, 6000.0),
+    ("Brilliance(6k-20k Hz)  ", 6000.0, 20000.0),
+];
+
+/// A deviation from the target curve; more than 3 dB either way stands out.
+fn print_deviation(label: &str, db: f64) {
+    let text = format!("{db:>+6.1} dB");
+    let text = if db.abs() > 3.0 {
+        text.red()
+    } else {
+        text.green()
+    };
+    println!("  {label} {text}");
+}
+
 fn print_band(label: &str, db: f64) {
-    let bar_len = ((db + 10.0) * 3.0).max(0.0).min(40.0) as usize;
+    let bar_len = ((db + 10.0) * 3.0).clamp(0.0, 40.0) as usize;
     let bar: String = "#".repeat(bar_len);
     let color_bar = if db > -3.0 {
         bar.green()
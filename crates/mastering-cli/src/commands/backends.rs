@@ -3,6 +3,9 @@ use colored::Colorize;
 
 use mastering_core::backends::MasteringEngine;
 use mastering_core::config::Config;
+use mastering_core::error::MasteringError;
+use mastering_core::gpu;
+use mastering_core::stems::StemSeparator;
 use mastering_core::types::Backend;
 
 pub async fn run() -> Result<()> {
@@ -11,14 +14,34 @@ pub async fn run() -> Result<()> {
     println!("\n{}", "Available Backends".bold().cyan());
 
     let backends = [
-        (Backend::Matchering, "Reference-based mastering (matches EQ, loudness, stereo width)"),
-        (Backend::Ai, "AI-assisted mastering (LLM suggests DSP parameters)"),
-        (Backend::LocalMl, "Local ML models (DeepAFx-ST, HuggingFace)"),
+        (
+            Backend::Matchering,
+            "Reference-based mastering (matches EQ, loudness, stereo width)",
+        ),
+        (
+            Backend::Ai,
+            "AI-assisted mastering (LLM suggests DSP parameters)",
+        ),
+        (
+            Backend::LocalMl,
+            "Local ML models (DeepAFx-ST, HuggingFace)",
+        ),
+        (
+            Backend::Basic,
+            "Built-in EQ, compressor and limiter (no Python or network)",
+        ),
+        (
+            Backend::Recipe,
+            "Saved recipes rendered as they are (--recipe)",
+        ),
     ];
 
     for (backend, description) in &backends {
         let engine = MasteringEngine::from_config(*backend, &config);
-        let available = engine.check_available().await.unwrap_or(false);
+        let (available, problem) = match engine.check_available().await {
+            Ok(available) => (available, None),
+            Err(e) => (false, Some(e)),
+        };
 
         let status = if available {
             "READY".bold().green()
@@ -28,8 +51,35 @@ pub async fn run() -> Result<()> {
 
         println!("\n  {} [{}]", backend.to_string().bold().white(), status);
         println!("    {description}");
+        if let Some(e) = problem {
+            let explanation = match e.downcast_ref::<MasteringError>() {
+                Some(err) => err.user_message(),
+                None => format!("{e:#}"),
+            };
+            for line in explanation.lines().filter(|l| !l.is_empty()) {
+                println!("    {}", line.dimmed());
+            }
+        }
+        if *backend == Backend::LocalMl {
+            let devices: Vec<String> = gpu::available_devices()
+                .iter()
+                .map(|d| d.to_string())
+                .collect();
+            println!(
+                "    Devices: {} (configured: {})",
+                devices.join(", "),
+                config.backends.local_ml.device
+            );
+        }
     }
 
+    let stems = match StemSeparator::new(&config).check_available().await {
+        Ok(true) => "READY".bold().green(),
+        _ => "NOT AVAILABLE".bold().red(),
+    };
+    println!("\n  {} [{}]", "Stem separation".bold().white(), stems);
+    println!("    Demucs {} for --stems", config.stems.model.dimmed());
+
     // Show AI provider details
     println!("\n{}", "AI Providers".bold().cyan());
 
@@ -37,7 +87,22 @@ pub async fn run() -> Result<()> {
         ("Ollama (local)", !config.ai.ollama.endpoint.is_empty()),
         ("KeyhanStudio", !config.ai.keyhanstudio.endpoint.is_empty() && !config.ai.keyhanstudio.api_key.is_empty()),
         ("OpenAI", !config.ai.openai.api_key.is_empty()),
+        (
+            "Azure OpenAI",
+            !config.ai.azure_openai.endpoint.is_empty()
+                && !config.ai.azure_openai.api_key.is_empty(),
+        ),
+        (
+            "OpenAI-compatible",
+            !config.ai.openai_compatible.base_url.is_empty()
+                && !config.ai.openai_compatible.model.is_empty(),
+        ),
         ("Anthropic", !config.ai.anthropic.api_key.is_empty()),
+        (
+            "Local GGUF",
+            cfg!(feature = "local-llm") && !config.ai.local_gguf.model_path.is_empty(),
+        ),
+        ("Rules (offline)", true),
     ];
 
     for (name, configured) in &providers {
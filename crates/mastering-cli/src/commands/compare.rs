@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::{analysis, cache};
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// First audio file (A)
+    pub a: PathBuf,
+
+    /// Second audio file (B)
+    pub b: PathBuf,
+
+    /// Output comparison as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Ignore cached analysis results and re-analyze
+    #[arg(long)]
+    pub no_cache: bool,
+}
+
+pub async fn run(args: CompareArgs) -> Result<()> {
+    for path in [&args.a, &args.b] {
+        super::ensure_input(path)?;
+    }
+
+    let spinner = indicatif::ProgressBar::new_spinner();
+    spinner.set_style(
+        indicatif::ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.set_message("Analyzing both files...");
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let cache = (!args.no_cache).then(cache::global_cache);
+    let comparison = analysis::compare_files(&args.a, &args.b, cache)
+        .await
+        .context("Audio comparison failed")?;
+
+    spinner.finish_and_clear();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+        return Ok(());
+    }
+
+    let (a, b, d) = (&comparison.a, &comparison.b, &comparison.delta);
+
+    println!("\n{}", "COMPARE".bold().cyan());
+    println!("  A: {}", args.a.display().to_string().white());
+    println!("  B: {}", args.b.display().to_string().white());
+
+    println!(
+        "\n{}",
+        format!("  {:<18} {:>9} {:>9} {:>9}", "Metric", "A", "B", "B - A")
+            .bold()
+            .yellow()
+    );
+    print_row(
+        "Integrated LUFS",
+        a.lufs_integrated,
+        b.lufs_integrated,
+        d.lufs_integrated,
+    );
+    print_row(
+        "Short-term Max",
+        a.lufs_short_term_max,
+        b.lufs_short_term_max,
+        d.lufs_short_term_max,
+    );
+    print_row("RMS (dB)", a.rms_db, b.rms_db, d.rms_db);
+    print_row("Peak (dB)", a.peak_db, b.peak_db, d.peak_db);
+    print_row(
+        "True Peak (dB)",
+        a.true_peak_db,
+        b.true_peak_db,
+        d.true_peak_db,
+    );
+    print_row(
+        "Dynamic Range (dB)",
+        a.dynamic_range_db,
+        b.dynamic_range_db,
+        d.dynamic_range_db,
+    );
+    print_row(
+        "Stereo Width",
+        a.stereo_width,
+        b.stereo_width,
+        d.stereo_width,
+    );
+    print_row(
+        "Balance L/R (dB)",
+        a.stereo_balance_db,
+        b.stereo_balance_db,
+        d.stereo_balance_db,
+    );
+    print_row(
+        "Phase Correlation",
+        a.phase_correlation,
+        b.phase_correlation,
+        d.phase_correlation,
+    );
+
+    println!("\n{}", "Frequency Balance (dB)".bold().yellow());
+    let (fa, fb, fd) = (&a.frequency_bands, &b.frequency_bands, &d.frequency_bands);
+    print_row("Sub-bass", fa.sub_bass, fb.sub_bass, fd.sub_bass);
+    print_row("Bass", fa.bass, fb.bass, fd.bass);
+    print_row("Low-mid", fa.low_mid, fb.low_mid, fd.low_mid);
+    print_row("Mid", fa.mid, fb.mid, fd.mid);
+    print_row("Upper-mid", fa.upper_mid, fb.upper_mid, fd.upper_mid);
+    print_row("Presence", fa.presence, fb.presence, fd.presence);
+    print_row("Brilliance", fa.brilliance, fb.brilliance, fd.brilliance);
+
+    println!("\n{}", "Spectrum".bold().yellow());
+    let (sa, sb, sd) = (&a.spectral, &b.spectral, &d.spectral);
+    print_row(
+        "Centroid (Hz)",
+        sa.centroid_hz,
+        sb.centroid_hz,
+        sd.centroid_hz,
+    );
+    print_row("Rolloff (Hz)", sa.rolloff_hz, sb.rolloff_hz, sd.rolloff_hz);
+    print_row("Flatness", sa.flatness, sb.flatness, sd.flatness);
+    print_row(
+        "Tilt (dB/oct)",
+        sa.tilt_db_per_octave,
+        sb.tilt_db_per_octave,
+        sd.tilt_db_per_octave,
+    );
+
+    println!();
+    Ok(())
+}
+
+fn print_row(label: &str, a: f64, b: f64, delta: f64) {
+    let delta_str = format!("{delta:>+9.2}");
+    let delta_col = if delta.abs() < 0.05 {
+        delta_str.dimmed()
+    } else if delta > 0.0 {
+        delta_str.green()
+    } else {
+        delta_str.red()
+    };
+    println!("  {label:<18} {a:>9.2} {b:>9.2} {delta_col}");
+}
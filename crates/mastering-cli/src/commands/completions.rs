@@ -0,0 +1,76 @@
+use anyhow::Result;
+use clap::builder::PossibleValuesParser;
+use clap::{Args, Command, CommandFactory};
+use clap_complete::Shell;
+
+use mastering_core::config::Config;
+use mastering_core::jobs::JobStatus;
+use mastering_core::types::{Backend, Preset};
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// Shell to generate completions for: bash, zsh, fish, powershell, elvish
+    pub shell: Shell,
+}
+
+/// Print a completion script for `shell` to stdout.
+///
+/// Preset names include the custom presets in the config as it is now, so
+/// re-generate the script after creating or deleting one.
+pub fn run(args: CompletionsArgs) -> Result<()> {
+    let config = Config::load().unwrap_or_default();
+    let presets: Vec<String> = Preset::ALL
+        .iter()
+        .map(ToString::to_string)
+        .chain(config.presets.keys().cloned())
+        .collect();
+    let backends: Vec<String> = Backend::ALL.iter().map(ToString::to_string).collect();
+
+    let mut command = completable(crate::Cli::command(), &presets, &backends);
+    clap_complete::generate(
+        args.shell,
+        &mut command,
+        "mastering",
+        &mut std::io::stdout(),
+    );
+    Ok(())
+}
+
+/// The CLI with preset and backend arguments limited to known names, so
+/// the shell can offer them. Only used for completion; parsing still
+/// accepts anything and reports unknown names itself.
+fn completable(command: Command, presets: &[String], backends: &[String]) -> Command {
+    let presets = || PossibleValuesParser::new(presets.iter().cloned());
+    let backends = || PossibleValuesParser::new(backends.iter().cloned());
+    command
+        .mut_subcommand("master", |c| {
+            c.mut_arg("preset", |a| a.value_parser(presets()))
+                .mut_arg("backend", |a| a.value_parser(backends()))
+        })
+        .mut_subcommand("jobs", |c| {
+            c.mut_subcommand("list", |c| {
+                let statuses = JobStatus::ALL.iter().map(ToString::to_string);
+                c.mut_arg("status", |a| {
+                    a.value_parser(PossibleValuesParser::new(statuses))
+                })
+            })
+            .mut_subcommand("add", |c| {
+                c.mut_arg("preset", |a| a.value_parser(presets()))
+                    .mut_arg("backend", |a| a.value_parser(backends()))
+            })
+        })
+        .mut_subcommand("watch", |c| {
+            c.mut_arg("preset", |a| a.value_parser(presets()))
+                .mut_arg("backend", |a| a.value_parser(backends()))
+        })
+        .mut_subcommand("tui", |c| {
+            c.mut_arg("preset", |a| a.value_parser(presets()))
+                .mut_arg("backend", |a| a.value_parser(backends()))
+        })
+        .mut_subcommand("preset", |c| {
+            c.mut_subcommand("show", |c| c.mut_arg("name", |a| a.value_parser(presets())))
+                .mut_subcommand("delete", |c| {
+                    c.mut_arg("name", |a| a.value_parser(presets()))
+                })
+        })
+}
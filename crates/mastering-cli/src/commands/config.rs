@@ -1,8 +1,10 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Args;
 use colored::Colorize;
 
 use mastering_core::config::Config;
+use mastering_core::secrets;
+use mastering_core::types::AiProvider;
 
 #[derive(Args)]
 pub struct ConfigArgs {
@@ -13,6 +15,14 @@ pub struct ConfigArgs {
     /// Show the config file path
     #[arg(long)]
     pub path: bool,
+
+    /// Store an AI provider's API key, read from stdin, in the OS keyring
+    #[arg(long, value_name = "PROVIDER")]
+    pub set_key: Option<String>,
+
+    /// Remove an AI provider's API key from the OS keyring
+    #[arg(long, value_name = "PROVIDER")]
+    pub delete_key: Option<String>,
 }
 
 pub fn run(args: ConfigArgs) -> Result<()> {
@@ -22,6 +32,31 @@ pub fn run(args: ConfigArgs) -> Result<()> {
         return Ok(());
     }
 
+    if let Some(provider) = args.set_key {
+        let provider: AiProvider = provider.parse()?;
+        let var = secrets::env_var(provider)
+            .with_context(|| format!("{provider} does not use an API key"))?;
+        eprintln!("Paste the {provider} API key and press Enter:");
+        let mut key = String::new();
+        std::io::stdin().read_line(&mut key)?;
+        secrets::store(provider, &key)?;
+        println!(
+            "{} {provider} API key stored in the OS keyring ({var} still takes precedence)",
+            "OK".bold().green()
+        );
+        return Ok(());
+    }
+
+    if let Some(provider) = args.delete_key {
+        let provider: AiProvider = provider.parse()?;
+        secrets::delete(provider)?;
+        println!(
+            "{} {provider} API key removed from the OS keyring",
+            "OK".bold().green()
+        );
+        return Ok(());
+    }
+
     if args.init {
         let config = Config::default();
         config.save()?;
@@ -57,9 +92,21 @@ pub fn run(args: ConfigArgs) -> Result<()> {
     println!("  Default Bit Depth: {}", config.general.default_bit_depth);
     println!("  Default Format:    {}", config.general.default_format);
     println!("  Target LUFS:       {:.1}", config.general.target_lufs);
+    if config.general.offline {
+        println!("  Offline:           {}", "yes".yellow());
+    }
 
     println!("\n{}", "AI".bold().yellow());
     println!("  Default Provider:  {}", config.ai.default_provider);
+    println!("  Passes:            {}", config.ai.passes);
+    println!(
+        "  Candidates:        {} ({:.0}s excerpt)",
+        config.ai.candidates, config.ai.candidate_excerpt_secs
+    );
+    println!(
+        "  Streaming:         {}",
+        if config.ai.stream { "on" } else { "off" }
+    );
     println!("  Ollama Endpoint:   {}", config.ai.ollama.endpoint);
     println!("  Ollama Model:      {}", config.ai.ollama.model);
     println!(
@@ -72,26 +119,65 @@ pub fn run(args: ConfigArgs) -> Result<()> {
     );
     println!(
         "  OpenAI:            {}",
-        if config.ai.openai.api_key.is_empty() {
+        key_status(
+            AiProvider::OpenAi,
+            &config.ai.openai.api_key,
+            &config.ai.openai.model
+        )
+    );
+    println!(
+        "  Azure OpenAI:      {}",
+        if config.ai.azure_openai.endpoint.is_empty() {
             "not configured".dimmed().to_string()
         } else {
-            format!("{} ({})", "configured".green(), config.ai.openai.model)
+            format!(
+                "{} ({})",
+                config.ai.azure_openai.endpoint, config.ai.azure_openai.deployment
+            )
         }
     );
     println!(
-        "  Anthropic:         {}",
-        if config.ai.anthropic.api_key.is_empty() {
+        "  OpenAI-compatible: {}",
+        if config.ai.openai_compatible.base_url.is_empty() {
             "not configured".dimmed().to_string()
         } else {
             format!(
                 "{} ({})",
-                "configured".green(),
-                config.ai.anthropic.model
+                config.ai.openai_compatible.base_url, config.ai.openai_compatible.model
             )
         }
     );
+    println!(
+        "  Anthropic:         {}",
+        key_status(
+            AiProvider::Anthropic,
+            &config.ai.anthropic.api_key,
+            &config.ai.anthropic.model
+        )
+    );
+    if let Some((timeout_secs, max_tokens)) = config.ai.request_limits(config.ai.default_provider) {
+        println!("  Request Limits:    {timeout_secs}s, {max_tokens} tokens");
+    }
+    if !config.ai.network.proxy.is_empty() {
+        println!("  Proxy:             {}", config.ai.network.proxy);
+    }
+    if !config.ai.network.ca_cert_file.is_empty() {
+        println!("  Extra CA Certs:    {}", config.ai.network.ca_cert_file);
+    }
+    if !config.ai.pricing.is_empty() {
+        println!("  Custom Prices:     {}", config.ai.pricing.len());
+    }
+    println!(
+        "  Local GGUF:        {}",
+        if config.ai.local_gguf.model_path.is_empty() {
+            "not configured".dimmed().to_string()
+        } else {
+            config.ai.local_gguf.model_path.clone()
+        }
+    );
 
     println!("\n{}", "Backends".bold().yellow());
+    println!("  Matchering Engine: {}", config.backends.matchering.engine);
     println!(
         "  Matchering Python: {}",
         config.backends.matchering.python_path
@@ -104,7 +190,44 @@ pub fn run(args: ConfigArgs) -> Result<()> {
         "  Local ML Model:    {}",
         config.backends.local_ml.default_model
     );
+    println!(
+        "  Local ML ONNX:     {}",
+        if config.backends.local_ml.onnx_model.is_empty() {
+            "not configured (uses Python)".dimmed().to_string()
+        } else {
+            format!(
+                "{} at {} Hz",
+                config.backends.local_ml.onnx_model, config.backends.local_ml.onnx_sample_rate
+            )
+        }
+    );
+    println!("  Local ML Device:   {}", config.backends.local_ml.device);
+    println!(
+        "  Timeouts:          matchering {}s, local ML {}s, AI DSP {}s",
+        config.backends.matchering.timeout_secs,
+        config.backends.local_ml.timeout_secs,
+        config.backends.dsp_timeout_secs
+    );
+
+    if !config.presets.is_empty() {
+        println!("\n{}", "Custom Presets".bold().yellow());
+        for (name, preset) in &config.presets {
+            println!("  {:<18} {}", format!("{name}:"), preset.summary());
+            if let Some(ref description) = preset.description {
+                println!("  {:<18} {}", "", description.dimmed());
+            }
+        }
+    }
 
     println!();
     Ok(())
 }
+
+/// "configured (model, key from …)" or "not configured" for a provider
+/// that needs an API key.
+fn key_status(provider: AiProvider, config_key: &str, model: &str) -> String {
+    match secrets::lookup(provider, config_key) {
+        Some((_, source)) => format!("{} ({model}, key from {source})", "configured".green()),
+        None => "not configured".dimmed().to_string(),
+    }
+}
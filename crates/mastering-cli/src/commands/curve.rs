@@ -0,0 +1,63 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+
+use mastering_core::curves::CurveStore;
+
+#[derive(Args)]
+pub struct CurveArgs {
+    #[command(subcommand)]
+    pub command: CurveCommand,
+}
+
+#[derive(Subcommand)]
+pub enum CurveCommand {
+    /// List the target curves in the curves folder
+    List,
+
+    /// Print a curve's levels, relative to their mean
+    Show {
+        /// Name of the curve, without its extension
+        name: String,
+    },
+}
+
+pub fn run(args: CurveArgs) -> Result<()> {
+    let store = CurveStore::open_default()?;
+
+    match args.command {
+        CurveCommand::List => list(&store),
+        CurveCommand::Show { name } => {
+            let curve = store.load(&name)?;
+            println!("\n{}", format!("Curve: {name}").bold().cyan());
+            for (frequency, db) in curve.frequencies.iter().zip(&curve.levels_db) {
+                println!("  {frequency:>8.0} Hz  {db:>+6.1} dB");
+            }
+            println!();
+            Ok(())
+        }
+    }
+}
+
+fn list(store: &CurveStore) -> Result<()> {
+    let names = store.list()?;
+    println!("\n{}", "Target Curves".bold().cyan());
+    if names.is_empty() {
+        println!(
+            "\n  No curves yet. Add CSV files of {} lines, or save one with {}",
+            "frequency,dB".cyan(),
+            "mastering analyze <file> --save-curve <file>.json".cyan()
+        );
+    } else {
+        println!();
+        for name in &names {
+            println!("  {}", name.bold().white());
+        }
+    }
+    println!(
+        "\n  Stored in {}",
+        store.dir().display().to_string().dimmed()
+    );
+    println!();
+    Ok(())
+}
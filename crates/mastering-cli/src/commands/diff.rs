@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::diff;
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// First audio file (A)
+    pub a: PathBuf,
+
+    /// Second audio file (B)
+    pub b: PathBuf,
+
+    /// Where to write the difference signal (B - A) [default: <B>_diff.wav]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Only report the residual; don't write the difference signal
+    #[arg(long, conflicts_with = "output")]
+    pub no_export: bool,
+
+    /// Largest offset between the files to search when aligning them, in milliseconds
+    #[arg(long, value_name = "MS", default_value_t = 1000.0)]
+    pub max_offset: f64,
+
+    /// Output the result as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub async fn run(args: DiffArgs) -> Result<()> {
+    for path in [&args.a, &args.b] {
+        super::ensure_input(path)?;
+    }
+    let output = (!args.no_export).then(|| {
+        args.output.clone().unwrap_or_else(|| {
+            let stem = args
+                .b
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("output");
+            args.b.with_file_name(format!("{stem}_diff.wav"))
+        })
+    });
+
+    let spinner = indicatif::ProgressBar::new_spinner();
+    spinner.set_style(
+        indicatif::ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.set_message("Aligning and subtracting...");
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let (a, b, max_offset) = (args.a.clone(), args.b.clone(), args.max_offset);
+    let result =
+        tokio::task::spawn_blocking(move || diff::null_test(&a, &b, max_offset, output.as_deref()))
+            .await
+            .context("Null test task failed")?
+            .context("Null test failed")?;
+
+    spinner.finish_and_clear();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return Ok(());
+    }
+
+    println!("\n{}", "NULL TEST".bold().cyan());
+    println!("  A: {}", args.a.display().to_string().white());
+    println!("  B: {}", args.b.display().to_string().white());
+
+    println!("\n{}", "Alignment".bold().yellow());
+    println!(
+        "  Offset:            {} frames ({:+.2} ms)",
+        result.offset_frames, result.offset_ms
+    );
+    println!("  Compared:          {:.1}s", result.compared_secs);
+
+    println!("\n{}", "Residual (B - A)".bold().yellow());
+    println!("  RMS:               {:.1} dBFS", result.residual_rms_db);
+    println!("  Peak:              {:.1} dBFS", result.residual_peak_db);
+    println!("  Null Depth:        {:.1} dB", result.null_depth_db);
+
+    if let Some(ref path) = result.difference_path {
+        println!("\n{} {}", "Difference:".bold().green(), path.display());
+    }
+
+    println!();
+    Ok(())
+}
@@ -0,0 +1,395 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::config::Config;
+use mastering_core::jobs::{self, JobFilter, JobRecord, JobStatus, JobStore};
+use mastering_core::pipeline::{self, CancellationToken};
+
+#[derive(Args)]
+pub struct JobsArgs {
+    #[command(subcommand)]
+    pub command: JobsCommand,
+}
+
+#[derive(Subcommand)]
+pub enum JobsCommand {
+    /// List queued and finished jobs, newest first
+    List {
+        /// Only jobs with this status: queued, running, done, failed, cancelled
+        #[arg(short, long)]
+        status: Option<String>,
+
+        /// Show at most this many jobs
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+
+        /// Print the jobs as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print a job's settings and result as JSON
+    Show {
+        /// Id of the job
+        id: i64,
+    },
+
+    /// Queue files to be mastered by `mastering jobs run`
+    Add(AddArgs),
+
+    /// Master the queued jobs one at a time until the queue is empty
+    Run,
+
+    /// Master again with the settings and parameters of an earlier job
+    Remaster(RemasterArgs),
+
+    /// Queue failed or cancelled jobs again
+    Retry {
+        /// Ids of the jobs
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+
+    /// Remove jobs from the queue and history; masters are kept
+    Delete {
+        /// Ids of the jobs
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+}
+
+#[derive(Args)]
+pub struct AddArgs {
+    /// Audio files to queue
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Mastering preset: streaming, cd, vinyl, loud, podcast, game, game-console, cinema,
+    /// trailer, or a custom preset from the config
+    #[arg(short, long)]
+    pub preset: Option<String>,
+
+    /// Mastering backend: auto, matchering, ai, local-ml, basic
+    #[arg(short, long, default_value = "auto")]
+    pub backend: String,
+
+    /// Output format: wav, flac, aiff, alac, mp3, aac (m4a), opus (ogg)
+    #[arg(short, long)]
+    pub format: Option<String>,
+
+    /// Folder to write masters to [default: next to each input]
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct RemasterArgs {
+    /// Id of the job to repeat
+    pub id: i64,
+
+    /// Master this file instead of the job's input
+    pub input: Option<PathBuf>,
+
+    /// Output file [default: the job's output for the same input, else next to the input]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Queue the job for `mastering jobs run` instead of mastering it now
+    #[arg(long)]
+    pub queue: bool,
+}
+
+pub async fn run(args: JobsArgs) -> Result<()> {
+    let store = JobStore::open_default()?;
+
+    match args.command {
+        JobsCommand::List {
+            status,
+            limit,
+            json,
+        } => {
+            let filter = JobFilter {
+                status: status.map(|s| s.parse()).transpose()?,
+                limit: Some(limit),
+            };
+            let records = store.list(&filter)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&records)?);
+            } else {
+                list(&store, &records);
+            }
+            Ok(())
+        }
+        JobsCommand::Show { id } => {
+            println!("{}", serde_json::to_string_pretty(&store.get(id)?)?);
+            Ok(())
+        }
+        JobsCommand::Add(args) => add(&store, args),
+        JobsCommand::Run => run_queue(&store).await,
+        JobsCommand::Remaster(args) => remaster(&store, args).await,
+        JobsCommand::Retry { ids } => {
+            for id in ids {
+                store.retry(id)?;
+                if !super::quiet() {
+                    println!("{} Queued job {id} again", "OK".bold().green());
+                }
+            }
+            Ok(())
+        }
+        JobsCommand::Delete { ids } => {
+            for id in ids {
+                store.delete(id)?;
+                if !super::quiet() {
+                    println!("{} Deleted job {id}", "OK".bold().green());
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn list(store: &JobStore, records: &[JobRecord]) {
+    println!("\n{}", "Jobs".bold().cyan());
+    if records.is_empty() {
+        println!(
+            "\n  No jobs yet. Queue some with {}",
+            "mastering jobs add <files>".cyan()
+        );
+        println!();
+        return;
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    for record in records {
+        let status = match record.status {
+            JobStatus::Queued => "queued".normal(),
+            JobStatus::Running => "running".cyan(),
+            JobStatus::Done => "done".green(),
+            JobStatus::Failed => "failed".red(),
+            JobStatus::Cancelled => "cancelled".yellow(),
+        };
+        println!(
+            "\n  {:>5}  {:<9}  {}  {}",
+            record.id.to_string().bold(),
+            status,
+            record.input_path().display().to_string().white(),
+            format_age(now.saturating_sub(record.created_at)).dimmed()
+        );
+        if let Some(ref result) = record.result {
+            let levels = result
+                .post_analysis
+                .as_ref()
+                .map(|post| {
+                    format!(
+                        " ({:.1} LUFS, {:.1} dBTP)",
+                        post.lufs_integrated, post.true_peak_db
+                    )
+                })
+                .unwrap_or_default();
+            println!("{:18}-> {}{levels}", "", result.output_path.display());
+        }
+        if let Some(ref error) = record.error {
+            println!("{:18}{}", "", error.dimmed());
+        }
+    }
+    println!(
+        "\n  Stored in {}",
+        store.path().display().to_string().dimmed()
+    );
+    println!();
+}
+
+fn add(store: &JobStore, args: AddArgs) -> Result<()> {
+    let config = Config::load().context("Loading configuration")?;
+    for file in &args.files {
+        super::ensure_input(file)?;
+    }
+    // The queue may be run from another folder, so store absolute paths
+    let out = match args.out {
+        Some(out) => {
+            std::fs::create_dir_all(&out).with_context(|| format!("Creating {}", out.display()))?;
+            Some(out.canonicalize()?)
+        }
+        None => None,
+    };
+    let settings = super::JobSettings {
+        preset: args.preset,
+        backend: args.backend.parse()?,
+        format: args.format.map(|s| s.parse()).transpose()?,
+        out,
+    };
+    for file in &args.files {
+        let input = file
+            .canonicalize()
+            .with_context(|| format!("Resolving {}", file.display()))?;
+        let id = store.enqueue(&settings.build_job(&input, &config)?)?;
+        if !super::quiet() {
+            println!(
+                "{} Queued {} as job {id}",
+                "OK".bold().green(),
+                file.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn remaster(store: &JobStore, args: RemasterArgs) -> Result<()> {
+    let record = store.get(args.id)?;
+    let input = match args.input {
+        Some(ref input) => {
+            super::ensure_input(input)?;
+            Some(input.canonicalize()?)
+        }
+        None => {
+            super::ensure_input(record.input_path())?;
+            None
+        }
+    };
+    let mut job = record.remaster(input.as_deref());
+    if let Some(output) = args.output {
+        job.output_path = Some(std::path::absolute(output)?);
+    }
+    let quiet = super::quiet();
+
+    if args.queue {
+        let id = store.enqueue(&job)?;
+        if !quiet {
+            println!(
+                "{} Queued a remaster of job {} as job {id}",
+                "OK".bold().green(),
+                args.id
+            );
+        }
+        return Ok(());
+    }
+
+    let config = Config::load().context("Loading configuration")?;
+    let cancel = job.cancel_token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+    if !quiet {
+        println!(
+            "\n{}  job {} -> {}",
+            "REMASTERING".bold().cyan(),
+            args.id,
+            record.input_path().display().to_string().white()
+        );
+    }
+    let spinner = indicatif::ProgressBar::new_spinner();
+    spinner.set_style(
+        indicatif::ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    if quiet {
+        spinner.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+    }
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    let result =
+        pipeline::run_with_progress(&job, &config, &super::master::spinner_progress(&spinner))
+            .await;
+    spinner.finish_and_clear();
+    jobs::remember(&job, &result);
+
+    if job.cancel_token.is_cancelled() {
+        if !quiet {
+            println!("{} Remastering cancelled", "!".bold().yellow());
+        }
+        std::process::exit(crate::exit::CANCELLED.into());
+    }
+    let result = result.map_err(|e| crate::exit::in_backend(e, job.resolved_backend()))?;
+    if !quiet {
+        super::master::print_result(&result);
+    }
+    Ok(())
+}
+
+async fn run_queue(store: &JobStore) -> Result<()> {
+    let config = Config::load().context("Loading configuration")?;
+    store.requeue_interrupted()?;
+
+    // Ctrl-C stops the queue and cancels the job being mastered
+    let cancel = CancellationToken::new();
+    tokio::spawn({
+        let cancel = cancel.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancel.cancel();
+            }
+        }
+    });
+
+    let quiet = super::quiet();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let printer = tokio::spawn(async move {
+        while let Some(record) = rx.recv().await {
+            if !quiet {
+                print_record(&record);
+            }
+        }
+    });
+    let summary = jobs::run_queue(store, &config, Some(tx), cancel.clone()).await?;
+    printer.await.context("Queue output task failed")?;
+
+    if !quiet {
+        let failed = match summary.failed {
+            0 => "0 failed".normal(),
+            n => format!("{n} failed").red(),
+        };
+        let label = if cancel.is_cancelled() {
+            "Stopped:"
+        } else {
+            "Queue empty:"
+        };
+        println!("\n{} {} done, {failed}", label.bold().blue(), summary.done);
+    }
+    Ok(())
+}
+
+fn print_record(record: &JobRecord) {
+    match record.status {
+        JobStatus::Running => {
+            println!(
+                "  {} job {}: {}",
+                "Mastering".cyan(),
+                record.id,
+                record.input_path().display()
+            );
+        }
+        JobStatus::Done => {
+            let output = record
+                .output_path()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default();
+            let secs = record.duration_secs().unwrap_or_default();
+            println!("  {} {output} in {secs}s", "Mastered:".bold().green());
+        }
+        JobStatus::Failed => {
+            let error = record.error.as_deref().unwrap_or_default();
+            println!("  {} job {}: {error}", "Failed:".bold().red(), record.id);
+        }
+        JobStatus::Cancelled => {
+            println!("  {} job {}", "Cancelled:".bold().yellow(), record.id);
+        }
+        JobStatus::Queued => {}
+    }
+}
+
+/// How long ago something happened, e.g. `3h ago`.
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
@@ -1,102 +1,514 @@
 use anyhow::{Context, Result};
 use clap::Args;
 use colored::Colorize;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
+use crate::stdio;
+use mastering_core::analysis;
 use mastering_core::config::Config;
-use mastering_core::pipeline::{self, MasteringJob};
-use mastering_core::types::{AiProvider, AudioFormat, Backend, Preset};
+use mastering_core::config::ParamLimits;
+use mastering_core::curves::CurveStore;
+use mastering_core::jobs;
+use mastering_core::metadata::TagOverrides;
+use mastering_core::pipeline::{
+    self, CancellationToken, MasteringJob, ParamReview, ProgressReporter,
+};
+use mastering_core::recipes::{Recipe, RecipeStore};
+use mastering_core::references::ReferenceLibrary;
+use mastering_core::types::{
+    AiProvider, AudioFormat, Backend, DeliveryTarget, Device, Dither, EqChannel, FadeCurve,
+    MasteringParams, MasteringResult, OverwritePolicy, RestorationStages, SampleFormat,
+    SilenceTrim, StemAdjustment, StemInput, SurroundMode, TimeRange,
+};
 
 #[derive(Args)]
 pub struct MasterArgs {
-    /// Input audio file to master
-    pub input: PathBuf,
+    /// Input audio file to master, or - to read a WAV or FLAC file from stdin
+    #[arg(required_unless_present = "stem")]
+    pub input: Option<PathBuf>,
+
+    /// Stem file to sum into the input as FILE or FILE:GAIN_DB (repeatable)
+    #[arg(long, value_name = "FILE[:GAIN_DB]", conflicts_with = "input")]
+    pub stem: Vec<String>,
 
     /// Reference track (triggers Matchering mode)
     #[arg(short, long)]
     pub reference: Option<PathBuf>,
 
-    /// Mastering backend: auto, matchering, ai, local-ml
+    /// Use a reference from the library carrying this genre or mood tag
+    #[arg(long, value_name = "TAG", conflicts_with = "reference")]
+    pub reference_tag: Option<String>,
+
+    /// Match the reference's tonal balance with a few EQ bands on the
+    /// built-in DSP chain instead of Matchering. The reference may also be
+    /// a curve saved with `analyze --save-curve`
+    #[arg(long)]
+    pub match_eq: bool,
+
+    /// Steer the EQ toward a target curve from the config's curves folder
+    #[arg(long, value_name = "NAME", conflicts_with = "match_eq")]
+    pub curve: Option<String>,
+
+    /// Share of the match EQ or curve difference to correct, 0 to 1
+    /// [default: general.match_eq_strength]
+    #[arg(long, value_name = "AMOUNT")]
+    pub match_strength: Option<f64>,
+
+    /// Mastering backend: auto, matchering, ai, local-ml, basic
     #[arg(short, long, default_value = "auto")]
     pub backend: String,
 
-    /// AI provider: ollama, keyhanstudio, openai, anthropic
+    /// Device for the local-ml backend: auto, cpu, cuda, metal
+    #[arg(long)]
+    pub device: Option<String>,
+
+    /// AI provider: ollama, lmstudio, keyhanstudio, openai, azure-openai, openai-compatible, anthropic, local-gguf, rules
     #[arg(long)]
     pub ai_provider: Option<String>,
 
-    /// Output file path
+    /// Output file path, or - to write the master to stdout
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
+    /// If the output exists: overwrite, error, or version (write NAME_v2, NAME_v3, ...)
+    /// [default: general.overwrite]
+    #[arg(long, value_name = "POLICY")]
+    pub overwrite: Option<String>,
+
     /// Output bit depth: 16, 24, or 32
     #[arg(long)]
     pub bit_depth: Option<u16>,
 
-    /// Output format: wav, flac, mp3
+    /// Write 32-bit float samples (WAV only; implies --bit-depth 32)
+    #[arg(long)]
+    pub float: bool,
+
+    /// Output format: wav, flac, aiff, alac, mp3, aac (m4a), opus (ogg)
     #[arg(short, long)]
     pub format: Option<String>,
 
+    /// Bitrate in kbps for lossy formats (mp3, aac, opus)
+    #[arg(long)]
+    pub bitrate: Option<u32>,
+
+    /// Extra deliverable as LUFS[:FORMAT[:BITS|KBPS]], e.g. -9:wav:16 or -14:mp3:320 (repeatable)
+    #[arg(
+        long,
+        value_name = "LUFS[:FORMAT[:BITS|KBPS]]",
+        allow_hyphen_values = true
+    )]
+    pub target: Vec<String>,
+
+    /// Output sample rate in Hz (e.g. 44100); defaults to the source rate
+    #[arg(long)]
+    pub sample_rate: Option<u32>,
+
+    /// Dither for 16/24-bit output: none, tpdf, high-pass, lipshitz, f-weighted
+    #[arg(long)]
+    pub dither: Option<String>,
+
+    /// Files with more than two channels: downmix (to stereo) or pass-through
+    #[arg(long)]
+    pub surround: Option<String>,
+
+    /// Override the title tag of the output
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// Override the artist tag of the output
+    #[arg(long)]
+    pub artist: Option<String>,
+
+    /// Override the album tag of the output
+    #[arg(long)]
+    pub album: Option<String>,
+
+    /// Override the album artist tag of the output
+    #[arg(long)]
+    pub album_artist: Option<String>,
+
+    /// Set the ISRC of the output
+    #[arg(long)]
+    pub isrc: Option<String>,
+
     /// Target loudness in LUFS
     #[arg(long)]
     pub target_lufs: Option<f64>,
 
-    /// Mastering preset: streaming, cd, vinyl, loud
+    /// Mastering preset: streaming, cd, vinyl, loud, podcast, game, game-console, cinema,
+    /// trailer, or a custom preset from the config
     #[arg(short, long)]
     pub preset: Option<String>,
 
+    /// Render a saved recipe as it is, with no AI call (see `mastering recipe list`)
+    #[arg(long, value_name = "NAME")]
+    pub recipe: Option<String>,
+
+    /// Fade-in length in seconds [default: the preset's]
+    #[arg(long, value_name = "SECS")]
+    pub fade_in: Option<f64>,
+
+    /// Fade-out length in seconds [default: the preset's]
+    #[arg(long, value_name = "SECS")]
+    pub fade_out: Option<f64>,
+
+    /// Fade shape: linear, log, cosine [default: the preset's]
+    #[arg(long)]
+    pub fade_curve: Option<String>,
+
+    /// Describe the intended sound for the AI, e.g. "warm, punchy, club-ready"
+    #[arg(long)]
+    pub brief: Option<String>,
+
+    /// Ask the AI to explain each parameter choice
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Process for spoken word: higher high-pass, de-essing, gentle compression, click repair
+    #[arg(long)]
+    pub speech: bool,
+
+    /// Reduce broadband noise (hiss, room tone) before mastering
+    #[arg(long)]
+    pub denoise: bool,
+
+    /// Remove 50/60 Hz mains hum and its harmonics before mastering
+    #[arg(long)]
+    pub dehum: bool,
+
+    /// Repair clicks before mastering
+    #[arg(long)]
+    pub declick: bool,
+
+    /// Repair vinyl crackle before mastering
+    #[arg(long)]
+    pub decrackle: bool,
+
+    /// Even out the left and right channels before mastering when they differ in level
+    #[arg(long)]
+    pub fix_balance: bool,
+
+    /// Invert the right channel before mastering when it is out of phase with the left
+    #[arg(long)]
+    pub fix_polarity: bool,
+
+    /// Cut leading and trailing silence before mastering
+    #[arg(long)]
+    pub trim_silence: bool,
+
+    /// Level in dBFS below which --trim-silence treats audio as silent [default: -60]
+    #[arg(
+        long,
+        value_name = "DB",
+        allow_negative_numbers = true,
+        requires = "trim_silence"
+    )]
+    pub silence_threshold: Option<f64>,
+
+    /// Silence in milliseconds --trim-silence keeps before and after the audio [default: 250]
+    #[arg(long, value_name = "MS", requires = "trim_silence")]
+    pub silence_padding: Option<f64>,
+
+    /// Master with one static chain instead of evening out the gain of
+    /// quiet and loud sections first
+    #[arg(long)]
+    pub no_sections: bool,
+
+    /// Master from this many seconds into the input; the output holds only the excerpt
+    #[arg(long, value_name = "SECS")]
+    pub start: Option<f64>,
+
+    /// Master up to this many seconds into the input
+    #[arg(long, value_name = "SECS")]
+    pub end: Option<f64>,
+
+    /// Quickly render only the loudest 30 seconds to <input>_preview for auditioning
+    #[arg(long)]
+    pub preview: bool,
+
+    /// Separate the input into stems and rebalance them before mastering
+    #[arg(long)]
+    pub stems: bool,
+
+    /// Gain for one stem as NAME=DB, e.g. vocals=1.5 (repeatable); skips the AI's suggestions
+    #[arg(long, value_name = "NAME=DB", requires = "stems")]
+    pub stem_gain: Vec<String>,
+
+    /// JSON file mapping stem names to {"gain_db", "eq"}; skips the AI's suggestions
+    #[arg(long, value_name = "FILE", requires = "stems")]
+    pub stem_params: Option<PathBuf>,
+
     /// Skip the final limiter
     #[arg(long)]
     pub no_limiter: bool,
 
+    /// Fail if the output exceeds the peak ceiling instead of re-limiting it
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Write ReplayGain tags (R128 tags for Opus) measured on the output
+    #[arg(long)]
+    pub replaygain: bool,
+
+    /// Review the AI's parameters before rendering: accept them, edit them
+    /// in $EDITOR or field by field, or reject them
+    #[arg(long)]
+    pub interactive: bool,
+
+    /// Save the applied parameters as JSON (for `mastering refine`)
+    #[arg(long)]
+    pub save_params: Option<PathBuf>,
+
+    /// Save the applied parameters as a named recipe for `--recipe`
+    #[arg(long, value_name = "NAME")]
+    pub save_recipe: Option<String>,
+
     /// Analyze only, don't process
     #[arg(long)]
     pub dry_run: bool,
+
+    /// Print the full result as JSON on stdout; progress and notes go to stderr
+    #[arg(long, conflicts_with = "interactive")]
+    pub json: bool,
+
+    /// Ignore cached analysis results and re-analyze
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Never use the network; cloud AI providers are refused or replaced by the rules provider
+    #[arg(long)]
+    pub offline: bool,
 }
 
 pub async fn run(args: MasterArgs) -> Result<()> {
     let config = Config::load().context("Loading configuration")?;
 
-    anyhow::ensure!(
-        args.input.exists(),
-        "Input file not found: {}",
-        args.input.display()
-    );
+    let stem_inputs: Vec<StemInput> = args.stem.iter().map(|s| s.parse()).collect::<Result<_>>()?;
+    let targets: Vec<DeliveryTarget> = args
+        .target
+        .iter()
+        .map(|t| t.parse())
+        .collect::<Result<_>>()?;
+    let from_stdin = args.input.as_deref().is_some_and(stdio::is_stdio);
+    let to_stdout = args.output.as_deref().is_some_and(stdio::is_stdio);
+    for path in args
+        .input
+        .iter()
+        .filter(|_| !from_stdin)
+        .chain(stem_inputs.iter().map(|s| &s.path))
+    {
+        super::ensure_input(path)?;
+    }
+    if from_stdin {
+        anyhow::ensure!(
+            args.output.is_some(),
+            "Reading the input from stdin needs -o FILE, or -o - to write to stdout"
+        );
+    }
+    if to_stdout {
+        anyhow::ensure!(
+            !args.json,
+            "--json and -o - both write to stdout; use one of them"
+        );
+        anyhow::ensure!(
+            targets.is_empty(),
+            "--target writes files next to the output and cannot be used with -o -"
+        );
+        stdio::ensure_stdout_redirected()?;
+    }
 
     let backend: Backend = args.backend.parse()?;
-    let ai_provider: Option<AiProvider> = args
-        .ai_provider
-        .map(|s| s.parse())
-        .transpose()?;
+    let reference = match args.reference_tag {
+        Some(ref tag) => Some(ReferenceLibrary::open_default()?.pick(tag)?),
+        None => args.reference,
+    };
+    let (reference, match_eq) = if args.match_eq {
+        anyhow::ensure!(
+            reference.is_some(),
+            "--match-eq needs --reference or --reference-tag"
+        );
+        (None, reference)
+    } else if let Some(ref name) = args.curve {
+        (reference, Some(CurveStore::open_default()?.path(name)?))
+    } else {
+        (reference, None)
+    };
+    if let Some(strength) = args.match_strength {
+        anyhow::ensure!(
+            (0.0..=1.0).contains(&strength),
+            "--match-strength must be between 0 and 1"
+        );
+        anyhow::ensure!(
+            match_eq.is_some(),
+            "--match-strength needs --match-eq or --curve"
+        );
+    }
+    let ai_provider: Option<AiProvider> = args.ai_provider.map(|s| s.parse()).transpose()?;
     let format: Option<AudioFormat> = args.format.map(|s| s.parse()).transpose()?;
-    let preset: Option<Preset> = args.preset.map(|s| s.parse()).transpose()?;
+    let dither: Option<Dither> = args.dither.map(|s| s.parse()).transpose()?;
+    let overwrite: Option<OverwritePolicy> = args.overwrite.map(|s| s.parse()).transpose()?;
+    let fade_curve: Option<FadeCurve> = args.fade_curve.map(|s| s.parse()).transpose()?;
+    for secs in [args.fade_in, args.fade_out].into_iter().flatten() {
+        anyhow::ensure!(secs >= 0.0, "Fade lengths cannot be negative (got {secs})");
+    }
+    let surround_mode: Option<SurroundMode> = args.surround.map(|s| s.parse()).transpose()?;
+    let device: Option<Device> = args.device.map(|s| s.parse()).transpose()?;
+    let stem_adjustments = stem_adjustments(args.stem_params.as_deref(), &args.stem_gain)?;
+    let trim_silence = args.trim_silence.then(|| {
+        let defaults = SilenceTrim::default();
+        SilenceTrim {
+            threshold_db: args.silence_threshold.unwrap_or(defaults.threshold_db),
+            padding_ms: args.silence_padding.unwrap_or(defaults.padding_ms),
+        }
+    });
+    let range = (args.start.is_some() || args.end.is_some()).then(|| TimeRange {
+        start_secs: args.start.unwrap_or(0.0),
+        end_secs: args.end,
+    });
+    if let Some(ref range) = range {
+        range.validate()?;
+    }
 
     if let Some(bd) = args.bit_depth {
         anyhow::ensure!(
             bd == 16 || bd == 24 || bd == 32,
             "Bit depth must be 16, 24, or 32 (got {bd})"
         );
+        anyhow::ensure!(
+            !args.float || bd == 32,
+            "--float requires a bit depth of 32"
+        );
     }
+    let sample_format = if args.float {
+        SampleFormat::Float
+    } else {
+        SampleFormat::Int
+    };
+
+    // Piped audio is spooled through a temporary folder for the pipeline
+    let spool = (from_stdin || to_stdout)
+        .then(stdio::Spool::new)
+        .transpose()?;
+    let input = match spool {
+        Some(ref spool) if from_stdin => Some(spool.read_stdin()?),
+        _ => args.input.clone(),
+    };
 
-    let job = MasteringJob {
-        input_path: args.input.clone(),
+    let cancel_token = CancellationToken::new();
+    let mut job = MasteringJob {
+        input_path: input.unwrap_or_default(),
+        stem_inputs,
         output_path: args.output,
-        reference_path: args.reference,
+        overwrite,
+        reference_path: reference,
+        match_eq,
+        match_eq_strength: args.match_strength,
         backend,
         ai_provider,
         lmstudio_model: None,
+        device,
         bit_depth: args.bit_depth,
+        sample_format,
         format,
+        bitrate_kbps: args.bitrate,
+        targets,
+        sample_rate: args.sample_rate,
+        dither,
+        surround_mode,
         target_lufs: args.target_lufs,
+        ceiling_db: None,
         no_limiter: args.no_limiter,
-        preset,
+        preset: None,
+        params: None,
+        fade_in_secs: args.fade_in,
+        fade_out_secs: args.fade_out,
+        fade_curve,
+        brief: args
+            .brief
+            .map(|b| b.trim().to_string())
+            .filter(|b| !b.is_empty()),
+        refinement: None,
+        explain: args.explain,
+        review: ParamReview::disabled(),
+        speech: args.speech,
+        restoration: RestorationStages {
+            denoise: args.denoise,
+            dehum: args.dehum,
+            declick: args.declick,
+            decrackle: args.decrackle,
+        },
+        fix_balance: args.fix_balance,
+        fix_polarity: args.fix_polarity,
+        trim_silence,
+        no_sections: args.no_sections,
+        range,
+        preview: args.preview,
+        stems: args.stems,
+        stem_adjustments,
         dry_run: args.dry_run,
+        // A spooled input never has the same path twice
+        no_cache: args.no_cache || from_stdin,
+        offline: args.offline,
+        strict: args.strict,
+        replaygain: args.replaygain,
+        tags: TagOverrides {
+            title: args.title,
+            artist: args.artist,
+            album: args.album,
+            album_artist: args.album_artist,
+            isrc: args.isrc,
+        },
+        cancel_token: cancel_token.clone(),
+    };
+    if let Some(ref name) = args.preset {
+        job.apply_preset(name, &config)?;
+    }
+    if let Some(ref name) = args.recipe {
+        let recipe = RecipeStore::open_default()?.load(name)?;
+        job.apply_recipe(&recipe, &config);
+    }
+    if let Some(spool) = spool.as_ref().filter(|_| to_stdout) {
+        let format = job.format.unwrap_or(config.general.default_format);
+        job.output_path = Some(spool.output_path(format));
+    }
+    if args.interactive {
+        anyhow::ensure!(
+            job.resolved_backend() == Backend::Ai,
+            "--interactive reviews the AI backend's parameters; use --backend ai, or auto without a reference"
+        );
+        anyhow::ensure!(
+            std::io::stdin().is_terminal(),
+            "--interactive needs a terminal to prompt on"
+        );
+    }
+
+    // Ctrl-C cancels the job; the pipeline kills the backend and removes partial output
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel_token.cancel();
+        }
+    });
+
+    // With --json or -o -, stdout carries nothing but the result or the audio
+    let (json, quiet) = (args.json, super::quiet());
+    let note = |line: String| match (quiet, json || to_stdout) {
+        (true, _) => {}
+        (_, true) => eprintln!("{line}"),
+        _ => println!("{line}"),
     };
 
-    println!(
+    let source = match args.input {
+        Some(_) if from_stdin => "stdin".to_string(),
+        Some(ref input) => input.display().to_string(),
+        None => format!("{} stems", job.stem_inputs.len()),
+    };
+    note(format!(
         "\n{}  {}",
         "MASTERING".bold().cyan(),
-        args.input.display().to_string().white()
-    );
+        source.white()
+    ));
 
     let spinner = indicatif::ProgressBar::new_spinner();
     spinner.set_style(
@@ -105,16 +517,119 @@ pub async fn run(args: MasterArgs) -> Result<()> {
             .unwrap(),
     );
     spinner.set_message("Processing...");
+    if quiet {
+        spinner.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+    }
     spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    if args.interactive {
+        let (spinner, cancel) = (spinner.clone(), job.cancel_token.clone());
+        let limits = config.ai.limits.clone();
+        job.review = ParamReview::new(move |params| {
+            spinner.suspend(|| review_params(params, &limits, &cancel))
+        });
+    }
 
-    let result = pipeline::run(&job, &config).await?;
+    let result = pipeline::run_with_progress(&job, &config, &spinner_progress(&spinner)).await;
 
     spinner.finish_and_clear();
+    // Piped audio passes through temporary files not worth keeping
+    if spool.is_none() {
+        jobs::remember(&job, &result);
+    }
+
+    if job.cancel_token.is_cancelled() {
+        note(format!("{} Mastering cancelled", "!".bold().yellow()));
+        // Exiting skips destructors, so remove the spool first
+        drop(spool);
+        std::process::exit(crate::exit::CANCELLED.into());
+    }
+    let result = result.map_err(|e| crate::exit::in_backend(e, job.resolved_backend()))?;
+
+    if let Some(ref path) = args.save_params {
+        save_params(path, &result)?;
+        note(format!("  Parameters saved to {}", path.display()));
+    }
+    if let Some(ref name) = args.save_recipe {
+        let recipe = Recipe::from_result(name, &job.input_path, &result)?;
+        let path = RecipeStore::open_default()?.save(&recipe)?;
+        note(format!("  Recipe {name} saved to {}", path.display()));
+    }
+
+    if to_stdout {
+        if !job.dry_run {
+            stdio::write_stdout(&result.output_path)?;
+        }
+        if let Some(ref post) = result.post_analysis {
+            note(format!(
+                "{} {:.1} LUFS, {:.1} dBTP written to stdout",
+                "Mastered:".bold().green(),
+                post.lufs_integrated,
+                post.true_peak_db
+            ));
+        }
+    } else if json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else if !quiet {
+        print_result(&result);
+    }
+    Ok(())
+}
+
+/// Stem adjustments from a `--stem-params` file, with `--stem-gain` values
+/// overriding its gains.
+fn stem_adjustments(
+    params: Option<&Path>,
+    gains: &[String],
+) -> Result<BTreeMap<String, StemAdjustment>> {
+    let mut adjustments: BTreeMap<String, StemAdjustment> = match params {
+        Some(path) => {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Reading stem parameters from {}", path.display()))?;
+            serde_json::from_str(&json)
+                .with_context(|| format!("Parsing stem parameters in {}", path.display()))?
+        }
+        None => BTreeMap::new(),
+    };
+    for gain in gains {
+        let (name, db) = gain
+            .split_once('=')
+            .with_context(|| format!("Expected NAME=DB for --stem-gain, got {gain}"))?;
+        let db: f64 = db
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid gain for stem {name}: {db}"))?;
+        adjustments
+            .entry(name.trim().to_string())
+            .or_default()
+            .gain_db = db;
+    }
+    Ok(adjustments)
+}
+
+/// A progress reporter that shows each update as the spinner message, so
+/// slow stages such as a streaming AI model do not look frozen.
+pub fn spinner_progress(spinner: &indicatif::ProgressBar) -> ProgressReporter {
+    let (reporter, mut rx) = ProgressReporter::channel();
+    let spinner = spinner.clone();
+    tokio::spawn(async move {
+        while let Some(update) = rx.recv().await {
+            spinner.set_message(update.message);
+        }
+    });
+    reporter
+}
 
-    // Print results
+/// Print the outcome of a mastering job.
+pub fn print_result(result: &MasteringResult) {
     println!("\n{}", "Results".bold().green());
     println!("  Backend:  {}", result.backend_used.cyan());
+    if let Some(device) = result.device {
+        println!("  Device:   {device}");
+    }
     println!("  Output:   {}", result.output_path.display().to_string().white());
+    if let Some(ref brief) = result.brief {
+        println!("  Brief:    {}", brief.italic());
+    }
 
     if let Some(ref pre) = result.pre_analysis {
         println!("\n{}", "Input Analysis".bold().yellow());
@@ -123,33 +638,409 @@ pub async fn run(args: MasterArgs) -> Result<()> {
         println!("  RMS:          {:.1} dB", pre.rms_db);
         println!("  Dynamic Range:{:.1} dB", pre.dynamic_range_db);
         println!("  Stereo Width: {:.2}", pre.stereo_width);
+        println!("  Balance:      {:+.1} dB (L/R)", pre.stereo_balance_db);
+        if pre.metadata.channels > 2 {
+            println!("  Channels:     {}", pre.metadata.channel_layout);
+        }
+        println!("  Sample Rate:  {} Hz", pre.metadata.sample_rate);
         println!("  Duration:     {:.1}s", pre.metadata.duration_secs);
+        if let Some(warning) = analysis::clipping_warning(pre) {
+            println!("  {} {warning}", "WARNING:".bold().red());
+        }
+        for warning in analysis::channel_warnings(pre) {
+            println!("  {} {warning}", "WARNING:".bold().red());
+        }
     }
 
     if let Some(ref post) = result.post_analysis {
         println!("\n{}", "Output Analysis".bold().green());
         println!("  LUFS:         {:.1}", post.lufs_integrated);
         println!("  Peak:         {:.1} dB", post.peak_db);
+        println!("  True Peak:    {:.1} dBTP", post.true_peak_db);
         println!("  RMS:          {:.1} dB", post.rms_db);
         println!("  Dynamic Range:{:.1} dB", post.dynamic_range_db);
         println!("  Stereo Width: {:.2}", post.stereo_width);
+        println!("  Balance:      {:+.1} dB (L/R)", post.stereo_balance_db);
+        match result.pre_analysis {
+            Some(ref pre) if pre.metadata.sample_rate != post.metadata.sample_rate => println!(
+                "  Sample Rate:  {} Hz (resampled from {} Hz)",
+                post.metadata.sample_rate, pre.metadata.sample_rate
+            ),
+            _ => println!("  Sample Rate:  {} Hz", post.metadata.sample_rate),
+        }
+        super::platforms::print_predictions(&analysis::platforms::predict(post));
+    }
+
+    if !result.deliverables.is_empty() {
+        println!("\n{}", "Deliverables".bold().green());
+        for d in &result.deliverables {
+            println!(
+                "  {} ({:.1} LUFS, {:.1} dBTP)",
+                d.path.display().to_string().white(),
+                d.lufs_integrated,
+                d.true_peak_db
+            );
+        }
+    }
+
+    if let Some(ref gain) = result.replaygain {
+        println!(
+            "\n{} track gain {:+.2} dB, peak {:.6}",
+            "ReplayGain:".bold().blue(),
+            gain.track_gain_db,
+            gain.track_peak
+        );
+    }
+
+    if let Some(excerpt) = result.excerpt {
+        println!(
+            "\n{} only {excerpt} of the input was mastered",
+            "Excerpt:".bold().blue()
+        );
+    }
+
+    if result.polarity_flipped {
+        println!(
+            "\n{} right channel inverted to fix out-of-phase input",
+            "Polarity:".bold().blue()
+        );
+    }
+
+    if let Some(trimmed) = result.silence_trimmed {
+        println!(
+            "\n{} {:.2}s cut from the start, {:.2}s from the end",
+            "Silence:".bold().blue(),
+            trimmed.leading_secs,
+            trimmed.trailing_secs
+        );
+    }
+
+    if !result.sections.is_empty() {
+        println!("\n{}", "Sections".bold().blue());
+        for section in &result.sections {
+            println!(
+                "  {:>6.1}s - {:>6.1}s  {:>6.1} dB  {:+.1} dB",
+                section.start_secs, section.end_secs, section.level_db, section.gain_db
+            );
+        }
+    }
+
+    if let Some(db) = result.balance_correction_db {
+        let louder = if db > 0.0 { "left" } else { "right" };
+        println!(
+            "\n{} {louder} channel turned down {:.1} dB",
+            "Balance:".bold().blue(),
+            db.abs()
+        );
+    }
+
+    if let Some(ref restoration) = result.restoration {
+        println!("\n{}", "Restoration".bold().blue());
+        match restoration.hum_hz {
+            Some(hz) => println!("  Hum:          removed at {hz:.0} Hz"),
+            None => println!("  Hum:          {}", "none found".dimmed()),
+        }
+        println!("  Clicks:       {} repaired", restoration.clicks_repaired);
+        if let Some(floor) = restoration.noise_floor_db {
+            println!("  Noise Floor:  {floor:.1} dBFS (reduced)");
+        }
+    }
+
+    if let Some(ref stems) = result.stem_adjustments {
+        println!("\n{}", "Stem Adjustments".bold().blue());
+        if stems.is_empty() {
+            println!("  {}", "none; stems recombined unchanged".dimmed());
+        }
+        for (name, adjustment) in stems {
+            println!(
+                "  {:<12}{:+.1} dB, {} EQ band(s)",
+                name,
+                adjustment.gain_db,
+                adjustment.eq.len()
+            );
+        }
     }
 
     if let Some(ref params) = result.params_applied {
         println!("\n{}", "Applied Parameters".bold().blue());
-        println!("  EQ Bands:     {}", params.eq.len());
+        let mid_side = params
+            .eq
+            .iter()
+            .filter(|b| b.channel != EqChannel::Stereo)
+            .count();
+        if mid_side > 0 {
+            println!("  EQ Bands:     {} ({mid_side} mid/side)", params.eq.len());
+        } else {
+            println!("  EQ Bands:     {}", params.eq.len());
+        }
+        if let Some(ref multiband) = params.multiband {
+            let crossovers: Vec<String> = multiband
+                .crossovers
+                .iter()
+                .map(|f| format!("{f:.0}"))
+                .collect();
+            println!(
+                "  Multiband:    {} bands, crossovers {} Hz",
+                multiband.bands.len(),
+                crossovers.join(" / ")
+            );
+            for (i, band) in multiband.bands.iter().enumerate() {
+                println!(
+                    "    Band {}:     ratio {:.1}:1, threshold {:.1} dB",
+                    i + 1,
+                    band.ratio,
+                    band.threshold_db
+                );
+            }
+        }
         println!(
             "  Compression:  ratio {:.1}:1, threshold {:.1} dB",
             params.compression.ratio, params.compression.threshold_db
         );
+        if params.de_esser.enabled {
+            println!(
+                "  De-esser:     above {:.0} Hz, threshold {:.1} dB, up to {:.1} dB",
+                params.de_esser.frequency,
+                params.de_esser.threshold_db,
+                params.de_esser.max_reduction_db
+            );
+        }
         if params.limiter.enabled {
-            println!("  Limiter:      ceiling {:.1} dB", params.limiter.ceiling_db);
+            let limiter = &params.limiter;
+            if limiter.oversampling > 1 {
+                println!(
+                    "  Limiter:      ceiling {:.1} dBTP, {:.1} ms look-ahead, {}x oversampled",
+                    limiter.ceiling_db, limiter.lookahead_ms, limiter.oversampling
+                );
+            } else {
+                println!(
+                    "  Limiter:      ceiling {:.1} dB, {:.1} ms look-ahead",
+                    limiter.ceiling_db, limiter.lookahead_ms
+                );
+            }
         } else {
             println!("  Limiter:      disabled");
         }
         println!("  Target LUFS:  {:.1}", params.target_lufs);
     }
 
+    if !result.candidates.is_empty() {
+        println!("\n{}", "Candidates".bold().blue());
+        for (i, c) in result.candidates.iter().enumerate() {
+            let line = format!(
+                "  #{}  score {:.2}  LUFS {:.1} (error {:.2})  spectral error {:.2} dB",
+                i + 1,
+                c.score,
+                c.lufs,
+                c.lufs_error,
+                c.spectral_error
+            );
+            if c.selected {
+                println!("{}  {}", line, "selected".green());
+            } else {
+                println!("{}", line.dimmed());
+            }
+        }
+    }
+
+    if let (Some(ref explanation), Some(ref params)) = (&result.explanation, &result.params_applied)
+    {
+        println!("\n{}", "Why These Settings".bold().blue());
+        if !explanation.summary.is_empty() {
+            println!("  {}", explanation.summary);
+        }
+        for (band, reason) in params.eq.iter().zip(&explanation.eq) {
+            let channel = match band.channel {
+                EqChannel::Stereo => "",
+                EqChannel::Mid => " (mid)",
+                EqChannel::Side => " (side)",
+            };
+            println!(
+                "  EQ {:>7.0} Hz {:+.1} dB{channel}: {}",
+                band.frequency,
+                band.gain_db,
+                reason.dimmed()
+            );
+        }
+        for (label, reason) in [
+            ("De-esser", &explanation.de_esser),
+            ("Multiband", &explanation.multiband),
+            ("Compression", &explanation.compression),
+            ("Limiter", &explanation.limiter),
+            ("Stereo", &explanation.stereo),
+        ] {
+            if !reason.is_empty() {
+                println!("  {label}: {}", reason.dimmed());
+            }
+        }
+    }
+
+    if let Some(ref usage) = result.usage {
+        println!("\n{}", "AI Usage".bold().blue());
+        println!("  Requests:     {}", usage.requests);
+        println!(
+            "  Tokens:       {} in / {} out",
+            usage.input_tokens, usage.output_tokens
+        );
+        match usage.estimated_cost_usd {
+            Some(cost) => println!("  Est. Cost:    ${cost:.4}"),
+            None => println!("  Est. Cost:    {}", "unknown model price".dimmed()),
+        }
+    }
+
+    if !result.param_corrections.is_empty() {
+        println!("\n{}", "Parameter Corrections".bold().yellow());
+        for c in &result.param_corrections {
+            println!("  {}: {}", c.field, c.message);
+        }
+    }
+
     println!();
+}
+
+/// Write the applied parameters as JSON, e.g. as input to `mastering refine`.
+pub fn save_params(path: &Path, result: &MasteringResult) -> Result<()> {
+    let params = result
+        .params_applied
+        .as_ref()
+        .context("The backend did not report the parameters it applied")?;
+    std::fs::write(path, serde_json::to_string_pretty(params)?)
+        .with_context(|| format!("Writing parameters to {}", path.display()))
+}
+
+/// Show the AI's parameters and let the user accept, edit or reject them.
+/// Rejecting cancels the job.
+fn review_params(
+    mut params: MasteringParams,
+    limits: &ParamLimits,
+    cancel: &CancellationToken,
+) -> Result<MasteringParams> {
+    loop {
+        println!("\n{}", "Suggested Parameters".bold().yellow());
+        println!("{}", serde_json::to_string_pretty(&params)?);
+        print!("\n  [a]ccept  [e]dit in $EDITOR  [f]ield by field  [q]uit: ");
+        std::io::stdout().flush()?;
+
+        let edited = match read_line()?.to_lowercase().as_str() {
+            "" | "a" | "accept" => return Ok(params),
+            "e" | "edit" => edit_in_editor(&params),
+            "f" | "field" => edit_fields(&params),
+            "q" | "quit" => {
+                cancel.cancel();
+                anyhow::bail!("Parameters rejected at review");
+            }
+            other => {
+                println!("  {} Unknown choice: {other}", "!".bold().yellow());
+                continue;
+            }
+        };
+        match edited {
+            Ok(mut edited) => {
+                for c in mastering_core::validate::clamp_params(&mut edited, limits) {
+                    println!(
+                        "  {} {}: {}",
+                        "Corrected".bold().yellow(),
+                        c.field,
+                        c.message
+                    );
+                }
+                params = edited;
+            }
+            Err(e) => println!("  {} {e:#}", "!".bold().red()),
+        }
+    }
+}
+
+fn read_line() -> Result<String> {
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+/// Open the parameters as JSON in $VISUAL or $EDITOR and parse the result.
+fn edit_in_editor(params: &MasteringParams) -> Result<MasteringParams> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+    let path = std::env::temp_dir().join(format!("mastering-params-{}.json", std::process::id()));
+    std::fs::write(&path, serde_json::to_string_pretty(params)?)?;
+
+    // The editor may carry arguments, e.g. "code --wait"
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("$EDITOR is empty")?;
+    let status = std::process::Command::new(program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("Starting editor {editor}"));
+    let json = std::fs::read_to_string(&path);
+    let _ = std::fs::remove_file(&path);
+    anyhow::ensure!(
+        status?.success(),
+        "Editor {editor} exited with an error; keeping the previous parameters"
+    );
+    serde_json::from_str(&json?)
+        .context("The edited parameters are not valid; keeping the previous ones")
+}
+
+/// Prompt for every number and switch in the parameters; Enter keeps a value.
+fn edit_fields(params: &MasteringParams) -> Result<MasteringParams> {
+    let mut value = serde_json::to_value(params)?;
+    println!("  Enter a new value, or press Enter to keep the current one");
+    prompt_leaves(&mut value, String::new())?;
+    serde_json::from_value(value)
+        .context("The edited parameters are not valid; keeping the previous ones")
+}
+
+fn prompt_leaves(value: &mut serde_json::Value, path: String) -> Result<()> {
+    use serde_json::Value;
+
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{path}.{key}")
+        }
+    };
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                prompt_leaves(child, join(key))?;
+            }
+        }
+        Value::Array(items) => {
+            for (i, child) in items.iter_mut().enumerate() {
+                prompt_leaves(child, format!("{path}[{i}]"))?;
+            }
+        }
+        Value::Number(_) | Value::Bool(_) => loop {
+            print!("  {path} [{value}]: ");
+            std::io::stdout().flush()?;
+            let input = read_line()?;
+            if input.is_empty() {
+                break;
+            }
+            match serde_json::from_str::<Value>(&input) {
+                Ok(new)
+                    if new.is_number() == value.is_number()
+                        && new.is_boolean() == value.is_boolean() =>
+                {
+                    *value = new;
+                    break;
+                }
+                _ => println!(
+                    "  {} expected a {}",
+                    "!".bold().yellow(),
+                    if value.is_boolean() {
+                        "true or false"
+                    } else {
+                        "number"
+                    }
+                ),
+            }
+        },
+        // Names such as band types are edited in $EDITOR
+        _ => {}
+    }
     Ok(())
 }
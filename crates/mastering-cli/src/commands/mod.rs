@@ -1,4 +1,77 @@
+pub mod ab;
 pub mod analyze;
 pub mod backends;
+pub mod compare;
+pub mod completions;
 pub mod config;
+pub mod curve;
+pub mod diff;
+pub mod jobs;
 pub mod master;
+pub mod models;
+pub mod platforms;
+pub mod preset;
+pub mod recipe;
+pub mod reference;
+pub mod refine;
+pub mod spectrogram;
+pub mod tui;
+pub mod validate;
+pub mod watch;
+pub mod waveform;
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use mastering_core::config::Config;
+use mastering_core::pipeline::MasteringJob;
+use mastering_core::types::{AudioFormat, Backend};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Print nothing but errors and output asked for, such as `--json`.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Fail with [`exit::INPUT`](crate::exit::INPUT) unless `path` exists.
+pub fn ensure_input(path: &Path) -> anyhow::Result<()> {
+    if path.exists() {
+        Ok(())
+    } else {
+        Err(crate::exit::InputMissing(path.to_path_buf()).into())
+    }
+}
+
+/// Settings every file is mastered with by commands that master many
+/// files, such as `tui`, `watch` and `jobs add`.
+pub struct JobSettings {
+    pub preset: Option<String>,
+    pub backend: Backend,
+    pub format: Option<AudioFormat>,
+    /// Folder to write masters to instead of next to each input.
+    pub out: Option<PathBuf>,
+}
+
+impl JobSettings {
+    pub fn build_job(&self, input: &Path, config: &Config) -> anyhow::Result<MasteringJob> {
+        let mut job = MasteringJob {
+            input_path: input.to_path_buf(),
+            backend: self.backend,
+            format: self.format,
+            ..Default::default()
+        };
+        if let Some(ref name) = self.preset {
+            job.apply_preset(name, config)?;
+        }
+        if let Some(ref out) = self.out {
+            let output = job.resolved_output_path(config);
+            job.output_path = output.file_name().map(|name| out.join(name));
+        }
+        Ok(job)
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+
+use mastering_core::config::Config;
+use mastering_core::models::ModelStore;
+
+#[derive(Args)]
+pub struct ModelsArgs {
+    #[command(subcommand)]
+    pub command: ModelsCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ModelsCommand {
+    /// List configured and installed models
+    List,
+
+    /// Download a configured model from the HuggingFace Hub
+    Download {
+        /// Name of the model in [backends.local_ml.models]
+        name: String,
+    },
+
+    /// Delete an installed model
+    Remove {
+        /// Name of the installed model
+        name: String,
+    },
+}
+
+pub async fn run(args: ModelsArgs) -> Result<()> {
+    let config = Config::load()?;
+    let store = ModelStore::new(ModelStore::default_dir()?);
+
+    match args.command {
+        ModelsCommand::List => list(&config, &store),
+        ModelsCommand::Download { name } => download(&config, &store, &name).await,
+        ModelsCommand::Remove { name } => {
+            store.remove(&name)?;
+            println!("{} Removed model {name}", "OK".bold().green());
+            Ok(())
+        }
+    }
+}
+
+fn list(config: &Config, store: &ModelStore) -> Result<()> {
+    let models = store.list(&config.backends.local_ml.models)?;
+    println!("\n{}", "Local ML Models".bold().cyan());
+    if models.is_empty() {
+        println!(
+            "\n  No models configured. Add one under [backends.local_ml.models.<name>] in {}",
+            Config::config_path()?.display()
+        );
+        return Ok(());
+    }
+
+    for model in &models {
+        let status = if model.installed {
+            "INSTALLED".bold().green()
+        } else {
+            "NOT INSTALLED".bold().yellow()
+        };
+        println!("\n  {} [{}]", model.name.bold().white(), status);
+        if let Some(source) = &model.source {
+            println!("    Source: {}@{}", source.repo, source.revision);
+        }
+        if model.installed {
+            println!(
+                "    Path:   {} ({:.1} MB)",
+                model.path.display(),
+                model.size_bytes as f64 / 1_048_576.0
+            );
+        }
+    }
+    println!();
+    Ok(())
+}
+
+async fn download(config: &Config, store: &ModelStore, name: &str) -> Result<()> {
+    let bar = indicatif::ProgressBar::new(0);
+    bar.set_style(
+        indicatif::ProgressStyle::default_bar()
+            .template("{msg} [{bar:30.cyan}] {bytes}/{total_bytes}")
+            .unwrap()
+            .progress_chars("=> "),
+    );
+
+    let path = store
+        .download(name, config, |file, done, total| {
+            if bar.message() != file {
+                bar.set_message(file.to_string());
+                bar.set_length(total.unwrap_or(0));
+            }
+            bar.set_position(done);
+        })
+        .await;
+    bar.finish_and_clear();
+    let path = path?;
+
+    println!(
+        "{} Model {name} verified and installed in {}",
+        "OK".bold().green(),
+        path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::analysis::platforms::{self, PlatformPrediction};
+use mastering_core::{analysis, cache};
+
+#[derive(Args)]
+pub struct PlatformsArgs {
+    /// Audio file, usually a finished master
+    pub input: PathBuf,
+
+    /// Output the predictions as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Ignore cached analysis results and re-analyze
+    #[arg(long)]
+    pub no_cache: bool,
+}
+
+pub async fn run(args: PlatformsArgs) -> Result<()> {
+    super::ensure_input(&args.input)?;
+
+    let cache = (!args.no_cache).then(cache::global_cache);
+    let analysis = analysis::analyze_file_cached(&args.input, cache)
+        .await
+        .context("Audio analysis failed")?;
+    let predictions = platforms::predict(&analysis);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&predictions)?);
+        return Ok(());
+    }
+
+    println!(
+        "\n{}  {}",
+        "PLATFORMS".bold().cyan(),
+        args.input.display().to_string().white()
+    );
+    println!(
+        "  {:.1} LUFS integrated, {:.1} dBTP true peak",
+        analysis.lufs_integrated, analysis.true_peak_db
+    );
+    print_predictions(&predictions);
+    println!();
+    Ok(())
+}
+
+/// Print a table of platform predictions.
+pub fn print_predictions(predictions: &[PlatformPrediction]) {
+    println!(
+        "\n{}",
+        format!(
+            "  {:<14} {:>8} {:>8} {:>10} {:>10}",
+            "Platform", "Target", "Gain", "Playback", "True Peak"
+        )
+        .bold()
+        .yellow()
+    );
+    for p in predictions {
+        let mut notes = Vec::new();
+        if p.below_target {
+            notes.push("quieter than other tracks".yellow().to_string());
+        }
+        if p.peak_risk {
+            notes.push("encoder may clip".red().to_string());
+        }
+        println!(
+            "  {:<14} {:>8.1} {:>+8.1} {:>10.1} {:>10.1}  {}",
+            p.platform,
+            p.target_lufs,
+            p.gain_db,
+            p.playback_lufs,
+            p.playback_true_peak_db,
+            notes.join(", ")
+        );
+    }
+}
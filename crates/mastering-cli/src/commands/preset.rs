@@ -0,0 +1,218 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::config::{Config, CustomPreset};
+use mastering_core::types::{AudioFormat, MasteringParams, Preset};
+
+#[derive(Args)]
+pub struct PresetArgs {
+    #[command(subcommand)]
+    pub command: PresetCommand,
+}
+
+#[derive(Subcommand)]
+pub enum PresetCommand {
+    /// List built-in and custom presets
+    List,
+
+    /// Show the settings of a preset
+    Show {
+        /// Name of the preset
+        name: String,
+    },
+
+    /// Create a custom preset, or replace one with --force
+    Create(CreateArgs),
+
+    /// Delete a custom preset
+    Delete {
+        /// Name of the custom preset
+        name: String,
+    },
+}
+
+#[derive(Args)]
+pub struct CreateArgs {
+    /// Name of the new preset
+    pub name: String,
+
+    /// One-line description shown in listings
+    #[arg(long)]
+    pub description: Option<String>,
+
+    /// Target integrated loudness in LUFS
+    #[arg(long, allow_hyphen_values = true)]
+    pub target_lufs: Option<f64>,
+
+    /// Highest true peak of the output in dBTP
+    #[arg(long, value_name = "DBTP", allow_hyphen_values = true)]
+    pub ceiling: Option<f64>,
+
+    /// Output format: wav, flac, mp3, aac, opus
+    #[arg(long)]
+    pub format: Option<String>,
+
+    /// Output bit depth
+    #[arg(long)]
+    pub bit_depth: Option<u16>,
+
+    /// Fix the chain to parameters saved by `mastering master --save-params`
+    #[arg(long, value_name = "FILE")]
+    pub from_params: Option<PathBuf>,
+
+    /// Replace an existing custom preset of the same name
+    #[arg(long)]
+    pub force: bool,
+}
+
+pub fn run(args: PresetArgs) -> Result<()> {
+    let mut config = Config::load()?;
+
+    match args.command {
+        PresetCommand::List => {
+            list(&config);
+            Ok(())
+        }
+        PresetCommand::Show { name } => show(&config, &name),
+        PresetCommand::Create(args) => create(&mut config, args),
+        PresetCommand::Delete { name } => {
+            if name.parse::<Preset>().is_ok() {
+                anyhow::bail!("{name} is a built-in preset and cannot be deleted");
+            }
+            config
+                .presets
+                .remove(&name)
+                .with_context(|| format!("No custom preset named {name}"))?;
+            config.save()?;
+            println!("{} Deleted preset {name}", "OK".bold().green());
+            Ok(())
+        }
+    }
+}
+
+fn list(config: &Config) {
+    println!("\n{}", "Built-in Presets".bold().yellow());
+    for preset in Preset::ALL {
+        println!("  {:<18} {}", preset.to_string(), preset.description());
+    }
+
+    println!("\n{}", "Custom Presets".bold().yellow());
+    if config.presets.is_empty() {
+        println!(
+            "  None yet. Create one with {}",
+            "mastering preset create <name>".cyan()
+        );
+    }
+    for (name, preset) in &config.presets {
+        let description = preset.description.as_deref().unwrap_or_default();
+        println!(
+            "  {:<18} {}  {}",
+            name,
+            preset.summary(),
+            description.dimmed()
+        );
+    }
+    println!();
+}
+
+fn show(config: &Config, name: &str) -> Result<()> {
+    if let Ok(preset) = name.parse::<Preset>() {
+        println!(
+            "\n{}  {}",
+            "PRESET".bold().cyan(),
+            preset.to_string().bold()
+        );
+        println!("  {}", preset.description());
+        println!("\n  Target LUFS:       {:.1}", preset.target_lufs());
+        if let Some(ceiling) = preset.true_peak_ceiling_db() {
+            println!("  True Peak Ceiling: {ceiling:.1} dBTP");
+        }
+        if let Some((min, max)) = preset.loudness_range_lu() {
+            println!("  Loudness Range:    {min:.0} – {max:.0} LU");
+        }
+        if preset.is_speech() {
+            println!("  Speech Mode:       yes");
+        }
+        println!();
+        return Ok(());
+    }
+
+    let preset = config.presets.get(name).with_context(|| {
+        format!("Unknown preset: {name}. Run `mastering preset list` to see them all")
+    })?;
+    println!("\n{}  {}", "PRESET".bold().cyan(), name.bold());
+    if let Some(ref description) = preset.description {
+        println!("  {description}");
+    }
+    println!();
+    if let Some(lufs) = preset.target_lufs {
+        println!("  Target LUFS:       {lufs:.1}");
+    }
+    if let Some(ceiling) = preset.ceiling_db {
+        println!("  True Peak Ceiling: {ceiling:.1} dBTP");
+    }
+    if let Some(format) = preset.format {
+        println!("  Format:            {format}");
+    }
+    if let Some(bits) = preset.bit_depth {
+        println!("  Bit Depth:         {bits}");
+    }
+    if let Some(ref params) = preset.params {
+        println!("\n{}", "Fixed Chain".bold().yellow());
+        println!("{}", serde_json::to_string_pretty(params)?);
+    }
+    println!();
+    Ok(())
+}
+
+fn create(config: &mut Config, args: CreateArgs) -> Result<()> {
+    let name = args.name.trim();
+    anyhow::ensure!(!name.is_empty(), "Preset name cannot be empty");
+    if name.parse::<Preset>().is_ok() {
+        anyhow::bail!("{name} is the name of a built-in preset; choose another");
+    }
+    if config.presets.contains_key(name) && !args.force {
+        anyhow::bail!("Preset {name} already exists. Use --force to replace it");
+    }
+
+    let params: Option<MasteringParams> = args
+        .from_params
+        .as_ref()
+        .map(|path| -> Result<MasteringParams> {
+            let json = std::fs::read_to_string(path)
+                .with_context(|| format!("Reading parameters from {}", path.display()))?;
+            serde_json::from_str(&json)
+                .with_context(|| format!("Parsing parameters in {}", path.display()))
+        })
+        .transpose()?;
+    let format: Option<AudioFormat> = args.format.as_deref().map(str::parse).transpose()?;
+
+    let preset = CustomPreset {
+        description: args.description,
+        // A fixed chain keeps the loudness it was mastered to unless told otherwise
+        target_lufs: args.target_lufs.or(params.as_ref().map(|p| p.target_lufs)),
+        ceiling_db: args.ceiling,
+        format,
+        bit_depth: args.bit_depth,
+        params,
+    };
+    anyhow::ensure!(
+        preset.target_lufs.is_some()
+            || preset.ceiling_db.is_some()
+            || preset.format.is_some()
+            || preset.bit_depth.is_some(),
+        "A preset needs at least one setting: --target-lufs, --ceiling, --format, --bit-depth or --from-params"
+    );
+
+    let summary = preset.summary();
+    config.presets.insert(name.to_string(), preset);
+    config.save()?;
+    println!("{} Saved preset {name} ({summary})", "OK".bold().green());
+    println!(
+        "  Use it with {}",
+        format!("mastering master <input> --preset {name}").cyan()
+    );
+    Ok(())
+}
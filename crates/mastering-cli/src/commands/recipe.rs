@@ -0,0 +1,84 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+
+use mastering_core::recipes::RecipeStore;
+
+#[derive(Args)]
+pub struct RecipeArgs {
+    #[command(subcommand)]
+    pub command: RecipeCommand,
+}
+
+#[derive(Subcommand)]
+pub enum RecipeCommand {
+    /// List saved recipes
+    List,
+
+    /// Print a recipe's parameters as JSON
+    Show {
+        /// Name of the recipe
+        name: String,
+    },
+
+    /// Delete a saved recipe
+    Delete {
+        /// Name of the recipe
+        name: String,
+    },
+}
+
+pub fn run(args: RecipeArgs) -> Result<()> {
+    let store = RecipeStore::open_default()?;
+
+    match args.command {
+        RecipeCommand::List => list(&store),
+        RecipeCommand::Show { name } => {
+            let recipe = store.load(&name)?;
+            println!("{}", serde_json::to_string_pretty(&recipe)?);
+            Ok(())
+        }
+        RecipeCommand::Delete { name } => {
+            store.remove(&name)?;
+            println!("{} Deleted recipe {name}", "OK".bold().green());
+            Ok(())
+        }
+    }
+}
+
+fn list(store: &RecipeStore) -> Result<()> {
+    let recipes = store.list()?;
+    println!("\n{}", "Recipes".bold().cyan());
+    if recipes.is_empty() {
+        println!(
+            "\n  No recipes saved. Save one with {}",
+            "mastering master <input> --save-recipe <name>".cyan()
+        );
+        println!();
+        return Ok(());
+    }
+
+    for recipe in &recipes {
+        let source = match (&recipe.source_file, &recipe.source_backend) {
+            (Some(file), Some(backend)) => format!("from {file} ({backend})"),
+            (Some(file), None) => format!("from {file}"),
+            (None, Some(backend)) => format!("({backend})"),
+            (None, None) => String::new(),
+        };
+        println!(
+            "\n  {}  {:.1} LUFS  {}",
+            recipe.name.bold().white(),
+            recipe.params.target_lufs,
+            source.dimmed()
+        );
+        if let Some(ref description) = recipe.description {
+            println!("    {description}");
+        }
+    }
+    println!(
+        "\n  Stored in {}",
+        store.dir().display().to_string().dimmed()
+    );
+    println!();
+    Ok(())
+}
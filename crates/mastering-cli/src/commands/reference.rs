@@ -0,0 +1,100 @@
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::references::ReferenceLibrary;
+
+#[derive(Args)]
+pub struct ReferenceArgs {
+    #[command(subcommand)]
+    pub command: ReferenceCommand,
+}
+
+#[derive(Subcommand)]
+pub enum ReferenceCommand {
+    /// Copy a reference track into the library
+    Add {
+        /// Audio file to add
+        file: PathBuf,
+
+        /// Name in the library [default: the file name]
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Genre or mood tag; repeat for several, e.g. --tag techno --tag dark
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+
+    /// List the references in the library
+    List {
+        /// Only references carrying this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+
+    /// Remove a reference from the library
+    Remove {
+        /// Name of the reference
+        name: String,
+    },
+}
+
+pub fn run(args: ReferenceArgs) -> Result<()> {
+    let library = ReferenceLibrary::open_default()?;
+
+    match args.command {
+        ReferenceCommand::Add { file, name, tags } => {
+            let entry = library.add(&file, name.as_deref(), &tags)?;
+            println!(
+                "{} Added reference {} [{}]",
+                "OK".bold().green(),
+                entry.name,
+                entry.tags.join(", ")
+            );
+            Ok(())
+        }
+        ReferenceCommand::List { tag } => list(&library, tag.as_deref()),
+        ReferenceCommand::Remove { name } => {
+            library.remove(&name)?;
+            println!("{} Removed reference {name}", "OK".bold().green());
+            Ok(())
+        }
+    }
+}
+
+fn list(library: &ReferenceLibrary, tag: Option<&str>) -> Result<()> {
+    let entries = match tag {
+        Some(tag) => library.with_tag(tag)?,
+        None => library.list()?,
+    };
+    println!("\n{}", "Reference Library".bold().cyan());
+    if entries.is_empty() {
+        println!(
+            "\n  No references{}. Add one with {}",
+            tag.map(|t| format!(" tagged '{t}'")).unwrap_or_default(),
+            "mastering reference add <file> --tag <genre>".cyan()
+        );
+        println!();
+        return Ok(());
+    }
+
+    for entry in &entries {
+        println!(
+            "  {:<24} {}",
+            entry.name.bold().white(),
+            entry.tags.join(", ").dimmed()
+        );
+    }
+    println!(
+        "\n  Stored in {}",
+        library.dir().display().to_string().dimmed()
+    );
+    println!(
+        "  Master against one with {}",
+        "mastering master <input> --reference-tag <tag>".cyan()
+    );
+    println!();
+    Ok(())
+}
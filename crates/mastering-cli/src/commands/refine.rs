@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::config::Config;
+use mastering_core::jobs;
+use mastering_core::pipeline::{self, CancellationToken, MasteringJob};
+use mastering_core::types::{AiProvider, AudioFormat, Backend, MasteringParams, Refinement};
+
+use super::master::{print_result, save_params, spinner_progress};
+
+#[derive(Args)]
+pub struct RefineArgs {
+    /// The original, unmastered input file
+    pub input: PathBuf,
+
+    /// What to change, e.g. "make it brighter"
+    pub feedback: String,
+
+    /// The previous master to refine
+    #[arg(long)]
+    pub previous: PathBuf,
+
+    /// Parameters of the previous master (from `master --save-params`)
+    #[arg(long)]
+    pub params: PathBuf,
+
+    /// Output file path (default: <previous>_refined.<ext>)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// AI provider: ollama, lmstudio, keyhanstudio, openai, azure-openai, openai-compatible, anthropic, local-gguf
+    #[arg(long)]
+    pub ai_provider: Option<String>,
+
+    /// Target loudness in LUFS (default: the previous target)
+    #[arg(long)]
+    pub target_lufs: Option<f64>,
+
+    /// Ask the AI to explain the adjusted parameters
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Never use the network; only a provider on this machine may refine
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Save the refined parameters as JSON, e.g. for another round
+    #[arg(long)]
+    pub save_params: Option<PathBuf>,
+}
+
+pub async fn run(args: RefineArgs) -> Result<()> {
+    let config = Config::load().context("Loading configuration")?;
+
+    for path in [&args.input, &args.previous, &args.params] {
+        super::ensure_input(path)?;
+    }
+
+    let previous_params: MasteringParams = serde_json::from_str(
+        &std::fs::read_to_string(&args.params)
+            .with_context(|| format!("Reading {}", args.params.display()))?,
+    )
+    .with_context(|| format!("Parsing parameters in {}", args.params.display()))?;
+    let ai_provider: Option<AiProvider> = args.ai_provider.map(|s| s.parse()).transpose()?;
+
+    // Keep the previous master's format unless told otherwise
+    let format: Option<AudioFormat> = args
+        .previous
+        .extension()
+        .and_then(|e| e.to_str())
+        .and_then(|e| e.parse().ok());
+    let output = args.output.unwrap_or_else(|| {
+        let stem = args
+            .previous
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        let ext = format.unwrap_or(config.general.default_format).extension();
+        args.previous
+            .with_file_name(format!("{stem}_refined.{ext}"))
+    });
+
+    let cancel_token = CancellationToken::new();
+    let job = MasteringJob {
+        input_path: args.input.clone(),
+        output_path: Some(output),
+        backend: Backend::Ai,
+        ai_provider,
+        format,
+        target_lufs: args.target_lufs.or(Some(previous_params.target_lufs)),
+        refinement: Some(Refinement {
+            feedback: args.feedback.clone(),
+            previous_params,
+            previous_output: args.previous.clone(),
+        }),
+        explain: args.explain,
+        offline: args.offline,
+        cancel_token: cancel_token.clone(),
+        ..Default::default()
+    };
+
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel_token.cancel();
+        }
+    });
+
+    let quiet = super::quiet();
+    if !quiet {
+        println!(
+            "\n{}  {}  {}",
+            "REFINING".bold().cyan(),
+            args.previous.display().to_string().white(),
+            format!("\"{}\"", args.feedback).italic()
+        );
+    }
+
+    let spinner = indicatif::ProgressBar::new_spinner();
+    spinner.set_style(
+        indicatif::ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.set_message("Processing...");
+    if quiet {
+        spinner.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+    }
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let result = pipeline::run_with_progress(&job, &config, &spinner_progress(&spinner)).await;
+
+    spinner.finish_and_clear();
+    jobs::remember(&job, &result);
+
+    if job.cancel_token.is_cancelled() {
+        if !quiet {
+            println!("{} Refinement cancelled", "!".bold().yellow());
+        }
+        std::process::exit(crate::exit::CANCELLED.into());
+    }
+    let result = result.map_err(|e| crate::exit::in_backend(e, Backend::Ai))?;
+
+    if let Some(ref path) = args.save_params {
+        save_params(path, &result)?;
+        if !quiet {
+            println!("  Parameters saved to {}", path.display());
+        }
+    }
+
+    if !quiet {
+        print_result(&result);
+    }
+    Ok(())
+}
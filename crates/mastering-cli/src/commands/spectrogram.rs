@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::render::{self, Colormap, SpectrogramOptions};
+
+#[derive(Args)]
+pub struct SpectrogramArgs {
+    /// Audio file to draw
+    pub input: PathBuf,
+
+    /// Where to write the image [default: <input>_spectrogram.png]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Image width in pixels
+    #[arg(long, default_value_t = 1200)]
+    pub width: u32,
+
+    /// Image height in pixels
+    #[arg(long, default_value_t = 600)]
+    pub height: u32,
+
+    /// Colormap: magma, viridis, gray
+    #[arg(long, default_value = "magma")]
+    pub colormap: String,
+
+    /// Levels shown below the loudest bin, in dB
+    #[arg(long, value_name = "DB", default_value_t = 90.0)]
+    pub range: f32,
+
+    /// Lowest frequency shown, in Hz
+    #[arg(long, value_name = "HZ", default_value_t = 20.0)]
+    pub min_freq: f64,
+
+    /// FFT size; larger resolves low frequencies better but smears time
+    #[arg(long, default_value_t = 4096)]
+    pub fft_size: usize,
+}
+
+pub async fn run(args: SpectrogramArgs) -> Result<()> {
+    super::ensure_input(&args.input)?;
+    anyhow::ensure!(
+        args.width > 0 && args.height > 0,
+        "Image size must be at least 1x1"
+    );
+    let colormap: Colormap = args.colormap.parse()?;
+    let output = args.output.clone().unwrap_or_else(|| {
+        let stem = args
+            .input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        args.input.with_file_name(format!("{stem}_spectrogram.png"))
+    });
+    let opts = SpectrogramOptions {
+        width: args.width,
+        height: args.height,
+        colormap,
+        min_freq: args.min_freq,
+        range_db: args.range,
+        fft_size: args.fft_size,
+    };
+
+    let spinner = indicatif::ProgressBar::new_spinner();
+    spinner.set_style(
+        indicatif::ProgressStyle::default_spinner()
+            .template("{spinner:.cyan} {msg}")
+            .unwrap(),
+    );
+    spinner.set_message("Rendering spectrogram...");
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let (input, path) = (args.input.clone(), output.clone());
+    tokio::task::spawn_blocking(move || render::spectrogram_png(&input, &path, &opts))
+        .await
+        .context("Spectrogram task failed")?
+        .context("Rendering the spectrogram failed")?;
+
+    spinner.finish_and_clear();
+    println!("{} {}", "Spectrogram:".bold().green(), output.display());
+    Ok(())
+}
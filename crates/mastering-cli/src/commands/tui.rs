@@ -0,0 +1,915 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::symbols;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Cell, LineGauge, List, ListItem, ListState, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+
+use mastering_core::config::Config;
+use mastering_core::pipeline::{
+    self, is_supported_input, BatchJobStatus, BatchStatusUpdate, CancellationToken,
+    ProgressReporter, ProgressUpdate,
+};
+use mastering_core::types::{AudioAnalysis, MasteringResult};
+use mastering_core::{analysis, cache};
+
+/// How often the screen is redrawn when nothing else happens.
+const TICK: Duration = Duration::from_millis(100);
+/// How long a file has to stay highlighted before it is analyzed, so
+/// scrolling past files does not start an analysis for each of them.
+const ANALYSIS_DELAY: Duration = Duration::from_millis(300);
+
+#[derive(Args)]
+pub struct TuiArgs {
+    /// Folder to start browsing in [default: current folder]
+    pub dir: Option<PathBuf>,
+
+    /// Mastering preset for queued files: streaming, cd, vinyl, loud, podcast,
+    /// game, game-console, cinema, trailer, or a custom preset from the config
+    #[arg(short, long)]
+    pub preset: Option<String>,
+
+    /// Mastering backend: auto, matchering, ai, local-ml, basic
+    #[arg(short, long, default_value = "auto")]
+    pub backend: String,
+
+    /// Output format: wav, flac, aiff, alac, mp3, aac (m4a), opus (ogg)
+    #[arg(short, long)]
+    pub format: Option<String>,
+
+    /// Folder to write masters to [default: next to each input]
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+
+    /// Files mastered at once [default: general.batch_concurrency]
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+
+    /// Master every file again, even those whose output is already up to date
+    #[arg(long)]
+    pub force: bool,
+}
+
+pub async fn run(args: TuiArgs) -> Result<()> {
+    anyhow::ensure!(
+        std::io::stdin().is_terminal() && std::io::stdout().is_terminal(),
+        "`mastering tui` needs a terminal"
+    );
+    let config = Config::load().context("Loading configuration")?;
+    let settings = super::JobSettings {
+        preset: args.preset,
+        backend: args.backend.parse()?,
+        format: args.format.map(|s| s.parse()).transpose()?,
+        out: args.out,
+    };
+    // Fail on an unknown preset now rather than for every queued file
+    settings.build_job(Path::new("input.wav"), &config)?;
+    if let Some(ref out) = settings.out {
+        std::fs::create_dir_all(out)
+            .with_context(|| format!("Creating output folder {}", out.display()))?;
+    }
+    let dir = match args.dir {
+        Some(dir) => dir,
+        None => std::env::current_dir().context("Reading the current folder")?,
+    };
+    let dir = dir
+        .canonicalize()
+        .with_context(|| format!("Folder not found: {}", dir.display()))?;
+    let concurrency = args.jobs.unwrap_or(config.general.batch_concurrency);
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut app = App::new(config, settings, concurrency, args.force, dir, tx.clone())?;
+
+    // Terminal input is read on its own thread; a timeout becomes a tick
+    std::thread::spawn(move || loop {
+        let message = match event::poll(TICK) {
+            Ok(true) => match event::read() {
+                Ok(Event::Key(key)) => Message::Key(key),
+                Ok(_) => Message::Tick,
+                Err(_) => break,
+            },
+            Ok(false) => Message::Tick,
+            Err(_) => break,
+        };
+        if tx.send(message).is_err() {
+            break;
+        }
+    });
+
+    let mut terminal = ratatui::try_init().context("Setting up the terminal")?;
+    let result = app.run(&mut terminal, rx).await;
+    ratatui::restore();
+    result?;
+
+    if !super::quiet() {
+        app.print_summary();
+    }
+    Ok(())
+}
+
+/// Everything the dashboard reacts to, from the terminal and from
+/// background tasks.
+enum Message {
+    Key(KeyEvent),
+    Tick,
+    Analyzed(PathBuf, Result<Box<AudioAnalysis>, String>),
+    /// Progress of the queue entry at the given position.
+    Progress(usize, ProgressUpdate),
+    /// Status change of the queue entry at the given position.
+    Status(usize, BatchStatusUpdate),
+    /// Results of a finished batch by queue position.
+    BatchDone(Vec<(usize, Result<MasteringResult>)>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Browser,
+    Queue,
+}
+
+struct BrowserEntry {
+    path: PathBuf,
+    name: String,
+    is_dir: bool,
+}
+
+struct QueueEntry {
+    path: PathBuf,
+    /// `None` until the entry is part of a started batch.
+    status: Option<BatchJobStatus>,
+    percent: f32,
+    stage: String,
+    result: Option<Box<MasteringResult>>,
+    error: Option<String>,
+    cancel: Option<CancellationToken>,
+}
+
+impl QueueEntry {
+    fn is_active(&self) -> bool {
+        matches!(
+            self.status,
+            None | Some(BatchJobStatus::Queued | BatchJobStatus::Running)
+        )
+    }
+
+    /// Whether the entry has a result or an error to show.
+    fn is_finished(&self) -> bool {
+        self.result.is_some() || self.status == Some(BatchJobStatus::Failed)
+    }
+}
+
+enum Meter {
+    Pending,
+    Ready(Box<AudioAnalysis>),
+    Failed(String),
+}
+
+struct App {
+    config: Config,
+    settings: super::JobSettings,
+    concurrency: usize,
+    force: bool,
+    dir: PathBuf,
+    entries: Vec<BrowserEntry>,
+    browser: ListState,
+    queue: Vec<QueueEntry>,
+    selected: usize,
+    focus: Focus,
+    meters: HashMap<PathBuf, Meter>,
+    highlighted_at: Instant,
+    running: bool,
+    quitting: bool,
+    notice: Option<String>,
+    tx: mpsc::UnboundedSender<Message>,
+}
+
+impl App {
+    fn new(
+        config: Config,
+        settings: super::JobSettings,
+        concurrency: usize,
+        force: bool,
+        dir: PathBuf,
+        tx: mpsc::UnboundedSender<Message>,
+    ) -> Result<Self> {
+        let entries = list_dir(&dir).with_context(|| format!("Reading {}", dir.display()))?;
+        Ok(Self {
+            config,
+            settings,
+            concurrency,
+            force,
+            dir,
+            entries,
+            browser: ListState::default().with_selected(Some(0)),
+            queue: Vec::new(),
+            selected: 0,
+            focus: Focus::Browser,
+            meters: HashMap::new(),
+            highlighted_at: Instant::now(),
+            running: false,
+            quitting: false,
+            notice: None,
+            tx,
+        })
+    }
+
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        mut rx: mpsc::UnboundedReceiver<Message>,
+    ) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Some(message) = rx.recv().await else {
+                return Ok(());
+            };
+            self.handle(message);
+            while let Ok(message) = rx.try_recv() {
+                self.handle(message);
+            }
+            if self.quitting && !self.running {
+                return Ok(());
+            }
+        }
+    }
+
+    fn handle(&mut self, message: Message) {
+        match message {
+            Message::Key(key) if key.kind == KeyEventKind::Press => self.handle_key(key),
+            Message::Key(_) => {}
+            Message::Tick => self.analyze_highlighted(),
+            Message::Analyzed(path, result) => {
+                let meter = match result {
+                    Ok(analysis) => Meter::Ready(analysis),
+                    Err(e) => Meter::Failed(e),
+                };
+                self.meters.insert(path, meter);
+            }
+            Message::Progress(index, update) => {
+                let entry = &mut self.queue[index];
+                entry.percent = update.percent;
+                entry.stage = update.stage.to_string().replace('_', " ");
+            }
+            Message::Status(index, update) => {
+                let entry = &mut self.queue[index];
+                entry.status = Some(update.status);
+                if update.error.is_some() {
+                    entry.error = update.error;
+                }
+            }
+            Message::BatchDone(results) => {
+                for (index, result) in results {
+                    let entry = &mut self.queue[index];
+                    entry.cancel = None;
+                    match result {
+                        Ok(result) => {
+                            if entry.status != Some(BatchJobStatus::Skipped) {
+                                entry.status = Some(BatchJobStatus::Succeeded);
+                            }
+                            entry.percent = 100.0;
+                            entry.result = Some(Box::new(result));
+                        }
+                        Err(e) => {
+                            entry.status = Some(BatchJobStatus::Failed);
+                            entry.error.get_or_insert_with(|| format!("{e:#}"));
+                        }
+                    }
+                }
+                self.running = false;
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        self.notice = None;
+        let ctrl_c =
+            key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+        match key.code {
+            _ if ctrl_c => self.quit(),
+            KeyCode::Char('q') | KeyCode::Esc => self.quit(),
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Browser => Focus::Queue,
+                    Focus::Queue => Focus::Browser,
+                };
+                self.highlighted_at = Instant::now();
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::PageDown => self.move_selection(10),
+            KeyCode::Char('s') => self.start_batch(),
+            KeyCode::Char('c') => self.cancel_batch(),
+            KeyCode::Char('a') => {
+                let files: Vec<PathBuf> = self
+                    .entries
+                    .iter()
+                    .filter(|e| !e.is_dir)
+                    .map(|e| e.path.clone())
+                    .collect();
+                for path in files {
+                    self.enqueue(path);
+                }
+            }
+            _ => match self.focus {
+                Focus::Browser => self.handle_browser_key(key.code),
+                Focus::Queue => self.handle_queue_key(key.code),
+            },
+        }
+    }
+
+    fn handle_browser_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => {
+                let Some(entry) = self.browser.selected().and_then(|i| self.entries.get(i)) else {
+                    return;
+                };
+                let path = entry.path.clone();
+                if entry.is_dir {
+                    if code != KeyCode::Char(' ') {
+                        // Coming back up from a sub-folder highlights it
+                        let from = self.dir.clone();
+                        self.open_dir(path, Some(&from));
+                    }
+                } else {
+                    self.enqueue(path);
+                }
+            }
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                if let Some(parent) = self.dir.parent().map(Path::to_path_buf) {
+                    let from = self.dir.clone();
+                    self.open_dir(parent, Some(&from));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_queue_key(&mut self, code: KeyCode) {
+        if !matches!(code, KeyCode::Char('d') | KeyCode::Delete) || self.queue.is_empty() {
+            return;
+        }
+        // Batch updates refer to queue positions, so they must not move
+        if self.running {
+            self.notice = Some("Files can be removed once the batch has finished".into());
+            return;
+        }
+        self.queue.remove(self.selected);
+        self.selected = self.selected.min(self.queue.len().saturating_sub(1));
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (current, len) = match self.focus {
+            Focus::Browser => (self.browser.selected().unwrap_or(0), self.entries.len()),
+            Focus::Queue => (self.selected, self.queue.len()),
+        };
+        if len == 0 {
+            return;
+        }
+        let next = current.saturating_add_signed(delta).min(len - 1);
+        match self.focus {
+            Focus::Browser => self.browser.select(Some(next)),
+            Focus::Queue => self.selected = next,
+        }
+        if next != current {
+            self.highlighted_at = Instant::now();
+        }
+    }
+
+    /// Show `dir`, highlighting `select` if it is one of its entries.
+    fn open_dir(&mut self, dir: PathBuf, select: Option<&Path>) {
+        match list_dir(&dir) {
+            Ok(entries) => {
+                let index = select
+                    .and_then(|path| entries.iter().position(|e| e.path == path))
+                    .unwrap_or(0);
+                self.entries = entries;
+                self.browser.select(Some(index));
+                self.dir = dir;
+                self.highlighted_at = Instant::now();
+            }
+            Err(e) => self.notice = Some(format!("Cannot open {}: {e}", dir.display())),
+        }
+    }
+
+    /// Add `path` to the queue unless it is already waiting or being mastered.
+    fn enqueue(&mut self, path: PathBuf) {
+        if self.queue.iter().any(|e| e.path == path && e.is_active()) {
+            return;
+        }
+        self.queue.push(QueueEntry {
+            path,
+            status: None,
+            percent: 0.0,
+            stage: String::new(),
+            result: None,
+            error: None,
+            cancel: None,
+        });
+    }
+
+    /// Master every file that has not been started yet.
+    fn start_batch(&mut self) {
+        if self.running {
+            self.notice = Some("A batch is already running".into());
+            return;
+        }
+        let mut jobs = Vec::new();
+        let mut positions = Vec::new();
+        for (index, entry) in self.queue.iter_mut().enumerate() {
+            if entry.status.is_some() {
+                continue;
+            }
+            let job = match self.settings.build_job(&entry.path, &self.config) {
+                Ok(job) => job,
+                Err(e) => {
+                    entry.status = Some(BatchJobStatus::Failed);
+                    entry.error = Some(format!("{e:#}"));
+                    continue;
+                }
+            };
+            entry.cancel = Some(job.cancel_token.clone());
+
+            let (progress, mut progress_rx) = ProgressReporter::channel();
+            let tx = self.tx.clone();
+            tokio::spawn(async move {
+                while let Some(update) = progress_rx.recv().await {
+                    if tx.send(Message::Progress(index, update)).is_err() {
+                        break;
+                    }
+                }
+            });
+            jobs.push((job, self.config.clone(), progress));
+            positions.push(index);
+        }
+        if jobs.is_empty() {
+            self.notice = Some("Nothing to master; add files with Enter or a".into());
+            return;
+        }
+        self.running = true;
+
+        let (status_tx, mut status_rx) = mpsc::unbounded_channel::<BatchStatusUpdate>();
+        let tx = self.tx.clone();
+        let batch_positions = positions.clone();
+        tokio::spawn(async move {
+            while let Some(update) = status_rx.recv().await {
+                if tx
+                    .send(Message::Status(batch_positions[update.index], update))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let (concurrency, force, tx) = (self.concurrency, self.force, self.tx.clone());
+        tokio::spawn(async move {
+            let results = pipeline::run_batch(jobs, concurrency, force, Some(status_tx)).await;
+            let _ = tx.send(Message::BatchDone(
+                positions.into_iter().zip(results).collect(),
+            ));
+        });
+    }
+
+    fn cancel_batch(&mut self) {
+        for token in self.queue.iter().filter_map(|e| e.cancel.as_ref()) {
+            token.cancel();
+        }
+    }
+
+    /// Quit, cancelling a running batch first so partial output is removed.
+    fn quit(&mut self) {
+        if self.running {
+            self.notice = Some("Cancelling...".into());
+            self.cancel_batch();
+        }
+        self.quitting = true;
+    }
+
+    fn highlighted(&self) -> Option<&Path> {
+        match self.focus {
+            Focus::Browser => self
+                .browser
+                .selected()
+                .and_then(|i| self.entries.get(i))
+                .filter(|e| !e.is_dir)
+                .map(|e| e.path.as_path()),
+            Focus::Queue => self.queue.get(self.selected).map(|e| e.path.as_path()),
+        }
+    }
+
+    /// Analyze the highlighted file once it has stayed highlighted for a moment.
+    fn analyze_highlighted(&mut self) {
+        if self.highlighted_at.elapsed() < ANALYSIS_DELAY {
+            return;
+        }
+        let Some(path) = self.highlighted().map(Path::to_path_buf) else {
+            return;
+        };
+        if self.meters.contains_key(&path) {
+            return;
+        }
+        self.meters.insert(path.clone(), Meter::Pending);
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let result = analysis::analyze_file_cached(&path, Some(cache::global_cache()))
+                .await
+                .map(Box::new)
+                .map_err(|e| format!("{e:#}"));
+            let _ = tx.send(Message::Analyzed(path, result));
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        let [browser, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Fill(1)]).areas(main);
+        let [meters, queue, results] = Layout::vertical([
+            Constraint::Length(10),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ])
+        .areas(right);
+
+        self.draw_browser(frame, browser);
+        self.draw_meters(frame, meters);
+        self.draw_queue(frame, queue);
+        self.draw_results(frame, results);
+        self.draw_help(frame, help);
+    }
+
+    fn pane(&self, title: String, focus: Option<Focus>) -> Block<'static> {
+        let style = if focus == Some(self.focus) {
+            Style::new().cyan()
+        } else {
+            Style::new().dark_gray()
+        };
+        Block::bordered().title(title).border_style(style)
+    }
+
+    fn draw_browser(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                if entry.is_dir {
+                    ListItem::new(format!("{}/", entry.name)).blue().bold()
+                } else if self
+                    .queue
+                    .iter()
+                    .any(|e| e.path == entry.path && e.is_active())
+                {
+                    ListItem::new(format!("+ {}", entry.name)).green()
+                } else {
+                    ListItem::new(format!("  {}", entry.name))
+                }
+            })
+            .collect();
+        let list = List::new(items)
+            .block(self.pane(format!(" {} ", self.dir.display()), Some(Focus::Browser)))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.browser);
+    }
+
+    fn draw_meters(&self, frame: &mut Frame, area: Rect) {
+        // A finished file shows the master's levels
+        let finished = match self.focus {
+            Focus::Queue => self
+                .queue
+                .get(self.selected)
+                .and_then(|e| e.result.as_ref())
+                .and_then(|r| r.post_analysis.as_ref()),
+            Focus::Browser => None,
+        };
+        let path = self.highlighted();
+        let name = path.map(display_name).unwrap_or_default();
+        let title = match finished {
+            Some(_) => format!(" Levels: {name} (master) "),
+            None if path.is_some() => format!(" Levels: {name} "),
+            None => " Levels ".to_string(),
+        };
+        let block = self.pane(title, None);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let analysis = match (finished, path.and_then(|p| self.meters.get(p))) {
+            (Some(analysis), _) => analysis,
+            (None, Some(Meter::Ready(analysis))) => analysis.as_ref(),
+            (None, Some(Meter::Pending)) => {
+                frame.render_widget(Paragraph::new("Analyzing...").dark_gray(), inner);
+                return;
+            }
+            (None, Some(Meter::Failed(e))) => {
+                frame.render_widget(Paragraph::new(e.as_str()).red(), inner);
+                return;
+            }
+            (None, None) => {
+                let hint = if path.is_some() {
+                    ""
+                } else {
+                    "Highlight a file to see its levels"
+                };
+                frame.render_widget(Paragraph::new(hint).dark_gray(), inner);
+                return;
+            }
+        };
+
+        let meta = &analysis.metadata;
+        let mut rows = vec![(
+            "",
+            0.0,
+            format!(
+                "{}  {} Hz  {} ch  {}",
+                format_duration(meta.duration_secs),
+                meta.sample_rate,
+                meta.channels,
+                meta.format
+            ),
+            Color::Reset,
+        )];
+        rows.extend(meters(analysis));
+        let areas = Layout::vertical(vec![Constraint::Length(1); rows.len()]).split(inner);
+        for ((label, ratio, value, color), area) in rows.into_iter().zip(areas.iter()) {
+            if label.is_empty() {
+                frame.render_widget(Paragraph::new(value).dark_gray(), *area);
+                continue;
+            }
+            let gauge = LineGauge::default()
+                .ratio(ratio.clamp(0.0, 1.0))
+                .label(format!("{label:<14}{value:>12} "))
+                .line_set(symbols::line::THICK)
+                .filled_style(Style::new().fg(color))
+                .unfilled_style(Style::new().dark_gray());
+            frame.render_widget(gauge, *area);
+        }
+    }
+
+    fn draw_queue(&self, frame: &mut Frame, area: Rect) {
+        let done = self.queue.iter().filter(|e| !e.is_active()).count();
+        let title = format!(" Queue ({done}/{} done) ", self.queue.len());
+        let block = self.pane(title, Some(Focus::Queue));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        if self.queue.is_empty() {
+            let hint = "Add files with Enter, or a for the whole folder; start with s";
+            frame.render_widget(Paragraph::new(hint).dark_gray(), inner);
+            return;
+        }
+
+        let visible = usize::from(inner.height).max(1);
+        let offset = self.selected.saturating_sub(visible - 1);
+        let areas = Layout::vertical(vec![Constraint::Length(1); visible]).split(inner);
+        for (index, area) in (offset..self.queue.len()).zip(areas.iter()) {
+            let entry = &self.queue[index];
+            let (status, color) = match entry.status {
+                None => ("waiting".to_string(), Color::DarkGray),
+                Some(BatchJobStatus::Queued) => ("queued".to_string(), Color::DarkGray),
+                Some(BatchJobStatus::Running) => (entry.stage.clone(), Color::Cyan),
+                Some(BatchJobStatus::Succeeded) => ("done".to_string(), Color::Green),
+                Some(BatchJobStatus::Failed) => ("failed".to_string(), Color::Red),
+                Some(BatchJobStatus::Skipped) => ("up to date".to_string(), Color::Green),
+            };
+            let marker = if index == self.selected && self.focus == Focus::Queue {
+                ">"
+            } else {
+                " "
+            };
+            let name = truncate(&display_name(&entry.path), 28);
+            let gauge = LineGauge::default()
+                .ratio(f64::from(entry.percent / 100.0).clamp(0.0, 1.0))
+                .label(format!("{marker} {name:<28} {status:<14}"))
+                .line_set(symbols::line::THICK)
+                .filled_style(Style::new().fg(color))
+                .unfilled_style(Style::new().dark_gray());
+            frame.render_widget(gauge, *area);
+        }
+    }
+
+    fn draw_results(&self, frame: &mut Frame, area: Rect) {
+        let rows: Vec<Row> = self
+            .queue
+            .iter()
+            .filter(|e| e.is_finished())
+            .map(|entry| {
+                let name = Cell::from(display_name(&entry.path));
+                match (&entry.result, &entry.error) {
+                    (Some(result), _) => {
+                        let loudness = match (&result.pre_analysis, &result.post_analysis) {
+                            (Some(pre), Some(post)) => format!(
+                                "{:.1} → {:.1} LUFS  {:.1} dBTP",
+                                pre.lufs_integrated, post.lufs_integrated, post.true_peak_db
+                            ),
+                            _ => String::new(),
+                        };
+                        Row::new(vec![
+                            name,
+                            Cell::from(display_name(&result.output_path)).green(),
+                            Cell::from(loudness),
+                        ])
+                    }
+                    (None, error) => Row::new(vec![
+                        name,
+                        Cell::from(error.clone().unwrap_or_else(|| "Failed".into())).red(),
+                        Cell::from(""),
+                    ]),
+                }
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(30),
+                Constraint::Percentage(35),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(["File", "Output", "Loudness"]).bold())
+        .block(self.pane(" Results ".to_string(), None));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_help(&self, frame: &mut Frame, area: Rect) {
+        let line = match self.notice {
+            Some(ref notice) => Line::from(Span::from(notice.as_str()).yellow()),
+            None => {
+                let keys = [
+                    ("Tab", "switch pane"),
+                    ("Enter", "open/add"),
+                    ("a", "add folder"),
+                    ("s", "start"),
+                    ("c", "cancel"),
+                    ("d", "remove"),
+                    ("q", "quit"),
+                ];
+                let spans: Vec<Span> = keys
+                    .iter()
+                    .flat_map(|(key, action)| {
+                        [
+                            Span::from(format!(" {key} ")).bold().cyan(),
+                            Span::from(format!("{action} ")),
+                        ]
+                    })
+                    .collect();
+                Line::from(spans)
+            }
+        };
+        frame.render_widget(Paragraph::new(line), area);
+    }
+
+    /// Print what was mastered once the dashboard is closed.
+    fn print_summary(&self) {
+        for entry in self.queue.iter().filter(|e| e.is_finished()) {
+            match (&entry.result, &entry.error) {
+                (Some(result), _) => summary::mastered(&entry.path, &result.output_path),
+                (None, error) => {
+                    summary::failed(&entry.path, error.as_deref().unwrap_or("unknown error"))
+                }
+            }
+        }
+    }
+}
+
+/// Plain terminal output, kept apart because `colored` and ratatui's
+/// `Stylize` share method names.
+mod summary {
+    use colored::Colorize;
+    use std::path::Path;
+
+    pub(super) fn mastered(input: &Path, output: &Path) {
+        println!(
+            "{} {} -> {}",
+            "Mastered:".bold().green(),
+            input.display(),
+            output.display()
+        );
+    }
+
+    pub(super) fn failed(input: &Path, error: &str) {
+        println!("{} {}: {error}", "Failed:".bold().red(), input.display());
+    }
+}
+
+/// Meter label, position from 0 to 1, value and color for each level shown.
+fn meters(analysis: &AudioAnalysis) -> Vec<(&'static str, f64, String, Color)> {
+    let scale = |value: f64, min: f64, max: f64| (value - min) / (max - min);
+    let peak_color = match analysis.true_peak_db {
+        p if p > -1.0 => Color::Red,
+        p if p > -2.0 => Color::Yellow,
+        _ => Color::Green,
+    };
+    let correlation_color = if analysis.phase_correlation < 0.0 {
+        Color::Red
+    } else {
+        Color::Green
+    };
+    vec![
+        (
+            "Loudness",
+            scale(analysis.lufs_integrated, -40.0, 0.0),
+            format!("{:.1} LUFS", analysis.lufs_integrated),
+            Color::Cyan,
+        ),
+        (
+            "Short-term max",
+            scale(analysis.lufs_short_term_max, -40.0, 0.0),
+            format!("{:.1} LUFS", analysis.lufs_short_term_max),
+            Color::Cyan,
+        ),
+        (
+            "True peak",
+            scale(analysis.true_peak_db, -24.0, 3.0),
+            format!("{:.1} dBTP", analysis.true_peak_db),
+            peak_color,
+        ),
+        (
+            "Loudness range",
+            scale(analysis.loudness_range_lu, 0.0, 20.0),
+            format!("{:.1} LU", analysis.loudness_range_lu),
+            Color::Magenta,
+        ),
+        (
+            "Dynamic range",
+            scale(analysis.dynamic_range_db, 0.0, 20.0),
+            format!("{:.1} dB", analysis.dynamic_range_db),
+            Color::Magenta,
+        ),
+        (
+            "Stereo width",
+            analysis.stereo_width,
+            format!("{:.0}%", analysis.stereo_width * 100.0),
+            Color::Blue,
+        ),
+        (
+            "Correlation",
+            scale(analysis.phase_correlation, -1.0, 1.0),
+            format!("{:+.2}", analysis.phase_correlation),
+            correlation_color,
+        ),
+    ]
+}
+
+/// Sub-folders and supported audio files in `dir`, sorted by name, after
+/// an entry for the parent folder.
+fn list_dir(dir: &Path) -> std::io::Result<Vec<BrowserEntry>> {
+    let mut dirs = Vec::new();
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = display_name(&path);
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            dirs.push(BrowserEntry {
+                path,
+                name,
+                is_dir: true,
+            });
+        } else if is_supported_input(&path) {
+            files.push(BrowserEntry {
+                path,
+                name,
+                is_dir: false,
+            });
+        }
+    }
+    dirs.sort_by_key(|e| e.name.to_lowercase());
+    files.sort_by_key(|e| e.name.to_lowercase());
+
+    let parent = dir.parent().map(|parent| BrowserEntry {
+        path: parent.to_path_buf(),
+        name: "..".to_string(),
+        is_dir: true,
+    });
+    Ok(parent.into_iter().chain(dirs).chain(files).collect())
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    let mut short: String = s.chars().take(max.saturating_sub(1)).collect();
+    short.push('…');
+    short
+}
+
+fn format_duration(secs: f64) -> String {
+    let secs = secs.max(0.0).round() as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::analysis::compliance::{self, Spec};
+use mastering_core::{analysis, cache};
+
+#[derive(Args)]
+pub struct ValidateArgs {
+    /// Audio file to check
+    pub input: PathBuf,
+
+    /// Delivery spec: ebu-r128, ebu-r128-s1, atsc-a85, netflix
+    #[arg(long, default_value = "ebu-r128")]
+    pub spec: String,
+
+    /// Also fail if momentary loudness exceeds this, in LUFS
+    #[arg(long, value_name = "LUFS", allow_hyphen_values = true)]
+    pub max_momentary: Option<f64>,
+
+    /// Also fail if short-term loudness exceeds this, in LUFS
+    #[arg(long, value_name = "LUFS", allow_hyphen_values = true)]
+    pub max_short_term: Option<f64>,
+
+    /// Output the report as JSON
+    #[arg(long)]
+    pub json: bool,
+
+    /// Ignore cached analysis results and re-analyze
+    #[arg(long)]
+    pub no_cache: bool,
+}
+
+/// Exits with [`exit::VALIDATION`](crate::exit::VALIDATION) when the file
+/// fails any check.
+pub async fn run(args: ValidateArgs) -> Result<()> {
+    super::ensure_input(&args.input)?;
+    let spec: Spec = args.spec.parse()?;
+    let mut limits = spec.limits();
+    if let Some(max) = args.max_momentary {
+        limits.max_momentary_lufs = Some(max);
+    }
+    if let Some(max) = args.max_short_term {
+        limits.max_short_term_lufs = Some(max);
+    }
+
+    let cache = (!args.no_cache).then(cache::global_cache);
+    let analysis = analysis::analyze_file_cached(&args.input, cache)
+        .await
+        .context("Audio analysis failed")?;
+    let report = compliance::check(spec, limits, &analysis);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else if !super::quiet() {
+        println!(
+            "\n{}  {} against {}",
+            "VALIDATE".bold().cyan(),
+            args.input.display().to_string().white(),
+            spec.to_string().bold()
+        );
+        println!(
+            "\n{}",
+            format!("  {:<16} {:>10} {:>16}", "Check", "Measured", "Allowed")
+                .bold()
+                .yellow()
+        );
+        for c in &report.checks {
+            let status = if c.passed {
+                "PASS".green()
+            } else {
+                "FAIL".red()
+            };
+            println!(
+                "  {:<16} {:>10} {:>16}  {}",
+                c.name,
+                format!("{:.1} {}", c.value, c.unit),
+                c.limit,
+                status.bold()
+            );
+        }
+        if report.passed {
+            println!("\n{} meets {spec}", "PASSED:".bold().green());
+        } else {
+            let failed = report.checks.iter().filter(|c| !c.passed).count();
+            println!("\n{} {failed} check(s) failed", "FAILED:".bold().red());
+        }
+        println!();
+    }
+
+    if !report.passed {
+        std::process::exit(crate::exit::VALIDATION.into());
+    }
+    Ok(())
+}
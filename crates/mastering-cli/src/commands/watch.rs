@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use mastering_core::config::Config;
+use mastering_core::pipeline::{self, CancellationToken, WatchEvent, WatchFolder};
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// Folder to watch for new audio files
+    pub dir: PathBuf,
+
+    /// Folder to write masters to [default: <dir>/mastered]
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+
+    /// Mastering preset: streaming, cd, vinyl, loud, podcast, game, game-console, cinema,
+    /// trailer, or a custom preset from the config
+    #[arg(short, long)]
+    pub preset: Option<String>,
+
+    /// Mastering backend: auto, matchering, ai, local-ml, basic
+    #[arg(short, long, default_value = "auto")]
+    pub backend: String,
+
+    /// Output format: wav, flac, aiff, alac, mp3, aac (m4a), opus (ogg)
+    #[arg(short, long)]
+    pub format: Option<String>,
+
+    /// Also watch sub-folders; masters go to matching sub-folders of the output folder
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Also master the audio files already in the folder
+    #[arg(long)]
+    pub existing: bool,
+
+    /// Seconds a new file must stop growing before it is mastered
+    #[arg(long, value_name = "SECS", default_value_t = 2.0)]
+    pub settle: f64,
+}
+
+pub async fn run(args: WatchArgs) -> Result<()> {
+    super::ensure_input(&args.dir)?;
+    let config = Config::load().context("Loading configuration")?;
+    let settings = super::JobSettings {
+        preset: args.preset,
+        backend: args.backend.parse()?,
+        format: args.format.map(|s| s.parse()).transpose()?,
+        out: None,
+    };
+    // Fail on an unknown preset now rather than for every new file
+    settings.build_job(Path::new("input.wav"), &config)?;
+    anyhow::ensure!(
+        args.settle >= 0.0,
+        "--settle cannot be negative (got {})",
+        args.settle
+    );
+    let folder = WatchFolder {
+        output_dir: args.out.unwrap_or_else(|| args.dir.join("mastered")),
+        input_dir: args.dir,
+        recursive: args.recursive,
+        existing: args.existing,
+        settle_secs: args.settle,
+    };
+
+    // Ctrl-C stops watching and cancels the file being mastered
+    let cancel = CancellationToken::new();
+    tokio::spawn({
+        let cancel = cancel.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                cancel.cancel();
+            }
+        }
+    });
+
+    let quiet = super::quiet();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let printer = tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            if !quiet {
+                print_event(&event);
+            }
+        }
+    });
+
+    if !quiet {
+        println!(
+            "\n{}  {} -> {}",
+            "WATCHING".bold().cyan(),
+            folder.input_dir.display().to_string().white(),
+            folder.output_dir.display()
+        );
+        println!("  {}", "Press Ctrl-C to stop".dimmed());
+    }
+    let started = Instant::now();
+    let build_job = |path: &Path| settings.build_job(path, &config);
+    let summary = pipeline::watch_folder(&folder, &config, build_job, Some(tx), cancel).await?;
+    printer.await.context("Watch output task failed")?;
+
+    if !quiet {
+        let failed = match summary.failed {
+            0 => "0 failed".normal(),
+            n => format!("{n} failed").red(),
+        };
+        println!(
+            "\n{} watched for {}: {} mastered, {failed}",
+            "Stopped:".bold().blue(),
+            format_elapsed(started.elapsed().as_secs()),
+            summary.mastered
+        );
+    }
+    Ok(())
+}
+
+fn print_event(event: &WatchEvent) {
+    match event {
+        // Printed once the file has settled and mastering starts
+        WatchEvent::Detected { .. } => {}
+        WatchEvent::Started { input_path } => {
+            println!("  {} {}", "Mastering".cyan(), input_path.display());
+        }
+        WatchEvent::Mastered { result, .. } => {
+            let levels = result
+                .post_analysis
+                .as_ref()
+                .map(|post| {
+                    format!(
+                        " ({:.1} LUFS, {:.1} dBTP)",
+                        post.lufs_integrated, post.true_peak_db
+                    )
+                })
+                .unwrap_or_default();
+            println!(
+                "  {} {}{levels} in {:.1}s",
+                "Mastered:".bold().green(),
+                result.output_path.display(),
+                result.timings.total_secs
+            );
+        }
+        WatchEvent::Failed { input_path, error } => {
+            println!(
+                "  {} {}: {error}",
+                "Failed:".bold().red(),
+                input_path.display()
+            );
+        }
+    }
+}
+
+fn format_elapsed(secs: u64) -> String {
+    match (secs / 3600, secs % 3600 / 60, secs % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s:02}s"),
+        (h, m, _) => format!("{h}h {m:02}m"),
+    }
+}
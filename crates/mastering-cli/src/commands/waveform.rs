@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use std::path::PathBuf;
+
+use mastering_core::peaks;
+use mastering_core::render::{self, WaveformImageOptions};
+
+#[derive(Args)]
+pub struct WaveformArgs {
+    /// Audio file to draw, e.g. the unmastered input
+    pub input: PathBuf,
+
+    /// Second file drawn below (or over) the first, e.g. the master
+    pub master: Option<PathBuf>,
+
+    /// Where to write the image [default: <input>_waveform.png]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Image width in pixels
+    #[arg(long, default_value_t = 1600)]
+    pub width: u32,
+
+    /// Image height in pixels
+    #[arg(long, default_value_t = 400)]
+    pub height: u32,
+
+    /// Draw both waveforms on top of each other instead of stacked
+    #[arg(long, requires = "master")]
+    pub overlay: bool,
+
+    /// Ignore cached peak files and read the audio again
+    #[arg(long)]
+    pub no_cache: bool,
+}
+
+pub async fn run(args: WaveformArgs) -> Result<()> {
+    for path in std::iter::once(&args.input).chain(&args.master) {
+        super::ensure_input(path)?;
+    }
+    anyhow::ensure!(
+        args.width > 0 && args.height > 0,
+        "Image size must be at least 1x1"
+    );
+    let output = args.output.clone().unwrap_or_else(|| {
+        let stem = args
+            .input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        args.input.with_file_name(format!("{stem}_waveform.png"))
+    });
+    let opts = WaveformImageOptions {
+        width: args.width,
+        height: args.height,
+        overlay: args.overlay,
+    };
+
+    let (inputs, path, no_cache) = (
+        std::iter::once(args.input.clone())
+            .chain(args.master.clone())
+            .collect::<Vec<_>>(),
+        output.clone(),
+        args.no_cache,
+    );
+    tokio::task::spawn_blocking(move || {
+        let inputs: Vec<_> = inputs.iter().map(PathBuf::as_path).collect();
+        let cache = (!no_cache).then(peaks::global_peak_cache);
+        render::waveform_png(&inputs, &path, &opts, cache)
+    })
+    .await
+    .context("Waveform task failed")?
+    .context("Rendering the waveform failed")?;
+
+    println!("{} {}", "Waveform:".bold().green(), output.display());
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! Exit codes, so scripts and build systems can branch on why a command
+//! failed. Usage errors keep clap's code 2.
+
+use mastering_core::error::MasteringError;
+use mastering_core::pipeline::BackendFailed;
+use mastering_core::types::Backend;
+use std::path::PathBuf;
+
+/// Any failure without a more specific code.
+pub const FAILURE: u8 = 1;
+/// An input file is missing or cannot be read as audio.
+pub const INPUT: u8 = 3;
+/// A mastering backend is unavailable or failed: Python, a model or a
+/// bridge is missing, hung or crashed.
+pub const BACKEND: u8 = 4;
+/// The AI provider failed: unreachable, rate-limited or gave no usable answer.
+pub const AI: u8 = 5;
+/// A file did not meet its spec (`validate`), or `--strict` found the
+/// output over its peak ceiling.
+pub const VALIDATION: u8 = 6;
+/// The job was cancelled, e.g. with Ctrl-C.
+pub const CANCELLED: u8 = 130;
+
+/// An input file that does not exist.
+#[derive(Debug, thiserror::Error)]
+#[error("Input file not found: {}", .0.display())]
+pub struct InputMissing(pub PathBuf);
+
+/// An error that ends the program with a given exit code.
+#[derive(Debug, thiserror::Error)]
+#[error("{error:#}")]
+pub struct Exit {
+    pub code: u8,
+    pub error: anyhow::Error,
+}
+
+/// Exit code for `err`, from the first error in its chain that says
+/// what went wrong.
+pub fn code_for(err: &anyhow::Error) -> u8 {
+    if let Some(exit) = err.downcast_ref::<Exit>() {
+        return exit.code;
+    }
+    for cause in err.chain() {
+        if cause.is::<InputMissing>() {
+            return INPUT;
+        }
+        if let Some(e) = cause.downcast_ref::<MasteringError>() {
+            match e {
+                MasteringError::Cancelled => return CANCELLED,
+                MasteringError::FileIo {
+                    path: Some(path), ..
+                } if !path.exists() => return INPUT,
+                MasteringError::AudioDecodeFailed { .. } => return INPUT,
+                MasteringError::ValidationError {
+                    field: Some(field), ..
+                } if field == "input_path" => return INPUT,
+                MasteringError::PythonUnavailable { .. }
+                | MasteringError::ProcessTimeout { .. }
+                | MasteringError::BackendError { .. } => return BACKEND,
+                MasteringError::NetworkTimeout { .. } | MasteringError::ApiQuotaExceeded { .. } => {
+                    return AI
+                }
+                MasteringError::ProcessingError { stage, .. } if stage == "safety_check" => {
+                    return VALIDATION
+                }
+                _ => {}
+            }
+        }
+    }
+    FAILURE
+}
+
+/// Attribute a failed mastering job to `backend` when it failed inside the
+/// backend and nothing more specific is known.
+pub fn in_backend(err: anyhow::Error, backend: Backend) -> anyhow::Error {
+    let failed_in_backend = err.downcast_ref::<BackendFailed>().is_some();
+    if code_for(&err) != FAILURE || !failed_in_backend {
+        return err;
+    }
+    let code = if backend == Backend::Ai { AI } else { BACKEND };
+    Exit { code, error: err }.into()
+}
@@ -1,13 +1,18 @@
 mod commands;
+mod exit;
+mod stdio;
 
 use clap::{Parser, Subcommand};
+use std::process::ExitCode;
 
 #[derive(Parser)]
 #[command(
     name = "mastering",
     about = "AI-powered music mastering CLI",
     version,
-    author = "KeyhanStudio"
+    author = "KeyhanStudio",
+    after_help = "Exit codes: 0 success, 1 other failure, 2 usage error, 3 input missing or unreadable, \
+                  4 backend unavailable or failed, 5 AI provider failed, 6 validation failed, 130 cancelled"
 )]
 struct Cli {
     #[command(subcommand)]
@@ -16,41 +21,136 @@ struct Cli {
     /// Enable verbose logging
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Print only errors and requested output such as --json; check the exit code
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Master an audio track
-    Master(commands::master::MasterArgs),
+    Master(Box<commands::master::MasterArgs>),
+
+    /// Re-master a track from feedback on a previous master ("make it brighter")
+    Refine(commands::refine::RefineArgs),
 
     /// Analyze an audio file (loudness, spectrum, dynamics)
     Analyze(commands::analyze::AnalyzeArgs),
 
+    /// Compare the analysis of two audio files side by side
+    Compare(commands::compare::CompareArgs),
+
+    /// Predict how streaming platforms will normalize a master's loudness
+    Platforms(commands::platforms::PlatformsArgs),
+
+    /// Check a file against a broadcast loudness spec; exits non-zero if it fails
+    Validate(commands::validate::ValidateArgs),
+
+    /// Null-test two files: align them, subtract one from the other and report the residual
+    Diff(commands::diff::DiffArgs),
+
+    /// Render a log-frequency spectrogram of a file to a PNG image
+    Spectrogram(commands::spectrogram::SpectrogramArgs),
+
+    /// Render the waveform of a file, or of an input and its master, to a PNG image
+    Waveform(commands::waveform::WaveformArgs),
+
+    /// Render an original and its master at the same loudness for a fair A/B
+    Ab(commands::ab::AbArgs),
+
+    /// Create, list, show or delete custom presets
+    Preset(commands::preset::PresetArgs),
+
+    /// List or show target tonal-balance curves
+    Curve(commands::curve::CurveArgs),
+
+    /// List, show or delete saved recipes
+    Recipe(commands::recipe::RecipeArgs),
+
+    /// Manage the library of tagged reference tracks
+    Reference(commands::reference::ReferenceArgs),
+
     /// Show or initialize configuration
     Config(commands::config::ConfigArgs),
 
     /// List available backends and check their status
     Backends,
+
+    /// Queue jobs, run the queue, and list everything mastered so far
+    Jobs(commands::jobs::JobsArgs),
+
+    /// Master every audio file that appears in a folder
+    Watch(commands::watch::WatchArgs),
+
+    /// Browse, analyze and master files in a terminal dashboard
+    Tui(commands::tui::TuiArgs),
+
+    /// Print a shell completion script: bash, zsh, fish, powershell, elvish
+    Completions(commands::completions::CompletionsArgs),
+
+    /// Manage local ML models
+    Models(commands::models::ModelsArgs),
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
+    commands::set_quiet(cli.quiet);
 
-    let log_level = if cli.verbose { "debug" } else { "info" };
+    let log_level = match (cli.verbose, cli.quiet) {
+        (true, _) => "debug",
+        (_, true) => "error",
+        _ => "info",
+    };
+    // Log lines would be drawn over the dashboard
+    let filter = if matches!(cli.command, Commands::Tui(_)) {
+        tracing_subscriber::EnvFilter::new("off")
+    } else {
+        tracing_subscriber::EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(log_level))
+    };
     tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(log_level)),
-        )
+        .with_env_filter(filter)
         .with_target(false)
         .without_time()
+        // Logs stay off stdout so --json output can be piped
+        .with_writer(std::io::stderr)
         .init();
 
-    match cli.command {
-        Commands::Master(args) => commands::master::run(args).await,
+    let result = match cli.command {
+        Commands::Master(args) => commands::master::run(*args).await,
+        Commands::Refine(args) => commands::refine::run(args).await,
         Commands::Analyze(args) => commands::analyze::run(args).await,
+        Commands::Compare(args) => commands::compare::run(args).await,
+        Commands::Platforms(args) => commands::platforms::run(args).await,
+        Commands::Validate(args) => commands::validate::run(args).await,
+        Commands::Diff(args) => commands::diff::run(args).await,
+        Commands::Spectrogram(args) => commands::spectrogram::run(args).await,
+        Commands::Waveform(args) => commands::waveform::run(args).await,
+        Commands::Ab(args) => commands::ab::run(args).await,
+        Commands::Preset(args) => commands::preset::run(args),
+        Commands::Curve(args) => commands::curve::run(args),
+        Commands::Recipe(args) => commands::recipe::run(args),
+        Commands::Reference(args) => commands::reference::run(args),
         Commands::Config(args) => commands::config::run(args),
         Commands::Backends => commands::backends::run().await,
+        Commands::Jobs(args) => commands::jobs::run(args).await,
+        Commands::Watch(args) => commands::watch::run(args).await,
+        Commands::Tui(args) => commands::tui::run(args).await,
+        Commands::Completions(args) => commands::completions::run(args),
+        Commands::Models(args) => commands::models::run(args).await,
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let code = exit::code_for(&e);
+            match e.downcast::<exit::Exit>() {
+                Ok(exit) => eprintln!("Error: {:?}", exit.error),
+                Err(e) => eprintln!("Error: {e:?}"),
+            }
+            ExitCode::from(code)
+        }
     }
 }
@@ -0,0 +1,98 @@
+//! `-` as the input or output path: audio read from stdin and masters
+//! written to stdout, so `mastering master - -o -` can sit in a pipeline.
+//! The pipeline works on files, so both ends pass through a temporary
+//! folder that is removed afterwards.
+
+use anyhow::{Context, Result};
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+use mastering_core::types::AudioFormat;
+
+/// Whether `path` is `-`, standing for stdin or stdout.
+pub fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Temporary folder for audio read from stdin or bound for stdout;
+/// removed when dropped.
+pub struct Spool {
+    dir: tempfile::TempDir,
+}
+
+impl Spool {
+    pub fn new() -> Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix("mastering-")
+            .tempdir()
+            .context("Creating a temporary folder for piped audio")?;
+        Ok(Self { dir })
+    }
+
+    /// Copy stdin, which must hold a WAV or FLAC file, into the spool.
+    pub fn read_stdin(&self) -> Result<PathBuf> {
+        let stdin = std::io::stdin();
+        anyhow::ensure!(
+            !stdin.is_terminal(),
+            "Pipe WAV or FLAC audio into stdin to use - as the input"
+        );
+        let mut stdin = stdin.lock();
+        let mut header = Vec::with_capacity(12);
+        (&mut stdin)
+            .take(12)
+            .read_to_end(&mut header)
+            .context("Reading audio from stdin")?;
+        let Some(ext) = sniff(&header) else {
+            return Err(crate::exit::Exit {
+                code: crate::exit::INPUT,
+                error: anyhow::anyhow!("Audio on stdin must be a WAV or FLAC file"),
+            }
+            .into());
+        };
+
+        let path = self.dir.path().join(format!("stdin.{ext}"));
+        let mut file =
+            std::fs::File::create(&path).with_context(|| format!("Creating {}", path.display()))?;
+        file.write_all(&header)
+            .and_then(|()| std::io::copy(&mut stdin, &mut file).map(|_| ()))
+            .context("Reading audio from stdin")?;
+        Ok(path)
+    }
+
+    /// Where to render a master in `format` that is then sent to stdout.
+    pub fn output_path(&self, format: AudioFormat) -> PathBuf {
+        self.dir
+            .path()
+            .join(format!("stdout.{}", format.extension()))
+    }
+}
+
+/// Fail unless stdout is redirected, so audio is not dumped on a terminal.
+pub fn ensure_stdout_redirected() -> Result<()> {
+    anyhow::ensure!(
+        !std::io::stdout().is_terminal(),
+        "Refusing to write audio to a terminal; redirect or pipe stdout to use -o -"
+    );
+    Ok(())
+}
+
+/// Copy the file at `path` to stdout.
+pub fn write_stdout(path: &Path) -> Result<()> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+    let mut stdout = std::io::stdout().lock();
+    std::io::copy(&mut file, &mut stdout)
+        .and_then(|_| stdout.flush())
+        .context("Writing the master to stdout")
+}
+
+/// File extension for the container that `header` starts.
+fn sniff(header: &[u8]) -> Option<&'static str> {
+    match header {
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("wav"),
+        // RF64 is WAV for files over 4 GB
+        [b'R', b'F', b'6', b'4', _, _, _, _, b'W', b'A', b'V', b'E', ..] => Some("wav"),
+        [b'f', b'L', b'a', b'C', ..] => Some("flac"),
+        _ => None,
+    }
+}
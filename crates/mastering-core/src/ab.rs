@@ -0,0 +1,156 @@
+//! Loudness-matched A/B export.
+//!
+//! A louder version of a track tends to sound better, so comparing an input
+//! with its master by ear mostly compares their levels. This renders both at
+//! the same integrated loudness, as 32-bit float WAV so raising one cannot
+//! clip it, and compares the analysis of the two renders.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::analysis::{
+    self, compare::AnalysisComparison, decode::decode_audio, loudness::SILENCE_LUFS,
+};
+use crate::cache::AnalysisCache;
+use crate::dsp::dynamics;
+use crate::encode::{self, EncodeOptions};
+use crate::types::SampleFormat;
+
+/// Which file keeps its loudness; the other is brought to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchTo {
+    /// The original is rendered at the master's loudness.
+    #[default]
+    Master,
+    /// The master is rendered at the original's loudness.
+    Original,
+}
+
+impl std::fmt::Display for MatchTo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchTo::Master => write!(f, "master"),
+            MatchTo::Original => write!(f, "original"),
+        }
+    }
+}
+
+impl std::str::FromStr for MatchTo {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "master" => Ok(MatchTo::Master),
+            "original" | "input" => Ok(MatchTo::Original),
+            _ => anyhow::bail!("Unknown loudness match: {s}. Available: master, original"),
+        }
+    }
+}
+
+/// Files written by [`export`] and how they were matched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbExport {
+    /// The original at the matched loudness.
+    pub original_path: PathBuf,
+    /// The master at the matched loudness.
+    pub master_path: PathBuf,
+    /// This report as JSON.
+    pub report_path: PathBuf,
+    pub matched_to: MatchTo,
+    /// Integrated loudness both renders share, in LUFS.
+    pub lufs_integrated: f64,
+    /// Gain applied to the original, in dB.
+    pub original_gain_db: f64,
+    /// Gain applied to the master, in dB.
+    pub master_gain_db: f64,
+    /// Analysis of both renders; `a` is the original, `b` the master.
+    pub comparison: AnalysisComparison,
+}
+
+/// Render `original` and `master` at the same loudness into `output_dir`.
+///
+/// The files are named after the original: `<stem>_ab_original.wav`,
+/// `<stem>_ab_master.wav` and the report `<stem>_ab.json`.
+pub async fn export(
+    original: &Path,
+    master: &Path,
+    output_dir: &Path,
+    matched_to: MatchTo,
+    cache: Option<&AnalysisCache>,
+) -> Result<AbExport> {
+    let original_lufs = analysis::analyze_file_cached(original, cache)
+        .await?
+        .lufs_integrated;
+    let master_lufs = analysis::analyze_file_cached(master, cache)
+        .await?
+        .lufs_integrated;
+    anyhow::ensure!(
+        original_lufs > SILENCE_LUFS && master_lufs > SILENCE_LUFS,
+        "Cannot match the loudness of a silent file"
+    );
+    let (original_gain_db, master_gain_db, lufs_integrated) = match matched_to {
+        MatchTo::Master => (master_lufs - original_lufs, 0.0, master_lufs),
+        MatchTo::Original => (0.0, original_lufs - master_lufs, original_lufs),
+    };
+
+    let stem = original
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let original_path = output_dir.join(format!("{stem}_ab_original.wav"));
+    let master_path = output_dir.join(format!("{stem}_ab_master.wav"));
+    let report_path = output_dir.join(format!("{stem}_ab.json"));
+
+    let renders = [
+        (
+            original.to_path_buf(),
+            original_path.clone(),
+            original_gain_db,
+        ),
+        (master.to_path_buf(), master_path.clone(), master_gain_db),
+    ];
+    tokio::task::spawn_blocking(move || {
+        renders
+            .iter()
+            .try_for_each(|(input, output, gain_db)| render_with_gain(input, output, *gain_db))
+    })
+    .await
+    .context("A/B render task failed")??;
+
+    let comparison = analysis::compare_files(&original_path, &master_path, None).await?;
+    let export = AbExport {
+        original_path,
+        master_path,
+        report_path,
+        matched_to,
+        lufs_integrated,
+        original_gain_db,
+        master_gain_db,
+        comparison,
+    };
+    let json = serde_json::to_string_pretty(&export)?;
+    tokio::fs::write(&export.report_path, json)
+        .await
+        .with_context(|| format!("Writing {}", export.report_path.display()))?;
+    Ok(export)
+}
+
+/// Write `input` turned up or down by `gain_db` to `output` as 32-bit float WAV.
+fn render_with_gain(input: &Path, output: &Path, gain_db: f64) -> Result<()> {
+    let mut audio = decode_audio(input)?;
+    dynamics::apply_gain_db(&mut audio.samples, gain_db);
+    let opts = EncodeOptions {
+        bit_depth: 32,
+        sample_format: SampleFormat::Float,
+        ..Default::default()
+    };
+    encode::write_wav(
+        output,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        &opts,
+    )
+    .with_context(|| format!("Writing {}", output.display()))
+}
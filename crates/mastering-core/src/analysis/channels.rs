@@ -0,0 +1,230 @@
+//! Channel layouts of multi-channel audio.
+//!
+//! Speaker positions drive the BS.1770 per-channel loudness weights and the
+//! ITU-R BS.775 stereo downmix used for surround material.
+
+use serde::{Deserialize, Serialize};
+use symphonia::core::audio::Channels;
+
+/// Loudspeaker position of a single channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Speaker {
+    Left,
+    Right,
+    Center,
+    Lfe,
+    SideLeft,
+    SideRight,
+    RearLeft,
+    RearRight,
+    RearCenter,
+    Other,
+}
+
+/// Ordered speaker positions of the channels in an interleaved stream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelLayout {
+    speakers: Vec<Speaker>,
+}
+
+const DOWNMIX_CENTER: f32 = std::f32::consts::FRAC_1_SQRT_2;
+const DOWNMIX_SURROUND: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+impl ChannelLayout {
+    /// Conventional layout for a channel count (WAV/SMPTE ordering).
+    pub fn default_for(channels: u16) -> Self {
+        use Speaker::*;
+        let speakers = match channels {
+            1 => vec![Center],
+            2 => vec![Left, Right],
+            3 => vec![Left, Right, Center],
+            4 => vec![Left, Right, RearLeft, RearRight],
+            5 => vec![Left, Right, Center, RearLeft, RearRight],
+            6 => vec![Left, Right, Center, Lfe, RearLeft, RearRight],
+            8 => vec![
+                Left, Right, Center, Lfe, RearLeft, RearRight, SideLeft, SideRight,
+            ],
+            n => vec![Other; n as usize],
+        };
+        Self { speakers }
+    }
+
+    /// Layout from the channel mask reported by the decoder.
+    pub fn from_symphonia(channels: Channels) -> Self {
+        if channels == Channels::FRONT_LEFT {
+            return Self::default_for(1);
+        }
+        let speakers = channels
+            .iter()
+            .map(|ch| match ch {
+                Channels::FRONT_LEFT => Speaker::Left,
+                Channels::FRONT_RIGHT => Speaker::Right,
+                Channels::FRONT_CENTRE => Speaker::Center,
+                Channels::LFE1 | Channels::LFE2 => Speaker::Lfe,
+                Channels::SIDE_LEFT => Speaker::SideLeft,
+                Channels::SIDE_RIGHT => Speaker::SideRight,
+                Channels::REAR_LEFT | Channels::REAR_LEFT_CENTRE => Speaker::RearLeft,
+                Channels::REAR_RIGHT | Channels::REAR_RIGHT_CENTRE => Speaker::RearRight,
+                Channels::REAR_CENTRE => Speaker::RearCenter,
+                _ => Speaker::Other,
+            })
+            .collect();
+        Self { speakers }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.speakers.len()
+    }
+
+    pub fn speakers(&self) -> &[Speaker] {
+        &self.speakers
+    }
+
+    /// Whether the layout has more than two channels.
+    pub fn is_surround(&self) -> bool {
+        self.speakers.len() > 2
+    }
+
+    /// Short name such as "stereo" or "5.1".
+    pub fn name(&self) -> String {
+        let lfe = self.speakers.iter().filter(|&&s| s == Speaker::Lfe).count();
+        match (self.speakers.len(), lfe) {
+            (1, _) => "mono".into(),
+            (2, 0) => "stereo".into(),
+            (n, 0) => format!("{n}.0"),
+            (n, l) => format!("{}.{l}", n - l),
+        }
+    }
+
+    /// Display name of a channel, e.g. "Left" or "LFE".
+    pub fn channel_name(&self, channel: usize) -> String {
+        if self.speakers.len() == 1 {
+            return "Mono".into();
+        }
+        match self.speakers.get(channel) {
+            Some(Speaker::Left) => "Left".into(),
+            Some(Speaker::Right) => "Right".into(),
+            Some(Speaker::Center) => "Center".into(),
+            Some(Speaker::Lfe) => "LFE".into(),
+            Some(Speaker::SideLeft) => "Side Left".into(),
+            Some(Speaker::SideRight) => "Side Right".into(),
+            Some(Speaker::RearLeft) => "Rear Left".into(),
+            Some(Speaker::RearRight) => "Rear Right".into(),
+            Some(Speaker::RearCenter) => "Rear Center".into(),
+            Some(Speaker::Other) | None => format!("Channel {}", channel + 1),
+        }
+    }
+
+    /// BS.1770 weight of a channel's power in the loudness sum.
+    ///
+    /// Surrounds at ±60–120° count +1.5 dB (1.41), the LFE is excluded and
+    /// everything else is unity. In 7.1, the rear pair sits behind the side
+    /// surrounds and is weighted as unity.
+    pub fn weight(&self, channel: usize) -> f64 {
+        match self.speakers.get(channel) {
+            Some(Speaker::Lfe) => 0.0,
+            Some(Speaker::SideLeft | Speaker::SideRight) => 1.41,
+            Some(Speaker::RearLeft | Speaker::RearRight) if !self.has_side_surrounds() => 1.41,
+            _ => 1.0,
+        }
+    }
+
+    fn has_side_surrounds(&self) -> bool {
+        self.speakers
+            .iter()
+            .any(|s| matches!(s, Speaker::SideLeft | Speaker::SideRight))
+    }
+
+    /// Stereo (left, right) gains of each channel for an ITU-R BS.775 downmix.
+    ///
+    /// The LFE is dropped; centre and surrounds are mixed at -3 dB.
+    pub fn downmix_gains(&self) -> Vec<(f32, f32)> {
+        self.speakers
+            .iter()
+            .map(|s| match s {
+                Speaker::Left => (1.0, 0.0),
+                Speaker::Right => (0.0, 1.0),
+                Speaker::Center => {
+                    if self.speakers.len() == 1 {
+                        (1.0, 1.0)
+                    } else {
+                        (DOWNMIX_CENTER, DOWNMIX_CENTER)
+                    }
+                }
+                Speaker::Lfe => (0.0, 0.0),
+                Speaker::SideLeft | Speaker::RearLeft => (DOWNMIX_SURROUND, 0.0),
+                Speaker::SideRight | Speaker::RearRight => (0.0, DOWNMIX_SURROUND),
+                Speaker::RearCenter => (0.5, 0.5),
+                Speaker::Other => (0.5, 0.5),
+            })
+            .collect()
+    }
+
+    /// Downmix interleaved frames of this layout to interleaved stereo.
+    pub fn downmix_to_stereo(&self, samples: &[f32]) -> Vec<f32> {
+        let gains = self.downmix_gains();
+        let channels = gains.len().max(1);
+        let mut out = Vec::with_capacity(samples.len() / channels * 2);
+        for frame in samples.chunks_exact(channels) {
+            let (l, r) = frame
+                .iter()
+                .zip(&gains)
+                .fold((0.0, 0.0), |(l, r), (&x, &(gl, gr))| {
+                    (l + x * gl, r + x * gr)
+                });
+            out.push(l);
+            out.push(r);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_names() {
+        assert_eq!(ChannelLayout::default_for(1).name(), "mono");
+        assert_eq!(ChannelLayout::default_for(2).name(), "stereo");
+        assert_eq!(ChannelLayout::default_for(6).name(), "5.1");
+        assert_eq!(ChannelLayout::default_for(8).name(), "7.1");
+    }
+
+    #[test]
+    fn test_bs1770_weights() {
+        let surround51 = ChannelLayout::default_for(6);
+        assert_eq!(surround51.weight(3), 0.0);
+        assert_eq!(surround51.weight(4), 1.41);
+
+        let surround71 = ChannelLayout::default_for(8);
+        assert_eq!(surround71.weight(4), 1.0);
+        assert_eq!(surround71.weight(6), 1.41);
+
+        assert_eq!(ChannelLayout::default_for(4).weight(3), 1.41);
+    }
+
+    #[test]
+    fn test_from_symphonia_mask() {
+        let mask = Channels::FRONT_LEFT
+            | Channels::FRONT_RIGHT
+            | Channels::FRONT_CENTRE
+            | Channels::LFE1
+            | Channels::SIDE_LEFT
+            | Channels::SIDE_RIGHT;
+        let layout = ChannelLayout::from_symphonia(mask);
+        assert_eq!(layout.name(), "5.1");
+        assert_eq!(layout.weight(5), 1.41);
+    }
+
+    #[test]
+    fn test_downmix_drops_lfe() {
+        let layout = ChannelLayout::default_for(6);
+        // L, R, C, LFE, Ls, Rs
+        let frame = [0.5, 0.0, 0.0, 1.0, 0.0, 0.2];
+        let stereo = layout.downmix_to_stereo(&frame);
+        assert_eq!(stereo[0], 0.5);
+        assert!((stereo[1] - 0.2 * DOWNMIX_SURROUND).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::{AudioAnalysis, FrequencyBands, SpectralDescriptors};
+
+/// Differences between two analyses, expressed as `b - a`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisDelta {
+    pub lufs_integrated: f64,
+    pub lufs_short_term_max: f64,
+    pub rms_db: f64,
+    pub peak_db: f64,
+    pub true_peak_db: f64,
+    pub dynamic_range_db: f64,
+    pub stereo_width: f64,
+    #[serde(default)]
+    pub stereo_balance_db: f64,
+    #[serde(default)]
+    pub phase_correlation: f64,
+    pub frequency_bands: FrequencyBands,
+    #[serde(default)]
+    pub spectral: SpectralDescriptors,
+}
+
+/// Side-by-side comparison of two analysed files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisComparison {
+    pub a: AudioAnalysis,
+    pub b: AudioAnalysis,
+    pub delta: AnalysisDelta,
+}
+
+/// Compare two analyses.
+pub fn compare(a: AudioAnalysis, b: AudioAnalysis) -> AnalysisComparison {
+    let delta = AnalysisDelta {
+        lufs_integrated: b.lufs_integrated - a.lufs_integrated,
+        lufs_short_term_max: b.lufs_short_term_max - a.lufs_short_term_max,
+        rms_db: b.rms_db - a.rms_db,
+        peak_db: b.peak_db - a.peak_db,
+        true_peak_db: b.true_peak_db - a.true_peak_db,
+        dynamic_range_db: b.dynamic_range_db - a.dynamic_range_db,
+        stereo_width: b.stereo_width - a.stereo_width,
+        stereo_balance_db: b.stereo_balance_db - a.stereo_balance_db,
+        phase_correlation: b.phase_correlation - a.phase_correlation,
+        frequency_bands: band_delta(&a.frequency_bands, &b.frequency_bands),
+        spectral: SpectralDescriptors {
+            centroid_hz: b.spectral.centroid_hz - a.spectral.centroid_hz,
+            rolloff_hz: b.spectral.rolloff_hz - a.spectral.rolloff_hz,
+            flatness: b.spectral.flatness - a.spectral.flatness,
+            tilt_db_per_octave: b.spectral.tilt_db_per_octave - a.spectral.tilt_db_per_octave,
+        },
+    };
+    AnalysisComparison { a, b, delta }
+}
+
+fn band_delta(a: &FrequencyBands, b: &FrequencyBands) -> FrequencyBands {
+    FrequencyBands {
+        sub_bass: b.sub_bass - a.sub_bass,
+        bass: b.bass - a.bass,
+        low_mid: b.low_mid - a.low_mid,
+        mid: b.mid - a.mid,
+        upper_mid: b.upper_mid - a.upper_mid,
+        presence: b.presence - a.presence,
+        brilliance: b.brilliance - a.brilliance,
+    }
+}
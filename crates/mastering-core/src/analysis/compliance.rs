@@ -0,0 +1,211 @@
+//! Broadcast loudness compliance checks.
+//!
+//! Broadcasters and streaming services publish delivery specs: a target
+//! integrated loudness with a tolerance, a true peak ceiling and sometimes
+//! limits on loudness range or short-term loudness. A file either meets
+//! every limit of its spec or is rejected at delivery, so each check is a
+//! plain pass or fail.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::AudioAnalysis;
+
+/// A published delivery spec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Spec {
+    /// EBU R128 for long-form programmes.
+    EbuR128,
+    /// EBU R128 s1 for advertisements and other short-form content.
+    EbuR128S1,
+    /// ATSC A/85 (US broadcast television).
+    AtscA85,
+    /// Netflix original programming.
+    Netflix,
+}
+
+impl Spec {
+    pub const ALL: [Spec; 4] = [Spec::EbuR128, Spec::EbuR128S1, Spec::AtscA85, Spec::Netflix];
+
+    /// Limits of this spec.
+    pub fn limits(&self) -> SpecLimits {
+        match self {
+            Spec::EbuR128 => SpecLimits {
+                target_lufs: -23.0,
+                tolerance_lu: 0.5,
+                max_true_peak_db: -1.0,
+                loudness_range_lu: None,
+                max_short_term_lufs: None,
+                max_momentary_lufs: None,
+            },
+            Spec::EbuR128S1 => SpecLimits {
+                target_lufs: -23.0,
+                tolerance_lu: 0.5,
+                max_true_peak_db: -1.0,
+                loudness_range_lu: None,
+                max_short_term_lufs: Some(-18.0),
+                max_momentary_lufs: None,
+            },
+            Spec::AtscA85 => SpecLimits {
+                target_lufs: -24.0,
+                tolerance_lu: 2.0,
+                max_true_peak_db: -2.0,
+                loudness_range_lu: None,
+                max_short_term_lufs: None,
+                max_momentary_lufs: None,
+            },
+            Spec::Netflix => SpecLimits {
+                target_lufs: -27.0,
+                tolerance_lu: 2.0,
+                max_true_peak_db: -2.0,
+                loudness_range_lu: Some((4.0, 18.0)),
+                max_short_term_lufs: None,
+                max_momentary_lufs: None,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Spec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Spec::EbuR128 => write!(f, "ebu-r128"),
+            Spec::EbuR128S1 => write!(f, "ebu-r128-s1"),
+            Spec::AtscA85 => write!(f, "atsc-a85"),
+            Spec::Netflix => write!(f, "netflix"),
+        }
+    }
+}
+
+impl std::str::FromStr for Spec {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "-").as_str() {
+            "ebu-r128" | "r128" | "ebu" => Ok(Spec::EbuR128),
+            "ebu-r128-s1" | "r128-s1" => Ok(Spec::EbuR128S1),
+            "atsc-a85" | "a85" | "atsc" => Ok(Spec::AtscA85),
+            "netflix" => Ok(Spec::Netflix),
+            _ => anyhow::bail!(
+                "Unknown spec: {s}. Available: ebu-r128, ebu-r128-s1, atsc-a85, netflix"
+            ),
+        }
+    }
+}
+
+/// Loudness limits a file must meet.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpecLimits {
+    /// Target integrated loudness in LUFS.
+    pub target_lufs: f64,
+    /// Allowed deviation from the target either way, in LU.
+    pub tolerance_lu: f64,
+    /// Highest allowed true peak in dBTP.
+    pub max_true_peak_db: f64,
+    /// Allowed loudness range as (min, max) in LU.
+    pub loudness_range_lu: Option<(f64, f64)>,
+    /// Highest allowed short-term loudness in LUFS.
+    pub max_short_term_lufs: Option<f64>,
+    /// Highest allowed momentary loudness in LUFS.
+    pub max_momentary_lufs: Option<f64>,
+}
+
+/// One measured value against its limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComplianceCheck {
+    pub name: String,
+    pub value: f64,
+    pub unit: String,
+    /// The allowed values, e.g. "-23.0 ± 0.5".
+    pub limit: String,
+    pub passed: bool,
+}
+
+/// Result of checking a file against a spec.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceReport {
+    pub spec: Spec,
+    pub limits: SpecLimits,
+    pub checks: Vec<ComplianceCheck>,
+    /// Every check passed.
+    pub passed: bool,
+}
+
+/// Check `analysis` against `limits`.
+pub fn check(spec: Spec, limits: SpecLimits, analysis: &AudioAnalysis) -> ComplianceReport {
+    let check = |name: &str, value: f64, unit: &str, limit: String, passed: bool| ComplianceCheck {
+        name: name.to_string(),
+        value,
+        unit: unit.to_string(),
+        limit,
+        passed,
+    };
+
+    let mut checks = vec![
+        check(
+            "Integrated",
+            analysis.lufs_integrated,
+            "LUFS",
+            format!("{:.1} ± {:.1}", limits.target_lufs, limits.tolerance_lu),
+            (analysis.lufs_integrated - limits.target_lufs).abs() <= limits.tolerance_lu,
+        ),
+        check(
+            "True Peak",
+            analysis.true_peak_db,
+            "dBTP",
+            format!("≤ {:.1}", limits.max_true_peak_db),
+            analysis.true_peak_db <= limits.max_true_peak_db,
+        ),
+    ];
+    if let Some((min, max)) = limits.loudness_range_lu {
+        let lra = analysis.loudness_range_lu;
+        checks.push(check(
+            "Loudness Range",
+            lra,
+            "LU",
+            format!("{min:.1} – {max:.1}"),
+            (min..=max).contains(&lra),
+        ));
+    }
+    if let Some(max) = limits.max_short_term_lufs {
+        let value = analysis.lufs_short_term_max;
+        checks.push(check(
+            "Short-term Max",
+            value,
+            "LUFS",
+            format!("≤ {max:.1}"),
+            value <= max,
+        ));
+    }
+    if let Some(max) = limits.max_momentary_lufs {
+        let value = analysis.lufs_momentary_max;
+        checks.push(check(
+            "Momentary Max",
+            value,
+            "LUFS",
+            format!("≤ {max:.1}"),
+            value <= max,
+        ));
+    }
+
+    let passed = checks.iter().all(|c| c.passed);
+    ComplianceReport {
+        spec,
+        limits,
+        checks,
+        passed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_names_round_trip() {
+        for spec in Spec::ALL {
+            assert_eq!(spec.to_string().parse::<Spec>().unwrap(), spec);
+        }
+        assert_eq!("EBU_R128".parse::<Spec>().unwrap(), Spec::EbuR128);
+        assert!("bbc".parse::<Spec>().is_err());
+    }
+}
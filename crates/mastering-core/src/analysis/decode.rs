@@ -1,12 +1,15 @@
 use anyhow::{Context, Result};
 use std::path::Path;
 use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, FormatReader};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 
+use super::channels::ChannelLayout;
+use crate::types::TimeRange;
+
 /// Decoded audio data: interleaved f32 samples with metadata.
 #[derive(Debug, Clone)]
 pub struct DecodedAudio {
@@ -14,9 +17,23 @@ pub struct DecodedAudio {
     pub sample_rate: u32,
     pub channels: u16,
     pub total_frames: u64,
+    /// Speaker position of each channel.
+    pub layout: ChannelLayout,
 }
 
 impl DecodedAudio {
+    /// Wrap interleaved samples, assuming the conventional layout for `channels`.
+    pub fn new(samples: Vec<f32>, sample_rate: u32, channels: u16) -> Self {
+        let total_frames = (samples.len() / channels.max(1) as usize) as u64;
+        Self {
+            samples,
+            sample_rate,
+            channels,
+            total_frames,
+            layout: ChannelLayout::default_for(channels),
+        }
+    }
+
     /// Get samples for a single channel (0-indexed).
     pub fn channel_samples(&self, ch: u16) -> Vec<f32> {
         self.samples
@@ -27,93 +44,222 @@ impl DecodedAudio {
             .collect()
     }
 
+    /// Average all channels into a single mono signal.
+    pub fn mono_mixdown(&self) -> Vec<f32> {
+        let channels = self.channels as usize;
+        if channels <= 1 {
+            return self.samples.clone();
+        }
+        self.samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect()
+    }
+
     pub fn duration_secs(&self) -> f64 {
         self.total_frames as f64 / self.sample_rate as f64
     }
 }
 
-/// Decode an audio file into interleaved f32 samples using symphonia.
-pub fn decode_audio(path: &Path) -> Result<DecodedAudio> {
-    let file = std::fs::File::open(path)
-        .with_context(|| format!("Opening audio file: {}", path.display()))?;
+/// Streaming decoder yielding chunks of interleaved f32 samples.
+///
+/// Each chunk holds whole frames (one decoded packet), so memory use stays
+/// bounded regardless of file length.
+pub struct AudioStream {
+    format_reader: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+    channels: u16,
+    layout: ChannelLayout,
+    sample_buf: Option<SampleBuffer<f32>>,
+    /// Frames still to be dropped before the range starts.
+    skip_frames: u64,
+    /// Frames left before the range ends; `None` runs to the end.
+    remaining_frames: Option<u64>,
+}
+
+impl AudioStream {
+    /// Open `path` and prepare a decoder for its first audio track.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("Opening audio file: {}", path.display()))?;
 
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
 
-    let mut hint = Hint::new();
-    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-        hint.with_extension(ext);
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+
+        let probed = symphonia::default::get_probe()
+            .format(
+                &hint,
+                mss,
+                &FormatOptions::default(),
+                &MetadataOptions::default(),
+            )
+            .with_context(|| format!("Probing audio format: {}", path.display()))?;
+
+        let format_reader = probed.format;
+
+        let track = format_reader
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
+            .context("No supported audio track found")?;
+
+        let track_id = track.id;
+        let codec_params = track.codec_params.clone();
+
+        let sample_rate = codec_params.sample_rate.context("Missing sample rate")?;
+        let layout = codec_params
+            .channels
+            .map(ChannelLayout::from_symphonia)
+            .unwrap_or_else(|| ChannelLayout::default_for(2));
+        let channels = layout.channels() as u16;
+
+        let dec_opts = DecoderOptions::default();
+        let decoder = symphonia::default::get_codecs()
+            .make(&codec_params, &dec_opts)
+            .context("Creating audio decoder")?;
+
+        Ok(Self {
+            format_reader,
+            decoder,
+            track_id,
+            sample_rate,
+            channels,
+            layout,
+            sample_buf: None,
+            skip_frames: 0,
+            remaining_frames: None,
+        })
     }
 
-    let probed = symphonia::default::get_probe()
-        .format(
-            &hint,
-            mss,
-            &FormatOptions::default(),
-            &MetadataOptions::default(),
-        )
-        .with_context(|| format!("Probing audio format: {}", path.display()))?;
-
-    let mut format_reader = probed.format;
-
-    let track = format_reader
-        .tracks()
-        .iter()
-        .find(|t| t.codec_params.codec != symphonia::core::codecs::CODEC_TYPE_NULL)
-        .context("No supported audio track found")?;
-
-    let track_id = track.id;
-    let codec_params = track.codec_params.clone();
-
-    let sample_rate = codec_params
-        .sample_rate
-        .context("Missing sample rate")?;
-    let channels = codec_params
-        .channels
-        .map(|c| c.count() as u16)
-        .unwrap_or(2);
-
-    let dec_opts = DecoderOptions::default();
-    let mut decoder = symphonia::default::get_codecs()
-        .make(&codec_params, &dec_opts)
-        .context("Creating audio decoder")?;
+    /// Open `path` and limit the stream to `range`.
+    pub fn open_range(path: &Path, range: &TimeRange) -> Result<Self> {
+        let mut stream = Self::open(path)?;
+        stream.set_range(range);
+        Ok(stream)
+    }
 
-    let mut all_samples: Vec<f32> = Vec::new();
-    let mut total_frames: u64 = 0;
+    /// Limit the stream to `range`. Frames before its start are decoded and
+    /// dropped, and the stream ends at its end.
+    pub fn set_range(&mut self, range: &TimeRange) {
+        let rate = self.sample_rate as f64;
+        self.skip_frames = (range.start_secs.max(0.0) * rate) as u64;
+        self.remaining_frames = range
+            .end_secs
+            .map(|end| ((end - range.start_secs.max(0.0)).max(0.0) * rate) as u64);
+    }
 
-    loop {
-        let packet = match format_reader.next_packet() {
-            Ok(p) => p,
-            Err(symphonia::core::errors::Error::IoError(ref e))
-                if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn layout(&self) -> &ChannelLayout {
+        &self.layout
+    }
+
+    /// Decode the next packet, returning `None` at end of stream.
+    pub fn next_chunk(&mut self) -> Result<Option<&[f32]>> {
+        loop {
+            if self.remaining_frames == Some(0) {
+                return Ok(None);
+            }
+            let packet = match self.format_reader.next_packet() {
+                Ok(p) => p,
+                Err(symphonia::core::errors::Error::IoError(ref e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                {
+                    return Ok(None);
+                }
+                Err(e) => return Err(e).context("Reading packet"),
+            };
+
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(d) => d,
+                Err(symphonia::core::errors::Error::DecodeError(_)) => continue,
+                Err(e) => return Err(e).context("Decoding packet"),
+            };
+
+            let spec = *decoded.spec();
+            let num_frames = decoded.frames() as u64;
+            if num_frames == 0 {
+                continue;
+            }
+
+            // Reuse the sample buffer unless this packet is larger than any before
+            let needed = num_frames * spec.channels.count() as u64;
+            if self
+                .sample_buf
+                .as_ref()
+                .is_none_or(|buf| (buf.capacity() as u64) < needed)
             {
-                break;
+                self.sample_buf = Some(SampleBuffer::<f32>::new(num_frames, spec));
+            }
+
+            // Keep only the part of the packet inside the range
+            let skip = self.skip_frames.min(num_frames);
+            self.skip_frames -= skip;
+            let mut take = num_frames - skip;
+            if let Some(ref mut remaining) = self.remaining_frames {
+                take = take.min(*remaining);
+                *remaining -= take;
+            }
+            if take == 0 {
+                continue;
             }
-            Err(e) => return Err(e).context("Reading packet"),
-        };
 
-        if packet.track_id() != track_id {
-            continue;
+            let channels = spec.channels.count();
+            let buf = self
+                .sample_buf
+                .as_mut()
+                .expect("sample buffer allocated above");
+            buf.copy_interleaved_ref(decoded);
+            let (start, end) = (skip as usize * channels, (skip + take) as usize * channels);
+            return Ok(Some(&buf.samples()[start..end]));
         }
+    }
+}
 
-        let decoded = match decoder.decode(&packet) {
-            Ok(d) => d,
-            Err(symphonia::core::errors::Error::DecodeError(_)) => continue,
-            Err(e) => return Err(e).context("Decoding packet"),
-        };
+/// Decode an audio file into interleaved f32 samples using symphonia.
+///
+/// Loads the whole file into memory; prefer [`AudioStream`] for long files.
+pub fn decode_audio(path: &Path) -> Result<DecodedAudio> {
+    decode_stream(AudioStream::open(path)?)
+}
 
-        let spec = *decoded.spec();
-        let num_frames = decoded.frames();
-        total_frames += num_frames as u64;
+/// Decode the part of an audio file inside `range`.
+pub fn decode_audio_range(path: &Path, range: &TimeRange) -> Result<DecodedAudio> {
+    decode_stream(AudioStream::open_range(path, range)?)
+}
+
+fn decode_stream(mut stream: AudioStream) -> Result<DecodedAudio> {
+    let channels = stream.channels();
+    let sample_rate = stream.sample_rate();
+    let layout = stream.layout().clone();
 
-        let mut sample_buf = SampleBuffer::<f32>::new(num_frames as u64, spec);
-        sample_buf.copy_interleaved_ref(decoded);
-        all_samples.extend_from_slice(sample_buf.samples());
+    let mut all_samples: Vec<f32> = Vec::new();
+    while let Some(chunk) = stream.next_chunk()? {
+        all_samples.extend_from_slice(chunk);
     }
 
+    let total_frames = (all_samples.len() / channels.max(1) as usize) as u64;
     Ok(DecodedAudio {
         samples: all_samples,
         sample_rate,
         channels,
         total_frames,
+        layout,
     })
 }
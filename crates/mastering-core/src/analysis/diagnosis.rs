@@ -0,0 +1,306 @@
+//! Problem diagnosis.
+//!
+//! Rules over an [`AudioAnalysis`] that name common mix problems a master
+//! would make worse, each graded by how far the measurement is past its
+//! limit and paired with a plain-language suggestion for fixing it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::AudioAnalysis;
+
+/// How much a problem matters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Worth a listen; may be intended.
+    Info,
+    /// Likely to be audible in the master.
+    Warning,
+    /// Will clearly hurt the master.
+    Critical,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// A kind of problem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Problem {
+    /// Too much energy at 2-5 kHz.
+    Harsh,
+    /// Too much energy at 200-400 Hz.
+    Muddy,
+    /// Too much energy below 60 Hz.
+    ExcessiveSub,
+    /// Peaks already squashed close to the loudness.
+    OverLimited,
+    /// Left and right poorly correlated or the image over-wide.
+    PhaseySides,
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Harsh => write!(f, "Harsh upper mids"),
+            Problem::Muddy => write!(f, "Muddy low mids"),
+            Problem::ExcessiveSub => write!(f, "Excessive sub-bass"),
+            Problem::OverLimited => write!(f, "Over-limited"),
+            Problem::PhaseySides => write!(f, "Phasey sides"),
+        }
+    }
+}
+
+/// A problem found in an analysis.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Issue {
+    pub problem: Problem,
+    pub severity: Severity,
+    /// What was measured, e.g. "Upper-mids at -5.2 dB of the spectrum, 3.8 dB
+    /// above -9.0".
+    pub detail: String,
+    /// What to do about it.
+    pub suggestion: String,
+}
+
+/// Highest upper-mid (2-4 kHz) share of the spectrum, in dB.
+const UPPER_MID_MAX_DB: f64 = -9.0;
+
+/// Highest presence (4-6 kHz) share of the spectrum, in dB.
+const PRESENCE_MAX_DB: f64 = -12.0;
+
+/// Highest low-mid (250-500 Hz) share of the spectrum, in dB.
+const LOW_MID_MAX_DB: f64 = -6.0;
+
+/// Highest sub-bass (below 60 Hz) share of the spectrum, in dB.
+const SUB_BASS_MAX_DB: f64 = -6.0;
+
+/// Lowest peak-to-loudness ratio (true peak over integrated loudness) of a
+/// master with its transients intact, in LU.
+const MIN_PLR_LU: f64 = 9.0;
+
+/// Lowest left/right correlation of a solid stereo image.
+const MIN_CORRELATION: f64 = 0.3;
+
+/// Widest stereo image before the sides dominate.
+const MAX_WIDTH: f64 = 1.2;
+
+/// Excess over a spectral limit at which a problem becomes a warning and a
+/// critical one, in dB.
+const BAND_GRADES: (f64, f64) = (2.0, 5.0);
+
+/// Loudness below which the input is treated as silent, in LUFS.
+const SILENT_LUFS: f64 = -70.0;
+
+/// Problems in `analysis`, most severe first. Empty for a mix without any.
+pub fn diagnose(analysis: &AudioAnalysis) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    if analysis.lufs_integrated <= SILENT_LUFS {
+        return issues;
+    }
+    let bands = &analysis.frequency_bands;
+
+    let harsh = [
+        ("Upper-mids", bands.upper_mid, UPPER_MID_MAX_DB),
+        ("Presence", bands.presence, PRESENCE_MAX_DB),
+    ]
+    .into_iter()
+    .max_by(|a, b| (a.1 - a.2).total_cmp(&(b.1 - b.2)));
+    if let Some((name, level, limit)) = harsh.filter(|(_, level, limit)| level > limit) {
+        issues.push(Issue {
+            problem: Problem::Harsh,
+            severity: grade(level - limit, BAND_GRADES),
+            detail: band_detail(name, level, limit),
+            suggestion: "Cut 1-3 dB around 3 kHz with a broad peak (Q about 1); if it comes from sibilant vocals, turn on the de-esser instead".into(),
+        });
+    }
+
+    if bands.low_mid > LOW_MID_MAX_DB {
+        issues.push(Issue {
+            problem: Problem::Muddy,
+            severity: grade(bands.low_mid - LOW_MID_MAX_DB, BAND_GRADES),
+            detail: band_detail("Low-mids", bands.low_mid, LOW_MID_MAX_DB),
+            suggestion: "Cut 1-3 dB around 300 Hz with a broad peak to clear up the low mids"
+                .into(),
+        });
+    }
+
+    if bands.sub_bass > SUB_BASS_MAX_DB {
+        issues.push(Issue {
+            problem: Problem::ExcessiveSub,
+            severity: grade(bands.sub_bass - SUB_BASS_MAX_DB, BAND_GRADES),
+            detail: band_detail("Sub-bass", bands.sub_bass, SUB_BASS_MAX_DB),
+            suggestion: "Tame the sub with a low shelf cut below 60 Hz and a high-pass around 25-30 Hz, then check the kick and bass on full-range speakers".into(),
+        });
+    }
+
+    let plr = analysis.true_peak_db - analysis.lufs_integrated;
+    if plr < MIN_PLR_LU {
+        issues.push(Issue {
+            problem: Problem::OverLimited,
+            severity: grade(MIN_PLR_LU - plr, (1.0, 3.0)),
+            detail: format!("Peaks only {plr:.1} LU above the loudness (PLR), below {MIN_PLR_LU:.1}"),
+            suggestion: "The transients are already squashed: master to a lower loudness target, ease off the limiter, or start from a less limited mix".into(),
+        });
+    }
+
+    // Mono input has no sides
+    if analysis.mono_compatibility.is_some() {
+        let correlation = analysis.phase_correlation;
+        let wide = analysis.stereo_width > MAX_WIDTH;
+        if correlation < MIN_CORRELATION || wide {
+            let severity = if correlation < 0.0 || analysis.out_of_phase_secs > 0.0 {
+                Severity::Critical
+            } else if correlation < MIN_CORRELATION - 0.1 || analysis.stereo_width > MAX_WIDTH + 0.2
+            {
+                Severity::Warning
+            } else {
+                Severity::Info
+            };
+            issues.push(Issue {
+                problem: Problem::PhaseySides,
+                severity,
+                detail: format!(
+                    "Correlation {correlation:.2} and width {:.0}%; below {MIN_CORRELATION:.1} or above {:.0}% the image gets vague and thins out in mono",
+                    analysis.stereo_width * 100.0,
+                    MAX_WIDTH * 100.0
+                ),
+                suggestion: "Narrow the stereo width or high-pass the side channel below about 150 Hz, and check the mix in mono".into(),
+            });
+        }
+    }
+
+    issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+    issues
+}
+
+/// Severity of a measurement `excess` past its limit, given the excess at
+/// which it becomes a warning and a critical problem.
+fn grade(excess: f64, (warning, critical): (f64, f64)) -> Severity {
+    if excess >= critical {
+        Severity::Critical
+    } else if excess >= warning {
+        Severity::Warning
+    } else {
+        Severity::Info
+    }
+}
+
+fn band_detail(name: &str, level: f64, limit: f64) -> String {
+    format!(
+        "{name} at {level:.1} dB of the spectrum, {:.1} dB above {limit:.1}",
+        level - limit
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AudioMetadata, FrequencyBands, MonoCompatibility};
+
+    fn analysis(bands: FrequencyBands) -> AudioAnalysis {
+        AudioAnalysis {
+            metadata: AudioMetadata {
+                path: "song.wav".into(),
+                sample_rate: 44100,
+                channels: 2,
+                channel_layout: "stereo".into(),
+                duration_secs: 180.0,
+                bit_depth: Some(24),
+                format: "WAV".into(),
+            },
+            lufs_integrated: -14.0,
+            lufs_short_term_max: -11.0,
+            lufs_momentary_max: -9.0,
+            loudness_range_lu: 6.0,
+            rms_db: -16.0,
+            peak_db: -1.5,
+            true_peak_db: -1.0,
+            dynamic_range_db: 10.0,
+            stereo_width: 0.7,
+            phase_correlation: 0.6,
+            phase_correlation_min: 0.2,
+            mono_compatibility: Some(MonoCompatibility {
+                band_loss_db: bands.clone(),
+                correlation_over_time: Vec::new(),
+                correlation_window_secs: 0.5,
+            }),
+            frequency_bands: bands,
+            ..Default::default()
+        }
+    }
+
+    fn balanced() -> FrequencyBands {
+        FrequencyBands {
+            sub_bass: -10.0,
+            bass: -5.0,
+            low_mid: -8.0,
+            mid: -5.0,
+            upper_mid: -12.0,
+            presence: -16.0,
+            brilliance: -15.0,
+        }
+    }
+
+    #[test]
+    fn test_clean_mix_has_no_issues() {
+        assert!(diagnose(&analysis(balanced())).is_empty());
+    }
+
+    #[test]
+    fn test_problems_are_graded_and_sorted() {
+        let bands = FrequencyBands {
+            upper_mid: -3.0,
+            low_mid: -5.0,
+            ..balanced()
+        };
+        let issues = diagnose(&AudioAnalysis {
+            true_peak_db: -0.5,
+            lufs_integrated: -6.0,
+            ..analysis(bands)
+        });
+
+        let problems: Vec<_> = issues.iter().map(|i| (i.problem, i.severity)).collect();
+        assert_eq!(
+            problems,
+            [
+                (Problem::Harsh, Severity::Critical),
+                (Problem::OverLimited, Severity::Critical),
+                (Problem::Muddy, Severity::Info),
+            ]
+        );
+        assert!(
+            issues[0].detail.contains("Upper-mids at -3.0 dB"),
+            "{}",
+            issues[0].detail
+        );
+    }
+
+    #[test]
+    fn test_phasey_sides_only_for_stereo() {
+        let phasey = AudioAnalysis {
+            phase_correlation: -0.1,
+            stereo_width: 1.5,
+            ..analysis(balanced())
+        };
+        let issues = diagnose(&phasey);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(
+            (issues[0].problem, issues[0].severity),
+            (Problem::PhaseySides, Severity::Critical)
+        );
+
+        let mono = AudioAnalysis {
+            mono_compatibility: None,
+            ..phasey
+        };
+        assert!(diagnose(&mono).is_empty());
+    }
+}
@@ -0,0 +1,657 @@
+//! ITU-R BS.1770-4 / EBU R128 loudness measurement.
+//!
+//! Audio is passed through the two-stage K-weighting filter (a high-shelf
+//! "pre-filter" followed by the RLB high-pass), weighted per channel, and
+//! summed into a per-frame power series. Integrated loudness uses 400 ms
+//! blocks with 75% overlap and the absolute (-70 LUFS) and relative (-10 LU)
+//! gates; short-term loudness uses a 3 s sliding window. Loudness range
+//! follows EBU Tech 3342.
+//!
+//! [`PowerSeries`] keeps per-frame power for arbitrary window queries;
+//! [`LoudnessMeter`] measures incrementally for streamed audio.
+
+use serde::{Deserialize, Serialize};
+
+use super::channels::ChannelLayout;
+use super::decode::DecodedAudio;
+use crate::dsp::eq::Biquad;
+
+/// Floor value reported for silence, matching the rest of the analysis module.
+pub const SILENCE_LUFS: f64 = -100.0;
+
+/// Absolute gating threshold in LUFS.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// Relative gating threshold in LU below the ungated loudness.
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// Relative gate for loudness range measurement (EBU Tech 3342).
+const LRA_RELATIVE_GATE_LU: f64 = -20.0;
+
+/// Lower and upper percentiles of short-term loudness spanned by the
+/// loudness range.
+const LRA_PERCENTILES: (f64, f64) = (0.10, 0.95);
+
+/// Gating block length (momentary window) in seconds.
+pub const BLOCK_SECS: f64 = 0.4;
+
+/// Short-term window length in seconds.
+pub const SHORT_TERM_SECS: f64 = 3.0;
+
+/// Step between consecutive measurement windows in seconds (75% block overlap).
+pub const STEP_SECS: f64 = 0.1;
+
+/// Two-stage K-weighting filter for a single channel.
+///
+/// Coefficients are derived analytically so any sample rate is supported,
+/// not just the 48 kHz tables printed in BS.1770.
+#[derive(Debug, Clone, Copy)]
+pub struct KWeighting {
+    pre_filter: Biquad,
+    rlb_filter: Biquad,
+}
+
+impl KWeighting {
+    pub fn new(sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+
+        // Stage 1: high-shelf modelling the acoustic effect of the head.
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let pre_filter = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        // Stage 2: revised low-frequency B-curve (RLB) high-pass.
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let rlb_filter = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        Self {
+            pre_filter,
+            rlb_filter,
+        }
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f64) -> f64 {
+        let y = self.pre_filter.process(x);
+        self.rlb_filter.process(y)
+    }
+}
+
+/// Convert a mean-square (weighted) power to loudness in LUFS.
+pub fn power_to_lufs(power: f64) -> f64 {
+    if power <= 1e-20 {
+        SILENCE_LUFS
+    } else {
+        -0.691 + 10.0 * power.log10()
+    }
+}
+
+/// Per-frame K-weighted, channel-weighted power with a prefix sum for
+/// constant-time window averages.
+#[derive(Debug, Clone)]
+pub struct PowerSeries {
+    prefix: Vec<f64>,
+    sample_rate: u32,
+}
+
+impl PowerSeries {
+    /// K-weight every channel of `audio` and build the power series.
+    pub fn new(audio: &DecodedAudio) -> Self {
+        let channels = audio.channels as usize;
+        let frames = audio.samples.len().checked_div(channels).unwrap_or(0);
+
+        let mut power = vec![0.0f64; frames];
+        for ch in 0..channels {
+            let weight = audio.layout.weight(ch);
+            if weight == 0.0 {
+                continue;
+            }
+            let mut filter = KWeighting::new(audio.sample_rate);
+            for (frame, p) in power.iter_mut().enumerate() {
+                let y = filter.process(audio.samples[frame * channels + ch] as f64);
+                *p += weight * y * y;
+            }
+        }
+
+        let mut prefix = Vec::with_capacity(frames + 1);
+        prefix.push(0.0);
+        let mut acc = 0.0f64;
+        for p in power {
+            acc += p;
+            prefix.push(acc);
+        }
+
+        Self {
+            prefix,
+            sample_rate: audio.sample_rate,
+        }
+    }
+
+    /// Number of frames in the series.
+    pub fn frames(&self) -> usize {
+        self.prefix.len() - 1
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Mean power over frames `[start, end)`.
+    pub fn mean(&self, start: usize, end: usize) -> f64 {
+        let end = end.min(self.frames());
+        if start >= end {
+            return 0.0;
+        }
+        (self.prefix[end] - self.prefix[start]) / (end - start) as f64
+    }
+
+    /// Convert a duration in seconds to a frame count.
+    pub fn secs_to_frames(&self, secs: f64) -> usize {
+        (self.sample_rate as f64 * secs).round() as usize
+    }
+
+    /// Mean powers of consecutive windows of `window_secs`, advanced by [`STEP_SECS`].
+    pub fn window_powers(&self, window_secs: f64) -> Vec<f64> {
+        let window = self.secs_to_frames(window_secs).max(1);
+        let step = self.secs_to_frames(STEP_SECS).max(1);
+        let frames = self.frames();
+
+        let mut powers = Vec::new();
+        let mut pos = 0;
+        while pos + window <= frames {
+            powers.push(self.mean(pos, pos + window));
+            pos += step;
+        }
+        powers
+    }
+
+    /// Gated integrated loudness in LUFS.
+    pub fn integrated(&self) -> f64 {
+        let frames = self.frames();
+        if frames == 0 {
+            return SILENCE_LUFS;
+        }
+
+        let blocks = self.window_powers(BLOCK_SECS);
+        if blocks.is_empty() {
+            // Too short for a single gating block: report the ungated loudness
+            return power_to_lufs(self.mean(0, frames));
+        }
+
+        gated_loudness(&blocks)
+    }
+
+    /// Maximum short-term (3 s) loudness in LUFS.
+    pub fn short_term_max(&self) -> f64 {
+        let windows = self.window_powers(SHORT_TERM_SECS);
+        if windows.is_empty() {
+            return self.integrated();
+        }
+        windows
+            .into_iter()
+            .map(power_to_lufs)
+            .fold(SILENCE_LUFS, f64::max)
+    }
+}
+
+/// Incremental integrated and short-term loudness measurement.
+///
+/// Keeps only the K-weighted power of each completed 100 ms step, so memory
+/// grows by one value per step rather than per sample.
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    filters: Vec<KWeighting>,
+    weights: Vec<f64>,
+    step_frames: usize,
+    /// Summed power of the step currently being filled.
+    pending_power: f64,
+    pending_frames: usize,
+    /// Summed power of every completed step.
+    steps: Vec<f64>,
+    total_power: f64,
+    total_frames: u64,
+    /// Summed weighted power of each channel.
+    channel_power: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, layout: &ChannelLayout) -> Self {
+        let channels = layout.channels();
+        Self {
+            filters: vec![KWeighting::new(sample_rate); channels],
+            weights: (0..channels).map(|ch| layout.weight(ch)).collect(),
+            step_frames: ((sample_rate as f64 * STEP_SECS).round() as usize).max(1),
+            pending_power: 0.0,
+            pending_frames: 0,
+            steps: Vec::new(),
+            total_power: 0.0,
+            total_frames: 0,
+            channel_power: vec![0.0; channels],
+        }
+    }
+
+    /// Feed interleaved samples containing whole frames.
+    pub fn push(&mut self, samples: &[f32]) {
+        let channels = self.filters.len();
+        if channels == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(channels) {
+            let mut power = 0.0;
+            for (ch, ((filter, &weight), &x)) in self
+                .filters
+                .iter_mut()
+                .zip(&self.weights)
+                .zip(frame)
+                .enumerate()
+            {
+                let y = filter.process(x as f64);
+                self.channel_power[ch] += weight * y * y;
+                power += weight * y * y;
+            }
+
+            self.pending_power += power;
+            self.pending_frames += 1;
+            self.total_power += power;
+            self.total_frames += 1;
+
+            if self.pending_frames == self.step_frames {
+                self.steps.push(self.pending_power);
+                self.pending_power = 0.0;
+                self.pending_frames = 0;
+            }
+        }
+    }
+
+    /// Mean powers of windows spanning `window_steps` consecutive steps.
+    fn window_powers(&self, window_steps: usize) -> Vec<f64> {
+        let frames = (window_steps * self.step_frames) as f64;
+        self.steps
+            .windows(window_steps)
+            .map(|w| w.iter().sum::<f64>() / frames)
+            .collect()
+    }
+
+    /// Start in seconds of the loudest window of `window_secs`, or `None`
+    /// when the audio is shorter than the window.
+    pub fn loudest_window(&self, window_secs: f64) -> Option<f64> {
+        let window_steps = self.steps_in(window_secs).max(1);
+        if self.steps.len() < window_steps {
+            return None;
+        }
+        let mut sum: f64 = self.steps[..window_steps].iter().sum();
+        let (mut best, mut best_sum) = (0, sum);
+        for start in 1..=self.steps.len() - window_steps {
+            sum += self.steps[start + window_steps - 1] - self.steps[start - 1];
+            if sum > best_sum {
+                (best, best_sum) = (start, sum);
+            }
+        }
+        Some(best as f64 * STEP_SECS)
+    }
+
+    fn steps_in(&self, secs: f64) -> usize {
+        (secs / STEP_SECS).round() as usize
+    }
+
+    /// Share of each channel in the loudness sum, from 0 to 1; all zero for
+    /// silence.
+    pub fn channel_shares(&self) -> Vec<f64> {
+        self.channel_power
+            .iter()
+            .map(|&p| {
+                if self.total_power > 0.0 {
+                    p / self.total_power
+                } else {
+                    0.0
+                }
+            })
+            .collect()
+    }
+
+    /// Gated integrated loudness in LUFS.
+    pub fn integrated(&self) -> f64 {
+        if self.total_frames == 0 {
+            return SILENCE_LUFS;
+        }
+
+        let blocks = self.window_powers(self.steps_in(BLOCK_SECS));
+        if blocks.is_empty() {
+            // Too short for a single gating block: report the ungated loudness
+            return power_to_lufs(self.total_power / self.total_frames as f64);
+        }
+
+        gated_loudness(&blocks)
+    }
+
+    /// Maximum short-term (3 s) loudness in LUFS.
+    pub fn short_term_max(&self) -> f64 {
+        let windows = self.window_powers(self.steps_in(SHORT_TERM_SECS));
+        if windows.is_empty() {
+            return self.integrated();
+        }
+        windows
+            .into_iter()
+            .map(power_to_lufs)
+            .fold(SILENCE_LUFS, f64::max)
+    }
+
+    /// Maximum momentary (400 ms) loudness in LUFS.
+    pub fn momentary_max(&self) -> f64 {
+        let blocks = self.window_powers(self.steps_in(BLOCK_SECS));
+        if blocks.is_empty() {
+            return self.integrated();
+        }
+        blocks
+            .into_iter()
+            .map(power_to_lufs)
+            .fold(SILENCE_LUFS, f64::max)
+    }
+
+    /// Loudness range in LU: the spread between the 10th and 95th
+    /// percentiles of gated short-term loudness.
+    pub fn loudness_range(&self) -> f64 {
+        self.loudness_range_bounds()
+            .map_or(0.0, |(low, high)| high - low)
+    }
+
+    /// The 10th and 95th percentiles of gated short-term loudness in LUFS,
+    /// the low and high ends of the loudness range; `None` when nothing is
+    /// above the absolute gate.
+    pub fn loudness_range_bounds(&self) -> Option<(f64, f64)> {
+        let windows = self.window_powers(self.steps_in(SHORT_TERM_SECS));
+        let above_abs: Vec<f64> = windows
+            .into_iter()
+            .filter(|&p| power_to_lufs(p) > ABSOLUTE_GATE_LUFS)
+            .collect();
+        if above_abs.is_empty() {
+            return None;
+        }
+
+        let mean = above_abs.iter().sum::<f64>() / above_abs.len() as f64;
+        let relative_gate = power_to_lufs(mean) + LRA_RELATIVE_GATE_LU;
+        let mut gated: Vec<f64> = above_abs
+            .into_iter()
+            .map(power_to_lufs)
+            .filter(|&l| l > relative_gate)
+            .collect();
+        gated.sort_by(f64::total_cmp);
+
+        let percentile = |q: f64| gated[((gated.len() - 1) as f64 * q).round() as usize];
+        Some((percentile(LRA_PERCENTILES.0), percentile(LRA_PERCENTILES.1)))
+    }
+}
+
+/// Momentary and short-term loudness sampled over a track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoudnessTimeline {
+    /// Seconds between consecutive points.
+    pub step_secs: f64,
+    /// Time of each point in seconds; each value covers the window ending here.
+    pub times: Vec<f64>,
+    /// Momentary (400 ms) loudness in LUFS.
+    pub momentary: Vec<f64>,
+    /// Short-term (3 s) loudness in LUFS.
+    pub short_term: Vec<f64>,
+}
+
+impl PowerSeries {
+    /// Momentary and short-term loudness every `step_secs` seconds.
+    ///
+    /// Windows that would start before the beginning of the track are
+    /// truncated, so the first points measure whatever audio is available.
+    pub fn timeline(&self, step_secs: f64) -> LoudnessTimeline {
+        let step = self.secs_to_frames(step_secs).max(1);
+        let momentary_window = self.secs_to_frames(BLOCK_SECS).max(1);
+        let short_term_window = self.secs_to_frames(SHORT_TERM_SECS).max(1);
+        let frames = self.frames();
+
+        let mut timeline = LoudnessTimeline {
+            step_secs: step as f64 / self.sample_rate as f64,
+            times: Vec::new(),
+            momentary: Vec::new(),
+            short_term: Vec::new(),
+        };
+
+        let mut end = step.min(frames);
+        while end > 0 {
+            let window_lufs =
+                |window: usize| power_to_lufs(self.mean(end.saturating_sub(window), end));
+            timeline.times.push(end as f64 / self.sample_rate as f64);
+            timeline.momentary.push(window_lufs(momentary_window));
+            timeline.short_term.push(window_lufs(short_term_window));
+
+            if end == frames {
+                break;
+            }
+            end = (end + step).min(frames);
+        }
+        timeline
+    }
+}
+
+/// Apply the BS.1770 absolute and relative gates to block powers.
+fn gated_loudness(blocks: &[f64]) -> f64 {
+    let mean_of = |powers: &[f64]| powers.iter().sum::<f64>() / powers.len() as f64;
+
+    let above_abs: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|&p| power_to_lufs(p) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if above_abs.is_empty() {
+        return SILENCE_LUFS;
+    }
+
+    let relative_gate = power_to_lufs(mean_of(&above_abs)) + RELATIVE_GATE_LU;
+    let gated: Vec<f64> = above_abs
+        .into_iter()
+        .filter(|&p| power_to_lufs(p) > relative_gate)
+        .collect();
+    if gated.is_empty() {
+        return SILENCE_LUFS;
+    }
+
+    power_to_lufs(mean_of(&gated))
+}
+
+/// Gated loudness of several measurements taken as one programme, e.g. the
+/// tracks of an album.
+pub fn combined_loudness(meters: &[LoudnessMeter]) -> f64 {
+    let blocks: Vec<f64> = meters
+        .iter()
+        .flat_map(|m| m.window_powers(m.steps_in(BLOCK_SECS)))
+        .collect();
+    if blocks.is_empty() {
+        return SILENCE_LUFS;
+    }
+    gated_loudness(&blocks)
+}
+
+/// Integrated loudness of decoded audio in LUFS.
+pub fn integrated_loudness(audio: &DecodedAudio) -> f64 {
+    PowerSeries::new(audio).integrated()
+}
+
+/// Loudness timeline of decoded audio, sampled every `step_secs` seconds.
+pub fn loudness_timeline(audio: &DecodedAudio, step_secs: f64) -> LoudnessTimeline {
+    PowerSeries::new(audio).timeline(step_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, amplitude: f64, secs: f64, sample_rate: u32, channels: u16) -> DecodedAudio {
+        let frames = (sample_rate as f64 * secs) as usize;
+        let mut samples = Vec::with_capacity(frames * channels as usize);
+        for i in 0..frames {
+            let t = i as f64 / sample_rate as f64;
+            let s = (amplitude * (2.0 * std::f64::consts::PI * freq * t).sin()) as f32;
+            for _ in 0..channels {
+                samples.push(s);
+            }
+        }
+        DecodedAudio::new(samples, sample_rate, channels)
+    }
+
+    /// BS.1770: a 0 dBFS 1 kHz sine on a single channel reads -3.01 LKFS.
+    #[test]
+    fn test_full_scale_sine_single_channel() {
+        let audio = sine(997.0, 1.0, 5.0, 48000, 1);
+        let lufs = integrated_loudness(&audio);
+        assert!((lufs - (-3.01)).abs() < 0.1, "got {lufs}");
+    }
+
+    /// EBU Tech 3341 case 1: stereo 1 kHz sine at -23 dBFS reads -23 LUFS.
+    #[test]
+    fn test_stereo_minus_23() {
+        let amplitude = 10f64.powf(-23.0 / 20.0);
+        let audio = sine(1000.0, amplitude, 20.0, 48000, 2);
+        let lufs = integrated_loudness(&audio);
+        assert!((lufs - (-23.0)).abs() < 0.1, "got {lufs}");
+    }
+
+    #[test]
+    fn test_sample_rate_independent() {
+        let a = integrated_loudness(&sine(1000.0, 0.25, 5.0, 44100, 2));
+        let b = integrated_loudness(&sine(1000.0, 0.25, 5.0, 96000, 2));
+        assert!((a - b).abs() < 0.05, "44.1k={a} 96k={b}");
+    }
+
+    /// Low frequencies are attenuated by the RLB high-pass.
+    #[test]
+    fn test_k_weighting_attenuates_lows() {
+        let low = integrated_loudness(&sine(30.0, 0.5, 5.0, 48000, 2));
+        let mid = integrated_loudness(&sine(1000.0, 0.5, 5.0, 48000, 2));
+        assert!(low < mid - 1.0, "30 Hz={low} 1 kHz={mid}");
+    }
+
+    /// Quiet passages more than 10 LU below the programme are gated out.
+    #[test]
+    fn test_relative_gate() {
+        let loud = sine(1000.0, 10f64.powf(-20.0 / 20.0), 10.0, 48000, 2);
+        let quiet = sine(1000.0, 10f64.powf(-50.0 / 20.0), 10.0, 48000, 2);
+        let mut samples = loud.samples.clone();
+        samples.extend_from_slice(&quiet.samples);
+        let audio = DecodedAudio::new(samples, 48000, 2);
+        let lufs = integrated_loudness(&audio);
+        assert!((lufs - (-20.0)).abs() < 0.2, "got {lufs}");
+    }
+
+    #[test]
+    fn test_timeline_tracks_level_change() {
+        let loud = sine(1000.0, 10f64.powf(-20.0 / 20.0), 5.0, 48000, 2);
+        let quiet = sine(1000.0, 10f64.powf(-40.0 / 20.0), 5.0, 48000, 2);
+        let mut samples = loud.samples.clone();
+        samples.extend_from_slice(&quiet.samples);
+        let audio = DecodedAudio::new(samples, 48000, 2);
+
+        let timeline = loudness_timeline(&audio, STEP_SECS);
+        assert_eq!(timeline.times.len(), 100);
+        assert!((timeline.times.last().unwrap() - 10.0).abs() < 1e-9);
+
+        // 4 s in: both windows see only the loud section
+        assert!((timeline.momentary[39] - (-20.0)).abs() < 0.2);
+        assert!((timeline.short_term[39] - (-20.0)).abs() < 0.2);
+        // 9 s in: both windows see only the quiet section
+        assert!((timeline.momentary[89] - (-40.0)).abs() < 0.2);
+        assert!((timeline.short_term[89] - (-40.0)).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_meter_matches_power_series() {
+        let loud = sine(1000.0, 0.3, 6.0, 44100, 2);
+        let quiet = sine(200.0, 0.01, 6.0, 44100, 2);
+        let mut samples = loud.samples.clone();
+        samples.extend_from_slice(&quiet.samples);
+        let audio = DecodedAudio::new(samples, 44100, 2);
+
+        // Odd chunk sizes exercise steps that straddle chunk boundaries
+        let mut meter = LoudnessMeter::new(44100, &audio.layout);
+        for chunk in audio.samples.chunks(2 * 1153) {
+            meter.push(chunk);
+        }
+
+        let series = PowerSeries::new(&audio);
+        assert!((meter.integrated() - series.integrated()).abs() < 1e-6);
+        assert!((meter.short_term_max() - series.short_term_max()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_loudest_window_finds_loud_section() {
+        let quiet = sine(1000.0, 0.01, 4.0, 8000, 1);
+        let loud = sine(1000.0, 0.5, 2.0, 8000, 1);
+        let mut samples = quiet.samples.clone();
+        samples.extend_from_slice(&loud.samples);
+        samples.extend_from_slice(&quiet.samples);
+
+        let mut meter = LoudnessMeter::new(8000, &ChannelLayout::default_for(1));
+        meter.push(&samples);
+        let start = meter.loudest_window(2.0).unwrap();
+        assert!((start - 4.0).abs() < 1e-9, "{start}");
+        assert_eq!(meter.loudest_window(20.0), None);
+    }
+
+    #[test]
+    fn test_loudness_range_spans_level_change() {
+        // 10 s at -20 dBFS then 10 s at -30 dBFS: a 10 LU range
+        let loud = sine(1000.0, 0.1, 10.0, 8000, 1);
+        let quiet = sine(1000.0, 0.0316, 10.0, 8000, 1);
+        let mut samples = loud.samples.clone();
+        samples.extend_from_slice(&quiet.samples);
+
+        let mut meter = LoudnessMeter::new(8000, &ChannelLayout::default_for(1));
+        meter.push(&samples);
+        let lra = meter.loudness_range();
+        assert!((lra - 10.0).abs() < 0.5, "{lra}");
+        assert!(meter.momentary_max() >= meter.short_term_max());
+        let (low, high) = meter.loudness_range_bounds().unwrap();
+        assert!((high - low - lra).abs() < 1e-9);
+        assert!((high - meter.short_term_max()).abs() < 0.5, "{high}");
+        assert_eq!(
+            LoudnessMeter::new(8000, &ChannelLayout::default_for(1)).loudness_range_bounds(),
+            None
+        );
+
+        let mut steady = LoudnessMeter::new(8000, &ChannelLayout::default_for(1));
+        steady.push(&loud.samples);
+        assert!(steady.loudness_range() < 0.1);
+    }
+
+    /// Surrounds of a 5.1 mix are weighted +1.5 dB and the LFE is ignored.
+    #[test]
+    fn test_surround_channel_weighting() {
+        let amplitude = 10f64.powf(-23.0 / 20.0);
+        let tone = sine(1000.0, amplitude, 5.0, 48000, 1).samples;
+        let surround = |active: &[usize]| {
+            let samples = tone
+                .iter()
+                .flat_map(|&s| (0..6).map(move |ch| if active.contains(&ch) { s } else { 0.0 }))
+                .collect();
+            integrated_loudness(&DecodedAudio::new(samples, 48000, 6))
+        };
+
+        let front = surround(&[0]);
+        assert!((surround(&[4]) - front - 1.49).abs() < 0.05);
+        assert!(surround(&[3]) < -69.0);
+    }
+}
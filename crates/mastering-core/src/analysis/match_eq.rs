@@ -0,0 +1,480 @@
+//! Match EQ: a few EQ bands that move a track's tonal balance toward a
+//! reference.
+//!
+//! Both spectra are reduced to third-octave levels relative to their mean,
+//! so only the shape of the spectrum counts, not its level. Their difference
+//! is then fitted greedily with shelves and peaks, each placed on the largest
+//! remaining deviation. Unlike full reference matching, the result is an
+//! ordinary set of [`EqBand`]s rendered by the DSP chain.
+//!
+//! A target may also be a curve file: JSON as written by
+//! [`SpectralCurve::save`], or CSV with a frequency and a level per line.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::decode::{self, DecodedAudio};
+use super::spectrum::{compute_spectrum, power_to_db, Spectrum};
+use crate::dsp::eq::Biquad;
+use crate::types::{EqBand, EqBandType, EqChannel};
+
+/// FFT size of the measured spectra; large enough to resolve the lowest
+/// third-octave bands.
+const FFT_SIZE: usize = 16384;
+
+/// Lowest and highest band centres in Hz.
+const MIN_HZ: f64 = 20.0;
+const MAX_HZ: f64 = 20000.0;
+
+/// Bands per octave of a measured curve.
+const BANDS_PER_OCTAVE: f64 = 3.0;
+
+/// Levels at or below this are treated as silence.
+const SILENCE_DB: f64 = -99.0;
+
+/// Most bands a match adds.
+pub const MAX_BANDS: usize = 6;
+
+/// Largest boost or cut of a single band in dB.
+pub const MAX_GAIN_DB: f64 = 6.0;
+
+/// Deviations smaller than this are left alone, in dB.
+const MIN_GAIN_DB: f64 = 0.5;
+
+/// Deviations confined to below this frequency become a low shelf.
+const LOW_SHELF_MAX_HZ: f64 = 150.0;
+
+/// Deviations confined to above this frequency become a high shelf.
+const HIGH_SHELF_MIN_HZ: f64 = 6000.0;
+
+/// Smoothed spectral balance: level per band relative to the mean of all
+/// bands, in dB. A curve may also be written by hand, with any points.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpectralCurve {
+    /// Band centres in Hz, ascending.
+    pub frequencies: Vec<f64>,
+    pub levels_db: Vec<f64>,
+}
+
+impl SpectralCurve {
+    /// Third-octave curve of an averaged spectrum.
+    pub fn from_spectrum(spectrum: &Spectrum) -> Self {
+        let nyquist = spectrum.sample_rate as f64 / 2.0;
+        let half_band = 2f64.powf(0.5 / BANDS_PER_OCTAVE);
+        let bin_width = spectrum.bin_frequency(1);
+
+        let mut frequencies = Vec::new();
+        let mut levels_db = Vec::new();
+        for band in 0.. {
+            let centre = MIN_HZ * 2f64.powf(band as f64 / BANDS_PER_OCTAVE);
+            if centre > MAX_HZ.min(nyquist / half_band) {
+                break;
+            }
+            let lo = ((centre / half_band / bin_width).ceil() as usize).max(1);
+            let hi =
+                ((centre * half_band / bin_width).floor() as usize).min(spectrum.power.len() - 1);
+            // Narrow low bands may fall between bins; use the nearest one
+            let power = if lo <= hi {
+                spectrum.power[lo..=hi].iter().sum::<f64>() / (hi + 1 - lo) as f64
+            } else {
+                spectrum.power
+                    [((centre / bin_width).round() as usize).min(spectrum.power.len() - 1)]
+            };
+            frequencies.push(centre);
+            levels_db.push(power_to_db(power));
+        }
+
+        let mut curve = Self {
+            frequencies,
+            levels_db,
+        };
+        curve.normalize();
+        curve
+    }
+
+    /// Curve of decoded audio's mono mixdown.
+    pub fn from_audio(audio: &DecodedAudio) -> Self {
+        Self::from_spectrum(&compute_spectrum(audio, FFT_SIZE))
+    }
+
+    /// Curve of an audio file, or of a curve file.
+    pub fn measure(path: &Path) -> Result<Self> {
+        if is_curve_file(path) {
+            return Self::load(path);
+        }
+        let audio = decode::decode_audio(path)?;
+        let curve = Self::from_audio(&audio);
+        anyhow::ensure!(
+            !curve.is_silent(),
+            "{} is silent, so it has no tonal balance",
+            path.display()
+        );
+        Ok(curve)
+    }
+
+    /// Load a curve file, CSV when the extension says so and JSON otherwise.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Reading spectral curve: {}", path.display()))?;
+        let curve = if path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("csv"))
+        {
+            Self::from_csv(&text)
+        } else {
+            serde_json::from_str(&text).map_err(Into::into)
+        };
+        let mut curve =
+            curve.with_context(|| format!("Parsing spectral curve: {}", path.display()))?;
+        anyhow::ensure!(
+            !curve.frequencies.is_empty() && curve.frequencies.len() == curve.levels_db.len(),
+            "Spectral curve {} needs one level per frequency",
+            path.display()
+        );
+        anyhow::ensure!(
+            curve.frequencies.windows(2).all(|w| w[0] < w[1]),
+            "Spectral curve {} needs ascending frequencies",
+            path.display()
+        );
+        curve.normalize();
+        Ok(curve)
+    }
+
+    /// Parse `frequency,level_db` lines. Blank lines, `#` comments and a
+    /// header line that is not numeric are skipped.
+    pub fn from_csv(text: &str) -> Result<Self> {
+        let mut frequencies = Vec::new();
+        let mut levels_db = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.split([',', ';', '\t']).map(str::trim);
+            let (Some(frequency), Some(level)) = (fields.next(), fields.next()) else {
+                anyhow::bail!("Line {}: expected a frequency and a level", i + 1);
+            };
+            match (frequency.parse::<f64>(), level.parse::<f64>()) {
+                (Ok(frequency), Ok(level)) => {
+                    frequencies.push(frequency);
+                    levels_db.push(level);
+                }
+                // A header, e.g. "frequency,db"
+                _ if frequencies.is_empty() => continue,
+                _ => anyhow::bail!("Line {}: '{line}' is not a frequency and a level", i + 1),
+            }
+        }
+        Ok(Self {
+            frequencies,
+            levels_db,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Writing spectral curve: {}", path.display()))
+    }
+
+    /// Level at `frequency`, interpolated on a log-frequency scale and held
+    /// flat past the ends of the curve.
+    pub fn level_at(&self, frequency: f64) -> f64 {
+        let f = &self.frequencies;
+        let Some(upper) = f.iter().position(|&x| x >= frequency) else {
+            return *self.levels_db.last().unwrap_or(&0.0);
+        };
+        if upper == 0 {
+            return self.levels_db[0];
+        }
+        let t = (frequency / f[upper - 1]).ln() / (f[upper] / f[upper - 1]).ln();
+        self.levels_db[upper - 1] + t * (self.levels_db[upper] - self.levels_db[upper - 1])
+    }
+
+    fn is_silent(&self) -> bool {
+        self.levels_db.iter().all(|&db| db <= SILENCE_DB)
+    }
+
+    /// Shift the levels so their mean is 0 dB.
+    fn normalize(&mut self) {
+        if self.levels_db.is_empty() || self.is_silent() {
+            return;
+        }
+        let mean = self.levels_db.iter().sum::<f64>() / self.levels_db.len() as f64;
+        self.levels_db.iter_mut().for_each(|db| *db -= mean);
+    }
+}
+
+/// Whether `path` names a curve file rather than audio.
+pub fn is_curve_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("json") || e.eq_ignore_ascii_case("csv"))
+}
+
+/// Tonal balance for the EQ stage to move toward.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchEqTarget {
+    pub curve: SpectralCurve,
+    /// Share of the difference to correct, from 0 (none) to 1 (all of it).
+    pub strength: f64,
+}
+
+/// How far a track's tonal balance is from a target curve.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurveComparison {
+    /// Band centres of the track's curve in Hz.
+    pub frequencies: Vec<f64>,
+    /// Track level minus target level per band; positive where the track
+    /// has more energy than the target.
+    pub deviations_db: Vec<f64>,
+    /// RMS of the deviations.
+    pub rms_deviation_db: f64,
+}
+
+impl CurveComparison {
+    /// Mean deviation of the bands whose centre lies in `[f_low, f_high)`.
+    pub fn range_deviation_db(&self, f_low: f64, f_high: f64) -> f64 {
+        let in_range: Vec<f64> = self
+            .frequencies
+            .iter()
+            .zip(&self.deviations_db)
+            .filter(|(&f, _)| f >= f_low && f < f_high)
+            .map(|(_, &db)| db)
+            .collect();
+        if in_range.is_empty() {
+            return 0.0;
+        }
+        in_range.iter().sum::<f64>() / in_range.len() as f64
+    }
+}
+
+/// Compare `input` with `target`, both as relative levels.
+pub fn compare(input: &SpectralCurve, target: &SpectralCurve) -> CurveComparison {
+    let mut deviations_db: Vec<f64> = input
+        .frequencies
+        .iter()
+        .zip(&input.levels_db)
+        .map(|(&f, &db)| db - target.level_at(f))
+        .collect();
+    // Level offsets between the curves are not part of the balance
+    let mean = deviations_db.iter().sum::<f64>() / deviations_db.len().max(1) as f64;
+    deviations_db.iter_mut().for_each(|db| *db -= mean);
+    let rms_deviation_db = (deviations_db.iter().map(|db| db * db).sum::<f64>()
+        / deviations_db.len().max(1) as f64)
+        .sqrt();
+    CurveComparison {
+        frequencies: input.frequencies.clone(),
+        deviations_db,
+        rms_deviation_db,
+    }
+}
+
+/// EQ bands that move `input` toward `target` by `strength` (0 to 1) of
+/// the difference.
+///
+/// At most [`MAX_BANDS`] bands of at most ±[`MAX_GAIN_DB`] are returned,
+/// none for a silent input.
+pub fn match_bands(
+    input: &SpectralCurve,
+    target: &SpectralCurve,
+    strength: f64,
+    sample_rate: u32,
+) -> Vec<EqBand> {
+    if input.is_silent() {
+        return Vec::new();
+    }
+    let strength = strength.clamp(0.0, 1.0);
+    let difference = SpectralCurve {
+        frequencies: input.frequencies.clone(),
+        levels_db: input
+            .frequencies
+            .iter()
+            .zip(&input.levels_db)
+            .map(|(&f, &db)| strength * (target.level_at(f) - db))
+            .collect(),
+    };
+    fit_bands(&difference, sample_rate)
+}
+
+/// Fit `difference` with shelves and peaks, largest deviation first.
+fn fit_bands(difference: &SpectralCurve, sample_rate: u32) -> Vec<EqBand> {
+    let freqs = &difference.frequencies;
+    let mut residual = difference.levels_db.clone();
+    // Only the shape counts; the loudness stage sets the level
+    let mean = residual.iter().sum::<f64>() / residual.len().max(1) as f64;
+    residual.iter_mut().for_each(|db| *db -= mean);
+
+    let last = residual.len().saturating_sub(1);
+    let mut bands = Vec::new();
+    while bands.len() < MAX_BANDS {
+        let Some((peak, &peak_db)) = residual
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+        else {
+            break;
+        };
+        if peak_db.abs() < MIN_GAIN_DB {
+            break;
+        }
+
+        // The deviation spans its neighbours of the same sign above half its size
+        let within = |i: usize| {
+            residual[i].signum() == peak_db.signum() && residual[i].abs() >= peak_db.abs() / 2.0
+        };
+        let mut lo = peak;
+        while lo > 0 && within(lo - 1) {
+            lo -= 1;
+        }
+        let mut hi = peak;
+        while hi < last && within(hi + 1) {
+            hi += 1;
+        }
+        let mean_db = residual[lo..=hi].iter().sum::<f64>() / (hi + 1 - lo) as f64;
+
+        let band = if lo == 0 && freqs[peak] <= LOW_SHELF_MAX_HZ {
+            EqBand {
+                frequency: freqs[hi],
+                gain_db: mean_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB),
+                q: 0.707,
+                band_type: EqBandType::LowShelf,
+                channel: EqChannel::Stereo,
+            }
+        } else if hi == last && freqs[peak] >= HIGH_SHELF_MIN_HZ {
+            EqBand {
+                frequency: freqs[lo],
+                gain_db: mean_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB),
+                q: 0.707,
+                band_type: EqBandType::HighShelf,
+                channel: EqChannel::Stereo,
+            }
+        } else {
+            // A bandwidth of N octaves has Q = sqrt(2^N) / (2^N - 1)
+            let octaves = (freqs[hi] / freqs[lo]).log2() + 1.0 / BANDS_PER_OCTAVE;
+            let ratio = 2f64.powf(octaves);
+            EqBand {
+                frequency: freqs[peak],
+                gain_db: peak_db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB),
+                q: (ratio.sqrt() / (ratio - 1.0)).clamp(0.3, 4.0),
+                band_type: EqBandType::Peak,
+                channel: EqChannel::Stereo,
+            }
+        };
+
+        let filter = Biquad::from_band(&band, sample_rate);
+        for (db, &f) in residual.iter_mut().zip(freqs) {
+            *db -= filter.magnitude_db(f, sample_rate);
+        }
+        bands.push(band);
+    }
+    bands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::spectrum::compute_spectrum_mono;
+
+    /// Deterministic white noise.
+    fn noise(len: usize) -> Vec<f32> {
+        let mut state = 0x9e3779b97f4a7c15u64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+            })
+            .collect()
+    }
+
+    fn curve(samples: &[f32]) -> SpectralCurve {
+        SpectralCurve::from_spectrum(&compute_spectrum_mono(samples, 44100, FFT_SIZE))
+    }
+
+    #[test]
+    fn test_identical_curves_need_no_bands() {
+        let flat = curve(&noise(44100 * 2));
+        assert!(flat.levels_db.iter().all(|db| db.abs() < 3.0), "{flat:?}");
+        assert!(match_bands(&flat, &flat, 1.0, 44100).is_empty());
+    }
+
+    #[test]
+    fn test_bump_becomes_peak_band() {
+        let frequencies: Vec<f64> = (0..31).map(|i| 20.0 * 2f64.powf(i as f64 / 3.0)).collect();
+        let input = SpectralCurve {
+            levels_db: vec![0.0; frequencies.len()],
+            frequencies: frequencies.clone(),
+        };
+        // 4 dB more around 1 kHz in the target
+        let target = SpectralCurve {
+            levels_db: frequencies
+                .iter()
+                .map(|f| 4.0 * (-(f / 1000.0).log2().powi(2) * 2.0).exp())
+                .collect(),
+            frequencies,
+        };
+        let bands = match_bands(&input, &target, 1.0, 44100);
+        let first = &bands[0];
+        assert!(matches!(first.band_type, EqBandType::Peak), "{bands:?}");
+        assert!((first.frequency - 1000.0).abs() < 100.0, "{bands:?}");
+        assert!(
+            first.gain_db > 2.5 && first.gain_db <= MAX_GAIN_DB,
+            "{bands:?}"
+        );
+        assert!(bands.len() <= MAX_BANDS);
+
+        let half = match_bands(&input, &target, 0.5, 44100);
+        assert!(
+            (half[0].gain_db - first.gain_db / 2.0).abs() < 0.5,
+            "{half:?}"
+        );
+        assert!(match_bands(&input, &target, 0.0, 44100).is_empty());
+
+        let comparison = compare(&input, &target);
+        assert!(
+            comparison.range_deviation_db(800.0, 1300.0) < -2.0,
+            "{comparison:?}"
+        );
+        assert!(comparison.rms_deviation_db > 0.5);
+    }
+
+    #[test]
+    fn test_darker_target_cuts_highs() {
+        let bright = noise(44100 * 2);
+        let mut dark = bright.clone();
+        let mut y = 0.0;
+        for s in &mut dark {
+            y += 0.3 * (*s - y);
+            *s = y;
+        }
+        let bands = match_bands(&curve(&bright), &curve(&dark), 1.0, 44100);
+        assert!(!bands.is_empty());
+        assert!(bands.iter().all(|b| b.gain_db.abs() <= MAX_GAIN_DB));
+        let high_cut: f64 = bands
+            .iter()
+            .map(|b| Biquad::from_band(b, 44100).magnitude_db(10000.0, 44100))
+            .sum();
+        assert!(high_cut < -3.0, "{bands:?}");
+    }
+
+    #[test]
+    fn test_csv_curve() {
+        let curve =
+            SpectralCurve::from_csv("frequency,db\n# modern pop\n100, 3\n1000,0\n\n10000;-3\n")
+                .unwrap();
+        assert_eq!(curve.frequencies, [100.0, 1000.0, 10000.0]);
+        assert!((curve.level_at(3162.28) + 1.5).abs() < 1e-3);
+        assert!(SpectralCurve::from_csv("100,3\nloud,0\n").is_err());
+    }
+
+    #[test]
+    fn test_saved_curve_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("curve.json");
+        let saved = curve(&noise(44100));
+        saved.save(&path).unwrap();
+        let loaded = SpectralCurve::measure(&path).unwrap();
+        assert_eq!(loaded.frequencies.len(), saved.frequencies.len());
+        assert!((loaded.level_at(1000.0) - saved.level_at(1000.0)).abs() < 1e-9);
+    }
+}
@@ -1,394 +1,978 @@
 use anyhow::Result;
 use std::path::Path;
 
-use super::decode::DecodedAudio;
-use crate::types::{AudioAnalysis, AudioMetadata, FrequencyBands};
+use super::channels::ChannelLayout;
+use super::decode::{AudioStream, DecodedAudio};
+use super::loudness::LoudnessMeter;
+use super::spectrum::{self, Spectrum, SpectrumAccumulator};
+use super::tempo::TempoDetector;
+use super::true_peak::TruePeakMeter;
+use crate::types::{
+    AudioAnalysis, AudioMetadata, ChannelStats, ClippedRegion, ClippingReport, EbuMode,
+    FrequencyBands, MonoCompatibility, SilenceReport, SilentGap, SpectralDescriptors,
+    DEFAULT_SILENCE_THRESHOLD_DB,
+};
 
 /// Compute full audio analysis from decoded samples.
 pub fn analyze(path: &Path, audio: &DecodedAudio) -> Result<AudioAnalysis> {
-    let format = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("unknown")
-        .to_uppercase();
-
-    let metadata = AudioMetadata {
-        path: path.to_path_buf(),
-        sample_rate: audio.sample_rate,
-        channels: audio.channels,
-        duration_secs: audio.duration_secs(),
-        bit_depth: None,
-        format,
-    };
-
-    let rms_db = compute_rms_db(&audio.samples);
-    let peak_db = compute_peak_db(&audio.samples);
-    let true_peak_db = peak_db + 0.2; // simplified true-peak estimation
-    let lufs_integrated = compute_lufs(audio);
-    let lufs_short_term_max = compute_short_term_lufs_max(audio);
-    let dynamic_range_db = compute_dynamic_range(audio);
-    let stereo_width = compute_stereo_width(audio);
-    let frequency_bands = compute_frequency_bands(audio);
-
-    Ok(AudioAnalysis {
-        metadata,
-        lufs_integrated,
-        lufs_short_term_max,
-        rms_db,
-        peak_db,
-        true_peak_db,
-        dynamic_range_db,
-        stereo_width,
-        frequency_bands,
-    })
+    let mut metrics = MetricsAccumulator::new(audio.sample_rate, &audio.layout);
+    metrics.push(&audio.samples);
+    Ok(metrics.finish(path))
 }
 
-/// RMS level in dB.
-fn compute_rms_db(samples: &[f32]) -> f64 {
-    if samples.is_empty() {
-        return -100.0;
-    }
-    let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
-    let rms = (sum_sq / samples.len() as f64).sqrt();
-    if rms < 1e-10 {
-        -100.0
-    } else {
-        20.0 * rms.log10()
+/// Compute full audio analysis from a streaming decoder with bounded memory.
+pub fn analyze_stream(path: &Path, stream: &mut AudioStream) -> Result<AudioAnalysis> {
+    let mut metrics = MetricsAccumulator::new(stream.sample_rate(), stream.layout());
+    while let Some(chunk) = stream.next_chunk()? {
+        metrics.push(chunk);
     }
+    Ok(metrics.finish(path))
 }
 
-/// Peak level in dB.
+/// Accumulates every analysis metric over interleaved sample chunks.
+pub struct MetricsAccumulator {
+    sample_rate: u32,
+    channels: u16,
+    layout: ChannelLayout,
+    frames: u64,
+    levels: LevelStats,
+    channel_levels: Vec<LevelStats>,
+    clipping: ClipDetector,
+    loudness: LoudnessMeter,
+    true_peak: TruePeakMeter,
+    dynamic_range: DynamicRange,
+    noise_floor: NoiseFloor,
+    silence: SilenceDetector,
+    stereo: StereoImage,
+    spectrum: SpectrumAccumulator,
+    tempo: TempoDetector,
+    mono: Vec<f32>,
+}
 
-fn compute_peak_db(samples: &[f32]) -> f64 {
-    let peak = samples
-        .iter()
-        .map(|s| s.abs())
-        .fold(0.0f32, f32::max) as f64;
-    if peak < 1e-10 {
-        -100.0
-    } else {
-        20.0 * peak.log10()
+impl MetricsAccumulator {
+    pub fn new(sample_rate: u32, layout: &ChannelLayout) -> Self {
+        let channels = layout.channels() as u16;
+        Self {
+            sample_rate,
+            channels,
+            layout: layout.clone(),
+            frames: 0,
+            levels: LevelStats::default(),
+            channel_levels: (0..channels).map(|_| LevelStats::default()).collect(),
+            clipping: ClipDetector::new(sample_rate, channels),
+            loudness: LoudnessMeter::new(sample_rate, layout),
+            true_peak: TruePeakMeter::new(channels),
+            dynamic_range: DynamicRange::new(sample_rate, channels),
+            noise_floor: NoiseFloor::new(sample_rate, channels),
+            silence: SilenceDetector::new(sample_rate, channels),
+            stereo: StereoImage::new(sample_rate, layout),
+            spectrum: SpectrumAccumulator::new(sample_rate, spectrum::DEFAULT_FFT_SIZE),
+            tempo: TempoDetector::new(sample_rate),
+            mono: Vec::new(),
+        }
     }
-}
 
-/// Simplified ITU-R BS.1770 loudness measurement.
-/// Full implementation requires K-weighting filter; this is a practical approximation.
+    /// Feed interleaved samples containing whole frames.
+    pub fn push(&mut self, samples: &[f32]) {
+        let channels = self.channels as usize;
+        if channels == 0 {
+            return;
+        }
+        self.frames += (samples.len() / channels) as u64;
 
-fn compute_lufs(audio: &DecodedAudio) -> f64 {
-    let channels = audio.channels as usize;
-    if audio.samples.is_empty() || channels == 0 {
-        return -100.0;
+        self.levels.push(samples);
+        for frame in samples.chunks_exact(channels) {
+            for (levels, &s) in self.channel_levels.iter_mut().zip(frame) {
+                levels.push_sample(s);
+            }
+        }
+        self.clipping.push(samples);
+        self.loudness.push(samples);
+        self.true_peak.push(samples);
+        self.dynamic_range.push(samples);
+        self.noise_floor.push(samples);
+        self.silence.push(samples);
+        self.stereo.push(samples);
+
+        self.mono.clear();
+        self.mono.extend(
+            samples
+                .chunks_exact(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+        self.spectrum.push(&self.mono);
+        self.tempo.push(&self.mono);
     }
 
-    // K-weighting approximation: apply simple high-shelf boost
-    // For a proper implementation we'd use a biquad filter chain,
-    // but this gives reasonable results for analysis purposes.
-    let samples = &audio.samples;
-    let frame_count = samples.len() / channels;
-
-    // Gating block size: 400ms
-    let block_size = (audio.sample_rate as f64 * 0.4) as usize;
-    let hop_size = block_size / 4; // 75% overlap
+    pub fn finish(self, path: &Path) -> AudioAnalysis {
+        let format = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("unknown")
+            .to_uppercase();
+
+        let metadata = AudioMetadata {
+            path: path.to_path_buf(),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            channel_layout: self.layout.name(),
+            duration_secs: self.frames as f64 / self.sample_rate as f64,
+            bit_depth: None,
+            format,
+        };
 
-    if frame_count < block_size {
-        // Too short for proper gating, return simple RMS-based estimate
-        let rms_db = compute_rms_db(samples);
-        return rms_db - 0.691; // approximate K-weighting offset
+        let mut stereo = self.stereo;
+        stereo.flush();
+        let rms_db = self.levels.rms_db();
+        let noise_floor_db = self.noise_floor.finish(rms_db);
+        let spectrum = self.spectrum.finish();
+        let range_bounds = self.loudness.loudness_range_bounds();
+        let ebu_mode = EbuMode {
+            integrated_lufs: self.loudness.integrated(),
+            loudness_range_lu: range_bounds.map_or(0.0, |(low, high)| high - low),
+            loudness_range_low_lufs: range_bounds.map(|(low, _)| low),
+            loudness_range_high_lufs: range_bounds.map(|(_, high)| high),
+            momentary_max_lufs: self.loudness.momentary_max(),
+            short_term_max_lufs: self.loudness.short_term_max(),
+            true_peak_max_dbtp: self.true_peak.peak_db(),
+        };
+        let channel_stats = self
+            .channel_levels
+            .iter()
+            .zip(self.loudness.channel_shares())
+            .enumerate()
+            .map(|(ch, (levels, loudness_share))| ChannelStats {
+                name: self.layout.channel_name(ch),
+                peak_db: levels.peak_db(),
+                rms_db: levels.rms_db(),
+                loudness_share,
+            })
+            .collect();
+        AudioAnalysis {
+            metadata,
+            lufs_integrated: ebu_mode.integrated_lufs,
+            lufs_short_term_max: ebu_mode.short_term_max_lufs,
+            lufs_momentary_max: ebu_mode.momentary_max_lufs,
+            loudness_range_lu: ebu_mode.loudness_range_lu,
+            rms_db,
+            peak_db: self.levels.peak_db(),
+            true_peak_db: ebu_mode.true_peak_max_dbtp,
+            dynamic_range_db: self.dynamic_range.finish(),
+            stereo_width: stereo.width(),
+            stereo_balance_db: stereo.balance_db(),
+            phase_correlation: stereo.correlation(),
+            phase_correlation_min: stereo.correlation_min(),
+            out_of_phase_secs: stereo.out_of_phase_secs(),
+            mono_compatibility: stereo.mono_compatibility(),
+            clipping: self.clipping.finish(),
+            noise_floor_db,
+            signal_to_noise_db: noise_floor_db.map(|floor| rms_db - floor),
+            silence: self.silence.finish(),
+            tempo_bpm: self.tempo.finish(),
+            frequency_bands: bands_from_spectrum(&spectrum),
+            spectral: descriptors_from_spectrum(&spectrum),
+            channel_stats,
+            ebu_mode,
+        }
     }
+}
 
-    let mut block_loudness: Vec<f64> = Vec::new();
-
-    let mut pos = 0;
-    while pos + block_size <= frame_count {
-        let mut sum_sq = 0.0f64;
-        let mut count = 0usize;
+/// Running RMS and sample peak over the samples pushed.
+#[derive(Debug, Default)]
+struct LevelStats {
+    sum_sq: f64,
+    count: u64,
+    peak: f32,
+}
 
-        for frame_idx in pos..pos + block_size {
-            for ch in 0..channels {
-                let sample = samples[frame_idx * channels + ch] as f64;
-                sum_sq += sample * sample;
-                count += 1;
-            }
+impl LevelStats {
+    fn push(&mut self, samples: &[f32]) {
+        for &s in samples {
+            self.push_sample(s);
         }
+    }
+
+    fn push_sample(&mut self, s: f32) {
+        self.sum_sq += (s as f64) * (s as f64);
+        self.peak = self.peak.max(s.abs());
+        self.count += 1;
+    }
 
-        let mean_sq = sum_sq / count as f64;
-        if mean_sq > 0.0 {
-            let loudness = -0.691 + 10.0 * mean_sq.log10();
-            block_loudness.push(loudness);
+    /// RMS level in dB.
+    fn rms_db(&self) -> f64 {
+        if self.count == 0 {
+            return -100.0;
         }
+        let rms = (self.sum_sq / self.count as f64).sqrt();
+        if rms < 1e-10 {
+            -100.0
+        } else {
+            20.0 * rms.log10()
+        }
+    }
 
-        pos += hop_size;
+    /// Peak level in dB.
+    fn peak_db(&self) -> f64 {
+        let peak = self.peak as f64;
+        if peak < 1e-10 {
+            -100.0
+        } else {
+            20.0 * peak.log10()
+        }
     }
+}
 
-    if block_loudness.is_empty() {
-        return -100.0;
+/// Sample magnitude counted as full scale, about -0.001 dBFS so the largest
+/// positive 16-bit sample counts.
+const CLIP_LEVEL: f32 = 0.9999;
+
+/// Consecutive full-scale samples that make a clipped region; single
+/// full-scale samples are legitimate peaks.
+const CLIP_MIN_RUN: u64 = 3;
+
+/// Clipped regions listed individually in the report.
+const CLIP_WORST_REGIONS: usize = 5;
+
+/// Clipped regions, found as runs of full-scale samples in each channel.
+#[derive(Debug)]
+struct ClipDetector {
+    sample_rate: u32,
+    channels: usize,
+    frame: u64,
+    /// Start frame of the full-scale run in progress, per channel.
+    run_start: Vec<Option<u64>>,
+    /// Finished regions as (channel, start frame, length in frames).
+    regions: Vec<(u16, u64, u64)>,
+}
+
+impl ClipDetector {
+    fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels: channels as usize,
+            frame: 0,
+            run_start: vec![None; channels as usize],
+            regions: Vec::new(),
+        }
     }
 
-    // Absolute gating threshold: -70 LUFS
-    let above_abs_gate: Vec<f64> = block_loudness
-        .iter()
-        .copied()
-        .filter(|&l| l > -70.0)
-        .collect();
+    fn push(&mut self, samples: &[f32]) {
+        if self.channels == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, &s) in frame.iter().enumerate() {
+                if s.abs() >= CLIP_LEVEL {
+                    self.run_start[channel].get_or_insert(self.frame);
+                } else if let Some(start) = self.run_start[channel].take() {
+                    self.end_run(channel, start);
+                }
+            }
+            self.frame += 1;
+        }
+    }
 
-    if above_abs_gate.is_empty() {
-        return -100.0;
+    fn end_run(&mut self, channel: usize, start: u64) {
+        let len = self.frame - start;
+        if len >= CLIP_MIN_RUN {
+            self.regions.push((channel as u16, start, len));
+        }
     }
 
-    // Relative gating threshold: mean of above absolute gate - 10 LU
-    let mean_above: f64 = above_abs_gate.iter().sum::<f64>() / above_abs_gate.len() as f64;
-    let relative_gate = mean_above - 10.0;
+    fn finish(mut self) -> ClippingReport {
+        for channel in 0..self.channels {
+            if let Some(start) = self.run_start[channel].take() {
+                self.end_run(channel, start);
+            }
+        }
 
-    let gated: Vec<f64> = above_abs_gate
-        .into_iter()
-        .filter(|&l| l > relative_gate)
-        .collect();
+        // Union of the regions, so channels clipping together count once
+        let mut spans: Vec<(u64, u64)> = self
+            .regions
+            .iter()
+            .map(|&(_, start, len)| (start, start + len))
+            .collect();
+        spans.sort_unstable();
+        let (mut clipped_frames, mut covered) = (0, 0);
+        for (start, end) in spans {
+            let start = start.max(covered);
+            if end > start {
+                clipped_frames += end - start;
+                covered = end;
+            }
+        }
 
-    if gated.is_empty() {
-        return -100.0;
+        let secs = |frames: u64| frames as f64 / self.sample_rate as f64;
+        let mut regions = self.regions;
+        regions.sort_by_key(|&(channel, start, len)| (std::cmp::Reverse(len), start, channel));
+        ClippingReport {
+            regions: regions.len() as u64,
+            clipped_samples: regions.iter().map(|&(_, _, len)| len).sum(),
+            clipped_secs: secs(clipped_frames),
+            worst: regions
+                .iter()
+                .take(CLIP_WORST_REGIONS)
+                .map(|&(channel, start, len)| ClippedRegion {
+                    channel,
+                    start_secs: secs(start),
+                    duration_secs: secs(len),
+                })
+                .collect(),
+        }
     }
-
-    gated.iter().sum::<f64>() / gated.len() as f64
 }
 
-/// Maximum short-term loudness (3-second window).
+/// Dynamic range: difference between peak loudness of loud and quiet sections.
+///
+/// Collects the RMS of consecutive 0.5 s windows; only completed windows count.
+#[derive(Debug)]
+struct DynamicRange {
+    channels: usize,
+    window: usize,
+    frames_in_window: usize,
+    sum_sq: f64,
+    window_rms: Vec<f64>,
+}
 
-fn compute_short_term_lufs_max(audio: &DecodedAudio) -> f64 {
-    let channels = audio.channels as usize;
-    if audio.samples.is_empty() || channels == 0 {
-        return -100.0;
+impl DynamicRange {
+    fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            channels: channels as usize,
+            window: ((sample_rate as f64 * 0.5) as usize).max(1),
+            frames_in_window: 0,
+            sum_sq: 0.0,
+            window_rms: Vec::new(),
+        }
     }
 
-    let frame_count = audio.samples.len() / channels;
-    let window_size = (audio.sample_rate as f64 * 3.0) as usize;
-    let hop_size = (audio.sample_rate as f64 * 1.0) as usize;
-
-    if frame_count < window_size {
-        return compute_lufs(audio);
+    fn push(&mut self, samples: &[f32]) {
+        if self.channels == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            self.sum_sq += frame.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>();
+            self.frames_in_window += 1;
+
+            if self.frames_in_window == self.window {
+                let rms = (self.sum_sq / (self.window * self.channels) as f64).sqrt();
+                if rms > 1e-10 {
+                    self.window_rms.push(20.0 * rms.log10());
+                }
+                self.frames_in_window = 0;
+                self.sum_sq = 0.0;
+            }
+        }
     }
 
-    let mut max_loudness = -100.0f64;
-    let mut pos = 0;
+    fn finish(mut self) -> f64 {
+        let window_rms = &mut self.window_rms;
+        if window_rms.len() < 2 {
+            return 0.0;
+        }
 
-    while pos + window_size <= frame_count {
-        let mut sum_sq = 0.0f64;
-        let mut count = 0usize;
+        window_rms.sort_by(|a, b| a.partial_cmp(b).unwrap());
 
-        for frame_idx in pos..pos + window_size {
-            for ch in 0..channels {
-                let sample = audio.samples[frame_idx * channels + ch] as f64;
-                sum_sq += sample * sample;
-                count += 1;
-            }
-        }
+        let top_10 = &window_rms[window_rms.len() * 9 / 10..];
+        let bottom_10 = &window_rms[..window_rms.len() / 10];
 
-        let mean_sq = sum_sq / count as f64;
-        if mean_sq > 0.0 {
-            let loudness = -0.691 + 10.0 * mean_sq.log10();
-            if loudness > max_loudness {
-                max_loudness = loudness;
-            }
+        if top_10.is_empty() || bottom_10.is_empty() {
+            return 0.0;
         }
 
-        pos += hop_size;
+        let top_avg: f64 = top_10.iter().sum::<f64>() / top_10.len() as f64;
+        let bottom_avg: f64 = bottom_10.iter().sum::<f64>() / bottom_10.len() as f64;
+
+        (top_avg - bottom_avg).abs()
     }
+}
 
-    max_loudness
+/// Length of the windows the noise floor is measured in, in seconds.
+const NOISE_WINDOW_SECS: f64 = 0.05;
+
+/// Consecutive noise windows making up one segment (0.5 s).
+const NOISE_SEGMENT_WINDOWS: usize = 10;
+
+/// Largest level spread in dB between the windows of a segment for it to
+/// count as stable: steady hiss or room tone rather than speech or music.
+const NOISE_STABLE_SPREAD_DB: f64 = 6.0;
+
+/// How far below the overall RMS a stable segment must be to count as a
+/// pause rather than a sustained note.
+const NOISE_BELOW_SIGNAL_DB: f64 = 20.0;
+
+/// Share of the quiet stable segments, quietest first, the noise floor is
+/// averaged over.
+const NOISE_QUIETEST_SHARE: f64 = 0.1;
+
+/// Mean square below which a window is digital silence rather than noise
+/// (-120 dBFS).
+const DIGITAL_SILENCE: f64 = 1e-12;
+
+/// Noise floor: the level of the quietest stable segments.
+///
+/// Keeps the mean square of every stable 0.5 s segment; `finish` keeps the
+/// ones well below the overall level and averages the quietest of those.
+#[derive(Debug)]
+struct NoiseFloor {
+    channels: usize,
+    window: usize,
+    frames_in_window: usize,
+    sum_sq: f64,
+    segment: Vec<f64>,
+    stable: Vec<f64>,
 }
 
-/// Dynamic range: difference between peak loudness of loud and quiet sections.
+impl NoiseFloor {
+    fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            channels: channels as usize,
+            window: ((sample_rate as f64 * NOISE_WINDOW_SECS) as usize).max(1),
+            frames_in_window: 0,
+            sum_sq: 0.0,
+            segment: Vec::with_capacity(NOISE_SEGMENT_WINDOWS),
+            stable: Vec::new(),
+        }
+    }
 
-fn compute_dynamic_range(audio: &DecodedAudio) -> f64 {
-    let channels = audio.channels as usize;
-    if audio.samples.is_empty() || channels == 0 {
-        return 0.0;
+    fn push(&mut self, samples: &[f32]) {
+        if self.channels == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            self.sum_sq += frame.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>();
+            self.frames_in_window += 1;
+
+            if self.frames_in_window == self.window {
+                self.segment
+                    .push(self.sum_sq / (self.window * self.channels) as f64);
+                self.frames_in_window = 0;
+                self.sum_sq = 0.0;
+                if self.segment.len() == NOISE_SEGMENT_WINDOWS {
+                    self.end_segment();
+                }
+            }
+        }
     }
 
-    let frame_count = audio.samples.len() / channels;
-    let window = (audio.sample_rate as f64 * 0.5) as usize;
+    fn end_segment(&mut self) {
+        let min = self.segment.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = self.segment.iter().copied().fold(0.0, f64::max);
+        if min > DIGITAL_SILENCE && max <= min * 10f64.powf(NOISE_STABLE_SPREAD_DB / 10.0) {
+            self.stable
+                .push(self.segment.iter().sum::<f64>() / self.segment.len() as f64);
+        }
+        self.segment.clear();
+    }
 
-    if frame_count < window {
-        return 0.0;
+    /// Noise floor in dBFS given the overall RMS level; `None` without a
+    /// quiet stable segment.
+    fn finish(mut self, rms_db: f64) -> Option<f64> {
+        let ceiling = 10f64.powf((rms_db - NOISE_BELOW_SIGNAL_DB) / 10.0);
+        self.stable.retain(|&ms| ms <= ceiling);
+        if self.stable.is_empty() {
+            return None;
+        }
+        self.stable.sort_by(|a, b| a.total_cmp(b));
+        let count = ((self.stable.len() as f64 * NOISE_QUIETEST_SHARE).ceil() as usize).max(1);
+        let mean = self.stable[..count].iter().sum::<f64>() / count as f64;
+        Some(10.0 * mean.log10())
     }
+}
 
-    let mut window_rms: Vec<f64> = Vec::new();
-    let mut pos = 0;
+/// Shortest silence inside the track reported as a gap, in seconds.
+const SILENCE_GAP_MIN_SECS: f64 = 2.0;
+
+/// Silence at the start and end and long gaps in between; a frame is
+/// silent when every sample stays below [`DEFAULT_SILENCE_THRESHOLD_DB`].
+#[derive(Debug)]
+struct SilenceDetector {
+    sample_rate: u32,
+    channels: usize,
+    threshold: f32,
+    min_gap: u64,
+    frame: u64,
+    first_sound: Option<u64>,
+    last_sound: Option<u64>,
+    /// Gaps as (start frame, length in frames).
+    gaps: Vec<(u64, u64)>,
+}
 
-    while pos + window <= frame_count {
-        let mut sum_sq = 0.0f64;
-        let mut count = 0usize;
+impl SilenceDetector {
+    fn new(sample_rate: u32, channels: u16) -> Self {
+        Self {
+            sample_rate,
+            channels: channels as usize,
+            threshold: 10f64.powf(DEFAULT_SILENCE_THRESHOLD_DB / 20.0) as f32,
+            min_gap: (sample_rate as f64 * SILENCE_GAP_MIN_SECS) as u64,
+            frame: 0,
+            first_sound: None,
+            last_sound: None,
+            gaps: Vec::new(),
+        }
+    }
 
-        for frame_idx in pos..pos + window {
-            for ch in 0..channels {
-                let s = audio.samples[frame_idx * channels + ch] as f64;
-                sum_sq += s * s;
-                count += 1;
+    fn push(&mut self, samples: &[f32]) {
+        if self.channels == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channels) {
+            if frame.iter().any(|s| s.abs() > self.threshold) {
+                match self.last_sound {
+                    Some(last) => {
+                        let gap = self.frame - last - 1;
+                        if gap >= self.min_gap {
+                            self.gaps.push((last + 1, gap));
+                        }
+                    }
+                    None => self.first_sound = Some(self.frame),
+                }
+                self.last_sound = Some(self.frame);
             }
+            self.frame += 1;
         }
+    }
 
-        let rms = (sum_sq / count as f64).sqrt();
-        if rms > 1e-10 {
-            window_rms.push(20.0 * rms.log10());
+    fn finish(self) -> SilenceReport {
+        let secs = |frames: u64| frames as f64 / self.sample_rate as f64;
+        let (leading, trailing) = match (self.first_sound, self.last_sound) {
+            (Some(first), Some(last)) => (first, self.frame - last - 1),
+            _ => (self.frame, 0),
+        };
+        SilenceReport {
+            leading_secs: secs(leading),
+            trailing_secs: secs(trailing),
+            gaps: self
+                .gaps
+                .iter()
+                .map(|&(start, len)| SilentGap {
+                    start_secs: secs(start),
+                    duration_secs: secs(len),
+                })
+                .collect(),
         }
-
-        pos += window;
     }
+}
 
-    if window_rms.len() < 2 {
-        return 0.0;
-    }
+/// Length of the windows of the correlation-over-time curve, in seconds.
+const CORRELATION_WINDOW_SECS: f64 = 0.5;
+
+/// Windowed correlation below which left and right count as out of phase.
+const OUT_OF_PHASE_CORRELATION: f64 = -0.2;
+
+/// Shortest out-of-phase stretch counted as sustained, in seconds; brief
+/// dips are normal for wide reverbs and effects.
+const OUT_OF_PHASE_MIN_SECS: f64 = 2.0;
+
+/// Stereo image: width (0.0 = mono, 1.0 = full stereo, >1.0 = out-of-phase
+/// content), the level difference between left and right, and how the mix
+/// survives being folded down to mono.
+///
+/// Surround material is measured on its BS.775 stereo downmix.
+struct StereoImage {
+    channels: usize,
+    downmix: Option<Vec<(f32, f32)>>,
+    sum_mid_sq: f64,
+    sum_side_sq: f64,
+    sum_left_sq: f64,
+    sum_right_sq: f64,
+    sum_left_right: f64,
+    window_frames: usize,
+    window: Correlation,
+    correlation_over_time: Vec<f64>,
+    correlation_min: Option<f64>,
+    out_of_phase_run: usize,
+    out_of_phase_windows: usize,
+    mid_spectrum: SpectrumAccumulator,
+    side_spectrum: SpectrumAccumulator,
+    mid: Vec<f32>,
+    side: Vec<f32>,
+}
 
-    window_rms.sort_by(|a, b| a.partial_cmp(b).unwrap());
+/// Running sums for the correlation of left and right over one window.
+#[derive(Debug, Default)]
+struct Correlation {
+    frames: usize,
+    left_sq: f64,
+    right_sq: f64,
+    left_right: f64,
+}
 
-    let top_10 = &window_rms[window_rms.len() * 9 / 10..];
-    let bottom_10 = &window_rms[..window_rms.len() / 10];
+impl Correlation {
+    fn push(&mut self, left: f64, right: f64) {
+        self.frames += 1;
+        self.left_sq += left * left;
+        self.right_sq += right * right;
+        self.left_right += left * right;
+    }
 
-    if top_10.is_empty() || bottom_10.is_empty() {
-        return 0.0;
+    /// Pearson correlation from -1 (out of phase) to 1 (mono); 0 in silence.
+    fn coefficient(&self) -> f64 {
+        correlation(self.left_right, self.left_sq, self.right_sq)
     }
 
-    let top_avg: f64 = top_10.iter().sum::<f64>() / top_10.len() as f64;
-    let bottom_avg: f64 = bottom_10.iter().sum::<f64>() / bottom_10.len() as f64;
+    fn is_silent(&self) -> bool {
+        self.left_sq * self.right_sq < 1e-20
+    }
+}
 
-    (top_avg - bottom_avg).abs()
+fn correlation(left_right: f64, left_sq: f64, right_sq: f64) -> f64 {
+    let norm = (left_sq * right_sq).sqrt();
+    if norm < 1e-20 {
+        return 0.0;
+    }
+    (left_right / norm).clamp(-1.0, 1.0)
 }
 
-/// Stereo width: 0.0 = mono, 1.0 = full stereo, >1.0 = out-of-phase content.
+impl StereoImage {
+    fn new(sample_rate: u32, layout: &ChannelLayout) -> Self {
+        Self {
+            channels: layout.channels(),
+            downmix: layout.is_surround().then(|| layout.downmix_gains()),
+            sum_mid_sq: 0.0,
+            sum_side_sq: 0.0,
+            sum_left_sq: 0.0,
+            sum_right_sq: 0.0,
+            sum_left_right: 0.0,
+            window_frames: ((sample_rate as f64 * CORRELATION_WINDOW_SECS) as usize).max(1),
+            window: Correlation::default(),
+            correlation_over_time: Vec::new(),
+            correlation_min: None,
+            out_of_phase_run: 0,
+            out_of_phase_windows: 0,
+            mid_spectrum: SpectrumAccumulator::new(sample_rate, spectrum::DEFAULT_FFT_SIZE),
+            side_spectrum: SpectrumAccumulator::new(sample_rate, spectrum::DEFAULT_FFT_SIZE),
+            mid: Vec::new(),
+            side: Vec::new(),
+        }
+    }
 
-fn compute_stereo_width(audio: &DecodedAudio) -> f64 {
-    if audio.channels < 2 {
-        return 0.0;
+    fn push(&mut self, samples: &[f32]) {
+        if self.channels < 2 {
+            return;
+        }
+        self.mid.clear();
+        self.side.clear();
+        for frame in samples.chunks_exact(self.channels) {
+            let (left, right) = match self.downmix {
+                Some(ref gains) => frame
+                    .iter()
+                    .zip(gains)
+                    .fold((0.0, 0.0), |(l, r), (&x, &(gl, gr))| {
+                        (l + (x * gl) as f64, r + (x * gr) as f64)
+                    }),
+                None => (frame[0] as f64, frame[1] as f64),
+            };
+
+            let mid = (left + right) * 0.5;
+            let side = (left - right) * 0.5;
+
+            self.sum_mid_sq += mid * mid;
+            self.sum_side_sq += side * side;
+            self.sum_left_sq += left * left;
+            self.sum_right_sq += right * right;
+            self.sum_left_right += left * right;
+            self.mid.push(mid as f32);
+            self.side.push(side as f32);
+
+            self.window.push(left, right);
+            if self.window.frames == self.window_frames {
+                self.close_window();
+            }
+        }
+        self.mid_spectrum.push(&self.mid);
+        self.side_spectrum.push(&self.side);
     }
 
-    let channels = audio.channels as usize;
-    let frame_count = audio.samples.len() / channels;
+    fn width(&self) -> f64 {
+        if self.channels < 2 {
+            return 0.0;
+        }
 
-    let mut sum_mid_sq = 0.0f64;
-    let mut sum_side_sq = 0.0f64;
+        if self.sum_mid_sq < 1e-20 {
+            return if self.sum_side_sq > 1e-20 { 2.0 } else { 0.0 };
+        }
 
-    for i in 0..frame_count {
-        let left = audio.samples[i * channels] as f64;
-        let right = audio.samples[i * channels + 1] as f64;
+        let ratio = self.sum_side_sq / self.sum_mid_sq;
+        // Map to 0..1 range approximately: ratio of 1.0 means full stereo
+        ratio.sqrt().min(2.0)
+    }
 
-        let mid = (left + right) * 0.5;
-        let side = (left - right) * 0.5;
+    /// Energy of the left channel over the right in dB; positive when the
+    /// left is louder, 0 for mono or silence.
+    fn balance_db(&self) -> f64 {
+        if self.channels < 2 || self.sum_left_sq < 1e-20 && self.sum_right_sq < 1e-20 {
+            return 0.0;
+        }
+        let db = 10.0 * ((self.sum_left_sq + 1e-20) / (self.sum_right_sq + 1e-20)).log10();
+        db.clamp(-BALANCE_LIMIT_DB, BALANCE_LIMIT_DB)
+    }
 
-        sum_mid_sq += mid * mid;
-        sum_side_sq += side * side;
+    /// Record the correlation of the current window and start the next.
+    fn close_window(&mut self) {
+        let window = std::mem::take(&mut self.window);
+        let coefficient = window.coefficient();
+        self.correlation_over_time.push(coefficient);
+        if window.is_silent() {
+            return;
+        }
+        self.correlation_min = Some(
+            self.correlation_min
+                .map_or(coefficient, |m| m.min(coefficient)),
+        );
+        if coefficient < OUT_OF_PHASE_CORRELATION {
+            self.out_of_phase_run += 1;
+        } else {
+            self.end_out_of_phase_run();
+        }
     }
 
-    if sum_mid_sq < 1e-20 {
-        return if sum_side_sq > 1e-20 { 2.0 } else { 0.0 };
+    fn end_out_of_phase_run(&mut self) {
+        let min_windows = (OUT_OF_PHASE_MIN_SECS / CORRELATION_WINDOW_SECS).round() as usize;
+        if self.out_of_phase_run >= min_windows {
+            self.out_of_phase_windows += self.out_of_phase_run;
+        }
+        self.out_of_phase_run = 0;
     }
 
-    let ratio = sum_side_sq / sum_mid_sq;
-    // Map to 0..1 range approximately: ratio of 1.0 means full stereo
-    ratio.sqrt().min(2.0)
-}
+    /// Close the trailing partial window, which counts once it covers half a
+    /// window, and any out-of-phase stretch running to the end.
+    fn flush(&mut self) {
+        if self.window.frames * 2 >= self.window_frames {
+            self.close_window();
+        }
+        self.end_out_of_phase_run();
+    }
 
-/// Compute energy in 7 frequency bands using a basic DFT approach.
+    /// Lowest correlation of any window with signal; 1 for mono.
+    fn correlation_min(&self) -> f64 {
+        self.correlation_min.unwrap_or(1.0)
+    }
 
-fn compute_frequency_bands(audio: &DecodedAudio) -> FrequencyBands {
-    // Use mono mixdown
-    let mono: Vec<f64> = if audio.channels >= 2 {
-        let ch = audio.channels as usize;
-        let frames = audio.samples.len() / ch;
-        (0..frames)
-            .map(|i| {
-                let mut sum = 0.0f64;
-                for c in 0..ch {
-                    sum += audio.samples[i * ch + c] as f64;
+    /// Total length of sustained out-of-phase stretches in seconds.
+    fn out_of_phase_secs(&self) -> f64 {
+        self.out_of_phase_windows as f64 * CORRELATION_WINDOW_SECS
+    }
+
+    /// Correlation of left and right over the whole signal; 1 for mono.
+    fn correlation(&self) -> f64 {
+        if self.channels < 2 {
+            return 1.0;
+        }
+        correlation(self.sum_left_right, self.sum_left_sq, self.sum_right_sq)
+    }
+
+    /// Band losses of the mono fold-down and the correlation curve; `None`
+    /// for mono input.
+    fn mono_compatibility(self) -> Option<MonoCompatibility> {
+        if self.channels < 2 {
+            return None;
+        }
+        let mid = self.mid_spectrum.finish();
+        let side = self.side_spectrum.finish();
+        // L + R = 2M and L² + R² = 2(M² + S²), so the fold-down keeps M² / (M² + S²)
+        let floor =
+            (mid.power.iter().sum::<f64>() + side.power.iter().sum::<f64>()) * MONO_BAND_FLOOR;
+        let losses: Vec<f64> = BANDS
+            .iter()
+            .map(|&(f_low, f_high)| {
+                let mid = mid.band_power(f_low, f_high);
+                let total = mid + side.band_power(f_low, f_high);
+                if total < floor.max(1e-20) {
+                    0.0
+                } else {
+                    (-10.0 * (mid.max(1e-30) / total).log10()).min(MONO_LOSS_LIMIT_DB)
                 }
-                sum / ch as f64
             })
-            .collect()
-    } else {
-        audio.samples.iter().map(|&s| s as f64).collect()
-    };
+            .collect();
 
-    if mono.is_empty() {
-        return FrequencyBands {
-            sub_bass: -100.0,
-            bass: -100.0,
-            low_mid: -100.0,
-            mid: -100.0,
-            upper_mid: -100.0,
-            presence: -100.0,
-            brilliance: -100.0,
-        };
+        Some(MonoCompatibility {
+            band_loss_db: FrequencyBands {
+                sub_bass: losses[0],
+                bass: losses[1],
+                low_mid: losses[2],
+                mid: losses[3],
+                upper_mid: losses[4],
+                presence: losses[5],
+                brilliance: losses[6],
+            },
+            correlation_over_time: self.correlation_over_time,
+            correlation_window_secs: CORRELATION_WINDOW_SECS,
+        })
     }
+}
 
-    let sr = audio.sample_rate as f64;
+/// Largest mono fold-down loss reported; the band has all but cancelled.
+const MONO_LOSS_LIMIT_DB: f64 = 60.0;
 
-    // Band boundaries in Hz
-    let bands: [(f64, f64); 7] = [
-        (20.0, 60.0),      // Sub-bass
-        (60.0, 250.0),     // Bass
-        (250.0, 500.0),    // Low-mid
-        (500.0, 2000.0),   // Mid
-        (2000.0, 4000.0),  // Upper-mid
-        (4000.0, 6000.0),  // Presence
-        (6000.0, 20000.0), // Brilliance
-    ];
+/// Share of the total energy (-60 dB) below which a band counts as empty,
+/// so window leakage into it is not mistaken for cancellation.
+const MONO_BAND_FLOOR: f64 = 1e-6;
 
-    // Use Goertzel-like energy estimation on overlapping windows
-    let window_size = 4096.min(mono.len());
-    let num_windows = (mono.len() / window_size).max(1);
+/// Fold-down loss in dB above which a band is flagged.
+const MONO_LOSS_WARN_DB: f64 = 6.0;
 
-    let mut band_energies = [0.0f64; 7];
+/// Fold-down loss in dB above which the low end is flagged: club systems
+/// sum the bass to mono.
+const MONO_LOW_END_LOSS_WARN_DB: f64 = 3.0;
 
-    for w in 0..num_windows {
-        let start = w * window_size;
-        let end = (start + window_size).min(mono.len());
-        let segment = &mono[start..end];
-        let n = segment.len();
+/// Whether one channel of a stereo input looks polarity-inverted: the
+/// channels are anti-correlated overall and out of phase for at least half
+/// the track. A polarity flip of one channel fixes such a file.
+pub fn is_polarity_inverted(analysis: &AudioAnalysis) -> bool {
+    analysis.mono_compatibility.is_some()
+        && analysis.phase_correlation < 0.0
+        && analysis.out_of_phase_secs * 2.0 >= analysis.metadata.duration_secs
+}
 
-        // Simple DFT energy for each band
-        for (band_idx, &(f_low, f_high)) in bands.iter().enumerate() {
-            let k_low = ((f_low * n as f64) / sr).round() as usize;
-            let k_high = ((f_high * n as f64) / sr).round() as usize;
-            let k_high = k_high.min(n / 2);
+/// Warnings for a mix that will collapse badly when played in mono, as on
+/// club systems and phone speakers. Empty for mono input and mixes that
+/// fold down cleanly.
+pub fn mono_compatibility_warnings(analysis: &AudioAnalysis) -> Vec<String> {
+    let Some(ref mono) = analysis.mono_compatibility else {
+        return Vec::new();
+    };
+    let mut warnings = Vec::new();
+    if is_polarity_inverted(analysis) {
+        warnings.push(format!(
+            "Left and right are out of phase (correlation {:.2}); one channel's polarity is probably inverted",
+            analysis.phase_correlation
+        ));
+    } else if analysis.out_of_phase_secs > 0.0 {
+        warnings.push(format!(
+            "Left and right are out of phase for {:.1}s (lowest correlation {:.2}); those passages cancel in mono",
+            analysis.out_of_phase_secs, analysis.phase_correlation_min
+        ));
+    } else if analysis.phase_correlation < 0.0 {
+        warnings.push(format!(
+            "Left and right are mostly out of phase (correlation {:.2}); the mix cancels in mono",
+            analysis.phase_correlation
+        ));
+    }
+    let bands = &mono.band_loss_db;
+    let named = [
+        ("Sub-bass", bands.sub_bass, MONO_LOW_END_LOSS_WARN_DB),
+        ("Bass", bands.bass, MONO_LOW_END_LOSS_WARN_DB),
+        ("Low-mid", bands.low_mid, MONO_LOSS_WARN_DB),
+        ("Mid", bands.mid, MONO_LOSS_WARN_DB),
+        ("Upper-mid", bands.upper_mid, MONO_LOSS_WARN_DB),
+        ("Presence", bands.presence, MONO_LOSS_WARN_DB),
+        ("Brilliance", bands.brilliance, MONO_LOSS_WARN_DB),
+    ];
+    for (name, loss, limit) in named {
+        if loss > limit {
+            warnings.push(format!(
+                "{name} drops {loss:.1} dB when folded down to mono"
+            ));
+        }
+    }
+    warnings
+}
 
-            if k_low >= k_high {
-                continue;
-            }
+/// Warning for input that is already clipped: mastering makes the
+/// distortion louder and cannot restore the flattened peaks. `None` when no
+/// clipped region was found.
+pub fn clipping_warning(analysis: &AudioAnalysis) -> Option<String> {
+    let clipping = &analysis.clipping;
+    let worst = clipping.worst.first()?;
+    Some(format!(
+        "Input is already clipped: {} region(s) covering {:.2}s, the longest at {:.2}s; mastering will make the distortion louder",
+        clipping.regions, clipping.clipped_secs, worst.start_secs
+    ))
+}
 
-            // Compute energy at a few representative frequencies in the band
-            let num_probes = 8.min(k_high - k_low);
-            let step = ((k_high - k_low) as f64 / num_probes as f64).max(1.0) as usize;
-
-            let mut energy = 0.0f64;
-            let mut k = k_low;
-            while k < k_high {
-                // Goertzel algorithm for single DFT bin
-                let omega = 2.0 * std::f64::consts::PI * k as f64 / n as f64;
-                let coeff = 2.0 * omega.cos();
-                let mut s0 = 0.0f64;
-                let mut s1 = 0.0f64;
-                let mut s2;
-
-                for &sample in segment.iter() {
-                    s2 = s1;
-                    s1 = s0;
-                    s0 = sample + coeff * s1 - s2;
-                }
+/// Peak level in dB at or below which a channel counts as dead.
+const DEAD_CHANNEL_DB: f64 = -90.0;
 
-                let power = s0 * s0 + s1 * s1 - coeff * s0 * s1;
-                energy += power;
+/// RMS level in dB by which a channel may trail the loudest one before it
+/// is flagged as quiet.
+const QUIET_CHANNEL_DB: f64 = 12.0;
 
-                k += step.max(1);
+/// Warnings for channels that are silent or far quieter than the others,
+/// usually a wiring or export mistake that mastering cannot fix. The LFE
+/// is only checked for silence. Empty for mono input.
+pub fn channel_warnings(analysis: &AudioAnalysis) -> Vec<String> {
+    let stats = &analysis.channel_stats;
+    if stats.len() < 2 {
+        return Vec::new();
+    }
+    let loudest = stats
+        .iter()
+        .filter(|c| c.name != "LFE")
+        .map(|c| c.rms_db)
+        .fold(f64::NEG_INFINITY, f64::max);
+    if loudest <= DEAD_CHANNEL_DB {
+        return Vec::new();
+    }
+    stats
+        .iter()
+        .filter_map(|c| {
+            if c.peak_db <= DEAD_CHANNEL_DB {
+                Some(format!("{} channel is silent", c.name))
+            } else if c.name != "LFE" && loudest - c.rms_db > QUIET_CHANNEL_DB {
+                Some(format!(
+                    "{} channel is {:.1} dB quieter than the loudest channel",
+                    c.name,
+                    loudest - c.rms_db
+                ))
+            } else {
+                None
             }
+        })
+        .collect()
+}
 
-            band_energies[band_idx] += energy;
-        }
-    }
+/// Signal-to-noise ratio in dB below which the noise floor is flagged.
+const NOISE_SNR_WARN_DB: f64 = 40.0;
+
+/// Warning for a recording whose noise floor sits close enough to the
+/// signal that loudness processing will make the hiss audible. `None` when
+/// no noise floor was measured or it is low enough.
+pub fn noise_floor_warning(analysis: &AudioAnalysis) -> Option<String> {
+    let (floor, snr) = (analysis.noise_floor_db?, analysis.signal_to_noise_db?);
+    (snr < NOISE_SNR_WARN_DB).then(|| {
+        format!(
+            "Noise floor at {floor:.1} dBFS is only {snr:.1} dB below the signal; loudness processing will raise the hiss with it, so consider denoising first"
+        )
+    })
+}
+
+/// Largest balance reported; a channel this much quieter is as good as silent.
+const BALANCE_LIMIT_DB: f64 = 60.0;
+
+/// Band boundaries in Hz for the 7-band summary.
+const BANDS: [(f64, f64); 7] = [
+    (20.0, 60.0),      // Sub-bass
+    (60.0, 250.0),     // Bass
+    (250.0, 500.0),    // Low-mid
+    (500.0, 2000.0),   // Mid
+    (2000.0, 4000.0),  // Upper-mid
+    (4000.0, 6000.0),  // Presence
+    (6000.0, 20000.0), // Brilliance
+];
+
+/// Summarise a spectrum into the 7 analysis bands.
+pub fn bands_from_spectrum(spectrum: &Spectrum) -> FrequencyBands {
+    let band_energies: Vec<f64> = BANDS
+        .iter()
+        .map(|&(f_low, f_high)| spectrum.band_power(f_low, f_high))
+        .collect();
 
     // Normalize and convert to dB
     let total: f64 = band_energies.iter().sum();
-    let normalize = if total > 1e-20 { total } else { 1.0 };
+    if total < 1e-20 {
+        return FrequencyBands {
+            sub_bass: -100.0,
+            bass: -100.0,
+            low_mid: -100.0,
+            mid: -100.0,
+            upper_mid: -100.0,
+            presence: -100.0,
+            brilliance: -100.0,
+        };
+    }
 
     let to_db = |e: f64| -> f64 {
-        let ratio = e / normalize;
+        let ratio = e / total;
         if ratio < 1e-20 {
             -100.0
         } else {
@@ -407,21 +991,127 @@ fn compute_frequency_bands(audio: &DecodedAudio) -> FrequencyBands {
     }
 }
 
+/// Range the spectral descriptors are computed over, in Hz.
+const DESCRIPTOR_RANGE: (f64, f64) = (20.0, 20000.0);
+
+/// Share of the power below the rolloff frequency.
+const ROLLOFF_SHARE: f64 = 0.85;
+
+/// Compute the timbral descriptors of a spectrum. The tilt is fitted to
+/// third-octave levels so the many high bins do not outweigh the low ones.
+pub fn descriptors_from_spectrum(spectrum: &Spectrum) -> SpectralDescriptors {
+    let (low, high) = DESCRIPTOR_RANGE;
+    let bins: Vec<(f64, f64)> = spectrum
+        .power
+        .iter()
+        .enumerate()
+        .map(|(bin, &p)| (spectrum.bin_frequency(bin), p))
+        .filter(|&(f, _)| f >= low && f < high)
+        .collect();
+    let total: f64 = bins.iter().map(|&(_, p)| p).sum();
+    if bins.is_empty() || total < 1e-20 {
+        return SpectralDescriptors::default();
+    }
+
+    let centroid_hz = bins.iter().map(|&(f, p)| f * p).sum::<f64>() / total;
+    let mut below = 0.0;
+    let rolloff_hz = bins
+        .iter()
+        .find(|&&(_, p)| {
+            below += p;
+            below >= ROLLOFF_SHARE * total
+        })
+        .map_or(0.0, |&(f, _)| f);
+
+    let mean = total / bins.len() as f64;
+    let log_mean = bins.iter().map(|&(_, p)| p.max(1e-20).ln()).sum::<f64>() / bins.len() as f64;
+    let flatness = (log_mean.exp() / mean).clamp(0.0, 1.0);
+
+    // Mean power per bin of each third octave against its octave position
+    let points: Vec<(f64, f64)> = (0..)
+        .map(|i| low * 2f64.powf(i as f64 / 3.0))
+        .take_while(|&f| f < high)
+        .filter_map(|f_low| {
+            let f_high = f_low * 2f64.powf(1.0 / 3.0);
+            let band: Vec<f64> = bins
+                .iter()
+                .filter(|&&(f, _)| f >= f_low && f < f_high)
+                .map(|&(_, p)| p)
+                .collect();
+            let power = band.iter().sum::<f64>() / band.len().max(1) as f64;
+            (!band.is_empty() && power > 1e-20)
+                .then(|| ((f_low * f_high).sqrt().log2(), 10.0 * power.log10()))
+        })
+        .collect();
+    let n = points.len() as f64;
+    let (mean_x, mean_y) = (
+        points.iter().map(|p| p.0).sum::<f64>() / n,
+        points.iter().map(|p| p.1).sum::<f64>() / n,
+    );
+    let covariance: f64 = points
+        .iter()
+        .map(|&(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|&(x, _)| (x - mean_x).powi(2)).sum();
+    let tilt_db_per_octave = if variance > 0.0 {
+        covariance / variance
+    } else {
+        0.0
+    };
+
+    SpectralDescriptors {
+        centroid_hz,
+        rolloff_hz,
+        flatness,
+        tilt_db_per_octave,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::decode::DecodedAudio;
     use super::*;
-    use crate::types::FrequencyBands;
 
     /// Helper to create test audio data.
     fn create_test_audio(samples: Vec<f32>, sample_rate: u32, channels: u16) -> DecodedAudio {
-        let total_frames = samples.len() as u64 / channels as u64;
-        DecodedAudio {
-            samples,
-            sample_rate,
-            channels,
-            total_frames,
-        }
+        DecodedAudio::new(samples, sample_rate, channels)
+    }
+
+    fn compute_rms_db(samples: &[f32]) -> f64 {
+        let mut levels = LevelStats::default();
+        levels.push(samples);
+        levels.rms_db()
+    }
+
+    fn compute_peak_db(samples: &[f32]) -> f64 {
+        let mut levels = LevelStats::default();
+        levels.push(samples);
+        levels.peak_db()
+    }
+
+    fn compute_lufs(audio: &DecodedAudio) -> f64 {
+        let mut meter = LoudnessMeter::new(audio.sample_rate, &audio.layout);
+        meter.push(&audio.samples);
+        meter.integrated()
+    }
+
+    fn compute_dynamic_range(audio: &DecodedAudio) -> f64 {
+        let mut dr = DynamicRange::new(audio.sample_rate, audio.channels);
+        dr.push(&audio.samples);
+        dr.finish()
+    }
+
+    fn compute_stereo_width(audio: &DecodedAudio) -> f64 {
+        let mut image = StereoImage::new(audio.sample_rate, &audio.layout);
+        image.push(&audio.samples);
+        image.width()
+    }
+
+    fn compute_frequency_bands(audio: &DecodedAudio) -> FrequencyBands {
+        bands_from_spectrum(&spectrum::compute_spectrum(
+            audio,
+            spectrum::DEFAULT_FFT_SIZE,
+        ))
     }
 
     /// Helper to create sine wave samples.
@@ -523,6 +1213,195 @@ mod tests {
         assert!(width > 0.8, "Wide stereo should have high width value");
     }
 
+    /// A channel at half the level of the other reads about 6 dB of balance.
+    #[test]
+    fn test_stereo_balance() {
+        let samples: Vec<f32> = create_sine_wave(440.0, 1.0, 48000, 0.5)
+            .into_iter()
+            .flat_map(|s| [s * 0.5, s])
+            .collect();
+        let audio = create_test_audio(samples, 48000, 2);
+        let mut image = StereoImage::new(audio.sample_rate, &audio.layout);
+        image.push(&audio.samples);
+        assert!(
+            (image.balance_db() + 6.02).abs() < 0.05,
+            "{}",
+            image.balance_db()
+        );
+    }
+
+    /// Bass in phase and treble out of phase: only the treble is lost in mono.
+    #[test]
+    fn test_mono_compatibility() {
+        let bass = create_sine_wave(100.0, 2.0, 48000, 0.4);
+        let treble = create_sine_wave(8000.0, 2.0, 48000, 0.4);
+        let samples: Vec<f32> = bass
+            .iter()
+            .zip(&treble)
+            .flat_map(|(&b, &t)| [b + t, b - t])
+            .collect();
+        let audio = create_test_audio(samples, 48000, 2);
+        let analysis = analyze(Path::new("test.wav"), &audio).unwrap();
+
+        let mono = analysis.mono_compatibility.as_ref().unwrap();
+        assert!(mono.band_loss_db.bass < 0.1, "{}", mono.band_loss_db.bass);
+        assert!(
+            mono.band_loss_db.brilliance > 40.0,
+            "{}",
+            mono.band_loss_db.brilliance
+        );
+        assert_eq!(mono.correlation_over_time.len(), 4);
+        assert!(
+            analysis.phase_correlation.abs() < 0.05,
+            "{}",
+            analysis.phase_correlation
+        );
+
+        let warnings = mono_compatibility_warnings(&analysis);
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].starts_with("Brilliance"), "{warnings:?}");
+    }
+
+    /// An inverted channel is flagged; a short out-of-phase passage is only
+    /// reported.
+    #[test]
+    fn test_out_of_phase_detection() {
+        let sine = create_sine_wave(440.0, 6.0, 48000, 0.5);
+        let inverted: Vec<f32> = sine.iter().flat_map(|&s| [s, -s]).collect();
+        let analysis = analyze(
+            Path::new("test.wav"),
+            &create_test_audio(inverted, 48000, 2),
+        )
+        .unwrap();
+        assert!(analysis.phase_correlation < -0.99);
+        assert!((analysis.out_of_phase_secs - 6.0).abs() < 1e-9);
+        assert!(is_polarity_inverted(&analysis));
+
+        // Out of phase for the last 2 of 5 seconds
+        let partial: Vec<f32> = sine[..5 * 48000]
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &s)| [s, if i < 3 * 48000 { s } else { -s }])
+            .collect();
+        let analysis =
+            analyze(Path::new("test.wav"), &create_test_audio(partial, 48000, 2)).unwrap();
+        assert!(analysis.phase_correlation_min < -0.99);
+        assert!(
+            (analysis.out_of_phase_secs - 2.0).abs() < 1e-9,
+            "{}",
+            analysis.out_of_phase_secs
+        );
+        assert!(!is_polarity_inverted(&analysis));
+        assert!(mono_compatibility_warnings(&analysis)[0].contains("for 2.0s"));
+    }
+
+    /// The noise floor is measured in the pauses; a track without pauses
+    /// has none.
+    #[test]
+    fn test_noise_floor_in_pauses() {
+        let mut state = 0x2545_f491u32;
+        let hiss: Vec<f32> = (0..48000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * 0.01
+            })
+            .collect();
+        let tone = create_sine_wave(440.0, 2.0, 48000, 0.5);
+        let samples: Vec<f32> = tone.iter().chain(&hiss).chain(&tone).copied().collect();
+        let analysis =
+            analyze(Path::new("test.wav"), &create_test_audio(samples, 48000, 1)).unwrap();
+
+        // Uniform noise of amplitude 0.01 has an RMS of 0.01 / sqrt(3), about -44.8 dBFS
+        let floor = analysis.noise_floor_db.unwrap();
+        assert!((floor + 44.8).abs() < 0.5, "{floor}");
+        let snr = analysis.signal_to_noise_db.unwrap();
+        assert!((snr - (analysis.rms_db - floor)).abs() < 1e-9);
+        assert!(noise_floor_warning(&analysis)
+            .unwrap()
+            .contains("-44.8 dBFS"));
+
+        let steady = analyze(Path::new("test.wav"), &create_test_audio(tone, 48000, 1)).unwrap();
+        assert_eq!(steady.noise_floor_db, None);
+        assert!(noise_floor_warning(&steady).is_none());
+    }
+
+    /// Silence is reported at both ends and for long gaps only.
+    #[test]
+    fn test_silence_detection() {
+        let tone = create_sine_wave(440.0, 1.0, 1000, 0.5);
+        let silence = |secs: usize| vec![0.0f32; secs * 1000];
+        let samples: Vec<f32> = [
+            silence(1),
+            tone.clone(),
+            silence(3),
+            tone.clone(),
+            silence(1),
+            tone,
+            silence(2),
+        ]
+        .concat();
+        let analysis =
+            analyze(Path::new("test.wav"), &create_test_audio(samples, 1000, 1)).unwrap();
+
+        // The sine's first sample is zero, so the sound starts one frame in
+        let silence = &analysis.silence;
+        assert!(
+            (silence.leading_secs - 1.001).abs() < 1e-9,
+            "{}",
+            silence.leading_secs
+        );
+        assert!(
+            (silence.trailing_secs - 2.0).abs() < 0.01,
+            "{}",
+            silence.trailing_secs
+        );
+        assert_eq!(silence.gaps.len(), 1);
+        assert!((silence.gaps[0].start_secs - 2.0).abs() < 0.01);
+        assert!((silence.gaps[0].duration_secs - 3.0).abs() < 0.01);
+    }
+
+    /// Runs of three or more full-scale samples are clipped regions; the
+    /// duration counts channels clipping together once.
+    #[test]
+    fn test_clipping_detection() {
+        let sine = create_sine_wave(440.0, 1.0, 48000, 0.5);
+        let clean = analyze(
+            Path::new("test.wav"),
+            &create_test_audio(sine.clone(), 48000, 1),
+        )
+        .unwrap();
+        assert_eq!(clean.clipping.regions, 0);
+        assert!(clipping_warning(&clean).is_none());
+
+        let mut stereo: Vec<f32> = sine.iter().flat_map(|&s| [s, s]).collect();
+        // Both channels clipped for 10 frames at 0.25 s, the right one 2 frames longer
+        for frame in 12000..12010 {
+            stereo[frame * 2] = 1.0;
+            stereo[frame * 2 + 1] = -1.0;
+        }
+        stereo[12010 * 2 + 1] = -1.0;
+        stereo[12011 * 2 + 1] = -1.0;
+        // Left clipped for 4 frames at 0.5 s; a lone full-scale sample later
+        stereo[24000 * 2..24004 * 2]
+            .iter_mut()
+            .step_by(2)
+            .for_each(|s| *s = 1.0);
+        stereo[36000 * 2] = 1.0;
+
+        let analysis =
+            analyze(Path::new("test.wav"), &create_test_audio(stereo, 48000, 2)).unwrap();
+        let clipping = &analysis.clipping;
+        assert_eq!(clipping.regions, 3);
+        assert_eq!(clipping.clipped_samples, 10 + 12 + 4);
+        assert!((clipping.clipped_secs - 16.0 / 48000.0).abs() < 1e-12);
+        assert_eq!(clipping.worst[0].channel, 1);
+        assert!((clipping.worst[0].start_secs - 0.25).abs() < 1e-12);
+        assert!((clipping.worst[2].start_secs - 0.5).abs() < 1e-12);
+        assert!(clipping_warning(&analysis).unwrap().contains("3 region(s)"));
+    }
+
     /// Test LUFS calculation with silent audio.
     #[test]
     fn test_lufs_silent() {
@@ -538,19 +1417,13 @@ mod tests {
         let mut samples = Vec::new();
 
         // Very quiet section
-        for _ in 0..12000 {
-            samples.push(0.001);
-        }
+        samples.extend(std::iter::repeat_n(0.001, 12000));
 
         // Moderate section
-        for _ in 0..12000 {
-            samples.push(0.1);
-        }
+        samples.extend(std::iter::repeat_n(0.1, 12000));
 
         // Loud section
-        for _ in 0..12000 {
-            samples.push(0.5);
-        }
+        samples.extend(std::iter::repeat_n(0.5, 12000));
 
         // Interleaved stereo
         let stereo_samples: Vec<f32> = samples.iter().flat_map(|&s| [s, s]).collect();
@@ -577,6 +1450,95 @@ mod tests {
         assert!(total_energy > -600.0, "Should have some energy in frequency bands");
     }
 
+    /// Test spectral descriptors of a tone and of white noise.
+    #[test]
+    fn test_spectral_descriptors() {
+        let tone = create_test_audio(create_sine_wave(1000.0, 2.0, 48000, 0.5), 48000, 1);
+        let spectral = &analyze(Path::new("tone.wav"), &tone).unwrap().spectral;
+        assert!((spectral.centroid_hz - 1000.0).abs() < 50.0, "{spectral:?}");
+        assert!((spectral.rolloff_hz - 1000.0).abs() < 50.0, "{spectral:?}");
+        assert!(spectral.flatness < 0.01, "{spectral:?}");
+
+        let mut seed = 1u32;
+        let noise: Vec<f32> = (0..96000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 8) as f32 / (1 << 24) as f32 - 0.5
+            })
+            .collect();
+        let noise = create_test_audio(noise, 48000, 1);
+        let spectral = &analyze(Path::new("noise.wav"), &noise).unwrap().spectral;
+        assert!(spectral.flatness > 0.5, "{spectral:?}");
+        assert!(spectral.tilt_db_per_octave.abs() < 0.5, "{spectral:?}");
+        assert!(
+            (spectral.centroid_hz - 10000.0).abs() < 500.0,
+            "{spectral:?}"
+        );
+    }
+
+    /// Test per-channel levels and the dead channel warning.
+    #[test]
+    fn test_channel_stats() {
+        let tone = create_sine_wave(1000.0, 1.0, 48000, 0.5);
+        let samples: Vec<f32> = tone.iter().flat_map(|&s| [s, 0.0]).collect();
+        let analysis =
+            analyze(Path::new("dead.wav"), &create_test_audio(samples, 48000, 2)).unwrap();
+
+        let stats = &analysis.channel_stats;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            (stats[0].name.as_str(), stats[1].name.as_str()),
+            ("Left", "Right")
+        );
+        assert!((stats[0].peak_db - compute_peak_db(&tone)).abs() < 1e-6);
+        assert!((stats[0].rms_db - compute_rms_db(&tone)).abs() < 1e-6);
+        assert!((stats[0].loudness_share - 1.0).abs() < 1e-9);
+        assert_eq!(stats[1].peak_db, -100.0);
+        assert_eq!(channel_warnings(&analysis), ["Right channel is silent"]);
+
+        let both: Vec<f32> = tone.iter().flat_map(|&s| [s, s * 0.1]).collect();
+        let analysis = analyze(Path::new("quiet.wav"), &create_test_audio(both, 48000, 2)).unwrap();
+        assert!((analysis.channel_stats[1].loudness_share - 0.0099).abs() < 0.001);
+        assert_eq!(
+            channel_warnings(&analysis),
+            ["Right channel is 20.0 dB quieter than the loudest channel"]
+        );
+
+        let mono = analyze(Path::new("mono.wav"), &create_test_audio(tone, 48000, 1)).unwrap();
+        assert_eq!(mono.channel_stats[0].name, "Mono");
+        assert!(channel_warnings(&mono).is_empty());
+    }
+
+    /// Test the EBU Mode readings of a tone that drops 10 dB halfway.
+    #[test]
+    fn test_ebu_mode() {
+        // 10 s of a 1 kHz tone at -20 dBFS peak then 10 s at -30 dBFS; a
+        // full-scale 1 kHz sine reads -3 LUFS, so the loud half reads -23
+        let mut samples = create_sine_wave(1000.0, 10.0, 48000, 0.1);
+        samples.extend(create_sine_wave(1000.0, 10.0, 48000, 0.0316));
+        let audio = create_test_audio(samples, 48000, 1);
+        let ebu = analyze(Path::new("steps.wav"), &audio).unwrap().ebu_mode;
+        assert!((ebu.momentary_max_lufs + 23.0).abs() < 0.3, "{ebu:?}");
+        assert!((ebu.short_term_max_lufs + 23.0).abs() < 0.3, "{ebu:?}");
+        assert!((ebu.true_peak_max_dbtp + 20.0).abs() < 0.3, "{ebu:?}");
+        // Both halves pass the gates, so I averages their power: 2.6 dB
+        // below the loud half
+        assert!((ebu.integrated_lufs + 25.6).abs() < 0.3, "{ebu:?}");
+        let (low, high) = (
+            ebu.loudness_range_low_lufs.unwrap(),
+            ebu.loudness_range_high_lufs.unwrap(),
+        );
+        assert!((high + 23.0).abs() < 0.5, "{high}");
+        assert!((low + 33.0).abs() < 0.5, "{low}");
+        assert!((ebu.loudness_range_lu - 10.0).abs() < 0.5, "{ebu:?}");
+
+        let audio = create_test_audio(vec![0.0; 48000], 48000, 1);
+        let silent = analyze(Path::new("silent.wav"), &audio).unwrap().ebu_mode;
+        assert_eq!(silent.loudness_range_low_lufs, None);
+        assert_eq!(silent.loudness_range_high_lufs, None);
+        assert_eq!(silent.loudness_range_lu, 0.0);
+    }
+
     /// Test empty sample handling.
     #[test]
     fn test_empty_samples() {
@@ -608,4 +1570,28 @@ mod tests {
         assert!(bands.bass > -100.0, "Bass band should be calculated");
     }
 
+    /// Chunked accumulation gives the same result as a single pass.
+    #[test]
+    fn test_chunked_analysis_matches_whole() {
+        let samples: Vec<f32> = create_sine_wave(440.0, 3.0, 48000, 0.4)
+            .into_iter()
+            .zip(create_sine_wave(660.0, 3.0, 48000, 0.2))
+            .flat_map(|(l, r)| [l, r])
+            .collect();
+        let path = Path::new("test.wav");
+
+        let whole = analyze(path, &create_test_audio(samples.clone(), 48000, 2)).unwrap();
+        let mut metrics = MetricsAccumulator::new(48000, &ChannelLayout::default_for(2));
+        for chunk in samples.chunks(2 * 1000) {
+            metrics.push(chunk);
+        }
+        let chunked = metrics.finish(path);
+
+        assert!((whole.lufs_integrated - chunked.lufs_integrated).abs() < 1e-9);
+        assert!((whole.rms_db - chunked.rms_db).abs() < 1e-9);
+        assert!((whole.dynamic_range_db - chunked.dynamic_range_db).abs() < 1e-9);
+        assert!((whole.stereo_width - chunked.stereo_width).abs() < 1e-9);
+        assert!((whole.frequency_bands.mid - chunked.frequency_bands.mid).abs() < 1e-9);
+        assert!((whole.metadata.duration_secs - 3.0).abs() < 1e-9);
+    }
 }
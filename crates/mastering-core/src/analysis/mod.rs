@@ -1,16 +1,101 @@
+pub mod channels;
+pub mod compare;
+pub mod compliance;
 pub mod decode;
+pub mod diagnosis;
+pub mod loudness;
+pub mod match_eq;
 mod metrics;
+pub mod platforms;
+pub mod segments;
+pub mod spectrum;
+pub mod tempo;
+pub mod true_peak;
+pub mod vectorscope;
 
-pub use decode::decode_audio;
-pub use metrics::analyze;
+pub use decode::AudioStream;
+pub use decode::{decode_audio, decode_audio_range};
+pub use metrics::{
+    analyze, analyze_stream, channel_warnings, clipping_warning, is_polarity_inverted,
+    mono_compatibility_warnings, noise_floor_warning, MetricsAccumulator,
+};
 
-use crate::types::AudioAnalysis;
+use crate::cache::AnalysisCache;
+use crate::types::{AudioAnalysis, TimeRange};
 use anyhow::Result;
 use std::path::Path;
 
-/// Full analysis pipeline: decode file then compute all metrics.
+/// Full analysis pipeline: stream-decode the file and compute all metrics.
 pub async fn analyze_file(path: &Path) -> Result<AudioAnalysis> {
-    let decoded = decode::decode_audio(path)?;
-    let analysis = metrics::analyze(path, &decoded)?;
+    let mut stream = decode::AudioStream::open(path)?;
+    let analysis = metrics::analyze_stream(path, &mut stream)?;
     Ok(analysis)
 }
+
+/// Analyze only the part of a file inside `range`. Never cached.
+pub async fn analyze_file_range(path: &Path, range: &TimeRange) -> Result<AudioAnalysis> {
+    range.validate()?;
+    let mut stream = decode::AudioStream::open_range(path, range)?;
+    let analysis = metrics::analyze_stream(path, &mut stream)?;
+    Ok(analysis)
+}
+
+/// The loudest `secs` long section of a file, or the whole file when it is
+/// shorter than that.
+pub fn loudest_section(path: &Path, secs: f64) -> Result<TimeRange> {
+    let mut stream = decode::AudioStream::open(path)?;
+    let mut meter = loudness::LoudnessMeter::new(stream.sample_rate(), stream.layout());
+    while let Some(chunk) = stream.next_chunk()? {
+        meter.push(chunk);
+    }
+    Ok(match meter.loudest_window(secs) {
+        Some(start) => TimeRange {
+            start_secs: start,
+            end_secs: Some(start + secs),
+        },
+        None => TimeRange::default(),
+    })
+}
+
+/// Analyze a file, reusing a cached result when the file is unchanged.
+///
+/// Passing `None` for `cache` always analyzes from scratch.
+pub async fn analyze_file_cached(
+    path: &Path,
+    cache: Option<&AnalysisCache>,
+) -> Result<AudioAnalysis> {
+    let Some(cache) = cache else {
+        return analyze_file(path).await;
+    };
+
+    if let Some(analysis) = cache.get(path).await {
+        tracing::debug!("Using cached analysis for {}", path.display());
+        return Ok(analysis);
+    }
+
+    let analysis = analyze_file(path).await?;
+    if let Err(e) = cache.put(path, &analysis).await {
+        tracing::warn!("Failed to cache analysis for {}: {e}", path.display());
+    }
+    Ok(analysis)
+}
+
+/// Compare the tonal balance of a file with a target curve.
+pub async fn compare_to_curve(
+    path: &Path,
+    target: &match_eq::SpectralCurve,
+) -> Result<match_eq::CurveComparison> {
+    let curve = match_eq::SpectralCurve::measure(path)?;
+    Ok(match_eq::compare(&curve, target))
+}
+
+/// Analyze two files and compute their differences (`b - a`).
+pub async fn compare_files(
+    a: &Path,
+    b: &Path,
+    cache: Option<&AnalysisCache>,
+) -> Result<compare::AnalysisComparison> {
+    let analysis_a = analyze_file_cached(a, cache).await?;
+    let analysis_b = analyze_file_cached(b, cache).await?;
+    Ok(compare::compare(analysis_a, analysis_b))
+}
@@ -0,0 +1,136 @@
+//! Streaming platform loudness normalization prediction.
+//!
+//! Each platform plays tracks back at its own reference loudness. Louder
+//! tracks are turned down; quieter ones are turned up only by platforms
+//! that do so, and only as far as their peak headroom allows. Predicting the
+//! gain shows whether a master is pushed louder than any platform will play
+//! it, or will sit quieter than its neighbours in a playlist.
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::AudioAnalysis;
+
+/// True peak above which lossy transcoding risks clipping, in dBTP.
+pub const PEAK_RISK_DBTP: f64 = -1.0;
+
+/// Published normalization behaviour of a platform.
+#[derive(Debug, Clone, Copy)]
+pub struct Platform {
+    pub name: &'static str,
+    /// Playback reference loudness in LUFS.
+    pub target_lufs: f64,
+    /// Highest true peak a quiet track may be turned up to, in dBTP; `None`
+    /// when the platform never turns tracks up.
+    pub boost_ceiling_db: Option<f64>,
+}
+
+/// Platforms with published loudness targets.
+pub const PLATFORMS: &[Platform] = &[
+    Platform {
+        name: "Spotify",
+        target_lufs: -14.0,
+        boost_ceiling_db: Some(-1.0),
+    },
+    Platform {
+        name: "Apple Music",
+        target_lufs: -16.0,
+        boost_ceiling_db: Some(-1.0),
+    },
+    Platform {
+        name: "YouTube",
+        target_lufs: -14.0,
+        boost_ceiling_db: None,
+    },
+    Platform {
+        name: "Tidal",
+        target_lufs: -14.0,
+        boost_ceiling_db: None,
+    },
+    Platform {
+        name: "Amazon Music",
+        target_lufs: -14.0,
+        boost_ceiling_db: None,
+    },
+];
+
+/// How one platform is expected to play a track back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlatformPrediction {
+    pub platform: String,
+    pub target_lufs: f64,
+    /// Gain the platform applies, in dB; negative turns the track down.
+    pub gain_db: f64,
+    /// Loudness after normalization in LUFS.
+    pub playback_lufs: f64,
+    /// True peak after normalization in dBTP.
+    pub playback_true_peak_db: f64,
+    /// The track plays quieter than the target because the platform does
+    /// not turn it up, or not all the way.
+    pub below_target: bool,
+    /// The true peak after normalization is high enough that the
+    /// platform's lossy encoding may clip it.
+    pub peak_risk: bool,
+}
+
+/// Predict the normalization of every platform in [`PLATFORMS`].
+pub fn predict(analysis: &AudioAnalysis) -> Vec<PlatformPrediction> {
+    PLATFORMS
+        .iter()
+        .map(|p| predict_for(p, analysis.lufs_integrated, analysis.true_peak_db))
+        .collect()
+}
+
+/// Predict how `platform` normalizes a track of the given integrated
+/// loudness and true peak.
+pub fn predict_for(
+    platform: &Platform,
+    lufs_integrated: f64,
+    true_peak_db: f64,
+) -> PlatformPrediction {
+    let wanted = platform.target_lufs - lufs_integrated;
+    let gain_db = if wanted <= 0.0 {
+        wanted
+    } else {
+        match platform.boost_ceiling_db {
+            Some(ceiling) => wanted.min(ceiling - true_peak_db).max(0.0),
+            None => 0.0,
+        }
+    };
+    let playback_true_peak_db = true_peak_db + gain_db;
+    PlatformPrediction {
+        platform: platform.name.to_string(),
+        target_lufs: platform.target_lufs,
+        gain_db,
+        playback_lufs: lufs_integrated + gain_db,
+        playback_true_peak_db,
+        below_target: wanted - gain_db > 0.5,
+        peak_risk: playback_true_peak_db > PEAK_RISK_DBTP,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loud_master_is_turned_down() {
+        let spotify = predict_for(&PLATFORMS[0], -8.0, -0.2);
+        assert_eq!(spotify.gain_db, -6.0);
+        assert_eq!(spotify.playback_lufs, -14.0);
+        assert!(!spotify.below_target);
+        // Turned down 6 dB, the peak no longer risks clipping the encoder
+        assert!(!spotify.peak_risk);
+    }
+
+    #[test]
+    fn test_quiet_master_is_boosted_up_to_the_ceiling() {
+        let spotify = predict_for(&PLATFORMS[0], -20.0, -4.0);
+        assert_eq!(spotify.gain_db, 3.0);
+        assert!(spotify.below_target);
+
+        let youtube = predict_for(&PLATFORMS[2], -20.0, -4.0);
+        assert_eq!(youtube.gain_db, 0.0);
+        assert_eq!(youtube.playback_lufs, -20.0);
+        assert!(youtube.below_target);
+    }
+}
@@ -0,0 +1,115 @@
+//! Structural segmentation by energy.
+//!
+//! The track's level is measured in short windows, and a boundary is placed
+//! where the mean level of the stretch before a point differs most from the
+//! stretch after it (an energy novelty curve). That splits quiet verses from
+//! loud choruses without knowing anything else about the music.
+
+use crate::types::Section;
+
+/// Length of the level windows, in seconds.
+const WINDOW_SECS: f64 = 0.5;
+
+/// Stretch compared on either side of a candidate boundary, in seconds.
+const CONTEXT_SECS: f64 = 8.0;
+
+/// Shortest section, in seconds.
+pub const MIN_SECTION_SECS: f64 = 8.0;
+
+/// Smallest level change that makes a boundary, in dB.
+const MIN_NOVELTY_DB: f64 = 3.0;
+
+/// Window levels are floored here, in dB.
+const FLOOR_DB: f64 = -100.0;
+
+/// Sections of a mono signal, in order and covering all of it. A track
+/// without clear changes in level is a single section.
+pub fn find_sections(mono: &[f32], sample_rate: u32) -> Vec<Section> {
+    let window = ((WINDOW_SECS * sample_rate as f64) as usize).max(1);
+    let powers: Vec<f64> = mono
+        .chunks(window)
+        .map(|w| w.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / w.len() as f64)
+        .collect();
+    let levels: Vec<f64> = powers.iter().map(|&p| power_db(p)).collect();
+    let duration = mono.len() as f64 / sample_rate as f64;
+
+    let context = (CONTEXT_SECS / WINDOW_SECS) as usize;
+    let min_gap = (MIN_SECTION_SECS / WINDOW_SECS) as usize;
+    let mut candidates: Vec<(usize, f64)> = (context
+        ..levels.len().saturating_sub(context).max(context))
+        .filter(|&i| i >= min_gap && levels.len() - i >= min_gap)
+        .map(|i| {
+            let before = mean(&levels[i - context..i]);
+            let after = mean(&levels[i..i + context]);
+            (i, (after - before).abs())
+        })
+        .filter(|&(_, novelty)| novelty >= MIN_NOVELTY_DB)
+        .collect();
+    // Strongest changes first, each keeping the others a section apart
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut boundaries: Vec<usize> = Vec::new();
+    for (i, _) in candidates {
+        if boundaries.iter().all(|&b| b.abs_diff(i) >= min_gap) {
+            boundaries.push(i);
+        }
+    }
+    boundaries.sort_unstable();
+
+    let mut edges = vec![0];
+    edges.extend(boundaries);
+    edges.push(levels.len());
+    edges
+        .windows(2)
+        .filter(|w| w[0] < w[1])
+        .map(|w| Section {
+            start_secs: w[0] as f64 * WINDOW_SECS,
+            end_secs: (w[1] as f64 * WINDOW_SECS).min(duration),
+            level_db: power_db(mean(&powers[w[0]..w[1]])),
+            gain_db: 0.0,
+        })
+        .collect()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+fn power_db(power: f64) -> f64 {
+    if power <= 0.0 {
+        FLOOR_DB
+    } else {
+        (10.0 * power.log10()).max(FLOOR_DB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(secs: f64, amplitude: f32) -> Vec<f32> {
+        (0..(secs * 8000.0) as usize)
+            .map(|i| amplitude * (i as f32 * 0.3).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_quiet_and_loud_parts_split() {
+        let mut signal = tone(20.0, 0.1);
+        signal.extend(tone(20.0, 0.5));
+        signal.extend(tone(20.0, 0.1));
+        let sections = find_sections(&signal, 8000);
+
+        assert_eq!(sections.len(), 3, "{sections:?}");
+        assert!((sections[1].start_secs - 20.0).abs() <= 1.0, "{sections:?}");
+        assert!((sections[2].start_secs - 40.0).abs() <= 1.0, "{sections:?}");
+        assert!(sections[1].level_db - sections[0].level_db > 10.0);
+        assert_eq!(sections[2].end_secs, 60.0);
+    }
+
+    #[test]
+    fn test_steady_and_short_signals_are_one_section() {
+        assert_eq!(find_sections(&tone(60.0, 0.3), 8000).len(), 1);
+        assert_eq!(find_sections(&tone(2.0, 0.3), 8000).len(), 1);
+        assert!(find_sections(&[], 8000).is_empty());
+    }
+}
@@ -0,0 +1,316 @@
+//! FFT spectrum analysis.
+//!
+//! Computes an averaged power spectrum (Welch's method) of the mono mixdown
+//! using a Hann window with 50% overlap.
+
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use serde::{Deserialize, Serialize};
+
+use super::decode::DecodedAudio;
+
+/// FFT size used for analysis unless a caller asks for something else.
+pub const DEFAULT_FFT_SIZE: usize = 4096;
+
+/// Averaged power spectrum.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spectrum {
+    pub sample_rate: u32,
+    pub fft_size: usize,
+    /// Mean power per bin for bins `0..=fft_size / 2`, normalised by window energy.
+    pub power: Vec<f64>,
+}
+
+impl Spectrum {
+    /// Centre frequency of `bin` in Hz.
+    pub fn bin_frequency(&self, bin: usize) -> f64 {
+        bin as f64 * self.sample_rate as f64 / self.fft_size as f64
+    }
+
+    /// Centre frequencies of all bins in Hz.
+    pub fn frequencies(&self) -> Vec<f64> {
+        (0..self.power.len())
+            .map(|b| self.bin_frequency(b))
+            .collect()
+    }
+
+    /// Power per bin in dB (floored at -100 dB).
+    pub fn power_db(&self) -> Vec<f64> {
+        self.power.iter().map(|&p| power_to_db(p)).collect()
+    }
+
+    /// Total power of bins whose centre lies in `[f_low, f_high)`.
+    pub fn band_power(&self, f_low: f64, f_high: f64) -> f64 {
+        self.power
+            .iter()
+            .enumerate()
+            .filter(|(bin, _)| {
+                let f = self.bin_frequency(*bin);
+                f >= f_low && f < f_high
+            })
+            .map(|(_, &p)| p)
+            .sum()
+    }
+}
+
+/// Convert a power value to dB, floored at -100 dB.
+pub fn power_to_db(power: f64) -> f64 {
+    if power < 1e-10 {
+        -100.0
+    } else {
+        10.0 * power.log10()
+    }
+}
+
+/// Averaged spectrum of the mono mixdown of `audio`.
+pub fn compute_spectrum(audio: &DecodedAudio, fft_size: usize) -> Spectrum {
+    let mono = audio.mono_mixdown();
+    compute_spectrum_mono(&mono, audio.sample_rate, fft_size)
+}
+
+/// Hann-windowed forward FFT producing one-sided power frames.
+struct WindowedFft {
+    fft: std::sync::Arc<dyn rustfft::Fft<f64>>,
+    window: Vec<f64>,
+    window_energy: f64,
+    buffer: Vec<Complex<f64>>,
+}
+
+impl WindowedFft {
+    fn new(fft_size: usize) -> Self {
+        let window: Vec<f64> = (0..fft_size)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / fft_size as f64).cos())
+            .collect();
+        let window_energy = window.iter().map(|w| w * w).sum();
+        Self {
+            fft: FftPlanner::<f64>::new().plan_fft_forward(fft_size),
+            window,
+            window_energy,
+            buffer: vec![Complex::new(0.0, 0.0); fft_size],
+        }
+    }
+
+    fn fft_size(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Power spectrum of the frame starting at `start`, zero-padded past the end.
+    fn frame_power(&mut self, samples: &[f32], start: usize) -> impl Iterator<Item = f64> + '_ {
+        let fft_size = self.fft_size();
+        for (i, slot) in self.buffer.iter_mut().enumerate() {
+            let sample = samples.get(start + i).copied().unwrap_or(0.0) as f64;
+            *slot = Complex::new(sample * self.window[i], 0.0);
+        }
+        self.fft.process(&mut self.buffer);
+
+        let window_energy = self.window_energy;
+        self.buffer[..fft_size / 2 + 1]
+            .iter()
+            .enumerate()
+            .map(move |(bin, c)| {
+                // Double the one-sided spectrum except DC and Nyquist
+                let scale = if bin == 0 || bin == fft_size / 2 {
+                    1.0
+                } else {
+                    2.0
+                };
+                scale * c.norm_sqr() / window_energy
+            })
+    }
+}
+
+/// Averaged spectrum of a mono signal.
+///
+/// Signals shorter than `fft_size` are zero-padded into a single frame.
+pub fn compute_spectrum_mono(samples: &[f32], sample_rate: u32, fft_size: usize) -> Spectrum {
+    let mut acc = SpectrumAccumulator::new(sample_rate, fft_size);
+    acc.push(samples);
+    acc.finish()
+}
+
+/// Incremental Welch spectrum of a streamed mono signal.
+///
+/// Buffers at most one FFT frame of samples between calls to [`push`](Self::push).
+pub struct SpectrumAccumulator {
+    sample_rate: u32,
+    fft: WindowedFft,
+    pending: Vec<f32>,
+    power: Vec<f64>,
+    frames: usize,
+}
+
+impl SpectrumAccumulator {
+    pub fn new(sample_rate: u32, fft_size: usize) -> Self {
+        let fft_size = fft_size.max(16);
+        Self {
+            sample_rate,
+            fft: WindowedFft::new(fft_size),
+            pending: Vec::with_capacity(fft_size * 2),
+            power: vec![0.0; fft_size / 2 + 1],
+            frames: 0,
+        }
+    }
+
+    /// Feed the next block of mono samples.
+    pub fn push(&mut self, samples: &[f32]) {
+        let fft_size = self.fft.fft_size();
+        let hop = fft_size / 2;
+        for chunk in samples.chunks(fft_size) {
+            self.pending.extend_from_slice(chunk);
+            while self.pending.len() >= fft_size {
+                self.add_frame();
+                self.pending.drain(..hop);
+            }
+        }
+    }
+
+    fn add_frame(&mut self) {
+        for (p, frame_p) in self
+            .power
+            .iter_mut()
+            .zip(self.fft.frame_power(&self.pending, 0))
+        {
+            *p += frame_p;
+        }
+        self.frames += 1;
+    }
+
+    /// Average the accumulated frames into a spectrum.
+    pub fn finish(mut self) -> Spectrum {
+        if self.frames == 0 && !self.pending.is_empty() {
+            self.add_frame();
+        }
+        if self.frames > 0 {
+            for p in self.power.iter_mut() {
+                *p /= self.frames as f64;
+            }
+        }
+        Spectrum {
+            sample_rate: self.sample_rate,
+            fft_size: self.fft.fft_size(),
+            power: self.power,
+        }
+    }
+}
+
+/// Time-binned spectrum frames for spectrogram display.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spectrogram {
+    pub sample_rate: u32,
+    pub fft_size: usize,
+    /// Samples between the starts of consecutive frames.
+    pub hop_size: usize,
+    /// Start time of each frame in seconds.
+    pub times: Vec<f64>,
+    /// Power per bin in dB for each frame (`fft_size / 2 + 1` values per frame).
+    pub frames: Vec<Vec<f32>>,
+}
+
+/// Compute a spectrogram of a mono signal.
+///
+/// When `max_frames` is set and the signal would produce more frames, the
+/// hop is widened so that roughly `max_frames` frames span the whole signal.
+pub fn compute_spectrogram(
+    samples: &[f32],
+    sample_rate: u32,
+    fft_size: usize,
+    hop_size: usize,
+    max_frames: Option<usize>,
+) -> Spectrogram {
+    let fft_size = fft_size.max(16);
+    let mut hop_size = hop_size.max(1);
+    if let Some(max_frames) = max_frames.filter(|&m| m > 0) {
+        let needed_hop = samples.len().div_ceil(max_frames);
+        hop_size = hop_size.max(needed_hop);
+    }
+
+    let mut fft = WindowedFft::new(fft_size);
+    let mut times = Vec::new();
+    let mut frames = Vec::new();
+    let mut start = 0usize;
+    while start < samples.len() {
+        let frame: Vec<f32> = fft
+            .frame_power(samples, start)
+            .map(|p| power_to_db(p) as f32)
+            .collect();
+        times.push(start as f64 / sample_rate as f64);
+        frames.push(frame);
+        start += hop_size;
+    }
+
+    Spectrogram {
+        sample_rate,
+        fft_size,
+        hop_size,
+        times,
+        frames,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, secs: f64, sample_rate: u32) -> Vec<f32> {
+        (0..(sample_rate as f64 * secs) as usize)
+            .map(|i| {
+                (0.5 * (2.0 * std::f64::consts::PI * freq * i as f64 / sample_rate as f64).sin())
+                    as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_peak_at_tone_frequency() {
+        let spectrum = compute_spectrum_mono(&sine(1000.0, 1.0, 48000), 48000, DEFAULT_FFT_SIZE);
+        let peak_bin = spectrum
+            .power
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+            .map(|(bin, _)| bin)
+            .unwrap();
+        assert!((spectrum.bin_frequency(peak_bin) - 1000.0).abs() < 48000.0 / 4096.0);
+    }
+
+    #[test]
+    fn test_band_power_concentrated() {
+        let spectrum = compute_spectrum_mono(&sine(100.0, 2.0, 48000), 48000, DEFAULT_FFT_SIZE);
+        let total: f64 = spectrum.power.iter().sum();
+        assert!(spectrum.band_power(60.0, 250.0) / total > 0.99);
+    }
+
+    #[test]
+    fn test_chunked_accumulation_matches_single_pass() {
+        let samples = sine(3000.0, 1.0, 48000);
+        let whole = compute_spectrum_mono(&samples, 48000, 1024);
+        let mut acc = SpectrumAccumulator::new(48000, 1024);
+        for chunk in samples.chunks(777) {
+            acc.push(chunk);
+        }
+        let chunked = acc.finish();
+        assert!(whole
+            .power
+            .iter()
+            .zip(&chunked.power)
+            .all(|(a, b)| (a - b).abs() < 1e-12));
+    }
+
+    #[test]
+    fn test_spectrogram_frame_limit() {
+        let samples = sine(440.0, 10.0, 48000);
+        let spectrogram = compute_spectrogram(&samples, 48000, 2048, 512, Some(100));
+        assert!(spectrogram.frames.len() <= 100);
+        assert!(spectrogram.frames.len() >= 99);
+        assert!(spectrogram.frames.iter().all(|f| f.len() == 1025));
+        assert_eq!(spectrogram.times.len(), spectrogram.frames.len());
+    }
+
+    #[test]
+    fn test_short_and_empty_signals() {
+        let spectrum = compute_spectrum_mono(&sine(440.0, 0.01, 48000), 48000, DEFAULT_FFT_SIZE);
+        assert_eq!(spectrum.power.len(), DEFAULT_FFT_SIZE / 2 + 1);
+        let empty = compute_spectrum_mono(&[], 48000, DEFAULT_FFT_SIZE);
+        assert!(empty.power.iter().all(|&p| p == 0.0));
+    }
+}
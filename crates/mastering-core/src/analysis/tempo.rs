@@ -0,0 +1,255 @@
+//! Tempo estimation.
+//!
+//! Onsets show up as sudden rises in level: the mono signal's energy is
+//! measured over 40 ms every 10 ms and the rises of its log (the onset
+//! strength envelope) are kept. The envelope repeats at the beat period, so the lag
+//! with the strongest autocorrelation between [`MIN_BPM`] and [`MAX_BPM`]
+//! gives the tempo, weighted toward moderate tempos to pick the beat rather
+//! than the half or double of it.
+
+use std::collections::VecDeque;
+
+/// Length of the energy hops, in seconds.
+const HOP_SECS: f64 = 0.01;
+
+/// Hops the energy is measured over; long enough that the waveform of a
+/// bass note does not ripple the level.
+const FRAME_HOPS: usize = 4;
+
+/// Slowest tempo reported, in beats per minute.
+pub const MIN_BPM: f64 = 60.0;
+
+/// Fastest tempo reported, in beats per minute.
+pub const MAX_BPM: f64 = 200.0;
+
+/// Tempo the weighting is centred on, in beats per minute.
+const PREFERRED_BPM: f64 = 120.0;
+
+/// Width of the weighting in octaves of tempo.
+const PREFERENCE_OCTAVES: f64 = 1.0;
+
+/// Shortest input with a tempo, in seconds.
+const MIN_DURATION_SECS: f64 = 6.0;
+
+/// Autocorrelation at the beat period, relative to lag 0, below which the
+/// input has no steady pulse.
+const MIN_PERIODICITY: f64 = 0.1;
+
+/// Share of the autocorrelation at a period that half of it needs to be
+/// taken as the beat instead.
+const HALF_PERIOD_SHARE: f64 = 0.9;
+
+/// Mean onset strength below which the input has no onsets at all, in dB
+/// per hop.
+const MIN_ONSET_DB: f64 = 0.05;
+
+/// Builds the onset strength envelope of mono chunks.
+#[derive(Debug)]
+pub struct TempoDetector {
+    hop: usize,
+    hop_rate: f64,
+    frames_in_hop: usize,
+    sum_sq: f64,
+    /// Energy of the last [`FRAME_HOPS`] hops.
+    frame: VecDeque<f64>,
+    previous_db: Option<f64>,
+    onsets: Vec<f64>,
+}
+
+impl TempoDetector {
+    pub fn new(sample_rate: u32) -> Self {
+        let hop = ((sample_rate as f64 * HOP_SECS) as usize).max(1);
+        Self {
+            hop,
+            hop_rate: sample_rate as f64 / hop as f64,
+            frames_in_hop: 0,
+            sum_sq: 0.0,
+            frame: VecDeque::with_capacity(FRAME_HOPS),
+            previous_db: None,
+            onsets: Vec::new(),
+        }
+    }
+
+    /// Feed mono samples.
+    pub fn push(&mut self, mono: &[f32]) {
+        for &sample in mono {
+            self.sum_sq += (sample as f64) * (sample as f64);
+            self.frames_in_hop += 1;
+            if self.frames_in_hop == self.hop {
+                if self.frame.len() == FRAME_HOPS {
+                    self.frame.pop_front();
+                }
+                self.frame.push_back(self.sum_sq);
+                self.frames_in_hop = 0;
+                self.sum_sq = 0.0;
+                if self.frame.len() < FRAME_HOPS {
+                    continue;
+                }
+                let mean_sq = self.frame.iter().sum::<f64>() / (FRAME_HOPS * self.hop) as f64;
+                let db = 10.0 * (mean_sq + 1e-10).log10();
+                if let Some(previous) = self.previous_db {
+                    self.onsets.push((db - previous).max(0.0));
+                }
+                self.previous_db = Some(db);
+            }
+        }
+    }
+
+    /// Tempo in beats per minute, or `None` for input that is too short or
+    /// has no steady pulse.
+    pub fn finish(self) -> Option<f64> {
+        if (self.onsets.len() as f64) < MIN_DURATION_SECS * self.hop_rate {
+            return None;
+        }
+        let mean = self.onsets.iter().sum::<f64>() / self.onsets.len() as f64;
+        if mean < MIN_ONSET_DB {
+            return None;
+        }
+        // Smoothed, so a beat period between two hops still correlates well
+        let envelope: Vec<f64> = (0..self.onsets.len())
+            .map(|i| {
+                let tap = |offset: usize| {
+                    self.onsets
+                        .get((i + offset).wrapping_sub(2))
+                        .copied()
+                        .unwrap_or(mean)
+                };
+                (tap(0) + 4.0 * tap(1) + 6.0 * tap(2) + 4.0 * tap(3) + tap(4)) / 16.0 - mean
+            })
+            .collect();
+        let energy: f64 = envelope.iter().map(|e| e * e).sum();
+
+        let lag_of = |bpm: f64| 60.0 * self.hop_rate / bpm;
+        let min_lag = lag_of(MAX_BPM).floor() as usize;
+        let max_lag = (lag_of(MIN_BPM).ceil() as usize).min(envelope.len() - 1);
+        let autocorrelation: Vec<f64> = (0..=max_lag + 1)
+            .map(|lag| {
+                let overlap = envelope.len() - lag;
+                let sum: f64 = envelope
+                    .iter()
+                    .zip(&envelope[lag..])
+                    .map(|(a, b)| a * b)
+                    .sum();
+                // Unbiased, so long lags are not penalized for their shorter overlap
+                sum / overlap.max(1) as f64 * envelope.len() as f64 / energy
+            })
+            .collect();
+
+        let (mut best_lag, best_score) = (min_lag.max(1)..=max_lag)
+            .map(|lag| {
+                let octaves = (lag_of(PREFERRED_BPM) / lag as f64).log2() / PREFERENCE_OCTAVES;
+                (lag, autocorrelation[lag] * (-0.5 * octaves * octaves).exp())
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))?;
+        if best_score <= 0.0 || autocorrelation[best_lag] < MIN_PERIODICITY {
+            return None;
+        }
+        // A beat repeats at twice its period too; prefer the beat when it is
+        // nearly as strong, which happens when its period falls between hops
+        if let Some(half) = (best_lag / 2..=best_lag.div_ceil(2))
+            .filter(|&lag| lag >= min_lag.max(1))
+            .max_by(|&a, &b| autocorrelation[a].total_cmp(&autocorrelation[b]))
+        {
+            if autocorrelation[half] >= HALF_PERIOD_SHARE * autocorrelation[best_lag] {
+                best_lag = half;
+            }
+        }
+
+        // Parabolic interpolation around the peak for a finer period
+        let (left, centre, right) = (
+            autocorrelation[best_lag - 1],
+            autocorrelation[best_lag],
+            autocorrelation[best_lag + 1],
+        );
+        let curvature = left - 2.0 * centre + right;
+        let offset = if curvature < 0.0 {
+            (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        let bpm = 60.0 * self.hop_rate / (best_lag as f64 + offset);
+        Some((bpm * 10.0).round() / 10.0)
+    }
+}
+
+/// Length of a `1/division` note at `bpm`, in milliseconds; a 1/4 note is
+/// one beat.
+pub fn note_ms(bpm: f64, division: u32) -> f64 {
+    60_000.0 / bpm * 4.0 / division as f64
+}
+
+/// The length of a 1/4 to 1/32 note at `bpm` closest to `release_ms`, so a
+/// compressor lets go in time with the music.
+pub fn synced_release_ms(bpm: f64, release_ms: f64) -> f64 {
+    [4, 8, 16, 32]
+        .iter()
+        .map(|&division| note_ms(bpm, division))
+        .min_by(|a, b| {
+            (a / release_ms)
+                .ln()
+                .abs()
+                .total_cmp(&(b / release_ms).ln().abs())
+        })
+        .unwrap_or(release_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decaying noise bursts on every beat, like a kick drum.
+    fn clicks(bpm: f64, secs: f64, sample_rate: u32) -> Vec<f32> {
+        let beat = (60.0 / bpm * sample_rate as f64) as usize;
+        let mut seed = 1u32;
+        (0..(secs * sample_rate as f64) as usize)
+            .map(|i| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                let noise = (seed >> 8) as f32 / (1 << 24) as f32 - 0.5;
+                let since_beat = (i % beat) as f32 / sample_rate as f32;
+                noise * (-since_beat * 30.0).exp() + 0.01 * noise
+            })
+            .collect()
+    }
+
+    fn detect(samples: &[f32], sample_rate: u32) -> Option<f64> {
+        let mut detector = TempoDetector::new(sample_rate);
+        for chunk in samples.chunks(4096) {
+            detector.push(chunk);
+        }
+        detector.finish()
+    }
+
+    #[test]
+    fn test_detects_steady_beat() {
+        for bpm in [90.0, 128.0, 174.0] {
+            let tempo = detect(&clicks(bpm, 20.0, 22050), 22050).unwrap();
+            assert!((tempo - bpm).abs() < 1.5, "{bpm}: {tempo}");
+        }
+    }
+
+    #[test]
+    fn test_no_tempo_without_pulse() {
+        let tone: Vec<f32> = (0..22050 * 20)
+            .map(|i| 0.3 * (i as f32 * 0.05).sin())
+            .collect();
+        assert_eq!(detect(&tone, 22050), None);
+        let mut seed = 7u32;
+        let noise: Vec<f32> = (0..22050 * 20)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (seed >> 8) as f32 / (1 << 24) as f32 - 0.5
+            })
+            .collect();
+        assert_eq!(detect(&noise, 22050), None);
+        assert_eq!(detect(&clicks(120.0, 3.0, 22050), 22050), None);
+        assert_eq!(detect(&[], 22050), None);
+    }
+
+    #[test]
+    fn test_synced_release() {
+        assert_eq!(note_ms(120.0, 4), 500.0);
+        assert_eq!(synced_release_ms(120.0, 120.0), 125.0);
+        assert_eq!(synced_release_ms(120.0, 300.0), 250.0);
+        assert_eq!(synced_release_ms(60.0, 900.0), 1000.0);
+    }
+}
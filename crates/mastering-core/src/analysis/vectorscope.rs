@@ -0,0 +1,103 @@
+//! Vectorscope (goniometer) data.
+//!
+//! A vectorscope plots each left/right sample pair as a point; drawn
+//! rotated by 45° it shows mid up and side across, so a mono signal is a
+//! vertical line and wide or out-of-phase material spreads sideways. Pairs
+//! are decimated to a drawable number, and the correlation of left and
+//! right is measured per window so a correlation meter can follow along.
+//! Surround input is shown through its stereo downmix.
+
+use serde::{Deserialize, Serialize};
+
+use super::decode::DecodedAudio;
+
+/// Default length of the correlation windows, in seconds.
+pub const DEFAULT_WINDOW_SECS: f64 = 0.1;
+
+/// Left/right points and correlation over time of a track.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vectorscope {
+    /// Evenly spaced (left, right) sample pairs.
+    pub points: Vec<[f32; 2]>,
+    /// Seconds between consecutive points.
+    pub point_secs: f64,
+    /// Length of each correlation window in seconds.
+    pub window_secs: f64,
+    /// Correlation of left and right per window, from -1 (out of phase) to
+    /// 1 (mono); 0 in silence.
+    pub correlation: Vec<f64>,
+}
+
+/// Vectorscope data of `audio` with at most `max_points` points and
+/// correlation windows of `window_secs`.
+pub fn vectorscope(audio: &DecodedAudio, max_points: usize, window_secs: f64) -> Vectorscope {
+    let stereo = audio.layout.downmix_to_stereo(&audio.samples);
+    let frames = stereo.len() / 2;
+    let stride = frames.div_ceil(max_points.max(1)).max(1);
+    let points = stereo
+        .chunks_exact(2)
+        .step_by(stride)
+        .map(|pair| [pair[0], pair[1]])
+        .collect();
+
+    let window = ((window_secs * audio.sample_rate as f64) as usize).max(1);
+    let correlation = stereo
+        .chunks(window * 2)
+        .map(|chunk| {
+            let (mut left_right, mut left_sq, mut right_sq) = (0.0, 0.0, 0.0);
+            for pair in chunk.chunks_exact(2) {
+                let (l, r) = (pair[0] as f64, pair[1] as f64);
+                left_right += l * r;
+                left_sq += l * l;
+                right_sq += r * r;
+            }
+            let norm = (left_sq * right_sq).sqrt();
+            if norm < 1e-20 {
+                0.0
+            } else {
+                (left_right / norm).clamp(-1.0, 1.0)
+            }
+        })
+        .collect();
+
+    Vectorscope {
+        points,
+        point_secs: stride as f64 / audio.sample_rate as f64,
+        window_secs: window as f64 / audio.sample_rate as f64,
+        correlation,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimates_and_tracks_correlation() {
+        // One second in phase, then one second with the right channel inverted
+        let samples: Vec<f32> = (0..2000)
+            .flat_map(|i| {
+                let s = (i as f32 * 0.1).sin() * 0.5;
+                [s, if i < 1000 { s } else { -s }]
+            })
+            .collect();
+        let audio = DecodedAudio::new(samples, 1000, 2);
+        let scope = vectorscope(&audio, 500, 0.5);
+
+        assert_eq!(scope.points.len(), 500);
+        assert_eq!(scope.point_secs, 0.004);
+        assert_eq!(scope.points[0], [0.0, 0.0]);
+        assert_eq!(scope.points[1][0], scope.points[1][1]);
+        assert_eq!(scope.correlation.len(), 4);
+        assert!(scope.correlation[..2].iter().all(|&c| c > 0.99));
+        assert!(scope.correlation[2..].iter().all(|&c| c < -0.99));
+    }
+
+    #[test]
+    fn test_mono_is_a_line() {
+        let audio = DecodedAudio::new(vec![0.1, -0.2, 0.3], 1000, 1);
+        let scope = vectorscope(&audio, 10, 0.5);
+        assert_eq!(scope.points, [[0.1, 0.1], [-0.2, -0.2], [0.3, 0.3]]);
+        assert_eq!(scope.correlation, [1.0]);
+    }
+}
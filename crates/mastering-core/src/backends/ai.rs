@@ -1,12 +1,27 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::process::Command;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-use super::{BackendOutput, MasteringOptions};
+use super::{bridge, BackendOutput, MasteringOptions};
 use crate::analysis;
-use crate::config::Config;
-use crate::types::{AiProvider, MasteringParams};
+use crate::config::{
+    AnthropicConfig, AzureOpenAiConfig, Config, KeyhanStudioConfig, LmStudioConfig,
+    LocalGgufConfig, ModelPrice, NetworkConfig, OllamaConfig, OpenAiCompatibleConfig, OpenAiConfig,
+    ParamLimits, PromptsConfig, RulesConfig,
+};
+use crate::error::MasteringError;
+use crate::metadata;
+use crate::pipeline::{PipelineStage, ProgressReporter};
+use crate::rules;
+use crate::scoring;
+use crate::secrets;
+use crate::types::{
+    AiProvider, AudioAnalysis, MasteringParams, ParamCandidate, ParamCorrection, ParamExplanation,
+    Preset, Refinement, StemAdjustment, TokenUsage,
+};
+use crate::validate;
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LmStudioModel {
@@ -16,36 +31,57 @@ pub struct LmStudioModel {
 #[derive(Debug, Clone)]
 pub struct AiBackend {
     provider: AiProvider,
-    ollama_endpoint: String,
-    ollama_model: String,
-    lmstudio_endpoint: String,
-    lmstudio_model: String,
-    keyhanstudio_endpoint: String,
-    keyhanstudio_api_key: String,
-    openai_api_key: String,
-    openai_model: String,
-    anthropic_api_key: String,
-    anthropic_model: String,
+    ollama: OllamaConfig,
+    lmstudio: LmStudioConfig,
+    keyhanstudio: KeyhanStudioConfig,
+    openai: OpenAiConfig,
+    azure_openai: AzureOpenAiConfig,
+    openai_compatible: OpenAiCompatibleConfig,
+    anthropic: AnthropicConfig,
+    local_gguf: LocalGgufConfig,
+    rules: RulesConfig,
+    limits: ParamLimits,
+    passes: u32,
+    candidates: u32,
+    candidate_excerpt_secs: f64,
+    stream: bool,
+    prompts: PromptsConfig,
+    pricing: BTreeMap<String, ModelPrice>,
+    network: NetworkConfig,
     python_path: String,
     scripts_dir: std::path::PathBuf,
+    dsp_timeout: bridge::Timeout,
 }
 
 impl AiBackend {
+    /// API keys stay as they are in the config; the environment and keyring
+    /// are only asked for the key of the provider that is called.
     pub fn new(config: &Config) -> Self {
         Self {
             provider: config.ai.default_provider,
-            ollama_endpoint: config.ai.ollama.endpoint.clone(),
-            ollama_model: config.ai.ollama.model.clone(),
-            lmstudio_endpoint: config.ai.lmstudio.endpoint.clone(),
-            lmstudio_model: config.ai.lmstudio.model.clone(),
-            keyhanstudio_endpoint: config.ai.keyhanstudio.endpoint.clone(),
-            keyhanstudio_api_key: config.ai.keyhanstudio.api_key.clone(),
-            openai_api_key: config.ai.openai.api_key.clone(),
-            openai_model: config.ai.openai.model.clone(),
-            anthropic_api_key: config.ai.anthropic.api_key.clone(),
-            anthropic_model: config.ai.anthropic.model.clone(),
+            ollama: config.ai.ollama.clone(),
+            lmstudio: config.ai.lmstudio.clone(),
+            keyhanstudio: config.ai.keyhanstudio.clone(),
+            openai: config.ai.openai.clone(),
+            azure_openai: config.ai.azure_openai.clone(),
+            openai_compatible: config.ai.openai_compatible.clone(),
+            anthropic: config.ai.anthropic.clone(),
+            local_gguf: config.ai.local_gguf.clone(),
+            rules: config.ai.rules.clone(),
+            limits: config.ai.limits.clone(),
+            passes: config.ai.passes.max(1),
+            candidates: config.ai.candidates.max(1),
+            candidate_excerpt_secs: config.ai.candidate_excerpt_secs,
+            stream: config.ai.stream,
+            prompts: config.ai.prompts.clone(),
+            pricing: config.ai.pricing.clone(),
+            network: config.ai.network.clone(),
             python_path: config.backends.matchering.python_path.clone(),
             scripts_dir: Config::python_scripts_dir(),
+            dsp_timeout: bridge::Timeout::configured(
+                config.backends.dsp_timeout_secs,
+                "backends.dsp_timeout_secs",
+            ),
         }
     }
 
@@ -55,23 +91,234 @@ impl AiBackend {
     }
 
     pub async fn process(&self, opts: &MasteringOptions) -> Result<BackendOutput> {
+        // Fixed parameters, e.g. of a remastered job, are rendered as they
+        // are without asking the provider again
+        if let Some(ref params) = opts.params {
+            info!("Rendering fixed parameters with the DSP bridge");
+            let mut params = params.clone();
+            let corrections = validate::cap_ceiling(&mut params, opts.ceiling_db);
+            self.render(opts, &params).await?;
+            return Ok(BackendOutput {
+                output_path: opts.output_path.clone(),
+                params_applied: Some(params),
+                backend_name: format!("ai/{}", self.provider),
+                message: "Rendered fixed parameters with the DSP bridge".into(),
+                corrections,
+                explanation: None,
+                candidates: Vec::new(),
+                usage: None,
+                device: None,
+            });
+        }
+
         info!("AI-assisted mastering using provider: {}", self.provider);
 
         // Step 1: Analyze the input audio
         let analysis = analysis::analyze_file(&opts.input_path).await?;
-        let analysis_json = serde_json::to_string_pretty(&analysis)?;
+        let analysis_json = analysis_prompt_json(&analysis)?;
         debug!("Audio analysis:\n{analysis_json}");
 
-        // Step 2: Ask the AI for mastering parameters
-        let prompt = build_mastering_prompt(&analysis_json, opts);
-        let ai_response = self.call_ai(&prompt).await?;
-        debug!("AI response:\n{ai_response}");
+        // Steps 2-3: Derive mastering parameters, from rules or the AI's response
+        let mut explanation = None;
+        let mut candidates = Vec::new();
+        let mut usage = TokenUsage::default();
+        let (mut params, mut corrections) = if self.provider == AiProvider::Rules {
+            anyhow::ensure!(
+                opts.refinement.is_none(),
+                "The rules provider cannot act on feedback; choose a language model provider to refine"
+            );
+            let mut params =
+                rules::suggest_params(&analysis, opts.target_lufs, opts.no_limiter, &self.rules);
+            if opts.speech {
+                rules::adapt_for_speech(&mut params);
+            }
+            (params, Vec::new())
+        } else {
+            let system = self.system_prompt().await?;
+            let prompt = match opts.refinement {
+                Some(ref refinement) => {
+                    let previous = analysis::analyze_file(&refinement.previous_output)
+                        .await
+                        .context("Analyzing the previous master")?;
+                    let previous_json = analysis_prompt_json(&previous)?;
+                    build_refinement_prompt(&analysis_json, &previous_json, refinement, opts)?
+                }
+                None => self.user_prompt(&analysis_json, opts).await?,
+            };
+            let prompt = with_explain_request(prompt, opts);
+            // Feedback already says what to change, so refinements get one answer
+            let candidate_count = if opts.refinement.is_none() {
+                self.candidates
+            } else {
+                1
+            };
+            let prompt = with_candidates_request(prompt, candidate_count);
+            let ai_response = self
+                .call_ai(&system, &prompt, &mut usage, &opts.progress)
+                .await?;
+            debug!("AI response:\n{ai_response}");
+            if candidate_count > 1 {
+                let (proposal, scored) = self.choose_candidate(&ai_response, opts).await?;
+                candidates = scored;
+                if opts.explain {
+                    explanation = proposal.explanation;
+                }
+                (proposal.params, proposal.corrections)
+            } else {
+                explanation = opts
+                    .explain
+                    .then(|| parse_explanation(&ai_response))
+                    .flatten();
+                parse_mastering_params(&ai_response, &self.limits)?
+            }
+        };
+        corrections.extend(validate::cap_ceiling(&mut params, opts.ceiling_db));
+        for c in &corrections {
+            warn!("Corrected AI parameter {}: {}", c.field, c.message);
+        }
 
-        // Step 3: Parse mastering parameters from AI response
-        let params = parse_mastering_params(&ai_response)?;
-        let _params_json = serde_json::to_string(&params)?;
+        // The reviewer has the last word; what they change is validated again
+        if opts.review.is_enabled() {
+            params = opts.review.review(params).await?;
+            let mut reviewed = validate::clamp_params(&mut params, &self.limits);
+            reviewed.extend(validate::cap_ceiling(&mut params, opts.ceiling_db));
+            for c in &reviewed {
+                warn!("Corrected reviewed parameter {}: {}", c.field, c.message);
+            }
+            corrections.extend(reviewed);
+        }
 
         // Step 4: Apply parameters via Python DSP bridge
+        self.render(opts, &params).await?;
+
+        // Step 5: Optional feedback passes correcting what the first render
+        // missed; reviewed parameters are rendered as they were approved
+        let mut passes = 1;
+        if self.provider != AiProvider::Rules && !opts.review.is_enabled() {
+            let system = self.system_prompt().await?;
+            while passes < self.passes {
+                let post = analysis::analyze_file(&opts.output_path)
+                    .await
+                    .context("Analyzing the rendered pass")?;
+                let Some(feedback) =
+                    describe_deviation(&analysis, &post, &params, opts.target_lufs)
+                else {
+                    info!("Pass {passes} is on target; skipping further passes");
+                    break;
+                };
+                passes += 1;
+                info!("Pass {passes}: {feedback}");
+
+                let refinement = Refinement {
+                    feedback,
+                    previous_params: params.clone(),
+                    previous_output: opts.output_path.clone(),
+                };
+                let post_json = analysis_prompt_json(&post)?;
+                let prompt =
+                    build_refinement_prompt(&analysis_json, &post_json, &refinement, opts)?;
+                let prompt = with_explain_request(prompt, opts);
+                let ai_response = self
+                    .call_ai(&system, &prompt, &mut usage, &opts.progress)
+                    .await?;
+                debug!("AI response (pass {passes}):\n{ai_response}");
+                if opts.explain {
+                    explanation = parse_explanation(&ai_response);
+                }
+                let (mut next, mut next_corrections) =
+                    parse_mastering_params(&ai_response, &self.limits)?;
+                next_corrections.extend(validate::cap_ceiling(&mut next, opts.ceiling_db));
+                for c in &next_corrections {
+                    warn!("Corrected AI parameter {}: {}", c.field, c.message);
+                }
+                params = next;
+                corrections.extend(next_corrections);
+                self.render(opts, &params).await?;
+            }
+        }
+
+        if usage.requests > 0 {
+            usage.estimated_cost_usd = self.estimate_cost(&usage);
+            info!(
+                "AI usage: {} request(s), {} input / {} output tokens",
+                usage.requests, usage.input_tokens, usage.output_tokens
+            );
+        }
+        info!("AI-assisted mastering completed");
+
+        Ok(BackendOutput {
+            output_path: opts.output_path.clone(),
+            params_applied: Some(params),
+            backend_name: format!("ai/{}", self.provider),
+            message: if opts.refinement.is_some() {
+                format!(
+                    "Refined with {} AI provider from listener feedback",
+                    self.provider
+                )
+            } else if passes > 1 {
+                format!(
+                    "Mastered using {} AI provider in {passes} passes with post-analysis feedback",
+                    self.provider
+                )
+            } else if !candidates.is_empty() {
+                format!(
+                    "Mastered using {} AI provider with the best of {} scored candidates",
+                    self.provider,
+                    candidates.len()
+                )
+            } else {
+                format!(
+                    "Mastered using {} AI provider with custom EQ, compression, and limiting",
+                    self.provider
+                )
+            },
+            corrections,
+            explanation,
+            candidates,
+            usage: (usage.requests > 0).then_some(usage),
+            device: None,
+        })
+    }
+
+    /// Score the parameter sets of a candidates response on an excerpt of
+    /// the input and pick the best.
+    async fn choose_candidate(
+        &self,
+        response: &str,
+        opts: &MasteringOptions,
+    ) -> Result<(Proposal, Vec<ParamCandidate>)> {
+        let mut proposals = parse_proposals(response, &self.limits)?;
+        if proposals.len() == 1 {
+            warn!("AI returned a single parameter set instead of candidates");
+            return Ok((proposals.remove(0), Vec::new()));
+        }
+
+        let input = opts.input_path.clone();
+        let params = proposals.iter().map(|p| p.params.clone()).collect();
+        let (target_lufs, excerpt_secs) = (opts.target_lufs, self.candidate_excerpt_secs);
+        let scored = tokio::task::spawn_blocking(move || {
+            scoring::rank(&input, params, target_lufs, excerpt_secs)
+        })
+        .await
+        .context("Candidate scoring task failed")?
+        .context("Scoring parameter candidates")?;
+
+        for (i, c) in scored.iter().enumerate() {
+            info!(
+                "Candidate {}: score {:.2} (loudness error {:.2} LU, spectral error {:.2} dB){}",
+                i + 1,
+                c.score,
+                c.lufs_error,
+                c.spectral_error,
+                if c.selected { " — selected" } else { "" }
+            );
+        }
+        let best = scored.iter().position(|c| c.selected).unwrap_or(0);
+        Ok((proposals.swap_remove(best), scored))
+    }
+
+    /// Render `params` onto the input with the Python DSP bridge.
+    async fn render(&self, opts: &MasteringOptions, params: &MasteringParams) -> Result<()> {
         let script = self.scripts_dir.join("apply_fx.py");
         anyhow::ensure!(
             script.exists(),
@@ -84,67 +331,175 @@ impl AiBackend {
             "output": opts.output_path.to_string_lossy(),
             "params": params,
             "bit_depth": opts.bit_depth,
+            "sample_format": opts.sample_format,
         });
 
-        let output = Command::new(&self.python_path)
-            .arg(&script)
-            .arg(request.to_string())
-            .output()
-            .with_context(|| {
-                format!(
-                    "Failed to run DSP bridge. Is Python installed at '{}'?",
-                    self.python_path
-                )
-            })?;
+        let request = request.to_string();
+        let output = bridge::run(
+            &self.python_path,
+            [script.as_os_str(), request.as_ref()],
+            self.dsp_timeout,
+            "DSP bridge",
+            &opts.progress,
+        )
+        .await?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("DSP processing failed:\n{stderr}");
+            anyhow::bail!("DSP processing failed:\n{}", output.stderr);
         }
+        Ok(())
+    }
 
-        info!("AI-assisted mastering completed");
+    /// Ask the provider for a gain and EQ per stem, from an analysis of each
+    /// stem, before the stems are summed and mastered.
+    ///
+    /// Stems the model invents are dropped and values are clamped to the
+    /// parameter limits; the clamps are returned as corrections.
+    pub async fn suggest_stem_adjustments(
+        &self,
+        stems: &[(String, AudioAnalysis)],
+        brief: Option<&str>,
+        progress: &ProgressReporter,
+    ) -> Result<(
+        BTreeMap<String, StemAdjustment>,
+        Vec<ParamCorrection>,
+        TokenUsage,
+    )> {
+        info!("Asking {} for stem adjustments", self.provider);
+        let prompt = build_stem_prompt(stems, brief)?;
+        let mut usage = TokenUsage::default();
+        let response = self
+            .call_ai(STEM_SYSTEM_PROMPT, &prompt, &mut usage, progress)
+            .await?;
+        debug!("Stem adjustment response:\n{response}");
+        usage.estimated_cost_usd = self.estimate_cost(&usage);
+
+        let names: Vec<&str> = stems.iter().map(|(name, _)| name.as_str()).collect();
+        let (adjustments, corrections) = parse_stem_adjustments(&response, &names, &self.limits)?;
+        Ok((adjustments, corrections, usage))
+    }
 
-        Ok(BackendOutput {
-            output_path: opts.output_path.clone(),
-            params_applied: Some(params),
-            backend_name: format!("ai/{}", self.provider),
-            message: format!(
-                "Mastered using {} AI provider with custom EQ, compression, and limiting",
-                self.provider
-            ),
-        })
+    /// The configured system prompt file, or the built-in prompt for the provider.
+    async fn system_prompt(&self) -> Result<String> {
+        if !self.prompts.system_file.is_empty() {
+            return read_template(&self.prompts.system_file).await;
+        }
+        Ok(match self.provider {
+            AiProvider::LmStudio => LMSTUDIO_SYSTEM_PROMPT,
+            _ => SYSTEM_PROMPT,
+        }
+        .to_string())
+    }
+
+    /// The configured user prompt template rendered for `opts`, or the built-in prompt.
+    async fn user_prompt(&self, analysis_json: &str, opts: &MasteringOptions) -> Result<String> {
+        if self.prompts.user_file.is_empty() {
+            return Ok(build_mastering_prompt(analysis_json, opts));
+        }
+        let template = read_template(&self.prompts.user_file).await?;
+        let genre = metadata::read_genre(&opts.input_path);
+        Ok(render_prompt(
+            &template,
+            analysis_json,
+            opts,
+            genre.as_deref(),
+        ))
+    }
+
+    /// Send one request to the provider, adding its token counts to `usage`.
+    ///
+    /// Streaming providers report the tokens received to `progress`.
+    async fn call_ai(
+        &self,
+        system: &str,
+        prompt: &str,
+        usage: &mut TokenUsage,
+        progress: &ProgressReporter,
+    ) -> Result<String> {
+        let completion = match self.provider {
+            AiProvider::Ollama => self.call_ollama(system, prompt, progress).await,
+            AiProvider::LmStudio => self.call_lmstudio(system, prompt, progress).await,
+            AiProvider::KeyhanStudio => self.call_keyhanstudio(system, prompt).await,
+            AiProvider::OpenAi => self.call_openai(system, prompt, progress).await,
+            AiProvider::AzureOpenAi => self.call_azure_openai(system, prompt, progress).await,
+            AiProvider::OpenAiCompatible => {
+                self.call_openai_compatible(system, prompt, progress).await
+            }
+            AiProvider::Anthropic => self.call_anthropic(system, prompt).await,
+            AiProvider::LocalGguf => self.call_local_gguf(system, prompt).await,
+            AiProvider::Rules => anyhow::bail!("The rules provider does not use a language model"),
+        }?;
+        usage.requests += 1;
+        if let Some(ref reported) = completion.usage {
+            usage.add(reported);
+        }
+        Ok(completion.text)
     }
 
-    async fn call_ai(&self, prompt: &str) -> Result<String> {
+    /// Name prices are looked up by: the model, or the Azure deployment.
+    fn model_name(&self) -> &str {
         match self.provider {
-            AiProvider::Ollama => self.call_ollama(prompt).await,
-            AiProvider::LmStudio => self.call_lmstudio(prompt).await,
-            AiProvider::KeyhanStudio => self.call_keyhanstudio(prompt).await,
-            AiProvider::OpenAi => self.call_openai(prompt).await,
-            AiProvider::Anthropic => self.call_anthropic(prompt).await,
+            AiProvider::Ollama => &self.ollama.model,
+            AiProvider::LmStudio => &self.lmstudio.model,
+            AiProvider::KeyhanStudio => "keyhanstudio",
+            AiProvider::OpenAi => &self.openai.model,
+            AiProvider::AzureOpenAi => &self.azure_openai.deployment,
+            AiProvider::OpenAiCompatible => &self.openai_compatible.model,
+            AiProvider::Anthropic => &self.anthropic.model,
+            AiProvider::LocalGguf => &self.local_gguf.model_path,
+            AiProvider::Rules => "rules",
         }
     }
 
-    async fn call_ollama(&self, prompt: &str) -> Result<String> {
-        let client = reqwest::Client::new();
-        let url = format!("{}/api/generate", self.ollama_endpoint);
+    /// Estimated cost of `usage`; local providers are free and unknown models
+    /// have no estimate.
+    fn estimate_cost(&self, usage: &TokenUsage) -> Option<f64> {
+        if matches!(
+            self.provider,
+            AiProvider::Ollama | AiProvider::LmStudio | AiProvider::LocalGguf | AiProvider::Rules
+        ) {
+            return Some(0.0);
+        }
+        model_price(self.model_name(), &self.pricing)
+            .map(|price| price.cost(usage.input_tokens, usage.output_tokens))
+    }
 
-        let body = serde_json::json!({
-            "model": self.ollama_model,
+    async fn call_ollama(
+        &self,
+        system: &str,
+        prompt: &str,
+        progress: &ProgressReporter,
+    ) -> Result<Completion> {
+        let timeout_secs = self.ollama.timeout_secs;
+        let client = http_client(&self.network, Some(Duration::from_secs(timeout_secs)))?;
+        let url = format!("{}/api/generate", self.ollama.endpoint);
+
+        let mut body = serde_json::json!({
+            "model": self.ollama.model,
             "prompt": prompt,
-            "stream": false,
+            "stream": self.stream,
             "format": "json",
+            "options": { "num_predict": self.ollama.max_tokens },
         });
+        // Ollama models keep their own system prompt unless one is configured
+        if !self.prompts.system_file.is_empty() {
+            body["system"] = serde_json::Value::from(system);
+        }
 
-        let resp = client
-            .post(&url)
-            .json(&body)
-            .send()
-            .await
-            .context("Calling Ollama API")?;
-
-        let status = resp.status();
-        let text = resp.text().await?;
+        let req = client.post(&url).json(&body);
+        if self.stream {
+            return stream_completion(
+                req,
+                AiProvider::Ollama,
+                timeout_secs,
+                "Calling Ollama API",
+                StreamFormat::Ollama,
+                progress,
+            )
+            .await;
+        }
+        let (status, text) =
+            send_request(req, AiProvider::Ollama, timeout_secs, "Calling Ollama API").await?;
 
         if !status.is_success() {
             anyhow::bail!("Ollama API error ({status}): {text}");
@@ -156,34 +511,44 @@ impl AiBackend {
             .unwrap_or(&text)
             .to_string();
 
-        Ok(response)
+        Ok(Completion {
+            usage: parse_usage(&parsed),
+            text: response,
+        })
     }
 
-    async fn call_keyhanstudio(&self, prompt: &str) -> Result<String> {
+    async fn call_keyhanstudio(&self, system: &str, prompt: &str) -> Result<Completion> {
         anyhow::ensure!(
-            !self.keyhanstudio_endpoint.is_empty(),
+            !self.keyhanstudio.endpoint.is_empty(),
             "KeyhanStudio endpoint not configured. Set it in ~/.config/mastering/config.toml"
         );
 
-        let client = reqwest::Client::new();
+        let timeout_secs = self.keyhanstudio.timeout_secs;
+        let client = http_client(&self.network, Some(Duration::from_secs(timeout_secs)))?;
 
         let body = serde_json::json!({
             "messages": [
-                {"role": "system", "content": SYSTEM_PROMPT},
+                {"role": "system", "content": system},
                 {"role": "user", "content": prompt}
             ],
             "response_format": { "type": "json_object" },
+            "max_tokens": self.keyhanstudio.max_tokens,
         });
 
-        let mut req = client.post(&self.keyhanstudio_endpoint).json(&body);
+        let mut req = client.post(&self.keyhanstudio.endpoint).json(&body);
 
-        if !self.keyhanstudio_api_key.is_empty() {
-            req = req.header("Authorization", format!("Bearer {}", self.keyhanstudio_api_key));
+        let api_key = secrets::api_key(AiProvider::KeyhanStudio, &self.keyhanstudio.api_key);
+        if !api_key.is_empty() {
+            req = req.header("Authorization", format!("Bearer {api_key}"));
         }
 
-        let resp = req.send().await.context("Calling KeyhanStudio API")?;
-        let status = resp.status();
-        let text = resp.text().await?;
+        let (status, text) = send_request(
+            req,
+            AiProvider::KeyhanStudio,
+            timeout_secs,
+            "Calling KeyhanStudio API",
+        )
+        .await?;
 
         if !status.is_success() {
             anyhow::bail!("KeyhanStudio API error ({status}): {text}");
@@ -196,36 +561,57 @@ impl AiBackend {
             .unwrap_or(&text)
             .to_string();
 
-        Ok(content)
+        Ok(Completion {
+            usage: parse_usage(&parsed),
+            text: content,
+        })
     }
 
-    async fn call_openai(&self, prompt: &str) -> Result<String> {
+    async fn call_openai(
+        &self,
+        system: &str,
+        prompt: &str,
+        progress: &ProgressReporter,
+    ) -> Result<Completion> {
+        let api_key = secrets::api_key(AiProvider::OpenAi, &self.openai.api_key);
         anyhow::ensure!(
-            !self.openai_api_key.is_empty(),
-            "OpenAI API key not configured. Set it in ~/.config/mastering/config.toml"
+            !api_key.is_empty(),
+            "OpenAI API key not configured. Set OPENAI_API_KEY, store it with `mastering config --set-key openai`, or set it in ~/.config/mastering/config.toml"
         );
 
-        let client = reqwest::Client::new();
+        let timeout_secs = self.openai.timeout_secs;
+        let client = http_client(&self.network, Some(Duration::from_secs(timeout_secs)))?;
 
-        let body = serde_json::json!({
-            "model": self.openai_model,
+        let mut body = serde_json::json!({
+            "model": self.openai.model,
             "messages": [
-                {"role": "system", "content": SYSTEM_PROMPT},
+                {"role": "system", "content": system},
                 {"role": "user", "content": prompt}
             ],
             "response_format": { "type": "json_object" },
+            "max_tokens": self.openai.max_tokens,
         });
+        if self.stream {
+            enable_sse_stream(&mut body, true);
+        }
 
-        let resp = client
+        let req = client
             .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.openai_api_key))
-            .json(&body)
-            .send()
-            .await
-            .context("Calling OpenAI API")?;
-
-        let status = resp.status();
-        let text = resp.text().await?;
+            .header("Authorization", format!("Bearer {api_key}"))
+            .json(&body);
+        if self.stream {
+            return stream_completion(
+                req,
+                AiProvider::OpenAi,
+                timeout_secs,
+                "Calling OpenAI API",
+                StreamFormat::OpenAi,
+                progress,
+            )
+            .await;
+        }
+        let (status, text) =
+            send_request(req, AiProvider::OpenAi, timeout_secs, "Calling OpenAI API").await?;
 
         if !status.is_success() {
             anyhow::bail!("OpenAI API error ({status}): {text}");
@@ -237,38 +623,184 @@ impl AiBackend {
             .unwrap_or(&text)
             .to_string();
 
-        Ok(content)
+        Ok(Completion {
+            usage: parse_usage(&parsed),
+            text: content,
+        })
+    }
+
+    async fn call_azure_openai(
+        &self,
+        system: &str,
+        prompt: &str,
+        progress: &ProgressReporter,
+    ) -> Result<Completion> {
+        let azure = &self.azure_openai;
+        let api_key = secrets::api_key(AiProvider::AzureOpenAi, &azure.api_key);
+        anyhow::ensure!(
+            !azure.endpoint.is_empty() && !azure.deployment.is_empty() && !api_key.is_empty(),
+            "Azure OpenAI endpoint and deployment must be set in ~/.config/mastering/config.toml, and the API key there, in AZURE_OPENAI_API_KEY or in the OS keyring"
+        );
+
+        let client = http_client(&self.network, Some(Duration::from_secs(azure.timeout_secs)))?;
+
+        // The deployment determines the model, so the body carries none
+        let mut body = serde_json::json!({
+            "messages": [
+                {"role": "system", "content": system},
+                {"role": "user", "content": prompt}
+            ],
+            "response_format": { "type": "json_object" },
+            "max_tokens": azure.max_tokens,
+        });
+        if self.stream {
+            enable_sse_stream(&mut body, true);
+        }
+
+        let req = client
+            .post(azure_chat_url(azure))
+            .header("api-key", &api_key)
+            .json(&body);
+        if self.stream {
+            return stream_completion(
+                req,
+                AiProvider::AzureOpenAi,
+                azure.timeout_secs,
+                "Calling Azure OpenAI API",
+                StreamFormat::OpenAi,
+                progress,
+            )
+            .await;
+        }
+        let (status, text) = send_request(
+            req,
+            AiProvider::AzureOpenAi,
+            azure.timeout_secs,
+            "Calling Azure OpenAI API",
+        )
+        .await?;
+
+        if !status.is_success() {
+            anyhow::bail!("Azure OpenAI API error ({status}): {text}");
+        }
+
+        let parsed: serde_json::Value = serde_json::from_str(&text)?;
+        let content = parsed["choices"][0]["message"]["content"]
+            .as_str()
+            .unwrap_or(&text)
+            .to_string();
+
+        Ok(Completion {
+            usage: parse_usage(&parsed),
+            text: content,
+        })
+    }
+
+    async fn call_openai_compatible(
+        &self,
+        system: &str,
+        prompt: &str,
+        progress: &ProgressReporter,
+    ) -> Result<Completion> {
+        let compat = &self.openai_compatible;
+        anyhow::ensure!(
+            !compat.base_url.is_empty() && !compat.model.is_empty(),
+            "OpenAI-compatible base_url and model must be set in ~/.config/mastering/config.toml"
+        );
+
+        let client = http_client(
+            &self.network,
+            Some(Duration::from_secs(compat.timeout_secs)),
+        )?;
+
+        // response_format is left out: not every gateway supports JSON mode,
+        // and parse_mastering_params copes with fenced output
+        let mut body = serde_json::json!({
+            "model": compat.model,
+            "messages": [
+                {"role": "system", "content": system},
+                {"role": "user", "content": prompt}
+            ],
+            "max_tokens": compat.max_tokens,
+        });
+        if self.stream {
+            enable_sse_stream(&mut body, false);
+        }
+
+        let mut req = client.post(compat_chat_url(&compat.base_url)).json(&body);
+        let api_key = secrets::api_key(AiProvider::OpenAiCompatible, &compat.api_key);
+        if !api_key.is_empty() {
+            req = req.header("Authorization", format!("Bearer {api_key}"));
+        }
+
+        let context = format!("Calling OpenAI-compatible API at {}", compat.base_url);
+        if self.stream {
+            return stream_completion(
+                req,
+                AiProvider::OpenAiCompatible,
+                compat.timeout_secs,
+                &context,
+                StreamFormat::OpenAi,
+                progress,
+            )
+            .await;
+        }
+        let (status, text) = send_request(
+            req,
+            AiProvider::OpenAiCompatible,
+            compat.timeout_secs,
+            &context,
+        )
+        .await?;
+
+        if !status.is_success() {
+            anyhow::bail!("OpenAI-compatible API error ({status}): {text}");
+        }
+
+        let parsed: serde_json::Value = serde_json::from_str(&text)?;
+        let content = parsed["choices"][0]["message"]["content"]
+            .as_str()
+            .unwrap_or(&text)
+            .to_string();
+
+        Ok(Completion {
+            usage: parse_usage(&parsed),
+            text: content,
+        })
     }
 
-    async fn call_anthropic(&self, prompt: &str) -> Result<String> {
+    async fn call_anthropic(&self, system: &str, prompt: &str) -> Result<Completion> {
+        let api_key = secrets::api_key(AiProvider::Anthropic, &self.anthropic.api_key);
         anyhow::ensure!(
-            !self.anthropic_api_key.is_empty(),
-            "Anthropic API key not configured. Set it in ~/.config/mastering/config.toml"
+            !api_key.is_empty(),
+            "Anthropic API key not configured. Set ANTHROPIC_API_KEY, store it with `mastering config --set-key anthropic`, or set it in ~/.config/mastering/config.toml"
         );
 
-        let client = reqwest::Client::new();
+        let timeout_secs = self.anthropic.timeout_secs;
+        let client = http_client(&self.network, Some(Duration::from_secs(timeout_secs)))?;
 
         let body = serde_json::json!({
-            "model": self.anthropic_model,
-            "max_tokens": 4096,
-            "system": SYSTEM_PROMPT,
+            "model": self.anthropic.model,
+            "max_tokens": self.anthropic.max_tokens,
+            "system": system,
             "messages": [
                 {"role": "user", "content": prompt}
             ],
         });
 
-        let resp = client
+        let req = client
             .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", &self.anthropic_api_key)
+            .header("x-api-key", &api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .context("Calling Anthropic API")?;
-
-        let status = resp.status();
-        let text = resp.text().await?;
+            .json(&body);
+        let (status, text) = send_request(
+            req,
+            AiProvider::Anthropic,
+            timeout_secs,
+            "Calling Anthropic API",
+        )
+        .await?;
 
         if !status.is_success() {
             anyhow::bail!("Anthropic API error ({status}): {text}");
@@ -280,35 +812,78 @@ impl AiBackend {
             .unwrap_or(&text)
             .to_string();
 
-        Ok(content)
+        Ok(Completion {
+            usage: parse_usage(&parsed),
+            text: content,
+        })
     }
 
-    async fn call_lmstudio(&self, prompt: &str) -> Result<String> {
-        let client = reqwest::Client::new();
+    #[cfg(feature = "local-llm")]
+    async fn call_local_gguf(&self, system: &str, prompt: &str) -> Result<Completion> {
+        anyhow::ensure!(
+            !self.local_gguf.model_path.is_empty(),
+            "GGUF model path not configured. Set ai.local_gguf.model_path in ~/.config/mastering/config.toml"
+        );
+
+        let config = self.local_gguf.clone();
+        let system = system.to_string();
+        let prompt = prompt.to_string();
+        let text =
+            tokio::task::spawn_blocking(move || super::gguf::generate(&config, &system, &prompt))
+                .await
+                .context("Local inference task panicked")??;
+        Ok(Completion { text, usage: None })
+    }
+
+    #[cfg(not(feature = "local-llm"))]
+    async fn call_local_gguf(&self, _system: &str, _prompt: &str) -> Result<Completion> {
+        anyhow::bail!(
+            "Cannot run {}: this build does not include embedded inference (rebuild with --features local-llm)",
+            self.local_gguf.model_path
+        )
+    }
+
+    async fn call_lmstudio(
+        &self,
+        system: &str,
+        prompt: &str,
+        progress: &ProgressReporter,
+    ) -> Result<Completion> {
+        let timeout_secs = self.lmstudio.timeout_secs;
+        let client = http_client(&self.network, Some(Duration::from_secs(timeout_secs)))?;
         let url = format!(
             "{}/chat/completions",
-            self.lmstudio_endpoint.trim_end_matches('/')
+            self.lmstudio.endpoint.trim_end_matches('/')
         );
 
-        let body = serde_json::json!({
-            "model": self.lmstudio_model,
+        let mut body = serde_json::json!({
+            "model": self.lmstudio.model,
             "messages": [
-                {"role": "system", "content": LMSTUDIO_SYSTEM_PROMPT},
+                {"role": "system", "content": system},
                 {"role": "user", "content": prompt}
             ],
             "response_format": { "type": "json_object" },
             "temperature": 0.3,
+            "max_tokens": self.lmstudio.max_tokens,
         });
+        if self.stream {
+            enable_sse_stream(&mut body, false);
+        }
 
-        let resp = client
-            .post(&url)
-            .json(&body)
-            .send()
-            .await
-            .context("Calling LM Studio API — is LM Studio running and a model loaded?")?;
-
-        let status = resp.status();
-        let text = resp.text().await?;
+        let req = client.post(&url).json(&body);
+        let context = "Calling LM Studio API — is LM Studio running and a model loaded?";
+        if self.stream {
+            return stream_completion(
+                req,
+                AiProvider::LmStudio,
+                timeout_secs,
+                context,
+                StreamFormat::OpenAi,
+                progress,
+            )
+            .await;
+        }
+        let (status, text) = send_request(req, AiProvider::LmStudio, timeout_secs, context).await?;
 
         if !status.is_success() {
             anyhow::bail!("LM Studio API error ({status}): {text}");
@@ -320,38 +895,48 @@ impl AiBackend {
             .unwrap_or(&text)
             .to_string();
 
-        Ok(content)
+        Ok(Completion {
+            usage: parse_usage(&parsed),
+            text: content,
+        })
     }
 
     pub async fn check_available(&self) -> Result<bool> {
         match self.provider {
             AiProvider::Ollama => {
-                let client = reqwest::Client::builder()
-                    .timeout(std::time::Duration::from_secs(3))
-                    .build()?;
-                let resp = client.get(&self.ollama_endpoint).send().await;
+                let client = http_client(&self.network, Some(Duration::from_secs(3)))?;
+                let resp = client.get(&self.ollama.endpoint).send().await;
                 Ok(resp.is_ok())
             }
             AiProvider::LmStudio => {
-                let client = reqwest::Client::builder()
-                    .timeout(std::time::Duration::from_secs(3))
-                    .build()?;
-                let url = self.lmstudio_endpoint.trim_end_matches('/');
+                let client = http_client(&self.network, Some(Duration::from_secs(3)))?;
+                let url = self.lmstudio.endpoint.trim_end_matches('/');
                 let resp = client.get(url).send().await;
                 Ok(resp.is_ok())
             }
-            AiProvider::KeyhanStudio => {
-                Ok(!self.keyhanstudio_endpoint.is_empty())
+            AiProvider::KeyhanStudio => Ok(!self.keyhanstudio.endpoint.is_empty()),
+            AiProvider::OpenAi => {
+                Ok(!secrets::api_key(AiProvider::OpenAi, &self.openai.api_key).is_empty())
             }
-            AiProvider::OpenAi => Ok(!self.openai_api_key.is_empty()),
-            AiProvider::Anthropic => Ok(!self.anthropic_api_key.is_empty()),
+            AiProvider::AzureOpenAi => Ok(!self.azure_openai.endpoint.is_empty()
+                && !self.azure_openai.deployment.is_empty()
+                && !secrets::api_key(AiProvider::AzureOpenAi, &self.azure_openai.api_key)
+                    .is_empty()),
+            AiProvider::OpenAiCompatible => Ok(!self.openai_compatible.base_url.is_empty()
+                && !self.openai_compatible.model.is_empty()),
+            AiProvider::Anthropic => {
+                Ok(!secrets::api_key(AiProvider::Anthropic, &self.anthropic.api_key).is_empty())
+            }
+            #[cfg(feature = "local-llm")]
+            AiProvider::LocalGguf => Ok(super::gguf::is_configured(&self.local_gguf)),
+            #[cfg(not(feature = "local-llm"))]
+            AiProvider::LocalGguf => Ok(false),
+            AiProvider::Rules => Ok(self.scripts_dir.join("apply_fx.py").exists()),
         }
     }
 
-    pub async fn lmstudio_status(endpoint: &str) -> Result<bool> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(3))
-            .build()?;
+    pub async fn lmstudio_status(endpoint: &str, network: &NetworkConfig) -> Result<bool> {
+        let client = http_client(network, Some(Duration::from_secs(3)))?;
         let url = endpoint.trim_end_matches('/');
         let resp = client.get(url).send().await;
         match resp {
@@ -360,10 +945,11 @@ impl AiBackend {
         }
     }
 
-    pub async fn lmstudio_models(endpoint: &str) -> Result<Vec<LmStudioModel>> {
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(5))
-            .build()?;
+    pub async fn lmstudio_models(
+        endpoint: &str,
+        network: &NetworkConfig,
+    ) -> Result<Vec<LmStudioModel>> {
+        let client = http_client(network, Some(Duration::from_secs(5)))?;
         let url = format!("{}/models", endpoint.trim_end_matches('/'));
         let resp = client.get(&url).send().await
             .context("Failed to connect to LM Studio. Is it running?")?;
@@ -393,8 +979,22 @@ The JSON must have this exact structure:
   "eq": [
     {"frequency": 80.0, "gain_db": 1.5, "q": 0.7, "band_type": "low_shelf"},
     {"frequency": 3000.0, "gain_db": -0.5, "q": 1.0, "band_type": "peak"},
-    {"frequency": 12000.0, "gain_db": 2.0, "q": 0.7, "band_type": "high_shelf"}
+    {"frequency": 12000.0, "gain_db": 2.0, "q": 0.7, "band_type": "high_shelf", "channel": "stereo"}
   ],
+  "de_esser": {
+    "enabled": false,
+    "frequency": 6500.0,
+    "threshold_db": -30.0,
+    "max_reduction_db": 6.0
+  },
+  "multiband": {
+    "crossovers": [200.0, 2500.0],
+    "bands": [
+      {"threshold_db": -20.0, "ratio": 2.0, "attack_ms": 30.0, "release_ms": 200.0, "makeup_gain_db": 0.0},
+      {"threshold_db": -18.0, "ratio": 1.5, "attack_ms": 15.0, "release_ms": 120.0, "makeup_gain_db": 0.0},
+      {"threshold_db": -22.0, "ratio": 2.0, "attack_ms": 5.0, "release_ms": 80.0, "makeup_gain_db": 0.0}
+    ]
+  },
   "compression": {
     "threshold_db": -18.0,
     "ratio": 2.5,
@@ -406,7 +1006,9 @@ The JSON must have this exact structure:
   "limiter": {
     "enabled": true,
     "ceiling_db": -1.0,
-    "release_ms": 50.0
+    "release_ms": 50.0,
+    "lookahead_ms": 5.0,
+    "oversampling": 4
   },
   "stereo": {
     "width": 1.0,
@@ -416,25 +1018,48 @@ The JSON must have this exact structure:
 }
 
 band_type must be one of: low_shelf, high_shelf, peak, low_pass, high_pass
+channel is optional: stereo (the default, both channels), mid (L+R) or side (L-R). Use mid/side bands to e.g. cut mud from the mid while adding air to the sides.
+Enable the de_esser only for harsh sibilance (a strong presence/brilliance band on vocal material); its frequency is the lower edge of the band it turns down, 5000-9000 Hz.
+multiband is optional: set it to null unless one frequency range needs its own dynamics control (a boomy low end, harsh upper mids). It runs before the main compressor and needs exactly one more band than crossovers, lowest first.
 Provide musically appropriate values based on the analysis. Be subtle with EQ (usually +/- 3dB max)."#;
 
+const STEM_SYSTEM_PROMPT: &str = r#"You are a professional mix and mastering engineer AI. A track has been separated into stems. Given an analysis of each stem, you suggest a gain and corrective EQ per stem that improve the balance of the mix before it is mastered. You respond ONLY with valid JSON, no explanations.
+
+The JSON must map stem names to adjustments, for example:
+{
+  "vocals": {"gain_db": 1.0, "eq": [{"frequency": 250.0, "gain_db": -1.5, "q": 1.0, "band_type": "peak"}]},
+  "drums": {"gain_db": 0.0, "eq": []},
+  "bass": {"gain_db": -1.0, "eq": [{"frequency": 40.0, "gain_db": 0.0, "q": 0.7, "band_type": "high_pass"}]}
+}
+
+band_type must be one of: low_shelf, high_shelf, peak, low_pass, high_pass
+Only use the stem names given. Leave a stem out, or give it a gain_db of 0.0 and no EQ, when it needs no change. Be subtle: gains within +/- 3dB and EQ within +/- 3dB are usually enough."#;
+
 const LMSTUDIO_SYSTEM_PROMPT: &str = r#"You are a professional audio mastering engineer. You receive audio analysis data and output precise mastering parameters as a JSON object. You output ONLY valid JSON — no explanations, no markdown, no commentary.
 
 STEP 1 - ANALYZE the audio:
 - Compare loudness to target: if LUFS is much louder than target, reduce gain; if much quieter, plan gain boost
 - Check dynamic range: wide range (>15dB) suggests gentle compression; narrow range (<6dB) suggests minimal compression
 - Examine frequency bands: identify if sub-bass is excessive, midrange is muddy, or brilliance is lacking
+- Read the spectral descriptors: a low centroid or a tilt steeper than about -5 dB/octave sounds dark, a high centroid or a tilt flatter than about -3 dB/octave bright
 - Note stereo width: values far from 1.0 may need correction
 
 STEP 2 - RECOMMEND parameters:
 - EQ: Apply corrective EQ first (cut problematic frequencies), then gentle enhancement (usually +/- 2dB max)
   - Use low_shelf for bass adjustments (60-120Hz), peak for midrange (250-4000Hz), high_shelf for air (8000-12000Hz)
   - Q values: 0.5-1.0 for broad shaping, 1.0-3.0 for surgical corrections
+  - channel: stereo (default), mid or side; e.g. cut low-mid mud in the mid, add air to the side, high_pass the side below 100Hz to tighten the bass
 - Compression: Match to genre and dynamic range
   - Gentle: ratio 1.5-2.5, slow attack (15-30ms), auto release
+  - When tempo_bpm is known, time release_ms to a note: 60000 / tempo_bpm is a 1/4 note, half that a 1/8 note, a quarter of it a 1/16 note
   - Moderate: ratio 2.5-4.0, medium attack (5-15ms)
   - Never exceed ratio 6.0 for mastering
-- Limiter: Set ceiling at -1.0 to -0.5 dB, moderate release (30-100ms)
+- De-esser: Enable only when the presence and brilliance bands suggest harsh sibilance on vocals
+  - frequency 5000-9000Hz (lower edge of the band turned down), max_reduction_db 3-6dB
+- Multiband: Optional, null by default. Use it when one range is uneven (boomy bass, harsh mids) and the main compressor should stay gentle
+  - 2-4 bands, one more band than crossovers, lowest first; ratios 1.5-3.0, slower attack and release in the low band
+- Limiter: Set ceiling at -1.0 to -0.5 dBTP, moderate release (30-100ms)
+  - lookahead_ms 1-10 (longer is smoother on dense material), oversampling 4 for true-peak limiting
 - Stereo: Width 0.9-1.1 is safe. Adjust only if analysis shows problems.
 - Target LUFS: Match the specified target precisely.
 
@@ -443,8 +1068,22 @@ STEP 3 - OUTPUT this exact JSON structure:
   "eq": [
     {"frequency": 80.0, "gain_db": 1.5, "q": 0.7, "band_type": "low_shelf"},
     {"frequency": 3000.0, "gain_db": -0.5, "q": 1.0, "band_type": "peak"},
-    {"frequency": 12000.0, "gain_db": 2.0, "q": 0.7, "band_type": "high_shelf"}
+    {"frequency": 12000.0, "gain_db": 2.0, "q": 0.7, "band_type": "high_shelf", "channel": "stereo"}
   ],
+  "de_esser": {
+    "enabled": false,
+    "frequency": 6500.0,
+    "threshold_db": -30.0,
+    "max_reduction_db": 6.0
+  },
+  "multiband": {
+    "crossovers": [200.0, 2500.0],
+    "bands": [
+      {"threshold_db": -20.0, "ratio": 2.0, "attack_ms": 30.0, "release_ms": 200.0, "makeup_gain_db": 0.0},
+      {"threshold_db": -18.0, "ratio": 1.5, "attack_ms": 15.0, "release_ms": 120.0, "makeup_gain_db": 0.0},
+      {"threshold_db": -22.0, "ratio": 2.0, "attack_ms": 5.0, "release_ms": 80.0, "makeup_gain_db": 0.0}
+    ]
+  },
   "compression": {
     "threshold_db": -18.0,
     "ratio": 2.5,
@@ -456,7 +1095,9 @@ STEP 3 - OUTPUT this exact JSON structure:
   "limiter": {
     "enabled": true,
     "ceiling_db": -1.0,
-    "release_ms": 50.0
+    "release_ms": 50.0,
+    "lookahead_ms": 5.0,
+    "oversampling": 4
   },
   "stereo": {
     "width": 1.0,
@@ -466,14 +1107,354 @@ STEP 3 - OUTPUT this exact JSON structure:
 }
 
 band_type must be one of: low_shelf, high_shelf, peak, low_pass, high_pass
-Value ranges: EQ gain -6 to +6 dB, Q 0.3 to 5.0, compression ratio 1.0 to 6.0, stereo width 0.5 to 1.5.
+Value ranges: EQ gain -6 to +6 dB, Q 0.3 to 5.0, de-esser reduction 0 to 6 dB, compression ratio 1.0 to 6.0, stereo width 0.5 to 1.5.
 IMPORTANT: Return ONLY the JSON object. No other text."#;
 
+/// HTTP client for AI requests, honouring the proxy and extra root
+/// certificates of `network`.
+pub fn http_client(network: &NetworkConfig, timeout: Option<Duration>) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(timeout) = timeout {
+        builder = builder.timeout(timeout);
+    }
+    if !network.proxy.is_empty() {
+        let proxy = reqwest::Proxy::all(&network.proxy)
+            .with_context(|| format!("Invalid proxy URL: {}", network.proxy))?
+            .no_proxy(reqwest::NoProxy::from_string(&network.no_proxy));
+        builder = builder.proxy(proxy);
+    }
+    if !network.ca_cert_file.is_empty() {
+        let pem = std::fs::read(&network.ca_cert_file)
+            .with_context(|| format!("Reading CA certificates from {}", network.ca_cert_file))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Parsing CA certificates in {}", network.ca_cert_file))?;
+        anyhow::ensure!(
+            !certs.is_empty(),
+            "No certificates found in {}",
+            network.ca_cert_file
+        );
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    builder.build().context("Building the HTTP client")
+}
+
+/// Send an AI request and read the response body.
+///
+/// A request that outlasts `timeout_secs` fails with a retryable
+/// [`MasteringError::NetworkTimeout`] naming the setting to raise.
+async fn send_request(
+    req: reqwest::RequestBuilder,
+    provider: AiProvider,
+    timeout_secs: u64,
+    context: &str,
+) -> Result<(reqwest::StatusCode, String)> {
+    let check = |e| request_error(e, provider, timeout_secs, context);
+    let resp = req.send().await.map_err(check)?;
+    let status = resp.status();
+    let text = resp.text().await.map_err(check)?;
+    Ok((status, text))
+}
+
+fn request_error(
+    e: reqwest::Error,
+    provider: AiProvider,
+    timeout_secs: u64,
+    context: &str,
+) -> anyhow::Error {
+    if e.is_timeout() {
+        timeout_error(provider, timeout_secs).into()
+    } else {
+        anyhow::Error::new(e).context(context.to_string())
+    }
+}
+
+fn timeout_error(provider: AiProvider, timeout_secs: u64) -> MasteringError {
+    let section = provider.to_string().replace('-', "_");
+    MasteringError::NetworkTimeout {
+        message: format!("{provider} sent no response within {timeout_secs}s"),
+        can_retry: true,
+        suggested_action: format!(
+            "Try again, or raise ai.{section}.timeout_secs in ~/.config/mastering/config.toml if the model needs longer."
+        ),
+    }
+}
+
+/// Ask an OpenAI-style API for server-sent events. Only OpenAI and Azure are
+/// sent `stream_options`; other gateways may reject fields they do not know.
+fn enable_sse_stream(body: &mut serde_json::Value, include_usage: bool) {
+    body["stream"] = serde_json::Value::Bool(true);
+    if include_usage {
+        body["stream_options"] = serde_json::json!({ "include_usage": true });
+    }
+}
+
+/// Wire format of a streamed completion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamFormat {
+    /// Ollama's newline-delimited JSON objects.
+    Ollama,
+    /// Server-sent events carrying OpenAI chat completion chunks.
+    OpenAi,
+}
+
+/// Tokens received between two progress reports of a streamed response.
+const STREAM_REPORT_INTERVAL: u64 = 8;
+
+/// Send a streaming AI request and collect the response, reporting the
+/// tokens received so far to `progress`.
+async fn stream_completion(
+    req: reqwest::RequestBuilder,
+    provider: AiProvider,
+    timeout_secs: u64,
+    context: &str,
+    format: StreamFormat,
+    progress: &ProgressReporter,
+) -> Result<Completion> {
+    let check = |e| request_error(e, provider, timeout_secs, context);
+    let mut resp = req.send().await.map_err(check)?;
+    let status = resp.status();
+    if !status.is_success() {
+        let text = resp.text().await.map_err(check)?;
+        anyhow::bail!("{provider} API error ({status}): {text}");
+    }
+
+    progress.report(
+        PipelineStage::Processing,
+        0.0,
+        format!("{provider} model thinking…"),
+    );
+    let mut decoder = StreamDecoder::new(format);
+    let mut reported = 0;
+    while let Some(chunk) = resp.chunk().await.map_err(check)? {
+        decoder.push(&chunk)?;
+        if decoder.tokens >= reported + STREAM_REPORT_INTERVAL {
+            reported = decoder.tokens;
+            progress.report_tokens(
+                PipelineStage::Processing,
+                0.0,
+                format!("{provider} model thinking… {reported} tokens"),
+                reported,
+            );
+        }
+    }
+    debug!("Streamed {} tokens from {provider}", decoder.tokens);
+    decoder.finish()
+}
+
+/// Incremental parser of a streamed completion body.
+struct StreamDecoder {
+    format: StreamFormat,
+    /// Bytes of a line whose end has not arrived yet.
+    pending: Vec<u8>,
+    text: String,
+    /// Content chunks received, one token each for the supported APIs.
+    tokens: u64,
+    usage: Option<TokenUsage>,
+}
+
+impl StreamDecoder {
+    fn new(format: StreamFormat) -> Self {
+        Self {
+            format,
+            pending: Vec::new(),
+            text: String::new(),
+            tokens: 0,
+            usage: None,
+        }
+    }
+
+    /// Consume the next chunk of the body.
+    fn push(&mut self, chunk: &[u8]) -> Result<()> {
+        self.pending.extend_from_slice(chunk);
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.line(String::from_utf8_lossy(&line).trim())?;
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, line: &str) -> Result<()> {
+        let payload = match self.format {
+            StreamFormat::Ollama => line,
+            // Event names, ids and keep-alive comments carry no content
+            StreamFormat::OpenAi => match line.strip_prefix("data:") {
+                Some(data) => data.trim(),
+                None => return Ok(()),
+            },
+        };
+        if payload.is_empty() || payload == "[DONE]" {
+            return Ok(());
+        }
+
+        let value: serde_json::Value = serde_json::from_str(payload)
+            .with_context(|| format!("Malformed chunk in streamed AI response: {payload}"))?;
+        if let Some(error) = value.get("error") {
+            anyhow::bail!("AI provider failed mid-response: {error}");
+        }
+        let delta = match self.format {
+            StreamFormat::Ollama => value["response"].as_str(),
+            StreamFormat::OpenAi => value["choices"][0]["delta"]["content"].as_str(),
+        };
+        if let Some(delta) = delta.filter(|d| !d.is_empty()) {
+            self.text.push_str(delta);
+            self.tokens += 1;
+        }
+        // Ollama's final object and OpenAI's last chunk carry the totals
+        if let Some(usage) = parse_usage(&value) {
+            self.usage = Some(usage);
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Completion> {
+        let rest = std::mem::take(&mut self.pending);
+        self.line(String::from_utf8_lossy(&rest).trim())?;
+        Ok(Completion {
+            text: self.text,
+            usage: self.usage,
+        })
+    }
+}
+
+/// Text of a model response with the tokens it used, when reported.
+struct Completion {
+    text: String,
+    usage: Option<TokenUsage>,
+}
+
+/// Token counts of a response body: OpenAI-style `usage.prompt_tokens`,
+/// Anthropic's `usage.input_tokens` or Ollama's `prompt_eval_count`.
+fn parse_usage(response: &serde_json::Value) -> Option<TokenUsage> {
+    let (input, output) = match response.get("usage") {
+        Some(u) => (
+            u.get("prompt_tokens").or_else(|| u.get("input_tokens"))?,
+            u.get("completion_tokens")
+                .or_else(|| u.get("output_tokens"))?,
+        ),
+        None => (
+            response.get("prompt_eval_count")?,
+            response.get("eval_count")?,
+        ),
+    };
+    Some(TokenUsage {
+        requests: 0,
+        input_tokens: input.as_u64()?,
+        output_tokens: output.as_u64()?,
+        estimated_cost_usd: None,
+    })
+}
+
+/// Built-in prices in USD per million input and output tokens, matched by
+/// model name prefix; more specific prefixes come first.
+const MODEL_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-5-sonnet", 3.00, 15.00),
+    ("claude-3-7-sonnet", 3.00, 15.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-opus-4", 15.00, 75.00),
+    ("claude-3-opus", 15.00, 75.00),
+];
+
+/// Price of `model`: a configured override, else the built-in table.
+fn model_price(model: &str, overrides: &BTreeMap<String, ModelPrice>) -> Option<ModelPrice> {
+    if let Some(price) = overrides.get(model) {
+        return Some(*price);
+    }
+    MODEL_PRICES
+        .iter()
+        .find(|(prefix, _, _)| model.starts_with(prefix))
+        .map(|&(_, input_per_mtok, output_per_mtok)| ModelPrice {
+            input_per_mtok,
+            output_per_mtok,
+        })
+}
+
+/// Chat completions URL of an Azure OpenAI deployment.
+fn azure_chat_url(azure: &AzureOpenAiConfig) -> String {
+    format!(
+        "{}/openai/deployments/{}/chat/completions?api-version={}",
+        azure.endpoint.trim_end_matches('/'),
+        azure.deployment,
+        azure.api_version
+    )
+}
+
+/// Chat completions URL under an OpenAI-compatible API root.
+fn compat_chat_url(base_url: &str) -> String {
+    format!("{}/chat/completions", base_url.trim_end_matches('/'))
+}
+
+async fn read_template(path: &str) -> Result<String> {
+    tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Reading prompt template {path}"))
+}
+
+/// Fill the placeholders of a user prompt template.
+///
+/// Supported: `{analysis}`, `{preset}`, `{target_lufs}`, `{no_limiter}`,
+/// `{genre}` and `{brief}`.
+fn render_prompt(
+    template: &str,
+    analysis_json: &str,
+    opts: &MasteringOptions,
+    genre: Option<&str>,
+) -> String {
+    let preset = opts
+        .preset
+        .map(preset_summary)
+        .unwrap_or_else(|| "none".into());
+
+    template
+        .replace("{analysis}", analysis_json)
+        .replace("{preset}", &preset)
+        .replace("{target_lufs}", &opts.target_lufs.to_string())
+        .replace("{no_limiter}", &opts.no_limiter.to_string())
+        .replace("{genre}", genre.unwrap_or("unknown"))
+        .replace("{brief}", opts.brief.as_deref().unwrap_or("none"))
+}
+
+/// A preset's name and description, with its loudness range guidance.
+fn preset_summary(preset: Preset) -> String {
+    let mut summary = format!("{} — {}", preset, preset.description());
+    if let Some((min, max)) = preset.loudness_range_lu() {
+        summary.push_str(&format!(
+            "; aim for a loudness range of {min:.0}–{max:.0} LU"
+        ));
+    }
+    summary
+}
+
 fn build_mastering_prompt(analysis_json: &str, opts: &MasteringOptions) -> String {
     let preset_info = opts
         .preset
-        .map(|p| format!("\nPreset: {} — {}", p, p.description()))
+        .map(|p| format!("\nPreset: {}", preset_summary(p)))
+        .unwrap_or_default();
+    let brief_info = opts
+        .brief
+        .as_deref()
+        .map(|b| {
+            format!("\nBrief: {b}\nTailor the EQ, compression and stereo image to this brief.")
+        })
+        .unwrap_or_default();
+    let ceiling_info = opts
+        .ceiling_db
+        .map(|c| format!("\nLimiter Ceiling: at most {c:.1} dBTP"))
         .unwrap_or_default();
+    let speech_info = if opts.speech {
+        "\nContent: spoken word. Favour intelligibility: high-pass around 80 Hz, enable the de-esser, \
+         compress gently but steadily, and do not widen the stereo image."
+    } else {
+        ""
+    };
 
     format!(
         r#"Analyze this audio and provide mastering parameters as JSON.
@@ -482,18 +1463,275 @@ Audio Analysis:
 {analysis_json}
 
 Target LUFS: {target_lufs}
-No Limiter: {no_limiter}{preset_info}
+No Limiter: {no_limiter}{ceiling_info}{preset_info}{speech_info}{brief_info}
 
-Provide your mastering parameters as a JSON object with keys: eq, compression, limiter, stereo, target_lufs."#,
+Provide your mastering parameters as a JSON object with keys: eq, de_esser, multiband, compression, limiter, stereo, target_lufs."#,
         target_lufs = opts.target_lufs,
         no_limiter = opts.no_limiter,
     )
 }
 
-fn parse_mastering_params(response: &str) -> Result<MasteringParams> {
+/// Prompt asking for stem adjustments, with the analysis of each stem.
+fn build_stem_prompt(stems: &[(String, AudioAnalysis)], brief: Option<&str>) -> Result<String> {
+    let mut analyses = serde_json::Map::new();
+    for (name, analysis) in stems {
+        analyses.insert(
+            name.clone(),
+            serde_json::json!({
+                "lufs_integrated": analysis.lufs_integrated,
+                "peak_db": analysis.peak_db,
+                "dynamic_range_db": analysis.dynamic_range_db,
+                "stereo_width": analysis.stereo_width,
+                "frequency_bands": analysis.frequency_bands,
+            }),
+        );
+    }
+    let brief_info = brief
+        .map(|b| format!("\nBrief: {b}\nBalance the stems toward this brief."))
+        .unwrap_or_default();
+
+    Ok(format!(
+        r#"Suggest per-stem adjustments for this track.
+
+Stem Analysis:
+{analysis}{brief_info}
+
+Stems: {names}
+Provide a JSON object mapping stem names to {{"gain_db", "eq"}}."#,
+        analysis = serde_json::to_string_pretty(&analyses)?,
+        names = stems
+            .iter()
+            .map(|(n, _)| n.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+    ))
+}
+
+/// Parse the AI's stem adjustments, dropping unknown stems and clamping
+/// values to `limits`.
+fn parse_stem_adjustments(
+    response: &str,
+    stems: &[&str],
+    limits: &ParamLimits,
+) -> Result<(BTreeMap<String, StemAdjustment>, Vec<ParamCorrection>)> {
+    let mut adjustments: BTreeMap<String, StemAdjustment> =
+        serde_json::from_value(extract_json(response)?)
+            .context("The AI's stem adjustments are not in the expected format")?;
+    let mut corrections = Vec::new();
+    adjustments.retain(|name, _| {
+        let known = stems.contains(&name.as_str());
+        if !known {
+            corrections.push(ParamCorrection::new(
+                format!("stems.{name}"),
+                "not a stem of this track; dropped",
+            ));
+        }
+        known
+    });
+    corrections.extend(validate::clamp_stem_adjustments(&mut adjustments, limits));
+    Ok((adjustments, corrections))
+}
+
+/// Loudness error that triggers another pass.
+const PASS_LUFS_TOLERANCE: f64 = 0.5;
+/// Change in a band's share of the energy, relative to the source, that is
+/// reported back to the AI.
+const PASS_BAND_SHIFT_DB: f64 = 2.0;
+
+/// Describe how a rendered pass misses its targets, or `None` when it is
+/// on target.
+///
+/// Band shifts compare the output's spectral balance with the source, so the
+/// AI can tell whether its EQ overshot.
+fn describe_deviation(
+    source: &AudioAnalysis,
+    output: &AudioAnalysis,
+    params: &MasteringParams,
+    target_lufs: f64,
+) -> Option<String> {
+    let mut issues = Vec::new();
+
+    let lufs_error = output.lufs_integrated - target_lufs;
+    if lufs_error.abs() > PASS_LUFS_TOLERANCE {
+        issues.push(format!(
+            "output is {:.1} LU too {} ({:.1} LUFS against a {target_lufs:.1} LUFS target)",
+            lufs_error.abs(),
+            if lufs_error < 0.0 { "quiet" } else { "loud" },
+            output.lufs_integrated,
+        ));
+    }
+
+    if params.limiter.enabled && output.true_peak_db > params.limiter.ceiling_db + 0.1 {
+        issues.push(format!(
+            "true peak is {:.1} dBTP, above the {:.1} dB ceiling",
+            output.true_peak_db, params.limiter.ceiling_db
+        ));
+    }
+
+    let (s, o) = (&source.frequency_bands, &output.frequency_bands);
+    let bands = [
+        ("sub-bass (20-60 Hz)", s.sub_bass, o.sub_bass),
+        ("bass (60-250 Hz)", s.bass, o.bass),
+        ("low-mids (250-500 Hz)", s.low_mid, o.low_mid),
+        ("mids (500-2000 Hz)", s.mid, o.mid),
+        ("upper mids (2-4 kHz)", s.upper_mid, o.upper_mid),
+        ("presence (4-6 kHz)", s.presence, o.presence),
+        ("brilliance (6-20 kHz)", s.brilliance, o.brilliance),
+    ];
+    for (name, before, after) in bands {
+        let shift = after - before;
+        if shift.abs() > PASS_BAND_SHIFT_DB {
+            issues.push(format!(
+                "{name} is {:.1} dB {} than in the source",
+                shift.abs(),
+                if shift > 0.0 { "louder" } else { "quieter" }
+            ));
+        }
+    }
+
+    if issues.is_empty() {
+        return None;
+    }
+    let mut feedback = issues.join("; ");
+    feedback[..1].make_ascii_uppercase();
+    Some(feedback)
+}
+
+/// An analysis as pretty JSON for a prompt, without the correlation curve,
+/// which is there for plotting and would only spend tokens.
+fn analysis_prompt_json(analysis: &AudioAnalysis) -> Result<String> {
+    let mut analysis = analysis.clone();
+    if let Some(ref mut mono) = analysis.mono_compatibility {
+        mono.correlation_over_time.clear();
+    }
+    Ok(serde_json::to_string_pretty(&analysis)?)
+}
+
+/// Follow-up prompt asking the AI to adjust its previous parameters to feedback.
+fn build_refinement_prompt(
+    analysis_json: &str,
+    previous_analysis_json: &str,
+    refinement: &Refinement,
+    opts: &MasteringOptions,
+) -> Result<String> {
+    let previous_params = serde_json::to_string_pretty(&refinement.previous_params)?;
+    let brief_info = opts
+        .brief
+        .as_deref()
+        .map(|b| format!("\nBrief: {b}"))
+        .unwrap_or_default();
+
+    Ok(format!(
+        r#"You mastered this track before and the listener has feedback on the result.
+
+Source Analysis:
+{analysis_json}
+
+Previous Parameters:
+{previous_params}
+
+Analysis of the Previous Master:
+{previous_analysis_json}
+
+Target LUFS: {target_lufs}
+No Limiter: {no_limiter}{brief_info}
+
+Feedback: "{feedback}"
+
+Adjust the previous parameters to address the feedback and keep what already works. Provide the complete parameter set as a JSON object with keys: eq, de_esser, multiband, compression, limiter, stereo, target_lufs."#,
+        target_lufs = opts.target_lufs,
+        no_limiter = opts.no_limiter,
+        feedback = refinement.feedback,
+    ))
+}
+
+/// Asked for when `MasteringOptions::explain` is set; `parse_explanation`
+/// reads the answer back.
+const EXPLAIN_INSTRUCTION: &str = r#"
+
+Also add an "explanation" key to the JSON object, written for a client without audio engineering experience:
+"explanation": {"summary": "overall approach", "eq": ["why each EQ band, in order"], "de_esser": "why", "multiband": "why, if used", "compression": "why", "limiter": "why", "stereo": "why"}"#;
+
+fn with_explain_request(mut prompt: String, opts: &MasteringOptions) -> String {
+    if opts.explain {
+        prompt.push_str(EXPLAIN_INSTRUCTION);
+    }
+    prompt
+}
+
+/// Asked for when more than one candidate is configured; `parse_proposals`
+/// reads the answer back.
+const CANDIDATES_INSTRUCTION: &str = r#"
+
+Propose {count} distinct candidate parameter sets, e.g. one conservative and one bolder, and respond with {"candidates": [...]} where each entry is a complete parameter object as described above."#;
+
+fn with_candidates_request(mut prompt: String, count: u32) -> String {
+    if count > 1 {
+        prompt.push_str(&CANDIDATES_INSTRUCTION.replace("{count}", &count.to_string()));
+    }
+    prompt
+}
+
+/// One validated parameter set from an AI response.
+struct Proposal {
+    params: MasteringParams,
+    corrections: Vec<ParamCorrection>,
+    explanation: Option<ParamExplanation>,
+}
+
+/// Every parameter set in an AI response: the entries of its `candidates`
+/// list, or the response itself. Unusable candidates are skipped as long as
+/// one remains.
+fn parse_proposals(response: &str, limits: &ParamLimits) -> Result<Vec<Proposal>> {
+    let value = extract_json(response)?;
+    let Some(serde_json::Value::Array(entries)) = value.get("candidates") else {
+        let explanation = explanation_from_value(&value);
+        let (params, corrections) = validate::params_from_json(value, limits)?;
+        return Ok(vec![Proposal {
+            params,
+            corrections,
+            explanation,
+        }]);
+    };
+
+    let mut proposals = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        match validate::params_from_json(entry.clone(), limits) {
+            Ok((params, corrections)) => proposals.push(Proposal {
+                params,
+                corrections,
+                explanation: explanation_from_value(entry),
+            }),
+            Err(e) => warn!("Skipping AI candidate {}: {e:#}", i + 1),
+        }
+    }
+    anyhow::ensure!(
+        !proposals.is_empty(),
+        "None of the AI's parameter candidates were usable"
+    );
+    Ok(proposals)
+}
+
+/// The `explanation` object of an AI response, if it has a usable one.
+fn parse_explanation(response: &str) -> Option<ParamExplanation> {
+    explanation_from_value(&extract_json(response).ok()?)
+}
+
+fn explanation_from_value(value: &serde_json::Value) -> Option<ParamExplanation> {
+    match serde_json::from_value::<ParamExplanation>(value.get("explanation")?.clone()) {
+        Ok(explanation) => Some(explanation),
+        Err(e) => {
+            warn!("Ignoring malformed explanation in AI response: {e}");
+            None
+        }
+    }
+}
+
+/// The JSON object in an AI response, with or without surrounding prose.
+fn extract_json(response: &str) -> Result<serde_json::Value> {
     // Try parsing the response directly
-    if let Ok(params) = serde_json::from_str::<MasteringParams>(response) {
-        return Ok(params);
+    if let Ok(value) = serde_json::from_str::<serde_json::Value>(response) {
+        return Ok(value);
     }
 
     // Try extracting JSON from markdown code blocks
@@ -507,10 +1745,18 @@ fn parse_mastering_params(response: &str) -> Result<MasteringParams> {
         response
     };
 
-    serde_json::from_str::<MasteringParams>(json_str)
+    serde_json::from_str::<serde_json::Value>(json_str)
         .context("Failed to parse AI response as mastering parameters. The AI may have returned an unexpected format.")
 }
 
+/// Parse the AI's response, dropping malformed bands and clamping values to `limits`.
+fn parse_mastering_params(
+    response: &str,
+    limits: &ParamLimits,
+) -> Result<(MasteringParams, Vec<ParamCorrection>)> {
+    validate::params_from_json(extract_json(response)?, limits)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -524,10 +1770,11 @@ mod tests {
   "stereo": {"width": 1.0, "balance": 0.0},
   "target_lufs": -14.0
 }"#;
-        let result = parse_mastering_params(response);
+        let result = parse_mastering_params(response, &ParamLimits::default());
         assert!(result.is_ok(), "Should parse valid JSON");
-        let params = result.unwrap();
+        let (params, corrections) = result.unwrap();
         assert_eq!(params.target_lufs, -14.0);
+        assert!(corrections.is_empty());
     }
 
     #[test]
@@ -545,19 +1792,220 @@ mod tests {
 ```
 
 I recommend using these settings."#;
-        let result = parse_mastering_params(response);
+        let result = parse_mastering_params(response, &ParamLimits::default());
         assert!(result.is_ok(), "Should parse JSON from markdown");
-        let params = result.unwrap();
+        let (params, _) = result.unwrap();
         assert_eq!(params.target_lufs, -14.0);
     }
 
     #[test]
     fn test_parse_mastering_params_invalid() {
         let response = "I couldn't process that audio file.";
-        let result = parse_mastering_params(response);
+        let result = parse_mastering_params(response, &ParamLimits::default());
         assert!(result.is_err(), "Should fail on non-JSON response");
     }
 
+    #[test]
+    fn test_parse_explanation() {
+        let response = r#"{
+  "eq": [{"frequency": 10000.0, "gain_db": 1.5, "q": 0.7, "band_type": "high_shelf"}],
+  "compression": {"threshold_db": -20.0, "ratio": 2.0, "attack_ms": 10.0, "release_ms": 100.0, "knee_db": 6.0, "makeup_gain_db": 0.0},
+  "limiter": {"enabled": true, "ceiling_db": -1.0, "release_ms": 50.0},
+  "stereo": {"width": 1.0, "balance": 0.0},
+  "target_lufs": -14.0,
+  "explanation": {"summary": "Open up the top end", "eq": ["The mix lacks air above 10 kHz"], "compression": "Gentle glue"}
+}"#;
+        let explanation = parse_explanation(response).unwrap();
+        assert_eq!(explanation.summary, "Open up the top end");
+        assert_eq!(explanation.eq.len(), 1);
+        assert_eq!(explanation.stereo, "");
+
+        let (params, _) = parse_mastering_params(response, &ParamLimits::default()).unwrap();
+        assert_eq!(params.eq.len(), 1);
+        assert!(parse_explanation(r#"{"eq": []}"#).is_none());
+    }
+
+    #[test]
+    fn test_parse_proposals() {
+        let candidate = |ratio: f64| {
+            format!(
+                r#"{{"eq": [], "compression": {{"threshold_db": -20.0, "ratio": {ratio}, "attack_ms": 10.0, "release_ms": 100.0, "knee_db": 6.0, "makeup_gain_db": 0.0}}, "limiter": {{"enabled": true, "ceiling_db": -1.0, "release_ms": 50.0}}, "stereo": {{"width": 1.0, "balance": 0.0}}, "target_lufs": -14.0}}"#
+            )
+        };
+        let response = format!(
+            r#"{{"candidates": [{}, {{"eq": []}}, {}]}}"#,
+            candidate(1.5),
+            candidate(4.0)
+        );
+        let proposals = parse_proposals(&response, &ParamLimits::default()).unwrap();
+        let ratios: Vec<_> = proposals
+            .iter()
+            .map(|p| p.params.compression.ratio)
+            .collect();
+        assert_eq!(ratios, [1.5, 4.0]);
+
+        assert_eq!(
+            parse_proposals(&candidate(2.0), &ParamLimits::default())
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(
+            parse_proposals(r#"{"candidates": [{"eq": []}]}"#, &ParamLimits::default()).is_err()
+        );
+
+        assert_eq!(with_candidates_request("Prompt".into(), 1), "Prompt");
+        assert!(with_candidates_request("Prompt".into(), 3).contains("Propose 3 distinct"));
+    }
+
+    #[test]
+    fn test_parse_stem_adjustments() {
+        let response = r#"Here you go:
+{"vocals": {"gain_db": 1.5}, "drums": {"gain_db": 20.0, "eq": []}, "kazoo": {"gain_db": 3.0}}"#;
+        let (adjustments, corrections) = parse_stem_adjustments(
+            response,
+            &["vocals", "drums", "bass"],
+            &ParamLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(adjustments.len(), 2);
+        assert_eq!(adjustments["vocals"].gain_db, 1.5);
+        assert_eq!(adjustments["drums"].gain_db, validate::MAX_STEM_GAIN_DB);
+        let fields: Vec<_> = corrections.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["stems.kazoo", "stems.drums.gain_db"]);
+    }
+
+    #[test]
+    fn test_parse_usage() {
+        let openai = serde_json::json!({"choices": [], "usage": {"prompt_tokens": 1200, "completion_tokens": 300}});
+        let anthropic = serde_json::json!({"content": [], "usage": {"input_tokens": 900, "output_tokens": 250}});
+        let ollama =
+            serde_json::json!({"response": "{}", "prompt_eval_count": 800, "eval_count": 200});
+
+        assert_eq!(
+            parse_usage(&openai).map(|u| (u.input_tokens, u.output_tokens)),
+            Some((1200, 300))
+        );
+        assert_eq!(
+            parse_usage(&anthropic).map(|u| (u.input_tokens, u.output_tokens)),
+            Some((900, 250))
+        );
+        assert_eq!(
+            parse_usage(&ollama).map(|u| (u.input_tokens, u.output_tokens)),
+            Some((800, 200))
+        );
+        assert!(parse_usage(&serde_json::json!({"response": "{}"})).is_none());
+    }
+
+    #[test]
+    fn test_model_price() {
+        let mut overrides = BTreeMap::new();
+        assert_eq!(
+            model_price("gpt-4o-mini-2024-07-18", &overrides)
+                .unwrap()
+                .input_per_mtok,
+            0.15
+        );
+        assert_eq!(
+            model_price("gpt-4o", &overrides).unwrap().input_per_mtok,
+            2.50
+        );
+        assert!(model_price("my-finetune", &overrides).is_none());
+
+        let price = ModelPrice {
+            input_per_mtok: 1.0,
+            output_per_mtok: 2.0,
+        };
+        overrides.insert("my-finetune".to_string(), price);
+        assert_eq!(model_price("my-finetune", &overrides), Some(price));
+        assert!((price.cost(500_000, 250_000) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_http_client_network_settings() {
+        let mut network = NetworkConfig::default();
+        assert!(http_client(&network, None).is_ok());
+
+        network.proxy = "socks5://127.0.0.1:1080".into();
+        assert!(http_client(&network, Some(Duration::from_secs(3))).is_ok());
+
+        network.proxy = "not a url".into();
+        let err = http_client(&network, None).unwrap_err();
+        assert!(format!("{err:#}").contains("Invalid proxy URL"));
+
+        network.proxy.clear();
+        network.ca_cert_file = "/nonexistent/studio-ca.pem".into();
+        let err = http_client(&network, None).unwrap_err();
+        assert!(format!("{err:#}").contains("studio-ca.pem"));
+    }
+
+    #[test]
+    fn test_stream_decoder() {
+        let mut ollama = StreamDecoder::new(StreamFormat::Ollama);
+        ollama
+            .push(b"{\"response\":\"{\\\"eq\\\"\",\"done\":false}\n{\"resp")
+            .unwrap();
+        ollama.push(b"onse\":\": []}\",\"done\":false}\n").unwrap();
+        ollama
+            .push(b"{\"response\":\"\",\"done\":true,\"prompt_eval_count\":800,\"eval_count\":2}")
+            .unwrap();
+        assert_eq!(ollama.tokens, 2);
+        let completion = ollama.finish().unwrap();
+        assert_eq!(completion.text, r#"{"eq": []}"#);
+        assert_eq!(completion.usage.unwrap().output_tokens, 2);
+
+        let mut sse = StreamDecoder::new(StreamFormat::OpenAi);
+        sse.push(b": keep-alive\n\ndata: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}\n\n")
+            .unwrap();
+        sse.push(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"caf\u{e9}\"}}],\"usage\":null}\n\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        sse.push(b"data: {\"choices\":[],\"usage\":{\"prompt_tokens\":10,\"completion_tokens\":1}}\n\ndata: [DONE]\n\n")
+            .unwrap();
+        assert_eq!(sse.tokens, 1);
+        let completion = sse.finish().unwrap();
+        assert_eq!(completion.text, "caf\u{e9}");
+        assert_eq!(completion.usage.unwrap().input_tokens, 10);
+
+        let mut failed = StreamDecoder::new(StreamFormat::Ollama);
+        assert!(failed.push(b"{\"error\":\"model not found\"}\n").is_err());
+    }
+
+    #[test]
+    fn test_timeout_error_names_setting() {
+        let err = timeout_error(AiProvider::AzureOpenAi, 90);
+        assert!(err.can_retry());
+        assert!(err.to_string().contains("within 90s"));
+        assert!(err.user_message().contains("ai.azure_openai.timeout_secs"));
+    }
+
+    #[test]
+    fn test_azure_chat_url() {
+        let azure = AzureOpenAiConfig {
+            endpoint: "https://studio.openai.azure.com/".into(),
+            deployment: "gpt-4o-mastering".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            azure_chat_url(&azure),
+            "https://studio.openai.azure.com/openai/deployments/gpt-4o-mastering/chat/completions?api-version=2024-06-01"
+        );
+    }
+
+    #[test]
+    fn test_compat_chat_url() {
+        assert_eq!(
+            compat_chat_url("https://openrouter.ai/api/v1/"),
+            "https://openrouter.ai/api/v1/chat/completions"
+        );
+        assert_eq!(
+            compat_chat_url("http://localhost:1234/v1"),
+            "http://localhost:1234/v1/chat/completions"
+        );
+    }
+
     #[test]
     fn test_build_mastering_prompt_basic() {
         let opts = MasteringOptions {
@@ -565,15 +2013,58 @@ I recommend using these settings."#;
             output_path: std::path::PathBuf::from("/test/output.wav"),
             reference_path: None,
             bit_depth: 24,
+            sample_format: crate::types::SampleFormat::Int,
             target_lufs: -16.0,
             no_limiter: false,
             preset: None,
+            params: None,
+            match_eq: None,
+            ceiling_db: None,
+            speech: false,
+            brief: None,
+            refinement: None,
+            explain: false,
+            review: Default::default(),
+            progress: ProgressReporter::disabled(),
         };
 
         let prompt = build_mastering_prompt("{}", &opts);
         assert!(prompt.contains("16"), "Should contain LUFS value");
         assert!(prompt.contains("false"), "Should contain no_limiter flag");
-        assert!(!prompt.contains("Preset"), "Should not contain preset when None");
+        assert!(
+            !prompt.contains("Preset"),
+            "Should not contain preset when None"
+        );
+        assert!(
+            !prompt.contains("Brief"),
+            "Should not contain brief when None"
+        );
+    }
+
+    #[test]
+    fn test_build_mastering_prompt_with_brief() {
+        let opts = MasteringOptions {
+            input_path: std::path::PathBuf::from("/test/input.wav"),
+            output_path: std::path::PathBuf::from("/test/output.wav"),
+            reference_path: None,
+            bit_depth: 24,
+            sample_format: crate::types::SampleFormat::Int,
+            target_lufs: -9.0,
+            no_limiter: false,
+            preset: None,
+            params: None,
+            match_eq: None,
+            ceiling_db: None,
+            speech: false,
+            brief: Some("warm, punchy, club-ready".into()),
+            refinement: None,
+            explain: false,
+            review: Default::default(),
+            progress: ProgressReporter::disabled(),
+        };
+
+        let prompt = build_mastering_prompt("{}", &opts);
+        assert!(prompt.contains("Brief: warm, punchy, club-ready"));
     }
 
     #[test]
@@ -583,9 +2074,19 @@ I recommend using these settings."#;
             output_path: std::path::PathBuf::from("/test/output.wav"),
             reference_path: None,
             bit_depth: 24,
+            sample_format: crate::types::SampleFormat::Int,
             target_lufs: -14.0,
             no_limiter: true,
             preset: Some(crate::types::Preset::Streaming),
+            params: None,
+            match_eq: None,
+            ceiling_db: None,
+            speech: false,
+            brief: None,
+            refinement: None,
+            explain: false,
+            review: Default::default(),
+            progress: ProgressReporter::disabled(),
         };
 
         let prompt = build_mastering_prompt("{}", &opts);
@@ -595,6 +2096,150 @@ I recommend using these settings."#;
         assert!(prompt.contains("true"), "Should contain no_limiter flag");
     }
 
+    fn analysis(lufs: f64, brilliance: f64) -> AudioAnalysis {
+        AudioAnalysis {
+            metadata: crate::types::AudioMetadata {
+                path: "song.wav".into(),
+                sample_rate: 44100,
+                channels: 2,
+                channel_layout: "stereo".into(),
+                duration_secs: 180.0,
+                bit_depth: Some(24),
+                format: "WAV".into(),
+            },
+            lufs_integrated: lufs,
+            lufs_short_term_max: lufs + 4.0,
+            lufs_momentary_max: lufs + 6.0,
+            loudness_range_lu: 6.0,
+            rms_db: lufs - 2.0,
+            peak_db: -1.5,
+            true_peak_db: -1.2,
+            dynamic_range_db: 8.0,
+            stereo_width: 0.6,
+            phase_correlation: 1.0,
+            phase_correlation_min: 1.0,
+            frequency_bands: crate::types::FrequencyBands {
+                sub_bass: -10.0,
+                bass: -5.0,
+                low_mid: -8.0,
+                mid: -5.0,
+                upper_mid: -12.0,
+                presence: -16.0,
+                brilliance,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_describe_deviation() {
+        let params = rules::suggest_params(
+            &analysis(-20.0, -15.0),
+            -14.0,
+            false,
+            &RulesConfig::default(),
+        );
+        let source = analysis(-20.0, -15.0);
+
+        assert_eq!(
+            describe_deviation(&source, &analysis(-14.3, -14.0), &params, -14.0),
+            None
+        );
+
+        let feedback =
+            describe_deviation(&source, &analysis(-15.8, -12.5), &params, -14.0).unwrap();
+        assert_eq!(
+            feedback,
+            "Output is 1.8 LU too quiet (-15.8 LUFS against a -14.0 LUFS target); \
+             brilliance (6-20 kHz) is 2.5 dB louder than in the source"
+        );
+    }
+
+    #[test]
+    fn test_build_refinement_prompt() {
+        let previous_params: MasteringParams = serde_json::from_str(
+            r#"{
+  "eq": [{"frequency": 10000.0, "gain_db": 1.0, "q": 0.7, "band_type": "high_shelf"}],
+  "compression": {"threshold_db": -20.0, "ratio": 2.0, "attack_ms": 10.0, "release_ms": 100.0, "knee_db": 6.0, "makeup_gain_db": 0.0},
+  "limiter": {"enabled": true, "ceiling_db": -1.0, "release_ms": 50.0},
+  "stereo": {"width": 1.0, "balance": 0.0},
+  "target_lufs": -14.0
+}"#,
+        )
+        .unwrap();
+        let refinement = Refinement {
+            feedback: "make it brighter".into(),
+            previous_params,
+            previous_output: std::path::PathBuf::from("/test/output.wav"),
+        };
+        let opts = MasteringOptions {
+            input_path: std::path::PathBuf::from("/test/input.wav"),
+            output_path: std::path::PathBuf::from("/test/output_refined.wav"),
+            reference_path: None,
+            bit_depth: 24,
+            sample_format: crate::types::SampleFormat::Int,
+            target_lufs: -14.0,
+            no_limiter: false,
+            preset: None,
+            params: None,
+            match_eq: None,
+            ceiling_db: None,
+            speech: false,
+            brief: None,
+            refinement: Some(refinement.clone()),
+            explain: false,
+            review: Default::default(),
+            progress: ProgressReporter::disabled(),
+        };
+
+        let prompt =
+            build_refinement_prompt("{}", r#"{"lufs_integrated": -14.2}"#, &refinement, &opts)
+                .unwrap();
+        assert!(prompt.contains(r#"Feedback: "make it brighter""#));
+        assert!(
+            prompt.contains("\"high_shelf\""),
+            "Should include the previous parameters"
+        );
+        assert!(
+            prompt.contains("-14.2"),
+            "Should include the previous master's analysis"
+        );
+    }
+
+    #[test]
+    fn test_render_prompt_template() {
+        let opts = MasteringOptions {
+            input_path: std::path::PathBuf::from("/test/input.wav"),
+            output_path: std::path::PathBuf::from("/test/output.wav"),
+            reference_path: None,
+            bit_depth: 24,
+            sample_format: crate::types::SampleFormat::Int,
+            target_lufs: -9.0,
+            no_limiter: false,
+            preset: None,
+            params: None,
+            match_eq: None,
+            ceiling_db: None,
+            speech: false,
+            brief: None,
+            refinement: None,
+            explain: false,
+            review: Default::default(),
+            progress: ProgressReporter::disabled(),
+        };
+
+        let prompt = render_prompt(
+            "Master this {genre} track to {target_lufs} LUFS (preset: {preset}).\n{analysis}",
+            r#"{"lufs": -20}"#,
+            &opts,
+            Some("techno"),
+        );
+        assert_eq!(
+            prompt,
+            "Master this techno track to -9 LUFS (preset: none).\n{\"lufs\": -20}"
+        );
+    }
+
     #[test]
     fn test_backend_with_provider() {
         let backend = AiBackend::new(&Config::default());
@@ -612,5 +2257,71 @@ I recommend using these settings."#;
         // Result depends on whether Python is installed, so we just check it returns
         drop(result);
     }
+
+    /// Test a render through the DSP bridge script. Skipped when Python
+    /// lacks the packages the script needs.
+    #[tokio::test]
+    async fn test_render_runs_dsp_bridge() {
+        let backend = AiBackend {
+            scripts_dir: std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../python"),
+            ..AiBackend::new(&Config::default())
+        };
+        let packages = "import numpy, soundfile, pedalboard";
+        if !bridge::probe(&backend.python_path, packages)
+            .await
+            .unwrap_or(false)
+        {
+            eprintln!("Skipping: numpy, soundfile or pedalboard is not installed");
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+        for i in 0..44100 {
+            let phase = i as f32 * 440.0 / 44100.0 * std::f32::consts::TAU;
+            let sample = (phase.sin() * 8000.0) as i16;
+            writer.write_sample(sample).unwrap();
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let response = r#"{
+  "eq": [{"frequency": 100.0, "gain_db": 2.0, "q": 0.7, "band_type": "low_shelf"}],
+  "compression": {"threshold_db": -20.0, "ratio": 2.0, "attack_ms": 10.0, "release_ms": 100.0, "knee_db": 2.0, "makeup_gain_db": 1.0},
+  "limiter": {"enabled": true, "ceiling_db": -1.0, "release_ms": 100.0},
+  "stereo": {"width": 1.0, "balance": 0.0},
+  "target_lufs": -14.0
+}"#;
+        let (params, _) = parse_mastering_params(response, &ParamLimits::default()).unwrap();
+        let opts = MasteringOptions {
+            input_path: input,
+            output_path: dir.path().join("output.wav"),
+            reference_path: None,
+            bit_depth: 24,
+            sample_format: crate::types::SampleFormat::Int,
+            target_lufs: -14.0,
+            no_limiter: false,
+            preset: None,
+            params: None,
+            match_eq: None,
+            ceiling_db: None,
+            speech: false,
+            brief: None,
+            refinement: None,
+            explain: false,
+            review: Default::default(),
+            progress: ProgressReporter::disabled(),
+        };
+
+        backend.render(&opts, &params).await.unwrap();
+        assert!(opts.output_path.exists());
+    }
 }
 
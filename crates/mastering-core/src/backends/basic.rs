@@ -0,0 +1,281 @@
+use anyhow::{Context, Result};
+use tracing::info;
+
+use super::{BackendOutput, MasteringOptions};
+use crate::analysis::match_eq::{self, SpectralCurve};
+use crate::analysis::{self, decode};
+use crate::config::Config;
+use crate::dsp;
+use crate::encode::{self, EncodeOptions};
+use crate::rules;
+use crate::types::{
+    AudioAnalysis, CompressionParams, DeEsserParams, EqBand, EqBandType, EqChannel, LimiterParams,
+    MasteringParams, ParamCorrection, StereoParams,
+};
+use crate::validate;
+
+/// Band balance (dB relative to total energy) of a typical finished mix.
+/// The heuristic EQ moves the input halfway toward it.
+const REFERENCE_BANDS: [f64; 7] = [-12.0, -5.0, -8.0, -5.0, -12.0, -16.0, -15.0];
+
+/// Largest boost or cut the heuristic EQ applies per band, in dB.
+const MAX_EQ_DB: f64 = 3.0;
+
+/// Pure-Rust mastering: heuristic EQ, compression, loudness normalization
+/// and limiting. Needs neither Python nor a network connection.
+#[derive(Debug, Clone, Default)]
+pub struct BasicBackend;
+
+impl BasicBackend {
+    pub fn new(_config: &Config) -> Self {
+        Self
+    }
+
+    pub async fn process(&self, opts: &MasteringOptions) -> Result<BackendOutput> {
+        info!("Basic mastering of: {}", opts.input_path.display());
+
+        let output_path = opts.output_path.clone();
+        let matched = opts.match_eq.is_some();
+        let opts = opts.clone();
+        let (params, corrections) = tokio::task::spawn_blocking(
+            move || -> Result<(MasteringParams, Vec<ParamCorrection>)> {
+                let mut audio = decode::decode_audio(&opts.input_path)?;
+                let analysis = analysis::analyze(&opts.input_path, &audio)?;
+                let mut params = match opts.params {
+                    Some(ref fixed) => {
+                        let mut params = fixed.clone();
+                        params.target_lufs = opts.target_lufs;
+                        params.limiter.enabled &= !opts.no_limiter;
+                        params
+                    }
+                    None => heuristic_params(&analysis, &opts),
+                };
+                if opts.speech && opts.params.is_none() {
+                    rules::adapt_for_speech(&mut params);
+                }
+                if let Some(ref target) = opts.match_eq {
+                    let input = SpectralCurve::from_audio(&audio);
+                    let bands = match_eq::match_bands(
+                        &input,
+                        &target.curve,
+                        target.strength,
+                        audio.sample_rate,
+                    );
+                    info!("Match EQ adds {} bands", bands.len());
+                    // The match replaces the suggested tonal bands, keeping the pass filters
+                    if opts.params.is_none() {
+                        params.eq.retain(|b| {
+                            matches!(b.band_type, EqBandType::HighPass | EqBandType::LowPass)
+                        });
+                    }
+                    params.eq.extend(bands);
+                }
+                let corrections = validate::cap_ceiling(&mut params, opts.ceiling_db);
+
+                dsp::master(&mut audio, &params);
+
+                let encode_opts = EncodeOptions {
+                    bit_depth: opts.bit_depth,
+                    sample_format: opts.sample_format,
+                    ..Default::default()
+                };
+                encode::write_wav(
+                    &opts.output_path,
+                    &audio.samples,
+                    audio.channels,
+                    audio.sample_rate,
+                    &encode_opts,
+                )?;
+                Ok((params, corrections))
+            },
+        )
+        .await
+        .context("Basic mastering task failed")??;
+
+        info!("Basic mastering completed");
+
+        Ok(BackendOutput {
+            output_path,
+            params_applied: Some(params),
+            backend_name: "basic".into(),
+            message: if matched {
+                "Mastered with match EQ and the built-in compressor and limiter".into()
+            } else {
+                "Mastered with the built-in EQ, compressor and limiter".into()
+            },
+            corrections,
+            explanation: None,
+            candidates: Vec::new(),
+            usage: None,
+            device: None,
+        })
+    }
+
+    pub async fn check_available(&self) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+/// Derive mastering parameters from the input analysis.
+pub fn heuristic_params(analysis: &AudioAnalysis, opts: &MasteringOptions) -> MasteringParams {
+    let bands = &analysis.frequency_bands;
+    let measured = [
+        bands.sub_bass,
+        bands.bass,
+        bands.low_mid,
+        bands.mid,
+        bands.upper_mid,
+        bands.presence,
+        bands.brilliance,
+    ];
+    let correction =
+        |i: usize| ((REFERENCE_BANDS[i] - measured[i]) * 0.5).clamp(-MAX_EQ_DB, MAX_EQ_DB);
+
+    let eq = vec![
+        EqBand {
+            frequency: 25.0,
+            gain_db: 0.0,
+            q: 0.707,
+            band_type: EqBandType::HighPass,
+            channel: EqChannel::Stereo,
+        },
+        EqBand {
+            frequency: 80.0,
+            gain_db: (correction(0) + correction(1)) / 2.0,
+            q: 0.707,
+            band_type: EqBandType::LowShelf,
+            channel: EqChannel::Stereo,
+        },
+        EqBand {
+            frequency: 300.0,
+            // Mud is cut freely but low-mids are only lightly boosted
+            gain_db: correction(2).min(1.5),
+            q: 1.0,
+            band_type: EqBandType::Peak,
+            channel: EqChannel::Stereo,
+        },
+        EqBand {
+            frequency: 3000.0,
+            gain_db: correction(4),
+            q: 1.2,
+            band_type: EqBandType::Peak,
+            channel: EqChannel::Stereo,
+        },
+        EqBand {
+            frequency: 10000.0,
+            gain_db: (correction(5) + correction(6)) / 2.0,
+            q: 0.707,
+            band_type: EqBandType::HighShelf,
+            channel: EqChannel::Stereo,
+        },
+    ];
+
+    // More dynamic material gets a firmer ratio
+    let ratio = match analysis.dynamic_range_db {
+        dr if dr > 10.0 => 2.5,
+        dr if dr > 6.0 => 2.0,
+        _ => 1.5,
+    };
+    let compression = CompressionParams {
+        threshold_db: (analysis.rms_db + 3.0).min(-6.0),
+        ratio,
+        attack_ms: 15.0,
+        release_ms: 120.0,
+        knee_db: 6.0,
+        makeup_gain_db: 0.0,
+    };
+
+    MasteringParams {
+        eq,
+        de_esser: DeEsserParams::default(),
+        multiband: None,
+        compression,
+        limiter: LimiterParams {
+            enabled: !opts.no_limiter,
+            ceiling_db: -1.0,
+            release_ms: 60.0,
+            ..Default::default()
+        },
+        stereo: StereoParams {
+            // Rein in very wide mixes that would fold down poorly
+            width: if analysis.stereo_width > 1.2 {
+                0.9
+            } else {
+                1.0
+            },
+            balance: 0.0,
+        },
+        target_lufs: opts.target_lufs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SampleFormat;
+
+    fn options(output_path: std::path::PathBuf) -> MasteringOptions {
+        MasteringOptions {
+            input_path: std::path::PathBuf::new(),
+            output_path,
+            reference_path: None,
+            bit_depth: 24,
+            sample_format: SampleFormat::Int,
+            target_lufs: -12.0,
+            no_limiter: false,
+            preset: None,
+            params: None,
+            match_eq: None,
+            ceiling_db: None,
+            speech: false,
+            brief: None,
+            refinement: None,
+            explain: false,
+            review: Default::default(),
+            progress: crate::pipeline::ProgressReporter::disabled(),
+        }
+    }
+
+    #[test]
+    fn test_heuristics_follow_band_balance() {
+        let audio = decode::DecodedAudio::new(
+            (0..44100 * 2)
+                .map(|i| (2.0 * std::f32::consts::PI * 60.0 * (i / 2) as f32 / 44100.0).sin() * 0.3)
+                .collect(),
+            44100,
+            2,
+        );
+        let analysis = analysis::analyze(std::path::Path::new("bass.wav"), &audio).unwrap();
+        let params = heuristic_params(&analysis, &options(Default::default()));
+
+        // Bass-heavy input: the low shelf cuts and the top end is lifted
+        assert!(params.eq[1].gain_db < 0.0);
+        assert!(params.eq[4].gain_db > 0.0);
+        assert!(params.eq.iter().all(|b| b.gain_db.abs() <= MAX_EQ_DB));
+        assert_eq!(params.target_lufs, -12.0);
+    }
+
+    #[tokio::test]
+    async fn test_process_writes_mastered_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.wav");
+        let output = dir.path().join("out.wav");
+        let samples: Vec<f32> = (0..44100 * 4)
+            .flat_map(|i| {
+                let t = i as f32 / 44100.0;
+                let s = 0.05 * (2.0 * std::f32::consts::PI * 440.0 * t).sin();
+                [s, s * 0.8]
+            })
+            .collect();
+        encode::write_wav(&input, &samples, 2, 44100, &EncodeOptions::default()).unwrap();
+
+        let mut opts = options(output.clone());
+        opts.input_path = input;
+        let result = BasicBackend.process(&opts).await.unwrap();
+
+        assert_eq!(result.backend_name, "basic");
+        let mastered = decode::decode_audio(&output).unwrap();
+        let lufs = crate::analysis::loudness::integrated_loudness(&mastered);
+        assert!((lufs + 12.0).abs() < 1.0, "lufs {lufs}");
+    }
+}
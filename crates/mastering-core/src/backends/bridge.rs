@@ -0,0 +1,306 @@
+//! Running the Python bridge scripts.
+//!
+//! Bridges run under tokio so a long render never blocks the runtime. Stdout
+//! and stderr are drained concurrently, so a chatty bridge cannot stall on a
+//! full pipe. Each bridge leads its own process group, and the whole group is
+//! killed when the bridge overruns its timeout or the future is dropped (e.g.
+//! when the job is cancelled), so workers it forked die with it.
+//!
+//! Bridges may report progress on stdout while they work, one JSON object
+//! per line: `{"progress": 40, "message": "Matching levels"}`, with the
+//! percentage of the bridge's own work. These lines are forwarded to the
+//! pipeline's progress channel; every other line is kept as the bridge's
+//! output, whose last JSON object is its result.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::ffi::OsStr;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tracing::debug;
+
+use crate::error::MasteringError;
+use crate::pipeline::{PipelineStage, ProgressReporter};
+
+/// How long a bridge may run, and the setting that controls it.
+#[derive(Debug, Clone, Copy)]
+pub struct Timeout {
+    pub duration: Duration,
+    /// Config key named in the timeout error, if the limit is configurable.
+    pub config_key: Option<&'static str>,
+}
+
+impl Timeout {
+    /// A limit of `secs` seconds set by `config_key`.
+    pub fn configured(secs: u64, config_key: &'static str) -> Self {
+        Self {
+            duration: Duration::from_secs(secs),
+            config_key: Some(config_key),
+        }
+    }
+}
+
+/// Longest an availability probe such as `python -c "import matchering"` may take.
+pub const CHECK_TIMEOUT: Timeout = Timeout {
+    duration: Duration::from_secs(10),
+    config_key: None,
+};
+
+/// A progress line of the bridge protocol.
+#[derive(Debug, Deserialize)]
+struct ProgressLine {
+    progress: f32,
+    #[serde(default)]
+    message: String,
+}
+
+/// What a finished bridge process wrote and how it exited.
+#[derive(Debug)]
+pub struct BridgeOutput {
+    pub status: ExitStatus,
+    /// Stdout without the progress lines.
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Kills a bridge's process group on drop, unless it was disarmed after the
+/// bridge exited.
+struct TreeGuard(Option<u32>);
+
+impl TreeGuard {
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for TreeGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0.take() {
+            kill_tree(pid);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn kill_tree(pid: u32) {
+    // SAFETY: killpg has no memory-safety preconditions; the bridge was
+    // spawned as the leader of process group `pid`
+    let rc = unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+    if rc != 0 {
+        debug!(
+            "Failed to kill process group {pid}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(windows)]
+fn kill_tree(pid: u32) {
+    let status = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    if let Err(e) = status {
+        debug!("Failed to kill process tree {pid}: {e}");
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn kill_tree(_pid: u32) {}
+
+/// Run `python` with `args`, killing its process tree if it outlasts
+/// `timeout`. Progress lines are reported to `progress` as the processing stage.
+///
+/// `label` names the bridge in error messages, e.g. "matchering bridge". A
+/// timeout fails with [`MasteringError::ProcessTimeout`].
+pub async fn run<I, S>(
+    python: &str,
+    args: I,
+    timeout: Timeout,
+    label: &str,
+    progress: &ProgressReporter,
+) -> Result<BridgeOutput>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut command = tokio::process::Command::new(python);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to run {label}. Is Python installed at '{python}'?"))?;
+    let mut tree = TreeGuard(child.id());
+
+    let stdout = child
+        .stdout
+        .take()
+        .context("Bridge stdout was not captured")?;
+    let stderr = child
+        .stderr
+        .take()
+        .context("Bridge stderr was not captured")?;
+
+    let read_stdout = async {
+        let mut lines = BufReader::new(stdout).lines();
+        let mut text = String::new();
+        while let Some(line) = lines.next_line().await? {
+            debug!("{label}: {line}");
+            if let Ok(update) = serde_json::from_str::<ProgressLine>(&line) {
+                progress.report(PipelineStage::Processing, update.progress, update.message);
+                continue;
+            }
+            text.push_str(&line);
+            text.push('\n');
+        }
+        Ok::<_, std::io::Error>(text)
+    };
+    let read_stderr = async {
+        let mut bytes = Vec::new();
+        BufReader::new(stderr).read_to_end(&mut bytes).await?;
+        Ok::<_, std::io::Error>(String::from_utf8_lossy(&bytes).into_owned())
+    };
+
+    let finished = tokio::time::timeout(timeout.duration, async {
+        tokio::try_join!(read_stdout, read_stderr, child.wait())
+    })
+    .await;
+
+    match finished {
+        Ok(result) => {
+            let (stdout, stderr, status) =
+                result.with_context(|| format!("Reading the output of the {label}"))?;
+            tree.disarm();
+            Ok(BridgeOutput {
+                status,
+                stdout,
+                stderr,
+            })
+        }
+        Err(_) => {
+            drop(tree);
+            // Reap the process so no zombie outlives the job
+            if let Err(e) = child.kill().await {
+                debug!("Failed to kill {label}: {e}");
+            }
+            Err(MasteringError::ProcessTimeout {
+                process: label.to_string(),
+                timeout_secs: timeout.duration.as_secs(),
+                config_key: timeout.config_key.map(str::to_string),
+            }
+            .into())
+        }
+    }
+}
+
+/// Whether `python -c code` exits successfully within [`CHECK_TIMEOUT`].
+///
+/// A probe that hangs is an error rather than `false`, so diagnostics can
+/// tell a stuck interpreter from a missing package.
+pub async fn probe(python: &str, code: &str) -> Result<bool> {
+    let progress = ProgressReporter::disabled();
+    match run(
+        python,
+        ["-c", code],
+        CHECK_TIMEOUT,
+        "Python probe",
+        &progress,
+    )
+    .await
+    {
+        Ok(output) => Ok(output.status.success()),
+        Err(e) if e.downcast_ref::<MasteringError>().is_some() => Err(e),
+        Err(e) => {
+            debug!("{e:#}");
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_collects_output() {
+        let (progress, mut rx) = ProgressReporter::channel();
+        let output = run(
+            "sh",
+            [
+                "-c",
+                r#"echo line1; echo '{"progress": 50, "message": "Halfway"}'; echo oops >&2; echo line2; exit 3"#,
+            ],
+            Timeout::configured(5, "test.timeout_secs"),
+            "test bridge",
+            &progress,
+        )
+        .await
+        .unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, "line1\nline2\n");
+        assert_eq!(output.stderr, "oops\n");
+
+        let update = rx.try_recv().unwrap();
+        assert_eq!(update.stage, PipelineStage::Processing);
+        assert_eq!(update.stage_percent, 50.0);
+        assert_eq!(update.message, "Halfway");
+    }
+
+    #[tokio::test]
+    async fn test_run_kills_on_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("worker.pid");
+        let script = format!("sleep 30 & echo $! > '{}'; wait", pid_file.display());
+        let timeout = Timeout {
+            duration: Duration::from_millis(300),
+            config_key: Some("test.timeout_secs"),
+        };
+
+        let start = std::time::Instant::now();
+        let progress = ProgressReporter::disabled();
+        let err = run(
+            "sh",
+            ["-c", script.as_str()],
+            timeout,
+            "test bridge",
+            &progress,
+        )
+        .await
+        .unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        match MasteringError::from(err) {
+            MasteringError::ProcessTimeout { config_key, .. } => {
+                assert_eq!(config_key.as_deref(), Some("test.timeout_secs"));
+            }
+            other => panic!("expected a process timeout, got {other:?}"),
+        }
+
+        // The worker the bridge forked must have died with it
+        let worker = std::fs::read_to_string(&pid_file).unwrap();
+        let worker: i32 = worker.trim().parse().unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // SAFETY: signal 0 only checks that the process exists
+        let alive = unsafe { libc::kill(worker, 0) } == 0 && !is_zombie(worker);
+        assert!(!alive, "worker {worker} outlived the bridge");
+    }
+
+    fn is_zombie(pid: i32) -> bool {
+        std::fs::read_to_string(format!("/proc/{pid}/stat"))
+            .map(|stat| {
+                stat.rsplit(')')
+                    .next()
+                    .unwrap_or("")
+                    .trim_start()
+                    .starts_with('Z')
+            })
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,124 @@
+//! Embedded inference of quantized GGUF models.
+//!
+//! Runs a small llama-family model on the CPU with candle so the AI backend
+//! can produce parameters without Ollama or any network service. Only built
+//! with the `local-llm` feature.
+
+use anyhow::{Context, Result};
+use candle_core::quantized::gguf_file;
+use candle_core::{Device, Tensor};
+use candle_transformers::generation::{LogitsProcessor, Sampling};
+use candle_transformers::models::quantized_llama::ModelWeights;
+use std::path::{Path, PathBuf};
+use tokenizers::Tokenizer;
+
+use crate::config::LocalGgufConfig;
+
+/// Tokens that end an assistant turn across the common chat formats.
+const STOP_TOKENS: &[&str] = &[
+    "</s>",
+    "<|eot_id|>",
+    "<|end_of_text|>",
+    "<|im_end|>",
+    "<|endoftext|>",
+];
+
+/// Generate a completion for `prompt` with the model configured in `config`.
+///
+/// Decoding is greedy, so the same prompt always yields the same parameters.
+pub fn generate(config: &LocalGgufConfig, system: &str, prompt: &str) -> Result<String> {
+    let model_path = PathBuf::from(&config.model_path);
+    let tokenizer_path = tokenizer_path(config);
+    let device = Device::Cpu;
+
+    let mut file = std::fs::File::open(&model_path)
+        .with_context(|| format!("Opening GGUF model {}", model_path.display()))?;
+    let content = gguf_file::Content::read(&mut file)
+        .map_err(|e| anyhow::anyhow!("Reading GGUF model {}: {e}", model_path.display()))?;
+    let mut model = ModelWeights::from_gguf(content, &mut file, &device)?;
+
+    let tokenizer = Tokenizer::from_file(&tokenizer_path)
+        .map_err(|e| anyhow::anyhow!("Loading tokenizer {}: {e}", tokenizer_path.display()))?;
+    let stop: Vec<u32> = STOP_TOKENS
+        .iter()
+        .filter_map(|t| tokenizer.token_to_id(t))
+        .collect();
+
+    let text = render_template(&config.chat_template, system, prompt);
+    let prompt_tokens = tokenizer
+        .encode(text, true)
+        .map_err(|e| anyhow::anyhow!("Tokenizing prompt: {e}"))?
+        .get_ids()
+        .to_vec();
+
+    let mut sampler = LogitsProcessor::from_sampling(0, Sampling::ArgMax);
+    let mut generated = Vec::with_capacity(config.max_tokens);
+
+    let input = Tensor::new(prompt_tokens.as_slice(), &device)?.unsqueeze(0)?;
+    let logits = model.forward(&input, 0)?.squeeze(0)?;
+    let mut next = sampler.sample(&logits)?;
+
+    for index in 0..config.max_tokens {
+        if stop.contains(&next) {
+            break;
+        }
+        generated.push(next);
+        let input = Tensor::new(&[next], &device)?.unsqueeze(0)?;
+        let logits = model
+            .forward(&input, prompt_tokens.len() + index)?
+            .squeeze(0)?;
+        next = sampler.sample(&logits)?;
+    }
+
+    tokenizer
+        .decode(&generated, true)
+        .map_err(|e| anyhow::anyhow!("Decoding model output: {e}"))
+}
+
+/// Whether the model and tokenizer files exist.
+pub fn is_configured(config: &LocalGgufConfig) -> bool {
+    !config.model_path.is_empty()
+        && Path::new(&config.model_path).is_file()
+        && tokenizer_path(config).is_file()
+}
+
+/// Explicit tokenizer path, or `tokenizer.json` next to the model.
+fn tokenizer_path(config: &LocalGgufConfig) -> PathBuf {
+    if !config.tokenizer_path.is_empty() {
+        return PathBuf::from(&config.tokenizer_path);
+    }
+    Path::new(&config.model_path)
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join("tokenizer.json")
+}
+
+fn render_template(template: &str, system: &str, prompt: &str) -> String {
+    template
+        .replace("{system}", system)
+        .replace("{prompt}", prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenizer_defaults_next_to_model() {
+        let config = LocalGgufConfig {
+            model_path: "/models/qwen2.5-1.5b-q4.gguf".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            tokenizer_path(&config),
+            PathBuf::from("/models/tokenizer.json")
+        );
+        assert!(!is_configured(&config));
+    }
+
+    #[test]
+    fn test_render_template() {
+        let text = render_template("<s>{system}|{prompt}", "sys", "user");
+        assert_eq!(text, "<s>sys|user");
+    }
+}
@@ -1,27 +1,52 @@
 use anyhow::{Context, Result};
-use std::process::Command;
 use tracing::{debug, info};
 
-use super::{BackendOutput, MasteringOptions};
-use crate::config::Config;
+use super::{bridge, BackendOutput, MasteringOptions};
+use crate::config::{Config, LocalMlConfig};
+use crate::gpu;
+use crate::models::ModelStore;
 
+/// Local ML models, run by ONNX Runtime when an ONNX model is configured
+/// and by the Python inference script otherwise.
 #[derive(Debug, Clone)]
 pub struct LocalMlBackend {
-    python_path: String,
-    default_model: String,
+    config: LocalMlConfig,
     scripts_dir: std::path::PathBuf,
+    /// Installed directory of `default_model`, if there is one.
+    model_dir: Option<std::path::PathBuf>,
+    timeout: bridge::Timeout,
 }
 
 impl LocalMlBackend {
     pub fn new(config: &Config) -> Self {
+        let mut local_ml = config.backends.local_ml.clone();
+        let store = ModelStore::default_dir().ok().map(ModelStore::new);
+        if let Some(file) = store
+            .as_ref()
+            .and_then(|s| s.onnx_file(&local_ml.onnx_model))
+        {
+            local_ml.onnx_model = file.to_string_lossy().into_owned();
+        }
+        let model_dir = store
+            .filter(|s| s.is_installed(&local_ml.default_model))
+            .map(|s| s.path(&local_ml.default_model));
+
         Self {
-            python_path: config.backends.local_ml.python_path.clone(),
-            default_model: config.backends.local_ml.default_model.clone(),
+            config: local_ml,
             scripts_dir: Config::python_scripts_dir(),
+            model_dir,
+            timeout: bridge::Timeout::configured(
+                config.backends.local_ml.timeout_secs,
+                "backends.local_ml.timeout_secs",
+            ),
         }
     }
 
     pub async fn process(&self, opts: &MasteringOptions) -> Result<BackendOutput> {
+        if !self.config.onnx_model.is_empty() {
+            return self.process_onnx(opts).await;
+        }
+
         let script = self.scripts_dir.join("ml_inference.py");
         anyhow::ensure!(
             script.exists(),
@@ -29,43 +54,42 @@ impl LocalMlBackend {
             script.display()
         );
 
+        let device = gpu::resolve_device(self.config.device)?;
         info!(
-            "Running local ML model '{}' on: {}",
-            self.default_model,
+            "Running local ML model '{}' on {device}: {}",
+            self.config.default_model,
             opts.input_path.display()
         );
 
         let request = serde_json::json!({
             "input": opts.input_path.to_string_lossy(),
             "output": opts.output_path.to_string_lossy(),
-            "model": self.default_model,
+            "model": self.config.default_model,
+            "model_dir": self.model_dir.as_ref().map(|p| p.to_string_lossy().to_string()),
             "reference": opts.reference_path.as_ref().map(|p| p.to_string_lossy().to_string()),
             "bit_depth": opts.bit_depth,
+            "sample_format": opts.sample_format,
             "target_lufs": opts.target_lufs,
+            "device": device,
         });
 
-        let output = Command::new(&self.python_path)
-            .arg(&script)
-            .arg(request.to_string())
-            .output()
-            .with_context(|| {
-                format!(
-                    "Failed to run ML inference script. Is Python installed at '{}'?",
-                    self.python_path
-                )
-            })?;
-
-        debug!(
-            "ML inference stdout: {}",
-            String::from_utf8_lossy(&output.stdout)
-        );
+        let request = request.to_string();
+        let output = bridge::run(
+            &self.config.python_path,
+            [script.as_os_str(), request.as_ref()],
+            self.timeout,
+            "ML inference script",
+            &opts.progress,
+        )
+        .await?;
+
+        debug!("ML inference stdout: {}", output.stdout);
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("ML inference failed:\n{stderr}");
+            anyhow::bail!("ML inference failed:\n{}", output.stderr);
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stdout = &output.stdout;
         let response: serde_json::Value = serde_json::from_str(stdout.trim())
             .with_context(|| format!("Parsing ML inference output: {stdout}"))?;
 
@@ -74,6 +98,12 @@ impl LocalMlBackend {
             .map(std::path::PathBuf::from)
             .unwrap_or_else(|| opts.output_path.clone());
 
+        // The script falls back to the CPU when PyTorch cannot use the device
+        let device = response["device"]
+            .as_str()
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(device);
+
         let message = response["message"]
             .as_str()
             .unwrap_or("ML model processing completed")
@@ -82,32 +112,98 @@ impl LocalMlBackend {
         Ok(BackendOutput {
             output_path: result_path,
             params_applied: None,
-            backend_name: format!("local-ml/{}", self.default_model),
+            backend_name: format!("local-ml/{}", self.config.default_model),
             message,
+            corrections: Vec::new(),
+            explanation: None,
+            candidates: Vec::new(),
+            usage: None,
+            device: Some(device),
+        })
+    }
+
+    #[cfg(feature = "onnx")]
+    async fn process_onnx(&self, opts: &MasteringOptions) -> Result<BackendOutput> {
+        use crate::analysis::decode;
+        use crate::encode::{self, EncodeOptions};
+        use crate::pipeline::PipelineStage;
+        use crate::types::Device;
+
+        let name = super::onnx::model_name(&self.config);
+        info!(
+            "Running ONNX model '{name}' on: {}",
+            opts.input_path.display()
+        );
+
+        let output_path = opts.output_path.clone();
+        let config = self.config.clone();
+        let opts = opts.clone();
+        let device = tokio::task::spawn_blocking(move || -> Result<Device> {
+            let mut audio = decode::decode_audio(&opts.input_path)?;
+            let reference = opts
+                .reference_path
+                .as_deref()
+                .map(decode::decode_audio)
+                .transpose()
+                .context("Decoding reference track")?;
+            let (samples, device) =
+                super::onnx::process(&config, &audio, reference.as_ref(), |pct| {
+                    opts.progress
+                        .report(PipelineStage::Processing, pct, "Running ONNX model")
+                })?;
+            audio.samples = samples;
+
+            let encode_opts = EncodeOptions {
+                bit_depth: opts.bit_depth,
+                sample_format: opts.sample_format,
+                ..Default::default()
+            };
+            encode::write_wav(
+                &opts.output_path,
+                &audio.samples,
+                audio.channels,
+                audio.sample_rate,
+                &encode_opts,
+            )?;
+            Ok(device)
+        })
+        .await
+        .context("ONNX inference task failed")??;
+
+        Ok(BackendOutput {
+            output_path,
+            params_applied: None,
+            backend_name: format!("local-ml/{name}"),
+            message: format!("Processed with the {name} ONNX model on {device}"),
+            corrections: Vec::new(),
+            explanation: None,
+            candidates: Vec::new(),
+            usage: None,
+            device: Some(device),
         })
     }
 
+    #[cfg(not(feature = "onnx"))]
+    async fn process_onnx(&self, _opts: &MasteringOptions) -> Result<BackendOutput> {
+        anyhow::bail!(
+            "Cannot run {}: this build does not include ONNX Runtime (rebuild with --features onnx)",
+            self.config.onnx_model
+        )
+    }
+
     pub async fn check_available(&self) -> Result<bool> {
+        if !self.config.onnx_model.is_empty() {
+            #[cfg(feature = "onnx")]
+            return Ok(super::onnx::is_configured(&self.config));
+            #[cfg(not(feature = "onnx"))]
+            return Ok(false);
+        }
+
         let script = self.scripts_dir.join("ml_inference.py");
         if !script.exists() {
             return Ok(false);
         }
 
-        let python = self.python_path.clone();
-        let result = tokio::time::timeout(
-            std::time::Duration::from_secs(10),
-            tokio::task::spawn_blocking(move || {
-                Command::new(&python)
-                    .arg("-c")
-                    .arg("import soundfile; print('ok')")
-                    .output()
-            }),
-        )
-        .await;
-
-        match result {
-            Ok(Ok(Ok(o))) => Ok(o.status.success()),
-            _ => Ok(false),
-        }
+        bridge::probe(&self.config.python_path, "import soundfile; print('ok')").await
     }
 }
@@ -1,21 +1,33 @@
 use anyhow::{Context, Result};
-use std::process::Command;
 use tracing::{debug, info};
 
-use super::{BackendOutput, MasteringOptions};
+use super::{bridge, BackendOutput, MasteringOptions};
+use crate::analysis::decode;
 use crate::config::Config;
+use crate::dsp::matching;
+use crate::encode::{self, EncodeOptions};
+use crate::pipeline::PipelineStage;
+use crate::types::MatchingEngine;
 
+/// Reference matching, natively or through the matchering Python package.
 #[derive(Debug, Clone)]
 pub struct MatcheringBackend {
+    engine: MatchingEngine,
     python_path: String,
     scripts_dir: std::path::PathBuf,
+    timeout: bridge::Timeout,
 }
 
 impl MatcheringBackend {
     pub fn new(config: &Config) -> Self {
         Self {
+            engine: config.backends.matchering.engine,
             python_path: config.backends.matchering.python_path.clone(),
             scripts_dir: Config::python_scripts_dir(),
+            timeout: bridge::Timeout::configured(
+                config.backends.matchering.timeout_secs,
+                "backends.matchering.timeout_secs",
+            ),
         }
     }
 
@@ -25,6 +37,10 @@ impl MatcheringBackend {
             .as_ref()
             .context("Matchering backend requires a reference track (--reference)")?;
 
+        if self.engine == MatchingEngine::Native {
+            return self.process_native(opts, reference).await;
+        }
+
         let script = self.scripts_dir.join("matchering_bridge.py");
         anyhow::ensure!(
             script.exists(),
@@ -43,28 +59,27 @@ impl MatcheringBackend {
             "reference": reference.to_string_lossy(),
             "output": opts.output_path.to_string_lossy(),
             "bit_depth": opts.bit_depth,
+            "sample_format": opts.sample_format,
             "no_limiter": opts.no_limiter,
         });
 
-        let output = Command::new(&self.python_path)
-            .arg(&script)
-            .arg(request.to_string())
-            .output()
-            .with_context(|| {
-                format!(
-                    "Failed to run matchering bridge script. Is Python installed at '{}'?",
-                    self.python_path
-                )
-            })?;
+        let request = request.to_string();
+        let output = bridge::run(
+            &self.python_path,
+            [script.as_os_str(), request.as_ref()],
+            self.timeout,
+            "matchering bridge",
+            &opts.progress,
+        )
+        .await?;
 
-        debug!("Matchering stdout: {}", String::from_utf8_lossy(&output.stdout));
+        debug!("Matchering stdout: {}", output.stdout);
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("Matchering failed:\n{stderr}");
+            anyhow::bail!("Matchering failed:\n{}", output.stderr);
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stdout = &output.stdout;
         let response: serde_json::Value = serde_json::from_str(stdout.trim())
             .with_context(|| format!("Parsing matchering output: {stdout}"))?;
 
@@ -83,30 +98,86 @@ impl MatcheringBackend {
             params_applied: None,
             backend_name: "matchering".into(),
             message,
+            corrections: Vec::new(),
+            explanation: None,
+            candidates: Vec::new(),
+            usage: None,
+            device: None,
+        })
+    }
+
+    async fn process_native(
+        &self,
+        opts: &MasteringOptions,
+        reference: &std::path::Path,
+    ) -> Result<BackendOutput> {
+        info!(
+            "Matching {} to {}",
+            opts.input_path.display(),
+            reference.display()
+        );
+
+        let output_path = opts.output_path.clone();
+        let reference = reference.to_path_buf();
+        let opts = opts.clone();
+        let report = tokio::task::spawn_blocking(move || -> Result<matching::MatchReport> {
+            let mut audio = decode::decode_audio(&opts.input_path)?;
+            let reference = decode::decode_audio(&reference)
+                .with_context(|| format!("Decoding reference {}", reference.display()))?;
+            let report = matching::match_reference(
+                &mut audio,
+                &reference,
+                !opts.no_limiter,
+                |pct, stage| opts.progress.report(PipelineStage::Processing, pct, stage),
+            )?;
+
+            let encode_opts = EncodeOptions {
+                bit_depth: opts.bit_depth,
+                sample_format: opts.sample_format,
+                ..Default::default()
+            };
+            encode::write_wav(
+                &opts.output_path,
+                &audio.samples,
+                audio.channels,
+                audio.sample_rate,
+                &encode_opts,
+            )?;
+            Ok(report)
+        })
+        .await
+        .context("Reference matching task failed")??;
+
+        info!(
+            "Matched reference: {:+.1} dB, EQ up to {:+.1} dB, width x{:.2}",
+            report.gain_db, report.max_eq_db, report.width
+        );
+
+        Ok(BackendOutput {
+            output_path,
+            params_applied: None,
+            backend_name: "matchering".into(),
+            message: format!(
+                "Matched to reference ({:+.1} dB level, up to {:+.1} dB EQ, width x{:.2})",
+                report.gain_db, report.max_eq_db, report.width
+            ),
+            corrections: Vec::new(),
+            explanation: None,
+            candidates: Vec::new(),
+            usage: None,
+            device: None,
         })
     }
 
     pub async fn check_available(&self) -> Result<bool> {
+        if self.engine == MatchingEngine::Native {
+            return Ok(true);
+        }
         let script = self.scripts_dir.join("matchering_bridge.py");
         if !script.exists() {
             return Ok(false);
         }
 
-        let python = self.python_path.clone();
-        let result = tokio::time::timeout(
-            std::time::Duration::from_secs(10),
-            tokio::task::spawn_blocking(move || {
-                Command::new(&python)
-                    .arg("-c")
-                    .arg("import matchering; print('ok')")
-                    .output()
-            }),
-        )
-        .await;
-
-        match result {
-            Ok(Ok(Ok(o))) => Ok(o.status.success()),
-            _ => Ok(false),
-        }
+        bridge::probe(&self.python_path, "import matchering; print('ok')").await
     }
 }
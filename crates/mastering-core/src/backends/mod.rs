@@ -1,13 +1,24 @@
 pub mod ai;
+pub mod basic;
+pub mod bridge;
+#[cfg(feature = "local-llm")]
+pub mod gguf;
 pub mod local_ml;
 pub mod matchering;
+#[cfg(feature = "onnx")]
+pub mod onnx;
+pub mod recipe;
 
 use anyhow::Result;
 use std::path::PathBuf;
 
+use crate::analysis::match_eq::MatchEqTarget;
 use crate::config::Config;
 use crate::error::MasteringError;
-use crate::types::{Backend, MasteringParams};
+use crate::pipeline::{ParamReview, ProgressReporter};
+use crate::types::{
+    Backend, MasteringParams, ParamCandidate, ParamCorrection, ParamExplanation, TokenUsage,
+};
 
 /// Options passed to any mastering backend.
 #[derive(Debug, Clone)]
@@ -16,9 +27,28 @@ pub struct MasteringOptions {
     pub output_path: PathBuf,
     pub reference_path: Option<PathBuf>,
     pub bit_depth: u16,
+    pub sample_format: crate::types::SampleFormat,
     pub target_lufs: f64,
     pub no_limiter: bool,
     pub preset: Option<crate::types::Preset>,
+    /// Fixed parameters to render instead of suggesting them.
+    pub params: Option<MasteringParams>,
+    /// Tonal balance to move toward with a few extra EQ bands.
+    pub match_eq: Option<MatchEqTarget>,
+    /// Highest true peak the limiter may let through, in dBTP.
+    pub ceiling_db: Option<f64>,
+    /// The input is spoken word; bias processing toward voice.
+    pub speech: bool,
+    /// Free-form description of the intended sound, passed to the AI.
+    pub brief: Option<String>,
+    /// Feedback on an earlier master to refine instead of starting over.
+    pub refinement: Option<crate::types::Refinement>,
+    /// Ask the AI to explain its parameter choices.
+    pub explain: bool,
+    /// Shown the suggested parameters before rendering, and may change them.
+    pub review: ParamReview,
+    /// Receives status updates while the backend works, e.g. streamed tokens.
+    pub progress: ProgressReporter,
 }
 
 /// Result from a mastering backend.
@@ -28,13 +58,25 @@ pub struct BackendOutput {
     pub params_applied: Option<MasteringParams>,
     pub backend_name: String,
     pub message: String,
+    /// Parameters clamped or dropped before processing.
+    pub corrections: Vec<ParamCorrection>,
+    pub explanation: Option<ParamExplanation>,
+    /// Scored parameter sets the applied one was chosen from.
+    pub candidates: Vec<ParamCandidate>,
+    /// Language model tokens spent, for backends that call one.
+    pub usage: Option<TokenUsage>,
+    /// Device a local ML model ran on.
+    pub device: Option<crate::types::Device>,
 }
 
 /// Enum-dispatch mastering engine — avoids async trait objects.
+#[allow(clippy::large_enum_variant)]
 pub enum MasteringEngine {
     Matchering(matchering::MatcheringBackend),
     Ai(ai::AiBackend),
     LocalMl(local_ml::LocalMlBackend),
+    Basic(basic::BasicBackend),
+    Recipe(recipe::RecipeBackend),
 }
 
 impl MasteringEngine {
@@ -47,6 +89,12 @@ impl MasteringEngine {
             crate::types::Backend::LocalMl => {
                 MasteringEngine::LocalMl(local_ml::LocalMlBackend::new(config))
             }
+            crate::types::Backend::Basic => {
+                MasteringEngine::Basic(basic::BasicBackend::new(config))
+            }
+            crate::types::Backend::Recipe => {
+                MasteringEngine::Recipe(recipe::RecipeBackend::new(config))
+            }
             crate::types::Backend::Auto => {
                 // Auto is resolved by the pipeline before reaching here; default to AI
                 MasteringEngine::Ai(ai::AiBackend::new(config))
@@ -59,6 +107,8 @@ impl MasteringEngine {
             MasteringEngine::Matchering(b) => b.process(opts).await,
             MasteringEngine::Ai(b) => b.process(opts).await,
             MasteringEngine::LocalMl(b) => b.process(opts).await,
+            MasteringEngine::Basic(b) => b.process(opts).await,
+            MasteringEngine::Recipe(b) => b.process(opts).await,
         }
     }
 
@@ -67,6 +117,8 @@ impl MasteringEngine {
             MasteringEngine::Matchering(_) => "matchering",
             MasteringEngine::Ai(_) => "ai",
             MasteringEngine::LocalMl(_) => "local-ml",
+            MasteringEngine::Basic(_) => "basic",
+            MasteringEngine::Recipe(_) => "recipe",
         }
     }
 
@@ -75,24 +127,35 @@ impl MasteringEngine {
             MasteringEngine::Matchering(b) => b.check_available().await,
             MasteringEngine::Ai(b) => b.check_available().await,
             MasteringEngine::LocalMl(b) => b.check_available().await,
+            MasteringEngine::Basic(b) => b.check_available().await,
+            MasteringEngine::Recipe(b) => b.check_available().await,
         }
     }
 
     /// Get the next fallback backend in the chain.
     ///
-    /// Fallback order: AI → Matchering → LocalMl
+    /// Fallback order: AI → Matchering → LocalMl → Basic. A recipe never
+    /// falls back, as another backend would not render the same chain.
     pub fn fallback(&self, config: &Config) -> Option<Self> {
         match self {
-            MasteringEngine::Ai(_) => Some(MasteringEngine::Matchering(matchering::MatcheringBackend::new(config))),
-            MasteringEngine::Matchering(_) => Some(MasteringEngine::LocalMl(local_ml::LocalMlBackend::new(config))),
-            MasteringEngine::LocalMl(_) => None, // No more fallbacks
+            MasteringEngine::Ai(_) => Some(MasteringEngine::Matchering(
+                matchering::MatcheringBackend::new(config),
+            )),
+            MasteringEngine::Matchering(_) => Some(MasteringEngine::LocalMl(
+                local_ml::LocalMlBackend::new(config),
+            )),
+            MasteringEngine::LocalMl(_) => {
+                Some(MasteringEngine::Basic(basic::BasicBackend::new(config)))
+            }
+            MasteringEngine::Basic(_) => None, // No more fallbacks
+            MasteringEngine::Recipe(_) => None,
         }
     }
 
     /// Process with automatic fallback on failure.
     ///
     /// Attempts the current backend, and if it fails, tries fallback backends
-    /// in order: AI → Matchering → LocalMl.
+    /// in order: AI → Matchering → LocalMl → Basic.
     pub async fn process_with_fallback(
         &self,
         opts: &MasteringOptions,
@@ -100,7 +163,7 @@ impl MasteringEngine {
     ) -> Result<BackendOutput, MasteringError> {
         // Try current backend first
         match self.process(opts).await {
-            Ok(output) => return Ok(output),
+            Ok(output) => Ok(output),
             Err(e) => {
                 let error: MasteringError = e.into();
 
@@ -135,6 +198,8 @@ impl MasteringEngine {
             Backend::Matchering => Self::Matchering(matchering::MatcheringBackend::new(config)),
             Backend::Ai => Self::Ai(ai::AiBackend::new(config)),
             Backend::LocalMl => Self::LocalMl(local_ml::LocalMlBackend::new(config)),
+            Backend::Basic => Self::Basic(basic::BasicBackend::new(config)),
+            Backend::Recipe => Self::Recipe(recipe::RecipeBackend::new(config)),
         }
     }
 }
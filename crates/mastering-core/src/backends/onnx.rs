@@ -0,0 +1,259 @@
+//! In-process inference of DeepAFx-ST-style ONNX models.
+//!
+//! Runs an exported audio-to-audio model with ONNX Runtime so the local ML
+//! backend needs no Python. The model takes the track as a `[1, 1, samples]`
+//! tensor and, if it has a second input, a reference excerpt of the same
+//! shape, and returns the processed track in the first output. Each channel
+//! is resampled to the model's rate and run in overlapping chunks that are
+//! crossfaded back together. Only built with the `onnx` feature; the ONNX
+//! Runtime library itself is loaded when the first model runs.
+//!
+//! GPUs are used through ONNX Runtime's CUDA and Core ML execution
+//! providers, which the loaded library must have been built with. When the
+//! device was picked automatically and its provider fails to load, the model
+//! runs on the CPU instead.
+
+use anyhow::{Context, Result};
+use ort::ep;
+use ort::session::builder::SessionBuilder;
+use ort::session::Session;
+use ort::value::Tensor;
+use std::path::Path;
+use tracing::warn;
+
+use crate::analysis::decode::DecodedAudio;
+use crate::config::LocalMlConfig;
+use crate::gpu;
+use crate::resample;
+use crate::types::Device;
+
+/// Length of the chunks fed to the model, in seconds.
+const CHUNK_SECS: f64 = 10.0;
+
+/// Overlap crossfaded between neighbouring chunks, in seconds.
+const OVERLAP_SECS: f64 = 0.5;
+
+/// Length of the reference excerpt given to models that take one, in seconds.
+const REFERENCE_SECS: f64 = 10.0;
+
+/// Run the configured model over `audio` on the configured device,
+/// returning interleaved samples at the input's rate and channel count, and
+/// the device the model ran on.
+///
+/// `on_progress` receives the completed share of the work in percent.
+pub fn process(
+    config: &LocalMlConfig,
+    audio: &DecodedAudio,
+    reference: Option<&DecodedAudio>,
+    mut on_progress: impl FnMut(f32),
+) -> Result<(Vec<f32>, Device)> {
+    if !config.onnx_runtime.is_empty() {
+        ort::init_from(&config.onnx_runtime)
+            .with_context(|| format!("Loading ONNX Runtime from {}", config.onnx_runtime))?
+            .commit();
+    }
+    let (mut builder, device) = session_builder(config.device)?;
+    let mut session = builder
+        .commit_from_file(&config.onnx_model)
+        .with_context(|| format!("Loading ONNX model {}", config.onnx_model))?;
+
+    let rate = config.onnx_sample_rate;
+    let reference = if session.inputs().len() > 1 {
+        let reference = reference.with_context(|| {
+            format!(
+                "{} matches a reference track; pass one with --reference",
+                model_name(config)
+            )
+        })?;
+        Some(reference_excerpt(reference, rate)?)
+    } else {
+        None
+    };
+
+    let chunk = (CHUNK_SECS * rate as f64) as usize;
+    let overlap = (OVERLAP_SECS * rate as f64) as usize;
+    let channels = audio.channels as usize;
+    let frames = audio.total_frames as usize;
+    let mut processed = Vec::with_capacity(channels);
+    for ch in 0..channels {
+        let signal = resample::resample(
+            &audio.channel_samples(ch as u16),
+            1,
+            audio.sample_rate,
+            rate,
+        )?;
+        let output = process_chunked(
+            &signal,
+            chunk,
+            overlap,
+            |samples| run(&mut session, samples, reference.as_deref()),
+            |done| on_progress(100.0 * (ch as f32 + done) / channels as f32),
+        )?;
+        let mut output = resample::resample(&output, 1, rate, audio.sample_rate)?;
+        output.resize(frames, 0.0);
+        processed.push(output);
+    }
+
+    let samples = (0..frames)
+        .flat_map(|i| processed.iter().map(move |ch| ch[i]))
+        .collect();
+    Ok((samples, device))
+}
+
+/// A session builder with the execution provider for `requested` registered,
+/// and the device it will run on.
+fn session_builder(requested: Device) -> Result<(SessionBuilder, Device)> {
+    let device = gpu::resolve_device(requested)?;
+    let builder = Session::builder()?;
+    let provider = match device {
+        Device::Cuda => ep::CUDA::default().build(),
+        Device::Metal => ep::CoreML::default().build(),
+        Device::Cpu | Device::Auto => return Ok((builder, Device::Cpu)),
+    };
+    match builder.with_execution_providers([provider.error_on_failure()]) {
+        Ok(builder) => Ok((builder, device)),
+        Err(e) if requested == Device::Auto => {
+            warn!("Running the ONNX model on the CPU: the {device} provider failed to load: {e}");
+            Ok((e.recover(), Device::Cpu))
+        }
+        Err(e) => anyhow::bail!(
+            "ONNX Runtime could not use the {device} device: {e}. \
+             Its library must be built with {device} support, or set backends.local_ml.device to \"auto\"."
+        ),
+    }
+}
+
+/// Whether the configured model file exists.
+pub fn is_configured(config: &LocalMlConfig) -> bool {
+    !config.onnx_model.is_empty() && Path::new(&config.onnx_model).is_file()
+}
+
+/// The model's file stem, e.g. `deepafx-st` for `/models/deepafx-st.onnx`.
+pub fn model_name(config: &LocalMlConfig) -> String {
+    Path::new(&config.onnx_model).file_stem().map_or_else(
+        || config.onnx_model.clone(),
+        |s| s.to_string_lossy().into_owned(),
+    )
+}
+
+fn run(session: &mut Session, samples: &[f32], reference: Option<&[f32]>) -> Result<Vec<f32>> {
+    let input = Tensor::from_array(([1usize, 1, samples.len()], samples.to_vec()))?;
+    let outputs = match reference {
+        Some(reference) => {
+            let reference = Tensor::from_array(([1usize, 1, reference.len()], reference.to_vec()))?;
+            session.run(ort::inputs![input, reference])?
+        }
+        None => session.run(ort::inputs![input])?,
+    };
+    let (_, output) = outputs[0].try_extract_tensor::<f32>()?;
+    anyhow::ensure!(
+        output.len() == samples.len(),
+        "The ONNX model returned {} samples for {} input samples",
+        output.len(),
+        samples.len()
+    );
+    Ok(output.to_vec())
+}
+
+/// Mono excerpt from the middle of `reference` at `rate`.
+fn reference_excerpt(reference: &DecodedAudio, rate: u32) -> Result<Vec<f32>> {
+    let mono = resample::resample(&reference.mono_mixdown(), 1, reference.sample_rate, rate)?;
+    let len = ((REFERENCE_SECS * rate as f64) as usize).min(mono.len());
+    anyhow::ensure!(len > 0, "The reference track is empty");
+    let start = (mono.len() - len) / 2;
+    Ok(mono[start..start + len].to_vec())
+}
+
+/// Run `model` over `signal` in chunks of up to `chunk` samples that overlap
+/// by `overlap`, crossfading linearly across each overlap.
+///
+/// `on_progress` receives the share of `signal` done so far, from 0 to 1.
+fn process_chunked(
+    signal: &[f32],
+    chunk: usize,
+    overlap: usize,
+    mut model: impl FnMut(&[f32]) -> Result<Vec<f32>>,
+    mut on_progress: impl FnMut(f32),
+) -> Result<Vec<f32>> {
+    anyhow::ensure!(chunk > overlap, "Chunks must be longer than their overlap");
+    let mut out = vec![0.0; signal.len()];
+    let mut start = 0;
+    while start < signal.len() {
+        let end = (start + chunk).min(signal.len());
+        let processed = model(&signal[start..end])?;
+        for (i, &sample) in processed.iter().enumerate() {
+            let slot = &mut out[start + i];
+            *slot = if start > 0 && i < overlap {
+                let fade = (i as f32 + 0.5) / overlap as f32;
+                *slot * (1.0 - fade) + sample * fade
+            } else {
+                sample
+            };
+        }
+        on_progress(end as f32 / signal.len() as f32);
+        if end == signal.len() {
+            break;
+        }
+        start = end - overlap;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunked_identity_is_lossless() {
+        let signal: Vec<f32> = (0..1000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let mut calls = 0;
+        let out = process_chunked(
+            &signal,
+            300,
+            50,
+            |chunk| {
+                calls += 1;
+                assert!(chunk.len() <= 300);
+                Ok(chunk.to_vec())
+            },
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(calls, 4);
+        for (a, b) in signal.iter().zip(&out) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_chunked_crossfades_between_chunks() {
+        let signal = vec![0.0; 500];
+        let mut index = 0.0;
+        let out = process_chunked(
+            &signal,
+            300,
+            100,
+            |chunk| {
+                index += 1.0;
+                Ok(vec![index; chunk.len()])
+            },
+            |_| {},
+        )
+        .unwrap();
+        // The second chunk starts at 200 and fades in over 100 samples
+        assert_eq!(out[199], 1.0);
+        assert!(out[200] > 1.0 && out[200] < 1.1);
+        assert!(out.windows(2).all(|w| w[1] >= w[0]));
+        assert_eq!(out[300], 2.0);
+    }
+
+    #[test]
+    fn test_model_name_is_file_stem() {
+        let config = LocalMlConfig {
+            onnx_model: "/models/deepafx-st.onnx".into(),
+            ..Default::default()
+        };
+        assert_eq!(model_name(&config), "deepafx-st");
+        assert!(!is_configured(&config));
+    }
+}
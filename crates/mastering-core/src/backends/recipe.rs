@@ -0,0 +1,45 @@
+use anyhow::Result;
+use tracing::info;
+
+use super::basic::BasicBackend;
+use super::{BackendOutput, MasteringOptions};
+use crate::config::Config;
+use crate::error::MasteringError;
+
+/// Renders the fixed parameters of a saved recipe through the built-in DSP
+/// chain. No model is asked for parameters, so a recipe masters every
+/// track with the same chain.
+#[derive(Debug, Clone, Default)]
+pub struct RecipeBackend {
+    renderer: BasicBackend,
+}
+
+impl RecipeBackend {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            renderer: BasicBackend::new(config),
+        }
+    }
+
+    pub async fn process(&self, opts: &MasteringOptions) -> Result<BackendOutput> {
+        if opts.params.is_none() {
+            return Err(MasteringError::InvalidConfig {
+                message: "The recipe backend needs a recipe to render".into(),
+                config_key: Some("recipe".into()),
+            }
+            .into());
+        }
+        info!("Rendering recipe on: {}", opts.input_path.display());
+
+        let output = self.renderer.process(opts).await?;
+        Ok(BackendOutput {
+            backend_name: "recipe".into(),
+            message: "Rendered a saved recipe with the built-in DSP chain".into(),
+            ..output
+        })
+    }
+
+    pub async fn check_available(&self) -> Result<bool> {
+        Ok(true)
+    }
+}
@@ -1,160 +1,233 @@
-//! Result caching for mastering operations.
+//! On-disk caching of analysis results.
 //!
-//! Caches analysis results and processed audio to enable retry and re-mastering
-//! without re-processing.
+//! Each analysed file gets a small JSON entry keyed by its canonical path.
+//! An entry records the file's size and modification time, so it is ignored
+//! automatically once the file changes, and the [`ANALYSIS_VERSION`] it was
+//! computed with, so entries go stale when the metrics themselves change.
 
-use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, SystemTime};
+use std::time::UNIX_EPOCH;
 
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use tracing::debug;
 
 use crate::types::AudioAnalysis;
 
-/// Cache entry for analysis results.
+/// Version of the analysis algorithms. Bump when metric computation changes
+/// so previously cached results are recomputed.
+pub const ANALYSIS_VERSION: u32 = 16;
+
+/// A cached analysis result together with what it was computed from.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry {
-    /// Hash of the input file content
+    pub version: u32,
+    /// Fingerprint of the input file, see [`compute_file_hash`].
     pub file_hash: String,
-    /// When this entry was created
-    pub created_at: SystemTime,
-    /// Time-to-live for this entry
-    pub ttl: Duration,
+    pub analysis: AudioAnalysis,
 }
 
-/// Cache for audio analysis results.
+/// Disk-backed cache for audio analysis results.
+#[derive(Debug, Clone)]
 pub struct AnalysisCache {
-    entries: RwLock<HashMap<PathBuf, CacheEntry>>,
-    max_entries: usize,
-    default_ttl: Duration,
+    dir: PathBuf,
 }
 
 impl AnalysisCache {
-    /// Create a new analysis cache.
-    pub fn new(max_entries: usize, default_ttl: Duration) -> Self {
-        Self {
-            entries: RwLock::new(HashMap::new()),
-            max_entries,
-            default_ttl,
-        }
+    /// Create a cache that stores entries in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
     }
 
-    /// Create a cache with default settings (100 entries, 1 hour TTL).
-    pub fn with_defaults() -> Self {
-        Self::new(100, Duration::from_secs(3600))
+    /// Default cache location (`<cache dir>/mastering/analysis`).
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("mastering")
+            .join("analysis")
     }
 
-    /// Get a cached analysis result for a file.
-    pub async fn get(&self, path: &Path) -> Option<AudioAnalysis> {
-        let entries = self.entries.read().await;
-        // Check if entry exists and is not expired
-        // In a real implementation, we'd store and return the actual analysis
-        entries.get(path).and_then(|entry| {
-            if entry.created_at + entry.ttl > SystemTime::now() {
-                // Entry is valid
-                Some(()) // Placeholder - would return cached analysis
-            } else {
-                None
-            }
-        });
-        None // Placeholder - return actual cached analysis
+    pub fn dir(&self) -> &Path {
+        &self.dir
     }
 
-    /// Store an analysis result in the cache.
-    pub async fn put(&self, path: PathBuf, _analysis: &AudioAnalysis) {
-        let mut entries = self.entries.write().await;
-
-        // Calculate file hash (simplified - would use actual content hash)
-        let file_hash = format!("{:?}", path); // Placeholder
-
-        // Evict oldest entry if at capacity
-        if entries.len() >= self.max_entries {
-            if let Some(oldest) = entries
-                .iter()
-                .min_by_key(|(_, e)| e.created_at)
-                .map(|(k, _)| k.clone())
-            {
-                entries.remove(&oldest);
-            }
-        }
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let key = fnv1a(canonical.to_string_lossy().as_bytes());
+        self.dir.join(format!("{key:016x}.json"))
+    }
 
-        entries.insert(
-            path,
-            CacheEntry {
-                file_hash,
-                created_at: SystemTime::now(),
-                ttl: self.default_ttl,
-            },
-        );
+    /// Get the cached analysis for `path` if the file is unchanged since it was cached.
+    pub async fn get(&self, path: &Path) -> Option<AudioAnalysis> {
+        let file_hash = compute_file_hash(path).ok()?;
+        let contents = tokio::fs::read(self.entry_path(path)).await.ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&contents).ok()?;
+
+        if entry.version != ANALYSIS_VERSION || entry.file_hash != file_hash {
+            debug!("Stale analysis cache entry for {}", path.display());
+            return None;
+        }
+        Some(entry.analysis)
     }
 
-    /// Invalidate cache entry for a specific file.
-    pub async fn invalidate(&self, path: &Path) {
-        let mut entries = self.entries.write().await;
-        entries.remove(path);
+    /// Store the analysis result for `path`.
+    pub async fn put(&self, path: &Path, analysis: &AudioAnalysis) -> std::io::Result<()> {
+        let entry = CacheEntry {
+            version: ANALYSIS_VERSION,
+            file_hash: compute_file_hash(path)?,
+            analysis: analysis.clone(),
+        };
+        let contents = serde_json::to_vec(&entry)?;
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        // Write to a temporary file first so readers never see a partial entry
+        let entry_path = self.entry_path(path);
+        let tmp_path = entry_path.with_extension("json.tmp");
+        tokio::fs::write(&tmp_path, contents).await?;
+        tokio::fs::rename(&tmp_path, &entry_path).await
     }
 
-    /// Clear all cache entries.
-    pub async fn clear(&self) {
-        let mut entries = self.entries.write().await;
-        entries.clear();
+    /// Invalidate the cache entry for a specific file.
+    pub async fn invalidate(&self, path: &Path) {
+        let _ = tokio::fs::remove_file(self.entry_path(path)).await;
     }
 
-    /// Remove expired entries.
-    pub async fn cleanup(&self) {
-        let mut entries = self.entries.write().await;
-        let now = SystemTime::now();
-        entries.retain(|_, entry| entry.created_at + entry.ttl > now);
+    /// Remove all cache entries.
+    pub async fn clear(&self) -> std::io::Result<()> {
+        match tokio::fs::remove_dir_all(&self.dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
     }
 
     /// Get the number of cached entries.
     pub async fn len(&self) -> usize {
-        self.entries.read().await.len()
+        let Ok(mut dir) = tokio::fs::read_dir(&self.dir).await else {
+            return 0;
+        };
+        let mut count = 0;
+        while let Ok(Some(entry)) = dir.next_entry().await {
+            if entry.path().extension().is_some_and(|e| e == "json") {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Whether the cache holds no entries.
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
     }
 }
 
 /// Global cache instance.
 static GLOBAL_CACHE: std::sync::OnceLock<AnalysisCache> = std::sync::OnceLock::new();
 
-/// Get the global analysis cache.
+/// Get the global analysis cache in the default location.
 pub fn global_cache() -> &'static AnalysisCache {
-    GLOBAL_CACHE.get_or_init(AnalysisCache::with_defaults)
+    GLOBAL_CACHE.get_or_init(|| AnalysisCache::new(AnalysisCache::default_dir()))
 }
 
-/// Compute a hash of a file's contents.
+/// Fingerprint a file for cache invalidation.
 ///
-/// Uses a simple modification time and size-based hash for caching.
-/// In production, you'd use a content-based hash like blake3.
+/// Uses the size and modification time rather than a content hash, which is
+/// enough to notice edits without reading multi-GB files.
 pub fn compute_file_hash(path: &Path) -> Result<String, std::io::Error> {
     let metadata = std::fs::metadata(path)?;
     let modified = metadata.modified()?;
     let size = metadata.len();
 
-    // Simple hash based on mtime and size
-    use std::time::UNIX_EPOCH;
-    let mtime_secs = modified
+    let mtime_nanos = modified
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
-        .as_secs();
+        .as_nanos();
+
+    Ok(format!("{}-{}", mtime_nanos, size))
+}
 
-    Ok(format!("{}-{}", mtime_secs, size))
+/// 64-bit FNV-1a; stable across builds, unlike `DefaultHasher`.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{AudioMetadata, FrequencyBands};
+
+    fn sample_analysis(path: &Path) -> AudioAnalysis {
+        AudioAnalysis {
+            metadata: AudioMetadata {
+                path: path.to_path_buf(),
+                sample_rate: 48000,
+                channels: 2,
+                channel_layout: "stereo".into(),
+                duration_secs: 1.0,
+                format: "WAV".into(),
+                ..Default::default()
+            },
+            lufs_integrated: -14.0,
+            lufs_short_term_max: -12.0,
+            lufs_momentary_max: -10.0,
+            loudness_range_lu: 6.0,
+            rms_db: -16.0,
+            peak_db: -1.0,
+            true_peak_db: -0.8,
+            dynamic_range_db: 8.0,
+            stereo_width: 0.5,
+            phase_correlation: 1.0,
+            phase_correlation_min: 1.0,
+            frequency_bands: FrequencyBands {
+                sub_bass: -20.0,
+                bass: -10.0,
+                low_mid: -12.0,
+                mid: -8.0,
+                upper_mid: -14.0,
+                presence: -18.0,
+                brilliance: -22.0,
+            },
+            ..Default::default()
+        }
+    }
 
     #[tokio::test]
-    async fn test_cache_creation() {
-        let cache = AnalysisCache::with_defaults();
-        assert_eq!(cache.len().await, 0);
+    async fn test_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("track.wav");
+        std::fs::write(&audio, b"audio").unwrap();
+
+        let cache = AnalysisCache::new(dir.path().join("cache"));
+        assert!(cache.is_empty().await);
+        assert!(cache.get(&audio).await.is_none());
+
+        cache.put(&audio, &sample_analysis(&audio)).await.unwrap();
+        assert_eq!(cache.len().await, 1);
+        let cached = cache.get(&audio).await.unwrap();
+        assert_eq!(cached.lufs_integrated, -14.0);
+
+        cache.invalidate(&audio).await;
+        assert!(cache.get(&audio).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_modified_file_invalidates_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("track.wav");
+        std::fs::write(&audio, b"audio").unwrap();
+
+        let cache = AnalysisCache::new(dir.path().join("cache"));
+        cache.put(&audio, &sample_analysis(&audio)).await.unwrap();
+
+        std::fs::write(&audio, b"different audio").unwrap();
+        assert!(cache.get(&audio).await.is_none());
     }
 
     #[tokio::test]
-    async fn test_cache_put_and_len() {
-        let cache = AnalysisCache::with_defaults();
-        // Note: This test requires actual AudioAnalysis data
+    async fn test_clear_missing_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = AnalysisCache::new(dir.path().join("never-created"));
+        cache.clear().await.unwrap();
         assert_eq!(cache.len().await, 0);
     }
 }
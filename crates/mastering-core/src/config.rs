@@ -1,10 +1,16 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
-use crate::types::{AiProvider, AudioFormat, Backend};
+use crate::error::MasteringError;
+use crate::pipeline::WatchFolder;
+use crate::types::{
+    AiProvider, AudioFormat, Backend, Device, Dither, MasteringParams, MatchingEngine,
+    OverwritePolicy, SurroundMode,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default)]
     pub general: GeneralConfig,
@@ -12,6 +18,60 @@ pub struct Config {
     pub ai: AiConfig,
     #[serde(default)]
     pub backends: BackendsConfig,
+    #[serde(default)]
+    pub encoding: EncodingConfig,
+    #[serde(default)]
+    pub stems: StemsConfig,
+    #[serde(default)]
+    pub restoration: RestorationConfig,
+    #[serde(default)]
+    pub watch: WatchConfig,
+    /// User-defined presets by name, from `[presets.<name>]` sections.
+    #[serde(default)]
+    pub presets: BTreeMap<String, CustomPreset>,
+}
+
+/// A user-defined preset. Every field is optional; what a preset leaves
+/// out keeps its usual default, and options given on a job win over it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CustomPreset {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_lufs: Option<f64>,
+    /// Highest true peak of the output in dBTP.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ceiling_db: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<AudioFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bit_depth: Option<u16>,
+    /// Fixed parameters rendered as they are instead of asking a backend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<MasteringParams>,
+}
+
+impl CustomPreset {
+    /// One-line summary for listings, e.g. "-14 LUFS, -1.0 dBTP, fixed chain".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(lufs) = self.target_lufs {
+            parts.push(format!("{lufs} LUFS"));
+        }
+        if let Some(ceiling) = self.ceiling_db {
+            parts.push(format!("{ceiling:.1} dBTP"));
+        }
+        if let Some(format) = self.format {
+            parts.push(format.to_string());
+        }
+        if let Some(bits) = self.bit_depth {
+            parts.push(format!("{bits}-bit"));
+        }
+        if self.params.is_some() {
+            parts.push("fixed chain".into());
+        }
+        parts.join(", ")
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,12 +84,86 @@ pub struct GeneralConfig {
     pub default_format: AudioFormat,
     #[serde(default = "default_target_lufs")]
     pub target_lufs: f64,
+    /// Number of batch jobs processed in parallel.
+    #[serde(default = "default_batch_concurrency")]
+    pub batch_concurrency: usize,
+    /// Retry format conversion with a system ffmpeg if native encoding fails.
+    #[serde(default)]
+    pub ffmpeg_fallback: bool,
+    /// Handling of inputs with more than two channels.
+    #[serde(default)]
+    pub surround_mode: SurroundMode,
+    /// Accepted deviation of the output from the target loudness, in LU.
+    #[serde(default = "default_lufs_tolerance")]
+    pub lufs_tolerance: f64,
+    /// Corrective gain/limiter passes when the output misses the target (0 disables).
+    #[serde(default = "default_max_loudness_passes")]
+    pub max_loudness_passes: u32,
+    /// Left/right level difference in dB above which `--fix-balance` evens
+    /// out the channels.
+    #[serde(default = "default_balance_threshold_db")]
+    pub balance_threshold_db: f64,
+    /// Share of the difference to a match EQ target or target curve that
+    /// the EQ corrects, from 0 (none) to 1 (all of it).
+    #[serde(default = "default_match_eq_strength")]
+    pub match_eq_strength: f64,
+    /// Never reach beyond this machine: cloud AI providers are refused and a
+    /// configured default falls back to the rules provider.
+    #[serde(default)]
+    pub offline: bool,
+    /// What to do when an output file already exists.
+    #[serde(default)]
+    pub overwrite: OverwritePolicy,
+}
+
+/// Settings for writing the final output file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncodingConfig {
+    #[serde(default = "default_mp3_bitrate")]
+    pub mp3_bitrate_kbps: u32,
+    #[serde(default = "default_aac_bitrate")]
+    pub aac_bitrate_kbps: u32,
+    #[serde(default = "default_opus_bitrate")]
+    pub opus_bitrate_kbps: u32,
+    /// Output sample rate; `None` keeps the rate of the source.
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
+    /// Dither used when writing lossless output below 32 bits.
+    #[serde(default)]
+    pub dither: Dither,
+}
+
+impl EncodingConfig {
+    /// Configured bitrate for `format`, or `None` for lossless formats.
+    pub fn bitrate_for(&self, format: AudioFormat) -> Option<u32> {
+        match format {
+            AudioFormat::Mp3 => Some(self.mp3_bitrate_kbps),
+            AudioFormat::Aac => Some(self.aac_bitrate_kbps),
+            AudioFormat::Opus => Some(self.opus_bitrate_kbps),
+            AudioFormat::Wav | AudioFormat::Flac | AudioFormat::Aiff | AudioFormat::Alac => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiConfig {
     #[serde(default = "default_ai_provider")]
     pub default_provider: AiProvider,
+    /// Render passes per track; after each pass but the last, the AI sees
+    /// the output's analysis and may correct its parameters.
+    #[serde(default = "default_ai_passes")]
+    pub passes: u32,
+    /// Parameter sets requested per track. Above 1, each is rendered over an
+    /// excerpt with the native DSP chain and the best-scoring one is used.
+    #[serde(default = "default_ai_candidates")]
+    pub candidates: u32,
+    /// Length of the excerpt candidates are scored on, in seconds.
+    #[serde(default = "default_candidate_excerpt_secs")]
+    pub candidate_excerpt_secs: f64,
+    /// Stream responses from Ollama and OpenAI-style APIs so progress can
+    /// show the tokens received while the model is still answering.
+    #[serde(default = "default_ai_stream")]
+    pub stream: bool,
     #[serde(default)]
     pub ollama: OllamaConfig,
     #[serde(default)]
@@ -39,7 +173,234 @@ pub struct AiConfig {
     #[serde(default)]
     pub openai: OpenAiConfig,
     #[serde(default)]
+    pub azure_openai: AzureOpenAiConfig,
+    #[serde(default)]
+    pub openai_compatible: OpenAiCompatibleConfig,
+    #[serde(default)]
     pub anthropic: AnthropicConfig,
+    #[serde(default)]
+    pub local_gguf: LocalGgufConfig,
+    #[serde(default)]
+    pub rules: RulesConfig,
+    #[serde(default)]
+    pub limits: ParamLimits,
+    #[serde(default)]
+    pub prompts: PromptsConfig,
+    /// Per-model token prices used for cost estimates, overriding the
+    /// built-in prices. Keys are model names (or Azure deployment names).
+    #[serde(default)]
+    pub pricing: BTreeMap<String, ModelPrice>,
+    #[serde(default)]
+    pub network: NetworkConfig,
+}
+
+/// Proxy and TLS settings for every AI provider request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Proxy URL for all requests, e.g. `http://proxy:3128` or
+    /// `socks5://proxy:1080`. Empty connects directly.
+    #[serde(default)]
+    pub proxy: String,
+    /// Comma-separated hosts that bypass the proxy.
+    #[serde(default = "default_no_proxy")]
+    pub no_proxy: String,
+    /// PEM file of extra root certificates to trust, e.g. a studio CA.
+    #[serde(default)]
+    pub ca_cert_file: String,
+}
+
+impl AiConfig {
+    /// Response timeout in seconds and token limit of `provider`, or `None`
+    /// for providers that make no HTTP requests.
+    pub fn request_limits(&self, provider: AiProvider) -> Option<(u64, u32)> {
+        match provider {
+            AiProvider::Ollama => Some((self.ollama.timeout_secs, self.ollama.max_tokens)),
+            AiProvider::LmStudio => Some((self.lmstudio.timeout_secs, self.lmstudio.max_tokens)),
+            AiProvider::KeyhanStudio => {
+                Some((self.keyhanstudio.timeout_secs, self.keyhanstudio.max_tokens))
+            }
+            AiProvider::OpenAi => Some((self.openai.timeout_secs, self.openai.max_tokens)),
+            AiProvider::AzureOpenAi => {
+                Some((self.azure_openai.timeout_secs, self.azure_openai.max_tokens))
+            }
+            AiProvider::OpenAiCompatible => Some((
+                self.openai_compatible.timeout_secs,
+                self.openai_compatible.max_tokens,
+            )),
+            AiProvider::Anthropic => Some((self.anthropic.timeout_secs, self.anthropic.max_tokens)),
+            AiProvider::LocalGguf | AiProvider::Rules => None,
+        }
+    }
+
+    /// Whether `provider` reaches beyond this machine: cloud APIs always do,
+    /// Ollama and LM Studio only when pointed at another host.
+    pub fn needs_network(&self, provider: AiProvider) -> bool {
+        match provider {
+            AiProvider::Ollama => !is_local_endpoint(&self.ollama.endpoint),
+            AiProvider::LmStudio => !is_local_endpoint(&self.lmstudio.endpoint),
+            AiProvider::LocalGguf | AiProvider::Rules => false,
+            AiProvider::KeyhanStudio
+            | AiProvider::OpenAi
+            | AiProvider::AzureOpenAi
+            | AiProvider::OpenAiCompatible
+            | AiProvider::Anthropic => true,
+        }
+    }
+}
+
+/// Whether the host of `endpoint` is this machine.
+fn is_local_endpoint(endpoint: &str) -> bool {
+    let authority = endpoint
+        .split_once("://")
+        .map_or(endpoint, |(_, rest)| rest)
+        .split(['/', '?'])
+        .next()
+        .unwrap_or_default();
+    let host = match authority.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Price of a language model in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+}
+
+impl ModelPrice {
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_mtok + output_tokens as f64 * self.output_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Prompt template files that replace the built-in AI prompts.
+///
+/// Empty paths keep the defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptsConfig {
+    /// System prompt describing the engineer's role and the JSON format.
+    #[serde(default)]
+    pub system_file: String,
+    /// Per-track prompt; may use `{analysis}`, `{preset}`, `{target_lufs}`,
+    /// `{no_limiter}`, `{genre}` and `{brief}`.
+    #[serde(default)]
+    pub user_file: String,
+}
+
+/// Safe ranges that AI-generated parameters are clamped to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParamLimits {
+    /// Largest EQ boost or cut.
+    #[serde(default = "default_limits_eq_max_gain_db")]
+    pub eq_max_gain_db: f64,
+    #[serde(default = "default_limits_eq_min_frequency_hz")]
+    pub eq_min_frequency_hz: f64,
+    #[serde(default = "default_limits_eq_max_frequency_hz")]
+    pub eq_max_frequency_hz: f64,
+    #[serde(default = "default_limits_eq_min_q")]
+    pub eq_min_q: f64,
+    #[serde(default = "default_limits_eq_max_q")]
+    pub eq_max_q: f64,
+    /// Bands past this count are dropped.
+    #[serde(default = "default_limits_max_eq_bands")]
+    pub max_eq_bands: usize,
+    #[serde(default = "default_limits_compression_min_threshold_db")]
+    pub compression_min_threshold_db: f64,
+    #[serde(default = "default_limits_compression_max_ratio")]
+    pub compression_max_ratio: f64,
+    #[serde(default = "default_limits_compression_min_attack_ms")]
+    pub compression_min_attack_ms: f64,
+    #[serde(default = "default_limits_compression_max_attack_ms")]
+    pub compression_max_attack_ms: f64,
+    #[serde(default = "default_limits_compression_min_release_ms")]
+    pub compression_min_release_ms: f64,
+    #[serde(default = "default_limits_compression_max_release_ms")]
+    pub compression_max_release_ms: f64,
+    #[serde(default = "default_limits_compression_max_knee_db")]
+    pub compression_max_knee_db: f64,
+    #[serde(default = "default_limits_compression_max_makeup_db")]
+    pub compression_max_makeup_db: f64,
+    #[serde(default = "default_limits_limiter_min_ceiling_db")]
+    pub limiter_min_ceiling_db: f64,
+    #[serde(default = "default_limits_limiter_max_ceiling_db")]
+    pub limiter_max_ceiling_db: f64,
+    #[serde(default = "default_limits_limiter_min_release_ms")]
+    pub limiter_min_release_ms: f64,
+    #[serde(default = "default_limits_limiter_max_release_ms")]
+    pub limiter_max_release_ms: f64,
+    #[serde(default = "default_limits_max_stereo_width")]
+    pub max_stereo_width: f64,
+    #[serde(default = "default_limits_min_target_lufs")]
+    pub min_target_lufs: f64,
+    #[serde(default = "default_limits_max_target_lufs")]
+    pub max_target_lufs: f64,
+}
+
+/// Embedded GGUF model used by the `local-gguf` provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalGgufConfig {
+    /// Path to a quantized llama-family model, e.g. `qwen2.5-1.5b-instruct-q4_k_m.gguf`.
+    #[serde(default)]
+    pub model_path: String,
+    /// `tokenizer.json` of the model; defaults to the one next to the model file.
+    #[serde(default)]
+    pub tokenizer_path: String,
+    #[serde(default = "default_local_gguf_max_tokens")]
+    pub max_tokens: usize,
+    /// Prompt layout with `{system}` and `{prompt}` placeholders.
+    #[serde(default = "default_local_gguf_chat_template")]
+    pub chat_template: String,
+}
+
+/// Thresholds of the rule-based (`rules`) provider.
+///
+/// Band levels are in dB relative to the total energy, as reported in
+/// [`FrequencyBands`](crate::types::FrequencyBands).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulesConfig {
+    /// Boost below 60 Hz when sub-bass falls below this level.
+    #[serde(default = "default_rules_sub_bass_min_db")]
+    pub sub_bass_min_db: f64,
+    /// Cut the low shelf when bass exceeds this level.
+    #[serde(default = "default_rules_bass_max_db")]
+    pub bass_max_db: f64,
+    /// Cut around 300 Hz when low-mids exceed this level.
+    #[serde(default = "default_rules_mud_max_db")]
+    pub mud_max_db: f64,
+    /// Cut around 3 kHz when upper-mids exceed this level.
+    #[serde(default = "default_rules_harshness_max_db")]
+    pub harshness_max_db: f64,
+    /// Lift the top octave when brilliance falls below this level.
+    #[serde(default = "default_rules_air_min_db")]
+    pub air_min_db: f64,
+    /// Fraction of a band's deviation that the EQ corrects.
+    #[serde(default = "default_rules_eq_amount")]
+    pub eq_amount: f64,
+    /// Largest boost or cut of a single EQ band.
+    #[serde(default = "default_rules_max_eq_db")]
+    pub max_eq_db: f64,
+    /// Corner of the rumble high-pass filter.
+    #[serde(default = "default_rules_high_pass_hz")]
+    pub high_pass_hz: f64,
+    /// Dynamic range above which a 2.5:1 ratio is used.
+    #[serde(default = "default_rules_dynamic_range_high_db")]
+    pub dynamic_range_high_db: f64,
+    /// Dynamic range above which a 2:1 ratio is used (1.5:1 below).
+    #[serde(default = "default_rules_dynamic_range_low_db")]
+    pub dynamic_range_low_db: f64,
+    /// Stereo width above which the image is narrowed.
+    #[serde(default = "default_rules_max_stereo_width")]
+    pub max_stereo_width: f64,
+    /// Limiter ceiling.
+    #[serde(default = "default_rules_ceiling_db")]
+    pub ceiling_db: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +409,12 @@ pub struct OllamaConfig {
     pub endpoint: String,
     #[serde(default = "default_ollama_model")]
     pub model: String,
+    /// Seconds to wait for a response before giving up.
+    #[serde(default = "default_local_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Most tokens the model may generate per response.
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +423,10 @@ pub struct KeyhanStudioConfig {
     pub endpoint: String,
     #[serde(default)]
     pub api_key: String,
+    #[serde(default = "default_cloud_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +435,47 @@ pub struct OpenAiConfig {
     pub api_key: String,
     #[serde(default = "default_openai_model")]
     pub model: String,
+    #[serde(default = "default_cloud_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+}
+
+/// An Azure OpenAI resource and the model deployment to call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AzureOpenAiConfig {
+    /// Resource endpoint, e.g. `https://my-studio.openai.azure.com`.
+    #[serde(default)]
+    pub endpoint: String,
+    /// Name of the model deployment in the resource.
+    #[serde(default)]
+    pub deployment: String,
+    #[serde(default = "default_azure_api_version")]
+    pub api_version: String,
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_cloud_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+}
+
+/// A generic OpenAI-compatible gateway (OpenRouter, Groq, Together, ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAiCompatibleConfig {
+    /// API root that `/chat/completions` is appended to,
+    /// e.g. `https://openrouter.ai/api/v1`.
+    #[serde(default)]
+    pub base_url: String,
+    #[serde(default)]
+    pub model: String,
+    /// Sent as a bearer token; leave empty for gateways without auth.
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_cloud_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -72,6 +484,10 @@ pub struct AnthropicConfig {
     pub api_key: String,
     #[serde(default = "default_anthropic_model")]
     pub model: String,
+    #[serde(default = "default_cloud_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -80,6 +496,10 @@ pub struct LmStudioConfig {
     pub endpoint: String,
     #[serde(default)]
     pub model: String,
+    #[serde(default = "default_local_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,20 +508,115 @@ pub struct BackendsConfig {
     pub matchering: MatcheringConfig,
     #[serde(default)]
     pub local_ml: LocalMlConfig,
+    /// Seconds the AI backend's DSP render (apply_fx.py) may run before it is killed.
+    #[serde(default = "default_bridge_timeout_secs")]
+    pub dsp_timeout_secs: u64,
+}
+
+/// Stem separation run before mastering when a job asks for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StemsConfig {
+    #[serde(default = "default_python_path")]
+    pub python_path: String,
+    /// Demucs model, e.g. `htdemucs` or `htdemucs_6s` (six stems).
+    #[serde(default = "default_stems_model")]
+    pub model: String,
+    #[serde(default)]
+    pub device: Device,
+    /// Seconds the separation script may run before it is killed.
+    #[serde(default = "default_bridge_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+/// Watch folder the desktop app masters new files from in the background.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatchConfig {
+    /// Resume watching when the app starts; set while the watch runs.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Show a notification when a file has been mastered or failed.
+    #[serde(default = "default_watch_notify")]
+    pub notify: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub backend: Backend,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<AudioFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub folder: Option<WatchFolder>,
+}
+
+/// Settings for the restoration stages a job can enable.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestorationConfig {
+    /// Largest reduction of the noise gate, in dB.
+    #[serde(default = "default_denoise_reduction_db")]
+    pub denoise_reduction_db: f64,
+    /// Mains frequency to remove hum at; 0 detects 50 or 60 Hz.
+    #[serde(default)]
+    pub hum_frequency: f64,
+    /// Number of hum partials notched, the fundamental included.
+    #[serde(default = "default_hum_harmonics")]
+    pub hum_harmonics: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatcheringConfig {
+    /// Built-in matching, or the matchering Python package.
+    #[serde(default)]
+    pub engine: MatchingEngine,
     #[serde(default = "default_python_path")]
     pub python_path: String,
+    /// Seconds the matchering bridge may run before it is killed.
+    #[serde(default = "default_bridge_timeout_secs")]
+    pub timeout_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalMlConfig {
     #[serde(default = "default_python_path")]
     pub python_path: String,
+    /// Model run by the ML inference script. If a model of this name is
+    /// installed, the script is given its directory.
     #[serde(default = "default_ml_model")]
     pub default_model: String,
+    /// ONNX model run in-process instead of the ML inference script (needs
+    /// the `onnx` feature): a file path or the name of an installed model.
+    #[serde(default)]
+    pub onnx_model: String,
+    /// ONNX Runtime library to load; empty uses `ORT_DYLIB_PATH` or the
+    /// system library.
+    #[serde(default)]
+    pub onnx_runtime: String,
+    /// Sample rate the ONNX model expects, in Hz.
+    #[serde(default = "default_onnx_sample_rate")]
+    pub onnx_sample_rate: u32,
+    /// Device the models run on; `auto` picks the fastest one found.
+    #[serde(default)]
+    pub device: Device,
+    /// Seconds the ML inference script may run before it is killed.
+    #[serde(default = "default_bridge_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Models `mastering models download` can fetch, by name.
+    #[serde(default)]
+    pub models: BTreeMap<String, ModelSource>,
+}
+
+/// Where to download a local ML model from on the HuggingFace Hub.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelSource {
+    /// Repository id, e.g. `org/model`.
+    pub repo: String,
+    /// Branch, tag or commit to download.
+    #[serde(default = "default_model_revision")]
+    pub revision: String,
+    /// Files to fetch, relative to the repository root.
+    pub files: Vec<String>,
+    /// Expected SHA-256 per file. Files without one are checked against the
+    /// hashes the hub publishes.
+    #[serde(default)]
+    pub sha256: BTreeMap<String, String>,
 }
 
 // --- Default value functions ---
@@ -118,9 +633,59 @@ fn default_format() -> AudioFormat {
 fn default_target_lufs() -> f64 {
     -14.0
 }
+fn default_batch_concurrency() -> usize {
+    1
+}
+fn default_lufs_tolerance() -> f64 {
+    0.5
+}
+fn default_max_loudness_passes() -> u32 {
+    3
+}
+fn default_balance_threshold_db() -> f64 {
+    1.0
+}
+fn default_match_eq_strength() -> f64 {
+    1.0
+}
+fn default_mp3_bitrate() -> u32 {
+    320
+}
+fn default_aac_bitrate() -> u32 {
+    256
+}
+fn default_opus_bitrate() -> u32 {
+    160
+}
 fn default_ai_provider() -> AiProvider {
     AiProvider::Ollama
 }
+fn default_ai_passes() -> u32 {
+    1
+}
+fn default_ai_candidates() -> u32 {
+    1
+}
+fn default_candidate_excerpt_secs() -> f64 {
+    30.0
+}
+fn default_ai_stream() -> bool {
+    true
+}
+
+fn default_watch_notify() -> bool {
+    true
+}
+/// Local models can take minutes on CPU-only machines.
+fn default_local_timeout_secs() -> u64 {
+    300
+}
+fn default_cloud_timeout_secs() -> u64 {
+    120
+}
+fn default_max_tokens() -> u32 {
+    4096
+}
 fn default_ollama_endpoint() -> String {
     "http://localhost:11434".into()
 }
@@ -130,38 +695,178 @@ fn default_ollama_model() -> String {
 fn default_openai_model() -> String {
     "gpt-4o".into()
 }
+fn default_limits_eq_max_gain_db() -> f64 {
+    6.0
+}
+fn default_limits_eq_min_frequency_hz() -> f64 {
+    20.0
+}
+fn default_limits_eq_max_frequency_hz() -> f64 {
+    20000.0
+}
+fn default_limits_eq_min_q() -> f64 {
+    0.1
+}
+fn default_limits_eq_max_q() -> f64 {
+    10.0
+}
+fn default_limits_max_eq_bands() -> usize {
+    10
+}
+fn default_limits_compression_min_threshold_db() -> f64 {
+    -40.0
+}
+fn default_limits_compression_max_ratio() -> f64 {
+    8.0
+}
+fn default_limits_compression_min_attack_ms() -> f64 {
+    0.1
+}
+fn default_limits_compression_max_attack_ms() -> f64 {
+    100.0
+}
+fn default_limits_compression_min_release_ms() -> f64 {
+    10.0
+}
+fn default_limits_compression_max_release_ms() -> f64 {
+    2000.0
+}
+fn default_limits_compression_max_knee_db() -> f64 {
+    12.0
+}
+fn default_limits_compression_max_makeup_db() -> f64 {
+    12.0
+}
+fn default_limits_limiter_min_ceiling_db() -> f64 {
+    -6.0
+}
+fn default_limits_limiter_max_ceiling_db() -> f64 {
+    0.0
+}
+fn default_limits_limiter_min_release_ms() -> f64 {
+    1.0
+}
+fn default_limits_limiter_max_release_ms() -> f64 {
+    1000.0
+}
+fn default_limits_max_stereo_width() -> f64 {
+    2.0
+}
+fn default_limits_min_target_lufs() -> f64 {
+    -30.0
+}
+fn default_limits_max_target_lufs() -> f64 {
+    -5.0
+}
+fn default_local_gguf_max_tokens() -> usize {
+    512
+}
+fn default_local_gguf_chat_template() -> String {
+    "<|im_start|>system\n{system}<|im_end|>\n<|im_start|>user\n{prompt}<|im_end|>\n<|im_start|>assistant\n".into()
+}
+fn default_no_proxy() -> String {
+    "localhost,127.0.0.1,::1".into()
+}
+fn default_azure_api_version() -> String {
+    "2024-06-01".into()
+}
 fn default_anthropic_model() -> String {
     "claude-sonnet-4-20250514".into()
 }
 fn default_python_path() -> String {
     "python3".into()
 }
+fn default_model_revision() -> String {
+    "main".into()
+}
+fn default_onnx_sample_rate() -> u32 {
+    24_000
+}
+fn default_bridge_timeout_secs() -> u64 {
+    30 * 60
+}
 fn default_ml_model() -> String {
     "deepafx-st".into()
 }
+fn default_stems_model() -> String {
+    "htdemucs".into()
+}
+fn default_denoise_reduction_db() -> f64 {
+    12.0
+}
+fn default_hum_harmonics() -> u32 {
+    4
+}
 fn default_lmstudio_endpoint() -> String {
     "http://localhost:1234/v1".into()
 }
+fn default_rules_sub_bass_min_db() -> f64 {
+    -12.0
+}
+fn default_rules_bass_max_db() -> f64 {
+    -3.0
+}
+fn default_rules_mud_max_db() -> f64 {
+    -6.0
+}
+fn default_rules_harshness_max_db() -> f64 {
+    -9.0
+}
+fn default_rules_air_min_db() -> f64 {
+    -20.0
+}
+fn default_rules_eq_amount() -> f64 {
+    0.5
+}
+fn default_rules_max_eq_db() -> f64 {
+    3.0
+}
+fn default_rules_high_pass_hz() -> f64 {
+    25.0
+}
+fn default_rules_dynamic_range_high_db() -> f64 {
+    10.0
+}
+fn default_rules_dynamic_range_low_db() -> f64 {
+    6.0
+}
+fn default_rules_max_stereo_width() -> f64 {
+    1.2
+}
+fn default_rules_ceiling_db() -> f64 {
+    -1.0
+}
 
 // --- Default trait impls ---
 
-impl Default for Config {
+impl Default for GeneralConfig {
     fn default() -> Self {
         Self {
-            general: GeneralConfig::default(),
-            ai: AiConfig::default(),
-            backends: BackendsConfig::default(),
+            default_backend: default_backend(),
+            default_bit_depth: default_bit_depth(),
+            default_format: default_format(),
+            target_lufs: default_target_lufs(),
+            batch_concurrency: default_batch_concurrency(),
+            ffmpeg_fallback: false,
+            surround_mode: SurroundMode::default(),
+            lufs_tolerance: default_lufs_tolerance(),
+            max_loudness_passes: default_max_loudness_passes(),
+            balance_threshold_db: default_balance_threshold_db(),
+            match_eq_strength: default_match_eq_strength(),
+            offline: false,
+            overwrite: OverwritePolicy::default(),
         }
     }
 }
 
-impl Default for GeneralConfig {
+impl Default for EncodingConfig {
     fn default() -> Self {
         Self {
-            default_backend: default_backend(),
-            default_bit_depth: default_bit_depth(),
-            default_format: default_format(),
-            target_lufs: default_target_lufs(),
+            mp3_bitrate_kbps: default_mp3_bitrate(),
+            aac_bitrate_kbps: default_aac_bitrate(),
+            opus_bitrate_kbps: default_opus_bitrate(),
+            sample_rate: None,
+            dither: Dither::default(),
         }
     }
 }
@@ -170,11 +875,42 @@ impl Default for AiConfig {
     fn default() -> Self {
         Self {
             default_provider: default_ai_provider(),
+            passes: default_ai_passes(),
+            candidates: default_ai_candidates(),
+            candidate_excerpt_secs: default_candidate_excerpt_secs(),
+            stream: default_ai_stream(),
             ollama: OllamaConfig::default(),
             lmstudio: LmStudioConfig::default(),
             keyhanstudio: KeyhanStudioConfig::default(),
             openai: OpenAiConfig::default(),
+            azure_openai: AzureOpenAiConfig::default(),
+            openai_compatible: OpenAiCompatibleConfig::default(),
             anthropic: AnthropicConfig::default(),
+            local_gguf: LocalGgufConfig::default(),
+            rules: RulesConfig::default(),
+            limits: ParamLimits::default(),
+            prompts: PromptsConfig::default(),
+            pricing: BTreeMap::new(),
+            network: NetworkConfig::default(),
+        }
+    }
+}
+
+impl Default for RulesConfig {
+    fn default() -> Self {
+        Self {
+            sub_bass_min_db: default_rules_sub_bass_min_db(),
+            bass_max_db: default_rules_bass_max_db(),
+            mud_max_db: default_rules_mud_max_db(),
+            harshness_max_db: default_rules_harshness_max_db(),
+            air_min_db: default_rules_air_min_db(),
+            eq_amount: default_rules_eq_amount(),
+            max_eq_db: default_rules_max_eq_db(),
+            high_pass_hz: default_rules_high_pass_hz(),
+            dynamic_range_high_db: default_rules_dynamic_range_high_db(),
+            dynamic_range_low_db: default_rules_dynamic_range_low_db(),
+            max_stereo_width: default_rules_max_stereo_width(),
+            ceiling_db: default_rules_ceiling_db(),
         }
     }
 }
@@ -184,6 +920,8 @@ impl Default for LmStudioConfig {
         Self {
             endpoint: default_lmstudio_endpoint(),
             model: String::new(),
+            timeout_secs: default_local_timeout_secs(),
+            max_tokens: default_max_tokens(),
         }
     }
 }
@@ -193,6 +931,135 @@ impl Default for OllamaConfig {
         Self {
             endpoint: default_ollama_endpoint(),
             model: default_ollama_model(),
+            timeout_secs: default_local_timeout_secs(),
+            max_tokens: default_max_tokens(),
+        }
+    }
+}
+
+impl Default for OpenAiConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            model: default_openai_model(),
+            timeout_secs: default_cloud_timeout_secs(),
+            max_tokens: default_max_tokens(),
+        }
+    }
+}
+
+impl Default for ParamLimits {
+    fn default() -> Self {
+        Self {
+            eq_max_gain_db: default_limits_eq_max_gain_db(),
+            eq_min_frequency_hz: default_limits_eq_min_frequency_hz(),
+            eq_max_frequency_hz: default_limits_eq_max_frequency_hz(),
+            eq_min_q: default_limits_eq_min_q(),
+            eq_max_q: default_limits_eq_max_q(),
+            max_eq_bands: default_limits_max_eq_bands(),
+            compression_min_threshold_db: default_limits_compression_min_threshold_db(),
+            compression_max_ratio: default_limits_compression_max_ratio(),
+            compression_min_attack_ms: default_limits_compression_min_attack_ms(),
+            compression_max_attack_ms: default_limits_compression_max_attack_ms(),
+            compression_min_release_ms: default_limits_compression_min_release_ms(),
+            compression_max_release_ms: default_limits_compression_max_release_ms(),
+            compression_max_knee_db: default_limits_compression_max_knee_db(),
+            compression_max_makeup_db: default_limits_compression_max_makeup_db(),
+            limiter_min_ceiling_db: default_limits_limiter_min_ceiling_db(),
+            limiter_max_ceiling_db: default_limits_limiter_max_ceiling_db(),
+            limiter_min_release_ms: default_limits_limiter_min_release_ms(),
+            limiter_max_release_ms: default_limits_limiter_max_release_ms(),
+            max_stereo_width: default_limits_max_stereo_width(),
+            min_target_lufs: default_limits_min_target_lufs(),
+            max_target_lufs: default_limits_max_target_lufs(),
+        }
+    }
+}
+
+impl ParamLimits {
+    /// Check that every range is ordered, so clamping to it cannot panic.
+    pub fn validate(&self) -> Result<(), MasteringError> {
+        // Some limits are paired with a fixed bound when clamping
+        let ranges = [
+            ("eq_max_gain_db", 0.0, self.eq_max_gain_db),
+            (
+                "eq_min_frequency_hz",
+                self.eq_min_frequency_hz,
+                self.eq_max_frequency_hz,
+            ),
+            ("eq_min_q", self.eq_min_q, self.eq_max_q),
+            (
+                "compression_min_threshold_db",
+                self.compression_min_threshold_db,
+                0.0,
+            ),
+            ("compression_max_ratio", 1.0, self.compression_max_ratio),
+            (
+                "compression_min_attack_ms",
+                self.compression_min_attack_ms,
+                self.compression_max_attack_ms,
+            ),
+            (
+                "compression_min_release_ms",
+                self.compression_min_release_ms,
+                self.compression_max_release_ms,
+            ),
+            ("compression_max_knee_db", 0.0, self.compression_max_knee_db),
+            (
+                "compression_max_makeup_db",
+                0.0,
+                self.compression_max_makeup_db,
+            ),
+            (
+                "limiter_min_ceiling_db",
+                self.limiter_min_ceiling_db,
+                self.limiter_max_ceiling_db,
+            ),
+            (
+                "limiter_min_release_ms",
+                self.limiter_min_release_ms,
+                self.limiter_max_release_ms,
+            ),
+            ("max_stereo_width", 0.0, self.max_stereo_width),
+            (
+                "min_target_lufs",
+                self.min_target_lufs,
+                self.max_target_lufs,
+            ),
+        ];
+        for (key, min, max) in ranges {
+            // NaN compares as neither, and no range can hold it
+            if min.is_nan() || max.is_nan() || min > max {
+                return Err(MasteringError::InvalidConfig {
+                    message: format!("[ai.limits] {key} gives an empty range ({min} to {max})"),
+                    config_key: Some(format!("ai.limits.{key}")),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for LocalGgufConfig {
+    fn default() -> Self {
+        Self {
+            model_path: String::new(),
+            tokenizer_path: String::new(),
+            max_tokens: default_local_gguf_max_tokens(),
+            chat_template: default_local_gguf_chat_template(),
+        }
+    }
+}
+
+impl Default for AzureOpenAiConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            deployment: String::new(),
+            api_version: default_azure_api_version(),
+            api_key: String::new(),
+            timeout_secs: default_cloud_timeout_secs(),
+            max_tokens: default_max_tokens(),
         }
     }
 }
@@ -202,15 +1069,30 @@ impl Default for KeyhanStudioConfig {
         Self {
             endpoint: String::new(),
             api_key: String::new(),
+            timeout_secs: default_cloud_timeout_secs(),
+            max_tokens: default_max_tokens(),
         }
     }
 }
 
-impl Default for OpenAiConfig {
+impl Default for OpenAiCompatibleConfig {
     fn default() -> Self {
         Self {
+            base_url: String::new(),
+            model: String::new(),
             api_key: String::new(),
-            model: default_openai_model(),
+            timeout_secs: default_cloud_timeout_secs(),
+            max_tokens: default_max_tokens(),
+        }
+    }
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: String::new(),
+            no_proxy: default_no_proxy(),
+            ca_cert_file: String::new(),
         }
     }
 }
@@ -220,6 +1102,8 @@ impl Default for AnthropicConfig {
         Self {
             api_key: String::new(),
             model: default_anthropic_model(),
+            timeout_secs: default_cloud_timeout_secs(),
+            max_tokens: default_max_tokens(),
         }
     }
 }
@@ -229,6 +1113,28 @@ impl Default for BackendsConfig {
         Self {
             matchering: MatcheringConfig::default(),
             local_ml: LocalMlConfig::default(),
+            dsp_timeout_secs: default_bridge_timeout_secs(),
+        }
+    }
+}
+
+impl Default for StemsConfig {
+    fn default() -> Self {
+        Self {
+            python_path: default_python_path(),
+            model: default_stems_model(),
+            device: Device::default(),
+            timeout_secs: default_bridge_timeout_secs(),
+        }
+    }
+}
+
+impl Default for RestorationConfig {
+    fn default() -> Self {
+        Self {
+            denoise_reduction_db: default_denoise_reduction_db(),
+            hum_frequency: 0.0,
+            hum_harmonics: default_hum_harmonics(),
         }
     }
 }
@@ -236,7 +1142,9 @@ impl Default for BackendsConfig {
 impl Default for MatcheringConfig {
     fn default() -> Self {
         Self {
+            engine: MatchingEngine::default(),
             python_path: default_python_path(),
+            timeout_secs: default_bridge_timeout_secs(),
         }
     }
 }
@@ -246,6 +1154,12 @@ impl Default for LocalMlConfig {
         Self {
             python_path: default_python_path(),
             default_model: default_ml_model(),
+            onnx_model: String::new(),
+            onnx_runtime: String::new(),
+            onnx_sample_rate: default_onnx_sample_rate(),
+            device: Device::default(),
+            timeout_secs: default_bridge_timeout_secs(),
+            models: BTreeMap::new(),
         }
     }
 }
@@ -278,6 +1192,11 @@ impl Config {
             std::fs::read_to_string(path).with_context(|| format!("Reading config: {}", path.display()))?;
         let config: Config =
             toml::from_str(&contents).with_context(|| format!("Parsing config: {}", path.display()))?;
+        config
+            .ai
+            .limits
+            .validate()
+            .with_context(|| format!("Invalid config: {}", path.display()))?;
         Ok(config)
     }
 
@@ -0,0 +1,107 @@
+//! Target tonal-balance curves.
+//!
+//! Curves are files in `<config dir>/curves`, named after the sound they
+//! describe, e.g. `modern-pop.csv` or `k14.json`. Each holds levels by
+//! frequency (see [`SpectralCurve`]); a track can be compared against one,
+//! or mastered toward it with match EQ.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::analysis::match_eq::{is_curve_file, SpectralCurve};
+use crate::config::Config;
+
+/// Directory of target curves.
+#[derive(Debug, Clone)]
+pub struct CurveStore {
+    dir: PathBuf,
+}
+
+impl CurveStore {
+    /// Create a store that keeps curves in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Default location (`<config dir>/curves`).
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("curves"))
+    }
+
+    /// Store in the default location.
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(Self::default_dir()?))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Names of the curve files, without their extension, sorted.
+    pub fn list(&self) -> Result<Vec<String>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if is_curve_file(&path) {
+                if let Some(stem) = path.file_stem() {
+                    names.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Path of the curve called `name`, preferring JSON over CSV.
+    pub fn path(&self, name: &str) -> Result<PathBuf> {
+        anyhow::ensure!(
+            !name.is_empty() && !name.contains(['/', '\\']) && !name.starts_with('.'),
+            "Invalid curve name '{name}'"
+        );
+        ["json", "csv"]
+            .iter()
+            .map(|ext| self.dir.join(format!("{name}.{ext}")))
+            .find(|path| path.is_file())
+            .with_context(|| {
+                format!(
+                    "No curve named '{name}' in {} (add {name}.csv or {name}.json)",
+                    self.dir.display()
+                )
+            })
+    }
+
+    pub fn load(&self, name: &str) -> Result<SpectralCurve> {
+        SpectralCurve::load(&self.path(name)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CurveStore::new(dir.path());
+        assert!(store.list().unwrap().is_empty());
+
+        std::fs::write(
+            dir.path().join("modern-pop.csv"),
+            "hz,db\n60,4\n1000,0\n12000,-2\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a curve").unwrap();
+        assert_eq!(store.list().unwrap(), ["modern-pop"]);
+
+        let curve = store.load("modern-pop").unwrap();
+        assert_eq!(curve.frequencies.len(), 3);
+        // Loaded curves are relative to their mean
+        assert!(curve.levels_db.iter().sum::<f64>().abs() < 1e-9);
+        assert!(store.load("k14").is_err());
+        assert!(store.path("../modern-pop").is_err());
+    }
+}
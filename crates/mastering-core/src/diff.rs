@@ -0,0 +1,214 @@
+//! Null test between two files.
+//!
+//! The second file is time-aligned to the first by cross-correlation, then
+//! the first is subtracted from it (inverted and summed). What is left is
+//! exactly what changed between them: silence for identical audio, the
+//! added EQ, compression and limiting for an input and its master.
+
+use anyhow::{Context, Result};
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::analysis::decode::{decode_audio, DecodedAudio};
+use crate::encode::{self, EncodeOptions};
+use crate::types::SampleFormat;
+
+/// Audio from the start of each file used to find their offset, in seconds.
+const ALIGN_WINDOW_SECS: f64 = 10.0;
+
+/// Level reported for silence, matching the rest of the analysis.
+const SILENCE_DB: f64 = -100.0;
+
+/// Result of a null test.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NullTest {
+    /// Frames the second file lags behind the first; negative when it leads.
+    pub offset_frames: i64,
+    pub offset_ms: f64,
+    /// Length of the overlap that was compared, in seconds.
+    pub compared_secs: f64,
+    /// RMS level of the difference signal in dBFS.
+    pub residual_rms_db: f64,
+    /// Peak level of the difference signal in dBFS.
+    pub residual_peak_db: f64,
+    /// Residual RMS relative to the first file's RMS in dB; the more
+    /// negative, the more alike the files are.
+    pub null_depth_db: f64,
+    /// Where the difference signal was written, if it was.
+    pub difference_path: Option<PathBuf>,
+}
+
+/// Null-test `a` against `b`, searching offsets up to `max_offset_ms` and
+/// writing the difference (`b - a`) to `difference_path` when given.
+pub fn null_test(
+    a: &Path,
+    b: &Path,
+    max_offset_ms: f64,
+    difference_path: Option<&Path>,
+) -> Result<NullTest> {
+    let audio_a = decode_audio(a).with_context(|| format!("Decoding {}", a.display()))?;
+    let audio_b = decode_audio(b).with_context(|| format!("Decoding {}", b.display()))?;
+    anyhow::ensure!(
+        audio_a.sample_rate == audio_b.sample_rate,
+        "Sample rates differ ({} Hz vs {} Hz); resample one file first",
+        audio_a.sample_rate,
+        audio_b.sample_rate
+    );
+    anyhow::ensure!(
+        audio_a.channels == audio_b.channels,
+        "Channel counts differ ({} vs {})",
+        audio_a.channels,
+        audio_b.channels
+    );
+
+    let rate = audio_a.sample_rate as f64;
+    let window = (ALIGN_WINDOW_SECS * rate) as usize;
+    let max_lag = (max_offset_ms.max(0.0) / 1000.0 * rate) as usize;
+    let mono_a = audio_a.mono_mixdown();
+    let mono_b = audio_b.mono_mixdown();
+    let offset = find_offset(
+        &mono_a[..mono_a.len().min(window)],
+        &mono_b[..mono_b.len().min(window)],
+        max_lag,
+    );
+
+    let difference = difference(&audio_a, &audio_b, offset);
+    let (rms_a, _) = levels(&audio_a.samples);
+    let (rms, peak) = levels(&difference.samples);
+    let compared_secs = difference.duration_secs();
+
+    if let Some(path) = difference_path {
+        let opts = EncodeOptions {
+            bit_depth: 32,
+            sample_format: SampleFormat::Float,
+            ..Default::default()
+        };
+        encode::write_wav(
+            path,
+            &difference.samples,
+            difference.channels,
+            difference.sample_rate,
+            &opts,
+        )
+        .with_context(|| format!("Writing {}", path.display()))?;
+    }
+
+    Ok(NullTest {
+        offset_frames: offset,
+        offset_ms: offset as f64 / rate * 1000.0,
+        compared_secs,
+        residual_rms_db: rms,
+        residual_peak_db: peak,
+        null_depth_db: rms - rms_a,
+        difference_path: difference_path.map(Path::to_path_buf),
+    })
+}
+
+/// Lag of `b` behind `a` in frames, within `max_lag` either way, at which
+/// the two correlate best.
+pub fn find_offset(a: &[f32], b: &[f32], max_lag: usize) -> i64 {
+    if a.is_empty() || b.is_empty() || max_lag == 0 {
+        return 0;
+    }
+    // Circular cross-correlation through the FFT, padded so no lag wraps
+    let size = (a.len() + b.len()).next_power_of_two();
+    let mut planner = FftPlanner::<f64>::new();
+    let forward = planner.plan_fft_forward(size);
+    let spectrum = |x: &[f32]| {
+        let mut buf: Vec<Complex<f64>> = x.iter().map(|&s| Complex::new(s as f64, 0.0)).collect();
+        buf.resize(size, Complex::new(0.0, 0.0));
+        forward.process(&mut buf);
+        buf
+    };
+    let (spec_a, spec_b) = (spectrum(a), spectrum(b));
+    let mut corr: Vec<Complex<f64>> = spec_a
+        .iter()
+        .zip(&spec_b)
+        .map(|(x, y)| x.conj() * y)
+        .collect();
+    planner.plan_fft_inverse(size).process(&mut corr);
+
+    let at = |lag: i64| {
+        let index = if lag >= 0 {
+            lag as usize
+        } else {
+            size - (-lag) as usize
+        };
+        corr[index].re
+    };
+    let max_lag = max_lag.min(size / 2 - 1) as i64;
+    (-max_lag..=max_lag)
+        .max_by(|&x, &y| at(x).total_cmp(&at(y)).then(y.abs().cmp(&x.abs())))
+        .unwrap_or(0)
+}
+
+/// `b` minus `a` over the frames they share once `b` is moved back by `offset`.
+fn difference(a: &DecodedAudio, b: &DecodedAudio, offset: i64) -> DecodedAudio {
+    let channels = a.channels as usize;
+    let frames_a = a.samples.len() / channels;
+    let frames_b = b.samples.len() / channels;
+    let start = (-offset).max(0) as usize;
+    let end = (frames_b as i64 - offset).clamp(0, frames_a as i64) as usize;
+
+    let mut samples = Vec::with_capacity(end.saturating_sub(start) * channels);
+    for frame in start..end {
+        let frame_b = (frame as i64 + offset) as usize;
+        for ch in 0..channels {
+            samples.push(b.samples[frame_b * channels + ch] - a.samples[frame * channels + ch]);
+        }
+    }
+    DecodedAudio::new(samples, a.sample_rate, a.channels)
+}
+
+/// RMS and peak of `samples` in dBFS.
+fn levels(samples: &[f32]) -> (f64, f64) {
+    let to_db = |x: f64| {
+        if x < 1e-10 {
+            SILENCE_DB
+        } else {
+            20.0 * x.log10()
+        }
+    };
+    if samples.is_empty() {
+        return (SILENCE_DB, SILENCE_DB);
+    }
+    let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    (
+        to_db((sum_sq / samples.len() as f64).sqrt()),
+        to_db(peak as f64),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noise(len: usize) -> Vec<f32> {
+        // Deterministic pseudo-random signal, so correlation has a single peak
+        let mut state = 12345u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as f32 / 32768.0 - 1.0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_delayed_copy_nulls() {
+        let a = noise(4000);
+        let mut b = vec![0.0f32; 37];
+        b.extend_from_slice(&a);
+
+        assert_eq!(find_offset(&a, &b, 100), 37);
+        assert_eq!(find_offset(&b, &a, 100), -37);
+
+        let (audio_a, audio_b) = (DecodedAudio::new(a, 8000, 1), DecodedAudio::new(b, 8000, 1));
+        let diff = difference(&audio_a, &audio_b, 37);
+        assert_eq!(diff.samples.len(), 4000);
+        assert_eq!(levels(&diff.samples).0, SILENCE_DB);
+    }
+}
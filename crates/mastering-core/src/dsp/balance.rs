@@ -0,0 +1,64 @@
+//! Left/right balance correction.
+//!
+//! A stereo input whose channels differ in level is evened out by turning
+//! the louder channel down, so the correction can never create overs. The
+//! loudness normalization later in the chain makes up the lost level.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::analysis::decode::decode_audio;
+use crate::encode::{self, EncodeOptions};
+use crate::types::SampleFormat;
+
+/// Turn the louder channel of interleaved stereo `samples` down by
+/// `balance_db`, the left-over-right energy difference. Other channel
+/// counts are left unchanged.
+pub fn rebalance(samples: &mut [f32], channels: usize, balance_db: f64) {
+    if channels != 2 || balance_db == 0.0 {
+        return;
+    }
+    let gain = 10f64.powf(-balance_db.abs() / 20.0) as f32;
+    let louder = usize::from(balance_db < 0.0);
+    for frame in samples.chunks_exact_mut(2) {
+        frame[louder] *= gain;
+    }
+}
+
+/// Rebalance `input` by `balance_db` and write it to `output` as 32-bit
+/// float WAV.
+pub fn rebalance_file(input: &Path, output: &Path, balance_db: f64) -> Result<()> {
+    let mut audio = decode_audio(input)?;
+    rebalance(&mut audio.samples, audio.channels as usize, balance_db);
+    let opts = EncodeOptions {
+        bit_depth: 32,
+        sample_format: SampleFormat::Float,
+        ..Default::default()
+    };
+    encode::write_wav(
+        output,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        &opts,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebalance_turns_down_louder_channel() {
+        let mut samples = vec![0.5, 1.0, -0.5, -1.0];
+        rebalance(&mut samples, 2, -6.0206);
+        for frame in samples.chunks(2) {
+            assert!((frame[0] - frame[1]).abs() < 1e-4, "{frame:?}");
+            assert!((frame[0].abs() - 0.5).abs() < 1e-4);
+        }
+
+        let mut mono = vec![0.5; 4];
+        rebalance(&mut mono, 1, 6.0);
+        assert_eq!(mono, vec![0.5; 4]);
+    }
+}
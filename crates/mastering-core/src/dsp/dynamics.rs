@@ -0,0 +1,433 @@
+//! Compressor, multiband compressor, de-esser and peak limiter.
+//!
+//! All are stereo-linked: gain is computed from the loudest channel of each
+//! frame and applied to every channel, so the image does not shift.
+
+use std::collections::VecDeque;
+
+use super::eq::Biquad;
+use crate::analysis::true_peak;
+use crate::types::{
+    CompressionParams, DeEsserParams, EqBand, EqBandType, EqChannel, LimiterParams, MultibandParams,
+};
+
+fn db_to_gain(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+/// One-pole smoothing coefficient for a time constant in milliseconds.
+fn time_coefficient(ms: f64, sample_rate: u32) -> f64 {
+    let samples = ms.max(0.01) * 0.001 * sample_rate as f64;
+    (-1.0 / samples).exp()
+}
+
+/// Feed-forward compressor with a soft knee.
+pub fn compress(
+    samples: &mut [f32],
+    channels: usize,
+    sample_rate: u32,
+    params: &CompressionParams,
+) {
+    if channels == 0 || params.ratio <= 1.0 {
+        apply_gain_db(samples, params.makeup_gain_db);
+        return;
+    }
+
+    let attack = time_coefficient(params.attack_ms, sample_rate);
+    let release = time_coefficient(params.release_ms, sample_rate);
+    let knee = params.knee_db.max(0.0);
+    let makeup = params.makeup_gain_db;
+    let mut reduction_db = 0.0f64;
+
+    for frame in samples.chunks_exact_mut(channels) {
+        let peak = frame.iter().fold(0.0f32, |m, s| m.max(s.abs())) as f64;
+        let level_db = if peak > 1e-9 {
+            20.0 * peak.log10()
+        } else {
+            -180.0
+        };
+        let target_db = static_reduction_db(level_db, params.threshold_db, params.ratio, knee);
+
+        // Reduction grows with the attack time and recovers with the release time
+        let coef = if target_db > reduction_db {
+            attack
+        } else {
+            release
+        };
+        reduction_db = target_db + coef * (reduction_db - target_db);
+
+        let gain = db_to_gain(makeup - reduction_db) as f32;
+        for s in frame.iter_mut() {
+            *s *= gain;
+        }
+    }
+}
+
+/// Knee of each band of the multiband compressor in dB.
+const MULTIBAND_KNEE_DB: f64 = 6.0;
+
+/// Multiband compressor. Each band is split off the remainder of the signal
+/// with a zero-phase low-pass, so the bands sum back to the input exactly
+/// wherever they are left uncompressed.
+pub fn compress_multiband(
+    samples: &mut [f32],
+    channels: usize,
+    sample_rate: u32,
+    params: &MultibandParams,
+) {
+    let bands = params.bands.len().min(params.crossovers.len() + 1);
+    if channels == 0 || bands == 0 {
+        return;
+    }
+    let mut rest = samples.to_vec();
+    samples.fill(0.0);
+    for (i, band) in params.bands[..bands].iter().enumerate() {
+        let mut part = if i + 1 < bands {
+            let lowpass = EqBand {
+                frequency: params.crossovers[i],
+                gain_db: 0.0,
+                q: 0.707,
+                band_type: EqBandType::LowPass,
+                channel: EqChannel::Stereo,
+            };
+            let low: Vec<f32> =
+                zero_phase(&rest, channels, Biquad::from_band(&lowpass, sample_rate))
+                    .into_iter()
+                    .map(|s| s as f32)
+                    .collect();
+            for (r, l) in rest.iter_mut().zip(&low) {
+                *r -= l;
+            }
+            low
+        } else {
+            std::mem::take(&mut rest)
+        };
+
+        let compression = CompressionParams {
+            threshold_db: band.threshold_db,
+            ratio: band.ratio,
+            attack_ms: band.attack_ms,
+            release_ms: band.release_ms,
+            knee_db: MULTIBAND_KNEE_DB,
+            makeup_gain_db: band.makeup_gain_db,
+        };
+        compress(&mut part, channels, sample_rate, &compression);
+        for (s, p) in samples.iter_mut().zip(&part) {
+            *s += p;
+        }
+    }
+}
+
+/// `samples` filtered forwards and then backwards with `filter`, which
+/// squares its response and cancels its phase shift.
+fn zero_phase(samples: &[f32], channels: usize, filter: Biquad) -> Vec<f64> {
+    let mut out: Vec<f64> = samples.iter().map(|&s| s as f64).collect();
+    for ch in 0..channels {
+        let mut f = filter;
+        for s in out.iter_mut().skip(ch).step_by(channels) {
+            *s = f.process(*s);
+        }
+        let mut f = filter;
+        for s in out.iter_mut().skip(ch).step_by(channels).rev() {
+            *s = f.process(*s);
+        }
+    }
+    out
+}
+
+/// Attack of the de-esser's level detector in milliseconds.
+const DE_ESS_ATTACK_MS: f64 = 1.0;
+
+/// Release of the de-esser's level detector in milliseconds.
+const DE_ESS_RELEASE_MS: f64 = 60.0;
+
+/// Split-band de-esser. Only the band above `params.frequency` is turned
+/// down, so sibilants soften without dulling the rest of the mix.
+///
+/// The band is filtered forwards and then backwards, so it is in phase with
+/// the signal and turning it down cuts the highs rather than reshaping them.
+pub fn de_ess(samples: &mut [f32], channels: usize, sample_rate: u32, params: &DeEsserParams) {
+    if !params.enabled || channels == 0 || params.max_reduction_db <= 0.0 {
+        return;
+    }
+    let band = EqBand {
+        frequency: params.frequency,
+        gain_db: 0.0,
+        q: 0.707,
+        band_type: EqBandType::HighPass,
+        channel: EqChannel::Stereo,
+    };
+    let highs = zero_phase(samples, channels, Biquad::from_band(&band, sample_rate));
+
+    let attack = time_coefficient(DE_ESS_ATTACK_MS, sample_rate);
+    let release = time_coefficient(DE_ESS_RELEASE_MS, sample_rate);
+    let mut envelope = 0.0f64;
+    for (frame, highs) in samples
+        .chunks_exact_mut(channels)
+        .zip(highs.chunks_exact(channels))
+    {
+        let peak = highs.iter().fold(0.0f64, |m, h| m.max(h.abs()));
+        let coef = if peak > envelope { attack } else { release };
+        envelope = peak + coef * (envelope - peak);
+
+        let level_db = if envelope > 1e-9 {
+            20.0 * envelope.log10()
+        } else {
+            -180.0
+        };
+        let reduction_db = (level_db - params.threshold_db).clamp(0.0, params.max_reduction_db);
+        if reduction_db > 0.0 {
+            let cut = db_to_gain(-reduction_db) - 1.0;
+            for (s, h) in frame.iter_mut().zip(highs) {
+                *s += (h * cut) as f32;
+            }
+        }
+    }
+}
+
+/// Gain reduction in dB of the compressor's static curve at `level_db`.
+fn static_reduction_db(level_db: f64, threshold_db: f64, ratio: f64, knee_db: f64) -> f64 {
+    let over = level_db - threshold_db;
+    let slope = 1.0 - 1.0 / ratio;
+    if knee_db > 0.0 && over.abs() <= knee_db / 2.0 {
+        slope * (over + knee_db / 2.0).powi(2) / (2.0 * knee_db)
+    } else if over > 0.0 {
+        slope * over
+    } else {
+        0.0
+    }
+}
+
+/// Gain reduction, averaged over the release time, at which the limiter's
+/// release has slowed down fully.
+const LIMITER_SUSTAINED_DB: f64 = 6.0;
+
+/// How much slower the release gets under sustained limiting.
+const LIMITER_SUSTAINED_RELEASE: f64 = 3.0;
+
+/// Brickwall look-ahead limiter that keeps true peaks at or below the
+/// ceiling.
+///
+/// Peaks are detected on the signal oversampled `params.oversampling` times,
+/// which catches the inter-sample peaks a DAC reconstructs. The whole buffer
+/// is available, so look-ahead needs no delay line: the gain at each frame
+/// is the minimum required over the coming window, smoothed so it ramps down
+/// before a peak. It recovers exponentially in dB, and more slowly the more
+/// gain reduction the material has needed recently, so isolated transients
+/// release quickly while dense passages do not pump.
+pub fn limit(samples: &mut [f32], channels: usize, sample_rate: u32, params: &LimiterParams) {
+    if !params.enabled || channels == 0 {
+        return;
+    }
+    let ceiling = db_to_gain(params.ceiling_db);
+    let frames = samples.len() / channels;
+    if frames == 0 {
+        return;
+    }
+    let window = ((params.lookahead_ms * 0.001 * sample_rate as f64) as usize).max(1);
+
+    let required: Vec<f64> =
+        true_peak::frame_peaks(samples, channels, params.oversampling as usize)
+            .into_iter()
+            .map(|peak| if peak > ceiling { ceiling / peak } else { 1.0 })
+            .collect();
+
+    // Minimum of `required` over [i, i + window) via a monotonic deque
+    let mut held = vec![1.0; frames];
+    let mut deque: VecDeque<usize> = VecDeque::new();
+    for i in (0..frames).rev() {
+        while deque.back().is_some_and(|&j| required[j] >= required[i]) {
+            deque.pop_back();
+        }
+        deque.push_back(i);
+        while deque.front().is_some_and(|&j| j >= i + window) {
+            deque.pop_front();
+        }
+        held[i] = required[*deque.front().unwrap()];
+    }
+
+    // A moving average over the previous window never exceeds the held
+    // minimum at a peak, so the ramp-down still meets the ceiling
+    let average = time_coefficient(params.release_ms, sample_rate);
+    let mut sum = 0.0;
+    let mut gain_db = 0.0f64;
+    let mut sustained_db = 0.0f64;
+    for i in 0..frames {
+        sum += held[i];
+        if i >= window {
+            sum -= held[i - window];
+        }
+        let smoothed = sum / (i + 1).min(window) as f64;
+        let smoothed_db = 20.0 * smoothed.min(held[i]).log10();
+        gain_db = if smoothed_db < gain_db {
+            smoothed_db
+        } else {
+            let slowdown = (sustained_db / LIMITER_SUSTAINED_DB).min(1.0);
+            let release_ms =
+                params.release_ms * (1.0 + (LIMITER_SUSTAINED_RELEASE - 1.0) * slowdown);
+            let release = time_coefficient(release_ms, sample_rate);
+            smoothed_db + release * (gain_db - smoothed_db)
+        };
+        sustained_db = -gain_db + average * (sustained_db + gain_db);
+
+        let gain = db_to_gain(gain_db);
+        for s in &mut samples[i * channels..(i + 1) * channels] {
+            *s = (*s as f64 * gain) as f32;
+        }
+    }
+}
+
+/// Scale every sample by `gain_db`.
+pub fn apply_gain_db(samples: &mut [f32], gain_db: f64) {
+    if gain_db.abs() < 1e-6 {
+        return;
+    }
+    let gain = db_to_gain(gain_db) as f32;
+    for s in samples.iter_mut() {
+        *s *= gain;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::BandCompressionParams;
+
+    fn sine(amplitude: f32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let s = amplitude * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48000.0).sin();
+                [s, s]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_static_curve() {
+        assert_eq!(static_reduction_db(-30.0, -20.0, 4.0, 0.0), 0.0);
+        assert!((static_reduction_db(-10.0, -20.0, 4.0, 0.0) - 7.5).abs() < 1e-9);
+        // Inside the knee the curve is continuous with both sides
+        assert!(static_reduction_db(-22.9, -20.0, 4.0, 6.0) < 0.01);
+        assert!((static_reduction_db(-17.0, -20.0, 4.0, 6.0) - 2.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_compressor_reduces_loud_signal() {
+        let mut samples = sine(0.9, 48000);
+        let params = CompressionParams {
+            threshold_db: -20.0,
+            ratio: 4.0,
+            attack_ms: 5.0,
+            release_ms: 50.0,
+            knee_db: 0.0,
+            makeup_gain_db: 0.0,
+        };
+        compress(&mut samples, 2, 48000, &params);
+        let peak = samples[48000..].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(peak < 0.5, "peak {peak}");
+    }
+
+    #[test]
+    fn test_multiband_compresses_only_its_band() {
+        let tone = |freq: f32| -> Vec<f32> {
+            (0..48000)
+                .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / 48000.0).sin())
+                .collect()
+        };
+        let peak = |s: &[f32]| s[12000..36000].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        let band = |threshold_db: f64, ratio: f64| BandCompressionParams {
+            threshold_db,
+            ratio,
+            attack_ms: 5.0,
+            release_ms: 100.0,
+            makeup_gain_db: 0.0,
+        };
+        let mut params = MultibandParams {
+            crossovers: vec![300.0, 3000.0],
+            bands: vec![band(0.0, 1.0), band(0.0, 1.0), band(0.0, 1.0)],
+        };
+
+        // Uncompressed bands sum back to the input
+        let input = tone(1000.0);
+        let mut passed = input.clone();
+        compress_multiband(&mut passed, 1, 48000, &params);
+        assert!(input.iter().zip(&passed).all(|(a, b)| (a - b).abs() < 1e-4));
+
+        params.bands[0] = band(-20.0, 4.0);
+        let mut bass = tone(60.0);
+        compress_multiband(&mut bass, 1, 48000, &params);
+        assert!(peak(&bass) < 0.25, "{}", peak(&bass));
+
+        let mut treble = tone(8000.0);
+        let before = peak(&treble);
+        compress_multiband(&mut treble, 1, 48000, &params);
+        assert!((peak(&treble) - before).abs() < 0.01, "{}", peak(&treble));
+    }
+
+    #[test]
+    fn test_de_esser_cuts_only_loud_highs() {
+        let tone = |freq: f32, amplitude: f32| -> Vec<f32> {
+            (0..48000)
+                .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / 48000.0).sin())
+                .collect()
+        };
+        // Away from the edges, where the backwards pass starts cold
+        let peak = |s: &[f32]| s[12000..36000].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        let params = DeEsserParams {
+            enabled: true,
+            frequency: 6000.0,
+            threshold_db: -20.0,
+            max_reduction_db: 6.0,
+        };
+
+        let mut sibilant = tone(8000.0, 0.5);
+        let before = peak(&sibilant);
+        de_ess(&mut sibilant, 1, 48000, &params);
+        let cut = 20.0 * (peak(&sibilant) / before).log10();
+        assert!((-6.5..-3.0).contains(&cut), "{cut} dB");
+
+        let mut vocal = tone(500.0, 0.5);
+        de_ess(&mut vocal, 1, 48000, &params);
+        assert!((peak(&vocal) - 0.5).abs() < 0.01, "{}", peak(&vocal));
+    }
+
+    #[test]
+    fn test_limiter_holds_ceiling() {
+        let mut samples = sine(0.5, 48000);
+        // Transient spike well above the ceiling
+        samples[20000] = 1.8;
+        samples[20001] = -1.6;
+        let params = LimiterParams::default();
+        limit(&mut samples, 2, 48000, &params);
+
+        let ceiling = db_to_gain(-1.0) as f32;
+        let peak = samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(peak <= ceiling + 1e-6, "peak {peak}");
+        // Material well away from the spike is untouched
+        assert!((samples[2] - sine(0.5, 2)[2]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_limiter_holds_true_peak_ceiling() {
+        // A quarter-rate sine sampled at 45° peaks 3 dB above its samples,
+        // which stay below the ceiling
+        let tone: Vec<f32> = (0..48000)
+            .map(|i| {
+                1.2 * (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin()
+            })
+            .collect();
+
+        let mut oversampled = tone.clone();
+        limit(&mut oversampled, 1, 48000, &LimiterParams::default());
+        let tp = true_peak::true_peak_db(&oversampled[100..], 1);
+        assert!(tp <= -1.0 + 0.1, "true peak {tp}");
+
+        let mut sample_peaks = tone;
+        let params = LimiterParams {
+            oversampling: 1,
+            ..Default::default()
+        };
+        limit(&mut sample_peaks, 1, 48000, &params);
+        assert!(true_peak::true_peak_db(&sample_peaks[100..], 1) > 0.0);
+    }
+}
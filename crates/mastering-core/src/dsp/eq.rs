@@ -0,0 +1,294 @@
+//! Biquad filters for the equalizer stage.
+//!
+//! Coefficients follow Robert Bristow-Johnson's "Audio EQ Cookbook".
+
+use rustfft::num_complex::Complex;
+
+use crate::types::{EqBand, EqBandType, EqChannel};
+
+/// Direct form I biquad section.
+#[derive(Debug, Clone, Copy)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl Biquad {
+    /// Build a section from unnormalized coefficients.
+    pub fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    /// Design the filter described by an EQ band.
+    pub fn from_band(band: &EqBand, sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+        // Keep the centre frequency below Nyquist so the design stays stable
+        let f0 = band.frequency.clamp(10.0, fs * 0.49);
+        let q = band.q.max(0.1);
+        let w0 = 2.0 * std::f64::consts::PI * f0 / fs;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        let a = 10f64.powf(band.gain_db / 40.0);
+
+        match band.band_type {
+            EqBandType::Peak => Self::new(
+                [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+                [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+            ),
+            EqBandType::LowShelf => {
+                let sq = 2.0 * a.sqrt() * alpha;
+                Self::new(
+                    [
+                        a * ((a + 1.0) - (a - 1.0) * cos + sq),
+                        2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                        a * ((a + 1.0) - (a - 1.0) * cos - sq),
+                    ],
+                    [
+                        (a + 1.0) + (a - 1.0) * cos + sq,
+                        -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                        (a + 1.0) + (a - 1.0) * cos - sq,
+                    ],
+                )
+            }
+            EqBandType::HighShelf => {
+                let sq = 2.0 * a.sqrt() * alpha;
+                Self::new(
+                    [
+                        a * ((a + 1.0) + (a - 1.0) * cos + sq),
+                        -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                        a * ((a + 1.0) + (a - 1.0) * cos - sq),
+                    ],
+                    [
+                        (a + 1.0) - (a - 1.0) * cos + sq,
+                        2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                        (a + 1.0) - (a - 1.0) * cos - sq,
+                    ],
+                )
+            }
+            EqBandType::LowPass => Self::new(
+                [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+                [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            ),
+            EqBandType::HighPass => Self::new(
+                [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+                [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            ),
+        }
+    }
+
+    /// Gain of the filter at `frequency`, in dB.
+    pub fn magnitude_db(&self, frequency: f64, sample_rate: u32) -> f64 {
+        let w = 2.0 * std::f64::consts::PI * frequency / sample_rate as f64;
+        let z1 = Complex::from_polar(1.0, -w);
+        let z2 = z1 * z1;
+        let num = self.b0 + z1 * self.b1 + z2 * self.b2;
+        let den = 1.0 + z1 * self.a1 + z2 * self.a2;
+        20.0 * (num.norm() / den.norm()).log10()
+    }
+
+    #[inline]
+    pub fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Apply a chain of EQ bands to interleaved samples in place.
+///
+/// Bands with less than 0.1 dB of gain are skipped, except the pass filters
+/// which have no gain. When a stereo signal has mid or side bands it is
+/// equalized as mid and side, where stereo bands act on both.
+pub fn apply(samples: &mut [f32], channels: usize, sample_rate: u32, bands: &[EqBand]) {
+    let active: Vec<&EqBand> = bands
+        .iter()
+        .filter(|b| {
+            matches!(b.band_type, EqBandType::LowPass | EqBandType::HighPass)
+                || b.gain_db.abs() >= 0.1
+        })
+        .collect();
+    if active.is_empty() || channels == 0 {
+        return;
+    }
+    let on = |skip: EqChannel| -> Vec<&EqBand> {
+        active
+            .iter()
+            .copied()
+            .filter(|b| b.channel != skip)
+            .collect()
+    };
+
+    if channels != 2 || active.iter().all(|b| b.channel == EqChannel::Stereo) {
+        let bands = on(EqChannel::Side);
+        for ch in 0..channels {
+            filter_channel(samples, ch, channels, sample_rate, &bands);
+        }
+        return;
+    }
+
+    for frame in samples.chunks_exact_mut(2) {
+        let (l, r) = (frame[0], frame[1]);
+        frame[0] = (l + r) * 0.5;
+        frame[1] = (l - r) * 0.5;
+    }
+    filter_channel(samples, 0, 2, sample_rate, &on(EqChannel::Side));
+    filter_channel(samples, 1, 2, sample_rate, &on(EqChannel::Mid));
+    for frame in samples.chunks_exact_mut(2) {
+        let (mid, side) = (frame[0], frame[1]);
+        frame[0] = mid + side;
+        frame[1] = mid - side;
+    }
+}
+
+fn filter_channel(
+    samples: &mut [f32],
+    ch: usize,
+    channels: usize,
+    sample_rate: u32,
+    bands: &[&EqBand],
+) {
+    if bands.is_empty() {
+        return;
+    }
+    let mut filters: Vec<Biquad> = bands
+        .iter()
+        .map(|b| Biquad::from_band(b, sample_rate))
+        .collect();
+    for s in samples.iter_mut().skip(ch).step_by(channels) {
+        let mut x = *s as f64;
+        for f in filters.iter_mut() {
+            x = f.process(x);
+        }
+        *s = x as f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone_gain_db(band: &EqBand, freq: f64) -> f64 {
+        let rate = 48000;
+        let mut samples: Vec<f32> = (0..rate)
+            .map(|i| (2.0 * std::f64::consts::PI * freq * i as f64 / rate as f64).sin() as f32)
+            .collect();
+        apply(&mut samples, 1, rate as u32, std::slice::from_ref(band));
+        let peak = samples[rate / 2..]
+            .iter()
+            .fold(0.0f32, |m, s| m.max(s.abs()));
+        20.0 * (peak as f64).log10()
+    }
+
+    #[test]
+    fn test_peak_gain_at_centre() {
+        let band = EqBand {
+            frequency: 1000.0,
+            gain_db: 6.0,
+            q: 1.0,
+            band_type: EqBandType::Peak,
+            channel: EqChannel::Stereo,
+        };
+        assert!((tone_gain_db(&band, 1000.0) - 6.0).abs() < 0.1);
+        assert!(tone_gain_db(&band, 10000.0).abs() < 0.5);
+
+        let filter = Biquad::from_band(&band, 48000);
+        assert!((filter.magnitude_db(1000.0, 48000) - 6.0).abs() < 1e-6);
+        assert!((filter.magnitude_db(10000.0, 48000) - tone_gain_db(&band, 10000.0)).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_mid_side_bands() {
+        let rate = 48000;
+        // Left and right share a centred 100 Hz tone; the 5 kHz tone is in
+        // the left channel only, so it is half mid and half side
+        let samples: Vec<f32> = (0..rate)
+            .flat_map(|i| {
+                let t = i as f64 / rate as f64;
+                let centre = 0.3 * (2.0 * std::f64::consts::PI * 100.0 * t).sin();
+                let left = 0.3 * (2.0 * std::f64::consts::PI * 5000.0 * t).sin();
+                [(centre + left) as f32, centre as f32]
+            })
+            .collect();
+        let band = |frequency: f64, channel: EqChannel| EqBand {
+            frequency,
+            gain_db: -12.0,
+            q: 1.0,
+            band_type: EqBandType::Peak,
+            channel,
+        };
+        let peak = |s: &[f32], ch: usize| {
+            s[rate..]
+                .iter()
+                .skip(ch)
+                .step_by(2)
+                .fold(0.0f32, |m, s| m.max(s.abs()))
+        };
+
+        // Cutting 100 Hz on the sides leaves the centred tone alone
+        let mut side_cut = samples.clone();
+        apply(
+            &mut side_cut,
+            2,
+            rate as u32,
+            &[band(100.0, EqChannel::Side)],
+        );
+        assert!(
+            (peak(&side_cut, 1) - 0.3).abs() < 0.01,
+            "{}",
+            peak(&side_cut, 1)
+        );
+
+        // Cutting 100 Hz in the mid removes it from both channels
+        let mut mid_cut = samples.clone();
+        apply(&mut mid_cut, 2, rate as u32, &[band(100.0, EqChannel::Mid)]);
+        assert!(peak(&mid_cut, 1) < 0.1, "{}", peak(&mid_cut, 1));
+
+        // Cutting 5 kHz on the sides moves it toward the centre
+        let mut narrowed = samples;
+        apply(
+            &mut narrowed,
+            2,
+            rate as u32,
+            &[band(5000.0, EqChannel::Side)],
+        );
+        let difference = narrowed[rate..]
+            .chunks_exact(2)
+            .fold(0.0f32, |m, f| m.max((f[0] - f[1]).abs()));
+        assert!(difference < 0.1, "{difference}");
+    }
+
+    #[test]
+    fn test_low_shelf() {
+        let band = EqBand {
+            frequency: 200.0,
+            gain_db: -4.0,
+            q: 0.707,
+            band_type: EqBandType::LowShelf,
+            channel: EqChannel::Stereo,
+        };
+        assert!((tone_gain_db(&band, 30.0) + 4.0).abs() < 0.2);
+        assert!(tone_gain_db(&band, 5000.0).abs() < 0.1);
+    }
+}
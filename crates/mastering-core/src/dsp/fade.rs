@@ -0,0 +1,106 @@
+//! Fade-in and fade-out.
+//!
+//! Applied to the mastered output, after the limiter, so the fades are the
+//! last change to the level of the track's edges.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::analysis::decode::decode_audio;
+use crate::encode::{self, EncodeOptions};
+use crate::types::{FadeCurve, Fades};
+
+/// Level in dB a logarithmic fade starts from.
+const LOG_FADE_FLOOR_DB: f64 = -60.0;
+
+/// Gain of a fade `progress` (0 to 1) of the way in.
+pub fn fade_gain(curve: FadeCurve, progress: f64) -> f64 {
+    let x = progress.clamp(0.0, 1.0);
+    match curve {
+        FadeCurve::Linear => x,
+        FadeCurve::Log if x == 0.0 => 0.0,
+        FadeCurve::Log => 10f64.powf(LOG_FADE_FLOOR_DB * (1.0 - x) / 20.0),
+        FadeCurve::Cosine => 0.5 - 0.5 * (std::f64::consts::PI * x).cos(),
+    }
+}
+
+/// Fade interleaved `samples` in and out. Fades longer than the audio are
+/// shortened to fit it.
+pub fn apply(samples: &mut [f32], channels: usize, sample_rate: u32, fades: &Fades) {
+    if channels == 0 {
+        return;
+    }
+    let frames = samples.len() / channels;
+    let len = |secs: f64| ((secs.max(0.0) * sample_rate as f64) as usize).min(frames);
+    let (fade_in, fade_out) = (len(fades.in_secs), len(fades.out_secs));
+
+    for (i, frame) in samples.chunks_exact_mut(channels).enumerate() {
+        let mut gain = 1.0;
+        if i < fade_in {
+            gain *= fade_gain(fades.curve, i as f64 / fade_in as f64);
+        }
+        if i + fade_out >= frames {
+            gain *= fade_gain(fades.curve, (frames - 1 - i) as f64 / fade_out as f64);
+        }
+        if gain < 1.0 {
+            frame.iter_mut().for_each(|s| *s *= gain as f32);
+        }
+    }
+}
+
+/// Fade the audio file at `path`, rewriting it as a WAV file in the format
+/// given by `opts`.
+pub fn apply_file_in_place(path: &Path, fades: &Fades, opts: &EncodeOptions) -> Result<()> {
+    let mut audio = decode_audio(path)?;
+    apply(
+        &mut audio.samples,
+        audio.channels as usize,
+        audio.sample_rate,
+        fades,
+    );
+
+    // Write next to the original and swap so a failure leaves it intact
+    let tmp = path.with_extension("fade.wav");
+    encode::write_wav(
+        &tmp,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        opts,
+    )?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Replacing {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fade_curves() {
+        for curve in [FadeCurve::Linear, FadeCurve::Log, FadeCurve::Cosine] {
+            assert_eq!(fade_gain(curve, 0.0), 0.0);
+            assert!((fade_gain(curve, 1.0) - 1.0).abs() < 1e-12);
+        }
+        assert!((fade_gain(FadeCurve::Linear, 0.5) - 0.5).abs() < 1e-12);
+        assert!((fade_gain(FadeCurve::Cosine, 0.5) - 0.5).abs() < 1e-12);
+        // Halfway through a log fade is halfway in dB
+        assert!((fade_gain(FadeCurve::Log, 0.5) - 10f64.powf(-1.5)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_apply_fades_edges_only() {
+        let mut samples = vec![1.0f32; 2 * 100];
+        let fades = Fades {
+            in_secs: 0.01,
+            out_secs: 0.02,
+            curve: FadeCurve::Linear,
+        };
+        apply(&mut samples, 2, 1000, &fades);
+
+        assert_eq!(&samples[..2], &[0.0, 0.0]);
+        assert!((samples[2 * 5] - 0.5).abs() < 1e-6);
+        assert_eq!(samples[2 * 50], 1.0);
+        assert!((samples[2 * 90] - 0.45).abs() < 1e-6);
+        assert_eq!(&samples[2 * 99..], &[0.0, 0.0]);
+    }
+}
@@ -0,0 +1,408 @@
+//! Reference matching in the manner of Matchering.
+//!
+//! Makes a track resemble a reference in loudness, spectral balance and
+//! stereo width. Both are split into mid and side, and only their loudest
+//! pieces are measured, so intros, breaks and fades do not skew the match.
+//! The spectral difference becomes a linear-phase FIR filter per mid/side
+//! channel, applied by FFT convolution.
+
+use anyhow::Result;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::ops::Range;
+
+use super::dynamics;
+use crate::analysis::decode::DecodedAudio;
+use crate::analysis::spectrum::compute_spectrum_mono;
+use crate::resample;
+use crate::types::LimiterParams;
+
+/// FFT size of the compared spectra, and length of the matching filter.
+const FFT_SIZE: usize = 4096;
+
+/// Length of the pieces ranked by loudness, in seconds.
+const PIECE_SECS: f64 = 3.0;
+
+/// Width of the window the matching curve is smoothed over, in octaves.
+const SMOOTHING_OCTAVES: f64 = 1.0 / 3.0;
+
+/// Largest boost or cut of the matching curve, in dB.
+const MAX_MATCH_DB: f64 = 12.0;
+
+/// The matching curve is held flat below this frequency.
+const MIN_MATCH_HZ: f64 = 20.0;
+
+/// Largest side gain applied to match the reference's stereo width.
+const MAX_WIDTH_GAIN: f64 = 2.0;
+
+/// Signals with a lower RMS are treated as silent.
+const SILENCE_RMS: f64 = 1e-5;
+
+/// Peak ceiling of the matched master in dBFS.
+pub const CEILING_DB: f64 = -1.0;
+
+/// Release of the final limiter in milliseconds.
+const LIMITER_RELEASE_MS: f64 = 50.0;
+
+/// What matching changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchReport {
+    /// Gain that brought the track to the reference level, in dB.
+    pub gain_db: f64,
+    /// Largest boost or cut of the mid matching curve, in dB.
+    pub max_eq_db: f64,
+    /// Side gain that matched the reference's stereo width (1.0 = unchanged).
+    pub width: f64,
+}
+
+/// Match `target` to `reference` in place, finishing with a peak limiter at
+/// [`CEILING_DB`] unless `limiter` is off.
+///
+/// `on_stage` is called with a completion percentage and a description as
+/// each stage starts.
+pub fn match_reference(
+    target: &mut DecodedAudio,
+    reference: &DecodedAudio,
+    limiter: bool,
+    mut on_stage: impl FnMut(f32, &str),
+) -> Result<MatchReport> {
+    let channels = target.channels as usize;
+    anyhow::ensure!(
+        (1..=2).contains(&channels),
+        "Reference matching needs a mono or stereo track, not {channels} channels"
+    );
+    let rate = target.sample_rate;
+
+    on_stage(5.0, "Loading and analyzing");
+    let reference_stereo = if reference.channels == 2 {
+        reference.samples.clone()
+    } else {
+        reference.layout.downmix_to_stereo(&reference.samples)
+    };
+    let reference_stereo = resample::resample(&reference_stereo, 2, reference.sample_rate, rate)?;
+    let (ref_mid, ref_side) = to_mid_side(&reference_stereo, 2);
+    let (mut mid, mut side) = to_mid_side(&target.samples, channels);
+
+    let piece = ((PIECE_SECS * rate as f64) as usize).max(1);
+    let ref_pieces = loud_pieces(&ref_mid, piece);
+    anyhow::ensure!(!ref_pieces.is_empty(), "The reference track is silent");
+    let pieces = loud_pieces(&mid, piece);
+    anyhow::ensure!(
+        !pieces.is_empty(),
+        "The track is silent, so there is nothing to match"
+    );
+    let ref_mid_loud = gather(&ref_mid, &ref_pieces);
+    let ref_level = rms(&ref_mid_loud);
+
+    on_stage(25.0, "Matching levels");
+    let level_gain = ref_level / rms(&gather(&mid, &pieces));
+    scale(&mut mid, level_gain);
+    scale(&mut side, level_gain);
+
+    on_stage(45.0, "Matching frequencies");
+    let mid_curve = matching_curve(&gather(&mid, &pieces), &ref_mid_loud, rate);
+    mid = apply_curve(&mid, &mid_curve);
+
+    let ref_side_loud = gather(&ref_side, &ref_pieces);
+    let has_side = rms(&gather(&side, &pieces)) > SILENCE_RMS && rms(&ref_side_loud) > SILENCE_RMS;
+    if has_side {
+        // Only the side's tone is matched here; its level sets the width below
+        let before = rms(&gather(&side, &pieces));
+        let side_curve = matching_curve(&gather(&side, &pieces), &ref_side_loud, rate);
+        side = apply_curve(&side, &side_curve);
+        let after = rms(&gather(&side, &pieces)).max(SILENCE_RMS);
+        scale(&mut side, before / after);
+    }
+
+    on_stage(65.0, "Correcting levels");
+    // The filters moved the level, so match it again
+    let correction = ref_level / rms(&gather(&mid, &pieces)).max(SILENCE_RMS);
+    scale(&mut mid, correction);
+    scale(&mut side, correction);
+
+    let mut width = 1.0;
+    if has_side {
+        let ref_ratio = rms(&ref_side_loud) / ref_level;
+        let ratio = rms(&gather(&side, &pieces)) / ref_level;
+        width = (ref_ratio / ratio).clamp(0.0, MAX_WIDTH_GAIN);
+        scale(&mut side, width);
+    }
+
+    on_stage(85.0, "Final processing");
+    target.samples = from_mid_side(&mid, &side, channels);
+    if limiter {
+        let params = LimiterParams {
+            enabled: true,
+            ceiling_db: CEILING_DB,
+            release_ms: LIMITER_RELEASE_MS,
+            ..Default::default()
+        };
+        dynamics::limit(&mut target.samples, channels, rate, &params);
+    } else {
+        // Without a limiter, only keep the result from clipping
+        let peak = target.samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        if peak > 1.0 {
+            target.samples.iter_mut().for_each(|s| *s /= peak);
+        }
+    }
+
+    let max_eq_db = mid_curve
+        .iter()
+        .map(|g| 20.0 * g.log10())
+        .fold(0.0f64, |m, db| if db.abs() > m.abs() { db } else { m });
+    Ok(MatchReport {
+        gain_db: 20.0 * (level_gain * correction).log10(),
+        max_eq_db,
+        width,
+    })
+}
+
+/// Split interleaved mono or stereo samples into mid and side.
+fn to_mid_side(samples: &[f32], channels: usize) -> (Vec<f32>, Vec<f32>) {
+    if channels == 1 {
+        return (samples.to_vec(), vec![0.0; samples.len()]);
+    }
+    samples
+        .chunks_exact(2)
+        .map(|frame| ((frame[0] + frame[1]) * 0.5, (frame[0] - frame[1]) * 0.5))
+        .unzip()
+}
+
+fn from_mid_side(mid: &[f32], side: &[f32], channels: usize) -> Vec<f32> {
+    if channels == 1 {
+        return mid.to_vec();
+    }
+    mid.iter()
+        .zip(side)
+        .flat_map(|(&m, &s)| [m + s, m - s])
+        .collect()
+}
+
+/// Pieces of `signal` at least as loud as the whole of it.
+fn loud_pieces(signal: &[f32], piece: usize) -> Vec<Range<usize>> {
+    let overall = rms(signal);
+    if overall <= SILENCE_RMS {
+        return Vec::new();
+    }
+    (0..signal.len())
+        .step_by(piece)
+        .map(|start| start..(start + piece).min(signal.len()))
+        .filter(|range| rms(&signal[range.clone()]) >= overall)
+        .collect()
+}
+
+fn gather(signal: &[f32], pieces: &[Range<usize>]) -> Vec<f32> {
+    pieces
+        .iter()
+        .flat_map(|range| signal[range.clone()].iter().copied())
+        .collect()
+}
+
+fn rms(signal: &[f32]) -> f64 {
+    if signal.is_empty() {
+        return 0.0;
+    }
+    (signal.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / signal.len() as f64).sqrt()
+}
+
+fn scale(signal: &mut [f32], gain: f64) {
+    let gain = gain as f32;
+    signal.iter_mut().for_each(|s| *s *= gain);
+}
+
+/// Magnitude gain per FFT bin that turns the spectrum of `target` into that
+/// of `reference`, smoothed and limited to ±[`MAX_MATCH_DB`].
+fn matching_curve(target: &[f32], reference: &[f32], sample_rate: u32) -> Vec<f64> {
+    let target = compute_spectrum_mono(target, sample_rate, FFT_SIZE);
+    let reference = compute_spectrum_mono(reference, sample_rate, FFT_SIZE);
+
+    // Floor empty bins well below the content so they do not dominate
+    let floor = |power: &[f64]| power.iter().fold(0.0f64, |m, &p| m.max(p)) * 1e-10 + 1e-20;
+    let (t_floor, r_floor) = (floor(&target.power), floor(&reference.power));
+    let diff_db: Vec<f64> = target
+        .power
+        .iter()
+        .zip(&reference.power)
+        .map(|(&t, &r)| 10.0 * ((r + r_floor) / (t + t_floor)).log10())
+        .collect();
+
+    // Fractional-octave smoothing over a prefix sum
+    let mut prefix = vec![0.0; diff_db.len() + 1];
+    for (i, db) in diff_db.iter().enumerate() {
+        prefix[i + 1] = prefix[i] + db;
+    }
+    let half_width = 2f64.powf(SMOOTHING_OCTAVES / 2.0);
+    let last = diff_db.len() - 1;
+    let lowest = ((MIN_MATCH_HZ / target.bin_frequency(1)).ceil() as usize).clamp(1, last);
+    let mut curve: Vec<f64> = (0..diff_db.len())
+        .map(|bin| {
+            let centre = bin.max(lowest) as f64;
+            let lo = ((centre / half_width).floor() as usize).clamp(1, last);
+            let hi = ((centre * half_width).ceil() as usize).clamp(lo, last);
+            let mean = (prefix[hi + 1] - prefix[lo]) / (hi + 1 - lo) as f64;
+            mean.clamp(-MAX_MATCH_DB, MAX_MATCH_DB)
+        })
+        .collect();
+    for db in &mut curve {
+        *db = 10f64.powf(*db / 20.0);
+    }
+    curve
+}
+
+/// Filter `signal` with the linear-phase FIR whose magnitude response is
+/// `curve` (one gain per bin of a [`FFT_SIZE`] FFT).
+fn apply_curve(signal: &[f32], curve: &[f64]) -> Vec<f32> {
+    let mut planner = FftPlanner::<f64>::new();
+
+    // Zero-phase response -> impulse centred at FFT_SIZE / 2, windowed
+    let mut response = vec![Complex::new(0.0, 0.0); FFT_SIZE];
+    for (bin, &gain) in curve.iter().enumerate().take(FFT_SIZE / 2 + 1) {
+        response[bin] = Complex::new(gain, 0.0);
+        if bin > 0 && bin < FFT_SIZE / 2 {
+            response[FFT_SIZE - bin] = Complex::new(gain, 0.0);
+        }
+    }
+    planner.plan_fft_inverse(FFT_SIZE).process(&mut response);
+    let taps: Vec<f64> = (0..FFT_SIZE)
+        .map(|i| {
+            let impulse = response[(i + FFT_SIZE / 2) % FFT_SIZE].re / FFT_SIZE as f64;
+            let window =
+                0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / FFT_SIZE as f64).cos();
+            impulse * window
+        })
+        .collect();
+
+    convolve(signal, &taps, &mut planner)
+}
+
+/// Overlap-add FFT convolution, compensating the filter's delay of half its
+/// length so the output lines up with `signal`.
+fn convolve(signal: &[f32], taps: &[f64], planner: &mut FftPlanner<f64>) -> Vec<f32> {
+    let size = (2 * taps.len()).next_power_of_two();
+    let block = size - taps.len() + 1;
+    let forward = planner.plan_fft_forward(size);
+    let inverse = planner.plan_fft_inverse(size);
+
+    let mut kernel = vec![Complex::new(0.0, 0.0); size];
+    for (slot, &tap) in kernel.iter_mut().zip(taps) {
+        *slot = Complex::new(tap, 0.0);
+    }
+    forward.process(&mut kernel);
+
+    let mut out = vec![0.0f64; signal.len() + taps.len()];
+    let mut buffer = vec![Complex::new(0.0, 0.0); size];
+    for start in (0..signal.len()).step_by(block) {
+        let chunk = &signal[start..(start + block).min(signal.len())];
+        buffer.fill(Complex::new(0.0, 0.0));
+        for (slot, &s) in buffer.iter_mut().zip(chunk) {
+            *slot = Complex::new(s as f64, 0.0);
+        }
+        forward.process(&mut buffer);
+        for (x, h) in buffer.iter_mut().zip(&kernel) {
+            *x *= h;
+        }
+        inverse.process(&mut buffer);
+        for (i, c) in buffer.iter().take(chunk.len() + taps.len() - 1).enumerate() {
+            out[start + i] += c.re / size as f64;
+        }
+    }
+
+    let delay = taps.len() / 2;
+    out[delay..delay + signal.len()]
+        .iter()
+        .map(|&s| s as f32)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic white noise.
+    fn noise(len: usize, seed: u64) -> Vec<f32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+            })
+            .collect()
+    }
+
+    /// One-pole low-pass, to give a signal a darker tone.
+    fn darken(signal: &mut [f32], coefficient: f32) {
+        let mut y = 0.0;
+        for s in signal {
+            y += coefficient * (*s - y);
+            *s = y;
+        }
+    }
+
+    fn band_ratio_db(signal: &[f32]) -> f64 {
+        let spectrum = compute_spectrum_mono(signal, 44100, FFT_SIZE);
+        10.0 * (spectrum.band_power(100.0, 500.0) / spectrum.band_power(5000.0, 10000.0)).log10()
+    }
+
+    #[test]
+    fn test_flat_curve_passes_signal_through() {
+        let signal = noise(20000, 7);
+        let curve = vec![1.0; FFT_SIZE / 2 + 1];
+        let filtered = apply_curve(&signal, &curve);
+        assert_eq!(filtered.len(), signal.len());
+        let error = signal
+            .iter()
+            .zip(&filtered)
+            .fold(0.0f32, |m, (a, b)| m.max((a - b).abs()));
+        assert!(error < 1e-3, "error {error}");
+    }
+
+    #[test]
+    fn test_match_reference_matches_level_tone_and_width() {
+        let frames = 44100 * 8;
+        // Wide, bright and quiet track
+        let (left, right) = (noise(frames, 1), noise(frames, 2));
+        let track: Vec<f32> = left
+            .iter()
+            .zip(&right)
+            .flat_map(|(&l, &r)| [0.05 * l, 0.05 * r])
+            .collect();
+        let mut target = DecodedAudio::new(track, 44100, 2);
+
+        // Narrow, dark and loud reference
+        let mut mid = noise(frames, 3);
+        darken(&mut mid, 0.4);
+        let side = noise(frames, 4);
+        let reference: Vec<f32> = mid
+            .iter()
+            .zip(&side)
+            .flat_map(|(&m, &s)| [0.8 * m + 0.02 * s, 0.8 * m - 0.02 * s])
+            .collect();
+        let reference = DecodedAudio::new(reference, 44100, 2);
+
+        let mut stages = Vec::new();
+        let report =
+            match_reference(&mut target, &reference, false, |pct, _| stages.push(pct)).unwrap();
+        assert_eq!(stages.len(), 5);
+        assert!(report.gain_db > 0.0, "{report:?}");
+        assert!(report.width < 1.0, "{report:?}");
+
+        let (out_mid, out_side) = to_mid_side(&target.samples, 2);
+        let (ref_mid, ref_side) = to_mid_side(&reference.samples, 2);
+        let level_error = 20.0 * (rms(&out_mid) / rms(&ref_mid)).log10();
+        assert!(level_error.abs() < 1.0, "level off by {level_error} dB");
+        let tone_error = band_ratio_db(&out_mid) - band_ratio_db(&ref_mid);
+        assert!(tone_error.abs() < 3.0, "tone off by {tone_error} dB");
+        let width_error =
+            20.0 * ((rms(&out_side) / rms(&out_mid)) / (rms(&ref_side) / rms(&ref_mid))).log10();
+        assert!(width_error.abs() < 1.0, "width off by {width_error} dB");
+    }
+
+    #[test]
+    fn test_silent_reference_is_an_error() {
+        let mut target = DecodedAudio::new(noise(44100 * 2, 5), 44100, 1);
+        let reference = DecodedAudio::new(vec![0.0; 44100 * 2], 44100, 1);
+        assert!(match_reference(&mut target, &reference, true, |_, _| {}).is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! Native mastering signal chain.
+//!
+//! Applies [`MasteringParams`] without Python: EQ, de-essing, multiband and
+//! then single-band compression, stereo width, loudness normalization to the target LUFS and a final peak
+//! limiter.
+
+pub mod balance;
+pub mod dynamics;
+pub mod eq;
+pub mod fade;
+pub mod matching;
+pub mod polarity;
+pub mod restoration;
+pub mod sections;
+pub mod silence;
+
+use crate::analysis::decode::DecodedAudio;
+use crate::analysis::loudness::{integrated_loudness, SILENCE_LUFS};
+use crate::types::{MasteringParams, StereoParams};
+
+/// Largest loudness correction applied in one go, in dB.
+const MAX_LOUDNESS_GAIN_DB: f64 = 24.0;
+
+/// Gain/limit passes used to reach the target despite limiter gain reduction.
+const LOUDNESS_PASSES: usize = 3;
+
+/// Loudness error in LU accepted without another pass.
+const LOUDNESS_TOLERANCE_LU: f64 = 0.2;
+
+/// Run the full chain over `audio` in place.
+pub fn master(audio: &mut DecodedAudio, params: &MasteringParams) {
+    let channels = audio.channels as usize;
+    let rate = audio.sample_rate;
+
+    eq::apply(&mut audio.samples, channels, rate, &params.eq);
+    dynamics::de_ess(&mut audio.samples, channels, rate, &params.de_esser);
+    if let Some(multiband) = &params.multiband {
+        dynamics::compress_multiband(&mut audio.samples, channels, rate, multiband);
+    }
+    dynamics::compress(&mut audio.samples, channels, rate, &params.compression);
+    apply_stereo(&mut audio.samples, channels, &params.stereo);
+
+    // The limiter pulls loudness down, so re-apply the remaining gain to the
+    // unlimited signal until the limited result lands on target
+    let unlimited = audio.samples.clone();
+    let mut gain_db = 0.0;
+    for _ in 0..LOUDNESS_PASSES {
+        let lufs = integrated_loudness(audio);
+        if lufs <= SILENCE_LUFS {
+            break;
+        }
+        let error = params.target_lufs - lufs;
+        if gain_db != 0.0 && error.abs() < LOUDNESS_TOLERANCE_LU {
+            break;
+        }
+        gain_db = (gain_db + error).clamp(-MAX_LOUDNESS_GAIN_DB, MAX_LOUDNESS_GAIN_DB);
+
+        audio.samples.copy_from_slice(&unlimited);
+        dynamics::apply_gain_db(&mut audio.samples, gain_db);
+        dynamics::limit(&mut audio.samples, channels, rate, &params.limiter);
+    }
+}
+
+/// Mid/side width and left/right balance for stereo material.
+fn apply_stereo(samples: &mut [f32], channels: usize, stereo: &StereoParams) {
+    if channels != 2 {
+        return;
+    }
+    let width = stereo.width.max(0.0) as f32;
+    let balance = stereo.balance.clamp(-1.0, 1.0) as f32;
+    if (width - 1.0).abs() < 0.01 && balance.abs() < 0.01 {
+        return;
+    }
+    // Balance attenuates the opposite side only, keeping the centre at unity
+    let left_gain = (1.0 - balance).min(1.0);
+    let right_gain = (1.0 + balance).min(1.0);
+    for frame in samples.chunks_exact_mut(2) {
+        let mid = (frame[0] + frame[1]) * 0.5;
+        let side = (frame[0] - frame[1]) * 0.5 * width;
+        frame[0] = (mid + side) * left_gain;
+        frame[1] = (mid - side) * right_gain;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        CompressionParams, DeEsserParams, EqBand, EqBandType, EqChannel, LimiterParams,
+    };
+
+    fn params(target_lufs: f64) -> MasteringParams {
+        MasteringParams {
+            eq: vec![EqBand {
+                frequency: 100.0,
+                gain_db: 2.0,
+                q: 0.707,
+                band_type: EqBandType::LowShelf,
+                channel: EqChannel::Stereo,
+            }],
+            de_esser: DeEsserParams::default(),
+            multiband: None,
+            compression: CompressionParams {
+                threshold_db: -18.0,
+                ratio: 2.0,
+                attack_ms: 10.0,
+                release_ms: 100.0,
+                knee_db: 6.0,
+                makeup_gain_db: 0.0,
+            },
+            limiter: LimiterParams::default(),
+            stereo: StereoParams {
+                width: 1.0,
+                balance: 0.0,
+            },
+            target_lufs,
+        }
+    }
+
+    fn music(secs: usize) -> DecodedAudio {
+        let rate = 44100;
+        let samples = (0..rate * secs)
+            .flat_map(|i| {
+                let t = i as f32 / rate as f32;
+                let env = 0.5 + 0.5 * (2.0 * std::f32::consts::PI * 2.0 * t).sin().abs();
+                let l = env * 0.1 * (2.0 * std::f32::consts::PI * 110.0 * t).sin()
+                    + 0.05 * (2.0 * std::f32::consts::PI * 2500.0 * t).sin();
+                let r = env * 0.1 * (2.0 * std::f32::consts::PI * 165.0 * t).sin();
+                [l, r]
+            })
+            .collect();
+        DecodedAudio::new(samples, rate as u32, 2)
+    }
+
+    #[test]
+    fn test_master_reaches_target_under_ceiling() {
+        let mut audio = music(5);
+        master(&mut audio, &params(-10.0));
+
+        let lufs = integrated_loudness(&audio);
+        assert!((lufs + 10.0).abs() < 0.5, "lufs {lufs}");
+        let peak = audio.samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(peak <= 10f32.powf(-1.0 / 20.0) + 1e-6, "peak {peak}");
+    }
+
+    #[test]
+    fn test_width_zero_collapses_to_mono() {
+        let mut samples = vec![0.5, -0.5, 0.2, 0.4];
+        let stereo = StereoParams {
+            width: 0.0,
+            balance: 0.0,
+        };
+        apply_stereo(&mut samples, 2, &stereo);
+        assert_eq!(samples, vec![0.0, 0.0, 0.3, 0.3]);
+    }
+}
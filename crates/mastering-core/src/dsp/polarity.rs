@@ -0,0 +1,58 @@
+//! Polarity correction.
+//!
+//! A stereo file with one channel polarity-inverted, usually from a miswired
+//! microphone or a flipped channel in the session, cancels when folded down
+//! to mono. Inverting the right channel back restores it.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::analysis::decode::decode_audio;
+use crate::encode::{self, EncodeOptions};
+use crate::types::SampleFormat;
+
+/// Invert the right channel of interleaved stereo `samples`. Other channel
+/// counts are left unchanged.
+pub fn flip_right(samples: &mut [f32], channels: usize) {
+    if channels != 2 {
+        return;
+    }
+    for frame in samples.chunks_exact_mut(2) {
+        frame[1] = -frame[1];
+    }
+}
+
+/// Invert the right channel of `input` and write it to `output` as 32-bit
+/// float WAV.
+pub fn flip_file(input: &Path, output: &Path) -> Result<()> {
+    let mut audio = decode_audio(input)?;
+    flip_right(&mut audio.samples, audio.channels as usize);
+    let opts = EncodeOptions {
+        bit_depth: 32,
+        sample_format: SampleFormat::Float,
+        ..Default::default()
+    };
+    encode::write_wav(
+        output,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        &opts,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flip_right_inverts_only_right() {
+        let mut samples = vec![0.5, -0.5, -0.25, 0.25];
+        flip_right(&mut samples, 2);
+        assert_eq!(samples, vec![0.5, 0.5, -0.25, -0.25]);
+
+        let mut mono = vec![0.5; 4];
+        flip_right(&mut mono, 1);
+        assert_eq!(mono, vec![0.5; 4]);
+    }
+}
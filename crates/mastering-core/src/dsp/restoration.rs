@@ -0,0 +1,485 @@
+//! Restoration of noisy recordings before mastering.
+//!
+//! Aimed at podcast recordings and vinyl transfers. Each stage is optional:
+//! hum at the mains frequency and its harmonics is notched out, clicks and
+//! the finer crackle of worn records are detected as bursts the signal's
+//! recent course cannot predict and interpolated over, and broadband noise
+//! is reduced by spectral gating against a noise profile taken from the
+//! quietest parts of the recording.
+
+use anyhow::Result;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::path::Path;
+
+use super::eq::Biquad;
+use crate::analysis::decode::{decode_audio, DecodedAudio};
+use crate::config::RestorationConfig;
+use crate::encode::{self, EncodeOptions};
+use crate::types::{RestorationReport, RestorationStages, SampleFormat};
+
+/// Mains frequencies hum is looked for at.
+const MAINS_HZ: [f64; 2] = [50.0, 60.0];
+
+/// Power of the mains frequency over its neighbourhood above which hum is
+/// treated as present, in dB.
+const HUM_DETECT_DB: f64 = 6.0;
+
+/// Level of the hum relative to the whole signal below which it is ignored,
+/// in dB.
+const HUM_MIN_LEVEL_DB: f64 = -60.0;
+
+/// Q of the hum notches; narrow enough to leave bass notes alone.
+const HUM_Q: f64 = 30.0;
+
+/// Residual, in median absolute deviations, above which a sample is a click.
+const CLICK_THRESHOLD: f64 = 10.0;
+
+/// Longest click repaired, in milliseconds.
+const CLICK_MAX_MS: f64 = 2.0;
+
+/// Residual, in median absolute deviations, above which a sample is crackle.
+const CRACKLE_THRESHOLD: f64 = 6.0;
+
+/// Longest crackle impulse repaired, in milliseconds.
+const CRACKLE_MAX_MS: f64 = 0.5;
+
+/// Samples over which the residual's deviation is measured.
+const CLICK_BLOCK: usize = 2048;
+
+/// FFT size of the noise gate.
+const DENOISE_FFT: usize = 2048;
+
+/// Hop between noise gate frames; a quarter of the FFT size.
+const DENOISE_HOP: usize = DENOISE_FFT / 4;
+
+/// Share of the quietest frames the noise profile is taken from.
+const NOISE_PROFILE_SHARE: f64 = 0.1;
+
+/// Factor the noise profile is scaled by before it is subtracted.
+const OVER_SUBTRACTION: f64 = 2.0;
+
+/// Weight of the previous frame's gain, which keeps the gate from fluttering.
+const GAIN_SMOOTHING: f64 = 0.5;
+
+/// Run the enabled restoration stages over `input` and write the result to
+/// `output` as 32-bit float WAV.
+pub fn restore_file(
+    input: &Path,
+    output: &Path,
+    stages: &RestorationStages,
+    config: &RestorationConfig,
+) -> Result<RestorationReport> {
+    let mut audio = decode_audio(input)?;
+    let report = restore(&mut audio, stages, config);
+    let opts = EncodeOptions {
+        bit_depth: 32,
+        sample_format: SampleFormat::Float,
+        ..Default::default()
+    };
+    encode::write_wav(
+        output,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        &opts,
+    )?;
+    Ok(report)
+}
+
+/// Run the enabled restoration stages over `audio` in place.
+pub fn restore(
+    audio: &mut DecodedAudio,
+    stages: &RestorationStages,
+    config: &RestorationConfig,
+) -> RestorationReport {
+    let channels = audio.channels as usize;
+    let rate = audio.sample_rate;
+    let mut report = RestorationReport::default();
+    if channels == 0 {
+        return report;
+    }
+
+    if stages.dehum {
+        let hum = if config.hum_frequency > 0.0 {
+            Some(config.hum_frequency)
+        } else {
+            detect_hum(&audio.mono_mixdown(), rate)
+        };
+        if let Some(hz) = hum {
+            remove_hum(&mut audio.samples, channels, rate, hz, config.hum_harmonics);
+        }
+        report.hum_hz = hum;
+    }
+    if stages.declick {
+        report.clicks_repaired += declick(
+            &mut audio.samples,
+            channels,
+            rate,
+            CLICK_THRESHOLD,
+            CLICK_MAX_MS,
+        );
+    }
+    if stages.decrackle {
+        report.clicks_repaired += declick(
+            &mut audio.samples,
+            channels,
+            rate,
+            CRACKLE_THRESHOLD,
+            CRACKLE_MAX_MS,
+        );
+    }
+    if stages.denoise {
+        report.noise_floor_db = denoise(&mut audio.samples, channels, config.denoise_reduction_db);
+    }
+    report
+}
+
+/// The mains frequency whose hum stands out of `mono`, if any.
+pub fn detect_hum(mono: &[f32], sample_rate: u32) -> Option<f64> {
+    let signal_power =
+        mono.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / mono.len().max(1) as f64;
+    MAINS_HZ
+        .iter()
+        .filter_map(|&hz| {
+            let peak = goertzel_power(mono, sample_rate, hz);
+            if 10.0 * (peak / (signal_power + 1e-20)).log10() < HUM_MIN_LEVEL_DB {
+                return None;
+            }
+            let around = (goertzel_power(mono, sample_rate, hz - 7.0)
+                + goertzel_power(mono, sample_rate, hz + 7.0))
+                / 2.0;
+            Some((hz, 10.0 * (peak / (around + 1e-20)).log10()))
+        })
+        .filter(|&(_, prominence_db)| prominence_db > HUM_DETECT_DB)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(hz, _)| hz)
+}
+
+/// Notch out `hum_hz` and its first `harmonics - 1` overtones.
+///
+/// The notches run forwards and then backwards, so their phase shifts cancel
+/// and the music around them is left in place.
+pub fn remove_hum(
+    samples: &mut [f32],
+    channels: usize,
+    sample_rate: u32,
+    hum_hz: f64,
+    harmonics: u32,
+) {
+    let nyquist = sample_rate as f64 / 2.0;
+    let notches: Vec<Biquad> = (1..=harmonics.max(1))
+        .map(|k| hum_hz * k as f64)
+        .take_while(|&f| f < nyquist * 0.9)
+        .map(|f| notch(f, HUM_Q, sample_rate))
+        .collect();
+
+    for ch in 0..channels {
+        let mut filters = notches.clone();
+        for s in samples.iter_mut().skip(ch).step_by(channels) {
+            *s = filters.iter_mut().fold(*s as f64, |x, f| f.process(x)) as f32;
+        }
+        let mut filters = notches.clone();
+        for s in samples.iter_mut().skip(ch).step_by(channels).rev() {
+            *s = filters.iter_mut().fold(*s as f64, |x, f| f.process(x)) as f32;
+        }
+    }
+}
+
+fn notch(frequency: f64, q: f64, sample_rate: u32) -> Biquad {
+    let w0 = 2.0 * std::f64::consts::PI * frequency / sample_rate as f64;
+    let (sin, cos) = w0.sin_cos();
+    let alpha = sin / (2.0 * q);
+    Biquad::new(
+        [1.0, -2.0 * cos, 1.0],
+        [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+    )
+}
+
+/// Power of `signal` at `frequency`; a sinusoid's is its mean square.
+fn goertzel_power(signal: &[f32], sample_rate: u32, frequency: f64) -> f64 {
+    let coeff = 2.0 * (2.0 * std::f64::consts::PI * frequency / sample_rate as f64).cos();
+    let (mut s1, mut s2) = (0.0f64, 0.0f64);
+    for &x in signal {
+        let s = x as f64 + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s;
+    }
+    let n = signal.len().max(1) as f64;
+    2.0 * (s1 * s1 + s2 * s2 - coeff * s1 * s2) / (n * n)
+}
+
+/// Repair bursts of up to `max_ms` whose prediction residual exceeds
+/// `threshold` median absolute deviations of their surroundings, returning
+/// how many were repaired.
+pub fn declick(
+    samples: &mut [f32],
+    channels: usize,
+    sample_rate: u32,
+    threshold: f64,
+    max_ms: f64,
+) -> usize {
+    let max_len = ((max_ms / 1000.0 * sample_rate as f64) as usize).max(1);
+    let mut repaired = 0;
+    for ch in 0..channels {
+        let mut signal: Vec<f32> = samples.iter().skip(ch).step_by(channels).copied().collect();
+        for (start, end) in find_clicks(&signal, threshold, max_len) {
+            interpolate(&mut signal, start, end);
+            repaired += 1;
+        }
+        for (s, &x) in samples.iter_mut().skip(ch).step_by(channels).zip(&signal) {
+            *s = x;
+        }
+    }
+    repaired
+}
+
+/// Sample ranges of the clicks in `signal`, padded by two samples each side.
+fn find_clicks(signal: &[f32], threshold: f64, max_len: usize) -> Vec<(usize, usize)> {
+    if signal.len() < 8 {
+        return Vec::new();
+    }
+    // Residual of a linear prediction from the two previous samples
+    let residual: Vec<f64> = (0..signal.len())
+        .map(|n| {
+            if n < 2 {
+                0.0
+            } else {
+                (signal[n] as f64 - (2.0 * signal[n - 1] as f64 - signal[n - 2] as f64)).abs()
+            }
+        })
+        .collect();
+
+    let mut flagged = vec![false; signal.len()];
+    for (block, chunk) in residual.chunks(CLICK_BLOCK).enumerate() {
+        let mut sorted = chunk.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let mad = sorted[sorted.len() / 2] / 0.6745;
+        let limit = (threshold * mad).max(1e-4);
+        for (i, &r) in chunk.iter().enumerate() {
+            flagged[block * CLICK_BLOCK + i] = r > limit;
+        }
+    }
+
+    let mut clicks: Vec<(usize, usize)> = Vec::new();
+    let mut n = 0;
+    while n < flagged.len() {
+        if !flagged[n] {
+            n += 1;
+            continue;
+        }
+        let start = n.saturating_sub(2).max(2);
+        while n < flagged.len() && flagged[n] {
+            n += 1;
+        }
+        let end = (n + 2).min(signal.len() - 2);
+        match clicks.last_mut() {
+            // Merge bursts separated by a few samples
+            Some(last) if start <= last.1 + 4 => last.1 = end,
+            _ => clicks.push((start, end)),
+        }
+    }
+    clicks.retain(|&(start, end)| end > start && end - start <= max_len);
+    clicks
+}
+
+/// Replace `signal[start..end]` with a cubic that meets the samples on either
+/// side with their slopes.
+fn interpolate(signal: &mut [f32], start: usize, end: usize) {
+    let (p0, p1) = (signal[start - 1] as f64, signal[end] as f64);
+    let m0 = p0 - signal[start - 2] as f64;
+    let m1 = signal[end + 1] as f64 - p1;
+    let span = (end - start + 1) as f64;
+    let (m0, m1) = (m0 * span, m1 * span);
+    for (i, s) in signal[start..end].iter_mut().enumerate() {
+        let t = (i + 1) as f64 / span;
+        let (t2, t3) = (t * t, t * t * t);
+        *s = ((2.0 * t3 - 3.0 * t2 + 1.0) * p0
+            + (t3 - 2.0 * t2 + t) * m0
+            + (-2.0 * t3 + 3.0 * t2) * p1
+            + (t3 - t2) * m1) as f32;
+    }
+}
+
+/// Spectral noise gate reducing noise by up to `reduction_db`, returning the
+/// level of the noise profile in dBFS.
+pub fn denoise(samples: &mut [f32], channels: usize, reduction_db: f64) -> Option<f64> {
+    let floor = 10f64.powf(-reduction_db.abs() / 20.0);
+    let window: Vec<f64> = (0..DENOISE_FFT)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / DENOISE_FFT as f64).cos())
+        .collect();
+    let mut planner = FftPlanner::new();
+    let fft = planner.plan_fft_forward(DENOISE_FFT);
+    let ifft = planner.plan_fft_inverse(DENOISE_FFT);
+
+    let mut noise_levels = Vec::with_capacity(channels);
+    for ch in 0..channels {
+        let signal: Vec<f64> = samples
+            .iter()
+            .skip(ch)
+            .step_by(channels)
+            .map(|&s| s as f64)
+            .collect();
+        if signal.len() < DENOISE_FFT {
+            continue;
+        }
+        let frames = (signal.len() - DENOISE_FFT) / DENOISE_HOP + 1;
+        let spectrum = |frame: usize| -> Vec<Complex<f64>> {
+            let start = frame * DENOISE_HOP;
+            let mut buf: Vec<Complex<f64>> = signal[start..start + DENOISE_FFT]
+                .iter()
+                .zip(&window)
+                .map(|(x, w)| Complex::new(x * w, 0.0))
+                .collect();
+            fft.process(&mut buf);
+            buf
+        };
+
+        // Noise profile: the mean power spectrum of the quietest frames
+        let mut energies: Vec<(usize, f64)> = (0..frames)
+            .map(|f| {
+                let start = f * DENOISE_HOP;
+                (
+                    f,
+                    signal[start..start + DENOISE_FFT]
+                        .iter()
+                        .map(|x| x * x)
+                        .sum(),
+                )
+            })
+            .collect();
+        energies.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let quiet = ((frames as f64 * NOISE_PROFILE_SHARE) as usize).max(1);
+        let mut noise = vec![0.0f64; DENOISE_FFT];
+        for &(f, _) in &energies[..quiet] {
+            for (n, c) in noise.iter_mut().zip(spectrum(f)) {
+                *n += c.norm_sqr() / quiet as f64;
+            }
+        }
+        let window_power: f64 = window.iter().map(|w| w * w).sum();
+        let noise_power = noise.iter().sum::<f64>() / (DENOISE_FFT as f64 * window_power);
+        noise_levels.push(10.0 * (noise_power + 1e-20).log10());
+
+        // Gate each frame and overlap-add; Hann analysis and synthesis at a
+        // quarter-frame hop sum to 1.5
+        let mut out = vec![0.0f64; signal.len()];
+        let mut gains = vec![1.0f64; DENOISE_FFT];
+        for f in 0..frames {
+            let mut buf = spectrum(f);
+            for ((c, g), n) in buf.iter_mut().zip(gains.iter_mut()).zip(&noise) {
+                let power = c.norm_sqr();
+                let target = ((power - OVER_SUBTRACTION * n) / power.max(1e-20)).clamp(floor, 1.0);
+                *g = GAIN_SMOOTHING * *g + (1.0 - GAIN_SMOOTHING) * target;
+                *c *= *g;
+            }
+            ifft.process(&mut buf);
+            let start = f * DENOISE_HOP;
+            for (i, c) in buf.iter().enumerate() {
+                out[start + i] += c.re / DENOISE_FFT as f64 * window[i] / 1.5;
+            }
+        }
+
+        // The edges lack full overlap; keep the untouched samples there
+        let covered = (frames - 1) * DENOISE_HOP + DENOISE_FFT;
+        for (i, s) in samples.iter_mut().skip(ch).step_by(channels).enumerate() {
+            if i >= DENOISE_HOP * 3 && i < covered - DENOISE_HOP * 3 {
+                *s = out[i] as f32;
+            }
+        }
+    }
+    (!noise_levels.is_empty()).then(|| noise_levels.iter().sum::<f64>() / noise_levels.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 44100;
+
+    fn tone(freq: f64, amplitude: f64, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| {
+                (amplitude * (2.0 * std::f64::consts::PI * freq * i as f64 / RATE as f64).sin())
+                    as f32
+            })
+            .collect()
+    }
+
+    fn noise(amplitude: f32, len: usize) -> Vec<f32> {
+        let mut state = 0x2545_f491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f64 {
+        (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn test_hum_is_detected_and_removed() {
+        let music = tone(440.0, 0.3, RATE as usize * 2);
+        let hum = tone(60.0, 0.05, music.len());
+        let mut samples: Vec<f32> = music.iter().zip(&hum).map(|(a, b)| a + b).collect();
+
+        assert_eq!(detect_hum(&samples, RATE), Some(60.0));
+        assert_eq!(detect_hum(&music, RATE), None);
+
+        remove_hum(&mut samples, 1, RATE, 60.0, 4);
+        let tail = RATE as usize..samples.len();
+        let residual: Vec<f32> = samples[tail.clone()]
+            .iter()
+            .zip(&music[tail])
+            .map(|(a, b)| a - b)
+            .collect();
+        assert!(rms(&residual) < 0.005, "{}", rms(&residual));
+    }
+
+    #[test]
+    fn test_declick_repairs_impulses() {
+        let clean = tone(440.0, 0.3, RATE as usize);
+        let mut samples = clean.clone();
+        samples[10_000] += 0.8;
+        samples[30_000] -= 0.6;
+        samples[30_001] -= 0.5;
+
+        let repaired = declick(&mut samples, 1, RATE, CLICK_THRESHOLD, CLICK_MAX_MS);
+        assert_eq!(repaired, 2);
+        for i in [10_000, 30_000, 30_001] {
+            assert!(
+                (samples[i] - clean[i]).abs() < 0.01,
+                "sample {i}: {} vs {}",
+                samples[i],
+                clean[i]
+            );
+        }
+        assert_eq!(
+            declick(&mut clean.clone(), 1, RATE, CLICK_THRESHOLD, CLICK_MAX_MS),
+            0
+        );
+    }
+
+    #[test]
+    fn test_denoise_quiets_noise_and_keeps_tone() {
+        let len = RATE as usize * 3;
+        let mut music = tone(1000.0, 0.3, len);
+        // A silent second where only the noise remains
+        music[RATE as usize..2 * RATE as usize]
+            .iter_mut()
+            .for_each(|s| *s = 0.0);
+        let hiss = noise(0.01, len);
+        let mut samples: Vec<f32> = music.iter().zip(&hiss).map(|(a, b)| a + b).collect();
+
+        let floor = denoise(&mut samples, 1, 18.0).unwrap();
+        assert!(floor < -40.0, "{floor}");
+        let gap = RATE as usize + 4096..2 * RATE as usize - 4096;
+        assert!(rms(&samples[gap.clone()]) < rms(&hiss[gap]) / 4.0);
+        let body = 8192..RATE as usize - 8192;
+        assert!((rms(&samples[body.clone()]) / rms(&music[body]) - 1.0).abs() < 0.05);
+    }
+}
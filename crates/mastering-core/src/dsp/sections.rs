@@ -0,0 +1,157 @@
+//! Section-aware gain rides.
+//!
+//! Instead of one static chain for the whole track, each section found by
+//! [`find_sections`] is turned up or down by a fraction of its distance from
+//! the track's level before mastering. A quiet verse then meets the
+//! compressor and limiter a little hotter and a loud chorus a little cooler.
+//! Gains change over a short crossfade at each boundary.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::analysis::decode::decode_audio;
+use crate::analysis::segments::find_sections;
+use crate::encode::{self, EncodeOptions};
+use crate::types::{SampleFormat, Section};
+
+/// Share of a section's distance from the track level that is evened out.
+const AMOUNT: f64 = 0.3;
+
+/// Largest ride in either direction, in dB.
+const MAX_GAIN_DB: f64 = 2.0;
+
+/// Rides smaller than this are not worth a pass, in dB.
+const MIN_GAIN_DB: f64 = 0.1;
+
+/// Length of the gain change at a section boundary, in seconds.
+pub const CROSSFADE_SECS: f64 = 1.0;
+
+/// Set the gain of each section: quiet ones up and loud ones down, by
+/// [`AMOUNT`] of their distance from the duration-weighted track level.
+pub fn plan_gains(sections: &mut [Section]) {
+    let total_secs: f64 = sections.iter().map(|s| s.end_secs - s.start_secs).sum();
+    if total_secs <= 0.0 {
+        return;
+    }
+    let power: f64 = sections
+        .iter()
+        .map(|s| 10f64.powf(s.level_db / 10.0) * (s.end_secs - s.start_secs))
+        .sum::<f64>()
+        / total_secs;
+    let track_db = 10.0 * power.max(1e-10).log10();
+    for section in sections {
+        section.gain_db =
+            (-(section.level_db - track_db) * AMOUNT).clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+    }
+}
+
+/// Apply the sections' gains to interleaved `samples`, crossfading over
+/// [`CROSSFADE_SECS`] centred on each boundary.
+pub fn ride(samples: &mut [f32], channels: usize, sample_rate: u32, sections: &[Section]) {
+    if channels == 0 || sections.is_empty() {
+        return;
+    }
+    let half_fade = CROSSFADE_SECS / 2.0;
+    let mut current = 0;
+    for (frame_index, frame) in samples.chunks_exact_mut(channels).enumerate() {
+        let t = frame_index as f64 / sample_rate as f64;
+        while current + 1 < sections.len() && t >= sections[current + 1].start_secs + half_fade {
+            current += 1;
+        }
+        let mut gain_db = sections[current].gain_db;
+        // Ramp across the boundary ahead, if the frame is within its fade
+        if let Some(next) = sections.get(current + 1) {
+            let into_fade = t - (next.start_secs - half_fade);
+            if into_fade > 0.0 {
+                let progress = (into_fade / CROSSFADE_SECS).min(1.0);
+                gain_db += (next.gain_db - gain_db) * progress;
+            }
+        }
+        let gain = 10f64.powf(gain_db / 20.0) as f32;
+        frame.iter_mut().for_each(|s| *s *= gain);
+    }
+}
+
+/// Find the sections of `input`, and when any of them needs a ride, write
+/// the ridden audio to `output` as 32-bit float WAV. Returns the sections
+/// with their gains, or `None` when the input was left as it is.
+pub fn ride_file(input: &Path, output: &Path) -> Result<Option<Vec<Section>>> {
+    let mut audio = decode_audio(input)?;
+    let mut sections = find_sections(&audio.mono_mixdown(), audio.sample_rate);
+    plan_gains(&mut sections);
+    if sections.len() < 2 || sections.iter().all(|s| s.gain_db.abs() < MIN_GAIN_DB) {
+        return Ok(None);
+    }
+
+    ride(
+        &mut audio.samples,
+        audio.channels as usize,
+        audio.sample_rate,
+        &sections,
+    );
+    let opts = EncodeOptions {
+        bit_depth: 32,
+        sample_format: SampleFormat::Float,
+        ..Default::default()
+    };
+    encode::write_wav(
+        output,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        &opts,
+    )?;
+    Ok(Some(sections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(start_secs: f64, end_secs: f64, level_db: f64) -> Section {
+        Section {
+            start_secs,
+            end_secs,
+            level_db,
+            gain_db: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_quiet_sections_come_up_and_loud_ones_down() {
+        let mut sections = [section(0.0, 20.0, -30.0), section(20.0, 40.0, -12.0)];
+        plan_gains(&mut sections);
+        assert!(
+            sections[0].gain_db > 0.0 && sections[0].gain_db <= MAX_GAIN_DB,
+            "{sections:?}"
+        );
+        assert!(sections[1].gain_db < 0.0, "{sections:?}");
+
+        let mut steady = [section(0.0, 30.0, -14.0)];
+        plan_gains(&mut steady);
+        assert!(steady[0].gain_db.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ride_crossfades_between_sections() {
+        let sections = [
+            Section {
+                gain_db: 0.0,
+                ..section(0.0, 4.0, -20.0)
+            },
+            Section {
+                gain_db: -6.0206,
+                ..section(4.0, 8.0, -14.0)
+            },
+        ];
+        let rate = 100;
+        let mut samples = vec![1.0f32; 8 * rate];
+        ride(&mut samples, 1, rate as u32, &sections);
+
+        assert!((samples[200] - 1.0).abs() < 1e-4);
+        assert!((samples[700] - 0.5).abs() < 1e-3);
+        // Half-way through the fade, at the boundary itself
+        assert!((samples[400] - 0.7079).abs() < 0.02, "{}", samples[400]);
+        assert!(samples[350..=450].windows(2).all(|w| w[1] <= w[0]));
+    }
+}
@@ -0,0 +1,107 @@
+//! Silence trimming.
+//!
+//! Leading and trailing stretches where every sample stays below a
+//! threshold are cut, keeping some padding so the first attack and the end
+//! of a reverb tail survive. Silence inside the track is left alone.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::analysis::decode::decode_audio;
+use crate::encode::{self, EncodeOptions};
+use crate::types::{SampleFormat, SilenceTrim, TrimmedSilence};
+
+/// Frames of silence at the start and end of interleaved `samples`, or
+/// `None` when nothing rises above `threshold`.
+pub fn silent_edges(samples: &[f32], channels: usize, threshold: f32) -> Option<(usize, usize)> {
+    let is_sound = |frame: &[f32]| frame.iter().any(|s| s.abs() > threshold);
+    let mut frames = samples.chunks_exact(channels);
+    let first = frames.position(is_sound)?;
+    let last = samples
+        .chunks_exact(channels)
+        .rposition(is_sound)
+        .unwrap_or(first);
+    Some((first, samples.len() / channels - last - 1))
+}
+
+/// Cut leading and trailing silence from interleaved `samples`. Audio that
+/// is silent throughout is left unchanged.
+pub fn trim(
+    samples: &mut Vec<f32>,
+    channels: usize,
+    sample_rate: u32,
+    opts: &SilenceTrim,
+) -> TrimmedSilence {
+    let threshold = 10f64.powf(opts.threshold_db / 20.0) as f32;
+    let Some((leading, trailing)) = silent_edges(samples, channels, threshold) else {
+        return TrimmedSilence::default();
+    };
+    let padding = (opts.padding_ms.max(0.0) / 1000.0 * sample_rate as f64) as usize;
+    let (cut_start, cut_end) = (
+        leading.saturating_sub(padding),
+        trailing.saturating_sub(padding),
+    );
+
+    samples.truncate(samples.len() - cut_end * channels);
+    samples.drain(..cut_start * channels);
+    TrimmedSilence {
+        leading_secs: cut_start as f64 / sample_rate as f64,
+        trailing_secs: cut_end as f64 / sample_rate as f64,
+    }
+}
+
+/// Trim the silence of `input` and write it to `output` as 32-bit float WAV.
+pub fn trim_file(input: &Path, output: &Path, opts: &SilenceTrim) -> Result<TrimmedSilence> {
+    let mut audio = decode_audio(input)?;
+    let trimmed = trim(
+        &mut audio.samples,
+        audio.channels as usize,
+        audio.sample_rate,
+        opts,
+    );
+    let wav_opts = EncodeOptions {
+        bit_depth: 32,
+        sample_format: SampleFormat::Float,
+        ..Default::default()
+    };
+    encode::write_wav(
+        output,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        &wav_opts,
+    )?;
+    Ok(trimmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_keeps_padding() {
+        // 1 s of silence, 0.5 s of sound, 2 s of silence at 1 kHz, stereo
+        let mut samples = vec![0.0f32; 2000];
+        samples.extend([0.5; 1000]);
+        samples.extend([0.0005; 4000]);
+        let opts = SilenceTrim {
+            threshold_db: -60.0,
+            padding_ms: 100.0,
+        };
+
+        let trimmed = trim(&mut samples, 2, 1000, &opts);
+        assert_eq!(
+            trimmed,
+            TrimmedSilence {
+                leading_secs: 0.9,
+                trailing_secs: 1.9
+            }
+        );
+        assert_eq!(samples.len(), 2 * 700);
+        assert_eq!(samples[2 * 100], 0.5);
+
+        let mut silent = vec![0.0f32; 100];
+        assert_eq!(trim(&mut silent, 1, 1000, &opts), TrimmedSilence::default());
+        assert_eq!(silent.len(), 100);
+    }
+}
@@ -0,0 +1,554 @@
+//! Native audio encoders for the output conversion stage.
+//!
+//! FLAC is encoded with the pure-Rust `flacenc` crate, MP3 with LAME
+//! (statically linked through `mp3lame-encoder`) and AIFF is written
+//! directly, so none of them needs ffmpeg. AAC, Opus and ALAC have no native
+//! encoder and are converted with ffmpeg.
+//!
+//! Lossless formats are written through [`Quantizer`], which applies the
+//! requested dither and noise shaping when reducing to the output bit depth.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::analysis::decode::{decode_audio, AudioStream};
+use crate::types::{AudioFormat, Dither, SampleFormat};
+
+/// Default MP3 bitrate when none is requested.
+const DEFAULT_MP3_BITRATE_KBPS: u32 = 320;
+
+/// Options for writing an output file.
+#[derive(Debug, Clone, Copy)]
+pub struct EncodeOptions {
+    /// Bit depth of lossless formats (FLAC is limited to 24 bits).
+    pub bit_depth: u16,
+    /// Float samples are written for WAV only; other formats use integers.
+    pub sample_format: SampleFormat,
+    /// Bitrate of lossy formats.
+    pub bitrate_kbps: Option<u32>,
+    /// Dither applied when quantizing lossless formats.
+    pub dither: Dither,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            bit_depth: 24,
+            sample_format: SampleFormat::Int,
+            bitrate_kbps: None,
+            dither: Dither::None,
+        }
+    }
+}
+
+/// Whether `format` can be encoded without ffmpeg.
+pub fn has_native_encoder(format: AudioFormat) -> bool {
+    matches!(
+        format,
+        AudioFormat::Wav | AudioFormat::Flac | AudioFormat::Mp3 | AudioFormat::Aiff
+    )
+}
+
+/// Encode the audio file at `input` into `format` at `output`.
+pub fn encode_file(
+    input: &Path,
+    output: &Path,
+    format: AudioFormat,
+    opts: &EncodeOptions,
+) -> Result<()> {
+    match format {
+        AudioFormat::Wav => {
+            let audio = decode_audio(input)?;
+            write_wav(
+                output,
+                &audio.samples,
+                audio.channels,
+                audio.sample_rate,
+                opts,
+            )
+        }
+        AudioFormat::Flac => encode_flac(input, output, opts.bit_depth, opts.dither),
+        AudioFormat::Mp3 => encode_mp3(
+            input,
+            output,
+            opts.bitrate_kbps.unwrap_or(DEFAULT_MP3_BITRATE_KBPS),
+        ),
+        AudioFormat::Aiff => encode_aiff(input, output, opts.bit_depth, opts.dither),
+        AudioFormat::Aac | AudioFormat::Opus | AudioFormat::Alac => {
+            anyhow::bail!("No native {format} encoder available; ffmpeg is required")
+        }
+    }
+}
+
+/// Largest LAME bitrate not above `kbps` (at least 32 kbps).
+fn lame_bitrate(kbps: u32) -> mp3lame_encoder::Bitrate {
+    use mp3lame_encoder::Bitrate::*;
+    match kbps {
+        320.. => Kbps320,
+        256..=319 => Kbps256,
+        224..=255 => Kbps224,
+        192..=223 => Kbps192,
+        160..=191 => Kbps160,
+        128..=159 => Kbps128,
+        112..=127 => Kbps112,
+        96..=111 => Kbps96,
+        80..=95 => Kbps80,
+        64..=79 => Kbps64,
+        48..=63 => Kbps48,
+        40..=47 => Kbps40,
+        _ => Kbps32,
+    }
+}
+
+/// Quantize a float sample to a signed integer of `bits` bits.
+fn quantize(sample: f32, bits: u32) -> i32 {
+    let max = ((1i64 << (bits - 1)) - 1) as f64;
+    (sample as f64 * max).round().clamp(-max - 1.0, max) as i32
+}
+
+/// Round a requested bit depth to a supported integer PCM width.
+fn pcm_bits(bit_depth: u16) -> u32 {
+    match bit_depth {
+        0..=16 => 16,
+        17..=24 => 24,
+        _ => 32,
+    }
+}
+
+/// Error-feedback filter coefficients for each noise-shaping curve.
+fn shaping_filter(dither: Dither) -> &'static [f64] {
+    match dither {
+        Dither::None | Dither::Tpdf => &[],
+        Dither::HighPass => &[1.0],
+        Dither::Lipshitz => &[2.033, -2.165, 1.959, -1.590, 0.6149],
+        Dither::FWeighted => &[
+            2.412, -3.370, 3.937, -4.174, 3.353, -2.205, 1.281, -0.569, 0.0847,
+        ],
+    }
+}
+
+const MAX_SHAPING_TAPS: usize = 9;
+
+/// Converts interleaved float samples to integers, with optional TPDF dither
+/// and noise shaping. Keeps per-channel error history, so a single quantizer
+/// must see the whole stream in order.
+pub(crate) struct Quantizer {
+    bits: u32,
+    dither: Dither,
+    channels: usize,
+    next_channel: usize,
+    /// Most recent quantization errors per channel, newest first.
+    errors: Vec<[f64; MAX_SHAPING_TAPS]>,
+    rng: u64,
+}
+
+impl Quantizer {
+    pub(crate) fn new(bits: u32, channels: usize, dither: Dither) -> Self {
+        // Dither below the noise floor of 32-bit integers is pointless
+        let dither = if bits >= 32 { Dither::None } else { dither };
+        Self {
+            bits,
+            dither,
+            channels: channels.max(1),
+            next_channel: 0,
+            errors: vec![[0.0; MAX_SHAPING_TAPS]; channels.max(1)],
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub(crate) fn quantize(&mut self, sample: f32) -> i32 {
+        let ch = self.next_channel;
+        self.next_channel = (ch + 1) % self.channels;
+        if self.dither == Dither::None {
+            return quantize(sample, self.bits);
+        }
+
+        let max = ((1i64 << (self.bits - 1)) - 1) as f64;
+        let history = self.errors[ch];
+        let feedback: f64 = shaping_filter(self.dither)
+            .iter()
+            .zip(&history)
+            .map(|(c, e)| c * e)
+            .sum();
+        let shaped = sample as f64 * max - feedback;
+        let noise = self.uniform() - self.uniform();
+        let q = (shaped + noise).round();
+
+        let errors = &mut self.errors[ch];
+        errors.copy_within(0..MAX_SHAPING_TAPS - 1, 1);
+        errors[0] = q - shaped;
+        q.clamp(-max - 1.0, max) as i32
+    }
+
+    /// Uniform random value in [0, 1) from a xorshift64* generator.
+    fn uniform(&mut self) -> f64 {
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        let x = self.rng.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Write interleaved samples as a WAV file in the format given by `opts`.
+pub(crate) fn write_wav(
+    path: &Path,
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+    opts: &EncodeOptions,
+) -> Result<()> {
+    let float = opts.sample_format == SampleFormat::Float;
+    let bits = if float { 32 } else { pcm_bits(opts.bit_depth) };
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: bits as u16,
+        sample_format: if float {
+            hound::SampleFormat::Float
+        } else {
+            hound::SampleFormat::Int
+        },
+    };
+    let mut writer = hound::WavWriter::create(path, spec)
+        .with_context(|| format!("Creating WAV file: {}", path.display()))?;
+    if float {
+        for &sample in samples {
+            writer.write_sample(sample)?;
+        }
+    } else {
+        let mut quantizer = Quantizer::new(bits, channels as usize, opts.dither);
+        for &sample in samples {
+            writer.write_sample(quantizer.quantize(sample))?;
+        }
+    }
+    writer.finalize().context("Finalizing WAV file")
+}
+
+fn encode_flac(input: &Path, output: &Path, bit_depth: u16, dither: Dither) -> Result<()> {
+    use flacenc::component::BitRepr;
+    use flacenc::error::Verify;
+
+    let audio = decode_audio(input)?;
+    let bits = bit_depth.clamp(16, flacenc::constant::MAX_BITS_PER_SAMPLE as u16) as u32;
+    let mut quantizer = Quantizer::new(bits, audio.channels as usize, dither);
+    let samples: Vec<i32> = audio
+        .samples
+        .iter()
+        .map(|&s| quantizer.quantize(s))
+        .collect();
+
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .map_err(|(_, e)| anyhow::anyhow!("Invalid FLAC encoder config: {e}"))?;
+    let source = flacenc::source::MemSource::from_samples(
+        &samples,
+        audio.channels as usize,
+        bits as usize,
+        audio.sample_rate as usize,
+    );
+    let mut stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| anyhow::anyhow!("FLAC encoding failed: {e:?}"))?;
+
+    // The spec excludes the final (short) block from the minimum block size;
+    // counting it makes decoders treat the stream as variable-blocksize.
+    let info = stream.stream_info_mut();
+    let block_size = info.max_block_size();
+    info.set_block_sizes(block_size, block_size)
+        .map_err(|e| anyhow::anyhow!("Invalid FLAC block size: {e}"))?;
+
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream
+        .write(&mut sink)
+        .map_err(|e| anyhow::anyhow!("Writing FLAC stream failed: {e}"))?;
+    std::fs::write(output, sink.as_slice())
+        .with_context(|| format!("Writing FLAC file: {}", output.display()))
+}
+
+/// Encode a sample rate as the 80-bit IEEE extended float AIFF uses.
+fn aiff_sample_rate(rate: u32) -> [u8; 10] {
+    let mut out = [0u8; 10];
+    if rate == 0 {
+        return out;
+    }
+    let exponent = 31 - rate.leading_zeros();
+    let biased = (16383 + exponent) as u16;
+    let mantissa = (rate as u64) << (63 - exponent);
+    out[..2].copy_from_slice(&biased.to_be_bytes());
+    out[2..].copy_from_slice(&mantissa.to_be_bytes());
+    out
+}
+
+fn encode_aiff(input: &Path, output: &Path, bit_depth: u16, dither: Dither) -> Result<()> {
+    let audio = decode_audio(input)?;
+    let bits = pcm_bits(bit_depth);
+    let bytes_per_sample = (bits / 8) as usize;
+    let data_len = audio.samples.len() * bytes_per_sample;
+    anyhow::ensure!(
+        data_len <= (u32::MAX - 64) as usize,
+        "Audio too long for AIFF"
+    );
+
+    let comm_len = 18u32;
+    let ssnd_len = 8 + data_len as u32;
+    let pad = (data_len % 2) as u32;
+    let form_len = 4 + (8 + comm_len) + (8 + ssnd_len + pad);
+
+    let mut buf = Vec::with_capacity(8 + form_len as usize);
+    buf.extend_from_slice(b"FORM");
+    buf.extend_from_slice(&form_len.to_be_bytes());
+    buf.extend_from_slice(b"AIFF");
+
+    buf.extend_from_slice(b"COMM");
+    buf.extend_from_slice(&comm_len.to_be_bytes());
+    buf.extend_from_slice(&audio.channels.to_be_bytes());
+    buf.extend_from_slice(&(audio.total_frames as u32).to_be_bytes());
+    buf.extend_from_slice(&(bits as u16).to_be_bytes());
+    buf.extend_from_slice(&aiff_sample_rate(audio.sample_rate));
+
+    buf.extend_from_slice(b"SSND");
+    buf.extend_from_slice(&ssnd_len.to_be_bytes());
+    buf.extend_from_slice(&[0u8; 8]); // offset, block size
+    let mut quantizer = Quantizer::new(bits, audio.channels as usize, dither);
+    for &sample in &audio.samples {
+        let bytes = quantizer.quantize(sample).to_be_bytes();
+        buf.extend_from_slice(&bytes[4 - bytes_per_sample..]);
+    }
+    if pad == 1 {
+        buf.push(0);
+    }
+
+    std::fs::write(output, &buf).with_context(|| format!("Writing AIFF file: {}", output.display()))
+}
+
+fn encode_mp3(input: &Path, output: &Path, bitrate_kbps: u32) -> Result<()> {
+    use mp3lame_encoder::{max_required_buffer_size, Builder, FlushNoGap, InterleavedPcm, MonoPcm};
+
+    let mut stream = AudioStream::open(input)?;
+    let channels = stream.channels();
+    anyhow::ensure!(
+        channels == 1 || channels == 2,
+        "MP3 supports mono or stereo only (got {channels} channels)"
+    );
+
+    let lame_err = |e: mp3lame_encoder::BuildError| anyhow::anyhow!("Configuring MP3 encoder: {e}");
+    let mut builder = Builder::new().context("Creating MP3 encoder")?;
+    builder.set_num_channels(channels as u8).map_err(lame_err)?;
+    builder
+        .set_sample_rate(stream.sample_rate())
+        .map_err(lame_err)?;
+    builder
+        .set_brate(lame_bitrate(bitrate_kbps))
+        .map_err(lame_err)?;
+    builder
+        .set_quality(mp3lame_encoder::Quality::Best)
+        .map_err(lame_err)?;
+    let mut encoder = builder.build().map_err(lame_err)?;
+
+    let mut mp3 = Vec::new();
+    while let Some(chunk) = stream.next_chunk()? {
+        let frames = chunk.len() / channels as usize;
+        mp3.reserve(max_required_buffer_size(frames));
+        let result = if channels == 1 {
+            encoder.encode_to_vec(MonoPcm(chunk), &mut mp3)
+        } else {
+            encoder.encode_to_vec(InterleavedPcm(chunk), &mut mp3)
+        };
+        result.map_err(|e| anyhow::anyhow!("MP3 encoding failed: {e}"))?;
+    }
+
+    mp3.reserve(max_required_buffer_size(0));
+    encoder
+        .flush_to_vec::<FlushNoGap>(&mut mp3)
+        .map_err(|e| anyhow::anyhow!("Flushing MP3 encoder failed: {e}"))?;
+
+    std::fs::write(output, &mp3).with_context(|| format!("Writing MP3 file: {}", output.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_test_wav(path: &Path, sample_rate: u32, channels: u16) {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for i in 0..sample_rate {
+            let s = (0.5
+                * (2.0 * std::f64::consts::PI * 440.0 * i as f64 / sample_rate as f64).sin()
+                * i16::MAX as f64) as i16;
+            for _ in 0..channels {
+                writer.write_sample(s).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn lossless(bit_depth: u16) -> EncodeOptions {
+        EncodeOptions {
+            bit_depth,
+            ..Default::default()
+        }
+    }
+
+    /// Quantization error (in LSBs) of a quiet 16-bit sine with `dither`.
+    fn quantization_error(dither: Dither) -> Vec<f64> {
+        let mut quantizer = Quantizer::new(16, 1, dither);
+        (0..44100)
+            .map(|i| {
+                let x = 0.001 * (2.0 * std::f64::consts::PI * 100.0 * i as f64 / 44100.0).sin();
+                quantizer.quantize(x as f32) as f64 - x * 32767.0
+            })
+            .collect()
+    }
+
+    /// Power of one second of 44.1 kHz `signal` between 2 and 5 kHz, where
+    /// hearing is most sensitive.
+    fn sensitive_band_power(signal: &[f64]) -> f64 {
+        use rustfft::num_complex::Complex;
+        let mut buf: Vec<Complex<f64>> = signal.iter().map(|&s| Complex::new(s, 0.0)).collect();
+        rustfft::FftPlanner::new()
+            .plan_fft_forward(buf.len())
+            .process(&mut buf);
+        // One-second signal, so bin index equals frequency in Hz
+        buf[2000..5000].iter().map(|c| c.norm_sqr()).sum()
+    }
+
+    #[test]
+    fn test_tpdf_dither_preserves_sub_lsb_level() {
+        // A constant 0.3 LSB offset rounds away without dither
+        let level = 0.3 / 32767.0;
+        let mut plain = Quantizer::new(16, 1, Dither::None);
+        let mut dithered = Quantizer::new(16, 1, Dither::Tpdf);
+        let n = 100_000;
+        let plain_mean = (0..n).map(|_| plain.quantize(level) as f64).sum::<f64>() / n as f64;
+        let dithered_mean = (0..n).map(|_| dithered.quantize(level) as f64).sum::<f64>() / n as f64;
+        assert_eq!(plain_mean, 0.0);
+        assert!((dithered_mean - 0.3).abs() < 0.02, "mean {dithered_mean}");
+    }
+
+    #[test]
+    fn test_noise_shaping_moves_noise_out_of_sensitive_band() {
+        let flat = sensitive_band_power(&quantization_error(Dither::Tpdf));
+        for dither in [Dither::HighPass, Dither::Lipshitz, Dither::FWeighted] {
+            let shaped = sensitive_band_power(&quantization_error(dither));
+            assert!(shaped < flat, "{dither}: {shaped} >= {flat}");
+        }
+    }
+
+    #[test]
+    fn test_no_dither_at_32_bits() {
+        let mut quantizer = Quantizer::new(32, 2, Dither::FWeighted);
+        assert_eq!(quantizer.quantize(0.0), 0);
+        assert_eq!(quantizer.quantize(0.5), quantize(0.5, 32));
+    }
+
+    #[test]
+    fn test_quantize_range() {
+        assert_eq!(quantize(1.0, 16), 32767);
+        assert_eq!(quantize(-1.5, 16), -32768);
+        assert_eq!(quantize(0.0, 24), 0);
+    }
+
+    #[test]
+    fn test_lame_bitrate_rounds_down() {
+        assert_eq!(lame_bitrate(320) as u16, 320);
+        assert_eq!(lame_bitrate(200) as u16, 192);
+        assert_eq!(lame_bitrate(8) as u16, 32);
+    }
+
+    #[test]
+    fn test_float_wav_keeps_samples_exact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("float.wav");
+        // Values that integer PCM cannot represent exactly, including an over
+        let samples = [0.123_456_79f32, -0.5, 1.25, -1e-7];
+        let opts = EncodeOptions {
+            bit_depth: 32,
+            sample_format: SampleFormat::Float,
+            ..Default::default()
+        };
+        write_wav(&path, &samples, 2, 48000, &opts).unwrap();
+
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().sample_format, hound::SampleFormat::Float);
+        let read: Vec<f32> = reader.into_samples::<f32>().map(|s| s.unwrap()).collect();
+        assert_eq!(read, samples);
+    }
+
+    #[test]
+    fn test_flac_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("in.wav");
+        let flac = dir.path().join("out.flac");
+        write_test_wav(&wav, 44100, 2);
+
+        encode_file(&wav, &flac, AudioFormat::Flac, &lossless(16)).unwrap();
+
+        let original = decode_audio(&wav).unwrap();
+        let decoded = decode_audio(&flac).unwrap();
+        assert_eq!(decoded.channels, 2);
+        assert_eq!(decoded.total_frames, original.total_frames);
+        // Lossless at the same bit depth
+        assert!(original
+            .samples
+            .iter()
+            .zip(&decoded.samples)
+            .all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_aiff_sample_rate() {
+        assert_eq!(
+            aiff_sample_rate(44100),
+            [0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            aiff_sample_rate(48000),
+            [0x40, 0x0E, 0xBB, 0x80, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_aiff_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("in.wav");
+        let aiff = dir.path().join("out.aiff");
+        write_test_wav(&wav, 44100, 1);
+
+        encode_file(&wav, &aiff, AudioFormat::Aiff, &lossless(24)).unwrap();
+
+        let original = decode_audio(&wav).unwrap();
+        let decoded = decode_audio(&aiff).unwrap();
+        assert_eq!(decoded.sample_rate, 44100);
+        assert_eq!(decoded.total_frames, original.total_frames);
+        assert!(original
+            .samples
+            .iter()
+            .zip(&decoded.samples)
+            .all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_mp3_encode_decodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("in.wav");
+        let mp3 = dir.path().join("out.mp3");
+        write_test_wav(&wav, 48000, 2);
+
+        let opts = EncodeOptions {
+            bitrate_kbps: Some(192),
+            ..Default::default()
+        };
+        encode_file(&wav, &mp3, AudioFormat::Mp3, &opts).unwrap();
+
+        let decoded = decode_audio(&mp3).unwrap();
+        assert_eq!(decoded.sample_rate, 48000);
+        assert_eq!(decoded.channels, 2);
+        assert!(decoded.duration_secs() > 0.9);
+    }
+}
@@ -79,6 +79,18 @@ pub enum MasteringError {
         field: Option<String>,
     },
 
+    /// A backend subprocess overran its timeout and was killed
+    #[error("The {process} did not finish within {timeout_secs}s and was stopped")]
+    ProcessTimeout {
+        process: String,
+        timeout_secs: u64,
+        config_key: Option<String>,
+    },
+
+    /// The job was cancelled before it finished
+    #[error("Mastering job was cancelled")]
+    Cancelled,
+
     /// Generic errors with context
     #[error("Operation failed: {message}")]
     Generic {
@@ -111,6 +123,17 @@ impl MasteringError {
             } => {
                 format!("{}\n\nSuggestion: {}", self, suggested_action)
             }
+            MasteringError::ProcessTimeout { config_key, .. } => {
+                let suggestion = match config_key {
+                    Some(key) => format!(
+                        "Very long tracks may need more time; raise {key} in the config. \
+                         If it hangs on short tracks too, check the Python environment."
+                    ),
+                    None => "Python may be hung; check that the configured interpreter starts."
+                        .to_string(),
+                };
+                format!("{}\n\nSuggestion: {}", self, suggestion)
+            }
             _ => self.to_string(),
         }
     }
@@ -127,6 +150,8 @@ impl MasteringError {
             MasteringError::BackendError { can_fallback, .. } => *can_fallback,
             MasteringError::ProcessingError { .. } => true,
             MasteringError::ValidationError { .. } => true,
+            MasteringError::ProcessTimeout { .. } => false,
+            MasteringError::Cancelled => false,
             MasteringError::Generic { .. } => false,
         }
     }
@@ -140,6 +165,7 @@ impl MasteringError {
                 ..
             } | MasteringError::NetworkTimeout { .. }
                 | MasteringError::ApiQuotaExceeded { .. }
+                | MasteringError::ProcessTimeout { .. }
         )
     }
 
@@ -163,9 +189,7 @@ impl MasteringError {
         let file = file.into();
         let reason = reason.into();
         let suggested_action = if reason.contains("format") || reason.contains("codec") {
-            format!(
-                "Ensure the file is a supported format (WAV, FLAC, MP3, OGG, M4A)."
-            )
+            "Ensure the file is a supported format (WAV, FLAC, MP3, OGG, M4A).".to_string()
         } else if reason.contains("corrupt") || reason.contains("invalid") {
             "The file may be corrupted. Try opening it in another audio application to verify.".to_string()
         } else {
@@ -230,6 +254,11 @@ impl MasteringError {
 /// Conversion from anyhow::Error to MasteringError.
 impl From<anyhow::Error> for MasteringError {
     fn from(err: anyhow::Error) -> Self {
+        // Preserve structured errors that were raised and wrapped with context
+        let err = match err.downcast::<MasteringError>() {
+            Ok(mastering_err) => return mastering_err,
+            Err(err) => err,
+        };
         let msg = err.to_string();
 
         // Detect common error patterns and provide specific error types
@@ -294,6 +323,29 @@ mod tests {
         assert!(!MasteringError::api_quota_exceeded("test", "now").can_retry());
     }
 
+    #[test]
+    fn test_structured_error_survives_anyhow() {
+        let err =
+            anyhow::Error::new(MasteringError::Cancelled).context(crate::pipeline::BackendFailed);
+        assert!(matches!(
+            MasteringError::from(err),
+            MasteringError::Cancelled
+        ));
+    }
+
+    #[test]
+    fn test_process_timeout_names_setting() {
+        let err = MasteringError::ProcessTimeout {
+            process: "matchering bridge".into(),
+            timeout_secs: 60,
+            config_key: Some("backends.matchering.timeout_secs".into()),
+        };
+        let msg = err.user_message();
+        assert!(msg.contains("within 60s"));
+        assert!(msg.contains("backends.matchering.timeout_secs"));
+        assert!(err.can_fallback());
+    }
+
     #[test]
     fn test_can_fallback() {
         assert!(MasteringError::backend_error("test", "failed").can_fallback());
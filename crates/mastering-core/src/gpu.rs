@@ -3,6 +3,9 @@ use serde::{Deserialize, Serialize};
 use std::process::Command;
 use tracing::warn;
 
+use crate::error::MasteringError;
+use crate::types::Device;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GpuInfo {
     pub gpu_name: String,
@@ -62,11 +65,7 @@ pub fn detect_vram() -> Result<Vec<GpuInfo>> {
         }
 
         if trimmed.contains("VRAM (Total):") {
-            let vram_str = trimmed
-                .split(':')
-                .last()
-                .unwrap_or("")
-                .trim();
+            let vram_str = trimmed.split(':').next_back().unwrap_or("").trim();
             let vram_mb = parse_vram_string(vram_str);
 
             if !current_chipset.is_empty() {
@@ -136,6 +135,53 @@ fn detect_total_ram_mb() -> Result<u64> {
     Ok(mem_bytes / (1024 * 1024))
 }
 
+/// Devices local ML models can run on here, fastest first. The CPU is
+/// always last.
+pub fn available_devices() -> Vec<Device> {
+    let mut devices = Vec::new();
+    if has_cuda() {
+        devices.push(Device::Cuda);
+    }
+    if cfg!(target_os = "macos") {
+        devices.push(Device::Metal);
+    }
+    devices.push(Device::Cpu);
+    devices
+}
+
+/// The device to run on for a `requested` one: the fastest available for
+/// [`Device::Auto`], otherwise `requested` itself if it is available.
+pub fn resolve_device(requested: Device) -> Result<Device> {
+    pick_device(requested, &available_devices())
+}
+
+fn pick_device(requested: Device, available: &[Device]) -> Result<Device> {
+    if requested == Device::Auto {
+        return Ok(available.first().copied().unwrap_or(Device::Cpu));
+    }
+    if available.contains(&requested) {
+        return Ok(requested);
+    }
+    let found: Vec<String> = available.iter().map(Device::to_string).collect();
+    Err(MasteringError::InvalidConfig {
+        message: format!(
+            "No {requested} device found (available: {})",
+            found.join(", ")
+        ),
+        config_key: Some("backends.local_ml.device".into()),
+    }
+    .into())
+}
+
+/// Whether `nvidia-smi` lists a GPU.
+fn has_cuda() -> bool {
+    Command::new("nvidia-smi")
+        .arg("-L")
+        .output()
+        .map(|o| o.status.success() && String::from_utf8_lossy(&o.stdout).contains("GPU"))
+        .unwrap_or(false)
+}
+
 pub fn get_vram_tiers() -> Vec<VramTier> {
     vec![
         VramTier {
@@ -245,8 +291,7 @@ pub fn get_recommendations_for_vram(vram_mb: u64) -> Vec<ModelRecommendation> {
     let tiers = get_vram_tiers();
     tiers
         .iter()
-        .filter(|t| vram_mb >= t.vram_mb)
-        .last()
+        .rfind(|t| vram_mb >= t.vram_mb)
         .map(|t| t.recommended_models.clone())
         .unwrap_or_default()
 }
@@ -262,6 +307,20 @@ mod tests {
         assert_eq!(parse_vram_string("16GB"), 16384);
     }
 
+    #[test]
+    fn test_pick_device() {
+        let available = [Device::Cuda, Device::Cpu];
+        assert_eq!(pick_device(Device::Auto, &available).unwrap(), Device::Cuda);
+        assert_eq!(pick_device(Device::Cpu, &available).unwrap(), Device::Cpu);
+        assert_eq!(
+            pick_device(Device::Auto, &[Device::Cpu]).unwrap(),
+            Device::Cpu
+        );
+
+        let err = pick_device(Device::Metal, &available).unwrap_err();
+        assert!(err.to_string().contains("cuda, cpu"), "{err}");
+    }
+
     #[test]
     fn test_get_tiers() {
         let tiers = get_vram_tiers();
@@ -0,0 +1,611 @@
+//! Job queue and history.
+//!
+//! Every job queued or mastered is a row in a SQLite database at
+//! `<config dir>/jobs.db`, holding the job's settings, its status and its
+//! result. Queued jobs survive restarts, and finished ones stay listed so
+//! everything that was mastered can be looked up later.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::error::MasteringError;
+use crate::pipeline::{self, CancellationToken, MasteringJob};
+use crate::types::MasteringResult;
+
+const DB_FILE: &str = "jobs.db";
+
+/// How long to wait for another process writing to the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS jobs (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    status      TEXT NOT NULL,
+    input_path  TEXT NOT NULL,
+    job         TEXT NOT NULL,
+    result      TEXT,
+    error       TEXT,
+    created_at  INTEGER NOT NULL,
+    started_at  INTEGER,
+    finished_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS jobs_status ON jobs (status);
+";
+
+/// Where a job is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub const ALL: [JobStatus; 5] = [
+        JobStatus::Queued,
+        JobStatus::Running,
+        JobStatus::Done,
+        JobStatus::Failed,
+        JobStatus::Cancelled,
+    ];
+
+    /// The job has stopped and will not run again unless retried.
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+impl std::fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobStatus::Queued => write!(f, "queued"),
+            JobStatus::Running => write!(f, "running"),
+            JobStatus::Done => write!(f, "done"),
+            JobStatus::Failed => write!(f, "failed"),
+            JobStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::str::FromStr for JobStatus {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "queued" => Ok(JobStatus::Queued),
+            "running" => Ok(JobStatus::Running),
+            "done" => Ok(JobStatus::Done),
+            "failed" => Ok(JobStatus::Failed),
+            "cancelled" | "canceled" => Ok(JobStatus::Cancelled),
+            _ => anyhow::bail!(
+                "Unknown job status: {s}. Use: queued, running, done, failed, cancelled"
+            ),
+        }
+    }
+}
+
+/// A job in the queue or history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub id: i64,
+    pub status: JobStatus,
+    /// Settings the job was queued or run with.
+    pub job: MasteringJob,
+    /// Set once the job is done.
+    pub result: Option<MasteringResult>,
+    /// Why the job failed or was cancelled.
+    pub error: Option<String>,
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+}
+
+impl JobRecord {
+    /// The job's input, or its first stem when it was made only of stems.
+    pub fn input_path(&self) -> &Path {
+        match self.job.stem_inputs.first() {
+            Some(stem) if self.job.input_path.as_os_str().is_empty() => &stem.path,
+            _ => &self.job.input_path,
+        }
+    }
+
+    /// Where the master was written, once the job is done.
+    pub fn output_path(&self) -> Option<&Path> {
+        self.result.as_ref().map(|r| r.output_path.as_path())
+    }
+
+    /// Seconds the job ran for, once it has finished.
+    pub fn duration_secs(&self) -> Option<u64> {
+        Some(self.finished_at?.saturating_sub(self.started_at?))
+    }
+
+    /// The job again with the same settings, to master `input` instead of
+    /// the original input when given.
+    ///
+    /// When the job finished with parameters, they are fixed along with
+    /// any stem adjustments, so the same chain is rendered by the same
+    /// backend rather than new parameters suggested; a re-run AI job gives
+    /// the same master. The
+    /// output goes where the original did for the same input, and next to
+    /// the new input otherwise.
+    pub fn remaster(&self, input: Option<&Path>) -> MasteringJob {
+        let mut job = self.job.clone();
+        if let Some(input) = input {
+            job.input_path = input.to_path_buf();
+            job.stem_inputs.clear();
+            job.output_path = None;
+        }
+        if let Some(ref result) = self.result {
+            if let Some(ref params) = result.params_applied {
+                job.backend = self.job.resolved_backend();
+                job.target_lufs = Some(params.target_lufs);
+                job.params = Some(params.clone());
+                // Already folded into the parameters
+                job.refinement = None;
+                job.brief = None;
+                job.explain = false;
+            }
+            if let Some(ref adjustments) = result.stem_adjustments {
+                job.stem_adjustments = adjustments.clone();
+            }
+            if input.is_none() && job.output_path.is_none() {
+                job.output_path = Some(result.output_path.clone());
+            }
+        }
+        job
+    }
+}
+
+/// Which jobs [`JobStore::list`] returns.
+#[derive(Debug, Clone, Default)]
+pub struct JobFilter {
+    pub status: Option<JobStatus>,
+    /// Newest jobs only, at most this many.
+    pub limit: Option<usize>,
+}
+
+/// Database of queued and finished jobs.
+#[derive(Debug, Clone)]
+pub struct JobStore {
+    path: PathBuf,
+}
+
+impl JobStore {
+    /// Create a store kept in the database file at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Default location (`<config dir>/jobs.db`).
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join(DB_FILE))
+    }
+
+    /// Store in the default location.
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(Self::default_path()?))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add `job` to the end of the queue and return its id.
+    pub fn enqueue(&self, job: &MasteringJob) -> Result<i64> {
+        let conn = self.connect()?;
+        conn.execute(
+            "INSERT INTO jobs (status, input_path, job, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                JobStatus::Queued.to_string(),
+                job.input_path.to_string_lossy(),
+                serde_json::to_string(job)?,
+                now() as i64,
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+        info!("Queued job {id} for {}", job.input_path.display());
+        Ok(id)
+    }
+
+    /// Add a job that was run outside the queue to the history, with its
+    /// outcome, and return its id.
+    pub fn record(
+        &self,
+        job: &MasteringJob,
+        outcome: Result<&MasteringResult, &anyhow::Error>,
+    ) -> Result<i64> {
+        let (status, result, error) = outcome_columns(outcome)?;
+        let conn = self.connect()?;
+        let now = now() as i64;
+        conn.execute(
+            "INSERT INTO jobs (status, input_path, job, result, error, created_at, started_at, finished_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6, ?6)",
+            params![
+                status.to_string(),
+                job.input_path.to_string_lossy(),
+                serde_json::to_string(job)?,
+                result,
+                error,
+                now,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get(&self, id: i64) -> Result<JobRecord> {
+        self.connect()?
+            .query_row("SELECT * FROM jobs WHERE id = ?1", [id], record_from_row)
+            .optional()?
+            .with_context(|| format!("No job with id {id}"))?
+    }
+
+    /// Jobs matching `filter`, newest first.
+    pub fn list(&self, filter: &JobFilter) -> Result<Vec<JobRecord>> {
+        let conn = self.connect()?;
+        let limit = filter.limit.map_or(-1, |n| n as i64);
+        let status = filter.status.map(|s| s.to_string());
+        let mut statement = conn.prepare(
+            "SELECT * FROM jobs WHERE ?1 IS NULL OR status = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let rows = statement.query_map(params![status, limit], record_from_row)?;
+        rows.map(|row| row?).collect()
+    }
+
+    /// Mark the oldest queued job running and return it.
+    pub fn claim_next(&self) -> Result<Option<JobRecord>> {
+        let mut conn = self.connect()?;
+        let tx = conn.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let id: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM jobs WHERE status = ?1 ORDER BY id LIMIT 1",
+                [JobStatus::Queued.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        tx.execute(
+            "UPDATE jobs SET status = ?1, started_at = ?2 WHERE id = ?3",
+            params![JobStatus::Running.to_string(), now() as i64, id],
+        )?;
+        let record = tx.query_row("SELECT * FROM jobs WHERE id = ?1", [id], record_from_row)??;
+        tx.commit()?;
+        Ok(Some(record))
+    }
+
+    /// Store the outcome of a running job.
+    pub fn finish(&self, id: i64, outcome: &Result<MasteringResult>) -> Result<()> {
+        let (status, result, error) = outcome_columns(outcome.as_ref())?;
+        self.connect()?.execute(
+            "UPDATE jobs SET status = ?1, result = ?2, error = ?3, finished_at = ?4 WHERE id = ?5",
+            params![status.to_string(), result, error, now() as i64, id],
+        )?;
+        Ok(())
+    }
+
+    /// Queue a failed or cancelled job again.
+    pub fn retry(&self, id: i64) -> Result<()> {
+        let record = self.get(id)?;
+        anyhow::ensure!(
+            matches!(record.status, JobStatus::Failed | JobStatus::Cancelled),
+            "Job {id} is {}; only failed or cancelled jobs can be retried",
+            record.status
+        );
+        self.connect()?.execute(
+            "UPDATE jobs SET status = ?1, result = NULL, error = NULL, started_at = NULL, finished_at = NULL
+             WHERE id = ?2",
+            params![JobStatus::Queued.to_string(), id],
+        )?;
+        info!("Queued job {id} again");
+        Ok(())
+    }
+
+    /// Remove a job that is not running from the queue or history. Its
+    /// master, if any, is left on disk.
+    pub fn delete(&self, id: i64) -> Result<()> {
+        let record = self.get(id)?;
+        anyhow::ensure!(
+            record.status != JobStatus::Running,
+            "Job {id} is running and cannot be deleted"
+        );
+        self.connect()?
+            .execute("DELETE FROM jobs WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Queue jobs again that were left running when the process running
+    /// them quit. Only call this while no queue is being run.
+    pub fn requeue_interrupted(&self) -> Result<usize> {
+        let count = self.connect()?.execute(
+            "UPDATE jobs SET status = ?1, started_at = NULL WHERE status = ?2",
+            params![
+                JobStatus::Queued.to_string(),
+                JobStatus::Running.to_string()
+            ],
+        )?;
+        if count > 0 {
+            info!("Queued {count} interrupted job(s) again");
+        }
+        Ok(count)
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Creating {}", parent.display()))?;
+        }
+        let conn = Connection::open(&self.path)
+            .with_context(|| format!("Opening job database {}", self.path.display()))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(conn)
+    }
+}
+
+/// Jobs run by [`run_queue`] before the queue was empty or stopped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueSummary {
+    pub done: usize,
+    pub failed: usize,
+}
+
+/// Run queued jobs one at a time, oldest first, until the queue is empty
+/// or `cancel` is cancelled, which also cancels the job in progress.
+///
+/// Each job is sent to `events` when it starts and again when it finishes.
+pub async fn run_queue(
+    store: &JobStore,
+    config: &Config,
+    events: Option<mpsc::UnboundedSender<JobRecord>>,
+    cancel: CancellationToken,
+) -> Result<QueueSummary> {
+    let send = |record: &JobRecord| {
+        if let Some(ref sender) = events {
+            // A dropped receiver just means nobody is listening any more
+            let _ = sender.send(record.clone());
+        }
+    };
+
+    let mut summary = QueueSummary::default();
+    while !cancel.is_cancelled() {
+        let Some(record) = store.claim_next()? else {
+            break;
+        };
+        send(&record);
+        let mut job = record.job.clone();
+        job.cancel_token = cancel.child_token();
+        let outcome = pipeline::run(&job, config).await;
+        match outcome {
+            Ok(_) => summary.done += 1,
+            Err(ref e) if !is_cancelled(e) => {
+                warn!("Job {} failed: {e:#}", record.id);
+                summary.failed += 1;
+            }
+            Err(_) => {}
+        }
+        store.finish(record.id, &outcome)?;
+        send(&store.get(record.id)?);
+    }
+    Ok(summary)
+}
+
+/// Add a job that was run directly to the default history. Dry runs and
+/// previews are left out, and a history that cannot be written is only
+/// logged, so it never fails the job.
+pub fn remember(job: &MasteringJob, outcome: &Result<MasteringResult>) {
+    if job.dry_run || job.preview {
+        return;
+    }
+    // Relative paths would point elsewhere when the job is run again
+    let mut job = job.clone();
+    for path in [
+        &mut job.output_path,
+        &mut job.reference_path,
+        &mut job.match_eq,
+    ]
+    .into_iter()
+    .flatten()
+    .chain(std::iter::once(&mut job.input_path))
+    .chain(job.stem_inputs.iter_mut().map(|stem| &mut stem.path))
+    {
+        if let Ok(absolute) = std::path::absolute(&*path) {
+            *path = absolute;
+        }
+    }
+    let result;
+    let outcome = match outcome {
+        Ok(finished) => {
+            result = MasteringResult {
+                output_path: std::path::absolute(&finished.output_path)
+                    .unwrap_or_else(|_| finished.output_path.clone()),
+                ..finished.clone()
+            };
+            Ok(&result)
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = JobStore::open_default().and_then(|store| store.record(&job, outcome)) {
+        warn!("Failed to add the job to the history: {e:#}");
+    }
+}
+
+fn is_cancelled(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<MasteringError>(),
+        Some(MasteringError::Cancelled)
+    )
+}
+
+/// Status, result JSON and error message to store for `outcome`.
+fn outcome_columns(
+    outcome: Result<&MasteringResult, &anyhow::Error>,
+) -> Result<(JobStatus, Option<String>, Option<String>)> {
+    Ok(match outcome {
+        Ok(result) => (JobStatus::Done, Some(serde_json::to_string(result)?), None),
+        Err(e) if is_cancelled(e) => (JobStatus::Cancelled, None, Some(format!("{e:#}"))),
+        Err(e) => (JobStatus::Failed, None, Some(format!("{e:#}"))),
+    })
+}
+
+/// The job in `row`; the inner result fails when a column cannot be parsed.
+fn record_from_row(row: &Row<'_>) -> rusqlite::Result<Result<JobRecord>> {
+    let id: i64 = row.get("id")?;
+    let status: String = row.get("status")?;
+    let job: String = row.get("job")?;
+    let result: Option<String> = row.get("result")?;
+    let error: Option<String> = row.get("error")?;
+    let created_at: i64 = row.get("created_at")?;
+    let started_at: Option<i64> = row.get("started_at")?;
+    let finished_at: Option<i64> = row.get("finished_at")?;
+    Ok((|| {
+        Ok(JobRecord {
+            id,
+            status: status.parse()?,
+            job: serde_json::from_str(&job).with_context(|| format!("Reading job {id}"))?,
+            result: result
+                .map(|r| serde_json::from_str(&r))
+                .transpose()
+                .with_context(|| format!("Reading the result of job {id}"))?,
+            error,
+            created_at: created_at as u64,
+            started_at: started_at.map(|t| t as u64),
+            finished_at: finished_at.map(|t| t as u64),
+        })
+    })())
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(input: &str) -> MasteringJob {
+        MasteringJob {
+            input_path: PathBuf::from(input),
+            target_lufs: Some(-12.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_queue_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::new(dir.path().join("jobs.db"));
+
+        let first = store.enqueue(&job("a.wav")).unwrap();
+        let second = store.enqueue(&job("b.wav")).unwrap();
+        let claimed = store.claim_next().unwrap().unwrap();
+        assert_eq!(claimed.id, first, "oldest job first");
+        assert_eq!(claimed.status, JobStatus::Running);
+        assert_eq!(claimed.job.target_lufs, Some(-12.0));
+        assert!(store.delete(first).is_err(), "running jobs stay");
+
+        store
+            .finish(first, &Err(anyhow::anyhow!("decode failed")))
+            .unwrap();
+        let failed = store.get(first).unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert_eq!(failed.error.as_deref(), Some("decode failed"));
+        assert!(
+            store.retry(second).is_err(),
+            "only finished jobs are retried"
+        );
+
+        store.retry(first).unwrap();
+        let queued = store.list(&JobFilter {
+            status: Some(JobStatus::Queued),
+            limit: None,
+        });
+        let ids: Vec<i64> = queued.unwrap().iter().map(|r| r.id).collect();
+        assert_eq!(ids, [second, first], "newest first");
+
+        store.delete(second).unwrap();
+        assert!(store.get(second).is_err());
+        assert_eq!(store.list(&JobFilter::default()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_interrupted_jobs_are_requeued() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::new(dir.path().join("jobs.db"));
+        let id = store.enqueue(&job("a.wav")).unwrap();
+        store.claim_next().unwrap();
+        assert!(store.claim_next().unwrap().is_none());
+
+        // A new process opening the same database
+        let store = JobStore::new(store.path());
+        assert_eq!(store.requeue_interrupted().unwrap(), 1);
+        assert_eq!(store.claim_next().unwrap().unwrap().id, id);
+    }
+
+    #[test]
+    fn test_remaster_fixes_params() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::new(dir.path().join("jobs.db"));
+        let original = MasteringJob {
+            backend: crate::types::Backend::Auto,
+            brief: Some("warm".into()),
+            ..job("a.wav")
+        };
+        let result: MasteringResult = serde_json::from_str(
+            r#"{
+                "output_path": "out/a_mastered.wav",
+                "backend_used": "ai/ollama",
+                "params_applied": {
+                    "eq": [],
+                    "compression": { "threshold_db": -18.0, "ratio": 2.0, "attack_ms": 10.0,
+                                     "release_ms": 100.0, "knee_db": 6.0, "makeup_gain_db": 0.0 },
+                    "limiter": { "enabled": true, "ceiling_db": -1.0, "release_ms": 50.0 },
+                    "stereo": { "width": 1.0, "balance": 0.0 },
+                    "target_lufs": -10.0
+                }
+            }"#,
+        )
+        .unwrap();
+        let id = store.record(&original, Ok(&result)).unwrap();
+        let record = store.get(id).unwrap();
+
+        let again = record.remaster(None);
+        assert_eq!(again.input_path, PathBuf::from("a.wav"));
+        assert_eq!(again.output_path, Some(PathBuf::from("out/a_mastered.wav")));
+        assert_eq!(again.params.as_ref().map(|p| p.target_lufs), Some(-10.0));
+        assert_eq!(again.target_lufs, Some(-10.0));
+        assert_eq!(again.resolved_backend(), crate::types::Backend::Ai);
+        assert!(again.brief.is_none());
+
+        let other = record.remaster(Some(Path::new("b.wav")));
+        assert_eq!(other.input_path, PathBuf::from("b.wav"));
+        assert!(other.output_path.is_none());
+    }
+
+    #[test]
+    fn test_record_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::new(dir.path().join("jobs.db"));
+        let id = store
+            .record(&job("a.wav"), Err(&MasteringError::Cancelled.into()))
+            .unwrap();
+        let record = store.get(id).unwrap();
+        assert_eq!(record.status, JobStatus::Cancelled);
+        assert_eq!(record.duration_secs(), Some(0));
+    }
+}
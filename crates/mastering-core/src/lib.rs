@@ -1,11 +1,31 @@
+pub mod ab;
 pub mod analysis;
 pub mod backends;
 pub mod cache;
 pub mod config;
+pub mod curves;
+pub mod diff;
+pub mod dsp;
+pub mod encode;
 pub mod error;
 pub mod gpu;
+pub mod jobs;
+pub mod metadata;
+pub mod models;
+pub mod peaks;
 pub mod pipeline;
+pub mod recipes;
+pub mod references;
+pub mod render;
+pub mod replaygain;
+pub mod resample;
+pub mod rules;
+pub mod scoring;
+pub mod secrets;
+pub mod stems;
+pub mod surround;
 pub mod types;
+pub mod validate;
 
 // Re-export commonly used types
 pub use error::{MasteringError, Result};
@@ -0,0 +1,253 @@
+//! Carry tags and cover art from the source file over to the mastered output.
+//!
+//! Tags are read with `lofty`, converted to the output format's native tag
+//! type (ID3v2, Vorbis comments or MP4 atoms) and written after encoding.
+//! Items the target tag type cannot represent are dropped.
+
+use anyhow::{Context, Result};
+use lofty::config::WriteOptions;
+use lofty::file::{FileType, TaggedFileExt};
+use lofty::probe::Probe;
+use lofty::tag::{ItemKey, Tag, TagExt};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::replaygain::{R128_REFERENCE_LUFS, REFERENCE_LUFS};
+use crate::types::ReplayGain;
+
+/// Tag values that replace whatever the source file carries.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TagOverrides {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub artist: Option<String>,
+    #[serde(default)]
+    pub album: Option<String>,
+    #[serde(default)]
+    pub album_artist: Option<String>,
+    #[serde(default)]
+    pub isrc: Option<String>,
+}
+
+impl TagOverrides {
+    fn apply(&self, tag: &mut Tag) {
+        let fields = [
+            (ItemKey::TrackTitle, &self.title),
+            (ItemKey::TrackArtist, &self.artist),
+            (ItemKey::AlbumTitle, &self.album),
+            (ItemKey::AlbumArtist, &self.album_artist),
+            (ItemKey::Isrc, &self.isrc),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                tag.insert_text(key, value.clone());
+            }
+        }
+    }
+}
+
+/// Copy the tags of `input` to `output`, applying `overrides` on top.
+///
+/// Does nothing when there is neither a source tag nor an override.
+pub fn copy_tags(input: &Path, output: &Path, overrides: &TagOverrides) -> Result<()> {
+    let source = lofty::read_from_path(input)
+        .with_context(|| format!("Reading tags from {}", input.display()))?;
+
+    let output_type = Probe::open(output)
+        .and_then(|p| Ok(p.guess_file_type()?))
+        .with_context(|| format!("Probing {}", output.display()))?
+        .file_type()
+        .with_context(|| format!("Unknown output format: {}", output.display()))?;
+    let tag_type = output_type.primary_tag_type();
+
+    let mut tag = source
+        .primary_tag()
+        .or_else(|| source.first_tag())
+        .cloned()
+        .unwrap_or_else(|| Tag::new(tag_type));
+    tag.re_map(tag_type);
+    overrides.apply(&mut tag);
+
+    if tag.is_empty() {
+        return Ok(());
+    }
+    tag.save_to_path(output, WriteOptions::default())
+        .with_context(|| format!("Writing tags to {}", output.display()))
+}
+
+/// Write ReplayGain values to the tags of `path`, replacing any it has.
+///
+/// Opus files get R128 gain tags instead, as their specification asks.
+pub fn write_replaygain(path: &Path, gain: &ReplayGain) -> Result<()> {
+    let tagged = Probe::open(path)
+        .and_then(|p| p.guess_file_type()?.read())
+        .with_context(|| format!("Reading tags from {}", path.display()))?;
+    let tag_type = tagged.primary_tag_type();
+    let mut tag = tagged
+        .primary_tag()
+        .cloned()
+        .unwrap_or_else(|| Tag::new(tag_type));
+
+    let keys = [
+        ItemKey::ReplayGainTrackGain,
+        ItemKey::ReplayGainTrackPeak,
+        ItemKey::ReplayGainAlbumGain,
+        ItemKey::ReplayGainAlbumPeak,
+        ItemKey::R128TrackGain,
+        ItemKey::R128AlbumGain,
+    ];
+    for key in keys {
+        tag.remove_key(key);
+    }
+
+    if tagged.file_type() == FileType::Opus {
+        // Q7.8 fixed point, relative to the R128 reference
+        let q78 = |gain_db: f64| {
+            let db = gain_db + R128_REFERENCE_LUFS - REFERENCE_LUFS;
+            ((db * 256.0).round() as i64)
+                .clamp(i16::MIN as i64, i16::MAX as i64)
+                .to_string()
+        };
+        tag.insert_text(ItemKey::R128TrackGain, q78(gain.track_gain_db));
+        if let Some(album) = gain.album_gain_db {
+            tag.insert_text(ItemKey::R128AlbumGain, q78(album));
+        }
+    } else {
+        tag.insert_text(
+            ItemKey::ReplayGainTrackGain,
+            format!("{:.2} dB", gain.track_gain_db),
+        );
+        tag.insert_text(
+            ItemKey::ReplayGainTrackPeak,
+            format!("{:.6}", gain.track_peak),
+        );
+        if let (Some(album), Some(peak)) = (gain.album_gain_db, gain.album_peak) {
+            tag.insert_text(ItemKey::ReplayGainAlbumGain, format!("{album:.2} dB"));
+            tag.insert_text(ItemKey::ReplayGainAlbumPeak, format!("{peak:.6}"));
+        }
+    }
+
+    tag.save_to_path(path, WriteOptions::default())
+        .with_context(|| format!("Writing tags to {}", path.display()))
+}
+
+/// Genre tag of `path`, if it has one.
+pub fn read_genre(path: &Path) -> Option<String> {
+    use lofty::tag::Accessor;
+
+    let tagged = lofty::read_from_path(path).ok()?;
+    let tag = tagged.primary_tag().or_else(|| tagged.first_tag())?;
+    tag.genre().map(|g| g.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encode::{encode_file, EncodeOptions};
+    use crate::types::AudioFormat;
+    use lofty::picture::{MimeType, Picture, PictureType};
+    use lofty::tag::{Accessor, TagType};
+
+    fn write_test_wav(path: &Path) {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for i in 0..4410 {
+            let s = ((i % 100) as i16 - 50) * 100;
+            writer.write_sample(s).unwrap();
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn test_tags_carried_to_flac_with_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("in.wav");
+        let flac = dir.path().join("out.flac");
+        write_test_wav(&wav);
+
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.set_title("Song".into());
+        tag.set_album("Draft".into());
+        tag.push_picture(
+            Picture::unchecked(vec![0x89, b'P', b'N', b'G'])
+                .pic_type(PictureType::CoverFront)
+                .mime_type(MimeType::Png)
+                .build(),
+        );
+        tag.save_to_path(&wav, WriteOptions::default()).unwrap();
+
+        let opts = EncodeOptions {
+            bit_depth: 16,
+            ..Default::default()
+        };
+        encode_file(&wav, &flac, AudioFormat::Flac, &opts).unwrap();
+        let overrides = TagOverrides {
+            album: Some("Final".into()),
+            isrc: Some("USRC17607839".into()),
+            ..Default::default()
+        };
+        copy_tags(&wav, &flac, &overrides).unwrap();
+
+        let tagged = lofty::read_from_path(&flac).unwrap();
+        let tag = tagged.primary_tag().unwrap();
+        assert_eq!(tag.title().as_deref(), Some("Song"));
+        assert_eq!(tag.album().as_deref(), Some("Final"));
+        assert_eq!(tag.get_string(ItemKey::Isrc), Some("USRC17607839"));
+        assert_eq!(tag.pictures().len(), 1);
+    }
+
+    #[test]
+    fn test_replaygain_written_to_flac() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("in.wav");
+        let flac = dir.path().join("out.flac");
+        write_test_wav(&wav);
+        let opts = EncodeOptions {
+            bit_depth: 16,
+            ..Default::default()
+        };
+        encode_file(&wav, &flac, AudioFormat::Flac, &opts).unwrap();
+
+        let gain = ReplayGain {
+            track_gain_db: -4.5,
+            track_peak: 0.5,
+            album_gain_db: Some(-3.25),
+            album_peak: Some(0.75),
+        };
+        write_replaygain(&flac, &gain).unwrap();
+
+        let tagged = lofty::read_from_path(&flac).unwrap();
+        let tag = tagged.primary_tag().unwrap();
+        assert_eq!(
+            tag.get_string(ItemKey::ReplayGainTrackGain),
+            Some("-4.50 dB")
+        );
+        assert_eq!(
+            tag.get_string(ItemKey::ReplayGainTrackPeak),
+            Some("0.500000")
+        );
+        assert_eq!(
+            tag.get_string(ItemKey::ReplayGainAlbumGain),
+            Some("-3.25 dB")
+        );
+    }
+
+    #[test]
+    fn test_untagged_input_leaves_output_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let wav = dir.path().join("in.wav");
+        let out = dir.path().join("out.wav");
+        write_test_wav(&wav);
+        std::fs::copy(&wav, &out).unwrap();
+
+        copy_tags(&wav, &out, &TagOverrides::default()).unwrap();
+        assert_eq!(std::fs::read(&wav).unwrap(), std::fs::read(&out).unwrap());
+    }
+}
@@ -0,0 +1,395 @@
+//! Local ML models downloaded from the HuggingFace Hub.
+//!
+//! Models are declared in `[backends.local_ml.models.<name>]` and stored in
+//! `<config dir>/models/<name>`. Every downloaded file is verified before the
+//! model is installed: against the SHA-256 pinned in the config if there is
+//! one, otherwise against the hash the hub publishes for it (the LFS SHA-256
+//! for large files, the git blob SHA-1 for small ones). A download is staged
+//! in a hidden directory and only moved into place once all files check out.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use crate::backends::ai::http_client;
+use crate::config::{Config, ModelSource};
+use crate::error::MasteringError;
+
+/// Hub base URL; the `HF_ENDPOINT` environment variable overrides it.
+pub const HUB_URL: &str = "https://huggingface.co";
+
+/// A configured or installed model.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelStatus {
+    pub name: String,
+    /// Where the model comes from, if it is configured.
+    pub source: Option<ModelSource>,
+    pub installed: bool,
+    pub path: PathBuf,
+    /// Total size of the installed files.
+    pub size_bytes: u64,
+}
+
+/// Checksum a downloaded file must match.
+#[derive(Debug, Clone, PartialEq)]
+enum Checksum {
+    Sha256(String),
+    /// Git blob id, as the hub reports for files not stored in LFS.
+    GitBlobSha1(String),
+}
+
+/// A file entry of the hub's tree API.
+#[derive(Debug, Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(default)]
+    oid: String,
+    #[serde(default)]
+    lfs: Option<LfsInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LfsInfo {
+    oid: String,
+}
+
+/// Directory of installed models.
+#[derive(Debug, Clone)]
+pub struct ModelStore {
+    dir: PathBuf,
+}
+
+impl ModelStore {
+    /// Create a store that keeps models in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Default location (`<config dir>/models`).
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("models"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Directory of the model called `name`, installed or not.
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    pub fn is_installed(&self, name: &str) -> bool {
+        validate_name(name).is_ok() && self.path(name).is_dir()
+    }
+
+    /// Configured models and any others found installed, by name.
+    pub fn list(&self, configured: &BTreeMap<String, ModelSource>) -> Result<Vec<ModelStatus>> {
+        let mut names: Vec<String> = configured.keys().cloned().collect();
+        if self.dir.is_dir() {
+            for entry in std::fs::read_dir(&self.dir)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if entry.path().is_dir() && !name.starts_with('.') && !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names.sort();
+
+        Ok(names
+            .into_iter()
+            .map(|name| {
+                let path = self.path(&name);
+                ModelStatus {
+                    installed: path.is_dir(),
+                    size_bytes: dir_size(&path),
+                    source: configured.get(&name).cloned(),
+                    path,
+                    name,
+                }
+            })
+            .collect())
+    }
+
+    /// The first `.onnx` file of the installed model `name`.
+    pub fn onnx_file(&self, name: &str) -> Option<PathBuf> {
+        if !self.is_installed(name) {
+            return None;
+        }
+        let mut files: Vec<PathBuf> = std::fs::read_dir(self.path(name))
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "onnx"))
+            .collect();
+        files.sort();
+        files.into_iter().next()
+    }
+
+    /// Delete the installed model `name`.
+    pub fn remove(&self, name: &str) -> Result<()> {
+        validate_name(name)?;
+        let path = self.path(name);
+        anyhow::ensure!(path.is_dir(), "Model '{name}' is not installed");
+        std::fs::remove_dir_all(&path).with_context(|| format!("Removing {}", path.display()))?;
+        info!("Removed model {name}");
+        Ok(())
+    }
+
+    /// Download and verify the configured model `name`, replacing any
+    /// installed copy. Returns the model's directory.
+    ///
+    /// `on_progress` receives the file being fetched, the bytes received so
+    /// far and the file's size when the hub reports one.
+    pub async fn download(
+        &self,
+        name: &str,
+        config: &Config,
+        mut on_progress: impl FnMut(&str, u64, Option<u64>),
+    ) -> Result<PathBuf> {
+        validate_name(name)?;
+        if config.general.offline {
+            return Err(MasteringError::InvalidConfig {
+                message: format!("Cannot download model '{name}' in offline mode"),
+                config_key: Some("general.offline".into()),
+            }
+            .into());
+        }
+        let source = config.backends.local_ml.models.get(name).ok_or_else(|| {
+            MasteringError::InvalidConfig {
+                message: format!("Model '{name}' is not configured"),
+                config_key: Some(format!("backends.local_ml.models.{name}")),
+            }
+        })?;
+        anyhow::ensure!(
+            !source.files.is_empty(),
+            "Model '{name}' lists no files to download"
+        );
+        let client = http_client(&config.ai.network, None)?;
+        let hub = std::env::var("HF_ENDPOINT").unwrap_or_else(|_| HUB_URL.to_string());
+        let hub = hub.trim_end_matches('/');
+        let token = std::env::var("HF_TOKEN").ok().filter(|t| !t.is_empty());
+
+        let published = if source.files.iter().all(|f| source.sha256.contains_key(f)) {
+            BTreeMap::new()
+        } else {
+            published_checksums(&client, hub, source, token.as_deref()).await?
+        };
+
+        let staging = self.dir.join(format!(".{name}.partial"));
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+        std::fs::create_dir_all(&staging)
+            .with_context(|| format!("Creating {}", staging.display()))?;
+
+        for file in &source.files {
+            let checksum = match source.sha256.get(file) {
+                Some(sha256) => Checksum::Sha256(sha256.to_lowercase()),
+                None => published.get(file).cloned().with_context(|| {
+                    format!(
+                        "{} has no file {file} at revision {}",
+                        source.repo, source.revision
+                    )
+                })?,
+            };
+            let target = staging.join(file);
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let url = format!("{hub}/{}/resolve/{}/{file}", source.repo, source.revision);
+            info!("Downloading {url}");
+            let mut request = client.get(&url);
+            if let Some(token) = &token {
+                request = request.bearer_auth(token);
+            }
+            let mut response = request
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .with_context(|| format!("Downloading {file} from {}", source.repo))?;
+            let total = response.content_length();
+            let mut out = tokio::fs::File::create(&target).await?;
+            let mut received = 0u64;
+            while let Some(chunk) = response.chunk().await? {
+                out.write_all(&chunk).await?;
+                received += chunk.len() as u64;
+                on_progress(file, received, total);
+            }
+            out.flush().await?;
+            drop(out);
+
+            let path = target.clone();
+            let expected = checksum.clone();
+            let matches = tokio::task::spawn_blocking(move || checksum_matches(&path, &expected))
+                .await
+                .context("Checksum task failed")??;
+            if !matches {
+                let _ = std::fs::remove_dir_all(&staging);
+                anyhow::bail!(
+                    "{file} of model '{name}' failed checksum verification; nothing was installed"
+                );
+            }
+        }
+
+        let path = self.path(name);
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        std::fs::rename(&staging, &path)
+            .with_context(|| format!("Installing model into {}", path.display()))?;
+        info!("Installed model {name} into {}", path.display());
+        Ok(path)
+    }
+}
+
+/// Checksums the hub publishes for the files of `source`.
+async fn published_checksums(
+    client: &reqwest::Client,
+    hub: &str,
+    source: &ModelSource,
+    token: Option<&str>,
+) -> Result<BTreeMap<String, Checksum>> {
+    let url = format!(
+        "{hub}/api/models/{}/tree/{}?recursive=true",
+        source.repo, source.revision
+    );
+    let mut request = client.get(&url);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let entries: Vec<TreeEntry> = request
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Listing files of {} at {}", source.repo, source.revision))?
+        .json()
+        .await
+        .context("Parsing the hub's file list")?;
+
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let checksum = match entry.lfs {
+                Some(lfs) => Checksum::Sha256(lfs.oid),
+                None => Checksum::GitBlobSha1(entry.oid),
+            };
+            (entry.path, checksum)
+        })
+        .collect())
+}
+
+fn checksum_matches(path: &Path, expected: &Checksum) -> Result<bool> {
+    let actual = match expected {
+        Checksum::Sha256(_) => Checksum::Sha256(hash_file(path, Sha256::new())?),
+        Checksum::GitBlobSha1(_) => {
+            let mut hasher = Sha1::new();
+            hasher.update(format!("blob {}\0", std::fs::metadata(path)?.len()));
+            Checksum::GitBlobSha1(hash_file(path, hasher)?)
+        }
+    };
+    Ok(&actual == expected)
+}
+
+fn hash_file(path: &Path, mut hasher: impl Digest) -> Result<String> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Opening {}", path.display()))?;
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+/// Model names become directory names, so keep them to a safe alphabet.
+fn validate_name(name: &str) -> Result<()> {
+    anyhow::ensure!(
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')),
+        "Invalid model name '{name}': use letters, digits, '-', '_' and '.'"
+    );
+    Ok(())
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, "hello\n").unwrap();
+
+        let sha256 = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+        assert!(checksum_matches(&path, &Checksum::Sha256(sha256.into())).unwrap());
+        // `git hash-object` of the same content
+        let blob = "ce013625030ba8dba906f756967f9e9ca394464a";
+        assert!(checksum_matches(&path, &Checksum::GitBlobSha1(blob.into())).unwrap());
+        assert!(!checksum_matches(&path, &Checksum::Sha256(blob.into())).unwrap());
+    }
+
+    #[test]
+    fn test_list_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ModelStore::new(dir.path());
+        std::fs::create_dir_all(store.path("local-only")).unwrap();
+        std::fs::write(store.path("local-only").join("model.onnx"), [0u8; 10]).unwrap();
+        let configured = BTreeMap::from([(
+            "remote".to_string(),
+            ModelSource {
+                repo: "org/remote".into(),
+                revision: "main".into(),
+                files: vec!["model.onnx".into()],
+                sha256: BTreeMap::new(),
+            },
+        )]);
+
+        let models = store.list(&configured).unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].name, "local-only");
+        assert!(models[0].installed && models[0].source.is_none());
+        assert_eq!(models[0].size_bytes, 10);
+        assert!(!models[1].installed && models[1].source.is_some());
+        assert_eq!(
+            store.onnx_file("local-only"),
+            Some(store.path("local-only").join("model.onnx"))
+        );
+
+        store.remove("local-only").unwrap();
+        assert!(!store.is_installed("local-only"));
+        assert!(store.remove("../escape").is_err());
+    }
+}
@@ -0,0 +1,727 @@
+//! Waveform peak files.
+//!
+//! Drawing a waveform needs only the lowest and highest sample and the RMS
+//! level of each pixel, so every channel of a file, and its mono mixdown, is
+//! reduced once to min/max/RMS triplets at several zoom levels, each half as
+//! detailed as the one before, and kept as a small binary `.peaks` file in
+//! the cache directory. Waveform requests for any time range are then served
+//! from the closest level without decoding the audio again; only views
+//! zoomed in further than the finest level go back to the file.
+//!
+//! Like analysis cache entries, a peak file records the input's fingerprint
+//! and the [`PEAKS_VERSION`] it was written with, and is rebuilt when either
+//! changes.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::analysis::decode::{decode_audio_range, AudioStream, DecodedAudio};
+use crate::cache::{compute_file_hash, fnv1a};
+use crate::types::TimeRange;
+
+/// Version of the peak file format. Bump when the layout or the levels
+/// change so existing files are rebuilt.
+pub const PEAKS_VERSION: u32 = 2;
+
+/// Samples per peak of the finest level.
+pub const BASE_SAMPLES_PER_PEAK: usize = 256;
+
+/// Levels are halved until one has at most this many peaks.
+const MIN_LEVEL_PEAKS: usize = 1024;
+
+/// Start of every peak file.
+const MAGIC: &[u8; 4] = b"AMPK";
+
+/// Which channels a waveform is drawn for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelSelection {
+    /// The mono mixdown.
+    #[default]
+    Mix,
+    /// Every channel separately.
+    All,
+    /// A single channel, counting from 0.
+    Channel(usize),
+}
+
+/// Waveform of one channel, or of the mixdown.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelWaveform {
+    /// Channel name, e.g. "Left"; "Mix" for the mixdown of several channels.
+    pub name: String,
+    /// Evenly spaced (min, max, RMS) triplets.
+    pub points: Vec<[f32; 3]>,
+}
+
+/// Waveform of a time range of a file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Waveform {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    /// Seconds covered by each point.
+    pub point_secs: f64,
+    pub channels: Vec<ChannelWaveform>,
+}
+
+/// Min/max/RMS triplets of a file at one zoom level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeakLevel {
+    /// Samples covered by each triplet.
+    pub samples_per_peak: usize,
+    /// Triplets of each channel, followed by those of the mixdown when there
+    /// is more than one channel.
+    pub tracks: Vec<Vec<[f32; 3]>>,
+}
+
+/// Min/max/RMS peaks of a file at several zoom levels, finest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peaks {
+    pub sample_rate: u32,
+    /// Length of the file in frames.
+    pub frames: u64,
+    pub channel_names: Vec<String>,
+    pub levels: Vec<PeakLevel>,
+}
+
+impl Peaks {
+    /// Peaks of decoded audio, `samples_per_peak` samples to the finest level.
+    pub fn from_audio(audio: &DecodedAudio, samples_per_peak: usize) -> Self {
+        let mut builder = PeakBuilder::new(audio.channels as usize, samples_per_peak);
+        builder.push(&audio.samples);
+        builder.finish(audio.sample_rate, channel_names(audio))
+    }
+
+    /// Peaks of the file at `path`, read in chunks so the whole file is
+    /// never held in memory.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let mut stream = AudioStream::open(path)?;
+        let channels = stream.channels().max(1) as usize;
+        let names = (0..channels)
+            .map(|ch| stream.layout().channel_name(ch))
+            .collect();
+        let mut builder = PeakBuilder::new(channels, BASE_SAMPLES_PER_PEAK);
+        while let Some(chunk) = stream.next_chunk()? {
+            builder.push(chunk);
+        }
+        Ok(builder.finish(stream.sample_rate(), names))
+    }
+
+    /// Index of the mixdown in each level's tracks.
+    fn mix_track(&self) -> usize {
+        if self.channel_names.len() > 1 {
+            self.channel_names.len()
+        } else {
+            0
+        }
+    }
+
+    /// `num_points` triplets over `range` (or the whole file) for each
+    /// selected channel, or `None` when the points are closer together than
+    /// the finest level and the audio has to be decoded instead. Channels
+    /// the file does not have are left out.
+    pub fn waveform(
+        &self,
+        num_points: usize,
+        range: Option<&TimeRange>,
+        selection: ChannelSelection,
+    ) -> Option<Waveform> {
+        let to_frame =
+            |secs: f64| ((secs.max(0.0) * self.sample_rate as f64) as u64).min(self.frames);
+        let (start, end) = match range {
+            Some(range) => (
+                to_frame(range.start_secs),
+                range.end_secs.map_or(self.frames, to_frame),
+            ),
+            None => (0, self.frames),
+        };
+        let total = end.saturating_sub(start);
+        let num_points = num_points.max(1);
+
+        let tracks: Vec<(usize, String)> = match selection {
+            ChannelSelection::Mix if self.channel_names.len() > 1 => {
+                vec![(self.mix_track(), "Mix".into())]
+            }
+            ChannelSelection::Mix => vec![(0, self.channel_names[0].clone())],
+            ChannelSelection::All => self.channel_names.iter().cloned().enumerate().collect(),
+            ChannelSelection::Channel(ch) => self
+                .channel_names
+                .get(ch)
+                .map(|name| (ch, name.clone()))
+                .into_iter()
+                .collect(),
+        };
+
+        // The coarsest level still at least as detailed as the points
+        let frames_per_point = total as f64 / num_points as f64;
+        let level = if total == 0 {
+            &self.levels[0]
+        } else {
+            self.levels
+                .iter()
+                .rev()
+                .find(|level| level.samples_per_peak as f64 <= frames_per_point)?
+        };
+
+        let spp = level.samples_per_peak as u64;
+        let channels = tracks
+            .into_iter()
+            .map(|(track, name)| {
+                let peaks = &level.tracks[track];
+                let points = if total == 0 {
+                    Vec::new()
+                } else {
+                    (0..num_points as u64)
+                        .map(|i| {
+                            let from = start + i * total / num_points as u64;
+                            let to = start + (i + 1) * total / num_points as u64;
+                            let first = (from / spp) as usize;
+                            let last = (to.div_ceil(spp) as usize).clamp(first + 1, peaks.len());
+                            merge(&peaks[first.min(last - 1)..last])
+                        })
+                        .collect()
+                };
+                ChannelWaveform { name, points }
+            })
+            .collect();
+
+        Some(Waveform {
+            start_secs: start as f64 / self.sample_rate as f64,
+            end_secs: end as f64 / self.sample_rate as f64,
+            point_secs: frames_per_point / self.sample_rate as f64,
+            channels,
+        })
+    }
+
+    /// Serialize for a file fingerprinted `file_hash`. Values are stored as
+    /// 16-bit integers, min and max rounded outward so the drawn waveform
+    /// never shrinks.
+    fn to_bytes(&self, file_hash: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let put_u32 = |bytes: &mut Vec<u8>, v: u32| bytes.extend_from_slice(&v.to_le_bytes());
+        let put_str = |bytes: &mut Vec<u8>, s: &str| {
+            put_u32(bytes, s.len() as u32);
+            bytes.extend_from_slice(s.as_bytes());
+        };
+        bytes.extend_from_slice(MAGIC);
+        put_u32(&mut bytes, PEAKS_VERSION);
+        put_str(&mut bytes, file_hash);
+        put_u32(&mut bytes, self.sample_rate);
+        bytes.extend_from_slice(&self.frames.to_le_bytes());
+        put_u32(&mut bytes, self.channel_names.len() as u32);
+        for name in &self.channel_names {
+            put_str(&mut bytes, name);
+        }
+        put_u32(&mut bytes, self.levels.len() as u32);
+        for level in &self.levels {
+            put_u32(&mut bytes, level.samples_per_peak as u32);
+            put_u32(&mut bytes, level.tracks.len() as u32);
+            for track in &level.tracks {
+                put_u32(&mut bytes, track.len() as u32);
+                for [min, max, rms] in track {
+                    let quantize = |v: f32, round: fn(f32) -> f32| {
+                        round(v.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+                    };
+                    bytes.extend_from_slice(&quantize(*min, f32::floor).to_le_bytes());
+                    bytes.extend_from_slice(&quantize(*max, f32::ceil).to_le_bytes());
+                    bytes.extend_from_slice(&quantize(*rms, f32::round).to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Parse a peak file, returning `None` if it is malformed, from another
+    /// version, or was written for a different `file_hash`.
+    fn from_bytes(bytes: &[u8], file_hash: &str) -> Option<Self> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC || reader.u32()? != PEAKS_VERSION {
+            return None;
+        }
+        if reader.string()? != file_hash {
+            return None;
+        }
+        let sample_rate = reader.u32()?;
+        let frames = u64::from_le_bytes(reader.take(8)?.try_into().ok()?);
+        let channel_names = (0..reader.u32()?)
+            .map(|_| reader.string().map(str::to_string))
+            .collect::<Option<Vec<_>>>()?;
+        if channel_names.is_empty() {
+            return None;
+        }
+        let mut levels = Vec::new();
+        for _ in 0..reader.u32()? {
+            let samples_per_peak = reader.u32()? as usize;
+            let mut tracks = Vec::new();
+            for _ in 0..reader.u32()? {
+                let count = reader.u32()? as usize;
+                let data = reader.take(count.checked_mul(6)?)?;
+                let value = |b: &[u8]| i16::from_le_bytes([b[0], b[1]]) as f32 / i16::MAX as f32;
+                tracks.push(
+                    data.chunks_exact(6)
+                        .map(|p| [value(&p[..2]), value(&p[2..4]), value(&p[4..])])
+                        .collect(),
+                );
+            }
+            levels.push(PeakLevel {
+                samples_per_peak,
+                tracks,
+            });
+        }
+        let expected_tracks = channel_names.len() + usize::from(channel_names.len() > 1);
+        if levels.is_empty() || levels.iter().any(|l| l.tracks.len() != expected_tracks) {
+            return None;
+        }
+        Some(Self {
+            sample_rate,
+            frames,
+            channel_names,
+            levels,
+        })
+    }
+}
+
+fn channel_names(audio: &DecodedAudio) -> Vec<String> {
+    (0..audio.channels.max(1) as usize)
+        .map(|ch| audio.layout.channel_name(ch))
+        .collect()
+}
+
+/// Combine consecutive triplets into one.
+fn merge(peaks: &[[f32; 3]]) -> [f32; 3] {
+    let (min, max, sum_sq) = peaks.iter().fold(
+        (f32::INFINITY, f32::NEG_INFINITY, 0.0f64),
+        |(min, max, sum_sq), p| (min.min(p[0]), max.max(p[1]), sum_sq + (p[2] as f64).powi(2)),
+    );
+    [min, max, (sum_sq / peaks.len().max(1) as f64).sqrt() as f32]
+}
+
+/// Min, max and sum of squares of the samples of one peak so far.
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    min: f32,
+    max: f32,
+    sum_sq: f64,
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self {
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            sum_sq: 0.0,
+        }
+    }
+}
+
+impl Accumulator {
+    fn push(&mut self, sample: f32) {
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.sum_sq += (sample as f64) * (sample as f64);
+    }
+
+    fn peak(&self, samples: usize) -> [f32; 3] {
+        [
+            self.min,
+            self.max,
+            (self.sum_sq / samples as f64).sqrt() as f32,
+        ]
+    }
+}
+
+/// Builds the finest level from interleaved chunks.
+#[derive(Debug)]
+struct PeakBuilder {
+    channels: usize,
+    samples_per_peak: usize,
+    tracks: Vec<Vec<[f32; 3]>>,
+    current: Vec<Accumulator>,
+    in_current: usize,
+    frames: u64,
+}
+
+impl PeakBuilder {
+    fn new(channels: usize, samples_per_peak: usize) -> Self {
+        let channels = channels.max(1);
+        let track_count = channels + usize::from(channels > 1);
+        Self {
+            channels,
+            samples_per_peak: samples_per_peak.max(1),
+            tracks: vec![Vec::new(); track_count],
+            current: vec![Accumulator::default(); track_count],
+            in_current: 0,
+            frames: 0,
+        }
+    }
+
+    fn push(&mut self, interleaved: &[f32]) {
+        for frame in interleaved.chunks_exact(self.channels) {
+            for (acc, &sample) in self.current.iter_mut().zip(frame) {
+                acc.push(sample);
+            }
+            if self.channels > 1 {
+                self.current[self.channels].push(frame.iter().sum::<f32>() / self.channels as f32);
+            }
+            self.in_current += 1;
+            self.frames += 1;
+            if self.in_current == self.samples_per_peak {
+                self.flush();
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        for (track, acc) in self.tracks.iter_mut().zip(&mut self.current) {
+            track.push(acc.peak(self.in_current));
+            *acc = Accumulator::default();
+        }
+        self.in_current = 0;
+    }
+
+    fn finish(mut self, sample_rate: u32, channel_names: Vec<String>) -> Peaks {
+        if self.in_current > 0 {
+            self.flush();
+        }
+        let mut levels = vec![PeakLevel {
+            samples_per_peak: self.samples_per_peak,
+            tracks: self.tracks,
+        }];
+        while let Some(last) = levels
+            .last()
+            .filter(|l| l.tracks[0].len() > MIN_LEVEL_PEAKS)
+        {
+            let tracks = last
+                .tracks
+                .iter()
+                .map(|track| track.chunks(2).map(merge).collect())
+                .collect();
+            levels.push(PeakLevel {
+                samples_per_peak: last.samples_per_peak * 2,
+                tracks,
+            });
+        }
+        Peaks {
+            sample_rate,
+            frames: self.frames,
+            channel_names,
+            levels,
+        }
+    }
+}
+
+/// Little-endian reader over a byte slice.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn string(&mut self) -> Option<&'a str> {
+        let len = self.u32()? as usize;
+        std::str::from_utf8(self.take(len)?).ok()
+    }
+}
+
+/// Disk-backed store of peak files.
+#[derive(Debug, Clone)]
+pub struct PeakCache {
+    dir: PathBuf,
+}
+
+impl PeakCache {
+    /// Create a cache that stores peak files in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Default cache location (`<cache dir>/mastering/peaks`).
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("mastering")
+            .join("peaks")
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, path: &Path) -> PathBuf {
+        let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let key = fnv1a(canonical.to_string_lossy().as_bytes());
+        self.dir.join(format!("{key:016x}.peaks"))
+    }
+
+    /// Get the peaks of `path` if the file is unchanged since they were stored.
+    pub fn get(&self, path: &Path) -> Option<Peaks> {
+        let file_hash = compute_file_hash(path).ok()?;
+        let bytes = std::fs::read(self.entry_path(path)).ok()?;
+        let peaks = Peaks::from_bytes(&bytes, &file_hash);
+        if peaks.is_none() {
+            debug!("Stale peak file for {}", path.display());
+        }
+        peaks
+    }
+
+    /// Store the peaks of `path`.
+    pub fn put(&self, path: &Path, peaks: &Peaks) -> std::io::Result<()> {
+        let bytes = peaks.to_bytes(&compute_file_hash(path)?);
+        std::fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first so readers never see a partial file
+        let entry_path = self.entry_path(path);
+        let tmp_path = entry_path.with_extension("peaks.tmp");
+        std::fs::write(&tmp_path, bytes)?;
+        std::fs::rename(&tmp_path, &entry_path)
+    }
+
+    /// Get the peaks of `path`, building and storing them on first access.
+    /// A failure to store them is logged, not returned.
+    pub fn get_or_build(&self, path: &Path) -> Result<Peaks> {
+        if let Some(peaks) = self.get(path) {
+            return Ok(peaks);
+        }
+        let peaks = Peaks::from_file(path)?;
+        if let Err(e) = self.put(path, &peaks) {
+            debug!("Could not store peak file for {}: {e}", path.display());
+        }
+        Ok(peaks)
+    }
+
+    /// Remove all peak files.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Global peak cache instance.
+static GLOBAL_PEAK_CACHE: std::sync::OnceLock<PeakCache> = std::sync::OnceLock::new();
+
+/// Get the global peak cache in the default location.
+pub fn global_peak_cache() -> &'static PeakCache {
+    GLOBAL_PEAK_CACHE.get_or_init(|| PeakCache::new(PeakCache::default_dir()))
+}
+
+/// Waveform of `path` with `num_points` points over `range` (or the whole
+/// file) for the selected channels.
+///
+/// Served from the peak file in `cache`, built on first access, and from
+/// the decoded range when zoomed in past its finest level. Without a cache
+/// the peaks are built on every call.
+pub fn waveform(
+    path: &Path,
+    num_points: usize,
+    range: Option<&TimeRange>,
+    selection: ChannelSelection,
+    cache: Option<&PeakCache>,
+) -> Result<Waveform> {
+    if let Some(range) = range {
+        range.validate()?;
+    }
+    let peaks = match cache {
+        Some(cache) => cache.get_or_build(path)?,
+        None => Peaks::from_file(path)?,
+    };
+    if let ChannelSelection::Channel(ch) = selection {
+        anyhow::ensure!(
+            ch < peaks.channel_names.len(),
+            "Channel {} does not exist; the file has {} channel(s)",
+            ch + 1,
+            peaks.channel_names.len()
+        );
+    }
+    if let Some(waveform) = peaks.waveform(num_points, range, selection) {
+        return Ok(waveform);
+    }
+
+    // Zoomed in past the finest level: one point per sample at most
+    let range = range.cloned().unwrap_or(TimeRange {
+        start_secs: 0.0,
+        end_secs: None,
+    });
+    let audio = decode_audio_range(path, &range)?;
+    let exact = Peaks::from_audio(&audio, 1);
+    let mut waveform = exact
+        .waveform(
+            num_points.min(exact.frames.max(1) as usize),
+            None,
+            selection,
+        )
+        .expect("one sample per peak serves any number of points");
+    waveform.start_secs += range.start_secs;
+    waveform.end_secs += range.start_secs;
+    Ok(waveform)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(frames: usize) -> Vec<f32> {
+        (0..frames)
+            .map(|i| (i as f32 / frames as f32) * 2.0 - 1.0)
+            .collect()
+    }
+
+    /// Stereo audio with the ramp on the left and a quarter of it, inverted,
+    /// on the right.
+    fn stereo_ramp(frames: usize, sample_rate: u32) -> DecodedAudio {
+        let samples = ramp(frames)
+            .into_iter()
+            .flat_map(|s| [s, -0.25 * s])
+            .collect();
+        DecodedAudio::new(samples, sample_rate, 2)
+    }
+
+    #[test]
+    fn test_levels_halve_down_to_overview() {
+        let audio = DecodedAudio::new(ramp(BASE_SAMPLES_PER_PEAK * 5000 + 10), 44100, 1);
+        let peaks = Peaks::from_audio(&audio, BASE_SAMPLES_PER_PEAK);
+        assert_eq!(peaks.frames, BASE_SAMPLES_PER_PEAK as u64 * 5000 + 10);
+        assert_eq!(peaks.channel_names, ["Mono"]);
+        assert_eq!(peaks.levels[0].tracks[0].len(), 5001);
+        let last = peaks.levels.last().unwrap();
+        assert!(last.tracks[0].len() <= MIN_LEVEL_PEAKS);
+        assert_eq!(
+            last.samples_per_peak,
+            BASE_SAMPLES_PER_PEAK << (peaks.levels.len() - 1)
+        );
+        // Every level spans the whole signal
+        for level in &peaks.levels {
+            assert_eq!(level.tracks.len(), 1);
+            assert_eq!(level.tracks[0][0][0], -1.0);
+            assert!(level.tracks[0].last().unwrap()[1] > 0.99);
+        }
+    }
+
+    #[test]
+    fn test_waveform_per_channel_and_range() {
+        let frames = BASE_SAMPLES_PER_PEAK * 4000;
+        let peaks = Peaks::from_audio(&stereo_ramp(frames, 1000), BASE_SAMPLES_PER_PEAK);
+
+        let overview = peaks.waveform(100, None, ChannelSelection::All).unwrap();
+        assert_eq!(overview.channels.len(), 2);
+        assert_eq!(overview.channels[1].name, "Right");
+        let left = &overview.channels[0].points;
+        assert_eq!(left.len(), 100);
+        assert_eq!(left[0][0], -1.0);
+        assert!(left
+            .windows(2)
+            .all(|w| w[1][0] >= w[0][0] && w[1][1] >= w[0][1]));
+        // RMS lies between the extremes of a one-signed stretch
+        assert!(left[0][2] < 1.0 && left[0][2] > left[0][1].abs());
+
+        let right = peaks
+            .waveform(100, None, ChannelSelection::Channel(1))
+            .unwrap();
+        assert_eq!(right.channels.len(), 1);
+        assert!((right.channels[0].points[0][1] - 0.25).abs() < 1e-6);
+
+        // The mixdown of the second half starts near zero
+        let duration = frames as f64 / 1000.0;
+        let range = TimeRange {
+            start_secs: duration / 2.0,
+            end_secs: None,
+        };
+        let zoomed = peaks
+            .waveform(200, Some(&range), ChannelSelection::Mix)
+            .unwrap();
+        assert_eq!(zoomed.channels[0].name, "Mix");
+        assert_eq!(zoomed.channels[0].points.len(), 200);
+        assert!(zoomed.channels[0].points[0][0].abs() < 0.01);
+        assert_eq!(zoomed.start_secs, duration / 2.0);
+        assert_eq!(zoomed.end_secs, duration);
+
+        // Closer than the finest level needs the audio itself
+        let tiny = TimeRange {
+            start_secs: 0.0,
+            end_secs: Some(0.5),
+        };
+        assert_eq!(
+            peaks.waveform(100, Some(&tiny), ChannelSelection::Mix),
+            None
+        );
+    }
+
+    #[test]
+    fn test_zoomed_in_waveform_decodes_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ramp.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for s in stereo_ramp(8000, 8000).samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let cache = PeakCache::new(dir.path().join("peaks"));
+        let range = TimeRange {
+            start_secs: 0.5,
+            end_secs: Some(0.51),
+        };
+        let zoomed = waveform(
+            &path,
+            1000,
+            Some(&range),
+            ChannelSelection::All,
+            Some(&cache),
+        )
+        .unwrap();
+        assert_eq!(zoomed.channels.len(), 2);
+        // 80 frames in the range, so one point per frame
+        assert_eq!(zoomed.channels[0].points.len(), 80);
+        assert!((zoomed.start_secs - 0.5).abs() < 1e-9);
+        let [min, max, rms] = zoomed.channels[0].points[0];
+        assert_eq!(min, max);
+        assert_eq!(rms, min.abs());
+        assert!(cache.get(&path).is_some());
+
+        assert!(waveform(&path, 10, None, ChannelSelection::Channel(2), Some(&cache)).is_err());
+    }
+
+    #[test]
+    fn test_round_trip_and_invalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let audio_path = dir.path().join("song.wav");
+        std::fs::write(&audio_path, b"not really audio").unwrap();
+
+        let cache = PeakCache::new(dir.path().join("peaks"));
+        let audio = DecodedAudio::new(vec![0.5, -0.25, 1.5, -0.1], 8000, 2);
+        let peaks = Peaks::from_audio(&audio, BASE_SAMPLES_PER_PEAK);
+        assert!(cache.get(&audio_path).is_none());
+        cache.put(&audio_path, &peaks).unwrap();
+
+        let stored = cache.get(&audio_path).unwrap();
+        assert_eq!(stored.frames, 2);
+        assert_eq!(stored.channel_names, ["Left", "Right"]);
+        assert_eq!(stored.levels[0].tracks.len(), 3);
+        let [min, max, _] = stored.levels[0].tracks[0][0];
+        // Rounded outward and clamped to full scale
+        assert!(min <= 0.5 && min > 0.4999);
+        assert_eq!(max, 1.0);
+
+        std::fs::write(&audio_path, b"changed audio, longer").unwrap();
+        assert!(cache.get(&audio_path).is_none());
+    }
+}
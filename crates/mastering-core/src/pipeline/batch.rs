@@ -0,0 +1,145 @@
+//! Concurrent batch execution of mastering jobs.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+
+use super::{manifest, run_with_progress, MasteringJob, ProgressReporter};
+use crate::config::Config;
+use crate::types::MasteringResult;
+
+/// Lifecycle state of a job inside a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchJobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    /// Its output was already mastered from the same input and settings.
+    Skipped,
+}
+
+/// Status change of a single job in a batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchStatusUpdate {
+    /// Position of the job in the submitted batch.
+    pub index: usize,
+    pub input_path: PathBuf,
+    pub status: BatchJobStatus,
+    pub error: Option<String>,
+}
+
+fn notify(sender: &Option<mpsc::UnboundedSender<BatchStatusUpdate>>, update: BatchStatusUpdate) {
+    if let Some(sender) = sender {
+        // A dropped receiver just means nobody is listening any more
+        let _ = sender.send(update);
+    }
+}
+
+/// Run `jobs` with at most `concurrency` of them in flight at once.
+///
+/// Each job runs with the config it is paired with. Results are returned in
+/// submission order. Status changes are sent to `status` when provided;
+/// per-job progress goes to each job's reporter.
+///
+/// Each finished master gets a sidecar manifest, so running the batch again
+/// after an interruption skips the outputs that are already done and
+/// returns their earlier results. `force` masters every job regardless.
+pub async fn run_batch(
+    jobs: Vec<(MasteringJob, Config, ProgressReporter)>,
+    concurrency: usize,
+    force: bool,
+    status: Option<mpsc::UnboundedSender<BatchStatusUpdate>>,
+) -> Vec<Result<MasteringResult>> {
+    let total = jobs.len();
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+
+    let mut set = JoinSet::new();
+    for (index, (job, config, progress)) in jobs.into_iter().enumerate() {
+        notify(
+            &status,
+            BatchStatusUpdate {
+                index,
+                input_path: job.input_path.clone(),
+                status: BatchJobStatus::Queued,
+                error: None,
+            },
+        );
+
+        let semaphore = semaphore.clone();
+        let status = status.clone();
+        set.spawn(async move {
+            let _permit = semaphore
+                .acquire_owned()
+                .await
+                .expect("batch semaphore closed");
+
+            if let Some(result) =
+                manifest::completed(&job, &config).filter(|_| !force && !job.dry_run)
+            {
+                notify(
+                    &status,
+                    BatchStatusUpdate {
+                        index,
+                        input_path: job.input_path.clone(),
+                        status: BatchJobStatus::Skipped,
+                        error: None,
+                    },
+                );
+                return (index, Ok(result));
+            }
+
+            notify(
+                &status,
+                BatchStatusUpdate {
+                    index,
+                    input_path: job.input_path.clone(),
+                    status: BatchJobStatus::Running,
+                    error: None,
+                },
+            );
+
+            let result = run_with_progress(&job, &config, &progress).await;
+            if let Ok(ref result) = result {
+                if !job.dry_run {
+                    if let Err(e) = manifest::write(&job, &config, result) {
+                        tracing::warn!("Failed to write the batch manifest: {e:#}");
+                    }
+                }
+            }
+
+            let (job_status, error) = match &result {
+                Ok(_) => (BatchJobStatus::Succeeded, None),
+                Err(e) => (BatchJobStatus::Failed, Some(format!("{e:#}"))),
+            };
+            notify(
+                &status,
+                BatchStatusUpdate {
+                    index,
+                    input_path: job.input_path.clone(),
+                    status: job_status,
+                    error,
+                },
+            );
+
+            (index, result)
+        });
+    }
+
+    let mut results: Vec<Option<Result<MasteringResult>>> = (0..total).map(|_| None).collect();
+    while let Some(joined) = set.join_next().await {
+        match joined {
+            Ok((index, result)) => results[index] = Some(result),
+            Err(e) => tracing::error!("Batch task failed: {e}"),
+        }
+    }
+
+    results
+        .into_iter()
+        .map(|r| r.unwrap_or_else(|| Err(anyhow::anyhow!("Batch task aborted unexpectedly"))))
+        .collect()
+}
@@ -0,0 +1,157 @@
+//! Sidecar manifests recording how a master was made, so a batch that is
+//! run again can skip the outputs it already finished.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::MasteringJob;
+use crate::cache::{compute_file_hash, fnv1a};
+use crate::config::{Config, EncodingConfig, GeneralConfig, RestorationConfig, StemsConfig};
+use crate::types::MasteringResult;
+
+/// What a master was made from, written next to it as
+/// `.<output file name>.mastering.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputManifest {
+    /// Fingerprint of the job settings and the config that shape the output.
+    pub settings: String,
+    /// Fingerprint of the input files when they were mastered.
+    pub input: String,
+    /// Fingerprint of the output when it was written; a master edited or
+    /// replaced since no longer matches.
+    pub output: String,
+    pub result: MasteringResult,
+}
+
+/// The parts of a job and config that change the rendered output.
+#[derive(Serialize)]
+struct Settings<'a> {
+    job: MasteringJob,
+    general: GeneralConfig,
+    encoding: &'a EncodingConfig,
+    stems: &'a StemsConfig,
+    restoration: &'a RestorationConfig,
+}
+
+/// Path of the manifest for `output`.
+pub fn manifest_path(output: &Path) -> PathBuf {
+    let name = output
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    output.with_file_name(format!(".{name}.mastering.json"))
+}
+
+/// Write the manifest for a finished job next to its output.
+pub fn write(job: &MasteringJob, config: &Config, result: &MasteringResult) -> Result<()> {
+    let manifest = OutputManifest {
+        settings: settings_fingerprint(job, config)?,
+        input: input_fingerprint(job)?,
+        output: compute_file_hash(&result.output_path)?,
+        result: result.clone(),
+    };
+    let path = manifest_path(&result.output_path);
+    std::fs::write(&path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("Writing {}", path.display()))
+}
+
+/// Result of an earlier run of `job` whose output is still in place and was
+/// made from the same input and settings, or `None` if it must be mastered.
+pub fn completed(job: &MasteringJob, config: &Config) -> Option<MasteringResult> {
+    let output = job.resolved_output_path(config);
+    let manifest: OutputManifest = std::fs::read_to_string(manifest_path(&output))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())?;
+
+    let matches = manifest.result.output_path == output
+        && compute_file_hash(&output).ok()? == manifest.output
+        && input_fingerprint(job).ok()? == manifest.input
+        && settings_fingerprint(job, config).ok()? == manifest.settings;
+    matches.then_some(manifest.result)
+}
+
+fn settings_fingerprint(job: &MasteringJob, config: &Config) -> Result<String> {
+    // Settings that don't change the audio are left out
+    let mut job = job.clone();
+    job.output_path = None;
+    job.no_cache = false;
+    let mut general = config.general.clone();
+    general.batch_concurrency = 0;
+
+    let settings = Settings {
+        job,
+        general,
+        encoding: &config.encoding,
+        stems: &config.stems,
+        restoration: &config.restoration,
+    };
+    Ok(format!("{:016x}", fnv1a(&serde_json::to_vec(&settings)?)))
+}
+
+fn input_fingerprint(job: &MasteringJob) -> Result<String> {
+    let mut paths: Vec<&Path> = job.stem_inputs.iter().map(|s| s.path.as_path()).collect();
+    if !job.input_path.as_os_str().is_empty() {
+        paths.insert(0, &job.input_path);
+    }
+    let hashes = paths
+        .into_iter()
+        .map(|p| compute_file_hash(p).with_context(|| format!("Reading {}", p.display())))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(hashes.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result_for(output: &Path) -> MasteringResult {
+        serde_json::from_value(serde_json::json!({
+            "output_path": output,
+            "backend_used": "basic",
+            "pre_analysis": null,
+            "post_analysis": null,
+            "params_applied": null,
+            "timings": crate::types::Timings::default(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_manifest_path() {
+        assert_eq!(
+            manifest_path(Path::new("/music/song_mastered.wav")),
+            PathBuf::from("/music/.song_mastered.wav.mastering.json")
+        );
+    }
+
+    #[test]
+    fn test_completed_requires_matching_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("song.wav");
+        let output = dir.path().join("song_mastered.wav");
+        std::fs::write(&input, b"input").unwrap();
+        std::fs::write(&output, b"master").unwrap();
+        let config = Config::default();
+        let job = MasteringJob {
+            input_path: input.clone(),
+            output_path: Some(output.clone()),
+            ..Default::default()
+        };
+
+        assert!(completed(&job, &config).is_none());
+        write(&job, &config, &result_for(&output)).unwrap();
+        assert!(completed(&job, &config).is_some());
+
+        // Other settings need a new master
+        let louder = MasteringJob {
+            target_lufs: Some(-9.0),
+            ..job.clone()
+        };
+        assert!(completed(&louder, &config).is_none());
+
+        // So does a changed output
+        std::fs::write(&output, b"edited master").unwrap();
+        assert!(completed(&job, &config).is_none());
+    }
+}
@@ -1,18 +1,82 @@
+pub mod batch;
+pub mod manifest;
+pub mod progress;
+pub mod review;
+pub mod verify;
+pub mod watch;
+
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use tracing::{info, warn};
 
 use crate::analysis;
-use crate::backends::{MasteringEngine, MasteringOptions};
+use crate::analysis::match_eq::{MatchEqTarget, SpectralCurve};
+use crate::backends::ai::AiBackend;
+use crate::backends::{BackendOutput, MasteringEngine, MasteringOptions};
+use crate::cache;
 use crate::config::Config;
+use crate::dsp::sections as dsp_sections;
+use crate::dsp::{balance, fade, polarity, restoration, silence};
+use crate::encode;
 use crate::error::MasteringError;
-use crate::types::{AiProvider, AudioFormat, Backend, MasteringResult, Preset};
+use crate::metadata::{self, TagOverrides};
+use crate::recipes::Recipe;
+use crate::replaygain;
+use crate::resample;
+use crate::stems::{self, StemSeparator};
+use crate::surround;
+use crate::types::{
+    AiProvider, AudioFormat, AudioMetadata, Backend, Deliverable, DeliveryTarget, Device, Dither,
+    FadeCurve, Fades, LimiterParams, MasteringParams, MasteringResult, OverwritePolicy,
+    ParamCorrection, Preset, Refinement, RestorationStages, SampleFormat, SilenceTrim,
+    StemAdjustment, StemInput, SurroundMode, TimeRange, Timings, TokenUsage,
+};
+use crate::validate;
+
+pub use batch::{run_batch, BatchJobStatus, BatchStatusUpdate};
+pub use progress::{PipelineStage, ProgressReporter, ProgressUpdate};
+pub use review::ParamReview;
+pub use tokio_util::sync::CancellationToken;
+pub use watch::{watch_folder, WatchEvent, WatchFolder, WatchSummary};
 
 /// Maximum supported file size (500MB)
 const MAX_FILE_SIZE: u64 = 500 * 1024 * 1024;
 
+/// Free space required on top of the estimated output size, for tags,
+/// container overhead and whatever else is writing to the disk.
+const DISK_SPACE_MARGIN: u64 = 16 * 1024 * 1024;
+
+/// Sample peak ceiling for surround files mastered in pass-through mode.
+const SURROUND_CEILING_DB: f64 = -1.0;
+
+/// Ceiling assumed when the backend does not report its limiter settings.
+const DEFAULT_CEILING_DB: f64 = -1.0;
+
+/// Context of errors raised by the mastering backend itself, as opposed to
+/// the stages around it; find it with `anyhow::Error::downcast_ref`.
+#[derive(Debug, thiserror::Error)]
+#[error("Backend processing failed")]
+pub struct BackendFailed;
+
+/// Length of the excerpt rendered by a preview, in seconds.
+pub const PREVIEW_SECS: f64 = 30.0;
+
+/// Limiter release used by the loudness and peak correction passes.
+const SAFETY_RELEASE_MS: f64 = 50.0;
+
 /// Supported audio formats for input
-const SUPPORTED_INPUT_EXTENSIONS: &[&str] = &["wav", "flac", "mp3", "ogg", "m4a", "aac", "wma"];
+pub const SUPPORTED_INPUT_EXTENSIONS: &[&str] = &[
+    "wav", "flac", "mp3", "ogg", "m4a", "aac", "wma", "aif", "aiff", "caf",
+];
+
+/// Whether `path` has the extension of a supported input format.
+pub fn is_supported_input(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| SUPPORTED_INPUT_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
 
 /// Validate input file before processing.
 pub fn validate_input(path: &Path) -> Result<(), MasteringError> {
@@ -78,27 +142,13 @@ pub fn validate_input(path: &Path) -> Result<(), MasteringError> {
     Ok(())
 }
 
-/// Check disk space for output file.
-pub fn check_disk_space(output_path: &Path, _estimated_size: u64) -> Result<(), MasteringError> {
-    // Get parent directory
-    let parent = output_path
-        .parent()
-        .unwrap_or(Path::new("."));
-
-    // Get available space (simplified check)
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::MetadataExt;
-        match std::fs::metadata(parent) {
-            Ok(meta) => {
-                // This is a simplified check - in production you'd use statvfs
-                let _ = meta.dev(); // Suppress unused warning
-            }
-            Err(_) => {
-                warn!("Cannot check disk space for {}", parent.display());
-            }
-        }
-    }
+/// Check that the output directory exists or can be created, can be
+/// written to, and has room for `estimated_size` bytes.
+pub fn check_disk_space(output_path: &Path, estimated_size: u64) -> Result<(), MasteringError> {
+    let parent = match output_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
 
     // Ensure output directory exists or can be created
     if !parent.exists() {
@@ -110,7 +160,108 @@ pub fn check_disk_space(output_path: &Path, _estimated_size: u64) -> Result<(),
         }
     }
 
-    Ok(())
+    // Permissions alone don't tell, e.g. on read-only mounts, so write a file
+    let probe = parent.join(format!(".mastering-write-test-{}", std::process::id()));
+    match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+    {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+        }
+        Err(e) => {
+            return Err(MasteringError::FileIo {
+                message: format!(
+                    "Cannot write to output directory {}: {}",
+                    parent.display(),
+                    e
+                ),
+                path: Some(parent.to_path_buf()),
+            });
+        }
+    }
+
+    let needed = estimated_size.saturating_add(DISK_SPACE_MARGIN);
+    match available_space(parent) {
+        Some(available) if available < needed => Err(MasteringError::FileIo {
+            message: format!(
+                "Not enough disk space in {}: about {} MB needed, {} MB free",
+                parent.display(),
+                needed.div_ceil(1024 * 1024),
+                available / (1024 * 1024)
+            ),
+            path: Some(parent.to_path_buf()),
+        }),
+        Some(_) => Ok(()),
+        None => {
+            warn!("Cannot check disk space for {}", parent.display());
+            Ok(())
+        }
+    }
+}
+
+/// Bytes available to unprivileged users on the file system holding `dir`.
+#[cfg(unix)]
+fn available_space(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stat` is only read after success
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stat = unsafe { stat.assume_init() };
+    // The field types differ between platforms; they are u64 on Linux
+    #[allow(clippy::useless_conversion)]
+    Some(u64::from(stat.f_bavail).saturating_mul(u64::from(stat.f_frsize)))
+}
+
+#[cfg(not(unix))]
+fn available_space(_dir: &Path) -> Option<u64> {
+    None
+}
+
+/// Rough upper bound of the bytes a job writes for an input described by
+/// `metadata`: the output and its extra targets at their formats, plus the
+/// 32-bit float WAV the chain renders before encoding. Lossless compression
+/// is not counted on, so FLAC and ALAC are estimated at their PCM size.
+pub fn estimate_output_size(job: &MasteringJob, config: &Config, metadata: &AudioMetadata) -> u64 {
+    let duration = metadata.duration_secs.max(0.0);
+    let channels = f64::from(metadata.channels);
+    let output_rate = job
+        .sample_rate
+        .or(config.encoding.sample_rate)
+        .unwrap_or(metadata.sample_rate);
+    let default_format = job.format.unwrap_or(config.general.default_format);
+    let default_bits = match job.sample_format {
+        SampleFormat::Float => 32,
+        SampleFormat::Int => job.bit_depth.unwrap_or(config.general.default_bit_depth),
+    };
+
+    let size = |format: AudioFormat, bits: u16, kbps: Option<u32>| {
+        // Only lossy formats have a bitrate
+        let lossy = config.encoding.bitrate_for(format);
+        match lossy.map(|default| kbps.unwrap_or(default)) {
+            Some(kbps) => f64::from(kbps) * 1000.0 / 8.0 * duration,
+            None => duration * f64::from(output_rate) * channels * f64::from(bits) / 8.0,
+        }
+    };
+    let scratch = duration * f64::from(metadata.sample_rate) * channels * 4.0;
+    let output = size(default_format, default_bits, job.bitrate_kbps);
+    let targets: f64 = job
+        .targets
+        .iter()
+        .map(|t| {
+            size(
+                t.format.unwrap_or(default_format),
+                t.bit_depth.unwrap_or(default_bits),
+                t.bitrate_kbps,
+            )
+        })
+        .sum();
+    (scratch + output + targets).ceil() as u64
 }
 
 /// Trait for pre-flight checks that backends can implement.
@@ -123,20 +274,114 @@ pub trait PreflightCheck {
 }
 
 /// High-level mastering job request.
-#[derive(Debug, Clone)]
+///
+/// Serializes to the settings of the job, so it can be stored and run
+/// again later; the review callback and cancellation token are left out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MasteringJob {
     pub input_path: PathBuf,
+    /// Files of a track delivered as stems, summed into the input before
+    /// mastering. `input_path` may then be empty; it only names the output.
+    pub stem_inputs: Vec<StemInput>,
     pub output_path: Option<PathBuf>,
+    /// What to do when the output already exists; defaults to `general.overwrite`.
+    pub overwrite: Option<OverwritePolicy>,
     pub reference_path: Option<PathBuf>,
+    /// Reference track, or spectral curve saved as JSON, whose tonal balance
+    /// the basic backend matches with a few EQ bands; a lighter alternative
+    /// to full reference matching.
+    pub match_eq: Option<PathBuf>,
+    /// Share of the match EQ difference to correct, from 0 to 1; defaults
+    /// to `general.match_eq_strength`.
+    pub match_eq_strength: Option<f64>,
     pub backend: Backend,
     pub ai_provider: Option<AiProvider>,
     pub lmstudio_model: Option<String>,
+    /// Device for the local ML backend; defaults to `backends.local_ml.device`.
+    pub device: Option<Device>,
     pub bit_depth: Option<u16>,
+    /// Integer or 32-bit float samples; float applies to WAV output only.
+    pub sample_format: SampleFormat,
     pub format: Option<AudioFormat>,
+    /// Bitrate for lossy formats; defaults to the `[encoding]` config.
+    pub bitrate_kbps: Option<u32>,
+    /// Extra deliverables rendered from the master next to the main output,
+    /// each at its own loudness and format.
+    pub targets: Vec<DeliveryTarget>,
+    /// Output sample rate; defaults to the `[encoding]` config, then the source rate.
+    pub sample_rate: Option<u32>,
+    /// Dither for lossless output; defaults to the `[encoding]` config.
+    pub dither: Option<Dither>,
+    /// Handling of inputs with more than two channels; defaults to the config.
+    pub surround_mode: Option<SurroundMode>,
     pub target_lufs: Option<f64>,
+    /// Highest true peak of the output in dBTP; defaults to the preset's.
+    pub ceiling_db: Option<f64>,
     pub no_limiter: bool,
     pub preset: Option<Preset>,
+    /// Fixed parameters rendered as they are instead of suggested ones,
+    /// e.g. from a custom preset or a recipe.
+    pub params: Option<MasteringParams>,
+    /// Fade-in length in seconds; defaults to the preset's.
+    pub fade_in_secs: Option<f64>,
+    /// Fade-out length in seconds; defaults to the preset's.
+    pub fade_out_secs: Option<f64>,
+    /// Fade shape; defaults to the preset's.
+    pub fade_curve: Option<FadeCurve>,
+    /// Free-form description of the intended sound, e.g. "warm, punchy,
+    /// club-ready"; guides the AI backend.
+    pub brief: Option<String>,
+    /// Re-render with parameters adjusted to feedback on an earlier master.
+    /// Requires the AI backend.
+    pub refinement: Option<Refinement>,
+    /// Ask the AI backend to explain its parameter choices.
+    pub explain: bool,
+    /// Shown the AI backend's parameters before they are rendered; the
+    /// reviewed ones are validated again and replace them.
+    #[serde(skip)]
+    pub review: ParamReview,
+    /// Process for spoken word; also implied by speech presets.
+    pub speech: bool,
+    /// Restoration stages run on the input before mastering.
+    pub restoration: RestorationStages,
+    /// Even out a stereo input whose channels differ in level by more than
+    /// `general.balance_threshold_db`.
+    pub fix_balance: bool,
+    /// Invert the right channel of a stereo input whose channels are out of
+    /// phase for most of the track.
+    pub fix_polarity: bool,
+    /// Cut leading and trailing silence from the input before mastering.
+    pub trim_silence: Option<SilenceTrim>,
+    /// Master with one static chain, without evening out the gain of quiet
+    /// and loud sections first.
+    pub no_sections: bool,
+    /// Master only this part of the input; the output holds just the excerpt.
+    pub range: Option<TimeRange>,
+    /// Master only the loudest [`PREVIEW_SECS`] of the input, unless `range`
+    /// picks the part, and name the output `<stem>_preview`.
+    pub preview: bool,
+    /// Separate the input into stems and rebalance them before mastering.
+    pub stems: bool,
+    /// Gain and EQ per stem name. When empty, the AI backend suggests them;
+    /// other backends leave the stems unchanged.
+    pub stem_adjustments: BTreeMap<String, StemAdjustment>,
     pub dry_run: bool,
+    /// Skip the on-disk analysis cache and always re-analyze.
+    pub no_cache: bool,
+    /// Refuse AI providers that need the network; also set by the
+    /// `general.offline` config option.
+    pub offline: bool,
+    /// Fail instead of applying corrective limiting when the output exceeds
+    /// the peak ceiling.
+    pub strict: bool,
+    /// Write ReplayGain 2.0 tags (R128 tags for Opus) measured on the output.
+    pub replaygain: bool,
+    /// Tag values written over those copied from the input.
+    pub tags: TagOverrides,
+    /// Cancelling this token aborts the job and removes partial output.
+    #[serde(skip)]
+    pub cancel_token: CancellationToken,
 }
 
 impl MasteringJob {
@@ -146,28 +391,128 @@ impl MasteringJob {
             return out.clone();
         }
 
-        let stem = self
-            .input_path
+        // A job made only of stems is named "mix", next to its first stem
+        let input = match self.stem_inputs.first() {
+            Some(first) if self.input_path.as_os_str().is_empty() => {
+                first.path.with_file_name("mix")
+            }
+            _ => self.input_path.clone(),
+        };
+        let stem = input
             .file_stem()
             .and_then(|s| s.to_str())
             .unwrap_or("output");
 
         let format = self.format.unwrap_or(config.general.default_format);
-        let ext = match format {
-            AudioFormat::Wav => "wav",
-            AudioFormat::Flac => "flac",
-            AudioFormat::Mp3 => "mp3",
-        };
+        let ext = format.extension();
+
+        let suffix = if self.preview { "preview" } else { "mastered" };
+        let parent = input.parent().unwrap_or(Path::new("."));
+        parent.join(format!("{stem}_{suffix}.{ext}"))
+    }
+
+    /// Fades to apply: the job's values over the preset's defaults.
+    pub fn resolved_fades(&self) -> Fades {
+        let defaults = self.preset.map(|p| p.fades()).unwrap_or_default();
+        Fades {
+            in_secs: self.fade_in_secs.unwrap_or(defaults.in_secs),
+            out_secs: self.fade_out_secs.unwrap_or(defaults.out_secs),
+            curve: self.fade_curve.unwrap_or(defaults.curve),
+        }
+    }
+
+    /// Whether to process for spoken word.
+    pub fn speech_mode(&self) -> bool {
+        self.speech || self.preset.is_some_and(|p| p.is_speech())
+    }
 
-        let parent = self.input_path.parent().unwrap_or(Path::new("."));
-        parent.join(format!("{stem}_mastered.{ext}"))
+    /// Restoration stages to run: the job's, plus click repair for speech,
+    /// where mouth clicks are the most common defect.
+    pub fn resolved_restoration(&self) -> RestorationStages {
+        RestorationStages {
+            declick: self.restoration.declick || self.speech_mode(),
+            ..self.restoration
+        }
+    }
+
+    /// Highest true peak allowed by the job or its preset.
+    pub fn resolved_ceiling_db(&self) -> Option<f64> {
+        self.ceiling_db
+            .or_else(|| self.preset.and_then(|p| p.true_peak_ceiling_db()))
+    }
+
+    /// Apply the preset called `name`: a built-in one, or else a custom one
+    /// from the config, which fills in the options the job leaves unset.
+    /// A custom preset's parameters render through the basic backend.
+    pub fn apply_preset(&mut self, name: &str, config: &Config) -> Result<(), MasteringError> {
+        if let Ok(preset) = name.parse::<Preset>() {
+            self.preset = Some(preset);
+            return Ok(());
+        }
+        let custom = config.presets.get(name).ok_or_else(|| {
+            let builtin = Preset::ALL.map(|p| p.to_string());
+            let available: Vec<&str> = builtin
+                .iter()
+                .map(String::as_str)
+                .chain(config.presets.keys().map(String::as_str))
+                .collect();
+            MasteringError::InvalidConfig {
+                message: format!(
+                    "Unknown preset: {name}. Available: {}",
+                    available.join(", ")
+                ),
+                config_key: Some("preset".into()),
+            }
+        })?;
+
+        self.target_lufs = self.target_lufs.or(custom.target_lufs);
+        self.ceiling_db = self.ceiling_db.or(custom.ceiling_db);
+        self.format = self.format.or(custom.format);
+        self.bit_depth = self.bit_depth.or(custom.bit_depth);
+        if self.params.is_none() {
+            if let Some(ref params) = custom.params {
+                let mut params = params.clone();
+                for c in validate::clamp_params(&mut params, &config.ai.limits) {
+                    warn!(
+                        "Corrected preset {name} parameter {}: {}",
+                        c.field, c.message
+                    );
+                }
+                self.params = Some(params);
+                self.backend = Backend::Basic;
+            }
+        }
+        Ok(())
     }
 
-    /// Resolve which backend to actually use.
+    /// Render a saved recipe with the recipe backend. Its target loudness
+    /// applies unless the job sets one.
+    pub fn apply_recipe(&mut self, recipe: &Recipe, config: &Config) {
+        let mut params = recipe.params.clone();
+        for c in validate::clamp_params(&mut params, &config.ai.limits) {
+            warn!(
+                "Corrected recipe {} parameter {}: {}",
+                recipe.name, c.field, c.message
+            );
+        }
+        self.target_lufs.get_or_insert(params.target_lufs);
+        self.params = Some(params);
+        self.backend = Backend::Recipe;
+    }
+
+    /// Resolve which backend to actually use. Fixed parameters render
+    /// through the basic backend, unless they come from a recipe or are
+    /// given to the AI backend, whose DSP bridge renders them as they are;
+    /// so does match EQ unless another backend is chosen.
     pub fn resolved_backend(&self) -> Backend {
+        if self.params.is_some() && !matches!(self.backend, Backend::Recipe | Backend::Ai) {
+            return Backend::Basic;
+        }
         match self.backend {
             Backend::Auto => {
-                if self.reference_path.is_some() {
+                if self.match_eq.is_some() {
+                    Backend::Basic
+                } else if self.reference_path.is_some() {
                     Backend::Matchering
                 } else {
                     Backend::Ai
@@ -178,37 +523,408 @@ impl MasteringJob {
     }
 }
 
+/// The AI provider to use in offline mode.
+///
+/// A provider that stays on this machine is kept. A networked default from
+/// the config is replaced by the rules provider, while one asked for
+/// explicitly, or needed to refine a master, is refused before any work.
+fn offline_ai_provider(
+    job: &MasteringJob,
+    config: &Config,
+    backend: Backend,
+) -> Result<Option<AiProvider>, MasteringError> {
+    if backend != Backend::Ai {
+        return Ok(job.ai_provider);
+    }
+    let provider = job.ai_provider.unwrap_or(config.ai.default_provider);
+    if !config.ai.needs_network(provider) {
+        return Ok(job.ai_provider);
+    }
+    if job.ai_provider.is_some() || job.refinement.is_some() {
+        let alternatives = if job.refinement.is_some() {
+            "ollama, lmstudio or local-gguf running on this machine"
+        } else {
+            "ollama, lmstudio or local-gguf running on this machine, or rules"
+        };
+        return Err(MasteringError::InvalidConfig {
+            message: format!(
+                "Offline mode is on, but the {provider} AI provider needs network access. Use {alternatives}"
+            ),
+            config_key: Some("general.offline".into()),
+        });
+    }
+    warn!("Offline mode: using the rules provider instead of {provider}");
+    Ok(Some(AiProvider::Rules))
+}
+
 /// Execute the full mastering pipeline.
 pub async fn run(job: &MasteringJob, config: &Config) -> Result<MasteringResult> {
+    run_with_progress(job, config, &ProgressReporter::disabled()).await
+}
+
+/// Execute the full mastering pipeline, reporting per-stage progress.
+pub async fn run_with_progress(
+    job: &MasteringJob,
+    config: &Config,
+    progress: &ProgressReporter,
+) -> Result<MasteringResult> {
+    let versioned;
+    let job = match existing_output(job, config)? {
+        Some(output) => {
+            versioned = MasteringJob {
+                output_path: Some(output),
+                ..job.clone()
+            };
+            &versioned
+        }
+        None => job,
+    };
+
+    if job.stem_inputs.is_empty() {
+        master_file(job, config, progress).await
+    } else {
+        master_stem_inputs(job, config, progress).await
+    }
+}
+
+/// Apply the job's overwrite policy when its output or any of its delivery
+/// targets already exists: fail, or return the versioned path to write the
+/// output to instead, whose targets are free as well. `None` means the
+/// resolved outputs can be written.
+fn existing_output(job: &MasteringJob, config: &Config) -> Result<Option<PathBuf>, MasteringError> {
+    let output = job.resolved_output_path(config);
+    let existing = job_output_paths(job, &output, config)
+        .into_iter()
+        .find(|path| path.exists());
+    let Some(existing) = existing.filter(|_| !job.dry_run) else {
+        return Ok(None);
+    };
+    match job.overwrite.unwrap_or(config.general.overwrite) {
+        OverwritePolicy::Overwrite => Ok(None),
+        OverwritePolicy::Error => Err(MasteringError::FileIo {
+            message: format!(
+                "{} already exists; choose another output or set the overwrite policy to overwrite or version",
+                existing.display()
+            ),
+            path: Some(existing),
+        }),
+        OverwritePolicy::Version => {
+            let path = (2..)
+                .map(|n| version_path(&output, n))
+                .find(|path| {
+                    job_output_paths(job, path, config)
+                        .iter()
+                        .all(|p| !p.exists())
+                })
+                .expect("unbounded version range");
+            info!("{} exists, writing {}", existing.display(), path.display());
+            Ok(Some(path))
+        }
+    }
+}
+
+/// The files a job writes when its output goes to `output`: the output
+/// itself and one per delivery target.
+fn job_output_paths(job: &MasteringJob, output: &Path, config: &Config) -> Vec<PathBuf> {
+    let format = job.format.unwrap_or(config.general.default_format);
+    std::iter::once(output.to_path_buf())
+        .chain(
+            job.targets
+                .iter()
+                .map(|target| target_output_path(output, target, format)),
+        )
+        .collect()
+}
+
+/// First of `<stem>_v2.<ext>`, `<stem>_v3.<ext>`, ... next to `output`
+/// that does not exist yet.
+pub fn versioned_output_path(output: &Path) -> PathBuf {
+    (2..)
+        .map(|n| version_path(output, n))
+        .find(|path| !path.exists())
+        .expect("unbounded version range")
+}
+
+/// `<stem>_v<n>.<ext>` next to `output`.
+fn version_path(output: &Path, n: u32) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    match output.extension().and_then(|e| e.to_str()) {
+        Some(ext) => output.with_file_name(format!("{stem}_v{n}.{ext}")),
+        None => output.with_file_name(format!("{stem}_v{n}")),
+    }
+}
+
+/// Master with parameters chosen by the caller, e.g. from manual EQ and
+/// compressor controls, rendered through the built-in DSP chain. No backend
+/// is asked for parameters. Values outside `[ai.limits]` are clamped and
+/// listed in the result's `param_corrections`; the parameters' own target
+/// loudness applies.
+pub async fn master_with_params(
+    job: &MasteringJob,
+    params: MasteringParams,
+    config: &Config,
+    progress: &ProgressReporter,
+) -> Result<MasteringResult> {
+    let mut params = params;
+    let corrections = validate::clamp_params(&mut params, &config.ai.limits);
+    for c in &corrections {
+        warn!("Corrected parameter {}: {}", c.field, c.message);
+    }
+
+    let mut job = job.clone();
+    job.target_lufs = Some(params.target_lufs);
+    job.params = Some(params);
+    job.backend = Backend::Basic;
+    job.refinement = None;
+
+    let mut result = run_with_progress(&job, config, progress).await?;
+    result.param_corrections.splice(0..0, corrections);
+    Ok(result)
+}
+
+/// Master a single file, or the part of it the job's range or preview picks.
+async fn master_file(
+    job: &MasteringJob,
+    config: &Config,
+    progress: &ProgressReporter,
+) -> Result<MasteringResult> {
+    if job.preview && job.range.is_none() {
+        progress.report(
+            PipelineStage::Validation,
+            0.0,
+            "Finding the loudest section",
+        );
+        validate_input(&job.input_path)?;
+        let input = job.input_path.clone();
+        let range =
+            tokio::task::spawn_blocking(move || analysis::loudest_section(&input, PREVIEW_SECS))
+                .await
+                .context("Preview section task failed")?
+                .context("Finding the preview section failed")?;
+        info!("Previewing {range}");
+        let preview_job = MasteringJob {
+            output_path: Some(job.resolved_output_path(config)),
+            range: Some(range),
+            ..job.clone()
+        };
+        master_range(&preview_job, config, progress).await
+    } else if job.range.is_some() {
+        master_range(job, config, progress).await
+    } else {
+        master_input(job, config, progress).await
+    }
+}
+
+/// Sum the job's stem files and master the mix.
+async fn master_stem_inputs(
+    job: &MasteringJob,
+    config: &Config,
+    progress: &ProgressReporter,
+) -> Result<MasteringResult> {
+    progress.report(PipelineStage::Validation, 0.0, "Validating stems");
+    for stem in &job.stem_inputs {
+        validate_input(&stem.path)?;
+    }
+    ensure_not_cancelled(job)?;
+
+    let output_path = job.resolved_output_path(config);
+    let mix_path = temp_wav_path(&output_path, "mix");
+    info!("Summing {} stems", job.stem_inputs.len());
+    progress.report(PipelineStage::Validation, 50.0, "Summing stems");
+    let (inputs, out) = (job.stem_inputs.clone(), mix_path.clone());
+    let mixed = tokio::task::spawn_blocking(move || stems::mix_inputs(&inputs, &out))
+        .await
+        .context("Stem mix task failed")?
+        .context("Summing stems failed");
+    if let Err(e) = mixed {
+        remove_temp_files(std::slice::from_ref(&mix_path));
+        return Err(e);
+    }
+
+    // The mix is a temporary file, so its analysis is not cached
+    let mix_job = MasteringJob {
+        input_path: mix_path.clone(),
+        stem_inputs: Vec::new(),
+        output_path: Some(output_path),
+        no_cache: true,
+        ..job.clone()
+    };
+    let result = master_file(&mix_job, config, progress).await;
+    remove_temp_files(&[mix_path]);
+    result
+}
+
+/// Cut the job's time range out of its input and master the excerpt.
+async fn master_range(
+    job: &MasteringJob,
+    config: &Config,
+    progress: &ProgressReporter,
+) -> Result<MasteringResult> {
+    let range = job.range.unwrap_or_default();
+    range.validate()?;
+    progress.report(PipelineStage::Validation, 0.0, "Validating input");
+    validate_input(&job.input_path)?;
+    ensure_not_cancelled(job)?;
+
+    let output_path = job.resolved_output_path(config);
+    let excerpt_path = temp_wav_path(&output_path, "excerpt");
+    info!("Mastering {range} of {}", job.input_path.display());
+    progress.report(PipelineStage::Validation, 50.0, "Cutting excerpt");
+    let (input, out) = (job.input_path.clone(), excerpt_path.clone());
+    let excerpt = tokio::task::spawn_blocking(move || write_excerpt(&input, &out, &range))
+        .await
+        .context("Excerpt task failed")
+        .and_then(|r| r.context("Cutting the excerpt failed"));
+    if let Err(e) = excerpt {
+        remove_temp_files(std::slice::from_ref(&excerpt_path));
+        return Err(e);
+    }
+
+    // The excerpt is a temporary file, so its analysis is not cached
+    let excerpt_job = MasteringJob {
+        input_path: excerpt_path.clone(),
+        output_path: Some(output_path),
+        range: None,
+        no_cache: true,
+        ..job.clone()
+    };
+    let result = master_input(&excerpt_job, config, progress).await;
+    remove_temp_files(&[excerpt_path]);
+    result.map(|r| MasteringResult {
+        excerpt: Some(range),
+        ..r
+    })
+}
+
+/// Write the part of `input` inside `range` to `output` as 32-bit float WAV.
+fn write_excerpt(input: &Path, output: &Path, range: &TimeRange) -> Result<()> {
+    let audio = analysis::decode_audio_range(input, range)?;
+    anyhow::ensure!(
+        !audio.samples.is_empty(),
+        "The range {range} is past the end of the input"
+    );
+    let opts = encode::EncodeOptions {
+        bit_depth: 32,
+        sample_format: SampleFormat::Float,
+        ..Default::default()
+    };
+    encode::write_wav(
+        output,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        &opts,
+    )
+}
+
+/// Master a single input file.
+async fn master_input(
+    job: &MasteringJob,
+    config: &Config,
+    progress: &ProgressReporter,
+) -> Result<MasteringResult> {
     let pipeline_start = std::time::Instant::now();
 
     // Step 0: Validate input
+    progress.report(PipelineStage::Validation, 0.0, "Validating input");
     validate_input(&job.input_path)?;
 
     let output_path = job.resolved_output_path(config);
     let backend = job.resolved_backend();
     let bit_depth = job.bit_depth.unwrap_or(config.general.default_bit_depth);
-    let target_lufs = job
-        .target_lufs
-        .or_else(|| job.preset.map(|p| p.target_lufs()))
-        .unwrap_or(config.general.target_lufs);
+    let speech = job.speech_mode();
+    let stages = job.resolved_restoration();
 
     info!("Mastering pipeline started");
     info!("  Input:    {}", job.input_path.display());
     info!("  Output:   {}", output_path.display());
     info!("  Backend:  {backend}");
     info!("  Bit depth: {bit_depth}");
-    info!("  Target LUFS: {target_lufs}");
+    if speech {
+        info!("  Speech:   yes");
+    }
+
+    let default_format = job.format.unwrap_or(config.general.default_format);
+    let mut target_paths = vec![output_path.clone()];
+    for target in &job.targets {
+        let path = target_output_path(&output_path, target, default_format);
+        if target_paths.contains(&path) {
+            return Err(MasteringError::InvalidConfig {
+                message: format!(
+                    "Two outputs of the job would both be written to {}",
+                    path.display()
+                ),
+                config_key: Some("targets".into()),
+            }
+            .into());
+        }
+        info!("  Target:   {}", path.display());
+        target_paths.push(path);
+    }
+
+    if backend == Backend::Recipe && job.params.is_none() {
+        return Err(MasteringError::InvalidConfig {
+            message: "The recipe backend needs a recipe; apply one to the job first".into(),
+            config_key: Some("backend".into()),
+        }
+        .into());
+    }
+
+    if job.match_eq.is_some() && !matches!(backend, Backend::Basic | Backend::Recipe) {
+        return Err(MasteringError::InvalidConfig {
+            message: format!("Match EQ renders with the basic or recipe backend, not {backend}"),
+            config_key: Some("backend".into()),
+        }
+        .into());
+    }
+
+    if let Some(ref refinement) = job.refinement {
+        if backend != Backend::Ai {
+            return Err(MasteringError::InvalidConfig {
+                message: format!("Refining a master requires the AI backend, not {backend}"),
+                config_key: Some("backend".into()),
+            }
+            .into());
+        }
+        validate_input(&refinement.previous_output)?;
+        info!("  Refining: {}", refinement.previous_output.display());
+    }
+
+    let offline = job.offline || config.general.offline;
+    let ai_provider = if offline {
+        info!("  Offline:  yes");
+        offline_ai_provider(job, config, backend)?
+    } else {
+        job.ai_provider
+    };
+
+    ensure_not_cancelled(job)?;
 
     // Step 1: Pre-analysis
     let analysis_start = std::time::Instant::now();
     info!("Analyzing input audio...");
-    let pre_analysis = analysis::analyze_file(&job.input_path)
+    progress.report(PipelineStage::Analysis, 0.0, "Analyzing input audio");
+    let cache = (!job.no_cache).then(cache::global_cache);
+    let pre_analysis = analysis::analyze_file_cached(&job.input_path, cache)
         .await
         .context("Pre-analysis of input audio failed")?;
 
     let analysis_elapsed = analysis_start.elapsed();
     info!("Pre-analysis completed in {:.2}s", analysis_elapsed.as_secs_f64());
+    progress.report(PipelineStage::Analysis, 100.0, "Pre-analysis complete");
+
+    // Some presets aim lower for mono, so the target waits for the channel count
+    let target_lufs = job
+        .target_lufs
+        .or_else(|| {
+            job.preset
+                .map(|p| p.target_lufs_for(pre_analysis.metadata.channels))
+        })
+        .unwrap_or(config.general.target_lufs);
+    info!("  Target LUFS: {target_lufs}");
 
     info!(
         "  LUFS: {:.1}, Peak: {:.1} dB, RMS: {:.1} dB, Stereo Width: {:.2}",
@@ -217,62 +933,634 @@ pub async fn run(job: &MasteringJob, config: &Config) -> Result<MasteringResult>
         pre_analysis.rms_db,
         pre_analysis.stereo_width
     );
+    if let Some(warning) = analysis::clipping_warning(&pre_analysis) {
+        warn!("{warning}");
+    }
+    for warning in analysis::channel_warnings(&pre_analysis) {
+        warn!("{warning}");
+    }
+    if !stages.denoise {
+        if let Some(warning) = analysis::noise_floor_warning(&pre_analysis) {
+            warn!("{warning}");
+        }
+    }
 
     // Dry run: just show analysis and exit
     if job.dry_run {
         info!("Dry run — no processing performed");
+        progress.report(PipelineStage::Complete, 100.0, "Dry run complete");
         return Ok(MasteringResult {
             output_path,
             backend_used: backend.to_string(),
             pre_analysis: Some(pre_analysis),
             post_analysis: None,
             params_applied: None,
+            param_corrections: Vec::new(),
+            brief: job.brief.clone(),
+            explanation: None,
+            candidates: Vec::new(),
+            usage: None,
+            device: None,
+            stem_adjustments: None,
+            restoration: None,
+            polarity_flipped: false,
+            silence_trimmed: None,
+            excerpt: None,
+            deliverables: Vec::new(),
+            replaygain: None,
+            balance_correction_db: None,
+            sections: Vec::new(),
+            timings: Timings {
+                total_secs: pipeline_start.elapsed().as_secs_f64(),
+                analysis_secs: analysis_elapsed.as_secs_f64(),
+                processing_secs: 0.0,
+            },
         });
     }
 
+    // Fail now rather than at the write stage after a long render
+    check_disk_space(
+        &output_path,
+        estimate_output_size(job, config, &pre_analysis.metadata),
+    )?;
+
+    let match_eq = match job.match_eq {
+        Some(ref target) => {
+            info!("  Match EQ: {}", target.display());
+            let target = target.clone();
+            let curve = tokio::task::spawn_blocking(move || SpectralCurve::measure(&target))
+                .await
+                .context("Match EQ task failed")?
+                .context("Measuring the match EQ target failed")?;
+            let strength = job
+                .match_eq_strength
+                .unwrap_or(config.general.match_eq_strength);
+            Some(MatchEqTarget {
+                curve,
+                strength: strength.clamp(0.0, 1.0),
+            })
+        }
+        None => None,
+    };
+
+    ensure_not_cancelled(job)?;
+
     // Step 2: Create and configure the backend engine
     let mut config = config.clone();
     if let Some(ref model) = job.lmstudio_model {
         config.ai.lmstudio.model = model.clone();
     }
+    if let Some(device) = job.device {
+        config.backends.local_ml.device = device;
+    }
     let mut engine = MasteringEngine::from_config(backend, &config);
 
     // Override AI provider if specified
-    if let (MasteringEngine::Ai(ref mut ai_backend), Some(provider)) =
-        (&mut engine, job.ai_provider)
-    {
+    if let (MasteringEngine::Ai(ref mut ai_backend), Some(provider)) = (&mut engine, ai_provider) {
         *ai_backend = ai_backend.clone().with_provider(provider);
     }
 
+    // Backends always write WAV; other formats are encoded from an intermediate file.
+    // Dithered output is rendered at 32 bits and re-quantized by the native writer.
+    let final_format = job.format.unwrap_or(config.general.default_format);
+    let sample_format = if final_format == AudioFormat::Wav {
+        job.sample_format
+    } else {
+        if job.sample_format == SampleFormat::Float {
+            warn!("Float samples are only supported for WAV; writing integer {final_format}");
+        }
+        SampleFormat::Int
+    };
+    let bit_depth = if sample_format == SampleFormat::Float {
+        32
+    } else {
+        bit_depth
+    };
+    let dither = job.dither.unwrap_or(config.encoding.dither);
+    let requantize = dither != Dither::None
+        && bit_depth < 32
+        && !final_format.is_lossy()
+        && encode::has_native_encoder(final_format);
+    let (backend_bit_depth, backend_sample_format) = if requantize {
+        (32, SampleFormat::Float)
+    } else {
+        (bit_depth, sample_format)
+    };
+    let backend_path = if final_format == AudioFormat::Wav && !requantize {
+        output_path.clone()
+    } else {
+        temp_wav_path(&output_path, "master")
+    };
+
+    // Surround input is either downmixed for the backend or only loudness-normalized
+    let surround_mode = (pre_analysis.metadata.channels > 2)
+        .then(|| job.surround_mode.unwrap_or(config.general.surround_mode));
+    let mut backend_input = job.input_path.clone();
+    // Downmix, restoration, polarity, rebalanced, trimmed, section and stem files fed to the backend in turn
+    let mut temp_files = Vec::new();
+    if surround_mode == Some(SurroundMode::Downmix) {
+        info!(
+            "Downmixing {} input to stereo",
+            pre_analysis.metadata.channel_layout
+        );
+        let input = job.input_path.clone();
+        let path = temp_wav_path(&output_path, "downmix");
+        let out = path.clone();
+        let downmixed = tokio::task::spawn_blocking(move || surround::downmix_file(&input, &out))
+            .await
+            .context("Downmix task failed")
+            .and_then(|r| r.context("Downmixing surround input failed"));
+        temp_files.push(path.clone());
+        if let Err(e) = downmixed {
+            remove_temp_files(&temp_files);
+            return Err(e);
+        }
+        backend_input = path;
+    }
+
+    // Optionally clean up noisy recordings before anything else listens to them
+    let mut restoration = None;
+    if stages.any() {
+        info!("Restoring input ({})", restoration_summary(&stages));
+        progress.report(PipelineStage::Processing, 0.0, "Restoring audio");
+        let input = backend_input.clone();
+        let path = temp_wav_path(&output_path, "restored");
+        let (out, restoration_config) = (path.clone(), config.restoration.clone());
+        let restored = tokio::task::spawn_blocking(move || {
+            restoration::restore_file(&input, &out, &stages, &restoration_config)
+        })
+        .await
+        .context("Restoration task failed")
+        .and_then(|r| r.context("Restoring input failed"));
+        temp_files.push(path.clone());
+        let report =
+            match restored.and_then(|r| ensure_not_cancelled(job).map(|_| r).map_err(Into::into)) {
+                Ok(report) => report,
+                Err(e) => {
+                    remove_temp_files(&temp_files);
+                    return Err(e);
+                }
+            };
+        if let Some(hz) = report.hum_hz {
+            info!("  Removed {hz} Hz hum");
+        }
+        if report.clicks_repaired > 0 {
+            info!("  Repaired {} clicks", report.clicks_repaired);
+        }
+        backend_input = path;
+        restoration = Some(report);
+    }
+
+    // Optionally flip a polarity-inverted channel back
+    let mut polarity_flipped = false;
+    if job.fix_polarity && surround_mode == Some(SurroundMode::PassThrough) {
+        warn!("Polarity correction is not available for surround pass-through; mastering the input as is");
+    } else if job.fix_polarity && analysis::is_polarity_inverted(&pre_analysis) {
+        info!(
+            "Inverting the right channel: correlation {:.2}",
+            pre_analysis.phase_correlation
+        );
+        let input = backend_input.clone();
+        let path = temp_wav_path(&output_path, "polarity");
+        let out = path.clone();
+        let flipped = tokio::task::spawn_blocking(move || polarity::flip_file(&input, &out))
+            .await
+            .context("Polarity correction task failed")
+            .and_then(|r| r.context("Correcting polarity failed"));
+        temp_files.push(path.clone());
+        if let Err(e) = flipped.and_then(|_| ensure_not_cancelled(job).map_err(Into::into)) {
+            remove_temp_files(&temp_files);
+            return Err(e);
+        }
+        backend_input = path;
+        polarity_flipped = true;
+    } else if job.fix_polarity && pre_analysis.out_of_phase_secs > 0.0 {
+        warn!(
+            "Only {:.1}s of the input is out of phase; a polarity flip would break the rest, so none was applied",
+            pre_analysis.out_of_phase_secs
+        );
+    }
+
+    // Optionally even out a lopsided stereo image
+    let mut balance_correction_db = None;
+    let balance_db = pre_analysis.stereo_balance_db;
+    if job.fix_balance && surround_mode == Some(SurroundMode::PassThrough) {
+        warn!("Balance correction is not available for surround pass-through; mastering the input as is");
+    } else if job.fix_balance && balance_db.abs() > config.general.balance_threshold_db {
+        info!(
+            "Correcting stereo balance: {} channel {:.1} dB louder",
+            if balance_db > 0.0 { "left" } else { "right" },
+            balance_db.abs()
+        );
+        let input = backend_input.clone();
+        let path = temp_wav_path(&output_path, "balanced");
+        let out = path.clone();
+        let balanced =
+            tokio::task::spawn_blocking(move || balance::rebalance_file(&input, &out, balance_db))
+                .await
+                .context("Balance correction task failed")
+                .and_then(|r| r.context("Correcting stereo balance failed"));
+        temp_files.push(path.clone());
+        if let Err(e) = balanced.and_then(|_| ensure_not_cancelled(job).map_err(Into::into)) {
+            remove_temp_files(&temp_files);
+            return Err(e);
+        }
+        backend_input = path;
+        balance_correction_db = Some(balance_db);
+    }
+
+    // Optionally cut dead air from the start and end
+    let mut silence_trimmed = None;
+    if let Some(trim) = job.trim_silence {
+        let input = backend_input.clone();
+        let path = temp_wav_path(&output_path, "trimmed");
+        let out = path.clone();
+        let trimmed = tokio::task::spawn_blocking(move || silence::trim_file(&input, &out, &trim))
+            .await
+            .context("Silence trimming task failed")
+            .and_then(|r| r.context("Trimming silence failed"));
+        temp_files.push(path.clone());
+        let trimmed =
+            match trimmed.and_then(|t| ensure_not_cancelled(job).map(|_| t).map_err(Into::into)) {
+                Ok(trimmed) => trimmed,
+                Err(e) => {
+                    remove_temp_files(&temp_files);
+                    return Err(e);
+                }
+            };
+        info!(
+            "Trimmed {:.2}s of leading and {:.2}s of trailing silence",
+            trimmed.leading_secs, trimmed.trailing_secs
+        );
+        backend_input = path;
+        silence_trimmed = Some(trimmed);
+    }
+
+    // Give quiet and loud sections slightly different gain into the chain
+    let mut sections = Vec::new();
+    if !job.no_sections && surround_mode != Some(SurroundMode::PassThrough) {
+        progress.report(PipelineStage::Processing, 0.0, "Finding sections");
+        let input = backend_input.clone();
+        let path = temp_wav_path(&output_path, "sections");
+        let out = path.clone();
+        let ridden = tokio::task::spawn_blocking(move || dsp_sections::ride_file(&input, &out))
+            .await
+            .context("Section gain task failed")
+            .and_then(|r| r.context("Riding section gains failed"));
+        temp_files.push(path.clone());
+        match ridden.and_then(|r| ensure_not_cancelled(job).map(|_| r).map_err(Into::into)) {
+            Ok(Some(found)) => {
+                for section in &found {
+                    info!(
+                        "  Section {:.1}s-{:.1}s at {:.1} dB: {:+.1} dB",
+                        section.start_secs, section.end_secs, section.level_db, section.gain_db
+                    );
+                }
+                backend_input = path;
+                sections = found;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                remove_temp_files(&temp_files);
+                return Err(e);
+            }
+        }
+    }
+
+    // Optionally rebalance the stems of the (downmixed) input before mastering
+    let mut stem_remix = None;
+    if job.stems && surround_mode == Some(SurroundMode::PassThrough) {
+        warn!(
+            "Stem separation is not available for surround pass-through; mastering the input as is"
+        );
+    } else if job.stems {
+        let ai = match engine {
+            MasteringEngine::Ai(ref ai_backend)
+                if ai_provider.unwrap_or(config.ai.default_provider) != AiProvider::Rules =>
+            {
+                Some(ai_backend)
+            }
+            _ => None,
+        };
+        let remixed = tokio::select! {
+            result = separate_and_remix(job, &config, ai, &backend_input, &output_path, progress) => result,
+            _ = job.cancel_token.cancelled() => Err(MasteringError::Cancelled.into()),
+        };
+        let remixed = match remixed {
+            Ok(remixed) => remixed,
+            Err(e) => {
+                remove_temp_files(&temp_files);
+                return Err(e);
+            }
+        };
+        backend_input = remixed.path.clone();
+        temp_files.push(remixed.path.clone());
+        stem_remix = Some(remixed);
+    }
+
     let opts = MasteringOptions {
-        input_path: job.input_path.clone(),
-        output_path: output_path.clone(),
+        input_path: backend_input,
+        output_path: backend_path.clone(),
         reference_path: job.reference_path.clone(),
-        bit_depth,
+        bit_depth: backend_bit_depth,
+        sample_format: backend_sample_format,
         target_lufs,
         no_limiter: job.no_limiter,
         preset: job.preset,
+        params: job.params.clone(),
+        match_eq,
+        ceiling_db: job.resolved_ceiling_db(),
+        speech,
+        brief: job.brief.clone(),
+        refinement: job.refinement.clone(),
+        explain: job.explain,
+        review: job.review.clone(),
+        progress: progress.clone(),
     };
 
     // Step 3: Process
     let process_start = std::time::Instant::now();
-    info!("Processing with {} backend...", engine.name());
-    let backend_output = engine
-        .process(&opts)
-        .await
-        .context("Backend processing failed")?;
+    let output_existed = backend_path.exists();
+    let processed = if surround_mode == Some(SurroundMode::PassThrough) {
+        info!(
+            "Passing {} channels through with linked gain",
+            pre_analysis.metadata.channels
+        );
+        progress.report(
+            PipelineStage::Processing,
+            0.0,
+            "Normalizing surround channels",
+        );
+        process_surround_pass_through(&opts).await
+    } else {
+        info!("Processing with {} backend...", engine.name());
+        progress.report(
+            PipelineStage::Processing,
+            0.0,
+            format!("Processing with {} backend", engine.name()),
+        );
+        // Dropping the backend future kills any bridge subprocess and aborts HTTP calls
+        tokio::select! {
+            result = engine.process(&opts) => result.context(BackendFailed),
+            _ = job.cancel_token.cancelled() => {
+                warn!("Mastering cancelled during backend processing");
+                remove_partial_output(&backend_path, output_existed);
+                Err(MasteringError::Cancelled.into())
+            }
+        }
+    };
+    remove_temp_files(&temp_files);
+    let mut backend_output = match processed {
+        Ok(output) => output,
+        Err(e) => {
+            remove_partial_output(&backend_path, output_existed);
+            return Err(e);
+        }
+    };
+    if let Some(ref remix) = stem_remix {
+        backend_output
+            .corrections
+            .splice(0..0, remix.corrections.iter().cloned());
+        if let Some(ref stem_usage) = remix.usage {
+            backend_output
+                .usage
+                .get_or_insert_with(TokenUsage::default)
+                .add(stem_usage);
+        }
+    }
 
     let process_elapsed = process_start.elapsed();
     info!(
         "Backend processing completed in {:.2}s ({})",
         process_elapsed.as_secs_f64(),
-        engine.name()
+        backend_output.backend_name
+    );
+    progress.report(
+        PipelineStage::Processing,
+        100.0,
+        "Backend processing complete",
     );
 
+    // Resample, fade and verify the master; a failure in any of these removes it
+    let finished: Result<f64> = async {
+        // Resample the backend output before it is analyzed and encoded
+        let target_rate = job.sample_rate.or(config.encoding.sample_rate);
+        if let Some(rate) = target_rate.filter(|_| backend_output.output_path.exists()) {
+            let path = backend_output.output_path.clone();
+            let resample_opts = encode::EncodeOptions {
+                bit_depth: backend_bit_depth,
+                sample_format: backend_sample_format,
+                ..Default::default()
+            };
+            let original = tokio::task::spawn_blocking(move || {
+                resample::resample_file_in_place(&path, rate, &resample_opts)
+            })
+            .await
+            .context("Resampling task failed")?
+            .context("Sample-rate conversion failed")?;
+            if let Some(original) = original {
+                info!("Resampled output from {original} Hz to {rate} Hz");
+            }
+        }
+
+        // Fade the edges of the mastered track before its loudness is verified
+        let fades = job.resolved_fades();
+        if fades.any() && backend_output.output_path.exists() {
+            info!(
+                "Fading in over {:.2}s and out over {:.2}s ({} curve)",
+                fades.in_secs, fades.out_secs, fades.curve
+            );
+            let path = backend_output.output_path.clone();
+            let fade_opts = encode::EncodeOptions {
+                bit_depth: backend_bit_depth,
+                sample_format: backend_sample_format,
+                ..Default::default()
+            };
+            tokio::task::spawn_blocking(move || fade::apply_file_in_place(&path, &fades, &fade_opts))
+                .await
+                .context("Fade task failed")?
+                .context("Applying fades failed")?;
+        }
+
+        // Peak ceiling of the output: the limiter's, or full scale without one,
+        // and never above the preset's delivery ceiling
+        let preset_ceiling_db = job.resolved_ceiling_db();
+        let ceiling_db = if surround_mode == Some(SurroundMode::PassThrough) {
+            SURROUND_CEILING_DB
+        } else if job.no_limiter {
+            0.0
+        } else {
+            backend_output
+                .params_applied
+                .as_ref()
+                .map_or(DEFAULT_CEILING_DB, |p| p.limiter.ceiling_db)
+                .min(preset_ceiling_db.unwrap_or(f64::INFINITY))
+        };
+        let verify_opts = encode::EncodeOptions {
+            bit_depth: backend_bit_depth,
+            sample_format: backend_sample_format,
+            ..Default::default()
+        };
+
+        // Verify the output loudness and correct it if the backend missed the target.
+        // Surround pass-through is gain-only by design and is not pushed into a limiter.
+        let verify_loudness = config.general.max_loudness_passes > 0
+            && surround_mode != Some(SurroundMode::PassThrough)
+            && backend_output.output_path.exists();
+        if verify_loudness {
+            progress.report(PipelineStage::PostAnalysis, 0.0, "Verifying loudness");
+            let path = backend_output.output_path.clone();
+            let tolerance = config.general.lufs_tolerance;
+            let max_passes = config.general.max_loudness_passes;
+            let limiter = LimiterParams {
+                enabled: !job.no_limiter,
+                ceiling_db,
+                release_ms: SAFETY_RELEASE_MS,
+                ..Default::default()
+            };
+            let correction = tokio::task::spawn_blocking(move || {
+                verify::correct_loudness(
+                    &path,
+                    target_lufs,
+                    tolerance,
+                    max_passes,
+                    &limiter,
+                    &verify_opts,
+                )
+            })
+            .await
+            .context("Loudness verification task failed")?
+            .context("Loudness correction failed")?;
+            if let Some(fix) = correction {
+                info!(
+                    "Corrected output loudness from {:.1} to {:.1} LUFS ({:+.1} dB, {} pass(es))",
+                    fix.initial_lufs, fix.final_lufs, fix.gain_db, fix.passes
+                );
+                if (fix.final_lufs - target_lufs).abs() > tolerance {
+                    warn!(
+                        "Output is {:.1} LUFS, outside the {tolerance} LU tolerance of {target_lufs} LUFS",
+                        fix.final_lufs
+                    );
+                }
+            }
+        }
+
+        // Safety check: the true peak must not exceed the ceiling
+        if backend_output.output_path.exists() {
+            let path = backend_output.output_path.clone();
+            let true_peak_db = tokio::task::spawn_blocking(move || verify::measure_true_peak(&path))
+                .await
+                .context("Safety check task failed")?
+                .context("Measuring output true peak failed")?;
+
+            if true_peak_db > ceiling_db + verify::CEILING_MARGIN_DB {
+                if job.strict {
+                    return Err(MasteringError::ProcessingError {
+                        message: format!(
+                            "Output true peak of {true_peak_db:.2} dBTP exceeds the {ceiling_db:.1} dBTP ceiling \
+                             (strict mode does not apply corrective limiting)"
+                        ),
+                        stage: "safety_check".into(),
+                    }
+                    .into());
+                }
+
+                warn!("Output true peak {true_peak_db:.2} dBTP exceeds the {ceiling_db:.1} dBTP ceiling; limiting");
+                let path = backend_output.output_path.clone();
+                let fix = tokio::task::spawn_blocking(move || {
+                    verify::enforce_ceiling(&path, ceiling_db, SAFETY_RELEASE_MS, &verify_opts)
+                })
+                .await
+                .context("Safety limiting task failed")?
+                .context("Corrective limiting failed")?;
+                if let Some(fix) = fix {
+                    info!(
+                        "Corrective limiting brought the true peak from {:.2} to {:.2} dBTP in {} pass(es)",
+                        fix.initial_db, fix.final_db, fix.passes
+                    );
+                }
+            }
+        }
+
+        Ok(ceiling_db)
+    }
+    .await;
+    let ceiling_db = match finished {
+        Ok(ceiling_db) => ceiling_db,
+        Err(e) => {
+            remove_partial_output(&backend_path, output_existed);
+            return Err(e);
+        }
+    };
+
+    // Render each extra deliverable from the master before it is converted
+    let mut deliverables = Vec::new();
+    if !job.targets.is_empty() && backend_output.output_path.exists() {
+        let render = RenderTarget {
+            ceiling_db,
+            no_limiter: job.no_limiter,
+            tolerance: config.general.lufs_tolerance,
+            max_passes: config.general.max_loudness_passes.max(1),
+            format: final_format,
+            bit_depth,
+            dither,
+            ffmpeg_fallback: config.general.ffmpeg_fallback,
+        };
+        for (i, target) in job.targets.iter().enumerate() {
+            if let Err(e) = ensure_not_cancelled(job) {
+                remove_partial_output(&backend_path, output_existed);
+                return Err(e.into());
+            }
+            let message = format!("Rendering target {} of {}", i + 1, job.targets.len());
+            progress.report(PipelineStage::Conversion, 0.0, message);
+            let path = target_output_path(&output_path, target, final_format);
+            let bitrate_kbps = target.bitrate_kbps.or(job.bitrate_kbps).or_else(|| {
+                config
+                    .encoding
+                    .bitrate_for(target.format.unwrap_or(final_format))
+            });
+            let (source, out, target, render) = (
+                backend_output.output_path.clone(),
+                path.clone(),
+                target.clone(),
+                render.clone(),
+            );
+            let rendered = tokio::task::spawn_blocking(move || {
+                render.render(&source, &out, &target, bitrate_kbps)
+            })
+            .await
+            .context("Target render task failed")
+            .and_then(|r| r.with_context(|| format!("Rendering {} failed", path.display())));
+            let deliverable = match rendered {
+                Ok(deliverable) => deliverable,
+                Err(e) => {
+                    remove_partial_output(&backend_path, output_existed);
+                    return Err(e);
+                }
+            };
+            info!(
+                "  Target {}: {:.1} LUFS, {:.1} dBTP",
+                deliverable.path.display(),
+                deliverable.lufs_integrated,
+                deliverable.true_peak_db
+            );
+            if let Err(e) = metadata::copy_tags(&job.input_path, &deliverable.path, &job.tags) {
+                warn!("Failed to copy metadata tags: {e:#}");
+            }
+            if job.replaygain {
+                if let Err(e) = replaygain::tag_track(&deliverable.path) {
+                    warn!("Failed to write ReplayGain tags: {e:#}");
+                }
+            }
+            deliverables.push(deliverable);
+        }
+    }
+
     // Step 4: Post-analysis (if output file was created)
     let post_analysis = if backend_output.output_path.exists() {
         info!("Analyzing output...");
-        match analysis::analyze_file(&backend_output.output_path).await {
+        progress.report(PipelineStage::PostAnalysis, 0.0, "Analyzing output");
+        match analysis::analyze_file_cached(&backend_output.output_path, cache).await {
             Ok(a) => {
                 info!(
                     "  Output LUFS: {:.1}, Peak: {:.1} dB",
@@ -289,10 +1577,65 @@ pub async fn run(job: &MasteringJob, config: &Config) -> Result<MasteringResult>
         None
     };
 
-    // Step 5: Format conversion if needed
-    let final_format = job.format.unwrap_or(config.general.default_format);
-    if final_format != AudioFormat::Wav && backend_output.output_path.exists() {
-        convert_format(&backend_output.output_path, &output_path, final_format)?;
+    if job.cancel_token.is_cancelled() {
+        remove_partial_output(&backend_path, output_existed);
+        return Err(MasteringError::Cancelled.into());
+    }
+
+    // Step 5: Format conversion (and dithered re-quantization) if needed
+    if backend_output.output_path != output_path && backend_output.output_path.exists() {
+        progress.report(
+            PipelineStage::Conversion,
+            0.0,
+            format!("Converting to {final_format}"),
+        );
+        let input = backend_output.output_path.clone();
+        let output = output_path.clone();
+        let ffmpeg_fallback = config.general.ffmpeg_fallback;
+        let encode_opts = encode::EncodeOptions {
+            bit_depth,
+            sample_format,
+            bitrate_kbps: job
+                .bitrate_kbps
+                .or_else(|| config.encoding.bitrate_for(final_format)),
+            dither,
+        };
+        let converted = tokio::task::spawn_blocking(move || {
+            convert_format(&input, &output, final_format, &encode_opts, ffmpeg_fallback)
+        })
+        .await
+        .context("Format conversion task failed")?;
+
+        if backend_output.output_path != output_path {
+            if let Err(e) = std::fs::remove_file(&backend_output.output_path) {
+                warn!("Failed to remove intermediate file: {e}");
+            }
+        }
+        converted?;
+    }
+
+    // Step 6: Carry the input's tags and cover art over to the output
+    if output_path.exists() {
+        if let Err(e) = metadata::copy_tags(&job.input_path, &output_path, &job.tags) {
+            warn!("Failed to copy metadata tags: {e:#}");
+        }
+    }
+
+    // Step 7: Tag the output with the gain players need to level it
+    let mut replaygain = None;
+    if job.replaygain && output_path.exists() {
+        let path = output_path.clone();
+        match tokio::task::spawn_blocking(move || replaygain::tag_track(&path)).await {
+            Ok(Ok(gain)) => {
+                info!(
+                    "ReplayGain: {:+.2} dB, peak {:.6}",
+                    gain.track_gain_db, gain.track_peak
+                );
+                replaygain = Some(gain);
+            }
+            Ok(Err(e)) => warn!("Failed to write ReplayGain tags: {e:#}"),
+            Err(e) => warn!("ReplayGain task failed: {e}"),
+        }
     }
 
     let total_elapsed = pipeline_start.elapsed();
@@ -303,6 +1646,7 @@ pub async fn run(job: &MasteringJob, config: &Config) -> Result<MasteringResult>
         analysis_elapsed.as_secs_f64(),
         process_elapsed.as_secs_f64()
     );
+    progress.report(PipelineStage::Complete, 100.0, "Mastering complete");
 
     Ok(MasteringResult {
         output_path,
@@ -310,32 +1654,388 @@ pub async fn run(job: &MasteringJob, config: &Config) -> Result<MasteringResult>
         pre_analysis: Some(pre_analysis),
         post_analysis,
         params_applied: backend_output.params_applied,
+        param_corrections: backend_output.corrections,
+        brief: job.brief.clone(),
+        explanation: backend_output.explanation,
+        candidates: backend_output.candidates,
+        usage: backend_output.usage,
+        device: backend_output.device,
+        stem_adjustments: stem_remix.map(|r| r.adjustments),
+        restoration,
+        polarity_flipped,
+        silence_trimmed,
+        sections,
+        excerpt: None,
+        deliverables,
+        replaygain,
+        balance_correction_db,
+        timings: Timings {
+            total_secs: total_elapsed.as_secs_f64(),
+            analysis_secs: analysis_elapsed.as_secs_f64(),
+            processing_secs: process_elapsed.as_secs_f64(),
+        },
     })
 }
 
-/// Convert output format using ffmpeg.
-fn convert_format(input: &Path, output: &Path, format: AudioFormat) -> Result<()> {
+/// Settings shared by the renders of a job's delivery targets.
+#[derive(Debug, Clone)]
+struct RenderTarget {
+    ceiling_db: f64,
+    no_limiter: bool,
+    tolerance: f64,
+    max_passes: u32,
+    /// The job's format, bit depth and dither, used where a target sets none.
+    format: AudioFormat,
+    bit_depth: u16,
+    dither: Dither,
+    ffmpeg_fallback: bool,
+}
+
+impl RenderTarget {
+    /// Re-run the loudness and limiter stage on `master` for `target` and
+    /// encode the result to `output`.
+    fn render(
+        &self,
+        master: &Path,
+        output: &Path,
+        target: &DeliveryTarget,
+        bitrate_kbps: Option<u32>,
+    ) -> Result<Deliverable> {
+        let work = temp_wav_path(output, "target");
+        let float = encode::EncodeOptions {
+            bit_depth: 32,
+            sample_format: SampleFormat::Float,
+            ..Default::default()
+        };
+        let limiter = LimiterParams {
+            enabled: !self.no_limiter,
+            ceiling_db: self.ceiling_db,
+            release_ms: SAFETY_RELEASE_MS,
+            ..Default::default()
+        };
+        let format = target.format.unwrap_or(self.format);
+        let encode_opts = encode::EncodeOptions {
+            bit_depth: target.bit_depth.unwrap_or(self.bit_depth),
+            sample_format: SampleFormat::Int,
+            bitrate_kbps,
+            dither: self.dither,
+        };
+
+        let rendered = (|| {
+            let audio = analysis::decode_audio(master)?;
+            encode::write_wav(
+                &work,
+                &audio.samples,
+                audio.channels,
+                audio.sample_rate,
+                &float,
+            )?;
+            verify::correct_loudness(
+                &work,
+                target.target_lufs,
+                self.tolerance,
+                self.max_passes,
+                &limiter,
+                &float,
+            )?;
+            if verify::measure_true_peak(&work)? > self.ceiling_db + verify::CEILING_MARGIN_DB {
+                verify::enforce_ceiling(&work, self.ceiling_db, SAFETY_RELEASE_MS, &float)?;
+            }
+            convert_format(&work, output, format, &encode_opts, self.ffmpeg_fallback)
+        })();
+        remove_temp_files(std::slice::from_ref(&work));
+        rendered?;
+
+        let audio = analysis::decode_audio(output)?;
+        Ok(Deliverable {
+            path: output.to_path_buf(),
+            target: target.clone(),
+            lufs_integrated: analysis::loudness::integrated_loudness(&audio),
+            true_peak_db: analysis::true_peak::true_peak_db(&audio.samples, audio.channels),
+        })
+    }
+}
+
+/// Path of the file written for `target`, next to the main output.
+pub fn target_output_path(
+    output: &Path,
+    target: &DeliveryTarget,
+    default_format: AudioFormat,
+) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let ext = target.format.unwrap_or(default_format).extension();
+    output.with_file_name(format!("{stem}_{}.{ext}", target.file_suffix()))
+}
+
+/// The stems of a job's input summed back together after adjustment.
+struct StemRemix {
+    path: PathBuf,
+    adjustments: BTreeMap<String, StemAdjustment>,
+    corrections: Vec<ParamCorrection>,
+    usage: Option<TokenUsage>,
+}
+
+/// Separate `input` into stems, adjust them with the job's adjustments or,
+/// when it has none, those `ai` suggests, and write their sum next to
+/// `output_path`.
+async fn separate_and_remix(
+    job: &MasteringJob,
+    config: &Config,
+    ai: Option<&AiBackend>,
+    input: &Path,
+    output_path: &Path,
+    progress: &ProgressReporter,
+) -> Result<StemRemix> {
+    let stems_dir = stems_dir_path(output_path);
+    let result = async {
+        progress.report(PipelineStage::Processing, 0.0, "Separating stems");
+        let (stems, device) = StemSeparator::new(config)
+            .separate(input, &stems_dir, progress)
+            .await
+            .context("Stem separation failed")?;
+        info!(
+            "Separated {} stems on {device}: {}",
+            stems.len(),
+            stems
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+
+        let mut corrections = Vec::new();
+        let mut usage = None;
+        let adjustments = if !job.stem_adjustments.is_empty() {
+            stems::check_stem_names(&job.stem_adjustments, &stems)?;
+            let mut adjustments = job.stem_adjustments.clone();
+            corrections =
+                crate::validate::clamp_stem_adjustments(&mut adjustments, &config.ai.limits);
+            adjustments
+        } else if let Some(ai) = ai {
+            progress.report(PipelineStage::Processing, 0.0, "Analyzing stems");
+            let mut analyses = Vec::with_capacity(stems.len());
+            for stem in &stems {
+                let analysis = analysis::analyze_file(&stem.path)
+                    .await
+                    .with_context(|| format!("Analyzing the {} stem failed", stem.name))?;
+                analyses.push((stem.name.clone(), analysis));
+            }
+            let (adjustments, stem_corrections, stem_usage) = ai
+                .suggest_stem_adjustments(&analyses, job.brief.as_deref(), progress)
+                .await
+                .context("Suggesting stem adjustments failed")?;
+            corrections = stem_corrections;
+            usage = Some(stem_usage);
+            adjustments
+        } else {
+            BTreeMap::new()
+        };
+        for (name, adjustment) in &adjustments {
+            info!(
+                "  {name}: {:+.1} dB, {} EQ band(s)",
+                adjustment.gain_db,
+                adjustment.eq.len()
+            );
+        }
+
+        progress.report(PipelineStage::Processing, 0.0, "Recombining stems");
+        let sample_rate = analysis::decode::AudioStream::open(input)?.sample_rate();
+        let path = temp_wav_path(output_path, "stems");
+        let (out, remix_adjustments) = (path.clone(), adjustments.clone());
+        tokio::task::spawn_blocking(move || {
+            stems::remix(&stems, &remix_adjustments, sample_rate, &out)
+        })
+        .await
+        .context("Stem remix task failed")?
+        .context("Recombining stems failed")?;
+        Ok(StemRemix {
+            path,
+            adjustments,
+            corrections,
+            usage,
+        })
+    }
+    .await;
+    if stems_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&stems_dir) {
+            warn!("Failed to remove stems directory: {e}");
+        }
+    }
+    result
+}
+
+/// Remove the temporary files a job fed to its backend.
+fn remove_temp_files(paths: &[PathBuf]) {
+    for path in paths.iter().filter(|p| p.exists()) {
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!("Failed to remove temporary file {}: {e}", path.display());
+        }
+    }
+}
+
+/// The enabled restoration stages, e.g. "dehum, denoise".
+fn restoration_summary(stages: &RestorationStages) -> String {
+    [
+        (stages.dehum, "dehum"),
+        (stages.declick, "declick"),
+        (stages.decrackle, "decrackle"),
+        (stages.denoise, "denoise"),
+    ]
+    .iter()
+    .filter(|(on, _)| *on)
+    .map(|(_, name)| *name)
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Return a cancellation error if the job's token has fired.
+fn ensure_not_cancelled(job: &MasteringJob) -> Result<(), MasteringError> {
+    if job.cancel_token.is_cancelled() {
+        info!("Mastering job cancelled");
+        return Err(MasteringError::Cancelled);
+    }
+    Ok(())
+}
+
+/// Delete an output file left behind by a cancelled job.
+///
+/// Files that existed before the job started are left alone.
+fn remove_partial_output(path: &Path, existed_before: bool) {
+    if existed_before || !path.exists() {
+        return;
+    }
+    match std::fs::remove_file(path) {
+        Ok(()) => info!("Removed partial output: {}", path.display()),
+        Err(e) => warn!("Failed to remove partial output {}: {e}", path.display()),
+    }
+}
+
+/// Directory the stems of the input are separated into.
+fn stems_dir_path(output: &Path) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    output.with_file_name(format!(".{stem}.stems"))
+}
+
+/// Path of a hidden working WAV next to `output`, such as the backend's
+/// intermediate before conversion or an input stage fed to the backend.
+fn temp_wav_path(output: &Path, suffix: &str) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    output.with_file_name(format!(".{stem}.{suffix}.wav"))
+}
+
+/// Master a surround input without a backend: one gain, linked across all
+/// channels, brings it to the target loudness below a fixed peak ceiling.
+async fn process_surround_pass_through(opts: &MasteringOptions) -> Result<BackendOutput> {
+    let input = opts.input_path.clone();
+    let output = opts.output_path.clone();
+    let target_lufs = opts.target_lufs;
+    let encode_opts = encode::EncodeOptions {
+        bit_depth: opts.bit_depth,
+        sample_format: opts.sample_format,
+        ..Default::default()
+    };
+    let gain_db = tokio::task::spawn_blocking(move || {
+        surround::normalize_linked(
+            &input,
+            &output,
+            target_lufs,
+            SURROUND_CEILING_DB,
+            &encode_opts,
+        )
+    })
+    .await
+    .context("Surround normalization task failed")?
+    .context("Surround normalization failed")?;
+
+    Ok(BackendOutput {
+        output_path: opts.output_path.clone(),
+        params_applied: None,
+        backend_name: "pass-through".into(),
+        message: format!("Applied {gain_db:+.1} dB linked gain"),
+        corrections: Vec::new(),
+        explanation: None,
+        candidates: Vec::new(),
+        usage: None,
+        device: None,
+    })
+}
+
+/// Encode `input` into `format`.
+///
+/// Formats with a native encoder only use ffmpeg when `ffmpeg_fallback` is
+/// set and native encoding fails; AAC, Opus and ALAC always go through ffmpeg.
+fn convert_format(
+    input: &Path,
+    output: &Path,
+    format: AudioFormat,
+    opts: &encode::EncodeOptions,
+    ffmpeg_fallback: bool,
+) -> Result<()> {
     if input == output {
         return Ok(());
     }
 
+    info!("Converting to {} format...", format);
+    if !encode::has_native_encoder(format) {
+        return convert_with_ffmpeg(input, output, format, opts);
+    }
+
+    match encode::encode_file(input, output, format, opts) {
+        Ok(()) => Ok(()),
+        Err(e) if ffmpeg_fallback => {
+            warn!("Native {format} encoding failed ({e:#}); falling back to ffmpeg");
+            convert_with_ffmpeg(input, output, format, opts)
+        }
+        Err(e) => Err(e.context(format!("Encoding {format} output"))),
+    }
+}
+
+/// Convert output format using ffmpeg.
+fn convert_with_ffmpeg(
+    input: &Path,
+    output: &Path,
+    format: AudioFormat,
+    opts: &encode::EncodeOptions,
+) -> Result<()> {
+    let pcm_bits = match opts.bit_depth {
+        0..=16 => 16,
+        17..=24 => 24,
+        _ => 32,
+    };
     let codec = match format {
-        AudioFormat::Wav => return Ok(()), // Already WAV
-        AudioFormat::Flac => "flac",
-        AudioFormat::Mp3 => "libmp3lame",
+        AudioFormat::Wav if opts.sample_format == SampleFormat::Float => "pcm_f32le".into(),
+        AudioFormat::Wav => format!("pcm_s{pcm_bits}le"),
+        AudioFormat::Aiff => format!("pcm_s{pcm_bits}be"),
+        AudioFormat::Flac => "flac".into(),
+        AudioFormat::Mp3 => "libmp3lame".into(),
+        AudioFormat::Aac => "aac".into(),
+        AudioFormat::Opus => "libopus".into(),
+        AudioFormat::Alac => "alac".into(),
     };
 
-    info!("Converting to {} format...", format);
+    let mut args: Vec<String> = vec![
+        "-y".into(),
+        "-i".into(),
+        input.to_string_lossy().into_owned(),
+        "-codec:a".into(),
+        codec,
+    ];
+    if let Some(kbps) = opts.bitrate_kbps.filter(|_| format.is_lossy()) {
+        args.extend(["-b:a".into(), format!("{kbps}k")]);
+    }
+    args.push(output.to_string_lossy().into_owned());
 
     let status = std::process::Command::new("ffmpeg")
-        .args([
-            "-y",
-            "-i",
-            &input.to_string_lossy(),
-            "-codec:a",
-            codec,
-            &output.to_string_lossy(),
-        ])
+        .args(&args)
         .stdout(std::process::Stdio::null())
         .stderr(std::process::Stdio::null())
         .status()
@@ -0,0 +1,137 @@
+//! Progress reporting for the mastering pipeline.
+//!
+//! Callers that want feedback create a channel with [`ProgressReporter::channel`]
+//! and drain the receiver while [`super::run_with_progress`] executes.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// Stage of the mastering pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStage {
+    Validation,
+    Analysis,
+    Processing,
+    PostAnalysis,
+    Conversion,
+    Complete,
+}
+
+impl PipelineStage {
+    /// Overall progress range (percent) covered by this stage.
+    pub fn range(&self) -> (f32, f32) {
+        match self {
+            PipelineStage::Validation => (0.0, 5.0),
+            PipelineStage::Analysis => (5.0, 30.0),
+            PipelineStage::Processing => (30.0, 80.0),
+            PipelineStage::PostAnalysis => (80.0, 90.0),
+            PipelineStage::Conversion => (90.0, 100.0),
+            PipelineStage::Complete => (100.0, 100.0),
+        }
+    }
+}
+
+impl std::fmt::Display for PipelineStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PipelineStage::Validation => write!(f, "validation"),
+            PipelineStage::Analysis => write!(f, "analysis"),
+            PipelineStage::Processing => write!(f, "processing"),
+            PipelineStage::PostAnalysis => write!(f, "post_analysis"),
+            PipelineStage::Conversion => write!(f, "conversion"),
+            PipelineStage::Complete => write!(f, "complete"),
+        }
+    }
+}
+
+/// A single progress update.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressUpdate {
+    pub stage: PipelineStage,
+    /// Progress within the current stage (0–100).
+    pub stage_percent: f32,
+    /// Overall pipeline progress (0–100).
+    pub percent: f32,
+    pub message: String,
+    /// Tokens received so far while a language model streams its answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<u64>,
+}
+
+/// Sends [`ProgressUpdate`]s to an optional listener.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressReporter {
+    sender: Option<mpsc::UnboundedSender<ProgressUpdate>>,
+}
+
+impl ProgressReporter {
+    /// A reporter that discards all updates.
+    pub fn disabled() -> Self {
+        Self { sender: None }
+    }
+
+    /// Create a reporter together with the receiving end of its channel.
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<ProgressUpdate>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { sender: Some(tx) }, rx)
+    }
+
+    /// Report progress within `stage`; `stage_percent` is clamped to 0–100.
+    pub fn report(&self, stage: PipelineStage, stage_percent: f32, message: impl Into<String>) {
+        self.send(stage, stage_percent, message.into(), None);
+    }
+
+    /// Report progress along with the number of tokens a streaming model
+    /// has produced.
+    pub fn report_tokens(
+        &self,
+        stage: PipelineStage,
+        stage_percent: f32,
+        message: impl Into<String>,
+        tokens: u64,
+    ) {
+        self.send(stage, stage_percent, message.into(), Some(tokens));
+    }
+
+    fn send(&self, stage: PipelineStage, stage_percent: f32, message: String, tokens: Option<u64>) {
+        let Some(ref sender) = self.sender else {
+            return;
+        };
+        let stage_percent = stage_percent.clamp(0.0, 100.0);
+        let (start, end) = stage.range();
+        let percent = start + (end - start) * stage_percent / 100.0;
+        // A dropped receiver just means nobody is listening any more
+        let _ = sender.send(ProgressUpdate {
+            stage,
+            stage_percent,
+            percent,
+            message,
+            tokens,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overall_percent_mapping() {
+        let (reporter, mut rx) = ProgressReporter::channel();
+        reporter.report(PipelineStage::Processing, 50.0, "halfway");
+        let update = rx.try_recv().unwrap();
+        assert_eq!(update.stage, PipelineStage::Processing);
+        assert!((update.percent - 55.0).abs() < f32::EPSILON);
+        assert_eq!(update.tokens, None);
+
+        reporter.report_tokens(PipelineStage::Processing, 0.0, "thinking", 42);
+        assert_eq!(rx.try_recv().unwrap().tokens, Some(42));
+    }
+
+    #[test]
+    fn test_disabled_reporter_is_silent() {
+        let reporter = ProgressReporter::disabled();
+        reporter.report(PipelineStage::Analysis, 10.0, "ignored");
+    }
+}
@@ -0,0 +1,95 @@
+//! Review of suggested parameters before they are rendered.
+
+use anyhow::{Context, Result};
+use std::sync::Arc;
+
+use crate::types::MasteringParams;
+
+type ReviewFn = dyn Fn(MasteringParams) -> Result<MasteringParams> + Send + Sync;
+
+/// Hands the parameters a backend suggests to the caller, who may change
+/// them, before anything is rendered. The callback may block, e.g. on a
+/// prompt; it runs on a blocking thread. An error from it fails the job.
+#[derive(Clone, Default)]
+pub struct ParamReview {
+    callback: Option<Arc<ReviewFn>>,
+}
+
+impl ParamReview {
+    pub fn new(
+        callback: impl Fn(MasteringParams) -> Result<MasteringParams> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            callback: Some(Arc::new(callback)),
+        }
+    }
+
+    /// No review; parameters are rendered as suggested.
+    pub fn disabled() -> Self {
+        Self { callback: None }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.callback.is_some()
+    }
+
+    /// The reviewed parameters, or `params` unchanged without a reviewer.
+    pub async fn review(&self, params: MasteringParams) -> Result<MasteringParams> {
+        let Some(ref callback) = self.callback else {
+            return Ok(params);
+        };
+        let callback = callback.clone();
+        tokio::task::spawn_blocking(move || callback(params))
+            .await
+            .context("Parameter review task failed")?
+    }
+}
+
+impl std::fmt::Debug for ParamReview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParamReview")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params() -> MasteringParams {
+        serde_json::from_str(
+            r#"{
+                "eq": [],
+                "compression": { "threshold_db": -18.0, "ratio": 2.0, "attack_ms": 10.0,
+                                 "release_ms": 100.0, "knee_db": 6.0, "makeup_gain_db": 0.0 },
+                "limiter": { "enabled": true, "ceiling_db": -1.0, "release_ms": 50.0 },
+                "stereo": { "width": 1.0, "balance": 0.0 },
+                "target_lufs": -14.0
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reviewer_replaces_params() {
+        let review = ParamReview::new(|mut p| {
+            p.compression.ratio = 4.0;
+            Ok(p)
+        });
+        assert!(review.is_enabled());
+        assert_eq!(
+            review.review(params()).await.unwrap().compression.ratio,
+            4.0
+        );
+
+        let disabled = ParamReview::disabled();
+        assert_eq!(
+            disabled.review(params()).await.unwrap().compression.ratio,
+            2.0
+        );
+
+        let rejecting = ParamReview::new(|_| anyhow::bail!("rejected"));
+        assert!(rejecting.review(params()).await.is_err());
+    }
+}
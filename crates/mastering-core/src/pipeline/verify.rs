@@ -0,0 +1,286 @@
+//! Verification of the mastered output against the requested loudness and
+//! peak ceiling.
+//!
+//! Backends estimate loudness differently and often land 1-2 LU off target.
+//! The output is measured with the BS.1770 meter and, when it misses the
+//! target by more than the tolerance, corrected with a gain and limiter pass
+//! that is re-measured until it lands within tolerance or runs out of passes.
+//!
+//! A final safety check measures the true peak; output above the ceiling is
+//! limited again with the ceiling lowered by the inter-sample overshoot.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::analysis::decode::decode_audio;
+use crate::analysis::loudness::{integrated_loudness, SILENCE_LUFS};
+use crate::analysis::true_peak;
+use crate::dsp::dynamics;
+use crate::encode::{self, EncodeOptions};
+use crate::types::LimiterParams;
+
+/// Outcome of a loudness correction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoudnessCorrection {
+    /// Loudness measured before correction.
+    pub initial_lufs: f64,
+    /// Loudness after the last pass.
+    pub final_lufs: f64,
+    /// Total gain applied in dB.
+    pub gain_db: f64,
+    /// Number of gain/limiter passes run.
+    pub passes: u32,
+}
+
+/// Bring the WAV file at `path` within `tolerance_lu` of `target_lufs`.
+///
+/// Returns `None` when the file is already within tolerance (or silent) and
+/// was left untouched. Without a limiter, boosts stop short of 0 dBFS.
+pub fn correct_loudness(
+    path: &Path,
+    target_lufs: f64,
+    tolerance_lu: f64,
+    max_passes: u32,
+    limiter: &LimiterParams,
+    opts: &EncodeOptions,
+) -> Result<Option<LoudnessCorrection>> {
+    let mut audio = decode_audio(path)?;
+    let initial_lufs = integrated_loudness(&audio);
+    if max_passes == 0
+        || initial_lufs <= SILENCE_LUFS
+        || (initial_lufs - target_lufs).abs() <= tolerance_lu
+    {
+        return Ok(None);
+    }
+
+    let channels = audio.channels as usize;
+    let original = audio.samples.clone();
+    let peak = original.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    let headroom_db = if peak > 0.0 {
+        -20.0 * (peak as f64).log10()
+    } else {
+        0.0
+    };
+
+    let mut lufs = initial_lufs;
+    let mut gain_db = 0.0;
+    let mut passes = 0;
+    while passes < max_passes && (lufs - target_lufs).abs() > tolerance_lu {
+        let mut next_gain = gain_db + target_lufs - lufs;
+        if !limiter.enabled {
+            next_gain = next_gain.min(headroom_db);
+        }
+        if passes > 0 && (next_gain - gain_db).abs() < 1e-3 {
+            break; // no further progress possible
+        }
+        gain_db = next_gain;
+
+        audio.samples.copy_from_slice(&original);
+        dynamics::apply_gain_db(&mut audio.samples, gain_db);
+        dynamics::limit(&mut audio.samples, channels, audio.sample_rate, limiter);
+        lufs = integrated_loudness(&audio);
+        passes += 1;
+    }
+
+    // Write next to the original and swap so a failure leaves it intact
+    let tmp = path.with_extension("loudness.wav");
+    encode::write_wav(
+        &tmp,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        opts,
+    )?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Replacing {}", path.display()))?;
+
+    Ok(Some(LoudnessCorrection {
+        initial_lufs,
+        final_lufs: lufs,
+        gain_db,
+        passes,
+    }))
+}
+
+/// True peak above the ceiling, in dB, tolerated by the safety check.
+pub const CEILING_MARGIN_DB: f64 = 0.1;
+
+/// Limiter passes the safety check runs before giving up.
+const MAX_PEAK_PASSES: u32 = 4;
+
+/// Outcome of a true-peak correction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeakCorrection {
+    /// True peak in dBTP before correction.
+    pub initial_db: f64,
+    /// True peak in dBTP after the last pass.
+    pub final_db: f64,
+    /// Number of limiter passes run.
+    pub passes: u32,
+}
+
+/// True peak of the file at `path` in dBTP.
+pub fn measure_true_peak(path: &Path) -> Result<f64> {
+    let audio = decode_audio(path)?;
+    Ok(true_peak::true_peak_db(&audio.samples, audio.channels))
+}
+
+/// Limit the WAV file at `path` until its true peak is within
+/// [`CEILING_MARGIN_DB`] of `ceiling_db`.
+///
+/// Returns `None` when the file already complies and was left untouched.
+pub fn enforce_ceiling(
+    path: &Path,
+    ceiling_db: f64,
+    release_ms: f64,
+    opts: &EncodeOptions,
+) -> Result<Option<PeakCorrection>> {
+    let mut audio = decode_audio(path)?;
+    let channels = audio.channels as usize;
+    let initial_db = true_peak::true_peak_db(&audio.samples, audio.channels);
+    if initial_db <= ceiling_db + CEILING_MARGIN_DB {
+        return Ok(None);
+    }
+
+    // The limiter detects true peaks itself; any overshoot its gain changes
+    // leave is taken off the ceiling for the next pass
+    let original = audio.samples.clone();
+    let mut limit_db = ceiling_db;
+    let mut final_db = initial_db;
+    let mut passes = 0;
+    while passes < MAX_PEAK_PASSES && final_db > ceiling_db + CEILING_MARGIN_DB {
+        if passes > 0 {
+            limit_db -= final_db - ceiling_db;
+        }
+        audio.samples.copy_from_slice(&original);
+        let limiter = LimiterParams {
+            enabled: true,
+            ceiling_db: limit_db,
+            release_ms,
+            ..Default::default()
+        };
+        dynamics::limit(&mut audio.samples, channels, audio.sample_rate, &limiter);
+        final_db = true_peak::true_peak_db(&audio.samples, audio.channels);
+        passes += 1;
+    }
+
+    let tmp = path.with_extension("limit.wav");
+    encode::write_wav(
+        &tmp,
+        &audio.samples,
+        audio.channels,
+        audio.sample_rate,
+        opts,
+    )?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Replacing {}", path.display()))?;
+
+    Ok(Some(PeakCorrection {
+        initial_db,
+        final_db,
+        passes,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SampleFormat;
+
+    fn float_opts() -> EncodeOptions {
+        EncodeOptions {
+            bit_depth: 32,
+            sample_format: SampleFormat::Float,
+            ..Default::default()
+        }
+    }
+
+    fn write_tone(path: &Path, amplitude: f32) {
+        let samples: Vec<f32> = (0..48000 * 4)
+            .flat_map(|i| {
+                let s =
+                    amplitude * (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0).sin();
+                [s, s]
+            })
+            .collect();
+        encode::write_wav(path, &samples, 2, 48000, &float_opts()).unwrap();
+    }
+
+    fn limiter(enabled: bool) -> LimiterParams {
+        LimiterParams {
+            enabled,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_corrects_quiet_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.wav");
+        write_tone(&path, 0.05);
+
+        let fix = correct_loudness(&path, -14.0, 0.5, 3, &limiter(true), &float_opts())
+            .unwrap()
+            .expect("output should be corrected");
+        assert!(fix.passes >= 1);
+        let lufs = integrated_loudness(&decode_audio(&path).unwrap());
+        assert!((lufs + 14.0).abs() <= 0.5, "lufs {lufs}");
+        assert!((lufs - fix.final_lufs).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_within_tolerance_is_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.wav");
+        write_tone(&path, 0.05);
+        let lufs = integrated_loudness(&decode_audio(&path).unwrap());
+        let before = std::fs::read(&path).unwrap();
+
+        let fix =
+            correct_loudness(&path, lufs + 0.3, 0.5, 3, &limiter(true), &float_opts()).unwrap();
+        assert!(fix.is_none());
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+    }
+
+    #[test]
+    fn test_enforce_ceiling_on_clipping_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.wav");
+        // Quarter-rate tone whose inter-sample peaks overshoot its samples by 3 dB
+        let samples: Vec<f32> = (0..48000)
+            .flat_map(|i| {
+                let s =
+                    (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin();
+                [s, s]
+            })
+            .collect();
+        encode::write_wav(&path, &samples, 2, 48000, &float_opts()).unwrap();
+        assert!(measure_true_peak(&path).unwrap() > -0.5);
+
+        let fix = enforce_ceiling(&path, -1.0, 50.0, &float_opts())
+            .unwrap()
+            .expect("output should be limited");
+        assert!(fix.final_db <= -1.0 + CEILING_MARGIN_DB, "{fix:?}");
+        assert!(measure_true_peak(&path).unwrap() <= -1.0 + CEILING_MARGIN_DB);
+    }
+
+    #[test]
+    fn test_enforce_ceiling_leaves_compliant_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.wav");
+        write_tone(&path, 0.5);
+        assert!(enforce_ceiling(&path, -1.0, 50.0, &float_opts())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_no_limiter_stops_at_full_scale() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.wav");
+        write_tone(&path, 0.5);
+
+        correct_loudness(&path, -3.0, 0.5, 3, &limiter(false), &float_opts()).unwrap();
+        let audio = decode_audio(&path).unwrap();
+        let peak = audio.samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(peak <= 1.0 + 1e-5, "peak {peak}");
+    }
+}
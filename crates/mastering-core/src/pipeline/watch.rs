@@ -0,0 +1,286 @@
+//! Watch folders: master every audio file that appears in a folder.
+
+use anyhow::{Context, Result};
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
+
+use super::{is_supported_input, run, CancellationToken, MasteringJob};
+use crate::config::Config;
+use crate::types::MasteringResult;
+
+/// How often files waiting to settle are checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A folder whose new audio files are mastered into another folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchFolder {
+    pub input_dir: PathBuf,
+    /// Where masters are written; files in it are never picked up, even
+    /// when it lies inside `input_dir`.
+    pub output_dir: PathBuf,
+    /// Also watch sub-folders; their masters go to matching sub-folders
+    /// of `output_dir`.
+    #[serde(default)]
+    pub recursive: bool,
+    /// Master the audio files already in the folder when watching starts.
+    #[serde(default)]
+    pub existing: bool,
+    /// Seconds a file's size must stay the same before it is mastered, so
+    /// files still being copied in are left alone.
+    #[serde(default = "default_settle_secs")]
+    pub settle_secs: f64,
+}
+
+fn default_settle_secs() -> f64 {
+    2.0
+}
+
+impl WatchFolder {
+    pub fn new(input_dir: impl Into<PathBuf>, output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            input_dir: input_dir.into(),
+            output_dir: output_dir.into(),
+            recursive: false,
+            existing: false,
+            settle_secs: default_settle_secs(),
+        }
+    }
+}
+
+/// Something that happened to a file in a watch folder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WatchEvent {
+    /// A new file appeared and is waiting to settle.
+    Detected {
+        input_path: PathBuf,
+    },
+    /// Mastering of the file started.
+    Started {
+        input_path: PathBuf,
+    },
+    Mastered {
+        input_path: PathBuf,
+        result: Box<MasteringResult>,
+    },
+    Failed {
+        input_path: PathBuf,
+        error: String,
+    },
+}
+
+/// Files mastered by a watch folder before it was stopped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchSummary {
+    pub mastered: usize,
+    pub failed: usize,
+}
+
+/// A file whose size is watched until it stops changing.
+struct Settling {
+    size: u64,
+    since: Instant,
+}
+
+/// Master each audio file that appears in `folder` until `cancel` is
+/// cancelled, one file at a time.
+///
+/// `make_job` builds the job for a file; its output path is replaced with
+/// one in the output folder. Files are picked up again when they are
+/// replaced by a newer version. Cancelling also cancels the job in progress.
+pub async fn watch_folder<F>(
+    folder: &WatchFolder,
+    config: &Config,
+    make_job: F,
+    events: Option<mpsc::UnboundedSender<WatchEvent>>,
+    cancel: CancellationToken,
+) -> Result<WatchSummary>
+where
+    F: Fn(&Path) -> Result<MasteringJob>,
+{
+    let input_dir = folder
+        .input_dir
+        .canonicalize()
+        .with_context(|| format!("Watch folder not found: {}", folder.input_dir.display()))?;
+    anyhow::ensure!(input_dir.is_dir(), "Not a folder: {}", input_dir.display());
+    std::fs::create_dir_all(&folder.output_dir)
+        .with_context(|| format!("Creating output folder {}", folder.output_dir.display()))?;
+    let output_dir = folder.output_dir.canonicalize()?;
+    anyhow::ensure!(
+        output_dir != input_dir,
+        "The output folder must differ from the watched folder"
+    );
+    let settle = Duration::from_secs_f64(folder.settle_secs.max(0.0));
+    let send = |event: WatchEvent| {
+        if let Some(ref sender) = events {
+            // A dropped receiver just means nobody is listening any more
+            let _ = sender.send(event);
+        }
+    };
+
+    let (tx, mut changed) = mpsc::unbounded_channel();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+            Ok(event) if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) => {
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Watch folder error: {e}"),
+        })
+        .context("Starting the folder watcher")?;
+    let mode = if folder.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher
+        .watch(&input_dir, mode)
+        .with_context(|| format!("Watching {}", input_dir.display()))?;
+    tracing::info!("Watching {} for new audio files", input_dir.display());
+
+    let wanted = |path: &Path| {
+        is_supported_input(path)
+            && !path.starts_with(&output_dir)
+            && !path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with('.'))
+    };
+    let mut pending: HashMap<PathBuf, Settling> = HashMap::new();
+    // Modification time of each file when it was mastered
+    let mut mastered: HashMap<PathBuf, Option<SystemTime>> = HashMap::new();
+    if folder.existing {
+        for path in audio_files(&input_dir, folder.recursive)? {
+            if wanted(&path) {
+                send(WatchEvent::Detected {
+                    input_path: path.clone(),
+                });
+                pending.insert(
+                    path,
+                    Settling {
+                        size: 0,
+                        since: Instant::now(),
+                    },
+                );
+            }
+        }
+    }
+
+    let mut summary = WatchSummary::default();
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            Some(path) = changed.recv() => {
+                if pending.contains_key(&path) || !wanted(&path) || !path.is_file() {
+                    continue;
+                }
+                if mastered.get(&path).is_some_and(|&mtime| mtime == modified(&path)) {
+                    continue;
+                }
+                send(WatchEvent::Detected { input_path: path.clone() });
+                pending.insert(path, Settling { size: 0, since: Instant::now() });
+            }
+            _ = interval.tick() => {
+                let mut ready = Vec::new();
+                pending.retain(|path, settling| {
+                    let Ok(meta) = std::fs::metadata(path) else {
+                        // Gone again before it settled
+                        return false;
+                    };
+                    if meta.len() != settling.size {
+                        settling.size = meta.len();
+                        settling.since = Instant::now();
+                    } else if settling.size > 0 && settling.since.elapsed() >= settle {
+                        ready.push(path.clone());
+                        return false;
+                    }
+                    true
+                });
+                ready.sort();
+
+                for path in ready {
+                    if cancel.is_cancelled() {
+                        break;
+                    }
+                    mastered.insert(path.clone(), modified(&path));
+                    send(WatchEvent::Started { input_path: path.clone() });
+                    let job = make_job(&path)
+                        .and_then(|job| watch_job(job, &path, &input_dir, &output_dir, config, &cancel));
+                    let outcome = match job {
+                        Ok(job) => run(&job, config).await,
+                        Err(e) => Err(e),
+                    };
+                    match outcome {
+                        Ok(result) => {
+                            tracing::info!("Watch folder mastered {}", path.display());
+                            summary.mastered += 1;
+                            send(WatchEvent::Mastered { input_path: path, result: Box::new(result) });
+                        }
+                        // Stopping the watch is not a failure of the file
+                        Err(_) if cancel.is_cancelled() => break,
+                        Err(e) => {
+                            tracing::warn!("Watch folder failed to master {}: {e:#}", path.display());
+                            summary.failed += 1;
+                            send(WatchEvent::Failed { input_path: path, error: format!("{e:#}") });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// `job` with its output in the output folder, at the same place relative
+/// to it as `input` is to the watched folder, and cancelled with the watch.
+fn watch_job(
+    mut job: MasteringJob,
+    input: &Path,
+    input_dir: &Path,
+    output_dir: &Path,
+    config: &Config,
+    cancel: &CancellationToken,
+) -> Result<MasteringJob> {
+    let name = job
+        .resolved_output_path(config)
+        .file_name()
+        .map(PathBuf::from)
+        .context("Output path has no file name")?;
+    let relative = input
+        .parent()
+        .and_then(|parent| parent.strip_prefix(input_dir).ok())
+        .unwrap_or(Path::new(""));
+    let dir = output_dir.join(relative);
+    std::fs::create_dir_all(&dir).with_context(|| format!("Creating {}", dir.display()))?;
+    job.output_path = Some(dir.join(name));
+    job.cancel_token = cancel.child_token();
+    Ok(job)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Supported audio files in `dir`, and in its sub-folders when `recursive`.
+fn audio_files(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Reading {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if recursive {
+                files.extend(audio_files(&path, true)?);
+            }
+        } else if is_supported_input(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,208 @@
+//! Saved mastering recipes.
+//!
+//! A recipe is the exact parameter set a master was rendered with, kept as
+//! `<config dir>/recipes/<name>.json`. The recipe backend renders it on
+//! other tracks as it is, without asking any backend for new parameters, so
+//! the same recipe always yields the same chain.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::config::Config;
+use crate::types::{MasteringParams, MasteringResult};
+
+/// A named parameter set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipe {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Backend that chose the parameters.
+    #[serde(default)]
+    pub source_backend: Option<String>,
+    /// File the parameters were first applied to.
+    #[serde(default)]
+    pub source_file: Option<String>,
+    /// Seconds since the Unix epoch.
+    #[serde(default)]
+    pub created_at: u64,
+    pub params: MasteringParams,
+}
+
+impl Recipe {
+    /// A recipe of the parameters `result` was rendered with.
+    pub fn from_result(name: &str, input_path: &Path, result: &MasteringResult) -> Result<Self> {
+        let params = result
+            .params_applied
+            .clone()
+            .context("The backend did not report the parameters it applied")?;
+        Ok(Self {
+            name: name.to_string(),
+            description: None,
+            source_backend: Some(result.backend_used.clone()),
+            source_file: input_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned()),
+            created_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            params,
+        })
+    }
+}
+
+/// Directory of saved recipes.
+#[derive(Debug, Clone)]
+pub struct RecipeStore {
+    dir: PathBuf,
+}
+
+impl RecipeStore {
+    /// Create a store that keeps recipes in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Default location (`<config dir>/recipes`).
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("recipes"))
+    }
+
+    /// Store in the default location.
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(Self::default_dir()?))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.json"))
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        validate_name(name).is_ok() && self.path(name).is_file()
+    }
+
+    /// Save `recipe` under its name, replacing any recipe of that name.
+    pub fn save(&self, recipe: &Recipe) -> Result<PathBuf> {
+        validate_name(&recipe.name)?;
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Creating recipe directory: {}", self.dir.display()))?;
+        let path = self.path(&recipe.name);
+        std::fs::write(&path, serde_json::to_string_pretty(recipe)?)
+            .with_context(|| format!("Writing recipe: {}", path.display()))?;
+        info!("Saved recipe {} to {}", recipe.name, path.display());
+        Ok(path)
+    }
+
+    pub fn load(&self, name: &str) -> Result<Recipe> {
+        validate_name(name)?;
+        let path = self.path(name);
+        anyhow::ensure!(
+            path.is_file(),
+            "No recipe named '{name}' in {}",
+            self.dir.display()
+        );
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Reading recipe: {}", path.display()))?;
+        let mut recipe: Recipe = serde_json::from_str(&json)
+            .with_context(|| format!("Parsing recipe: {}", path.display()))?;
+        // The file name is the recipe's name, even if the file was copied
+        recipe.name = name.to_string();
+        Ok(recipe)
+    }
+
+    /// Every saved recipe, by name. Files that fail to parse are skipped.
+    pub fn list(&self) -> Result<Vec<Recipe>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                if let Some(stem) = path.file_stem() {
+                    names.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        names.sort();
+        Ok(names
+            .iter()
+            .filter_map(|name| self.load(name).ok())
+            .collect())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<()> {
+        validate_name(name)?;
+        let path = self.path(name);
+        anyhow::ensure!(path.is_file(), "No recipe named '{name}'");
+        std::fs::remove_file(&path).with_context(|| format!("Removing {}", path.display()))?;
+        info!("Removed recipe {name}");
+        Ok(())
+    }
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    anyhow::ensure!(
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')),
+        "Invalid recipe name '{name}': use letters, digits, '-', '_' and '.'"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipe(name: &str) -> Recipe {
+        Recipe {
+            name: name.into(),
+            description: Some("Warm".into()),
+            source_backend: Some("ai".into()),
+            source_file: Some("song.wav".into()),
+            created_at: 1,
+            params: serde_json::from_str(
+                r#"{
+                    "eq": [],
+                    "compression": { "threshold_db": -18.0, "ratio": 2.0, "attack_ms": 10.0,
+                                     "release_ms": 100.0, "knee_db": 6.0, "makeup_gain_db": 0.0 },
+                    "limiter": { "enabled": true, "ceiling_db": -1.0, "release_ms": 50.0 },
+                    "stereo": { "width": 1.0, "balance": 0.0 },
+                    "target_lufs": -14.0
+                }"#,
+            )
+            .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_save_load_list_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RecipeStore::new(dir.path().join("recipes"));
+        assert!(store.list().unwrap().is_empty());
+
+        store.save(&recipe("warm")).unwrap();
+        store.save(&recipe("bright")).unwrap();
+        let loaded = store.load("warm").unwrap();
+        assert_eq!(loaded.params.compression.ratio, 2.0);
+        assert_eq!(loaded.source_file.as_deref(), Some("song.wav"));
+
+        let names: Vec<_> = store.list().unwrap().into_iter().map(|r| r.name).collect();
+        assert_eq!(names, ["bright", "warm"]);
+
+        store.remove("warm").unwrap();
+        assert!(!store.exists("warm"));
+        assert!(store.load("warm").is_err());
+        assert!(store.save(&recipe("../escape")).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Library of reference tracks for reference-based mastering.
+//!
+//! References are copied into `<config dir>/references` and listed, with
+//! genre and mood tags, in `index.json` there. A job can then name a tag
+//! instead of a file, and the library picks a reference carrying it.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::config::Config;
+
+const INDEX_FILE: &str = "index.json";
+
+/// A reference track in the library.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceEntry {
+    pub name: String,
+    /// File name of the copy inside the library directory.
+    pub file: String,
+    /// Lowercase genre and mood tags, e.g. "techno", "dark".
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Seconds since the Unix epoch.
+    #[serde(default)]
+    pub added_at: u64,
+}
+
+impl ReferenceEntry {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = normalize_tag(tag);
+        self.tags.contains(&tag)
+    }
+}
+
+/// Directory of reference tracks and their index.
+#[derive(Debug, Clone)]
+pub struct ReferenceLibrary {
+    dir: PathBuf,
+}
+
+impl ReferenceLibrary {
+    /// Create a library kept in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Default location (`<config dir>/references`).
+    pub fn default_dir() -> Result<PathBuf> {
+        Ok(Config::config_dir()?.join("references"))
+    }
+
+    /// Library in the default location.
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(Self::default_dir()?))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Full path of an entry's audio file.
+    pub fn path(&self, entry: &ReferenceEntry) -> PathBuf {
+        self.dir.join(&entry.file)
+    }
+
+    /// Every reference, by name.
+    pub fn list(&self) -> Result<Vec<ReferenceEntry>> {
+        let mut entries = self.read_index()?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    pub fn get(&self, name: &str) -> Result<ReferenceEntry> {
+        self.list()?
+            .into_iter()
+            .find(|e| e.name == name)
+            .with_context(|| format!("No reference named '{name}' in the library"))
+    }
+
+    /// Copy `source` into the library under `name` (the file stem when
+    /// `None`) with the given tags.
+    pub fn add(
+        &self,
+        source: &Path,
+        name: Option<&str>,
+        tags: &[String],
+    ) -> Result<ReferenceEntry> {
+        anyhow::ensure!(
+            source.is_file(),
+            "Reference file not found: {}",
+            source.display()
+        );
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => source
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .context("The reference file has no name")?,
+        };
+        validate_name(&name)?;
+        let mut entries = self.read_index()?;
+        anyhow::ensure!(
+            !entries.iter().any(|e| e.name == name),
+            "A reference named '{name}' is already in the library"
+        );
+
+        let extension = source
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let file = if extension.is_empty() {
+            name.clone()
+        } else {
+            format!("{name}.{extension}")
+        };
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Creating reference library: {}", self.dir.display()))?;
+        std::fs::copy(source, self.dir.join(&file))
+            .with_context(|| format!("Copying {} into the reference library", source.display()))?;
+
+        let mut tags: Vec<String> = tags
+            .iter()
+            .map(|t| normalize_tag(t))
+            .filter(|t| !t.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        let entry = ReferenceEntry {
+            name,
+            file,
+            tags,
+            added_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        entries.push(entry.clone());
+        self.write_index(&entries)?;
+        info!("Added reference {} to the library", entry.name);
+        Ok(entry)
+    }
+
+    /// Remove a reference and its copied file.
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let mut entries = self.read_index()?;
+        let index = entries
+            .iter()
+            .position(|e| e.name == name)
+            .with_context(|| format!("No reference named '{name}' in the library"))?;
+        let entry = entries.remove(index);
+        let path = self.path(&entry);
+        if path.exists() {
+            std::fs::remove_file(&path).with_context(|| format!("Removing {}", path.display()))?;
+        }
+        self.write_index(&entries)?;
+        info!("Removed reference {name} from the library");
+        Ok(())
+    }
+
+    /// Every reference carrying `tag`.
+    pub fn with_tag(&self, tag: &str) -> Result<Vec<ReferenceEntry>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .filter(|e| e.has_tag(tag))
+            .collect())
+    }
+
+    /// Path of the reference to master against for `tag`: the most
+    /// recently added reference carrying it.
+    pub fn pick(&self, tag: &str) -> Result<PathBuf> {
+        // The index keeps references in the order they were added
+        let entry = self
+            .read_index()?
+            .into_iter()
+            .rfind(|e| e.has_tag(tag))
+            .with_context(|| {
+                format!(
+                    "No reference in the library is tagged '{}'",
+                    normalize_tag(tag)
+                )
+            })?;
+        info!("Using reference {} for tag {tag}", entry.name);
+        Ok(self.path(&entry))
+    }
+
+    fn read_index(&self) -> Result<Vec<ReferenceEntry>> {
+        let path = self.dir.join(INDEX_FILE);
+        if !path.is_file() {
+            return Ok(Vec::new());
+        }
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Reading reference index: {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Parsing reference index: {}", path.display()))
+    }
+
+    fn write_index(&self, entries: &[ReferenceEntry]) -> Result<()> {
+        let path = self.dir.join(INDEX_FILE);
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Creating reference library: {}", self.dir.display()))?;
+        std::fs::write(&path, serde_json::to_string_pretty(entries)?)
+            .with_context(|| format!("Writing reference index: {}", path.display()))
+    }
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().to_lowercase()
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    anyhow::ensure!(
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name != "index"
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ' ')),
+        "Invalid reference name '{name}': use letters, digits, spaces, '-', '_' and '.'"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_pick_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("Club Track.wav");
+        std::fs::write(&source, b"RIFF").unwrap();
+        let library = ReferenceLibrary::new(dir.path().join("references"));
+
+        let entry = library
+            .add(
+                &source,
+                None,
+                &["Techno".into(), " dark ".into(), "techno".into()],
+            )
+            .unwrap();
+        assert_eq!(entry.name, "Club Track");
+        assert_eq!(entry.tags, ["dark", "techno"]);
+        assert!(library.path(&entry).is_file());
+        assert!(library.add(&source, None, &[]).is_err(), "names are unique");
+
+        library
+            .add(&source, Some("ambient"), &["chill".into()])
+            .unwrap();
+        assert_eq!(library.pick("TECHNO").unwrap(), library.path(&entry));
+        assert!(library.pick("jazz").is_err());
+
+        library.remove("Club Track").unwrap();
+        assert!(!library.path(&entry).exists());
+        assert_eq!(library.list().unwrap().len(), 1);
+    }
+}
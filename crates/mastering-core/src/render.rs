@@ -0,0 +1,441 @@
+//! Images of audio for quick visual checks.
+//!
+//! A spectrogram drawn on a log-frequency axis shows at a glance what the
+//! numbers hide: the shelf of a lossy source cutting off around 16 kHz, hum
+//! lines, broadband noise under quiet passages. Waveform images of an input
+//! and its master, stacked or overlaid, show what the limiter did for
+//! delivery notes. Images are plain RGB and written as PNG.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::analysis::decode::{decode_audio, DecodedAudio};
+use crate::analysis::spectrum::compute_spectrogram;
+use crate::peaks::{self, ChannelSelection, PeakCache};
+
+/// Colours a spectrogram is drawn with, from quiet to loud.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Colormap {
+    /// Black through purple and orange to pale yellow.
+    #[default]
+    Magma,
+    /// Dark blue through teal to yellow.
+    Viridis,
+    /// Black to white.
+    Gray,
+}
+
+impl Colormap {
+    /// Anchor colours, evenly spaced from 0 to 1.
+    fn anchors(&self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Magma => &[
+                [0, 0, 4],
+                [59, 15, 112],
+                [140, 41, 129],
+                [222, 73, 104],
+                [254, 159, 109],
+                [252, 253, 191],
+            ],
+            Colormap::Viridis => &[
+                [68, 1, 84],
+                [59, 82, 139],
+                [33, 145, 140],
+                [94, 201, 98],
+                [253, 231, 37],
+            ],
+            Colormap::Gray => &[[0, 0, 0], [255, 255, 255]],
+        }
+    }
+
+    /// Colour of `value`, clamped to 0..=1.
+    pub fn color(&self, value: f32) -> [u8; 3] {
+        let anchors = self.anchors();
+        let pos = value.clamp(0.0, 1.0) * (anchors.len() - 1) as f32;
+        let i = (pos as usize).min(anchors.len() - 2);
+        let t = pos - i as f32;
+        let (a, b) = (anchors[i], anchors[i + 1]);
+        std::array::from_fn(|c| (a[c] as f32 + (b[c] as f32 - a[c] as f32) * t).round() as u8)
+    }
+}
+
+impl std::fmt::Display for Colormap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Colormap::Magma => write!(f, "magma"),
+            Colormap::Viridis => write!(f, "viridis"),
+            Colormap::Gray => write!(f, "gray"),
+        }
+    }
+}
+
+impl std::str::FromStr for Colormap {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "magma" => Ok(Colormap::Magma),
+            "viridis" => Ok(Colormap::Viridis),
+            "gray" | "grey" | "grayscale" => Ok(Colormap::Gray),
+            _ => anyhow::bail!("Unknown colormap: {s}. Available: magma, viridis, gray"),
+        }
+    }
+}
+
+/// How a spectrogram image is drawn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpectrogramOptions {
+    /// Image width in pixels; time runs left to right.
+    pub width: u32,
+    /// Image height in pixels; frequency runs bottom to top.
+    pub height: u32,
+    pub colormap: Colormap,
+    /// Lowest frequency shown, in Hz. The top is always Nyquist.
+    pub min_freq: f64,
+    /// Levels shown below the loudest bin, in dB; anything quieter is drawn
+    /// in the colormap's darkest colour.
+    pub range_db: f32,
+    pub fft_size: usize,
+}
+
+impl Default for SpectrogramOptions {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 600,
+            colormap: Colormap::Magma,
+            min_freq: 20.0,
+            range_db: 90.0,
+            fft_size: 4096,
+        }
+    }
+}
+
+/// An 8-bit RGB image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// Rows top to bottom, three bytes per pixel.
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// An image filled with `color`.
+    pub fn new(width: u32, height: u32, color: [u8; 3]) -> Self {
+        Self {
+            width,
+            height,
+            pixels: color.repeat(width as usize * height as usize),
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 3] {
+        let i = (y as usize * self.width as usize + x as usize) * 3;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: [u8; 3]) {
+        if x < self.width && y < self.height {
+            let i = (y as usize * self.width as usize + x as usize) * 3;
+            self.pixels[i..i + 3].copy_from_slice(&color);
+        }
+    }
+
+    /// Mix `color` into the pixel at (`x`, `y`) with opacity `alpha`.
+    pub fn blend_pixel(&mut self, x: u32, y: u32, color: [u8; 3], alpha: f32) {
+        if x < self.width && y < self.height {
+            let old = self.pixel(x, y);
+            let alpha = alpha.clamp(0.0, 1.0);
+            let mixed = std::array::from_fn(|c| {
+                (old[c] as f32 + (color[c] as f32 - old[c] as f32) * alpha).round() as u8
+            });
+            self.set_pixel(x, y, mixed);
+        }
+    }
+
+    /// Write the image as a PNG file.
+    pub fn write_png(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).with_context(|| format!("Creating {}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+}
+
+/// Spectrogram of the mono mixdown of `audio` on a log-frequency axis.
+///
+/// Each pixel shows the loudest FFT bin its frequency band covers, so
+/// narrow lines stay visible at the top of the image where many bins share
+/// a row.
+pub fn render_spectrogram(audio: &DecodedAudio, opts: &SpectrogramOptions) -> Image {
+    let (width, height) = (opts.width.max(1), opts.height.max(1));
+    let fft_size = opts.fft_size.max(16);
+    let mono = audio.mono_mixdown();
+    let spectrogram = compute_spectrogram(
+        &mono,
+        audio.sample_rate,
+        fft_size,
+        fft_size / 4,
+        Some(width as usize),
+    );
+
+    let mut image = Image::new(width, height, opts.colormap.color(0.0));
+    if spectrogram.frames.is_empty() {
+        return image;
+    }
+
+    let nyquist = audio.sample_rate as f64 / 2.0;
+    let bin_hz = audio.sample_rate as f64 / fft_size as f64;
+    let bins = fft_size / 2 + 1;
+    let min_freq = opts
+        .min_freq
+        .clamp(bin_hz.min(nyquist / 2.0), nyquist / 2.0);
+    // Bins covered by each row, bottom row first
+    let row_bins: Vec<(usize, usize)> = (0..height)
+        .map(|row| {
+            let freq =
+                |edge: u32| min_freq * (nyquist / min_freq).powf(edge as f64 / height as f64);
+            let lo = ((freq(row) / bin_hz) as usize).min(bins - 1);
+            let hi = ((freq(row + 1) / bin_hz).ceil() as usize).clamp(lo + 1, bins);
+            (lo, hi)
+        })
+        .collect();
+
+    let loudest = spectrogram
+        .frames
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f32::NEG_INFINITY, f32::max);
+    let range_db = opts.range_db.max(1.0);
+
+    let frames = spectrogram.frames.len();
+    for x in 0..width {
+        let frame = &spectrogram.frames[(x as usize * frames / width as usize).min(frames - 1)];
+        for (row, &(lo, hi)) in row_bins.iter().enumerate() {
+            let db = frame[lo..hi]
+                .iter()
+                .copied()
+                .fold(f32::NEG_INFINITY, f32::max);
+            let value = 1.0 - (loudest - db) / range_db;
+            image.set_pixel(x, height - 1 - row as u32, opts.colormap.color(value));
+        }
+    }
+    image
+}
+
+/// Render a spectrogram of the file at `input` and write it to `output` as PNG.
+pub fn spectrogram_png(input: &Path, output: &Path, opts: &SpectrogramOptions) -> Result<()> {
+    let audio = decode_audio(input).with_context(|| format!("Decoding {}", input.display()))?;
+    render_spectrogram(&audio, opts).write_png(output)
+}
+
+/// Background of waveform images.
+const WAVEFORM_BACKGROUND: [u8; 3] = [18, 20, 26];
+
+/// Colours of successive waveforms: the input in blue, its master in amber.
+const WAVEFORM_COLORS: [[u8; 3]; 4] = [
+    [90, 160, 230],
+    [240, 150, 60],
+    [120, 200, 120],
+    [220, 100, 180],
+];
+
+/// How a waveform image is drawn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaveformImageOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Draw every waveform across the full height on top of each other
+    /// instead of one above the other.
+    pub overlay: bool,
+}
+
+impl Default for WaveformImageOptions {
+    fn default() -> Self {
+        Self {
+            width: 1600,
+            height: 400,
+            overlay: false,
+        }
+    }
+}
+
+/// Min/max/RMS waveforms, e.g. of an input and its master, stacked in
+/// lanes of equal height or overlaid.
+///
+/// Peaks are drawn translucent and the RMS band on top of them in full
+/// colour, so overlaid waveforms stay distinguishable.
+pub fn render_waveforms(waveforms: &[Vec<[f32; 3]>], opts: &WaveformImageOptions) -> Image {
+    let (width, height) = (opts.width.max(1), opts.height.max(1));
+    let mut image = Image::new(width, height, WAVEFORM_BACKGROUND);
+    let lanes = if opts.overlay {
+        1
+    } else {
+        waveforms.len().max(1) as u32
+    };
+    let lane_height = height / lanes;
+
+    for (i, points) in waveforms.iter().enumerate() {
+        if points.is_empty() {
+            continue;
+        }
+        let color = WAVEFORM_COLORS[i % WAVEFORM_COLORS.len()];
+        let top = if opts.overlay {
+            0
+        } else {
+            i as u32 * lane_height
+        };
+        let half = lane_height as f32 / 2.0;
+        let mid = top as f32 + half;
+        let to_y = |v: f32| {
+            (mid - v.clamp(-1.0, 1.0) * half)
+                .round()
+                .clamp(top as f32, (top + lane_height - 1) as f32) as u32
+        };
+
+        for x in 0..width {
+            let [min, max, rms] =
+                points[(x as usize * points.len() / width as usize).min(points.len() - 1)];
+            for y in to_y(max)..=to_y(min) {
+                image.blend_pixel(x, y, color, 0.45);
+            }
+            for y in to_y(rms)..=to_y(-rms) {
+                image.blend_pixel(x, y, color, 0.9);
+            }
+        }
+    }
+    image
+}
+
+/// Render the mixdown waveforms of `inputs`, e.g. an input and its master,
+/// and write them to `output` as PNG. Peaks come from `cache` when given.
+pub fn waveform_png(
+    inputs: &[&Path],
+    output: &Path,
+    opts: &WaveformImageOptions,
+    cache: Option<&PeakCache>,
+) -> Result<()> {
+    let waveforms = inputs
+        .iter()
+        .map(|input| {
+            let waveform = peaks::waveform(
+                input,
+                opts.width.max(1) as usize,
+                None,
+                ChannelSelection::Mix,
+                cache,
+            )
+            .with_context(|| format!("Reading the waveform of {}", input.display()))?;
+            Ok(waveform
+                .channels
+                .into_iter()
+                .next()
+                .map(|c| c.points)
+                .unwrap_or_default())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    render_waveforms(&waveforms, opts).write_png(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, secs: f64, sample_rate: u32) -> DecodedAudio {
+        let samples = (0..(sample_rate as f64 * secs) as usize)
+            .map(|i| {
+                (0.5 * (2.0 * std::f64::consts::PI * freq * i as f64 / sample_rate as f64).sin())
+                    as f32
+            })
+            .collect();
+        DecodedAudio::new(samples, sample_rate, 1)
+    }
+
+    #[test]
+    fn test_colormap_ends() {
+        assert_eq!(Colormap::Gray.color(-1.0), [0, 0, 0]);
+        assert_eq!(Colormap::Gray.color(0.5), [128, 128, 128]);
+        assert_eq!(Colormap::Magma.color(1.0), [252, 253, 191]);
+        assert_eq!("Grey".parse::<Colormap>().unwrap(), Colormap::Gray);
+        assert!("rainbow".parse::<Colormap>().is_err());
+    }
+
+    #[test]
+    fn test_tone_is_brightest_at_its_frequency() {
+        let opts = SpectrogramOptions {
+            width: 50,
+            height: 200,
+            colormap: Colormap::Gray,
+            ..Default::default()
+        };
+        let image = render_spectrogram(&sine(1000.0, 2.0, 48000), &opts);
+        assert_eq!(image.pixels.len(), 50 * 200 * 3);
+
+        let brightest = (0..image.height)
+            .max_by_key(|&y| image.pixel(25, y)[0])
+            .unwrap();
+        // Row of 1 kHz on a 20 Hz..24 kHz log axis, counted from the top
+        let expected = 200.0 * (1.0 - (1000.0f64 / 20.0).ln() / (24000.0f64 / 20.0).ln());
+        assert!(
+            (brightest as f64 - expected).abs() <= 3.0,
+            "{brightest} vs {expected}"
+        );
+        assert_eq!(image.pixel(25, 0), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_waveforms_stacked_and_overlaid() {
+        let quiet = vec![[-0.1, 0.1, 0.05]; 10];
+        let loud = vec![[-1.0, 1.0, 0.7]; 10];
+        let opts = WaveformImageOptions {
+            width: 20,
+            height: 100,
+            overlay: false,
+        };
+        let stacked = render_waveforms(&[quiet.clone(), loud.clone()], &opts);
+        // The quiet input leaves most of its lane empty; the loud master fills its own
+        assert_eq!(stacked.pixel(5, 5), WAVEFORM_BACKGROUND);
+        assert_ne!(stacked.pixel(5, 25), WAVEFORM_BACKGROUND);
+        assert_ne!(stacked.pixel(5, 55), WAVEFORM_BACKGROUND);
+        assert_ne!(stacked.pixel(5, 99), WAVEFORM_BACKGROUND);
+
+        let overlaid = render_waveforms(
+            &[quiet, loud],
+            &WaveformImageOptions {
+                overlay: true,
+                ..opts
+            },
+        );
+        // Only the master reaches the top; both cover the centre
+        assert_ne!(overlaid.pixel(5, 1), WAVEFORM_BACKGROUND);
+        assert_ne!(overlaid.pixel(5, 50), overlaid.pixel(5, 1));
+    }
+
+    #[test]
+    fn test_write_png() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spec.png");
+        let mut image = Image::new(4, 3, [10, 20, 30]);
+        image.set_pixel(3, 2, [255, 0, 0]);
+        image.write_png(&path).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        assert_eq!((info.width, info.height), (4, 3));
+        assert_eq!(&buf[..3], &[10, 20, 30]);
+        assert_eq!(&buf[33..36], &[255, 0, 0]);
+    }
+}
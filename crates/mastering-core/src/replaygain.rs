@@ -0,0 +1,97 @@
+//! ReplayGain 2.0 and EBU R128 gain tags.
+//!
+//! Players use these tags to play tracks at an even volume without touching
+//! the audio. ReplayGain 2.0 measures loudness per BS.1770 and aims at
+//! -18 LUFS; the R128 tags of Opus files aim at -23 LUFS. Album gain treats
+//! all tracks as one programme, so the level differences between them
+//! survive.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::analysis::decode::AudioStream;
+use crate::analysis::loudness::{combined_loudness, LoudnessMeter, SILENCE_LUFS};
+use crate::metadata;
+use crate::types::ReplayGain;
+
+/// Loudness ReplayGain 2.0 brings tracks to, in LUFS.
+pub const REFERENCE_LUFS: f64 = -18.0;
+
+/// Loudness R128 gain tags bring tracks to, in LUFS.
+pub const R128_REFERENCE_LUFS: f64 = -23.0;
+
+/// Loudness meter and sample peak of one file.
+struct Measurement {
+    meter: LoudnessMeter,
+    peak: f64,
+}
+
+fn measure(path: &Path) -> Result<Measurement> {
+    let mut stream = AudioStream::open(path)?;
+    let mut meter = LoudnessMeter::new(stream.sample_rate(), stream.layout());
+    let mut peak = 0.0f32;
+    while let Some(chunk) = stream.next_chunk()? {
+        meter.push(chunk);
+        peak = chunk.iter().fold(peak, |m, s| m.max(s.abs()));
+    }
+    Ok(Measurement {
+        meter,
+        peak: peak as f64,
+    })
+}
+
+/// Gain to the reference loudness, or none for silence.
+fn gain_db(lufs: f64) -> f64 {
+    if lufs <= SILENCE_LUFS {
+        0.0
+    } else {
+        REFERENCE_LUFS - lufs
+    }
+}
+
+/// Track gain and peak of the file at `path`.
+pub fn track_gain(path: &Path) -> Result<ReplayGain> {
+    let m = measure(path)?;
+    Ok(ReplayGain {
+        track_gain_db: gain_db(m.meter.integrated()),
+        track_peak: m.peak,
+        album_gain_db: None,
+        album_peak: None,
+    })
+}
+
+/// Track and album gain of every file in `paths`, in order.
+pub fn album_gain(paths: &[PathBuf]) -> Result<Vec<ReplayGain>> {
+    let measurements = paths
+        .iter()
+        .map(|p| measure(p))
+        .collect::<Result<Vec<_>>>()?;
+    let meters: Vec<LoudnessMeter> = measurements.iter().map(|m| m.meter.clone()).collect();
+    let album_gain_db = gain_db(combined_loudness(&meters));
+    let album_peak = measurements.iter().fold(0.0f64, |m, t| m.max(t.peak));
+    Ok(measurements
+        .iter()
+        .map(|m| ReplayGain {
+            track_gain_db: gain_db(m.meter.integrated()),
+            track_peak: m.peak,
+            album_gain_db: Some(album_gain_db),
+            album_peak: Some(album_peak),
+        })
+        .collect())
+}
+
+/// Measure the track gain of `path` and write it to its tags.
+pub fn tag_track(path: &Path) -> Result<ReplayGain> {
+    let gain = track_gain(path)?;
+    metadata::write_replaygain(path, &gain)?;
+    Ok(gain)
+}
+
+/// Measure the track and album gain of `paths` and write them to their tags.
+pub fn tag_album(paths: &[PathBuf]) -> Result<Vec<ReplayGain>> {
+    let gains = album_gain(paths)?;
+    for (path, gain) in paths.iter().zip(&gains) {
+        metadata::write_replaygain(path, gain)?;
+    }
+    Ok(gains)
+}
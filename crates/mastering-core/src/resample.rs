@@ -0,0 +1,157 @@
+//! Sample-rate conversion for the output stage.
+//!
+//! Uses rubato's FFT resampler, whose band-limited interpolation doubles as
+//! the anti-aliasing filter when converting down (e.g. 96 kHz to 44.1 kHz).
+
+use anyhow::{Context, Result};
+use rubato::{FftFixedIn, Resampler};
+use std::path::Path;
+
+use crate::analysis::decode::decode_audio;
+use crate::encode::{self, EncodeOptions};
+
+/// Lowest and highest sample rates accepted as a conversion target.
+pub const MIN_SAMPLE_RATE: u32 = 8_000;
+pub const MAX_SAMPLE_RATE: u32 = 384_000;
+
+/// Input frames fed to the resampler per call.
+const CHUNK_FRAMES: usize = 4096;
+
+/// Resample interleaved `samples` from `from_rate` to `to_rate`.
+pub fn resample(
+    samples: &[f32],
+    channels: usize,
+    from_rate: u32,
+    to_rate: u32,
+) -> Result<Vec<f32>> {
+    anyhow::ensure!(channels > 0, "Cannot resample audio without channels");
+    anyhow::ensure!(
+        (MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&to_rate),
+        "Sample rate {to_rate} Hz is outside {MIN_SAMPLE_RATE}-{MAX_SAMPLE_RATE} Hz"
+    );
+    if from_rate == to_rate {
+        return Ok(samples.to_vec());
+    }
+
+    let frames = samples.len() / channels;
+    let planar: Vec<Vec<f32>> = (0..channels)
+        .map(|ch| samples.iter().skip(ch).step_by(channels).copied().collect())
+        .collect();
+
+    let mut resampler = FftFixedIn::<f32>::new(
+        from_rate as usize,
+        to_rate as usize,
+        CHUNK_FRAMES,
+        2,
+        channels,
+    )
+    .context("Creating resampler")?;
+    let delay = resampler.output_delay();
+    let expected = (frames as u64 * to_rate as u64).div_ceil(from_rate as u64) as usize;
+
+    let mut out: Vec<Vec<f32>> = vec![Vec::with_capacity(expected + delay); channels];
+
+    let mut pos = 0;
+    while frames - pos >= resampler.input_frames_next() {
+        let end = pos + resampler.input_frames_next();
+        let chunk: Vec<&[f32]> = planar.iter().map(|ch| &ch[pos..end]).collect();
+        append(&mut out, resampler.process(&chunk, None)?);
+        pos = end;
+    }
+    if pos < frames {
+        let rest: Vec<&[f32]> = planar.iter().map(|ch| &ch[pos..]).collect();
+        append(&mut out, resampler.process_partial(Some(&rest), None)?);
+    }
+    // Flush the filter until the delayed tail has come out
+    while out[0].len() < expected + delay {
+        append(&mut out, resampler.process_partial::<&[f32]>(None, None)?);
+    }
+
+    let mut interleaved = Vec::with_capacity(expected * channels);
+    for i in delay..delay + expected {
+        interleaved.extend(out.iter().map(|ch| ch[i]));
+    }
+    Ok(interleaved)
+}
+
+fn append(out: &mut [Vec<f32>], chunk: Vec<Vec<f32>>) {
+    for (dst, src) in out.iter_mut().zip(chunk) {
+        dst.extend(src);
+    }
+}
+
+/// Resample the audio file at `path` to `to_rate`, rewriting it as a WAV
+/// file in the format given by `opts`.
+///
+/// The pipeline resamples before the final write, so `opts` normally has no
+/// dither and a 32-bit format when the output is re-quantized later.
+///
+/// Returns the original sample rate, or `None` if the file was already at
+/// `to_rate` and left untouched.
+pub fn resample_file_in_place(
+    path: &Path,
+    to_rate: u32,
+    opts: &EncodeOptions,
+) -> Result<Option<u32>> {
+    let audio = decode_audio(path)?;
+    if audio.sample_rate == to_rate {
+        return Ok(None);
+    }
+
+    let samples = resample(
+        &audio.samples,
+        audio.channels as usize,
+        audio.sample_rate,
+        to_rate,
+    )?;
+
+    // Write next to the original and swap so a failure leaves it intact
+    let tmp = path.with_extension("resample.wav");
+    encode::write_wav(&tmp, &samples, audio.channels, to_rate, opts)?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Replacing {}", path.display()))?;
+    Ok(Some(audio.sample_rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f64, rate: u32, frames: usize, channels: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let s = (0.5 * (2.0 * std::f64::consts::PI * freq * i as f64 / rate as f64).sin())
+                    as f32;
+                std::iter::repeat_n(s, channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resample_length_and_level() {
+        let input = sine(1000.0, 96000, 96000, 2);
+        let output = resample(&input, 2, 96000, 44100).unwrap();
+        assert_eq!(output.len(), 44100 * 2);
+
+        // Away from the edges the tone keeps its amplitude
+        let peak = output[2000..80000]
+            .iter()
+            .fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!((peak - 0.5).abs() < 0.01, "peak {peak}");
+    }
+
+    #[test]
+    fn test_downsampling_removes_content_above_nyquist() {
+        // 30 kHz is above the 22.05 kHz Nyquist limit of the target rate
+        let input = sine(30000.0, 96000, 96000, 1);
+        let output = resample(&input, 1, 96000, 44100).unwrap();
+        let peak = output[2000..40000]
+            .iter()
+            .fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(peak < 0.01, "aliased peak {peak}");
+    }
+
+    #[test]
+    fn test_rejects_unsupported_rate() {
+        assert!(resample(&[0.0; 8], 1, 44100, 1000).is_err());
+    }
+}
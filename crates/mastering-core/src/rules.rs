@@ -0,0 +1,300 @@
+//! Rule-based mastering parameter suggestions.
+//!
+//! A deterministic, offline alternative to asking a language model: each rule
+//! looks at one aspect of the analysis and, when it falls outside the range
+//! set in [`RulesConfig`], contributes an EQ band or adjusts the dynamics and
+//! stereo settings. Used by the `rules` AI provider.
+//!
+//! [`adapt_for_speech`] then biases a suggestion toward spoken word.
+
+use crate::analysis::tempo::synced_release_ms;
+use crate::config::RulesConfig;
+use crate::types::{
+    AudioAnalysis, CompressionParams, DeEsserParams, EqBand, EqBandType, EqChannel, LimiterParams,
+    MasteringParams, StereoParams,
+};
+
+/// Compressor release without a tempo to time it to.
+const DEFAULT_RELEASE_MS: f64 = 120.0;
+
+/// Suggest mastering parameters for `analysis`.
+pub fn suggest_params(
+    analysis: &AudioAnalysis,
+    target_lufs: f64,
+    no_limiter: bool,
+    rules: &RulesConfig,
+) -> MasteringParams {
+    let bands = &analysis.frequency_bands;
+    // Correct a fraction of how far a band is outside its range, within limits
+    let amount =
+        |deviation: f64| (deviation * rules.eq_amount).clamp(-rules.max_eq_db, rules.max_eq_db);
+
+    let mut eq = vec![EqBand {
+        frequency: rules.high_pass_hz,
+        gain_db: 0.0,
+        q: 0.707,
+        band_type: EqBandType::HighPass,
+        channel: EqChannel::Stereo,
+    }];
+
+    if bands.sub_bass < rules.sub_bass_min_db {
+        eq.push(EqBand {
+            frequency: 60.0,
+            gain_db: amount(rules.sub_bass_min_db - bands.sub_bass),
+            q: 0.707,
+            band_type: EqBandType::LowShelf,
+            channel: EqChannel::Stereo,
+        });
+    } else if bands.bass > rules.bass_max_db {
+        eq.push(EqBand {
+            frequency: 120.0,
+            gain_db: amount(rules.bass_max_db - bands.bass),
+            q: 0.707,
+            band_type: EqBandType::LowShelf,
+            channel: EqChannel::Stereo,
+        });
+    }
+
+    if bands.low_mid > rules.mud_max_db {
+        eq.push(EqBand {
+            frequency: 300.0,
+            gain_db: amount(rules.mud_max_db - bands.low_mid),
+            q: 1.0,
+            band_type: EqBandType::Peak,
+            channel: EqChannel::Stereo,
+        });
+    }
+
+    if bands.upper_mid > rules.harshness_max_db {
+        eq.push(EqBand {
+            frequency: 3000.0,
+            gain_db: amount(rules.harshness_max_db - bands.upper_mid),
+            q: 1.2,
+            band_type: EqBandType::Peak,
+            channel: EqChannel::Stereo,
+        });
+    }
+
+    if bands.brilliance < rules.air_min_db {
+        eq.push(EqBand {
+            frequency: 10000.0,
+            gain_db: amount(rules.air_min_db - bands.brilliance),
+            q: 0.707,
+            band_type: EqBandType::HighShelf,
+            channel: EqChannel::Stereo,
+        });
+    }
+
+    // More dynamic material gets a firmer ratio
+    let ratio = if analysis.dynamic_range_db > rules.dynamic_range_high_db {
+        2.5
+    } else if analysis.dynamic_range_db > rules.dynamic_range_low_db {
+        2.0
+    } else {
+        1.5
+    };
+    // Let go in time with the beat when there is one
+    let release_ms = match analysis.tempo_bpm {
+        Some(bpm) => synced_release_ms(bpm, DEFAULT_RELEASE_MS),
+        None => DEFAULT_RELEASE_MS,
+    };
+    let compression = CompressionParams {
+        threshold_db: (analysis.rms_db + 3.0).min(-6.0),
+        ratio,
+        attack_ms: 15.0,
+        release_ms,
+        knee_db: 6.0,
+        makeup_gain_db: 0.0,
+    };
+
+    let width = if analysis.stereo_width > rules.max_stereo_width {
+        // Rein in very wide mixes that would fold down poorly
+        rules.max_stereo_width / analysis.stereo_width
+    } else {
+        1.0
+    };
+
+    MasteringParams {
+        eq,
+        de_esser: DeEsserParams::default(),
+        multiband: None,
+        compression,
+        limiter: LimiterParams {
+            enabled: !no_limiter,
+            ceiling_db: rules.ceiling_db,
+            release_ms: 60.0,
+            ..Default::default()
+        },
+        stereo: StereoParams {
+            width: width.max(0.5),
+            balance: 0.0,
+        },
+        target_lufs,
+    }
+}
+
+/// High-pass frequency for speech: below it there is only rumble, handling
+/// noise and plosive thumps.
+const SPEECH_HIGH_PASS_HZ: f64 = 80.0;
+
+/// Bias `params` toward voice: a higher high-pass, no low or air boosts,
+/// the de-esser on, gentle but steady compression and no widening.
+pub fn adapt_for_speech(params: &mut MasteringParams) {
+    for band in &mut params.eq {
+        match band.band_type {
+            EqBandType::HighPass => band.frequency = band.frequency.max(SPEECH_HIGH_PASS_HZ),
+            EqBandType::LowShelf | EqBandType::HighShelf => band.gain_db = band.gain_db.min(0.0),
+            _ => {}
+        }
+    }
+    params.eq.retain(|b| {
+        b.gain_db != 0.0 || matches!(b.band_type, EqBandType::HighPass | EqBandType::LowPass)
+    });
+
+    params.de_esser = DeEsserParams {
+        enabled: true,
+        frequency: 5500.0,
+        ..Default::default()
+    };
+    params.multiband = None;
+    params.compression = CompressionParams {
+        ratio: 3.0,
+        attack_ms: 10.0,
+        release_ms: 150.0,
+        knee_db: 6.0,
+        ..params.compression
+    };
+    params.stereo.width = params.stereo.width.min(1.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AudioMetadata, FrequencyBands};
+
+    fn analysis(bands: FrequencyBands) -> AudioAnalysis {
+        AudioAnalysis {
+            metadata: AudioMetadata {
+                path: "song.wav".into(),
+                sample_rate: 44100,
+                channels: 2,
+                channel_layout: "stereo".into(),
+                duration_secs: 180.0,
+                bit_depth: Some(24),
+                format: "WAV".into(),
+            },
+            lufs_integrated: -18.0,
+            lufs_short_term_max: -14.0,
+            lufs_momentary_max: -12.0,
+            loudness_range_lu: 6.0,
+            rms_db: -20.0,
+            peak_db: -3.0,
+            true_peak_db: -2.8,
+            dynamic_range_db: 12.0,
+            stereo_width: 0.6,
+            phase_correlation: 1.0,
+            phase_correlation_min: 1.0,
+            frequency_bands: bands,
+            ..Default::default()
+        }
+    }
+
+    fn balanced() -> FrequencyBands {
+        FrequencyBands {
+            sub_bass: -10.0,
+            bass: -5.0,
+            low_mid: -8.0,
+            mid: -5.0,
+            upper_mid: -12.0,
+            presence: -16.0,
+            brilliance: -15.0,
+        }
+    }
+
+    #[test]
+    fn test_balanced_mix_only_gets_high_pass() {
+        let params = suggest_params(&analysis(balanced()), -14.0, false, &RulesConfig::default());
+        assert_eq!(params.eq.len(), 1);
+        assert!(matches!(params.eq[0].band_type, EqBandType::HighPass));
+        assert_eq!(params.compression.ratio, 2.5);
+        assert!(params.limiter.enabled);
+    }
+
+    #[test]
+    fn test_thin_harsh_mix() {
+        let bands = FrequencyBands {
+            sub_bass: -20.0,
+            upper_mid: -4.0,
+            ..balanced()
+        };
+        let params = suggest_params(&analysis(bands), -14.0, true, &RulesConfig::default());
+
+        let sub = params.eq.iter().find(|b| b.frequency == 60.0).unwrap();
+        assert!(sub.gain_db > 0.0 && sub.gain_db <= RulesConfig::default().max_eq_db);
+        let harsh = params.eq.iter().find(|b| b.frequency == 3000.0).unwrap();
+        assert!(harsh.gain_db < 0.0);
+        assert!(!params.limiter.enabled);
+    }
+
+    #[test]
+    fn test_rules_are_tunable_and_deterministic() {
+        let bands = FrequencyBands {
+            sub_bass: -14.0,
+            ..balanced()
+        };
+        let strict = RulesConfig {
+            sub_bass_min_db: -16.0,
+            ..Default::default()
+        };
+        let params = suggest_params(&analysis(bands.clone()), -14.0, false, &strict);
+        assert_eq!(params.eq.len(), 1);
+
+        let a = suggest_params(
+            &analysis(bands.clone()),
+            -14.0,
+            false,
+            &RulesConfig::default(),
+        );
+        let b = suggest_params(&analysis(bands), -14.0, false, &RulesConfig::default());
+        assert_eq!(
+            serde_json::to_string(&a).unwrap(),
+            serde_json::to_string(&b).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_release_follows_tempo() {
+        let params = suggest_params(&analysis(balanced()), -14.0, false, &RulesConfig::default());
+        assert_eq!(params.compression.release_ms, DEFAULT_RELEASE_MS);
+
+        let with_tempo = AudioAnalysis {
+            tempo_bpm: Some(100.0),
+            ..analysis(balanced())
+        };
+        let params = suggest_params(&with_tempo, -14.0, false, &RulesConfig::default());
+        // A 1/16 note at 100 BPM
+        assert_eq!(params.compression.release_ms, 150.0);
+    }
+
+    #[test]
+    fn test_speech_drops_boosts_and_de_esses() {
+        let bands = FrequencyBands {
+            sub_bass: -20.0,
+            brilliance: -30.0,
+            ..balanced()
+        };
+        let mut params = suggest_params(&analysis(bands), -16.0, false, &RulesConfig::default());
+        assert!(params.eq.iter().any(|b| b.gain_db > 0.0));
+
+        adapt_for_speech(&mut params);
+        assert!(params.eq.iter().all(|b| b.gain_db <= 0.0));
+        let high_pass = params
+            .eq
+            .iter()
+            .find(|b| matches!(b.band_type, EqBandType::HighPass))
+            .unwrap();
+        assert_eq!(high_pass.frequency, SPEECH_HIGH_PASS_HZ);
+        assert!(params.de_esser.enabled);
+        assert_eq!(params.compression.ratio, 3.0);
+    }
+}
@@ -0,0 +1,198 @@
+//! Offline scoring of candidate mastering parameters.
+//!
+//! Each candidate is rendered with the native DSP chain over a short excerpt
+//! of the input and measured against the target: its loudness error, and how
+//! far its spectral balance strays from the source's. The candidate with the
+//! lowest score is the one worth rendering in full.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::analysis::{self, decode, decode::DecodedAudio, loudness::PowerSeries};
+use crate::dsp;
+use crate::types::{FrequencyBands, MasteringParams, ParamCandidate};
+
+/// Band shift against the source accepted without penalty, in dB.
+const FREE_BAND_SHIFT_DB: f64 = 2.0;
+
+/// Weight of one dB of spectral error against one LU of loudness error.
+const SPECTRAL_WEIGHT: f64 = 0.5;
+
+/// Score every candidate on an excerpt of `input` and mark the best one as
+/// selected. Candidates keep their order.
+pub fn rank(
+    input: &Path,
+    candidates: Vec<MasteringParams>,
+    target_lufs: f64,
+    excerpt_secs: f64,
+) -> Result<Vec<ParamCandidate>> {
+    let audio = decode::decode_audio(input)?;
+    let excerpt = loudest_excerpt(&audio, excerpt_secs);
+    let source = analysis::analyze(input, &excerpt)?;
+
+    let mut scored = candidates
+        .into_iter()
+        .map(|params| score(&excerpt, &source.frequency_bands, params, target_lufs))
+        .collect::<Result<Vec<_>>>()?;
+
+    if let Some(best) = scored.iter_mut().min_by(|a, b| a.score.total_cmp(&b.score)) {
+        best.selected = true;
+    }
+    Ok(scored)
+}
+
+/// Render `params` over `excerpt` and measure the result.
+pub fn score(
+    excerpt: &DecodedAudio,
+    source_bands: &FrequencyBands,
+    params: MasteringParams,
+    target_lufs: f64,
+) -> Result<ParamCandidate> {
+    let mut audio = excerpt.clone();
+    dsp::master(&mut audio, &params);
+    let rendered = analysis::analyze(Path::new("candidate"), &audio)?;
+
+    let lufs_error = (rendered.lufs_integrated - target_lufs).abs();
+    let spectral_error = spectral_error(source_bands, &rendered.frequency_bands);
+    Ok(ParamCandidate {
+        params,
+        lufs: rendered.lufs_integrated,
+        lufs_error,
+        spectral_error,
+        score: lufs_error + SPECTRAL_WEIGHT * spectral_error,
+        selected: false,
+    })
+}
+
+/// Mean band shift against the source beyond [`FREE_BAND_SHIFT_DB`].
+fn spectral_error(source: &FrequencyBands, rendered: &FrequencyBands) -> f64 {
+    let shifts = [
+        rendered.sub_bass - source.sub_bass,
+        rendered.bass - source.bass,
+        rendered.low_mid - source.low_mid,
+        rendered.mid - source.mid,
+        rendered.upper_mid - source.upper_mid,
+        rendered.presence - source.presence,
+        rendered.brilliance - source.brilliance,
+    ];
+    shifts
+        .iter()
+        .map(|s| (s.abs() - FREE_BAND_SHIFT_DB).max(0.0))
+        .sum::<f64>()
+        / shifts.len() as f64
+}
+
+/// The loudest `secs` of `audio`, searched in one-second steps; dense
+/// passages are where compression and limiting choices differ most.
+fn loudest_excerpt(audio: &DecodedAudio, secs: f64) -> DecodedAudio {
+    let series = PowerSeries::new(audio);
+    let frames = series.frames();
+    let window = series.secs_to_frames(secs).max(1);
+    if window >= frames {
+        return audio.clone();
+    }
+
+    let step = series.secs_to_frames(1.0).max(1);
+    let start = (0..=frames - window)
+        .step_by(step)
+        .max_by(|&a, &b| {
+            series
+                .mean(a, a + window)
+                .total_cmp(&series.mean(b, b + window))
+        })
+        .unwrap_or(0);
+
+    let channels = audio.channels as usize;
+    let samples = audio.samples[start * channels..(start + window) * channels].to_vec();
+    let mut excerpt = DecodedAudio::new(samples, audio.sample_rate, audio.channels);
+    excerpt.layout = audio.layout.clone();
+    excerpt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        CompressionParams, DeEsserParams, EqBand, EqBandType, EqChannel, LimiterParams,
+        StereoParams,
+    };
+
+    fn params(treble_db: f64) -> MasteringParams {
+        MasteringParams {
+            eq: vec![EqBand {
+                frequency: 8000.0,
+                gain_db: treble_db,
+                q: 0.707,
+                band_type: EqBandType::HighShelf,
+                channel: EqChannel::Stereo,
+            }],
+            de_esser: DeEsserParams::default(),
+            multiband: None,
+            compression: CompressionParams {
+                threshold_db: -18.0,
+                ratio: 2.0,
+                attack_ms: 10.0,
+                release_ms: 100.0,
+                knee_db: 6.0,
+                makeup_gain_db: 0.0,
+            },
+            limiter: LimiterParams::default(),
+            stereo: StereoParams {
+                width: 1.0,
+                balance: 0.0,
+            },
+            target_lufs: -14.0,
+        }
+    }
+
+    fn tones(secs: usize) -> DecodedAudio {
+        let rate = 44100;
+        let samples = (0..rate * secs)
+            .flat_map(|i| {
+                let t = i as f32 / rate as f32;
+                let s = 0.1 * (2.0 * std::f32::consts::PI * 200.0 * t).sin()
+                    + 0.05 * (2.0 * std::f32::consts::PI * 10000.0 * t).sin();
+                [s, s]
+            })
+            .collect();
+        DecodedAudio::new(samples, rate as u32, 2)
+    }
+
+    #[test]
+    fn test_heavy_eq_scores_worse() {
+        let audio = tones(3);
+        let source = analysis::analyze(Path::new("source"), &audio).unwrap();
+
+        let subtle = score(&audio, &source.frequency_bands, params(1.0), -14.0).unwrap();
+        let harsh = score(&audio, &source.frequency_bands, params(12.0), -14.0).unwrap();
+        assert!(subtle.lufs_error < 0.5, "lufs error {}", subtle.lufs_error);
+        assert!(
+            subtle.spectral_error < 0.5,
+            "spectral error {}",
+            subtle.spectral_error
+        );
+        assert!(harsh.spectral_error > subtle.spectral_error + 1.0);
+        assert!(harsh.score > subtle.score);
+    }
+
+    #[test]
+    fn test_loudest_excerpt() {
+        let rate = 8000;
+        let samples: Vec<f32> = (0..rate * 10)
+            .map(|i| {
+                let level = if (6 * rate..8 * rate).contains(&i) {
+                    0.5
+                } else {
+                    0.01
+                };
+                level * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / rate as f32).sin()
+            })
+            .collect();
+        let audio = DecodedAudio::new(samples, rate as u32, 1);
+
+        let excerpt = loudest_excerpt(&audio, 2.0);
+        assert_eq!(excerpt.total_frames, 2 * rate as u64);
+        assert_eq!(excerpt.samples, audio.samples[6 * rate..8 * rate]);
+        assert_eq!(loudest_excerpt(&audio, 30.0).total_frames, 10 * rate as u64);
+    }
+}
@@ -0,0 +1,153 @@
+//! API keys of the cloud AI providers.
+//!
+//! A key is taken from the provider's environment variable first, then from
+//! the OS keyring (when built with the `keyring` feature), and only then from
+//! config.toml, so shared machines need not keep keys in plaintext.
+
+use anyhow::Result;
+use tracing::debug;
+
+use crate::types::AiProvider;
+
+/// Keyring service the keys are stored under, one entry per provider.
+pub const KEYRING_SERVICE: &str = "audiomaster";
+
+/// Where an API key was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    Env,
+    Keyring,
+    Config,
+}
+
+impl std::fmt::Display for KeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeySource::Env => write!(f, "environment"),
+            KeySource::Keyring => write!(f, "keyring"),
+            KeySource::Config => write!(f, "config file"),
+        }
+    }
+}
+
+/// Environment variable holding the API key of `provider`, or `None` for
+/// providers that do not use one.
+pub fn env_var(provider: AiProvider) -> Option<&'static str> {
+    match provider {
+        AiProvider::KeyhanStudio => Some("KEYHANSTUDIO_API_KEY"),
+        AiProvider::OpenAi => Some("OPENAI_API_KEY"),
+        AiProvider::AzureOpenAi => Some("AZURE_OPENAI_API_KEY"),
+        AiProvider::OpenAiCompatible => Some("OPENAI_COMPATIBLE_API_KEY"),
+        AiProvider::Anthropic => Some("ANTHROPIC_API_KEY"),
+        AiProvider::Ollama | AiProvider::LmStudio | AiProvider::LocalGguf | AiProvider::Rules => {
+            None
+        }
+    }
+}
+
+/// The API key of `provider` and where it was found, falling back to
+/// `config_value` from config.toml.
+pub fn lookup(provider: AiProvider, config_value: &str) -> Option<(String, KeySource)> {
+    let var = env_var(provider)?;
+    if let Some(key) = std::env::var(var)
+        .ok()
+        .map(|k| k.trim().to_string())
+        .filter(|k| !k.is_empty())
+    {
+        return Some((key, KeySource::Env));
+    }
+    if let Some(key) = keyring_get(provider) {
+        return Some((key, KeySource::Keyring));
+    }
+    (!config_value.is_empty()).then(|| (config_value.to_string(), KeySource::Config))
+}
+
+/// The API key of `provider`, or an empty string when none is set.
+pub fn api_key(provider: AiProvider, config_value: &str) -> String {
+    lookup(provider, config_value)
+        .map(|(key, _)| key)
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_get(provider: AiProvider) -> Option<String> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, &provider.to_string()).ok()?;
+    match entry.get_password() {
+        Ok(key) => Some(key),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            debug!("Keyring lookup for {provider} failed: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_get(provider: AiProvider) -> Option<String> {
+    debug!("No keyring support in this build; skipping keyring lookup for {provider}");
+    None
+}
+
+/// Store the API key of `provider` in the OS keyring.
+#[cfg(feature = "keyring")]
+pub fn store(provider: AiProvider, key: &str) -> Result<()> {
+    use anyhow::Context;
+
+    anyhow::ensure!(
+        env_var(provider).is_some(),
+        "{provider} does not use an API key"
+    );
+    anyhow::ensure!(!key.trim().is_empty(), "The API key is empty");
+    keyring::Entry::new(KEYRING_SERVICE, &provider.to_string())
+        .and_then(|entry| entry.set_password(key.trim()))
+        .with_context(|| format!("Storing the {provider} API key in the OS keyring"))
+}
+
+/// Remove the API key of `provider` from the OS keyring, if it has one.
+#[cfg(feature = "keyring")]
+pub fn delete(provider: AiProvider) -> Result<()> {
+    use anyhow::Context;
+
+    let entry = keyring::Entry::new(KEYRING_SERVICE, &provider.to_string())
+        .context("Opening the OS keyring")?;
+    match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => {
+            Err(e).with_context(|| format!("Removing the {provider} API key from the OS keyring"))
+        }
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn store(_provider: AiProvider, _key: &str) -> Result<()> {
+    anyhow::bail!("This build has no OS keyring support (rebuild with --features keyring)")
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn delete(_provider: AiProvider) -> Result<()> {
+    anyhow::bail!("This build has no OS keyring support (rebuild with --features keyring)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var_overrides_config() {
+        let var = env_var(AiProvider::KeyhanStudio).unwrap();
+        std::env::set_var(var, " from-env ");
+        assert_eq!(
+            lookup(AiProvider::KeyhanStudio, "from-config"),
+            Some(("from-env".to_string(), KeySource::Env))
+        );
+
+        std::env::remove_var(var);
+        if cfg!(not(feature = "keyring")) {
+            assert_eq!(
+                lookup(AiProvider::KeyhanStudio, "from-config"),
+                Some(("from-config".to_string(), KeySource::Config))
+            );
+        }
+        assert_eq!(lookup(AiProvider::Ollama, "unused"), None);
+    }
+}
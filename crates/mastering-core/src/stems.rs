@@ -0,0 +1,349 @@
+//! Stem separation before mastering.
+//!
+//! A job with stems enabled has its input split by `stem_separation.py`
+//! (Demucs: drums, bass, vocals and other for the default model). Each stem
+//! then gets its own gain and EQ, and the stems are summed back into the
+//! file the backend masters. Adjustments come from the user or, on the AI
+//! backend, from the language model, which is shown an analysis of each stem.
+//!
+//! Tracks delivered as stems skip the separation: their files are summed at
+//! the gains given with them and the mix is mastered like any input.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+use crate::analysis::decode::{decode_audio, AudioStream};
+use crate::backends::bridge;
+use crate::config::{Config, StemsConfig};
+use crate::dsp::eq;
+use crate::encode::{self, EncodeOptions};
+use crate::error::MasteringError;
+use crate::gpu;
+use crate::pipeline::ProgressReporter;
+use crate::resample;
+use crate::types::{Device, SampleFormat, StemAdjustment, StemInput};
+
+/// One separated stem.
+#[derive(Debug, Clone)]
+pub struct Stem {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Runs the stem separation script.
+#[derive(Debug, Clone)]
+pub struct StemSeparator {
+    config: StemsConfig,
+    scripts_dir: PathBuf,
+    timeout: bridge::Timeout,
+}
+
+impl StemSeparator {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            config: config.stems.clone(),
+            scripts_dir: Config::python_scripts_dir(),
+            timeout: bridge::Timeout::configured(config.stems.timeout_secs, "stems.timeout_secs"),
+        }
+    }
+
+    /// Separate `input` into stems written to `output_dir`, returning them
+    /// by name and the device the model ran on.
+    pub async fn separate(
+        &self,
+        input: &Path,
+        output_dir: &Path,
+        progress: &ProgressReporter,
+    ) -> Result<(Vec<Stem>, Device)> {
+        let script = self.scripts_dir.join("stem_separation.py");
+        anyhow::ensure!(
+            script.exists(),
+            "Stem separation script not found at: {}",
+            script.display()
+        );
+        let device = gpu::resolve_device(self.config.device)?;
+        info!(
+            "Separating stems with {} on {device}: {}",
+            self.config.model,
+            input.display()
+        );
+
+        let request = serde_json::json!({
+            "input": input.to_string_lossy(),
+            "output_dir": output_dir.to_string_lossy(),
+            "model": self.config.model,
+            "device": device,
+        })
+        .to_string();
+        let output = bridge::run(
+            &self.config.python_path,
+            [script.as_os_str(), request.as_ref()],
+            self.timeout,
+            "stem separation script",
+            progress,
+        )
+        .await?;
+        debug!("Stem separation stdout: {}", output.stdout);
+
+        if !output.status.success() {
+            // Errors the script catches are printed as JSON; a crash leaves only stderr
+            let reported = serde_json::from_str::<serde_json::Value>(output.stdout.trim())
+                .ok()
+                .and_then(|r| r["error"].as_str().map(str::to_string));
+            match reported {
+                Some(error) => anyhow::bail!("Stem separation failed: {error}"),
+                None => anyhow::bail!("Stem separation failed:\n{}", output.stderr),
+            }
+        }
+
+        let response: serde_json::Value = serde_json::from_str(output.stdout.trim())
+            .with_context(|| format!("Parsing stem separation output: {}", output.stdout))?;
+        if let Some(error) = response["error"].as_str() {
+            anyhow::bail!("Stem separation failed: {error}");
+        }
+
+        let stems: Vec<Stem> = response["stems"]
+            .as_object()
+            .context("Stem separation returned no stems")?
+            .iter()
+            .filter_map(|(name, path)| {
+                Some(Stem {
+                    name: name.clone(),
+                    path: PathBuf::from(path.as_str()?),
+                })
+            })
+            .collect();
+        anyhow::ensure!(!stems.is_empty(), "Stem separation returned no stems");
+        let device = response["device"]
+            .as_str()
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(device);
+        Ok((stems, device))
+    }
+
+    pub async fn check_available(&self) -> Result<bool> {
+        if !self.scripts_dir.join("stem_separation.py").exists() {
+            return Ok(false);
+        }
+        bridge::probe(&self.config.python_path, "import demucs; print('ok')").await
+    }
+}
+
+/// Fail if `adjustments` names a stem that is not among `stems`.
+pub fn check_stem_names(
+    adjustments: &BTreeMap<String, StemAdjustment>,
+    stems: &[Stem],
+) -> Result<(), MasteringError> {
+    let unknown: Vec<&str> = adjustments
+        .keys()
+        .filter(|name| !stems.iter().any(|s| &s.name == *name))
+        .map(String::as_str)
+        .collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<&str> = stems.iter().map(|s| s.name.as_str()).collect();
+    Err(MasteringError::InvalidConfig {
+        message: format!(
+            "No stem named {} (this track has {})",
+            unknown.join(", "),
+            names.join(", ")
+        ),
+        config_key: Some("stems".into()),
+    })
+}
+
+/// Apply each stem's adjustment, sum the stems at `sample_rate` and write the
+/// mix to `output` as 32-bit float WAV, which keeps any overs for the
+/// mastering chain to handle.
+pub fn remix(
+    stems: &[Stem],
+    adjustments: &BTreeMap<String, StemAdjustment>,
+    sample_rate: u32,
+    output: &Path,
+) -> Result<()> {
+    let parts: Vec<_> = stems
+        .iter()
+        .map(|s| (s.name.as_str(), s.path.as_path(), adjustments.get(&s.name)))
+        .collect();
+    sum(&parts, sample_rate, output)
+}
+
+/// Sum the stem files of a track at their gains and the highest sample rate
+/// among them, writing the mix to `output` as 32-bit float WAV.
+pub fn mix_inputs(inputs: &[StemInput], output: &Path) -> Result<()> {
+    let mut sample_rate = 0;
+    for input in inputs {
+        let stream = AudioStream::open(&input.path)
+            .with_context(|| format!("Opening stem {}", input.path.display()))?;
+        sample_rate = sample_rate.max(stream.sample_rate());
+    }
+    let names: Vec<String> = inputs
+        .iter()
+        .map(|i| i.path.display().to_string())
+        .collect();
+    let gains: Vec<StemAdjustment> = inputs
+        .iter()
+        .map(|i| StemAdjustment {
+            gain_db: i.gain_db,
+            eq: Vec::new(),
+        })
+        .collect();
+    let parts: Vec<_> = inputs
+        .iter()
+        .zip(&names)
+        .zip(&gains)
+        .map(|((input, name), gain)| (name.as_str(), input.path.as_path(), Some(gain)))
+        .collect();
+    sum(&parts, sample_rate, output)
+}
+
+/// Sum `(name, file, adjustment)` parts. Mono parts are spread over all
+/// channels of the others.
+fn sum(
+    parts: &[(&str, &Path, Option<&StemAdjustment>)],
+    sample_rate: u32,
+    output: &Path,
+) -> Result<()> {
+    anyhow::ensure!(!parts.is_empty(), "There are no stems to sum");
+    let mut channels = 1;
+    for (name, path, _) in parts {
+        let stream = AudioStream::open(path).with_context(|| format!("Opening the {name} stem"))?;
+        channels = channels.max(stream.channels());
+    }
+
+    let mut mix: Vec<f32> = Vec::new();
+    for &(name, path, adjustment) in parts {
+        let audio = decode_audio(path).with_context(|| format!("Decoding the {name} stem"))?;
+        let samples = match audio.channels {
+            c if c == channels => audio.samples,
+            1 => audio
+                .samples
+                .iter()
+                .flat_map(|&s| std::iter::repeat_n(s, channels as usize))
+                .collect(),
+            c => anyhow::bail!("The {name} stem has {c} channels, the others {channels}"),
+        };
+        let mut samples = if audio.sample_rate == sample_rate {
+            samples
+        } else {
+            resample::resample(&samples, channels as usize, audio.sample_rate, sample_rate)?
+        };
+
+        if let Some(adjustment) = adjustment {
+            eq::apply(&mut samples, channels as usize, sample_rate, &adjustment.eq);
+            let gain = 10f64.powf(adjustment.gain_db / 20.0) as f32;
+            if gain != 1.0 {
+                samples.iter_mut().for_each(|s| *s *= gain);
+            }
+        }
+
+        if samples.len() > mix.len() {
+            mix.resize(samples.len(), 0.0);
+        }
+        for (m, s) in mix.iter_mut().zip(&samples) {
+            *m += s;
+        }
+    }
+
+    let opts = EncodeOptions {
+        bit_depth: 32,
+        sample_format: SampleFormat::Float,
+        ..Default::default()
+    };
+    encode::write_wav(output, &mix, channels, sample_rate, &opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_stem(dir: &Path, name: &str, value: f32) -> Stem {
+        let path = dir.join(format!("{name}.wav"));
+        let opts = EncodeOptions {
+            bit_depth: 32,
+            sample_format: SampleFormat::Float,
+            ..Default::default()
+        };
+        encode::write_wav(&path, &vec![value; 2 * 4410], 2, 44100, &opts).unwrap();
+        Stem {
+            name: name.into(),
+            path,
+        }
+    }
+
+    #[test]
+    fn test_remix_applies_gains() {
+        let dir = tempfile::tempdir().unwrap();
+        let stems = vec![
+            write_stem(dir.path(), "vocals", 0.1),
+            write_stem(dir.path(), "drums", 0.2),
+        ];
+        let adjustments = BTreeMap::from([(
+            "drums".to_string(),
+            StemAdjustment {
+                gain_db: -6.0206,
+                eq: Vec::new(),
+            },
+        )]);
+
+        let out = dir.path().join("mix.wav");
+        remix(&stems, &adjustments, 44100, &out).unwrap();
+        let mix = decode_audio(&out).unwrap();
+        assert_eq!(mix.channels, 2);
+        assert_eq!(mix.samples.len(), 2 * 4410);
+        assert!(
+            (mix.samples[100] - 0.2).abs() < 1e-4,
+            "{}",
+            mix.samples[100]
+        );
+    }
+
+    #[test]
+    fn test_mix_inputs_spreads_mono_stems() {
+        let dir = tempfile::tempdir().unwrap();
+        let stereo = write_stem(dir.path(), "drums", 0.2);
+        let mono = dir.path().join("vocals.wav");
+        let opts = EncodeOptions {
+            bit_depth: 32,
+            sample_format: SampleFormat::Float,
+            ..Default::default()
+        };
+        encode::write_wav(&mono, &vec![0.1; 4410], 1, 44100, &opts).unwrap();
+
+        let inputs: Vec<StemInput> = [
+            format!("{}:-6.0206", stereo.path.display()),
+            mono.display().to_string(),
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        assert_eq!(inputs[1].gain_db, 0.0);
+
+        let out = dir.path().join("mix.wav");
+        mix_inputs(&inputs, &out).unwrap();
+        let mix = decode_audio(&out).unwrap();
+        assert_eq!(mix.channels, 2);
+        assert!(
+            (mix.samples[101] - 0.2).abs() < 1e-4,
+            "{}",
+            mix.samples[101]
+        );
+    }
+
+    #[test]
+    fn test_unknown_stem_names_are_rejected() {
+        let stems = vec![Stem {
+            name: "vocals".into(),
+            path: PathBuf::new(),
+        }];
+        let mut adjustments = BTreeMap::from([("vocals".to_string(), StemAdjustment::default())]);
+        assert!(check_stem_names(&adjustments, &stems).is_ok());
+
+        adjustments.insert("guitar".into(), StemAdjustment::default());
+        let err = check_stem_names(&adjustments, &stems).unwrap_err();
+        assert!(err.to_string().contains("guitar"), "{err}");
+    }
+}
@@ -0,0 +1,130 @@
+//! Handling of inputs with more than two channels.
+//!
+//! The mastering backends work on stereo, so surround files are either
+//! downmixed before processing or bypass the backend and only receive a
+//! single gain, linked across all channels, that brings them to the target
+//! loudness without moving the mix balance.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::analysis::decode::decode_audio;
+use crate::analysis::loudness::integrated_loudness;
+use crate::encode::{self, EncodeOptions};
+
+/// Downmix `input` to stereo and write it to `output` as 32-bit float WAV.
+pub fn downmix_file(input: &Path, output: &Path) -> Result<()> {
+    let audio = decode_audio(input)?;
+    let stereo = audio.layout.downmix_to_stereo(&audio.samples);
+    let opts = EncodeOptions {
+        bit_depth: 32,
+        sample_format: crate::types::SampleFormat::Float,
+        ..Default::default()
+    };
+    encode::write_wav(output, &stereo, 2, audio.sample_rate, &opts)
+}
+
+/// Gain in dB that moves `lufs` to `target_lufs` without pushing a sample
+/// peak of `peak_db` above `ceiling_db`.
+pub fn linked_gain_db(lufs: f64, peak_db: f64, target_lufs: f64, ceiling_db: f64) -> f64 {
+    if !lufs.is_finite() {
+        return 0.0;
+    }
+    (target_lufs - lufs).min(ceiling_db - peak_db)
+}
+
+/// Apply one loudness gain to every channel of `input` and write `output`.
+///
+/// Returns the gain applied in dB.
+pub fn normalize_linked(
+    input: &Path,
+    output: &Path,
+    target_lufs: f64,
+    ceiling_db: f64,
+    opts: &EncodeOptions,
+) -> Result<f64> {
+    let audio = decode_audio(input)?;
+    let peak = audio.samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    let peak_db = if peak > 0.0 {
+        20.0 * (peak as f64).log10()
+    } else {
+        -120.0
+    };
+    let gain_db = linked_gain_db(
+        integrated_loudness(&audio),
+        peak_db,
+        target_lufs,
+        ceiling_db,
+    );
+
+    let gain = 10f64.powf(gain_db / 20.0) as f32;
+    let samples: Vec<f32> = audio.samples.iter().map(|s| s * gain).collect();
+    encode::write_wav(output, &samples, audio.channels, audio.sample_rate, opts)?;
+    Ok(gain_db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::decode::DecodedAudio;
+
+    fn write_surround(path: &Path) {
+        // -30 dBFS tone in L, R and C; the LFE carries a louder rumble
+        let samples: Vec<f32> = (0..48000)
+            .flat_map(|i| {
+                let t = i as f32 / 48000.0;
+                let s = 0.0316 * (2.0 * std::f32::consts::PI * 1000.0 * t).sin();
+                let lfe = 0.5 * (2.0 * std::f32::consts::PI * 40.0 * t).sin();
+                [s, s, s, lfe, 0.0, 0.0]
+            })
+            .collect();
+        let opts = EncodeOptions {
+            bit_depth: 32,
+            sample_format: crate::types::SampleFormat::Float,
+            ..Default::default()
+        };
+        encode::write_wav(path, &samples, 6, 48000, &opts).unwrap();
+    }
+
+    #[test]
+    fn test_downmix_file_is_stereo() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.wav");
+        let output = dir.path().join("out.wav");
+        write_surround(&input);
+
+        downmix_file(&input, &output).unwrap();
+        let stereo = decode_audio(&output).unwrap();
+        assert_eq!(stereo.channels, 2);
+        assert_eq!(stereo.total_frames, 48000);
+    }
+
+    #[test]
+    fn test_linked_gain_keeps_balance() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.wav");
+        let output = dir.path().join("out.wav");
+        write_surround(&input);
+
+        let opts = EncodeOptions {
+            bit_depth: 32,
+            sample_format: crate::types::SampleFormat::Float,
+            ..Default::default()
+        };
+        let gain_db = normalize_linked(&input, &output, -23.0, -1.0, &opts).unwrap();
+        let before = decode_audio(&input).unwrap();
+        let after: DecodedAudio = decode_audio(&output).unwrap();
+
+        assert_eq!(after.channels, 6);
+        let ratio = |ch: usize| after.samples[ch + 6 * 100] / before.samples[ch + 6 * 100];
+        assert!((ratio(0) - ratio(3)).abs() < 1e-4);
+        assert!((20.0 * (ratio(0) as f64).log10() - gain_db).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_linked_gain_respects_ceiling() {
+        assert_eq!(linked_gain_db(-30.0, -20.0, -14.0, -1.0), 16.0);
+        assert_eq!(linked_gain_db(-30.0, -6.0, -14.0, -1.0), 5.0);
+        assert_eq!(linked_gain_db(f64::NEG_INFINITY, -120.0, -14.0, -1.0), 0.0);
+    }
+}
@@ -4,10 +4,11 @@
 //! including audio analysis results, mastering parameters, and configuration types.
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 /// Metadata about an audio file.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AudioMetadata {
     /// Absolute path to the audio file.
     pub path: PathBuf,
@@ -15,6 +16,9 @@ pub struct AudioMetadata {
     pub sample_rate: u32,
     /// Number of audio channels (1 = mono, 2 = stereo).
     pub channels: u16,
+    /// Channel layout name (e.g., "stereo", "5.1").
+    #[serde(default)]
+    pub channel_layout: String,
     /// Duration in seconds.
     pub duration_secs: f64,
     /// Bit depth if known (e.g., 16, 24).
@@ -24,7 +28,7 @@ pub struct AudioMetadata {
 }
 
 /// Complete audio analysis results.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AudioAnalysis {
     /// File metadata.
     pub metadata: AudioMetadata,
@@ -32,6 +36,12 @@ pub struct AudioAnalysis {
     pub lufs_integrated: f64,
     /// Maximum short-term loudness (3-second window) in LUFS.
     pub lufs_short_term_max: f64,
+    /// Maximum momentary loudness (400 ms window) in LUFS.
+    #[serde(default)]
+    pub lufs_momentary_max: f64,
+    /// Loudness range (EBU Tech 3342) in LU.
+    #[serde(default)]
+    pub loudness_range_lu: f64,
     /// RMS level in dB.
     pub rms_db: f64,
     /// Sample peak level in dB.
@@ -42,12 +52,278 @@ pub struct AudioAnalysis {
     pub dynamic_range_db: f64,
     /// Stereo width (0.0 = mono, 1.0 = full stereo).
     pub stereo_width: f64,
+    /// Left/right energy difference in dB; positive when the left channel is
+    /// louder.
+    #[serde(default)]
+    pub stereo_balance_db: f64,
+    /// Correlation of left and right from -1 (out of phase) to 1 (mono).
+    #[serde(default)]
+    pub phase_correlation: f64,
+    /// Lowest correlation of any 0.5 s window with signal.
+    #[serde(default)]
+    pub phase_correlation_min: f64,
+    /// Total length of sustained (2 s or longer) out-of-phase passages, in
+    /// seconds.
+    #[serde(default)]
+    pub out_of_phase_secs: f64,
+    /// How the mix holds up folded down to mono; `None` for mono input.
+    #[serde(default)]
+    pub mono_compatibility: Option<MonoCompatibility>,
+    /// Runs of full-scale samples that show the input is already clipped.
+    #[serde(default)]
+    pub clipping: ClippingReport,
+    /// Level of the steady background noise in the quietest passages, in
+    /// dBFS; `None` when the input has no quiet passage to measure it in.
+    #[serde(default)]
+    pub noise_floor_db: Option<f64>,
+    /// RMS level above the noise floor in dB; `None` without a noise floor.
+    #[serde(default)]
+    pub signal_to_noise_db: Option<f64>,
+    /// Silence at the start and end and long silent gaps in between.
+    #[serde(default)]
+    pub silence: SilenceReport,
+    /// Estimated tempo in beats per minute; `None` without a steady pulse.
+    #[serde(default)]
+    pub tempo_bpm: Option<f64>,
     /// 7-band frequency analysis.
     pub frequency_bands: FrequencyBands,
+    /// Timbral descriptors of the long-term spectrum.
+    #[serde(default)]
+    pub spectral: SpectralDescriptors,
+    /// Levels of each channel, in stream order.
+    #[serde(default)]
+    pub channel_stats: Vec<ChannelStats>,
+    /// The readings of an EBU Mode loudness meter.
+    #[serde(default)]
+    pub ebu_mode: EbuMode,
 }
 
-/// 7-band frequency analysis results (all in dB).
+/// The values an EBU Mode meter (EBU Tech 3341) shows at the end of a
+/// programme, as broadcast delivery reports list them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EbuMode {
+    /// Integrated loudness (I) in LUFS.
+    pub integrated_lufs: f64,
+    /// Loudness range (LRA) in LU.
+    pub loudness_range_lu: f64,
+    /// Low end of the loudness range (10th percentile) in LUFS; `None`
+    /// when the programme is below the gate throughout.
+    pub loudness_range_low_lufs: Option<f64>,
+    /// High end of the loudness range (95th percentile) in LUFS.
+    pub loudness_range_high_lufs: Option<f64>,
+    /// Maximum momentary loudness (M, 400 ms) in LUFS.
+    pub momentary_max_lufs: f64,
+    /// Maximum short-term loudness (S, 3 s) in LUFS.
+    pub short_term_max_lufs: f64,
+    /// Maximum true peak (TP) in dBTP.
+    pub true_peak_max_dbtp: f64,
+}
+
+/// Levels of a single channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelStats {
+    /// Display name, e.g. "Left" or "LFE".
+    pub name: String,
+    /// Sample peak level in dB.
+    pub peak_db: f64,
+    /// RMS level in dB.
+    pub rms_db: f64,
+    /// Share of the channel in the integrated loudness, from 0 to 1; 0 for
+    /// the LFE, which loudness ignores.
+    pub loudness_share: f64,
+}
+
+/// Clipped regions: runs of three or more consecutive full-scale samples in
+/// one channel.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClippingReport {
+    /// Number of clipped regions over all channels.
+    pub regions: u64,
+    /// Samples inside clipped regions over all channels.
+    pub clipped_samples: u64,
+    /// Time during which at least one channel is clipped, in seconds.
+    pub clipped_secs: f64,
+    /// The longest regions, longest first.
+    pub worst: Vec<ClippedRegion>,
+}
+
+/// A single run of full-scale samples.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClippedRegion {
+    /// Zero-based channel index.
+    pub channel: u16,
+    /// Start of the region in seconds.
+    pub start_secs: f64,
+    /// Length of the region in seconds.
+    pub duration_secs: f64,
+}
+
+/// Stretches where every sample stays below
+/// [`DEFAULT_SILENCE_THRESHOLD_DB`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SilenceReport {
+    /// Silence before the first sound, in seconds.
+    pub leading_secs: f64,
+    /// Silence after the last sound, in seconds.
+    pub trailing_secs: f64,
+    /// Silent gaps of two seconds or more between sounds.
+    pub gaps: Vec<SilentGap>,
+}
+
+/// A silent stretch inside the track.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SilentGap {
+    /// Start of the gap in seconds.
+    pub start_secs: f64,
+    /// Length of the gap in seconds.
+    pub duration_secs: f64,
+}
+
+/// Level in dBFS below which samples count as silent, unless a trim sets
+/// its own threshold.
+pub const DEFAULT_SILENCE_THRESHOLD_DB: f64 = -60.0;
+
+/// How leading and trailing silence is trimmed before mastering.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SilenceTrim {
+    /// Level in dBFS below which samples count as silent.
+    pub threshold_db: f64,
+    /// Silence kept before the first and after the last sound, in
+    /// milliseconds.
+    pub padding_ms: f64,
+}
+
+impl Default for SilenceTrim {
+    fn default() -> Self {
+        Self {
+            threshold_db: DEFAULT_SILENCE_THRESHOLD_DB,
+            padding_ms: 250.0,
+        }
+    }
+}
+
+/// Silence cut from the start and end of the input.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrimmedSilence {
+    pub leading_secs: f64,
+    pub trailing_secs: f64,
+}
+
+/// A structural section of a track, e.g. a verse or a chorus.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    pub start_secs: f64,
+    pub end_secs: f64,
+    /// Mean level in dBFS.
+    pub level_db: f64,
+    /// Gain ridden on the section before mastering, in dB.
+    pub gain_db: f64,
+}
+
+/// Part of a file to analyze or master, in seconds from its start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeRange {
+    pub start_secs: f64,
+    /// End of the range; `None` runs to the end of the file.
+    pub end_secs: Option<f64>,
+}
+
+impl TimeRange {
+    /// Check that the range starts at or after 0 and ends after it starts.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.start_secs >= 0.0,
+            "Range start cannot be negative (got {}s)",
+            self.start_secs
+        );
+        if let Some(end) = self.end_secs {
+            anyhow::ensure!(
+                end > self.start_secs,
+                "Range end ({end}s) must be after its start ({}s)",
+                self.start_secs
+            );
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.end_secs {
+            Some(end) => write!(f, "{:.2}s-{:.2}s", self.start_secs, end),
+            None => write!(f, "{:.2}s-end", self.start_secs),
+        }
+    }
+}
+
+/// Shape of a fade's gain over its length.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FadeCurve {
+    /// Gain rises in a straight line.
+    #[default]
+    Linear,
+    /// Level rises evenly in dB, from -60 dB; sounds even to the ear.
+    Log,
+    /// Half a cosine: starts and ends gently.
+    Cosine,
+}
+
+impl std::fmt::Display for FadeCurve {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FadeCurve::Linear => write!(f, "linear"),
+            FadeCurve::Log => write!(f, "log"),
+            FadeCurve::Cosine => write!(f, "cosine"),
+        }
+    }
+}
+
+impl std::str::FromStr for FadeCurve {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linear" => Ok(FadeCurve::Linear),
+            "log" | "logarithmic" => Ok(FadeCurve::Log),
+            "cosine" | "cos" => Ok(FadeCurve::Cosine),
+            _ => anyhow::bail!("Unknown fade curve: {s}. Available: linear, log, cosine"),
+        }
+    }
+}
+
+/// Fade-in and fade-out applied at the end of the mastering chain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fades {
+    /// Length of the fade-in in seconds; 0 for none.
+    pub in_secs: f64,
+    /// Length of the fade-out in seconds; 0 for none.
+    pub out_secs: f64,
+    pub curve: FadeCurve,
+}
+
+impl Fades {
+    pub fn any(&self) -> bool {
+        self.in_secs > 0.0 || self.out_secs > 0.0
+    }
+}
+
+/// What a stereo mix loses when its channels are summed to mono.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonoCompatibility {
+    /// Level each band loses in the mono fold-down, in dB: 0 for in-phase
+    /// content, about 3 for uncorrelated and large for cancelling content.
+    pub band_loss_db: FrequencyBands,
+    /// Correlation of left and right per window, for plotting over time.
+    pub correlation_over_time: Vec<f64>,
+    /// Length of each correlation window in seconds.
+    pub correlation_window_secs: f64,
+}
+
+/// 7-band frequency analysis results (all in dB).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FrequencyBands {
     /// Sub-bass (20–60 Hz).
     pub sub_bass: f64,
@@ -65,10 +341,33 @@ pub struct FrequencyBands {
     pub brilliance: f64,
 }
 
+/// Standard timbral descriptors of the long-term spectrum between 20 Hz
+/// and 20 kHz.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SpectralDescriptors {
+    /// Power-weighted mean frequency in Hz; higher sounds brighter.
+    pub centroid_hz: f64,
+    /// Frequency below which 85% of the power lies, in Hz.
+    pub rolloff_hz: f64,
+    /// Geometric over arithmetic mean of the power, from 0 (tonal) to 1
+    /// (noise-like).
+    pub flatness: f64,
+    /// Slope of the spectrum in dB per octave: 0 for white noise, -3 for
+    /// pink noise; steeper is darker.
+    pub tilt_db_per_octave: f64,
+}
+
 /// Mastering parameters generated by AI or manual configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MasteringParams {
     pub eq: Vec<EqBand>,
+    /// Sibilance control between the EQ and the compressor.
+    #[serde(default)]
+    pub de_esser: DeEsserParams,
+    /// Per-band compression ahead of the main compressor, which then acts
+    /// as a glue compressor on the whole signal.
+    #[serde(default)]
+    pub multiband: Option<MultibandParams>,
     pub compression: CompressionParams,
     pub limiter: LimiterParams,
     pub stereo: StereoParams,
@@ -81,6 +380,9 @@ pub struct EqBand {
     pub gain_db: f64,
     pub q: f64,
     pub band_type: EqBandType,
+    /// Part of a stereo signal the band applies to.
+    #[serde(default)]
+    pub channel: EqChannel,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -93,6 +395,186 @@ pub enum EqBandType {
     HighPass,
 }
 
+/// Where an EQ band acts: on both channels alike, or on the mid (L+R) or
+/// side (L-R) signal only. Mono and multichannel audio treat mid bands as
+/// stereo ones and skip side bands.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EqChannel {
+    #[default]
+    Stereo,
+    Mid,
+    Side,
+}
+
+/// Level and tone changes made to one stem before the stems are summed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StemAdjustment {
+    #[serde(default)]
+    pub gain_db: f64,
+    #[serde(default)]
+    pub eq: Vec<EqBand>,
+}
+
+/// Restoration stages run on the input before it is mastered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestorationStages {
+    /// Broadband noise reduction.
+    #[serde(default)]
+    pub denoise: bool,
+    /// Mains hum removal at 50 or 60 Hz and its harmonics.
+    #[serde(default)]
+    pub dehum: bool,
+    /// Repair of clicks.
+    #[serde(default)]
+    pub declick: bool,
+    /// Repair of the dense, fine clicks of worn vinyl.
+    #[serde(default)]
+    pub decrackle: bool,
+}
+
+impl RestorationStages {
+    pub fn any(&self) -> bool {
+        self.denoise || self.dehum || self.declick || self.decrackle
+    }
+}
+
+/// What the restoration stages found and changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RestorationReport {
+    /// Mains frequency hum was removed at.
+    #[serde(default)]
+    pub hum_hz: Option<f64>,
+    /// Clicks and crackle impulses interpolated over.
+    #[serde(default)]
+    pub clicks_repaired: usize,
+    /// Level of the noise profile the gate worked against, in dBFS.
+    #[serde(default)]
+    pub noise_floor_db: Option<f64>,
+}
+
+/// One file of a track delivered as stems, with the gain it is summed at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StemInput {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub gain_db: f64,
+}
+
+impl std::str::FromStr for StemInput {
+    type Err = anyhow::Error;
+    /// Parse `FILE` or `FILE:GAIN_DB`, e.g. `vocals.wav:-1.5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, gain_db) = match s.rsplit_once(':') {
+            Some((path, gain)) if !path.is_empty() && gain.trim().parse::<f64>().is_ok() => {
+                (path, gain.trim().parse()?)
+            }
+            _ => (s, 0.0),
+        };
+        anyhow::ensure!(
+            !path.is_empty(),
+            "Expected FILE or FILE:GAIN_DB for a stem, got {s:?}"
+        );
+        Ok(StemInput {
+            path: PathBuf::from(path),
+            gain_db,
+        })
+    }
+}
+
+/// An extra deliverable of a job, rendered from the same master at its own
+/// loudness and in its own format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeliveryTarget {
+    pub target_lufs: f64,
+    /// Output format; defaults to the job's.
+    #[serde(default)]
+    pub format: Option<AudioFormat>,
+    /// Bit depth of lossless output; defaults to the job's.
+    #[serde(default)]
+    pub bit_depth: Option<u16>,
+    /// Bitrate of lossy output; defaults to the `[encoding]` config.
+    #[serde(default)]
+    pub bitrate_kbps: Option<u32>,
+}
+
+impl DeliveryTarget {
+    /// Suffix that tells this target's file apart, e.g. `14lufs_16bit`.
+    pub fn file_suffix(&self) -> String {
+        let mut suffix = format!("{}lufs", -self.target_lufs);
+        if let Some(bits) = self.bit_depth {
+            suffix.push_str(&format!("_{bits}bit"));
+        }
+        if let Some(kbps) = self.bitrate_kbps {
+            suffix.push_str(&format!("_{kbps}k"));
+        }
+        suffix
+    }
+}
+
+impl std::str::FromStr for DeliveryTarget {
+    type Err = anyhow::Error;
+    /// Parse `LUFS[:FORMAT[:BITS|KBPS]]`, e.g. `-9:wav:16` or `-14:mp3:320`.
+    /// The last field is a bit depth for lossless formats and a bitrate for
+    /// lossy ones.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split(':').map(str::trim);
+        let lufs = fields.next().unwrap_or_default();
+        let target_lufs: f64 = lufs.parse().map_err(|_| {
+            anyhow::anyhow!("Expected LUFS[:FORMAT[:BITS|KBPS]] for a target, got {s:?}")
+        })?;
+        let format: Option<AudioFormat> = fields.next().map(str::parse).transpose()?;
+        let mut target = DeliveryTarget {
+            target_lufs,
+            format,
+            bit_depth: None,
+            bitrate_kbps: None,
+        };
+        if let Some(last) = fields.next() {
+            let value: u32 = last.parse().map_err(|_| {
+                anyhow::anyhow!("Expected a bit depth or bitrate in target {s:?}, got {last:?}")
+            })?;
+            if format.is_some_and(|f| f.is_lossy()) {
+                target.bitrate_kbps = Some(value);
+            } else {
+                anyhow::ensure!(
+                    matches!(value, 16 | 24 | 32),
+                    "Bit depth must be 16, 24, or 32 in target {s:?}"
+                );
+                target.bit_depth = Some(value as u16);
+            }
+        }
+        anyhow::ensure!(fields.next().is_none(), "Too many fields in target {s:?}");
+        Ok(target)
+    }
+}
+
+/// ReplayGain 2.0 values of a file, relative to -18 LUFS.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReplayGain {
+    /// Gain that brings the track to the reference loudness, in dB.
+    pub track_gain_db: f64,
+    /// Sample peak of the track as a linear amplitude (1.0 is full scale).
+    pub track_peak: f64,
+    /// Gain that brings the whole album to the reference loudness, in dB.
+    #[serde(default)]
+    pub album_gain_db: Option<f64>,
+    /// Highest sample peak of any track of the album.
+    #[serde(default)]
+    pub album_peak: Option<f64>,
+}
+
+/// A file written for one of a job's [`DeliveryTarget`]s.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Deliverable {
+    pub path: PathBuf,
+    pub target: DeliveryTarget,
+    /// Measured loudness of the file in LUFS.
+    pub lufs_integrated: f64,
+    /// Measured true peak of the file in dBTP.
+    pub true_peak_db: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompressionParams {
     pub threshold_db: f64,
@@ -103,11 +585,93 @@ pub struct CompressionParams {
     pub makeup_gain_db: f64,
 }
 
+/// Multiband compression: the signal is split at `crossovers` into one band
+/// more than there are crossovers, and each band is compressed on its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MultibandParams {
+    /// Crossover frequencies in Hz, lowest first.
+    pub crossovers: Vec<f64>,
+    /// Compression of each band, lowest first.
+    pub bands: Vec<BandCompressionParams>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BandCompressionParams {
+    pub threshold_db: f64,
+    pub ratio: f64,
+    pub attack_ms: f64,
+    pub release_ms: f64,
+    #[serde(default)]
+    pub makeup_gain_db: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LimiterParams {
     pub enabled: bool,
+    /// Highest true peak let through, in dBTP.
     pub ceiling_db: f64,
     pub release_ms: f64,
+    /// How far ahead the gain starts to come down before a peak.
+    #[serde(default = "default_limiter_lookahead_ms")]
+    pub lookahead_ms: f64,
+    /// Oversampling of the peak detector; 1 limits sample peaks only.
+    #[serde(default = "default_limiter_oversampling")]
+    pub oversampling: u32,
+}
+
+fn default_limiter_lookahead_ms() -> f64 {
+    5.0
+}
+fn default_limiter_oversampling() -> u32 {
+    4
+}
+
+impl Default for LimiterParams {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ceiling_db: -1.0,
+            release_ms: 50.0,
+            lookahead_ms: default_limiter_lookahead_ms(),
+            oversampling: default_limiter_oversampling(),
+        }
+    }
+}
+
+/// Split-band de-esser: the band above `frequency` is turned down by as much
+/// as it exceeds `threshold_db`, up to `max_reduction_db`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeEsserParams {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Lower edge of the sibilance band, usually 5000 to 9000 Hz.
+    #[serde(default = "default_de_esser_frequency")]
+    pub frequency: f64,
+    #[serde(default = "default_de_esser_threshold_db")]
+    pub threshold_db: f64,
+    #[serde(default = "default_de_esser_max_reduction_db")]
+    pub max_reduction_db: f64,
+}
+
+fn default_de_esser_frequency() -> f64 {
+    6500.0
+}
+fn default_de_esser_threshold_db() -> f64 {
+    -30.0
+}
+fn default_de_esser_max_reduction_db() -> f64 {
+    6.0
+}
+
+impl Default for DeEsserParams {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            frequency: default_de_esser_frequency(),
+            threshold_db: default_de_esser_threshold_db(),
+            max_reduction_db: default_de_esser_max_reduction_db(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -123,15 +687,181 @@ pub struct MasteringResult {
     pub pre_analysis: Option<AudioAnalysis>,
     pub post_analysis: Option<AudioAnalysis>,
     pub params_applied: Option<MasteringParams>,
+    /// Parameters that failed validation and were clamped or dropped.
+    #[serde(default)]
+    pub param_corrections: Vec<ParamCorrection>,
+    /// Mastering brief the job was run with.
+    #[serde(default)]
+    pub brief: Option<String>,
+    /// The AI's reasoning behind `params_applied`, when requested.
+    #[serde(default)]
+    pub explanation: Option<ParamExplanation>,
+    /// Parameter sets the AI proposed, with their excerpt scores; empty
+    /// unless several candidates were requested.
+    #[serde(default)]
+    pub candidates: Vec<ParamCandidate>,
+    /// Language model tokens spent on the job; `None` when no model was called.
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+    /// Device the local ML model ran on; `None` for other backends.
+    #[serde(default)]
+    pub device: Option<Device>,
+    /// Gain and EQ applied to each stem; `None` unless stems were separated.
+    #[serde(default)]
+    pub stem_adjustments: Option<BTreeMap<String, StemAdjustment>>,
+    /// What restoration found and changed; `None` unless it ran.
+    #[serde(default)]
+    pub restoration: Option<RestorationReport>,
+    /// Whether the right channel was inverted to fix an out-of-phase input.
+    #[serde(default)]
+    pub polarity_flipped: bool,
+    /// Silence cut from the input; `None` unless trimming ran.
+    #[serde(default)]
+    pub silence_trimmed: Option<TrimmedSilence>,
+    /// Part of the input that was mastered; `None` for the whole input.
+    #[serde(default)]
+    pub excerpt: Option<TimeRange>,
+    /// Files written for the job's extra delivery targets.
+    #[serde(default)]
+    pub deliverables: Vec<Deliverable>,
+    /// ReplayGain values written to the output's tags; `None` unless asked for.
+    #[serde(default)]
+    pub replaygain: Option<ReplayGain>,
+    /// Left-over-right level difference evened out before mastering, in dB;
+    /// `None` unless the balance was corrected.
+    #[serde(default)]
+    pub balance_correction_db: Option<f64>,
+    /// Sections that got their own gain before mastering; empty when the
+    /// whole track ran through one static chain.
+    #[serde(default)]
+    pub sections: Vec<Section>,
+    /// How long the job and its main stages took.
+    #[serde(default)]
+    pub timings: Timings,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Wall-clock time spent on a mastering job, in seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timings {
+    pub total_secs: f64,
+    /// Analysis of the input.
+    pub analysis_secs: f64,
+    /// Backend processing; 0 for a dry run.
+    pub processing_secs: f64,
+}
+
+/// Language model requests and tokens spent, with an estimated cost.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub requests: u32,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Estimated cost in USD; `None` when the model's price is unknown.
+    #[serde(default)]
+    pub estimated_cost_usd: Option<f64>,
+}
+
+impl TokenUsage {
+    /// Add `other` to this total. A known cost plus an unknown one stays known.
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.estimated_cost_usd = match (self.estimated_cost_usd, other.estimated_cost_usd) {
+            (Some(a), Some(b)) => Some(a + b),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+/// A parameter set the AI proposed, scored on an excerpt of the input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParamCandidate {
+    pub params: MasteringParams,
+    /// Integrated loudness of the rendered excerpt.
+    pub lufs: f64,
+    /// Distance from the target loudness, in LU.
+    pub lufs_error: f64,
+    /// Mean band shift against the source beyond 2 dB.
+    pub spectral_error: f64,
+    /// Combined score; lower is better.
+    pub score: f64,
+    /// Whether this candidate was rendered.
+    pub selected: bool,
+}
+
+/// Human-readable reasons for a set of mastering parameters.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParamExplanation {
+    /// Overall approach in a sentence or two.
+    #[serde(default)]
+    pub summary: String,
+    /// One reason per EQ band, in the order of `MasteringParams::eq`.
+    #[serde(default)]
+    pub eq: Vec<String>,
+    #[serde(default)]
+    pub de_esser: String,
+    #[serde(default)]
+    pub multiband: String,
+    #[serde(default)]
+    pub compression: String,
+    #[serde(default)]
+    pub limiter: String,
+    #[serde(default)]
+    pub stereo: String,
+}
+
+/// Listener feedback on an earlier master, used to derive adjusted parameters.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Refinement {
+    /// What to change, e.g. "make it brighter".
+    pub feedback: String,
+    /// Parameters the previous master was rendered with.
+    pub previous_params: MasteringParams,
+    /// The previous master; it is re-analyzed for the follow-up prompt.
+    pub previous_output: PathBuf,
+}
+
+/// A mastering parameter that was out of range or malformed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamCorrection {
+    /// Path of the parameter, e.g. `eq[2].gain_db`.
+    pub field: String,
+    pub message: String,
+}
+
+impl ParamCorrection {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Backend {
+    #[default]
     Auto,
     Matchering,
     Ai,
     LocalMl,
+    /// Built-in DSP chain; needs neither Python nor an AI provider.
+    Basic,
+    /// Renders a saved recipe as it is, without choosing new parameters.
+    Recipe,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 6] = [
+        Backend::Auto,
+        Backend::Matchering,
+        Backend::Ai,
+        Backend::LocalMl,
+        Backend::Basic,
+        Backend::Recipe,
+    ];
 }
 
 impl std::fmt::Display for Backend {
@@ -141,6 +871,8 @@ impl std::fmt::Display for Backend {
             Backend::Matchering => write!(f, "matchering"),
             Backend::Ai => write!(f, "ai"),
             Backend::LocalMl => write!(f, "local-ml"),
+            Backend::Basic => write!(f, "basic"),
+            Backend::Recipe => write!(f, "recipe"),
         }
     }
 }
@@ -153,6 +885,8 @@ impl std::str::FromStr for Backend {
             "matchering" => Ok(Backend::Matchering),
             "ai" => Ok(Backend::Ai),
             "local-ml" | "local_ml" | "localml" => Ok(Backend::LocalMl),
+            "basic" => Ok(Backend::Basic),
+            "recipe" => Ok(Backend::Recipe),
             _ => anyhow::bail!("Unknown backend: {s}"),
         }
     }
@@ -165,7 +899,17 @@ pub enum AiProvider {
     LmStudio,
     KeyhanStudio,
     OpenAi,
+    /// OpenAI models hosted on Azure.
+    #[serde(rename = "azure_openai")]
+    AzureOpenAi,
+    /// Any gateway speaking the OpenAI chat completions API.
+    #[serde(rename = "openai_compatible")]
+    OpenAiCompatible,
     Anthropic,
+    /// Embedded GGUF model (requires the `local-llm` feature).
+    LocalGguf,
+    /// Offline, deterministic rules instead of a language model.
+    Rules,
 }
 
 impl std::fmt::Display for AiProvider {
@@ -175,7 +919,11 @@ impl std::fmt::Display for AiProvider {
             AiProvider::LmStudio => write!(f, "lmstudio"),
             AiProvider::KeyhanStudio => write!(f, "keyhanstudio"),
             AiProvider::OpenAi => write!(f, "openai"),
+            AiProvider::AzureOpenAi => write!(f, "azure-openai"),
+            AiProvider::OpenAiCompatible => write!(f, "openai-compatible"),
             AiProvider::Anthropic => write!(f, "anthropic"),
+            AiProvider::LocalGguf => write!(f, "local-gguf"),
+            AiProvider::Rules => write!(f, "rules"),
         }
     }
 }
@@ -188,7 +936,13 @@ impl std::str::FromStr for AiProvider {
             "lmstudio" | "lm_studio" => Ok(AiProvider::LmStudio),
             "keyhanstudio" | "keyhan" => Ok(AiProvider::KeyhanStudio),
             "openai" => Ok(AiProvider::OpenAi),
+            "azure-openai" | "azure_openai" | "azure" => Ok(AiProvider::AzureOpenAi),
+            "openai-compatible" | "openai_compatible" | "compatible" => {
+                Ok(AiProvider::OpenAiCompatible)
+            }
             "anthropic" | "claude" => Ok(AiProvider::Anthropic),
+            "local-gguf" | "local_gguf" | "gguf" => Ok(AiProvider::LocalGguf),
+            "rules" | "heuristics" => Ok(AiProvider::Rules),
             _ => anyhow::bail!("Unknown AI provider: {s}"),
         }
     }
@@ -200,6 +954,36 @@ pub enum AudioFormat {
     Wav,
     Flac,
     Mp3,
+    /// AAC in an MPEG-4 (`.m4a`) container.
+    Aac,
+    /// Opus in an Ogg (`.opus`) container.
+    Opus,
+    Aiff,
+    /// Apple Lossless in an MPEG-4 (`.m4a`) container.
+    Alac,
+}
+
+impl AudioFormat {
+    /// File extension used for output files.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::Aac => "m4a",
+            AudioFormat::Opus => "opus",
+            AudioFormat::Aiff => "aiff",
+            AudioFormat::Alac => "m4a",
+        }
+    }
+
+    /// Whether the format is lossy and therefore takes a bitrate.
+    pub fn is_lossy(&self) -> bool {
+        matches!(
+            self,
+            AudioFormat::Mp3 | AudioFormat::Aac | AudioFormat::Opus
+        )
+    }
 }
 
 impl std::fmt::Display for AudioFormat {
@@ -208,6 +992,10 @@ impl std::fmt::Display for AudioFormat {
             AudioFormat::Wav => write!(f, "wav"),
             AudioFormat::Flac => write!(f, "flac"),
             AudioFormat::Mp3 => write!(f, "mp3"),
+            AudioFormat::Aac => write!(f, "aac"),
+            AudioFormat::Opus => write!(f, "opus"),
+            AudioFormat::Aiff => write!(f, "aiff"),
+            AudioFormat::Alac => write!(f, "alac"),
         }
     }
 }
@@ -219,11 +1007,224 @@ impl std::str::FromStr for AudioFormat {
             "wav" => Ok(AudioFormat::Wav),
             "flac" => Ok(AudioFormat::Flac),
             "mp3" => Ok(AudioFormat::Mp3),
+            "aac" | "m4a" => Ok(AudioFormat::Aac),
+            "opus" | "ogg" => Ok(AudioFormat::Opus),
+            "aiff" | "aif" => Ok(AudioFormat::Aiff),
+            "alac" => Ok(AudioFormat::Alac),
             _ => anyhow::bail!("Unknown audio format: {s}"),
         }
     }
 }
 
+/// Sample encoding of PCM output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleFormat {
+    /// Signed integer samples at the requested bit depth.
+    #[default]
+    Int,
+    /// 32-bit IEEE float samples (WAV only).
+    Float,
+}
+
+impl std::fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SampleFormat::Int => write!(f, "int"),
+            SampleFormat::Float => write!(f, "float"),
+        }
+    }
+}
+
+impl std::str::FromStr for SampleFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "int" | "integer" | "pcm" => Ok(SampleFormat::Int),
+            "float" => Ok(SampleFormat::Float),
+            _ => anyhow::bail!("Unknown sample format: {s}"),
+        }
+    }
+}
+
+/// Dither applied when the output is written at a reduced bit depth.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dither {
+    /// Plain rounding.
+    None,
+    /// Flat triangular (TPDF) dither.
+    #[default]
+    Tpdf,
+    /// TPDF with first-order high-pass noise shaping.
+    HighPass,
+    /// TPDF with Lipshitz's 5-tap psychoacoustic shaping (designed for 44.1 kHz).
+    Lipshitz,
+    /// TPDF with Wannamaker's 9-tap F-weighted shaping (designed for 44.1 kHz).
+    FWeighted,
+}
+
+impl std::fmt::Display for Dither {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dither::None => write!(f, "none"),
+            Dither::Tpdf => write!(f, "tpdf"),
+            Dither::HighPass => write!(f, "high-pass"),
+            Dither::Lipshitz => write!(f, "lipshitz"),
+            Dither::FWeighted => write!(f, "f-weighted"),
+        }
+    }
+}
+
+impl std::str::FromStr for Dither {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" | "off" => Ok(Dither::None),
+            "tpdf" => Ok(Dither::Tpdf),
+            "high-pass" | "highpass" | "high_pass" => Ok(Dither::HighPass),
+            "lipshitz" => Ok(Dither::Lipshitz),
+            "f-weighted" | "fweighted" | "f_weighted" => Ok(Dither::FWeighted),
+            _ => anyhow::bail!("Unknown dither: {s}"),
+        }
+    }
+}
+
+/// How files with more than two channels are mastered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SurroundMode {
+    /// Downmix to stereo (ITU-R BS.775) and master the stereo mix.
+    #[default]
+    Downmix,
+    /// Keep every channel and apply one linked loudness gain across them.
+    PassThrough,
+}
+
+impl std::fmt::Display for SurroundMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SurroundMode::Downmix => write!(f, "downmix"),
+            SurroundMode::PassThrough => write!(f, "pass-through"),
+        }
+    }
+}
+
+impl std::str::FromStr for SurroundMode {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "downmix" | "stereo" => Ok(SurroundMode::Downmix),
+            "pass-through" | "passthrough" | "pass_through" => Ok(SurroundMode::PassThrough),
+            _ => anyhow::bail!("Unknown surround mode: {s}"),
+        }
+    }
+}
+
+/// What to do when a job's output file already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    /// Replace the existing file.
+    #[default]
+    Overwrite,
+    /// Fail before any processing starts.
+    Error,
+    /// Write to the first free `<name>_v2`, `<name>_v3`, ... instead.
+    Version,
+}
+
+impl std::fmt::Display for OverwritePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverwritePolicy::Overwrite => write!(f, "overwrite"),
+            OverwritePolicy::Error => write!(f, "error"),
+            OverwritePolicy::Version => write!(f, "version"),
+        }
+    }
+}
+
+impl std::str::FromStr for OverwritePolicy {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "overwrite" | "replace" => Ok(OverwritePolicy::Overwrite),
+            "error" | "fail" => Ok(OverwritePolicy::Error),
+            "version" | "auto-version" | "auto_version" => Ok(OverwritePolicy::Version),
+            _ => anyhow::bail!("Unknown overwrite policy: {s}. Use: overwrite, error, version"),
+        }
+    }
+}
+
+/// Implementation behind the matchering backend.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchingEngine {
+    /// Built-in reference matching; needs no Python.
+    #[default]
+    Native,
+    /// The matchering Python package, run through the bridge script.
+    Python,
+}
+
+impl std::fmt::Display for MatchingEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MatchingEngine::Native => write!(f, "native"),
+            MatchingEngine::Python => write!(f, "python"),
+        }
+    }
+}
+
+impl std::str::FromStr for MatchingEngine {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "native" | "rust" => Ok(MatchingEngine::Native),
+            "python" | "matchering" => Ok(MatchingEngine::Python),
+            _ => anyhow::bail!("Unknown matching engine: {s}"),
+        }
+    }
+}
+
+/// Hardware that local ML models run on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Device {
+    /// The fastest device found: CUDA, then Metal, then the CPU.
+    #[default]
+    Auto,
+    Cpu,
+    /// An NVIDIA GPU.
+    Cuda,
+    /// The GPU of a Mac, through Core ML or PyTorch's MPS.
+    Metal,
+}
+
+impl std::fmt::Display for Device {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Device::Auto => write!(f, "auto"),
+            Device::Cpu => write!(f, "cpu"),
+            Device::Cuda => write!(f, "cuda"),
+            Device::Metal => write!(f, "metal"),
+        }
+    }
+}
+
+impl std::str::FromStr for Device {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Device::Auto),
+            "cpu" => Ok(Device::Cpu),
+            "cuda" | "gpu" => Ok(Device::Cuda),
+            "metal" | "mps" | "coreml" => Ok(Device::Metal),
+            _ => anyhow::bail!("Unknown device: {s} (expected auto, cpu, cuda or metal)"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Preset {
@@ -231,15 +1232,89 @@ pub enum Preset {
     Cd,
     Vinyl,
     Loud,
+    Podcast,
+    /// Handheld and mobile game audio.
+    Game,
+    /// Console and PC game audio.
+    GameConsole,
+    /// Theatrical and home-cinema features.
+    Cinema,
+    Trailer,
 }
 
 impl Preset {
+    pub const ALL: [Preset; 9] = [
+        Preset::Streaming,
+        Preset::Cd,
+        Preset::Vinyl,
+        Preset::Loud,
+        Preset::Podcast,
+        Preset::Game,
+        Preset::GameConsole,
+        Preset::Cinema,
+        Preset::Trailer,
+    ];
+
     pub fn target_lufs(&self) -> f64 {
         match self {
             Preset::Streaming => -14.0,
             Preset::Cd => -9.0,
             Preset::Vinyl => -12.0,
             Preset::Loud => -6.0,
+            Preset::Podcast => -16.0,
+            Preset::Game => -18.0,
+            Preset::GameConsole => -20.0,
+            Preset::Cinema => -27.0,
+            Preset::Trailer => -24.0,
+        }
+    }
+
+    /// True peak the limiter must stay under, in dBTP, for presets whose
+    /// delivery spec sets one; others use the limiter's own ceiling.
+    pub fn true_peak_ceiling_db(&self) -> Option<f64> {
+        match self {
+            Preset::Game | Preset::GameConsole | Preset::Cinema | Preset::Trailer => Some(-2.0),
+            _ => None,
+        }
+    }
+
+    /// Recommended loudness range as (min, max) in LU. Guidance for the
+    /// AI backend rather than a hard limit.
+    pub fn loudness_range_lu(&self) -> Option<(f64, f64)> {
+        match self {
+            Preset::Game => Some((3.0, 10.0)),
+            Preset::GameConsole => Some((5.0, 15.0)),
+            Preset::Cinema => Some((8.0, 20.0)),
+            Preset::Trailer => Some((4.0, 10.0)),
+            _ => None,
+        }
+    }
+
+    /// Loudness target for audio with `channels` channels. Mono podcasts
+    /// sit 3 LU lower so they play as loud as stereo ones on the same
+    /// player.
+    pub fn target_lufs_for(&self, channels: u16) -> f64 {
+        match self {
+            Preset::Podcast if channels == 1 => -19.0,
+            _ => self.target_lufs(),
+        }
+    }
+
+    /// Whether the preset is meant for spoken word and turns on speech mode.
+    pub fn is_speech(&self) -> bool {
+        matches!(self, Preset::Podcast)
+    }
+
+    /// Default fades: CD and vinyl masters get short fades so tracks start
+    /// and end without a click.
+    pub fn fades(&self) -> Fades {
+        match self {
+            Preset::Cd | Preset::Vinyl => Fades {
+                in_secs: 0.01,
+                out_secs: 0.05,
+                curve: FadeCurve::Cosine,
+            },
+            _ => Fades::default(),
         }
     }
 
@@ -249,6 +1324,11 @@ impl Preset {
             Preset::Cd => "CD-level loudness (-9 LUFS)",
             Preset::Vinyl => "Vinyl-friendly dynamics (-12 LUFS)",
             Preset::Loud => "Maximum loudness (-6 LUFS)",
+            Preset::Podcast => "Spoken word (-16 LUFS stereo, -19 LUFS mono)",
+            Preset::Game => "Mobile and handheld games (-18 LUFS, -2 dBTP)",
+            Preset::GameConsole => "Console and PC games (-20 LUFS, -2 dBTP)",
+            Preset::Cinema => "Film with wide dynamics (-27 LUFS, -2 dBTP)",
+            Preset::Trailer => "Dense, punchy trailers (-24 LUFS, -2 dBTP)",
         }
     }
 }
@@ -260,6 +1340,11 @@ impl std::fmt::Display for Preset {
             Preset::Cd => write!(f, "cd"),
             Preset::Vinyl => write!(f, "vinyl"),
             Preset::Loud => write!(f, "loud"),
+            Preset::Podcast => write!(f, "podcast"),
+            Preset::Game => write!(f, "game"),
+            Preset::GameConsole => write!(f, "game-console"),
+            Preset::Cinema => write!(f, "cinema"),
+            Preset::Trailer => write!(f, "trailer"),
         }
     }
 }
@@ -272,7 +1357,14 @@ impl std::str::FromStr for Preset {
             "cd" => Ok(Preset::Cd),
             "vinyl" => Ok(Preset::Vinyl),
             "loud" => Ok(Preset::Loud),
-            _ => anyhow::bail!("Unknown preset: {s}. Available: streaming, cd, vinyl, loud"),
+            "podcast" => Ok(Preset::Podcast),
+            "game" => Ok(Preset::Game),
+            "game-console" | "game_console" => Ok(Preset::GameConsole),
+            "cinema" => Ok(Preset::Cinema),
+            "trailer" => Ok(Preset::Trailer),
+            _ => anyhow::bail!(
+                "Unknown preset: {s}. Available: streaming, cd, vinyl, loud, podcast, game, game-console, cinema, trailer"
+            ),
         }
     }
 }
@@ -0,0 +1,520 @@
+//! Validation of mastering parameters from untrusted sources.
+//!
+//! Language models occasionally return values no mastering engineer would
+//! use (a +30 dB shelf, a 100:1 ratio) or bands of a type that does not
+//! exist. Malformed bands are dropped, every value is clamped to the ranges
+//! in [`ParamLimits`], and each change is reported as a [`ParamCorrection`].
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+use crate::config::ParamLimits;
+use crate::types::{EqBand, MasteringParams, MultibandParams, ParamCorrection, StemAdjustment};
+
+/// Quietest level a stem can be turned down to; use it to mute a stem.
+pub const MIN_STEM_GAIN_DB: f64 = -60.0;
+
+/// Largest boost of a single stem.
+pub const MAX_STEM_GAIN_DB: f64 = 12.0;
+
+/// Range of the de-esser's band edge; sibilance sits between 5 and 9 kHz.
+pub const DE_ESSER_MIN_HZ: f64 = 4000.0;
+pub const DE_ESSER_MAX_HZ: f64 = 10000.0;
+
+/// Deepest cut the de-esser may make.
+pub const DE_ESSER_MAX_REDUCTION_DB: f64 = 12.0;
+
+/// Range of the limiter's look-ahead.
+pub const LIMITER_MIN_LOOKAHEAD_MS: f64 = 0.5;
+pub const LIMITER_MAX_LOOKAHEAD_MS: f64 = 20.0;
+
+/// Highest oversampling of the limiter's peak detector.
+pub const LIMITER_MAX_OVERSAMPLING: u32 = 8;
+
+/// Most bands the multiband compressor may split the signal into.
+pub const MULTIBAND_MAX_BANDS: usize = 5;
+
+/// Build parameters from a JSON value, dropping malformed EQ bands and
+/// multiband sections and clamping everything else to `limits`.
+pub fn params_from_json(
+    mut value: serde_json::Value,
+    limits: &ParamLimits,
+) -> Result<(MasteringParams, Vec<ParamCorrection>)> {
+    let mut corrections = Vec::new();
+
+    if let Some(eq) = value.get_mut("eq") {
+        let bands = match eq.take() {
+            serde_json::Value::Array(bands) => bands,
+            serde_json::Value::Null => Vec::new(),
+            other => {
+                corrections.push(ParamCorrection::new(
+                    "eq",
+                    format!("expected a list of bands, got {other}; ignored"),
+                ));
+                Vec::new()
+            }
+        };
+        let valid: Vec<serde_json::Value> = bands
+            .into_iter()
+            .enumerate()
+            .filter_map(
+                |(i, band)| match serde_json::from_value::<EqBand>(band.clone()) {
+                    Ok(_) => Some(band),
+                    Err(e) => {
+                        corrections.push(ParamCorrection::new(
+                            format!("eq[{i}]"),
+                            format!("rejected malformed band: {e}"),
+                        ));
+                        None
+                    }
+                },
+            )
+            .collect();
+        *eq = serde_json::Value::Array(valid);
+    }
+
+    if let Some(multiband) = value.get_mut("multiband").filter(|m| !m.is_null()) {
+        if let Err(e) = serde_json::from_value::<MultibandParams>(multiband.clone()) {
+            corrections.push(ParamCorrection::new(
+                "multiband",
+                format!("rejected malformed section: {e}"),
+            ));
+            *multiband = serde_json::Value::Null;
+        }
+    }
+
+    let mut params: MasteringParams = serde_json::from_value(value)
+        .context("Mastering parameters are missing required fields")?;
+    corrections.extend(clamp_params(&mut params, limits));
+    Ok((params, corrections))
+}
+
+/// Clamp every value of `params` into `limits`, returning what changed.
+pub fn clamp_params(params: &mut MasteringParams, limits: &ParamLimits) -> Vec<ParamCorrection> {
+    let mut c = Vec::new();
+
+    clamp_eq(&mut c, "eq", &mut params.eq, limits);
+
+    let de_esser = &mut params.de_esser;
+    clamp(
+        &mut c,
+        "de_esser.frequency",
+        &mut de_esser.frequency,
+        DE_ESSER_MIN_HZ,
+        DE_ESSER_MAX_HZ,
+    );
+    clamp(
+        &mut c,
+        "de_esser.threshold_db",
+        &mut de_esser.threshold_db,
+        limits.compression_min_threshold_db,
+        0.0,
+    );
+    clamp(
+        &mut c,
+        "de_esser.max_reduction_db",
+        &mut de_esser.max_reduction_db,
+        0.0,
+        DE_ESSER_MAX_REDUCTION_DB,
+    );
+
+    if let Some(multiband) = &mut params.multiband {
+        clamp_multiband(&mut c, multiband, limits);
+    }
+
+    let comp = &mut params.compression;
+    clamp(
+        &mut c,
+        "compression.threshold_db",
+        &mut comp.threshold_db,
+        limits.compression_min_threshold_db,
+        0.0,
+    );
+    clamp(
+        &mut c,
+        "compression.ratio",
+        &mut comp.ratio,
+        1.0,
+        limits.compression_max_ratio,
+    );
+    clamp(
+        &mut c,
+        "compression.attack_ms",
+        &mut comp.attack_ms,
+        limits.compression_min_attack_ms,
+        limits.compression_max_attack_ms,
+    );
+    clamp(
+        &mut c,
+        "compression.release_ms",
+        &mut comp.release_ms,
+        limits.compression_min_release_ms,
+        limits.compression_max_release_ms,
+    );
+    clamp(
+        &mut c,
+        "compression.knee_db",
+        &mut comp.knee_db,
+        0.0,
+        limits.compression_max_knee_db,
+    );
+    clamp(
+        &mut c,
+        "compression.makeup_gain_db",
+        &mut comp.makeup_gain_db,
+        0.0,
+        limits.compression_max_makeup_db,
+    );
+
+    let lim = &mut params.limiter;
+    clamp(
+        &mut c,
+        "limiter.ceiling_db",
+        &mut lim.ceiling_db,
+        limits.limiter_min_ceiling_db,
+        limits.limiter_max_ceiling_db,
+    );
+    clamp(
+        &mut c,
+        "limiter.release_ms",
+        &mut lim.release_ms,
+        limits.limiter_min_release_ms,
+        limits.limiter_max_release_ms,
+    );
+    clamp(
+        &mut c,
+        "limiter.lookahead_ms",
+        &mut lim.lookahead_ms,
+        LIMITER_MIN_LOOKAHEAD_MS,
+        LIMITER_MAX_LOOKAHEAD_MS,
+    );
+    let oversampling = lim.oversampling.clamp(1, LIMITER_MAX_OVERSAMPLING);
+    if oversampling != lim.oversampling {
+        c.push(ParamCorrection::new(
+            "limiter.oversampling",
+            format!("{} clamped to {oversampling}", lim.oversampling),
+        ));
+        lim.oversampling = oversampling;
+    }
+
+    clamp(
+        &mut c,
+        "stereo.width",
+        &mut params.stereo.width,
+        0.0,
+        limits.max_stereo_width,
+    );
+    clamp(
+        &mut c,
+        "stereo.balance",
+        &mut params.stereo.balance,
+        -1.0,
+        1.0,
+    );
+
+    clamp(
+        &mut c,
+        "target_lufs",
+        &mut params.target_lufs,
+        limits.min_target_lufs,
+        limits.max_target_lufs,
+    );
+
+    c
+}
+
+/// Clamp per-stem adjustments: gains to [`MIN_STEM_GAIN_DB`]..[`MAX_STEM_GAIN_DB`]
+/// and EQ bands to `limits`. Fields are reported as e.g. `stems.vocals.gain_db`.
+pub fn clamp_stem_adjustments(
+    adjustments: &mut BTreeMap<String, StemAdjustment>,
+    limits: &ParamLimits,
+) -> Vec<ParamCorrection> {
+    let mut c = Vec::new();
+    for (stem, adjustment) in adjustments.iter_mut() {
+        clamp(
+            &mut c,
+            &format!("stems.{stem}.gain_db"),
+            &mut adjustment.gain_db,
+            MIN_STEM_GAIN_DB,
+            MAX_STEM_GAIN_DB,
+        );
+        clamp_eq(
+            &mut c,
+            &format!("stems.{stem}.eq"),
+            &mut adjustment.eq,
+            limits,
+        );
+    }
+    c
+}
+
+/// Keep the crossovers ascending and in the EQ's frequency range, match the
+/// bands to them and clamp each band like the main compressor.
+fn clamp_multiband(
+    c: &mut Vec<ParamCorrection>,
+    multiband: &mut MultibandParams,
+    limits: &ParamLimits,
+) {
+    if multiband.bands.len() > MULTIBAND_MAX_BANDS {
+        c.push(ParamCorrection::new(
+            "multiband.bands",
+            format!(
+                "{} bands exceed the limit of {MULTIBAND_MAX_BANDS}; extra bands dropped",
+                multiband.bands.len()
+            ),
+        ));
+        multiband.bands.truncate(MULTIBAND_MAX_BANDS);
+    }
+    let crossovers = multiband.bands.len().saturating_sub(1);
+    if multiband.crossovers.len() > crossovers {
+        c.push(ParamCorrection::new(
+            "multiband.crossovers",
+            format!(
+                "{} crossovers for {} bands; extra crossovers dropped",
+                multiband.crossovers.len(),
+                multiband.bands.len()
+            ),
+        ));
+        multiband.crossovers.truncate(crossovers);
+    } else if multiband.crossovers.len() < crossovers {
+        c.push(ParamCorrection::new(
+            "multiband.bands",
+            format!(
+                "{} bands for {} crossovers; extra bands dropped",
+                multiband.bands.len(),
+                multiband.crossovers.len()
+            ),
+        ));
+        multiband.bands.truncate(multiband.crossovers.len() + 1);
+    }
+
+    for (i, frequency) in multiband.crossovers.iter_mut().enumerate() {
+        clamp(
+            c,
+            &format!("multiband.crossovers[{i}]"),
+            frequency,
+            limits.eq_min_frequency_hz,
+            limits.eq_max_frequency_hz,
+        );
+    }
+    if multiband.crossovers.windows(2).any(|w| w[0] > w[1]) {
+        c.push(ParamCorrection::new(
+            "multiband.crossovers",
+            "crossovers out of order; sorted",
+        ));
+        multiband.crossovers.sort_by(f64::total_cmp);
+    }
+
+    for (i, band) in multiband.bands.iter_mut().enumerate() {
+        let field = format!("multiband.bands[{i}]");
+        clamp(
+            c,
+            &format!("{field}.threshold_db"),
+            &mut band.threshold_db,
+            limits.compression_min_threshold_db,
+            0.0,
+        );
+        clamp(
+            c,
+            &format!("{field}.ratio"),
+            &mut band.ratio,
+            1.0,
+            limits.compression_max_ratio,
+        );
+        clamp(
+            c,
+            &format!("{field}.attack_ms"),
+            &mut band.attack_ms,
+            limits.compression_min_attack_ms,
+            limits.compression_max_attack_ms,
+        );
+        clamp(
+            c,
+            &format!("{field}.release_ms"),
+            &mut band.release_ms,
+            limits.compression_min_release_ms,
+            limits.compression_max_release_ms,
+        );
+        clamp(
+            c,
+            &format!("{field}.makeup_gain_db"),
+            &mut band.makeup_gain_db,
+            0.0,
+            limits.compression_max_makeup_db,
+        );
+    }
+}
+
+fn clamp_eq(c: &mut Vec<ParamCorrection>, field: &str, eq: &mut Vec<EqBand>, limits: &ParamLimits) {
+    if eq.len() > limits.max_eq_bands {
+        c.push(ParamCorrection::new(
+            field,
+            format!(
+                "{} bands exceed the limit of {}; extra bands dropped",
+                eq.len(),
+                limits.max_eq_bands
+            ),
+        ));
+        eq.truncate(limits.max_eq_bands);
+    }
+    for (i, band) in eq.iter_mut().enumerate() {
+        let gain = limits.eq_max_gain_db;
+        clamp(
+            c,
+            &format!("{field}[{i}].frequency"),
+            &mut band.frequency,
+            limits.eq_min_frequency_hz,
+            limits.eq_max_frequency_hz,
+        );
+        clamp(
+            c,
+            &format!("{field}[{i}].gain_db"),
+            &mut band.gain_db,
+            -gain,
+            gain,
+        );
+        clamp(
+            c,
+            &format!("{field}[{i}].q"),
+            &mut band.q,
+            limits.eq_min_q,
+            limits.eq_max_q,
+        );
+    }
+}
+
+/// Lower the limiter ceiling to `ceiling_db`, if given.
+pub fn cap_ceiling(params: &mut MasteringParams, ceiling_db: Option<f64>) -> Vec<ParamCorrection> {
+    let mut c = Vec::new();
+    if let Some(ceiling) = ceiling_db {
+        let lim = &mut params.limiter;
+        clamp(
+            &mut c,
+            "limiter.ceiling_db",
+            &mut lim.ceiling_db,
+            f64::MIN,
+            ceiling,
+        );
+    }
+    c
+}
+
+fn clamp(corrections: &mut Vec<ParamCorrection>, field: &str, value: &mut f64, min: f64, max: f64) {
+    let clamped = if value.is_nan() {
+        min
+    } else {
+        value.clamp(min, max)
+    };
+    if clamped != *value {
+        corrections.push(ParamCorrection::new(
+            field,
+            format!("{value} clamped to {clamped}"),
+        ));
+        *value = clamped;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params_json(eq: serde_json::Value, ratio: f64) -> serde_json::Value {
+        serde_json::json!({
+            "eq": eq,
+            "compression": {"threshold_db": -20.0, "ratio": ratio, "attack_ms": 5.0, "release_ms": 100.0, "knee_db": 2.0, "makeup_gain_db": 0.0},
+            "limiter": {"enabled": true, "ceiling_db": -1.0, "release_ms": 100.0},
+            "stereo": {"width": 1.0, "balance": 0.0},
+            "target_lufs": -14.0
+        })
+    }
+
+    #[test]
+    fn test_sane_params_pass_unchanged() {
+        let eq = serde_json::json!([{"frequency": 100.0, "gain_db": 1.5, "q": 0.7, "band_type": "low_shelf"}]);
+        let (params, corrections) =
+            params_from_json(params_json(eq, 2.0), &ParamLimits::default()).unwrap();
+        assert!(corrections.is_empty(), "{corrections:?}");
+        assert_eq!(params.eq[0].gain_db, 1.5);
+    }
+
+    #[test]
+    fn test_absurd_values_are_clamped() {
+        let eq = serde_json::json!([{"frequency": 8000.0, "gain_db": 30.0, "q": 0.7, "band_type": "high_shelf"}]);
+        let limits = ParamLimits::default();
+        let (params, corrections) = params_from_json(params_json(eq, 100.0), &limits).unwrap();
+        assert_eq!(params.eq[0].gain_db, limits.eq_max_gain_db);
+        assert_eq!(params.compression.ratio, limits.compression_max_ratio);
+        let fields: Vec<_> = corrections.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["eq[0].gain_db", "compression.ratio"]);
+    }
+
+    #[test]
+    fn test_malformed_bands_are_rejected() {
+        let eq = serde_json::json!([
+            {"frequency": 300.0, "gain_db": -2.0, "q": 1.0, "band_type": "notch_of_doom"},
+            {"frequency": 30.0, "gain_db": 0.0, "q": 0.7, "band_type": "high_pass"},
+            "not a band"
+        ]);
+        let (params, corrections) =
+            params_from_json(params_json(eq, 2.0), &ParamLimits::default()).unwrap();
+        assert_eq!(params.eq.len(), 1);
+        assert_eq!(corrections.len(), 2);
+        assert_eq!(corrections[0].field, "eq[0]");
+        assert_eq!(corrections[1].field, "eq[2]");
+    }
+
+    #[test]
+    fn test_multiband_is_matched_and_sorted() {
+        let band = serde_json::json!({"threshold_db": -20.0, "ratio": 2.0, "attack_ms": 10.0, "release_ms": 100.0});
+        let mut value = params_json(serde_json::json!([]), 2.0);
+        value["multiband"] =
+            serde_json::json!({"crossovers": [2000.0, 200.0, 8000.0], "bands": [band, band, band]});
+        let (params, corrections) = params_from_json(value, &ParamLimits::default()).unwrap();
+        let multiband = params.multiband.unwrap();
+        assert_eq!(multiband.crossovers, [200.0, 2000.0]);
+        assert_eq!(multiband.bands.len(), 3);
+        let fields: Vec<_> = corrections.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["multiband.crossovers", "multiband.crossovers"]);
+
+        let mut value = params_json(serde_json::json!([]), 2.0);
+        value["multiband"] = serde_json::json!({"crossovers": "low"});
+        let (params, corrections) = params_from_json(value, &ParamLimits::default()).unwrap();
+        assert!(params.multiband.is_none());
+        assert_eq!(corrections[0].field, "multiband");
+    }
+
+    #[test]
+    fn test_stem_adjustments_are_clamped() {
+        let mut adjustments: BTreeMap<String, StemAdjustment> = serde_json::from_value(serde_json::json!({
+            "vocals": {"gain_db": 2.0, "eq": [{"frequency": 3000.0, "gain_db": 40.0, "q": 1.0, "band_type": "peak"}]},
+            "drums": {"gain_db": 30.0}
+        }))
+        .unwrap();
+        let limits = ParamLimits::default();
+        let corrections = clamp_stem_adjustments(&mut adjustments, &limits);
+        assert_eq!(adjustments["drums"].gain_db, MAX_STEM_GAIN_DB);
+        assert_eq!(adjustments["vocals"].gain_db, 2.0);
+        assert_eq!(adjustments["vocals"].eq[0].gain_db, limits.eq_max_gain_db);
+        let fields: Vec<_> = corrections.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["stems.drums.gain_db", "stems.vocals.eq[0].gain_db"]
+        );
+    }
+
+    #[test]
+    fn test_ceiling_caps_limiter() {
+        let (mut params, _) = params_from_json(
+            params_json(serde_json::json!([]), 2.0),
+            &ParamLimits::default(),
+        )
+        .unwrap();
+        assert!(cap_ceiling(&mut params, None).is_empty());
+        assert!(cap_ceiling(&mut params, Some(-0.5)).is_empty());
+        assert_eq!(params.limiter.ceiling_db, -1.0);
+
+        let corrections = cap_ceiling(&mut params, Some(-2.0));
+        assert_eq!(params.limiter.ceiling_db, -2.0);
+        assert_eq!(corrections[0].field, "limiter.ceiling_db");
+    }
+}
@@ -65,6 +65,49 @@ fn test_config_roundtrip() {
     assert_eq!(parsed.ai.ollama.model, "llama3");
 }
 
+#[test]
+fn test_config_rejects_inverted_limits() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(
+        &path,
+        "[ai.limits]\neq_min_frequency_hz = 20000.0\neq_max_frequency_hz = 20.0\n",
+    )
+    .unwrap();
+    let err = Config::load_from(&path).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("eq_min_frequency_hz"),
+        "{err:#}"
+    );
+
+    std::fs::write(&path, "[ai.limits]\neq_max_gain_db = -3.0\n").unwrap();
+    assert!(Config::load_from(&path).is_err());
+
+    std::fs::write(&path, "[ai.limits]\neq_max_gain_db = 6.0\n").unwrap();
+    assert!(Config::load_from(&path).is_ok());
+}
+
+#[test]
+fn test_config_request_limits() {
+    let parsed: Config = toml::from_str(
+        "[ai.ollama]\nmodel = \"qwen2.5\"\n\n[ai.anthropic]\ntimeout_secs = 30\nmax_tokens = 8192\n",
+    )
+    .unwrap();
+    assert_eq!(
+        parsed.ai.request_limits(AiProvider::Ollama),
+        Some((300, 4096))
+    );
+    assert_eq!(
+        parsed.ai.request_limits(AiProvider::OpenAi),
+        Some((120, 4096))
+    );
+    assert_eq!(
+        parsed.ai.request_limits(AiProvider::Anthropic),
+        Some((30, 8192))
+    );
+    assert_eq!(parsed.ai.request_limits(AiProvider::Rules), None);
+}
+
 #[test]
 fn test_backend_parsing() {
     assert_eq!("auto".parse::<Backend>().unwrap(), Backend::Auto);
@@ -98,6 +141,19 @@ fn test_ai_provider_parsing() {
         "lm_studio".parse::<AiProvider>().unwrap(),
         AiProvider::LmStudio
     );
+    assert_eq!(
+        "heuristics".parse::<AiProvider>().unwrap(),
+        AiProvider::Rules
+    );
+    assert_eq!(
+        "azure".parse::<AiProvider>().unwrap(),
+        AiProvider::AzureOpenAi
+    );
+    assert_eq!(
+        "openai-compatible".parse::<AiProvider>().unwrap(),
+        AiProvider::OpenAiCompatible
+    );
+    assert_eq!("gguf".parse::<AiProvider>().unwrap(), AiProvider::LocalGguf);
 }
 
 #[test]
@@ -106,6 +162,18 @@ fn test_preset_values() {
     assert_eq!(Preset::Cd.target_lufs(), -9.0);
     assert_eq!(Preset::Vinyl.target_lufs(), -12.0);
     assert_eq!(Preset::Loud.target_lufs(), -6.0);
+    assert_eq!(Preset::Podcast.target_lufs(), -16.0);
+    assert_eq!(Preset::Podcast.target_lufs_for(1), -19.0);
+    assert_eq!(Preset::Podcast.target_lufs_for(2), -16.0);
+    assert_eq!(Preset::Streaming.target_lufs_for(1), -14.0);
+    assert_eq!("podcast".parse::<Preset>().unwrap(), Preset::Podcast);
+    assert_eq!(Preset::Game.target_lufs(), -18.0);
+    assert_eq!(Preset::GameConsole.target_lufs(), -20.0);
+    assert_eq!(Preset::Game.true_peak_ceiling_db(), Some(-2.0));
+    assert_eq!(Preset::Streaming.true_peak_ceiling_db(), None);
+    for preset in Preset::ALL {
+        assert_eq!(preset.to_string().parse::<Preset>().unwrap(), preset);
+    }
 }
 
 #[tokio::test]
@@ -150,6 +218,7 @@ fn test_mastering_job_output_path() {
         no_limiter: false,
         preset: None,
         dry_run: false,
+        ..Default::default()
     };
 
     let output = job.resolved_output_path(&config);
@@ -174,6 +243,7 @@ fn test_mastering_job_auto_backend() {
         no_limiter: false,
         preset: None,
         dry_run: false,
+        ..Default::default()
     };
     assert_eq!(job_no_ref.resolved_backend(), Backend::Ai);
 
@@ -190,6 +260,7 @@ fn test_mastering_job_auto_backend() {
         no_limiter: false,
         preset: None,
         dry_run: false,
+        ..Default::default()
     };
     assert_eq!(job_with_ref.resolved_backend(), Backend::Matchering);
 }
@@ -202,6 +273,11 @@ fn test_audio_format_parsing() {
     assert_eq!(AudioFormat::from_str("WAV").unwrap(), AudioFormat::Wav);
     assert_eq!(AudioFormat::from_str("flac").unwrap(), AudioFormat::Flac);
     assert_eq!(AudioFormat::from_str("mp3").unwrap(), AudioFormat::Mp3);
+    assert_eq!(AudioFormat::from_str("m4a").unwrap(), AudioFormat::Aac);
+    assert_eq!(AudioFormat::from_str("ogg").unwrap(), AudioFormat::Opus);
+    assert_eq!(AudioFormat::Opus.extension(), "opus");
+    assert_eq!(AudioFormat::from_str("aif").unwrap(), AudioFormat::Aiff);
+    assert_eq!(AudioFormat::from_str("alac").unwrap(), AudioFormat::Alac);
     assert!(AudioFormat::from_str("invalid").is_err());
 }
 
@@ -241,6 +317,7 @@ fn test_mastering_job_with_preset() {
         no_limiter: false,
         preset: Some(Preset::Vinyl),
         dry_run: false,
+        ..Default::default()
     };
 
     // Preset should be Vinyl
@@ -253,6 +330,7 @@ fn test_backend_display() {
     assert_eq!(Backend::Matchering.to_string(), "matchering");
     assert_eq!(Backend::Ai.to_string(), "ai");
     assert_eq!(Backend::LocalMl.to_string(), "local-ml");
+    assert_eq!(Backend::Basic.to_string(), "basic");
 }
 
 #[test]
@@ -261,6 +339,7 @@ fn test_ai_provider_display() {
     assert_eq!(AiProvider::OpenAi.to_string(), "openai");
     assert_eq!(AiProvider::Anthropic.to_string(), "anthropic");
     assert_eq!(AiProvider::KeyhanStudio.to_string(), "keyhanstudio");
+    assert_eq!(AiProvider::Rules.to_string(), "rules");
 }
 
 #[test]
@@ -323,3 +402,1427 @@ async fn test_analysis_with_short_audio() {
     assert!(analysis.metadata.duration_secs < 1.0);
 }
 
+#[tokio::test]
+async fn test_cancelled_job_stops_before_processing() {
+    use mastering_core::error::MasteringError;
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let wav_file = create_test_wav();
+    let output = tempfile::tempdir().unwrap().path().join("out.wav");
+    let job = MasteringJob {
+        input_path: wav_file.path().to_path_buf(),
+        output_path: Some(output.clone()),
+        ..Default::default()
+    };
+    job.cancel_token.cancel();
+
+    let err = pipeline::run(&job, &Config::default()).await.unwrap_err();
+    assert!(matches!(
+        MasteringError::from(err),
+        MasteringError::Cancelled
+    ));
+    assert!(!output.exists());
+}
+
+#[tokio::test]
+async fn test_run_batch_preserves_order() {
+    use mastering_core::pipeline::{self, BatchJobStatus, MasteringJob, ProgressReporter};
+
+    let files: Vec<_> = (0..3).map(|_| create_test_wav()).collect();
+    let mut jobs: Vec<_> = files
+        .iter()
+        .map(|f| {
+            let job = MasteringJob {
+                input_path: f.path().to_path_buf(),
+                dry_run: true,
+                ..Default::default()
+            };
+            (job, Config::default(), ProgressReporter::disabled())
+        })
+        .collect();
+    jobs.push((
+        MasteringJob {
+            input_path: std::path::PathBuf::from("/nonexistent/missing.wav"),
+            dry_run: true,
+            ..Default::default()
+        },
+        Config::default(),
+        ProgressReporter::disabled(),
+    ));
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let results = pipeline::run_batch(jobs, 2, false, Some(tx)).await;
+
+    assert_eq!(results.len(), 4);
+    for (file, result) in files.iter().zip(&results) {
+        let analysis = result.as_ref().unwrap().pre_analysis.as_ref().unwrap();
+        assert_eq!(analysis.metadata.path, file.path());
+    }
+    assert!(results[3].is_err());
+
+    let mut finished = 0;
+    while let Ok(update) = rx.try_recv() {
+        if matches!(
+            update.status,
+            BatchJobStatus::Succeeded | BatchJobStatus::Failed
+        ) {
+            finished += 1;
+        }
+    }
+    assert_eq!(finished, 4);
+}
+
+#[tokio::test]
+async fn test_run_batch_skips_finished_outputs() {
+    use mastering_core::pipeline::{self, BatchJobStatus, MasteringJob, ProgressReporter};
+
+    let dir = tempfile::tempdir().unwrap();
+    let wav = create_test_wav();
+    let batch = || {
+        vec![(
+            MasteringJob {
+                input_path: wav.path().to_path_buf(),
+                output_path: Some(dir.path().join("master.wav")),
+                backend: Backend::Basic,
+                ..Default::default()
+            },
+            Config::default(),
+            ProgressReporter::disabled(),
+        )]
+    };
+    let statuses = |force| async move {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let results = pipeline::run_batch(batch(), 1, force, Some(tx)).await;
+        assert!(results[0].is_ok());
+        let mut statuses = Vec::new();
+        while let Ok(update) = rx.try_recv() {
+            statuses.push(update.status);
+        }
+        statuses
+    };
+
+    assert!(statuses(false).await.contains(&BatchJobStatus::Succeeded));
+    assert!(statuses(false).await.contains(&BatchJobStatus::Skipped));
+    assert!(statuses(true).await.contains(&BatchJobStatus::Succeeded));
+}
+
+#[tokio::test]
+async fn test_overwrite_policies() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let dir = tempfile::tempdir().unwrap();
+    let wav = create_test_wav();
+    let output = dir.path().join("master.wav");
+    std::fs::write(&output, b"earlier master").unwrap();
+    let job = |overwrite| MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(output.clone()),
+        overwrite: Some(overwrite),
+        backend: Backend::Basic,
+        ..Default::default()
+    };
+
+    let err = pipeline::run(&job(OverwritePolicy::Error), &Config::default())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("already exists"), "{err}");
+    assert_eq!(std::fs::read(&output).unwrap(), b"earlier master");
+
+    let result = pipeline::run(&job(OverwritePolicy::Version), &Config::default())
+        .await
+        .unwrap();
+    assert_eq!(result.output_path, dir.path().join("master_v2.wav"));
+    assert_eq!(std::fs::read(&output).unwrap(), b"earlier master");
+    assert_eq!(
+        pipeline::versioned_output_path(&output),
+        dir.path().join("master_v3.wav")
+    );
+
+    let result = pipeline::run(&job(OverwritePolicy::Overwrite), &Config::default())
+        .await
+        .unwrap();
+    assert_eq!(result.output_path, output);
+    assert_ne!(std::fs::read(&output).unwrap(), b"earlier master");
+
+    // Delivery targets are held to the same policy
+    let fresh = dir.path().join("fresh.wav");
+    let target = dir.path().join("fresh_16lufs.wav");
+    std::fs::write(&target, b"earlier target").unwrap();
+    let with_target = |overwrite| MasteringJob {
+        output_path: Some(fresh.clone()),
+        targets: vec![DeliveryTarget {
+            target_lufs: -16.0,
+            format: None,
+            bit_depth: None,
+            bitrate_kbps: None,
+        }],
+        ..job(overwrite)
+    };
+
+    let err = pipeline::run(&with_target(OverwritePolicy::Error), &Config::default())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("fresh_16lufs.wav"), "{err}");
+    assert!(!fresh.exists());
+
+    let result = pipeline::run(&with_target(OverwritePolicy::Version), &Config::default())
+        .await
+        .unwrap();
+    assert_eq!(result.output_path, dir.path().join("fresh_v2.wav"));
+    assert_eq!(std::fs::read(&target).unwrap(), b"earlier target");
+    assert!(dir.path().join("fresh_v2_16lufs.wav").exists());
+}
+
+#[tokio::test]
+async fn test_failed_job_removes_intermediates() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let dir = tempfile::tempdir().unwrap();
+    let wav = create_test_wav();
+    // A FLAC master goes through an intermediate WAV; the rate fails resampling
+    let job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("master.flac")),
+        format: Some(AudioFormat::Flac),
+        sample_rate: Some(1_000),
+        backend: Backend::Basic,
+        ..Default::default()
+    };
+
+    let err = pipeline::run(&job, &Config::default()).await.unwrap_err();
+    assert!(format!("{err:#}").contains("Sample-rate"), "{err:#}");
+    let left: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert!(left.is_empty(), "{left:?}");
+}
+
+#[test]
+fn test_estimate_output_size() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let metadata = AudioMetadata {
+        path: "song.wav".into(),
+        sample_rate: 48000,
+        channels: 2,
+        channel_layout: "stereo".into(),
+        duration_secs: 60.0,
+        bit_depth: Some(24),
+        format: "WAV".into(),
+    };
+    let config = Config::default();
+    let scratch = 60 * 48000 * 2 * 4;
+
+    let wav = MasteringJob {
+        format: Some(AudioFormat::Wav),
+        bit_depth: Some(16),
+        ..Default::default()
+    };
+    assert_eq!(
+        pipeline::estimate_output_size(&wav, &config, &metadata),
+        scratch + 60 * 48000 * 2 * 2
+    );
+
+    let mp3 = MasteringJob {
+        format: Some(AudioFormat::Mp3),
+        bitrate_kbps: Some(128),
+        ..Default::default()
+    };
+    assert_eq!(
+        pipeline::estimate_output_size(&mp3, &config, &metadata),
+        scratch + 60 * 16000
+    );
+}
+
+#[test]
+fn test_check_disk_space() {
+    use mastering_core::pipeline;
+
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("new").join("master.wav");
+    pipeline::check_disk_space(&output, 1024).unwrap();
+    assert!(output.parent().unwrap().is_dir());
+    assert_eq!(
+        std::fs::read_dir(output.parent().unwrap()).unwrap().count(),
+        0
+    );
+
+    let err = pipeline::check_disk_space(&output, u64::MAX).unwrap_err();
+    assert!(err.to_string().contains("Not enough disk space"), "{err}");
+}
+
+#[tokio::test]
+async fn test_result_json_carries_timings() {
+    use mastering_core::pipeline::{self, MasteringJob};
+    use mastering_core::types::MasteringResult;
+
+    let wav_file = create_test_wav();
+    let job = MasteringJob {
+        input_path: wav_file.path().to_path_buf(),
+        dry_run: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+    assert!(result.timings.total_secs >= result.timings.analysis_secs);
+    assert_eq!(result.timings.processing_secs, 0.0);
+
+    let json = serde_json::to_string(&result).unwrap();
+    let parsed: MasteringResult = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.timings, result.timings);
+    assert_eq!(parsed.output_path, result.output_path);
+}
+
+#[tokio::test]
+async fn test_compare_identical_files() {
+    let wav_file = create_test_wav();
+    let comparison =
+        mastering_core::analysis::compare_files(wav_file.path(), wav_file.path(), None)
+            .await
+            .unwrap();
+
+    assert_eq!(comparison.delta.lufs_integrated, 0.0);
+    assert_eq!(comparison.delta.peak_db, 0.0);
+    assert_eq!(comparison.delta.frequency_bands.mid, 0.0);
+}
+
+#[tokio::test]
+async fn test_analysis_cache_hit() {
+    use mastering_core::cache::AnalysisCache;
+
+    let wav_file = create_test_wav();
+    let cache_dir = tempfile::tempdir().unwrap();
+    let cache = AnalysisCache::new(cache_dir.path());
+
+    let first = mastering_core::analysis::analyze_file_cached(wav_file.path(), Some(&cache))
+        .await
+        .unwrap();
+    assert_eq!(cache.len().await, 1);
+
+    let cached = cache
+        .get(wav_file.path())
+        .await
+        .expect("entry should be cached");
+    assert_eq!(cached.lufs_integrated, first.lufs_integrated);
+
+    let second = mastering_core::analysis::analyze_file_cached(wav_file.path(), Some(&cache))
+        .await
+        .unwrap();
+    assert_eq!(second.peak_db, first.peak_db);
+}
+
+#[tokio::test]
+async fn test_surround_pass_through_keeps_channels() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("surround.wav");
+    let output = dir.path().join("surround_mastered.wav");
+    let spec = hound::WavSpec {
+        channels: 6,
+        sample_rate: 48000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+    for i in 0..48000 * 3 {
+        let t = i as f64 / 48000.0;
+        let s = (1000.0 * (2.0 * std::f64::consts::PI * 1000.0 * t).sin()) as i16;
+        for ch in 0..6 {
+            writer.write_sample(if ch == 3 { 0 } else { s }).unwrap();
+        }
+    }
+    writer.finalize().unwrap();
+
+    let job = MasteringJob {
+        input_path: input,
+        output_path: Some(output.clone()),
+        format: Some(AudioFormat::Wav),
+        surround_mode: Some(SurroundMode::PassThrough),
+        target_lufs: Some(-20.0),
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    assert_eq!(result.backend_used, "pass-through");
+    let post = result.post_analysis.unwrap();
+    assert_eq!(post.metadata.channels, 6);
+    assert_eq!(post.metadata.channel_layout, "5.1");
+    assert!(
+        (post.lufs_integrated + 20.0).abs() < 0.5,
+        "{}",
+        post.lufs_integrated
+    );
+}
+
+#[test]
+fn test_config_needs_network() {
+    let mut config = Config::default();
+    assert!(!config.ai.needs_network(AiProvider::Ollama));
+    assert!(!config.ai.needs_network(AiProvider::LmStudio));
+    assert!(!config.ai.needs_network(AiProvider::Rules));
+    assert!(config.ai.needs_network(AiProvider::Anthropic));
+
+    config.ai.ollama.endpoint = "http://[::1]:11434".into();
+    assert!(!config.ai.needs_network(AiProvider::Ollama));
+    config.ai.ollama.endpoint = "http://gpu-box.lan:11434".into();
+    assert!(config.ai.needs_network(AiProvider::Ollama));
+}
+
+#[tokio::test]
+async fn test_offline_refuses_cloud_provider() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let input = create_test_wav();
+    let job = MasteringJob {
+        input_path: input.path().to_path_buf(),
+        backend: Backend::Ai,
+        ai_provider: Some(AiProvider::OpenAi),
+        offline: true,
+        no_cache: true,
+        ..Default::default()
+    };
+    let err = pipeline::run(&job, &Config::default()).await.unwrap_err();
+    assert!(err.to_string().contains("Offline mode"), "{err}");
+}
+
+#[tokio::test]
+async fn test_refinement_requires_ai_backend() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let input = create_test_wav();
+    let previous = create_test_wav();
+    let previous_params: MasteringParams = serde_json::from_str(
+        r#"{
+  "eq": [],
+  "compression": {"threshold_db": -20.0, "ratio": 2.0, "attack_ms": 10.0, "release_ms": 100.0, "knee_db": 6.0, "makeup_gain_db": 0.0},
+  "limiter": {"enabled": true, "ceiling_db": -1.0, "release_ms": 50.0},
+  "stereo": {"width": 1.0, "balance": 0.0},
+  "target_lufs": -14.0
+}"#,
+    )
+    .unwrap();
+
+    let job = MasteringJob {
+        input_path: input.path().to_path_buf(),
+        backend: Backend::Basic,
+        refinement: Some(Refinement {
+            feedback: "make it brighter".into(),
+            previous_params,
+            previous_output: previous.path().to_path_buf(),
+        }),
+        no_cache: true,
+        ..Default::default()
+    };
+    let err = pipeline::run(&job, &Config::default()).await.unwrap_err();
+    assert!(err.to_string().contains("requires the AI backend"), "{err}");
+}
+
+#[tokio::test]
+async fn test_reference_matching_needs_no_python() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let input = create_test_wav();
+    let reference = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("matched.wav");
+
+    let mut config = Config::default();
+    config.backends.matchering.python_path = "/nonexistent/python3".into();
+    let job = MasteringJob {
+        input_path: input.path().to_path_buf(),
+        output_path: Some(output.clone()),
+        reference_path: Some(reference.path().to_path_buf()),
+        format: Some(AudioFormat::Wav),
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &config).await.unwrap();
+
+    assert_eq!(result.backend_used, "matchering");
+    assert!(output.exists());
+}
+
+#[tokio::test]
+async fn test_stem_inputs_are_summed_and_mastered() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let drums = create_test_wav();
+    let bass = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("mix.wav");
+
+    let job = MasteringJob {
+        stem_inputs: vec![
+            StemInput {
+                path: drums.path().to_path_buf(),
+                gain_db: -3.0,
+            },
+            format!("{}:-6", bass.path().display()).parse().unwrap(),
+        ],
+        output_path: Some(output.clone()),
+        backend: Backend::Basic,
+        format: Some(AudioFormat::Wav),
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    assert_eq!(result.backend_used, "basic");
+    assert!(output.exists());
+    assert!(!dir.path().join(".mix.mix.wav").exists());
+}
+
+#[tokio::test]
+async fn test_restoration_runs_before_mastering() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let input = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("restored.wav");
+
+    let job = MasteringJob {
+        input_path: input.path().to_path_buf(),
+        output_path: Some(output.clone()),
+        backend: Backend::Basic,
+        restoration: RestorationStages {
+            denoise: true,
+            dehum: true,
+            declick: true,
+            decrackle: true,
+        },
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    let report = result.restoration.unwrap();
+    assert_eq!(report.hum_hz, None);
+    assert_eq!(report.clicks_repaired, 0);
+    assert!(output.exists());
+    assert!(!dir.path().join(".restored.restored.wav").exists());
+}
+
+#[tokio::test]
+async fn test_fix_balance_evens_out_channels() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    // The test file's left channel is about 1.2 dB louder than its right
+    let input = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("balanced.wav");
+
+    let job = MasteringJob {
+        input_path: input.path().to_path_buf(),
+        output_path: Some(output.clone()),
+        backend: Backend::Basic,
+        fix_balance: true,
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+    let corrected = result.balance_correction_db.unwrap();
+    assert!((corrected - 1.16).abs() < 0.05, "{corrected}");
+    assert!(!dir.path().join(".balanced.balanced.wav").exists());
+
+    // The chain treats both channels alike, so the output shifts by the correction
+    let job = MasteringJob {
+        fix_balance: false,
+        ..job
+    };
+    let unbalanced = pipeline::run(&job, &Config::default()).await.unwrap();
+    assert_eq!(unbalanced.balance_correction_db, None);
+    let shift = unbalanced.post_analysis.unwrap().stereo_balance_db
+        - result.post_analysis.unwrap().stereo_balance_db;
+    assert!((shift - corrected).abs() < 0.2, "{shift}");
+}
+
+#[tokio::test]
+async fn test_fix_polarity_flips_inverted_channel() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("inverted.wav");
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+    for i in 0..44100 * 3 {
+        let t = i as f64 / 44100.0;
+        let s = (12000.0 * (2.0 * std::f64::consts::PI * 440.0 * t).sin()) as i16;
+        writer.write_sample(s).unwrap();
+        writer.write_sample(-s).unwrap();
+    }
+    writer.finalize().unwrap();
+    let output = dir.path().join("fixed.wav");
+
+    let job = MasteringJob {
+        input_path: input,
+        output_path: Some(output.clone()),
+        backend: Backend::Basic,
+        fix_polarity: true,
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    assert!(result.polarity_flipped);
+    assert!(result.pre_analysis.unwrap().phase_correlation < -0.99);
+    assert!(result.post_analysis.unwrap().phase_correlation > 0.99);
+    assert!(!dir.path().join(".fixed.polarity.wav").exists());
+}
+
+#[tokio::test]
+async fn test_trim_silence_shortens_output() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("padded.wav");
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    // 2 s of silence, 2 s of tone, 3 s of silence
+    let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+    for i in 0..44100 * 7 {
+        let t = i as f64 / 44100.0;
+        let s = if (2.0..4.0).contains(&t) {
+            (12000.0 * (2.0 * std::f64::consts::PI * 440.0 * t).sin()) as i16
+        } else {
+            0
+        };
+        writer.write_sample(s).unwrap();
+        writer.write_sample(s).unwrap();
+    }
+    writer.finalize().unwrap();
+    let output = dir.path().join("trimmed.wav");
+
+    let job = MasteringJob {
+        input_path: input,
+        output_path: Some(output.clone()),
+        backend: Backend::Basic,
+        trim_silence: Some(SilenceTrim::default()),
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    let trimmed = result.silence_trimmed.unwrap();
+    assert!((trimmed.leading_secs - 1.75).abs() < 0.01, "{trimmed:?}");
+    assert!((trimmed.trailing_secs - 2.75).abs() < 0.01, "{trimmed:?}");
+    assert!(result.pre_analysis.unwrap().silence.leading_secs > 1.99);
+    let duration = result.post_analysis.unwrap().metadata.duration_secs;
+    assert!((duration - 2.5).abs() < 0.02, "{duration}");
+    assert!(!dir.path().join(".trimmed.trimmed.wav").exists());
+}
+
+#[test]
+fn test_mastering_job_fades_default_to_preset() {
+    use mastering_core::pipeline::MasteringJob;
+
+    let job = MasteringJob {
+        preset: Some(Preset::Vinyl),
+        fade_out_secs: Some(2.0),
+        ..Default::default()
+    };
+    let fades = job.resolved_fades();
+    assert_eq!(fades.in_secs, Preset::Vinyl.fades().in_secs);
+    assert_eq!(fades.out_secs, 2.0);
+    assert_eq!(fades.curve, FadeCurve::Cosine);
+
+    assert!(!MasteringJob::default().resolved_fades().any());
+}
+
+#[tokio::test]
+async fn test_range_masters_only_the_excerpt() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let wav = create_test_wav();
+    let range = TimeRange {
+        start_secs: 0.5,
+        end_secs: Some(1.5),
+    };
+    let analysis = mastering_core::analysis::analyze_file_range(wav.path(), &range)
+        .await
+        .unwrap();
+    assert!((analysis.metadata.duration_secs - 1.0).abs() < 0.01);
+
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("excerpt.wav");
+    let job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(output.clone()),
+        backend: Backend::Basic,
+        range: Some(TimeRange {
+            start_secs: 1.0,
+            end_secs: None,
+        }),
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+    let duration = result.post_analysis.unwrap().metadata.duration_secs;
+    assert!((duration - 1.0).abs() < 0.02, "{duration}");
+    assert!(!dir.path().join(".excerpt.excerpt.wav").exists());
+
+    let past_end = MasteringJob {
+        range: Some(TimeRange {
+            start_secs: 5.0,
+            end_secs: None,
+        }),
+        ..job
+    };
+    assert!(pipeline::run(&past_end, &Config::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_preview_masters_loudest_section() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("song.wav");
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    // 10 s quiet, 30 s loud, 10 s quiet
+    let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+    for i in 0..8000 * 50 {
+        let t = i as f64 / 8000.0;
+        let amplitude = if (10.0..40.0).contains(&t) {
+            12000.0
+        } else {
+            300.0
+        };
+        let s = (amplitude * (2.0 * std::f64::consts::PI * 440.0 * t).sin()) as i16;
+        writer.write_sample(s).unwrap();
+        writer.write_sample(s).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let job = MasteringJob {
+        input_path: input,
+        backend: Backend::Basic,
+        preview: true,
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    assert_eq!(result.output_path, dir.path().join("song_preview.wav"));
+    let excerpt = result.excerpt.unwrap();
+    assert!((excerpt.start_secs - 10.0).abs() < 0.2, "{excerpt:?}");
+    let duration = result.post_analysis.unwrap().metadata.duration_secs;
+    assert!((duration - 30.0).abs() < 0.05, "{duration}");
+}
+
+#[tokio::test]
+async fn test_ab_export_matches_loudness() {
+    use mastering_core::ab::{self, MatchTo};
+
+    let dir = tempfile::tempdir().unwrap();
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let write_tone = |name: &str, amplitude: f64| {
+        let path = dir.path().join(name);
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..44100 * 3 {
+            let t = i as f64 / 44100.0;
+            let s = (amplitude * (2.0 * std::f64::consts::PI * 440.0 * t).sin()) as i16;
+            writer.write_sample(s).unwrap();
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    };
+    let original = write_tone("song.wav", 3000.0);
+    let master = write_tone("song_mastered.wav", 20000.0);
+
+    let export = ab::export(&original, &master, dir.path(), MatchTo::Master, None)
+        .await
+        .unwrap();
+
+    assert!(
+        export.original_gain_db > 16.0,
+        "{}",
+        export.original_gain_db
+    );
+    assert_eq!(export.master_gain_db, 0.0);
+    assert!(export.comparison.delta.lufs_integrated.abs() < 0.1);
+    assert!(export.report_path.exists());
+    assert_eq!(
+        export.original_path,
+        dir.path().join("song_ab_original.wav")
+    );
+}
+
+#[test]
+fn test_delivery_target_parsing() {
+    let cd: DeliveryTarget = "-9:wav:16".parse().unwrap();
+    assert_eq!(cd.target_lufs, -9.0);
+    assert_eq!(cd.format, Some(AudioFormat::Wav));
+    assert_eq!(cd.bit_depth, Some(16));
+    assert_eq!(cd.file_suffix(), "9lufs_16bit");
+
+    let mp3: DeliveryTarget = "-14:mp3:320".parse().unwrap();
+    assert_eq!(mp3.bitrate_kbps, Some(320));
+    assert_eq!(mp3.bit_depth, None);
+
+    assert!("-14:wav:20".parse::<DeliveryTarget>().is_err());
+    assert!("loud".parse::<DeliveryTarget>().is_err());
+}
+
+#[tokio::test]
+async fn test_targets_render_extra_deliverables() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let wav = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("song.wav");
+    let job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(output.clone()),
+        backend: Backend::Basic,
+        target_lufs: Some(-14.0),
+        targets: vec!["-10:wav:16".parse().unwrap(), "-18:flac".parse().unwrap()],
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    assert_eq!(result.deliverables.len(), 2);
+    let loud = &result.deliverables[0];
+    assert_eq!(loud.path, dir.path().join("song_10lufs_16bit.wav"));
+    assert!(
+        (loud.lufs_integrated + 10.0).abs() < 1.0,
+        "{}",
+        loud.lufs_integrated
+    );
+    let quiet = &result.deliverables[1];
+    assert_eq!(quiet.path, dir.path().join("song_18lufs.flac"));
+    assert!(
+        (quiet.lufs_integrated + 18.0).abs() < 1.0,
+        "{}",
+        quiet.lufs_integrated
+    );
+    assert!(output.exists());
+    assert!(!dir.path().join(".song_18lufs.target.wav").exists());
+
+    let clash = MasteringJob {
+        targets: vec!["-10".parse().unwrap(), "-10:wav".parse().unwrap()],
+        ..job
+    };
+    assert!(pipeline::run(&clash, &Config::default()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_replaygain_measured_on_output() {
+    use mastering_core::pipeline::{self, MasteringJob};
+    use mastering_core::replaygain;
+
+    let wav = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("song.flac")),
+        format: Some(AudioFormat::Flac),
+        backend: Backend::Basic,
+        target_lufs: Some(-14.0),
+        replaygain: true,
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    let gain = result.replaygain.expect("ReplayGain computed");
+    let post = result.post_analysis.unwrap();
+    assert!(
+        (gain.track_gain_db - (replaygain::REFERENCE_LUFS - post.lufs_integrated)).abs() < 0.5,
+        "gain {} for {} LUFS",
+        gain.track_gain_db,
+        post.lufs_integrated
+    );
+    assert!(gain.track_peak > 0.0 && gain.track_peak <= 1.0);
+    assert!(gain.album_gain_db.is_none());
+
+    let album = replaygain::tag_album(&[result.output_path.clone(), result.output_path]).unwrap();
+    assert!((album[0].album_gain_db.unwrap() - gain.track_gain_db).abs() < 0.1);
+}
+
+#[tokio::test]
+async fn test_master_to_broadcast_spec_validates() {
+    use mastering_core::analysis::compliance::{self, Spec};
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let wav = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let limits = Spec::EbuR128.limits();
+    let job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("broadcast.wav")),
+        backend: Backend::Basic,
+        target_lufs: Some(limits.target_lufs),
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+    let post = result.post_analysis.unwrap();
+
+    let report = compliance::check(Spec::EbuR128, limits, &post);
+    assert!(report.passed, "{:?}", report.checks);
+
+    let report = compliance::check(Spec::Netflix, Spec::Netflix.limits(), &post);
+    assert!(!report.passed);
+    assert!(!report.checks[0].passed);
+}
+
+#[tokio::test]
+async fn test_podcast_preset_masters_mono_speech() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("episode.wav");
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+    for i in 0..44100 * 4 {
+        let t = i as f64 / 44100.0;
+        // A voice-like tone with a slow syllable envelope
+        let envelope = 0.5 + 0.5 * (2.0 * std::f64::consts::PI * 3.0 * t).sin().abs();
+        let s = 4000.0 * envelope * (2.0 * std::f64::consts::PI * 180.0 * t).sin();
+        writer.write_sample(s as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let job = MasteringJob {
+        input_path: input,
+        output_path: Some(dir.path().join("episode_mastered.wav")),
+        backend: Backend::Basic,
+        preset: Some(Preset::Podcast),
+        no_cache: true,
+        ..Default::default()
+    };
+    assert!(job.speech_mode());
+    assert!(job.resolved_restoration().declick);
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    let params = result.params_applied.unwrap();
+    assert_eq!(params.target_lufs, -19.0);
+    assert!(params.de_esser.enabled);
+    let post = result.post_analysis.unwrap();
+    assert!(
+        (post.lufs_integrated + 19.0).abs() < 1.0,
+        "{}",
+        post.lufs_integrated
+    );
+}
+
+#[tokio::test]
+async fn test_game_preset_limits_true_peak() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let wav = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("sfx.wav")),
+        backend: Backend::Basic,
+        preset: Some(Preset::Game),
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    assert_eq!(result.params_applied.unwrap().limiter.ceiling_db, -2.0);
+    let post = result.post_analysis.unwrap();
+    assert!(post.true_peak_db <= -1.9, "{}", post.true_peak_db);
+    assert!(
+        (post.lufs_integrated + 18.0).abs() < 1.0,
+        "{}",
+        post.lufs_integrated
+    );
+}
+
+#[tokio::test]
+async fn test_custom_preset_from_config() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let config: Config = toml::from_str(
+        r#"
+[presets.club]
+description = "Loud club edit"
+target_lufs = -10.0
+ceiling_db = -1.5
+format = "flac"
+
+[presets.fixed]
+target_lufs = -15.0
+
+[presets.fixed.params]
+target_lufs = -14.0
+eq = [{ frequency = 30.0, gain_db = 0.0, q = 0.7, band_type = "high_pass" }]
+compression = { threshold_db = -18.0, ratio = 2.0, attack_ms = 10.0, release_ms = 100.0, knee_db = 6.0, makeup_gain_db = 0.0 }
+limiter = { enabled = true, ceiling_db = -0.5, release_ms = 50.0 }
+stereo = { width = 1.0, balance = 0.0 }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.presets["club"].summary(),
+        "-10 LUFS, -1.5 dBTP, flac"
+    );
+
+    let mut job = MasteringJob {
+        target_lufs: Some(-12.0),
+        ..Default::default()
+    };
+    job.apply_preset("club", &config).unwrap();
+    assert_eq!(job.preset, None);
+    assert_eq!(job.target_lufs, Some(-12.0), "the job's own target wins");
+    assert_eq!(job.resolved_ceiling_db(), Some(-1.5));
+    assert_eq!(job.format, Some(AudioFormat::Flac));
+
+    let mut builtin = MasteringJob::default();
+    builtin.apply_preset("vinyl", &config).unwrap();
+    assert_eq!(builtin.preset, Some(Preset::Vinyl));
+    assert!(builtin.apply_preset("nope", &config).is_err());
+
+    let wav = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let mut job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("fixed.wav")),
+        backend: Backend::Ai,
+        no_cache: true,
+        ..Default::default()
+    };
+    job.apply_preset("fixed", &config).unwrap();
+    assert_eq!(job.resolved_backend(), Backend::Basic);
+    let result = pipeline::run(&job, &config).await.unwrap();
+
+    let params = result.params_applied.unwrap();
+    assert_eq!(params.target_lufs, -15.0);
+    assert_eq!(params.limiter.ceiling_db, -0.5);
+    assert_eq!(params.eq.len(), 1);
+    assert!(!params.de_esser.enabled);
+}
+
+#[tokio::test]
+async fn test_saved_params_round_trip_as_preset() {
+    use mastering_core::config::CustomPreset;
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    // Master once and keep the chain, as `mastering preset create --from-params` does
+    let wav = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("first.wav")),
+        backend: Backend::Basic,
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+    let params = result.params_applied.unwrap();
+
+    let mut config = Config::default();
+    config.presets.insert(
+        "liked".into(),
+        CustomPreset {
+            description: Some("The chain I liked".into()),
+            target_lufs: Some(params.target_lufs),
+            params: Some(params.clone()),
+            ..Default::default()
+        },
+    );
+    let path = dir.path().join("config.toml");
+    config.save_to(&path).unwrap();
+
+    let loaded = Config::load_from(&path).unwrap();
+    let preset = &loaded.presets["liked"];
+    assert_eq!(preset.description.as_deref(), Some("The chain I liked"));
+    let saved = preset.params.as_ref().unwrap();
+    assert_eq!(saved.eq.len(), params.eq.len());
+    assert_eq!(saved.compression.ratio, params.compression.ratio);
+    assert_eq!(saved.limiter.ceiling_db, params.limiter.ceiling_db);
+}
+
+#[tokio::test]
+async fn test_recipe_renders_saved_params_without_backend() {
+    use mastering_core::pipeline::{self, MasteringJob};
+    use mastering_core::recipes::{Recipe, RecipeStore};
+
+    let wav = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let config = Config::default();
+    let first = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("first.wav")),
+        backend: Backend::Basic,
+        target_lufs: Some(-16.0),
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&first, &config).await.unwrap();
+
+    let store = RecipeStore::new(dir.path().join("recipes"));
+    store
+        .save(&Recipe::from_result("quiet", &first.input_path, &result).unwrap())
+        .unwrap();
+    let recipe = store.load("quiet").unwrap();
+    assert_eq!(recipe.source_backend.as_deref(), Some("basic"));
+
+    let mut job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("second.wav")),
+        no_cache: true,
+        ..Default::default()
+    };
+    job.apply_recipe(&recipe, &config);
+    assert_eq!(job.resolved_backend(), Backend::Recipe);
+    assert_eq!(job.target_lufs, Some(-16.0));
+    let again = pipeline::run(&job, &config).await.unwrap();
+
+    assert_eq!(again.backend_used, "recipe");
+    let first_params = result.params_applied.unwrap();
+    let params = again.params_applied.unwrap();
+    assert_eq!(params.target_lufs, first_params.target_lufs);
+    assert_eq!(params.compression.ratio, first_params.compression.ratio);
+    assert_eq!(params.eq.len(), first_params.eq.len());
+
+    let missing = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("third.wav")),
+        backend: Backend::Recipe,
+        no_cache: true,
+        ..Default::default()
+    };
+    assert!(pipeline::run(&missing, &config).await.is_err());
+}
+
+#[tokio::test]
+async fn test_master_with_manual_params() {
+    use mastering_core::pipeline::{self, MasteringJob, ProgressReporter};
+
+    let wav = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let params: MasteringParams = serde_json::from_str(
+        r#"{
+            "eq": [{ "frequency": 120.0, "gain_db": 2.0, "q": 0.7, "band_type": "low_shelf" }],
+            "compression": { "threshold_db": -20.0, "ratio": 100.0, "attack_ms": 10.0,
+                             "release_ms": 120.0, "knee_db": 6.0, "makeup_gain_db": 0.0 },
+            "limiter": { "enabled": true, "ceiling_db": -1.0, "release_ms": 50.0 },
+            "stereo": { "width": 1.0, "balance": 0.0 },
+            "target_lufs": -18.0
+        }"#,
+    )
+    .unwrap();
+    let job = MasteringJob {
+        input_path: wav.path().to_path_buf(),
+        output_path: Some(dir.path().join("manual.wav")),
+        // Ignored: manual parameters never ask a backend
+        backend: Backend::Ai,
+        target_lufs: Some(-9.0),
+        no_cache: true,
+        ..Default::default()
+    };
+    let config = Config::default();
+    let result = pipeline::master_with_params(&job, params, &config, &ProgressReporter::disabled())
+        .await
+        .unwrap();
+
+    assert_eq!(result.backend_used, "basic");
+    let applied = result.params_applied.unwrap();
+    assert_eq!(applied.target_lufs, -18.0);
+    assert_eq!(
+        applied.compression.ratio,
+        config.ai.limits.compression_max_ratio
+    );
+    assert!(result
+        .param_corrections
+        .iter()
+        .any(|c| c.field.contains("ratio")));
+}
+
+#[test]
+fn test_reference_library_tag_picks_latest() {
+    use mastering_core::references::ReferenceLibrary;
+
+    let wav = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let library = ReferenceLibrary::new(dir.path().join("references"));
+    library
+        .add(wav.path(), Some("older"), &["house".into()])
+        .unwrap();
+    let newer = library
+        .add(wav.path(), Some("newer"), &["House".into(), "warm".into()])
+        .unwrap();
+
+    // Re-opening reads the same index
+    let reopened = ReferenceLibrary::new(library.dir());
+    assert_eq!(reopened.list().unwrap().len(), 2);
+    assert_eq!(reopened.with_tag("warm").unwrap(), vec![newer.clone()]);
+    let picked = reopened.pick("house").unwrap();
+    assert_eq!(picked, reopened.path(&newer));
+    assert_eq!(
+        std::fs::read(&picked).unwrap(),
+        std::fs::read(wav.path()).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_match_eq_renders_with_basic_backend() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    // A saved curve falling 12 dB from the lows to the highs
+    let input = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    let curve = dir.path().join("dark.json");
+    std::fs::write(
+        &curve,
+        r#"{ "frequencies": [20.0, 200.0, 2000.0, 20000.0], "levels_db": [6.0, 2.0, -2.0, -6.0] }"#,
+    )
+    .unwrap();
+
+    let job = MasteringJob {
+        input_path: input.path().to_path_buf(),
+        output_path: Some(dir.path().join("matched.wav")),
+        match_eq: Some(curve),
+        format: Some(AudioFormat::Wav),
+        no_cache: true,
+        ..Default::default()
+    };
+    assert_eq!(job.resolved_backend(), Backend::Basic);
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+
+    assert_eq!(result.backend_used, "basic");
+    let eq = result.params_applied.unwrap().eq;
+    let matched: Vec<_> = eq
+        .iter()
+        .filter(|b| !matches!(b.band_type, EqBandType::HighPass))
+        .collect();
+    assert!(!matched.is_empty() && matched.len() <= 6, "{eq:?}");
+    assert!(matched.iter().all(|b| b.gain_db.abs() <= 6.0), "{eq:?}");
+
+    let ai_job = MasteringJob {
+        backend: Backend::Ai,
+        ..job
+    };
+    let err = pipeline::run(&ai_job, &Config::default())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Match EQ"), "{err}");
+}
+
+#[tokio::test]
+async fn test_target_curve_strength_scales_match_eq() {
+    use mastering_core::analysis;
+    use mastering_core::curves::CurveStore;
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    let input = create_test_wav();
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(
+        dir.path().join("bright.csv"),
+        "hz,db\n100,-6\n1000,0\n10000,6\n",
+    )
+    .unwrap();
+    let store = CurveStore::new(dir.path());
+    let target = store.load("bright").unwrap();
+
+    let comparison = analysis::compare_to_curve(input.path(), &target)
+        .await
+        .unwrap();
+    assert_eq!(comparison.frequencies.len(), comparison.deviations_db.len());
+    assert!(comparison.rms_deviation_db > 1.0);
+
+    let job = MasteringJob {
+        input_path: input.path().to_path_buf(),
+        output_path: Some(dir.path().join("curved.wav")),
+        match_eq: Some(store.path("bright").unwrap()),
+        format: Some(AudioFormat::Wav),
+        no_cache: true,
+        ..Default::default()
+    };
+    let full = pipeline::run(&job, &Config::default()).await.unwrap();
+    assert!(full.params_applied.unwrap().eq.len() > 1);
+
+    // At zero strength only the rule-based high-pass is left
+    let job = MasteringJob {
+        match_eq_strength: Some(0.0),
+        ..job
+    };
+    let none = pipeline::run(&job, &Config::default()).await.unwrap();
+    let eq = none.params_applied.unwrap().eq;
+    assert!(
+        eq.iter()
+            .all(|b| matches!(b.band_type, EqBandType::HighPass)),
+        "{eq:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_sections_ride_quiet_and_loud_parts() {
+    use mastering_core::pipeline::{self, MasteringJob};
+
+    // A quiet verse, a loud chorus and a quiet verse
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("song.wav");
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 44100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&input, spec).unwrap();
+    for i in 0..44100 * 60 {
+        let amplitude = if (20..40).contains(&(i / 44100)) {
+            0.5
+        } else {
+            0.05
+        };
+        writer
+            .write_sample((amplitude * (i as f32 * 0.05).sin() * 32767.0) as i16)
+            .unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let job = MasteringJob {
+        input_path: input,
+        output_path: Some(dir.path().join("out.wav")),
+        backend: Backend::Basic,
+        no_cache: true,
+        ..Default::default()
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+    assert_eq!(result.sections.len(), 3, "{:?}", result.sections);
+    assert!(result.sections[0].gain_db > 0.0 && result.sections[1].gain_db < 0.0);
+    assert!(!dir.path().join(".out.sections.wav").exists());
+
+    let job = MasteringJob {
+        no_sections: true,
+        ..job
+    };
+    let result = pipeline::run(&job, &Config::default()).await.unwrap();
+    assert!(result.sections.is_empty());
+}
+
+#[tokio::test]
+async fn test_watch_folder_masters_new_files() {
+    use mastering_core::pipeline::{
+        self, CancellationToken, MasteringJob, WatchEvent, WatchFolder,
+    };
+
+    let input = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    let folder = WatchFolder {
+        settle_secs: 0.2,
+        ..WatchFolder::new(input.path(), output.path())
+    };
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let cancel = CancellationToken::new();
+    let watch = tokio::spawn({
+        let cancel = cancel.clone();
+        async move {
+            let make_job = |path: &std::path::Path| {
+                Ok(MasteringJob {
+                    input_path: path.to_path_buf(),
+                    backend: Backend::Basic,
+                    ..Default::default()
+                })
+            };
+            pipeline::watch_folder(&folder, &Config::default(), make_job, Some(tx), cancel).await
+        }
+    });
+
+    // Give the watcher a moment to start before the file arrives
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    let wav = create_test_wav();
+    std::fs::copy(wav.path(), input.path().join("song.wav")).unwrap();
+
+    let event = tokio::time::timeout(std::time::Duration::from_secs(60), async {
+        loop {
+            let event = rx.recv().await.unwrap();
+            if matches!(
+                event,
+                WatchEvent::Mastered { .. } | WatchEvent::Failed { .. }
+            ) {
+                break event;
+            }
+        }
+    })
+    .await
+    .expect("watch folder did not master the new file");
+    let WatchEvent::Mastered { result, .. } = event else {
+        panic!("mastering failed: {event:?}");
+    };
+    assert_eq!(
+        result.output_path,
+        output
+            .path()
+            .canonicalize()
+            .unwrap()
+            .join("song_mastered.wav")
+    );
+    assert!(result.output_path.exists());
+
+    cancel.cancel();
+    let summary = watch.await.unwrap().unwrap();
+    assert_eq!(summary.mastered, 1);
+    assert_eq!(summary.failed, 0);
+}
+
+#[tokio::test]
+async fn test_run_queue_masters_queued_jobs() {
+    use mastering_core::jobs::{self, JobStatus, JobStore};
+    use mastering_core::pipeline::{CancellationToken, MasteringJob};
+
+    let dir = tempfile::tempdir().unwrap();
+    let store = JobStore::new(dir.path().join("jobs.db"));
+    let wav = create_test_wav();
+    let good = store
+        .enqueue(&MasteringJob {
+            input_path: wav.path().to_path_buf(),
+            output_path: Some(dir.path().join("master.wav")),
+            backend: Backend::Basic,
+            ..Default::default()
+        })
+        .unwrap();
+    let missing = store
+        .enqueue(&MasteringJob {
+            input_path: dir.path().join("missing.wav"),
+            backend: Backend::Basic,
+            ..Default::default()
+        })
+        .unwrap();
+
+    let summary = jobs::run_queue(&store, &Config::default(), None, CancellationToken::new())
+        .await
+        .unwrap();
+    assert_eq!((summary.done, summary.failed), (1, 1));
+
+    let done = store.get(good).unwrap();
+    assert_eq!(done.status, JobStatus::Done);
+    assert_eq!(
+        done.output_path(),
+        Some(dir.path().join("master.wav").as_path())
+    );
+    assert!(done.output_path().unwrap().exists());
+    assert_eq!(store.get(missing).unwrap().status, JobStatus::Failed);
+}
@@ -1,9 +1,10 @@
 #!/usr/bin/env python3
 """
 DSP effects bridge script for the mastering CLI.
-Applies EQ, compression, limiting, and stereo adjustments using pedalboard.
+Applies EQ, de-essing, multiband and single-band compression, limiting, and stereo adjustments using pedalboard.
 Receives a JSON argument with input, output, and mastering parameters.
-Outputs a JSON result to stdout.
+Reports progress as {"progress": percent, "message": ...} lines on stdout,
+followed by a JSON result.
 """
 
 import json
@@ -27,6 +28,7 @@ def main():
     output_path = request.get("output")
     params = request.get("params", {})
     bit_depth = request.get("bit_depth", 24)
+    sample_format = request.get("sample_format", "int")
 
     if not input_path or not output_path:
         print(json.dumps({"error": "Missing required fields: input, output"}))
@@ -37,7 +39,7 @@ def main():
         sys.exit(1)
 
     try:
-        apply_effects(input_path, output_path, params, bit_depth)
+        apply_effects(input_path, output_path, params, bit_depth, sample_format)
         print(json.dumps({
             "output": output_path,
             "message": "DSP effects applied successfully",
@@ -46,7 +48,7 @@ def main():
         # Fallback to soundfile + numpy if pedalboard isn't available
         sys.stderr.write(f"[apply_fx] pedalboard not available ({e}), using numpy fallback\n")
         try:
-            apply_effects_fallback(input_path, output_path, params, bit_depth)
+            apply_effects_fallback(input_path, output_path, params, bit_depth, sample_format)
             print(json.dumps({
                 "output": output_path,
                 "message": "DSP effects applied (numpy fallback)",
@@ -59,7 +61,7 @@ def main():
         sys.exit(1)
 
 
-def apply_effects(input_path, output_path, params, bit_depth):
+def apply_effects(input_path, output_path, params, bit_depth, sample_format):
     """Apply effects using the pedalboard library."""
     from pedalboard import (
         Pedalboard,
@@ -72,6 +74,9 @@ def apply_effects(input_path, output_path, params, bit_depth):
     )
     from pedalboard.io import AudioFile
 
+    # EQ runs before the de-esser and multiband compressor, dynamics after them.
+    # Mid and side bands get boards of their own.
+    eq_boards = {"stereo": Pedalboard(), "mid": Pedalboard(), "side": Pedalboard()}
     board = Pedalboard()
 
     # EQ bands
@@ -80,16 +85,17 @@ def apply_effects(input_path, output_path, params, bit_depth):
         gain = band.get("gain_db", 0)
         q = band.get("q", 0.707)
         band_type = band.get("band_type", "peak")
+        eq_board = eq_boards.get(band.get("channel", "stereo"), eq_boards["stereo"])
 
         if abs(gain) < 0.1:
             continue
 
         if band_type == "low_shelf":
-            board.append(LowShelfFilter(cutoff_frequency_hz=freq, gain_db=gain, q=q))
+            eq_board.append(LowShelfFilter(cutoff_frequency_hz=freq, gain_db=gain, q=q))
         elif band_type == "high_shelf":
-            board.append(HighShelfFilter(cutoff_frequency_hz=freq, gain_db=gain, q=q))
+            eq_board.append(HighShelfFilter(cutoff_frequency_hz=freq, gain_db=gain, q=q))
         elif band_type == "peak":
-            board.append(PeakFilter(cutoff_frequency_hz=freq, gain_db=gain, q=q))
+            eq_board.append(PeakFilter(cutoff_frequency_hz=freq, gain_db=gain, q=q))
 
     # Compression
     comp = params.get("compression", {})
@@ -117,6 +123,7 @@ def apply_effects(input_path, output_path, params, bit_depth):
         ))
 
     # Process
+    _progress(10, "Reading audio")
     with AudioFile(input_path) as f:
         sample_rate = f.samplerate
         audio = f.read(f.frames)
@@ -132,7 +139,15 @@ def apply_effects(input_path, output_path, params, bit_depth):
         audio[1] = mid - side
 
     # Apply the pedalboard chain
-    processed = board(audio, sample_rate)
+    _progress(30, "Applying EQ, compression and limiting")
+    processed = _equalize(audio, sample_rate, eq_boards)
+    de_esser = params.get("de_esser", {})
+    if de_esser.get("enabled", False):
+        processed = _de_ess(processed, sample_rate, de_esser)
+    multiband = params.get("multiband")
+    if multiband:
+        processed = _compress_multiband(processed, sample_rate, multiband)
+    processed = board(processed, sample_rate)
 
     # Loudness normalization toward target LUFS
     target_lufs = params.get("target_lufs", -14.0)
@@ -148,14 +163,93 @@ def apply_effects(input_path, output_path, params, bit_depth):
         processed *= gain_linear
 
     # Write output
-    subtype_map = {16: "PCM_16", 24: "PCM_24", 32: "FLOAT"}
-    subtype = subtype_map.get(bit_depth, "PCM_24")
+    subtype = _subtype(bit_depth, sample_format)
 
     import soundfile as sf
+    _progress(90, "Writing output")
     sf.write(output_path, processed.T, sample_rate, subtype=subtype)
 
 
-def apply_effects_fallback(input_path, output_path, params, bit_depth):
+def _equalize(audio, sample_rate, eq_boards):
+    """Run the stereo EQ board, then the mid and side boards on the mid (L+R)
+    and side (L-R) signals. Mono audio treats mid bands as stereo ones and
+    skips side bands."""
+    processed = eq_boards["stereo"](audio, sample_rate)
+    if audio.shape[0] != 2:
+        return eq_boards["mid"](processed, sample_rate) if len(eq_boards["mid"]) else processed
+    if not len(eq_boards["mid"]) and not len(eq_boards["side"]):
+        return processed
+
+    mid = np.ascontiguousarray((processed[0:1] + processed[1:2]) / 2.0)
+    side = np.ascontiguousarray((processed[0:1] - processed[1:2]) / 2.0)
+    mid = eq_boards["mid"](mid, sample_rate)
+    side = eq_boards["side"](side, sample_rate)
+    return np.concatenate([mid + side, mid - side])
+
+
+def _de_ess(audio, sample_rate, de_esser):
+    """Split-band de-esser: turn the band above the frequency down by as much
+    as it exceeds the threshold, up to max_reduction_db."""
+    from pedalboard import HighpassFilter
+
+    # Filtered forwards and backwards so the band stays in phase with the signal
+    highpass = HighpassFilter(cutoff_frequency_hz=de_esser.get("frequency", 6500.0))
+    highs = highpass(audio, sample_rate)
+    highs = highpass(np.ascontiguousarray(highs[:, ::-1]), sample_rate)[:, ::-1]
+    threshold = de_esser.get("threshold_db", -30.0)
+    max_reduction = de_esser.get("max_reduction_db", 6.0)
+
+    # Linked peak level of the band in 1 ms blocks, released over 60 ms
+    block = max(1, sample_rate // 1000)
+    frames = highs.shape[1]
+    blocks = -(-frames // block)
+    padded = np.pad(np.abs(highs).max(axis=0), (0, blocks * block - frames))
+    peaks = padded.reshape(blocks, block).max(axis=1)
+    release = np.exp(-1.0 / 60.0)
+    envelope = np.empty(blocks)
+    level = 0.0
+    for i, peak in enumerate(peaks):
+        level = peak if peak > level else peak + release * (level - peak)
+        envelope[i] = level
+
+    level_db = 20 * np.log10(np.maximum(envelope, 1e-9))
+    reduction = np.clip(level_db - threshold, 0.0, max_reduction)
+    gain = np.repeat(10 ** (-reduction / 20.0), block)[:frames]
+    return audio + highs * (gain - 1.0)
+
+
+def _compress_multiband(audio, sample_rate, multiband):
+    """Split the signal at the crossovers and compress each band on its own.
+    Each band is split off the rest with a zero-phase low-pass, so the bands
+    sum back to the input."""
+    from pedalboard import Compressor, Gain, LowpassFilter, Pedalboard
+
+    crossovers = multiband.get("crossovers", [])
+    bands = multiband.get("bands", [])[:len(crossovers) + 1]
+    rest = audio
+    out = np.zeros_like(audio)
+    for i, band in enumerate(bands):
+        if i + 1 < len(bands):
+            lowpass = LowpassFilter(cutoff_frequency_hz=crossovers[i])
+            part = lowpass(rest, sample_rate)
+            part = lowpass(np.ascontiguousarray(part[:, ::-1]), sample_rate)[:, ::-1]
+            rest = rest - part
+        else:
+            part = rest
+        board = Pedalboard([
+            Compressor(
+                threshold_db=band.get("threshold_db", -20),
+                ratio=band.get("ratio", 2),
+                attack_ms=band.get("attack_ms", 10),
+                release_ms=band.get("release_ms", 100),
+            ),
+            Gain(gain_db=band.get("makeup_gain_db", 0)),
+        ])
+        out += board(np.ascontiguousarray(part, dtype=np.float32), sample_rate)
+    return out
+
+
+def apply_effects_fallback(input_path, output_path, params, bit_depth, sample_format):
     """Minimal fallback using only numpy and soundfile."""
     import soundfile as sf
 
@@ -192,10 +286,20 @@ def apply_effects_fallback(input_path, output_path, params, bit_depth):
         if peak > ceiling_linear:
             audio *= ceiling_linear / peak
 
-    subtype_map = {16: "PCM_16", 24: "PCM_24", 32: "FLOAT"}
-    subtype = subtype_map.get(bit_depth, "PCM_24")
+    subtype = _subtype(bit_depth, sample_format)
     sf.write(output_path, audio.T, sample_rate, subtype=subtype)
 
 
+def _subtype(bit_depth, sample_format):
+    if sample_format == "float":
+        return "FLOAT"
+    return {16: "PCM_16", 24: "PCM_24", 32: "PCM_32"}.get(bit_depth, "PCM_24")
+
+
+def _progress(percent, message):
+    """Report progress to the mastering CLI as a JSON line on stdout."""
+    print(json.dumps({"progress": percent, "message": message}), flush=True)
+
+
 if __name__ == "__main__":
     main()
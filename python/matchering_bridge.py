@@ -2,13 +2,23 @@
 """
 Matchering bridge script for the mastering CLI.
 Receives a JSON argument with target, reference, output, and options.
-Outputs a JSON result to stdout.
+Reports progress as {"progress": percent, "message": ...} lines on stdout,
+followed by a JSON result.
 """
 
 import json
 import sys
 import os
 
+# Matchering's log messages, in the order it works through them
+STEPS = [
+    ("loading and analysis", 5, "Loading and analyzing"),
+    ("matching levels", 25, "Matching levels"),
+    ("matching frequencies", 45, "Matching frequencies"),
+    ("correcting levels", 65, "Correcting levels"),
+    ("final processing", 85, "Final processing and saving"),
+]
+
 
 def main():
     if len(sys.argv) < 2:
@@ -25,6 +35,7 @@ def main():
     reference = request.get("reference")
     output = request.get("output")
     bit_depth = request.get("bit_depth", 24)
+    sample_format = request.get("sample_format", "int")
     no_limiter = request.get("no_limiter", False)
 
     if not target or not reference or not output:
@@ -42,10 +53,12 @@ def main():
     try:
         import matchering as mg
 
-        mg.log(lambda msg: sys.stderr.write(f"[matchering] {msg}\n"))
+        mg.log(_log)
 
         results = []
-        if bit_depth == 16:
+        if sample_format == "float":
+            results.append(mg.Result(output, subtype="FLOAT"))
+        elif bit_depth == 16:
             results.append(mg.pcm16(output))
         elif bit_depth == 32:
             results.append(mg.pcm32(output))
@@ -91,5 +104,19 @@ def main():
         sys.exit(1)
 
 
+def _log(msg):
+    sys.stderr.write(f"[matchering] {msg}\n")
+    lowered = msg.lower()
+    for key, percent, label in STEPS:
+        if key in lowered:
+            _progress(percent, label)
+            break
+
+
+def _progress(percent, message):
+    """Report progress to the mastering CLI as a JSON line on stdout."""
+    print(json.dumps({"progress": percent, "message": message}), flush=True)
+
+
 if __name__ == "__main__":
     main()
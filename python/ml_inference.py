@@ -2,8 +2,9 @@
 """
 ML inference bridge script for the mastering CLI.
 Runs local machine learning models for audio mastering.
-Receives a JSON argument with input, output, model name, and options.
-Outputs a JSON result to stdout.
+Receives a JSON argument with input, output, model name, device, and options.
+Reports progress as {"progress": percent, "message": ...} lines on stdout,
+followed by a JSON result.
 """
 
 import json
@@ -25,8 +26,11 @@ def main():
     input_path = request.get("input")
     output_path = request.get("output")
     model_name = request.get("model", "deepafx-st")
+    model_dir = request.get("model_dir")
+    device = request.get("device", "cpu")
     reference = request.get("reference")
     bit_depth = request.get("bit_depth", 24)
+    sample_format = request.get("sample_format", "int")
     target_lufs = request.get("target_lufs", -14.0)
 
     if not input_path or not output_path:
@@ -39,14 +43,15 @@ def main():
 
     try:
         if model_name == "deepafx-st":
-            process_deepafx(input_path, output_path, reference, bit_depth, target_lufs)
+            device = process_deepafx(input_path, output_path, reference, device, bit_depth, sample_format, target_lufs)
         else:
-            process_huggingface(input_path, output_path, model_name, bit_depth, target_lufs)
+            device = process_huggingface(input_path, output_path, model_name, model_dir, bit_depth, sample_format, target_lufs)
 
         print(json.dumps({
             "output": output_path,
             "message": f"ML inference completed with model: {model_name}",
             "model": model_name,
+            "device": device,
         }))
     except ImportError as e:
         print(json.dumps({
@@ -59,24 +64,29 @@ def main():
         sys.exit(1)
 
 
-def process_deepafx(input_path, output_path, reference, bit_depth, target_lufs):
+def process_deepafx(input_path, output_path, reference, device, bit_depth, sample_format, target_lufs):
     """
     Process audio using DeepAFx-ST style transfer.
     If the model isn't available locally, falls back to a simple
     neural-style loudness/EQ matching approach.
+    Returns the device the audio was processed on.
     """
     import numpy as np
     import soundfile as sf
 
+    _progress(10, "Reading audio")
     audio, sr = sf.read(input_path, always_2d=True)
 
     # Try loading DeepAFx-ST
     try:
         from deepafx_st.process import process_audio
         if reference and os.path.exists(reference):
+            device = _use_torch_device(device)
+            _progress(30, f"Running DeepAFx-ST style transfer on {device}")
             result = process_audio(input_path, reference)
-            sf.write(output_path, result, sr, subtype=_subtype(bit_depth))
-            return
+            _progress(90, "Writing output")
+            sf.write(output_path, result, sr, subtype=_subtype(bit_depth, sample_format))
+            return device
     except ImportError:
         sys.stderr.write(
             "[ml_inference] DeepAFx-ST not installed. "
@@ -85,6 +95,7 @@ def process_deepafx(input_path, output_path, reference, bit_depth, target_lufs):
         )
 
     # Fallback: basic processing with loudness normalization
+    _progress(40, "Normalizing loudness")
     processed = audio.copy()
 
     # Loudness normalization
@@ -101,12 +112,15 @@ def process_deepafx(input_path, output_path, reference, bit_depth, target_lufs):
     if peak > ceiling:
         processed *= ceiling / peak
 
-    sf.write(output_path, processed, sr, subtype=_subtype(bit_depth))
+    _progress(90, "Writing output")
+    sf.write(output_path, processed, sr, subtype=_subtype(bit_depth, sample_format))
+    return "cpu"
 
 
-def process_huggingface(input_path, output_path, model_name, bit_depth, target_lufs):
+def process_huggingface(input_path, output_path, model_name, model_dir, bit_depth, sample_format, target_lufs):
     """
     Process audio using a HuggingFace model.
+    model_dir holds the weights downloaded by `mastering models download`, if any.
     This is a placeholder for future model integration.
     """
     import numpy as np
@@ -114,10 +128,13 @@ def process_huggingface(input_path, output_path, model_name, bit_depth, target_l
 
     sys.stderr.write(
         f"[ml_inference] HuggingFace model '{model_name}' integration is experimental.\n"
+        f"Weights: {model_dir or 'not downloaded'}\n"
         f"Applying basic loudness normalization as fallback.\n"
     )
 
+    _progress(10, "Reading audio")
     audio, sr = sf.read(input_path, always_2d=True)
+    _progress(40, "Normalizing loudness")
     processed = audio.copy()
 
     # Basic loudness normalization
@@ -133,11 +150,39 @@ def process_huggingface(input_path, output_path, model_name, bit_depth, target_l
     if peak > ceiling:
         processed *= ceiling / peak
 
-    sf.write(output_path, processed, sr, subtype=_subtype(bit_depth))
+    _progress(90, "Writing output")
+    sf.write(output_path, processed, sr, subtype=_subtype(bit_depth, sample_format))
+    return "cpu"
 
 
-def _subtype(bit_depth):
-    return {16: "PCM_16", 24: "PCM_24", 32: "FLOAT"}.get(bit_depth, "PCM_24")
+def _use_torch_device(device):
+    """
+    Make `device` ("cuda", "metal" or "cpu") PyTorch's default device.
+    Returns the device actually used, which is "cpu" when PyTorch cannot
+    reach the requested one.
+    """
+    import torch
+
+    if device == "cuda" and torch.cuda.is_available():
+        torch.set_default_device("cuda")
+        return "cuda"
+    if device == "metal" and torch.backends.mps.is_available():
+        torch.set_default_device("mps")
+        return "metal"
+    if device != "cpu":
+        sys.stderr.write(f"[ml_inference] PyTorch cannot use {device}; running on the CPU\n")
+    return "cpu"
+
+
+def _subtype(bit_depth, sample_format):
+    if sample_format == "float":
+        return "FLOAT"
+    return {16: "PCM_16", 24: "PCM_24", 32: "PCM_32"}.get(bit_depth, "PCM_24")
+
+
+def _progress(percent, message):
+    """Report progress to the mastering CLI as a JSON line on stdout."""
+    print(json.dumps({"progress": percent, "message": message}), flush=True)
 
 
 if __name__ == "__main__":
@@ -0,0 +1,110 @@
+#!/usr/bin/env python3
+"""
+Stem separation script for the mastering CLI.
+Receives a JSON argument with input, output_dir, model, and device.
+Separates the input with Demucs and writes one 32-bit float WAV per stem.
+Reports progress as {"progress": percent, "message": ...} lines on stdout,
+followed by a JSON result mapping stem names to files.
+"""
+
+import json
+import sys
+import os
+
+
+def main():
+    if len(sys.argv) < 2:
+        print(json.dumps({"error": "No arguments provided"}))
+        sys.exit(1)
+
+    try:
+        request = json.loads(sys.argv[1])
+    except json.JSONDecodeError as e:
+        print(json.dumps({"error": f"Invalid JSON: {e}"}))
+        sys.exit(1)
+
+    input_path = request.get("input")
+    output_dir = request.get("output_dir")
+    model = request.get("model", "htdemucs")
+    device = request.get("device", "cpu")
+
+    if not input_path or not output_dir:
+        print(json.dumps({"error": "Missing required fields: input, output_dir"}))
+        sys.exit(1)
+
+    if not os.path.exists(input_path):
+        print(json.dumps({"error": f"Input file not found: {input_path}"}))
+        sys.exit(1)
+
+    try:
+        device, stems = separate(input_path, output_dir, model, device)
+        print(json.dumps({
+            "stems": stems,
+            "device": device,
+            "message": f"Separated {len(stems)} stems with {model}",
+        }))
+    except ImportError as e:
+        print(json.dumps({
+            "error": f"Required package not installed: {e}. Install with: pip install demucs"
+        }))
+        sys.exit(1)
+    except Exception as e:
+        print(json.dumps({"error": str(e)}))
+        sys.exit(1)
+
+
+def separate(input_path, output_dir, model, device):
+    """Separate input_path into output_dir/<stem>.wav. Returns the device used and the stem files."""
+    import soundfile as sf
+    import torch
+    from demucs.apply import apply_model
+    from demucs.audio import convert_audio, save_audio
+    from demucs.pretrained import get_model
+
+    torch_device = _torch_device(torch, device)
+    device = "metal" if torch_device == "mps" else torch_device
+
+    _progress(5, f"Loading {model}")
+    separator = get_model(model)
+    separator.eval()
+
+    _progress(15, "Reading audio")
+    audio, sr = sf.read(input_path, always_2d=True, dtype="float32")
+    wav = convert_audio(torch.from_numpy(audio.T), sr, separator.samplerate, separator.audio_channels)
+
+    # Demucs expects roughly unit-variance input
+    mono = wav.mean(0)
+    mean, std = mono.mean(), mono.std().clamp(min=1e-8)
+    _progress(25, f"Separating stems on {device}")
+    with torch.no_grad():
+        sources = apply_model(separator, ((wav - mean) / std)[None], device=torch_device, split=True, overlap=0.25)[0]
+    sources = sources * std + mean
+
+    _progress(90, "Writing stems")
+    os.makedirs(output_dir, exist_ok=True)
+    stems = {}
+    for name, source in zip(separator.sources, sources):
+        path = os.path.join(output_dir, f"{name}.wav")
+        save_audio(source.cpu(), path, separator.samplerate, clip="none", as_float=True)
+        stems[name] = path
+    return device, stems
+
+
+def _torch_device(torch, device):
+    """The PyTorch device for "cuda", "metal" or "cpu", or "cpu" if it is unavailable."""
+    if device == "cuda" and torch.cuda.is_available():
+        return "cuda"
+    if device == "metal" and torch.backends.mps.is_available():
+        return "mps"
+    if device != "cpu":
+        sys.stderr.write(f"[stem_separation] PyTorch cannot use {device}; running on the CPU\n")
+    return "cpu"
+
+
+def _progress(percent, message):
+    """Report progress to the mastering CLI as a JSON line on stdout."""
+    print(json.dumps({"progress": percent, "message": message}), flush=True)
+
+
+if __name__ == "__main__":
+    main()
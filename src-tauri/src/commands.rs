@@ -1,12 +1,53 @@
+use mastering_core::ab::{self, AbExport, MatchTo};
 use mastering_core::analysis;
 use mastering_core::analysis::decode::decode_audio;
+use mastering_core::analysis::diagnosis::{self, Issue};
+use mastering_core::analysis::match_eq::CurveComparison;
+use mastering_core::analysis::platforms::{self, PlatformPrediction};
+use mastering_core::analysis::vectorscope::{self, Vectorscope};
+use mastering_core::analysis::{loudness, spectrum};
 use mastering_core::backends::MasteringEngine;
-use mastering_core::config::Config;
+use mastering_core::cache;
+use mastering_core::config::{Config, WatchConfig};
+use mastering_core::curves::CurveStore;
 use mastering_core::error::MasteringError;
-use mastering_core::pipeline::{self, MasteringJob};
+use mastering_core::jobs::{self as history, JobFilter, JobRecord, JobStatus, JobStore};
+use mastering_core::metadata::TagOverrides;
+use mastering_core::models::{ModelStatus, ModelStore};
+use mastering_core::peaks::{self, ChannelSelection, Waveform};
+use mastering_core::pipeline::{
+    self, BatchJobStatus, BatchStatusUpdate, CancellationToken, MasteringJob, ParamReview,
+    ProgressReporter, ProgressUpdate, WatchEvent,
+};
+use mastering_core::recipes::{Recipe, RecipeStore};
+use mastering_core::references::ReferenceLibrary;
+use mastering_core::render::{self, WaveformImageOptions};
+use mastering_core::replaygain;
 use mastering_core::types::*;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+/// Event emitted with a [`ProgressEvent`] payload while a job runs.
+pub const PROGRESS_EVENT: &str = "mastering://progress";
+
+/// Event emitted with a [`BatchStatusEvent`] payload as batch jobs change state.
+pub const BATCH_STATUS_EVENT: &str = "mastering://batch-status";
+
+/// Event emitted with a [`ModelDownloadEvent`] payload while a model downloads.
+pub const MODEL_DOWNLOAD_EVENT: &str = "mastering://model-download";
+
+/// Event emitted with a [`WatchEvent`] payload for files in the watch folder.
+pub const WATCH_EVENT: &str = "mastering://watch";
+
+/// Event emitted with a [`JobRecord`] payload when a queued job starts or finishes.
+pub const JOB_EVENT: &str = "mastering://job";
+
+/// Watch folder events kept for [`WatchStatus::recent`].
+const WATCH_HISTORY: usize = 50;
 
 // ---------------------------------------------------------------------------
 // Shared types
@@ -53,6 +94,13 @@ impl From<MasteringError> for ErrorResponse {
             MasteringError::ValidationError { .. } => {
                 ("VALIDATION_ERROR".to_string(), true, false, None)
             }
+            MasteringError::ProcessTimeout { .. } => (
+                "PROCESS_TIMEOUT".to_string(),
+                false,
+                true,
+                Some(err.user_message()),
+            ),
+            MasteringError::Cancelled => ("CANCELLED".to_string(), false, false, None),
             MasteringError::Generic { .. } => ("UNKNOWN_ERROR".to_string(), false, false, None),
         };
 
@@ -89,12 +137,27 @@ pub struct AnalysisResult {
     pub metadata: AudioMetadata,
     pub lufs_integrated: f64,
     pub lufs_short_term_max: f64,
+    pub lufs_momentary_max: f64,
+    pub loudness_range_lu: f64,
     pub rms_db: f64,
     pub peak_db: f64,
     pub true_peak_db: f64,
     pub dynamic_range_db: f64,
     pub stereo_width: f64,
+    pub stereo_balance_db: f64,
+    pub phase_correlation: f64,
+    pub phase_correlation_min: f64,
+    pub out_of_phase_secs: f64,
+    pub mono_compatibility: Option<MonoCompatibility>,
+    pub clipping: ClippingReport,
+    pub noise_floor_db: Option<f64>,
+    pub signal_to_noise_db: Option<f64>,
+    pub silence: SilenceReport,
+    pub tempo_bpm: Option<f64>,
     pub frequency_bands: FrequencyBands,
+    pub spectral: SpectralDescriptors,
+    pub channel_stats: Vec<ChannelStats>,
+    pub ebu_mode: EbuMode,
 }
 
 impl From<AudioAnalysis> for AnalysisResult {
@@ -103,12 +166,27 @@ impl From<AudioAnalysis> for AnalysisResult {
             metadata: a.metadata,
             lufs_integrated: a.lufs_integrated,
             lufs_short_term_max: a.lufs_short_term_max,
+            lufs_momentary_max: a.lufs_momentary_max,
+            loudness_range_lu: a.loudness_range_lu,
             rms_db: a.rms_db,
             peak_db: a.peak_db,
             true_peak_db: a.true_peak_db,
             dynamic_range_db: a.dynamic_range_db,
             stereo_width: a.stereo_width,
+            stereo_balance_db: a.stereo_balance_db,
+            phase_correlation: a.phase_correlation,
+            phase_correlation_min: a.phase_correlation_min,
+            out_of_phase_secs: a.out_of_phase_secs,
+            mono_compatibility: a.mono_compatibility,
+            clipping: a.clipping,
+            noise_floor_db: a.noise_floor_db,
+            signal_to_noise_db: a.signal_to_noise_db,
+            silence: a.silence,
+            tempo_bpm: a.tempo_bpm,
             frequency_bands: a.frequency_bands,
+            spectral: a.spectral,
+            channel_stats: a.channel_stats,
+            ebu_mode: a.ebu_mode,
         }
     }
 }
@@ -119,6 +197,56 @@ pub struct MasterResult {
     pub backend_used: String,
     pub pre_analysis: Option<AnalysisResult>,
     pub post_analysis: Option<AnalysisResult>,
+    pub params_applied: Option<MasteringParams>,
+    pub param_corrections: Vec<ParamCorrection>,
+    pub brief: Option<String>,
+    pub explanation: Option<ParamExplanation>,
+    pub candidates: Vec<ParamCandidate>,
+    pub usage: Option<TokenUsage>,
+    pub device: Option<Device>,
+    pub stem_adjustments: Option<BTreeMap<String, StemAdjustment>>,
+    pub restoration: Option<RestorationReport>,
+    pub polarity_flipped: bool,
+    pub silence_trimmed: Option<TrimmedSilence>,
+    pub excerpt: Option<TimeRange>,
+    pub deliverables: Vec<Deliverable>,
+    pub replaygain: Option<ReplayGain>,
+    pub balance_correction_db: Option<f64>,
+    pub sections: Vec<Section>,
+    /// Expected loudness normalization of the master on streaming platforms.
+    pub platforms: Vec<PlatformPrediction>,
+}
+
+impl From<MasteringResult> for MasterResult {
+    fn from(r: MasteringResult) -> Self {
+        Self {
+            platforms: r
+                .post_analysis
+                .as_ref()
+                .map(platforms::predict)
+                .unwrap_or_default(),
+            output_path: r.output_path.to_string_lossy().to_string(),
+            backend_used: r.backend_used,
+            pre_analysis: r.pre_analysis.map(|a| a.into()),
+            post_analysis: r.post_analysis.map(|a| a.into()),
+            params_applied: r.params_applied,
+            param_corrections: r.param_corrections,
+            brief: r.brief,
+            explanation: r.explanation,
+            candidates: r.candidates,
+            usage: r.usage,
+            device: r.device,
+            stem_adjustments: r.stem_adjustments,
+            restoration: r.restoration,
+            polarity_flipped: r.polarity_flipped,
+            silence_trimmed: r.silence_trimmed,
+            excerpt: r.excerpt,
+            deliverables: r.deliverables,
+            replaygain: r.replaygain,
+            balance_correction_db: r.balance_correction_db,
+            sections: r.sections,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -136,6 +264,8 @@ pub struct BackendDiagnostic {
     pub error: Option<String>,
     pub python_path: String,
     pub scripts_dir: String,
+    /// How long a job may run on this backend before it is killed.
+    pub timeout_secs: Option<u64>,
 }
 
 #[derive(Serialize)]
@@ -143,21 +273,324 @@ pub struct PresetInfo {
     pub name: String,
     pub target_lufs: f64,
     pub description: String,
+    pub fades: Fades,
+    /// The preset turns on speech processing.
+    pub speech: bool,
+    /// Defined in the config rather than built in.
+    pub custom: bool,
 }
 
 #[derive(Deserialize)]
 pub struct MasterRequest {
+    /// May be empty when `stem_inputs` is given; it then only names the output.
+    #[serde(default)]
     pub input_path: String,
+    /// Stem files summed, at their gains, into the input before mastering.
+    #[serde(default)]
+    pub stem_inputs: Vec<StemInput>,
     pub output_path: Option<String>,
     pub reference_path: Option<String>,
+    /// Pick a reference from the library by genre or mood tag instead.
+    #[serde(default)]
+    pub reference_tag: Option<String>,
+    /// Match the reference's tonal balance with EQ bands on the built-in
+    /// DSP chain instead of Matchering.
+    #[serde(default)]
+    pub match_eq: bool,
+    /// Steer the EQ toward this target curve from the curves folder.
+    #[serde(default)]
+    pub target_curve: Option<String>,
+    /// Share of the match EQ or curve difference to correct, 0 to 1.
+    #[serde(default)]
+    pub match_eq_strength: Option<f64>,
     pub backend: Option<String>,
     pub ai_provider: Option<String>,
     pub lmstudio_model: Option<String>,
+    /// Device for the local ML backend; `None` uses the config.
+    #[serde(default)]
+    pub device: Option<Device>,
     pub bit_depth: Option<u16>,
+    /// "int" or "float"; float applies to WAV output only.
+    #[serde(default)]
+    pub sample_format: SampleFormat,
     pub format: Option<String>,
+    /// Bitrate in kbps for lossy formats.
+    pub bitrate_kbps: Option<u32>,
+    /// Extra deliverables rendered next to the main output.
+    #[serde(default)]
+    pub targets: Vec<DeliveryTarget>,
+    /// Output sample rate in Hz; `None` keeps the source rate.
+    pub sample_rate: Option<u32>,
+    /// Dither name, e.g. "tpdf"; `None` uses the config default.
+    pub dither: Option<String>,
+    /// "downmix" or "pass-through" for inputs with more than two channels.
+    pub surround_mode: Option<String>,
+    /// "overwrite", "error" or "version" when the output exists; `None`
+    /// uses the config.
+    #[serde(default)]
+    pub overwrite: Option<String>,
     pub target_lufs: Option<f64>,
     pub preset: Option<String>,
+    /// Saved recipe rendered as it is instead of asking a backend.
+    #[serde(default)]
+    pub recipe: Option<String>,
     pub no_limiter: bool,
+    /// Fade-in length in seconds; `None` uses the preset's.
+    #[serde(default)]
+    pub fade_in_secs: Option<f64>,
+    /// Fade-out length in seconds; `None` uses the preset's.
+    #[serde(default)]
+    pub fade_out_secs: Option<f64>,
+    /// "linear", "log" or "cosine"; `None` uses the preset's.
+    #[serde(default)]
+    pub fade_curve: Option<FadeCurve>,
+    /// Free-form description of the intended sound for the AI backend.
+    pub brief: Option<String>,
+    /// Ask the AI backend to explain its parameter choices.
+    #[serde(default)]
+    pub explain: bool,
+    /// Process for spoken word.
+    #[serde(default)]
+    pub speech: bool,
+    /// Restoration stages to run before mastering.
+    #[serde(default)]
+    pub restoration: RestorationStages,
+    /// Even out the channels of a stereo input that differ in level.
+    #[serde(default)]
+    pub fix_balance: bool,
+    /// Invert the right channel of a stereo input that is out of phase.
+    #[serde(default)]
+    pub fix_polarity: bool,
+    /// Cut leading and trailing silence before mastering.
+    #[serde(default)]
+    pub trim_silence: Option<SilenceTrim>,
+    /// Master with one static chain, without section-aware gain.
+    #[serde(default)]
+    pub no_sections: bool,
+    /// Master only this part of the input.
+    #[serde(default)]
+    pub range: Option<TimeRange>,
+    /// Separate the input into stems and rebalance them before mastering.
+    #[serde(default)]
+    pub stems: bool,
+    /// Gain and EQ per stem; when empty the AI backend suggests them.
+    #[serde(default)]
+    pub stem_adjustments: BTreeMap<String, StemAdjustment>,
+    /// Fail instead of re-limiting output that exceeds the peak ceiling.
+    #[serde(default)]
+    pub strict: bool,
+    /// Write ReplayGain tags measured on the output.
+    #[serde(default)]
+    pub replaygain: bool,
+    /// Tag values to write over those copied from the input.
+    #[serde(default)]
+    pub tags: TagOverrides,
+    /// Never use the network; cloud AI providers are refused or replaced by
+    /// the rules provider.
+    #[serde(default)]
+    pub offline: bool,
+    /// Identifier used by `cancel_job`; defaults to the input path.
+    pub job_id: Option<String>,
+}
+
+/// Feedback on a finished master, sent back with the original request.
+#[derive(Deserialize)]
+pub struct RefineRequest {
+    #[serde(flatten)]
+    pub master: MasterRequest,
+    pub feedback: String,
+    /// `params_applied` of the previous result.
+    pub previous_params: MasteringParams,
+    /// `output_path` of the previous result.
+    pub previous_output: String,
+}
+
+impl MasterRequest {
+    fn job_id(&self) -> String {
+        self.job_id
+            .clone()
+            .unwrap_or_else(|| match self.stem_inputs.first() {
+                Some(stem) if self.input_path.is_empty() => {
+                    stem.path.to_string_lossy().into_owned()
+                }
+                _ => self.input_path.clone(),
+            })
+    }
+}
+
+/// Cancellation tokens of jobs currently running, keyed by job id.
+#[derive(Default)]
+pub struct RunningJobs(Mutex<HashMap<String, CancellationToken>>);
+
+impl RunningJobs {
+    fn register(&self, job_id: &str, token: CancellationToken) {
+        self.0.lock().unwrap().insert(job_id.to_string(), token);
+    }
+
+    fn unregister(&self, job_id: &str) {
+        self.0.lock().unwrap().remove(job_id);
+    }
+
+    fn cancel(&self, job_id: &str) -> bool {
+        match self.0.lock().unwrap().get(job_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Language model usage of this session, totalled over finished jobs.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsageStats {
+    /// Jobs that called a language model.
+    pub jobs: u32,
+    pub total: TokenUsage,
+    /// Totals per backend, e.g. `ai/openai`.
+    pub by_backend: BTreeMap<String, TokenUsage>,
+}
+
+#[derive(Default)]
+pub struct UsageTotals(Mutex<UsageStats>);
+
+impl UsageTotals {
+    fn record(&self, result: &MasteringResult) {
+        let Some(ref usage) = result.usage else {
+            return;
+        };
+        let mut stats = self.0.lock().unwrap();
+        stats.jobs += 1;
+        stats.total.add(usage);
+        stats
+            .by_backend
+            .entry(result.backend_used.clone())
+            .or_default()
+            .add(usage);
+    }
+}
+
+/// Progress payload for [`PROGRESS_EVENT`], tagged with the job's input file.
+#[derive(Clone, Serialize)]
+pub struct ProgressEvent {
+    pub input_path: String,
+    #[serde(flatten)]
+    pub update: ProgressUpdate,
+}
+
+/// Status payload for [`BATCH_STATUS_EVENT`].
+#[derive(Clone, Serialize)]
+pub struct BatchStatusEvent {
+    pub job_id: String,
+    #[serde(flatten)]
+    pub update: BatchStatusUpdate,
+}
+
+/// Progress payload for [`MODEL_DOWNLOAD_EVENT`].
+#[derive(Clone, Serialize)]
+pub struct ModelDownloadEvent {
+    pub name: String,
+    pub file: String,
+    pub bytes_done: u64,
+    pub bytes_total: Option<u64>,
+}
+
+/// What the watch folder has done since it was started.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WatchProgress {
+    /// File being mastered right now.
+    pub current: Option<PathBuf>,
+    pub mastered: usize,
+    pub failed: usize,
+    /// Latest events, newest last.
+    pub recent: VecDeque<WatchEvent>,
+}
+
+impl WatchProgress {
+    fn record(&mut self, event: &WatchEvent) {
+        match event {
+            WatchEvent::Detected { .. } => {}
+            WatchEvent::Started { input_path } => self.current = Some(input_path.clone()),
+            WatchEvent::Mastered { .. } => {
+                self.current = None;
+                self.mastered += 1;
+            }
+            WatchEvent::Failed { .. } => {
+                self.current = None;
+                self.failed += 1;
+            }
+        }
+        if self.recent.len() == WATCH_HISTORY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(event.clone());
+    }
+}
+
+struct ActiveWatch {
+    settings: WatchConfig,
+    /// Seconds since the Unix epoch.
+    started_at: u64,
+    cancel: CancellationToken,
+    progress: Arc<Mutex<WatchProgress>>,
+}
+
+/// The watch folder running in the background, if any.
+#[derive(Default)]
+pub struct WatchService {
+    active: Mutex<Option<ActiveWatch>>,
+    /// Why the last watch stopped on its own.
+    error: Mutex<Option<String>>,
+}
+
+impl WatchService {
+    fn status(&self) -> WatchStatus {
+        let error = self.error.lock().unwrap().clone();
+        match *self.active.lock().unwrap() {
+            Some(ref active) => WatchStatus {
+                running: true,
+                settings: Some(active.settings.clone()),
+                started_at: Some(active.started_at),
+                progress: active.progress.lock().unwrap().clone(),
+                error,
+            },
+            None => WatchStatus {
+                running: false,
+                settings: None,
+                started_at: None,
+                progress: WatchProgress::default(),
+                error,
+            },
+        }
+    }
+
+    fn stop(&self) -> bool {
+        match self.active.lock().unwrap().take() {
+            Some(active) => {
+                active.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct WatchStatus {
+    pub running: bool,
+    pub settings: Option<WatchConfig>,
+    pub started_at: Option<u64>,
+    #[serde(flatten)]
+    pub progress: WatchProgress,
+    pub error: Option<String>,
+}
+
+/// Runs queued jobs in the background, one at a time.
+#[derive(Default)]
+pub struct JobQueue {
+    /// Stops the queue; `None` while it is idle.
+    running: Mutex<Option<CancellationToken>>,
 }
 
 #[derive(Serialize)]
@@ -172,8 +605,20 @@ pub struct BatchResult {
 // Commands
 // ---------------------------------------------------------------------------
 
+/// The time range named by optional start and end times, if either is set.
+fn time_range(start_secs: Option<f64>, end_secs: Option<f64>) -> Option<TimeRange> {
+    (start_secs.is_some() || end_secs.is_some()).then(|| TimeRange {
+        start_secs: start_secs.unwrap_or(0.0),
+        end_secs,
+    })
+}
+
 #[tauri::command]
-pub async fn analyze_file(path: String) -> Result<AnalysisResult, String> {
+pub async fn analyze_file(
+    path: String,
+    start_secs: Option<f64>,
+    end_secs: Option<f64>,
+) -> Result<AnalysisResult, String> {
     let path = PathBuf::from(&path);
 
     // Validate input
@@ -184,19 +629,117 @@ pub async fn analyze_file(path: String) -> Result<AnalysisResult, String> {
         }));
     }
 
-    let result = analysis::analyze_file(&path)
+    let result = match time_range(start_secs, end_secs) {
+        Some(range) => analysis::analyze_file_range(&path, &range).await,
+        None => analysis::analyze_file_cached(&path, Some(cache::global_cache())).await,
+    }
+    .map_err(|e| mastering_error_to_response(e.into()))?;
+    Ok(result.into())
+}
+
+/// Problems found in a file's analysis, most severe first, with suggestions.
+#[tauri::command]
+pub async fn diagnose_audio(path: String) -> Result<Vec<Issue>, String> {
+    let path = PathBuf::from(&path);
+    if !path.exists() {
+        return Err(mastering_error_to_response(MasteringError::FileIo {
+            message: "File not found".to_string(),
+            path: Some(path.clone()),
+        }));
+    }
+
+    let analysis = analysis::analyze_file_cached(&path, Some(cache::global_cache()))
         .await
         .map_err(|e| mastering_error_to_response(e.into()))?;
-    Ok(result.into())
+    Ok(diagnosis::diagnose(&analysis))
 }
 
+/// Min/max/RMS waveform of a time range of `path`, for the mixdown, every
+/// channel, or one channel. Served from the file's cached peaks, so
+/// zooming and scrolling do not decode the audio again.
 #[tauri::command]
 pub async fn get_waveform_data(
     path: String,
     num_points: usize,
-) -> Result<Vec<[f32; 2]>, String> {
+    start_secs: Option<f64>,
+    end_secs: Option<f64>,
+    channels: Option<ChannelSelection>,
+) -> Result<Waveform, String> {
     let path = PathBuf::from(&path);
     let num_points = if num_points == 0 { 1000 } else { num_points };
+    let range = time_range(start_secs, end_secs);
+
+    tokio::task::spawn_blocking(move || {
+        peaks::waveform(
+            &path,
+            num_points,
+            range.as_ref(),
+            channels.unwrap_or_default(),
+            Some(peaks::global_peak_cache()),
+        )
+        .map_err(|e| {
+            mastering_error_to_response(MasteringError::audio_decode_failed(
+                path.display().to_string(),
+                e.to_string(),
+            ))
+        })
+    })
+    .await
+    .map_err(|e| mastering_error_to_response(MasteringError::Generic {
+        message: format!("Task failed: {e}"),
+        source: None,
+    }))?
+}
+
+/// Create a progress reporter whose updates are re-emitted as Tauri events.
+///
+/// The forwarding task ends once the reporter (and all its clones) are dropped.
+fn progress_forwarder(app: &AppHandle, input_path: &str) -> ProgressReporter {
+    let (reporter, mut rx) = ProgressReporter::channel();
+    let app = app.clone();
+    let input_path = input_path.to_string();
+    tauri::async_runtime::spawn(async move {
+        while let Some(update) = rx.recv().await {
+            let event = ProgressEvent {
+                input_path: input_path.clone(),
+                update,
+            };
+            if let Err(e) = app.emit(PROGRESS_EVENT, event) {
+                tracing::warn!("Failed to emit progress event: {e}");
+            }
+        }
+    });
+    reporter
+}
+
+/// Spectrum data for the frontend: an averaged spectrum (for pre/post
+/// overlays) and time-binned frames (for a spectrogram).
+#[derive(Serialize)]
+pub struct SpectrumData {
+    pub sample_rate: u32,
+    pub fft_size: usize,
+    pub hop_size: usize,
+    /// Bin centre frequencies in Hz.
+    pub frequencies: Vec<f64>,
+    /// Averaged power per bin in dB.
+    pub average_db: Vec<f64>,
+    /// Start time of each spectrogram frame in seconds.
+    pub times: Vec<f64>,
+    /// Power per bin in dB for each frame.
+    pub frames: Vec<Vec<f32>>,
+}
+
+#[tauri::command]
+pub async fn get_spectrum_data(
+    path: String,
+    fft_size: Option<usize>,
+    hop_size: Option<usize>,
+    num_frames: Option<usize>,
+) -> Result<SpectrumData, String> {
+    let path = PathBuf::from(&path);
+    let fft_size = fft_size.unwrap_or(2048).clamp(256, 32768);
+    let hop_size = hop_size.unwrap_or(fft_size / 2);
+    let num_frames = num_frames.unwrap_or(400);
 
     tokio::task::spawn_blocking(move || {
         let decoded = decode_audio(&path).map_err(|e| {
@@ -206,39 +749,191 @@ pub async fn get_waveform_data(
             ))
         })?;
 
-        let mono: Vec<f32> = if decoded.channels == 1 {
-            decoded.samples.clone()
-        } else {
-            decoded
-                .samples
-                .chunks(decoded.channels as usize)
-                .map(|frame| frame.iter().sum::<f32>() / decoded.channels as f32)
-                .collect()
-        };
+        let mono = decoded.mono_mixdown();
+        let average = spectrum::compute_spectrum_mono(&mono, decoded.sample_rate, fft_size);
+        let spectrogram = spectrum::compute_spectrogram(
+            &mono,
+            decoded.sample_rate,
+            fft_size,
+            hop_size,
+            Some(num_frames),
+        );
 
-        let total = mono.len();
-        let bucket_size = (total / num_points).max(1);
-        let mut peaks: Vec<[f32; 2]> = Vec::with_capacity(num_points);
+        Ok(SpectrumData {
+            sample_rate: decoded.sample_rate,
+            fft_size: average.fft_size,
+            hop_size: spectrogram.hop_size,
+            frequencies: average.frequencies(),
+            average_db: average.power_db(),
+            times: spectrogram.times,
+            frames: spectrogram.frames,
+        })
+    })
+    .await
+    .map_err(|e| {
+        mastering_error_to_response(MasteringError::Generic {
+            message: format!("Task failed: {e}"),
+            source: None,
+        })
+    })?
+}
 
-        for i in 0..num_points {
-            let start = i * bucket_size;
-            let end = ((i + 1) * bucket_size).min(total);
-            if start >= total {
-                break;
-            }
-            let slice = &mono[start..end];
-            let min = slice.iter().cloned().fold(f32::INFINITY, f32::min);
-            let max = slice.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
-            peaks.push([min, max]);
+/// Predict how streaming platforms will normalize the loudness of a file.
+#[tauri::command]
+pub async fn predict_platforms(path: String) -> Result<Vec<PlatformPrediction>, String> {
+    let path = PathBuf::from(&path);
+    let analysis = analysis::analyze_file_cached(&path, Some(cache::global_cache()))
+        .await
+        .map_err(|e| mastering_error_to_response(e.into()))?;
+    Ok(platforms::predict(&analysis))
+}
+
+/// Render an original and its master at the same loudness, next to the
+/// master unless `output_dir` is given.
+#[tauri::command]
+pub async fn export_ab(
+    original_path: String,
+    master_path: String,
+    output_dir: Option<String>,
+    match_to: Option<MatchTo>,
+) -> Result<AbExport, String> {
+    let (original, master) = (PathBuf::from(&original_path), PathBuf::from(&master_path));
+    if let Some(missing) = [&original, &master].into_iter().find(|p| !p.exists()) {
+        return Err(mastering_error_to_response(MasteringError::FileIo {
+            message: "File not found".to_string(),
+            path: Some(missing.clone()),
+        }));
+    }
+    let output_dir = match output_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => master.parent().map(PathBuf::from).unwrap_or_default(),
+    };
+
+    ab::export(
+        &original,
+        &master,
+        &output_dir,
+        match_to.unwrap_or_default(),
+        Some(cache::global_cache()),
+    )
+    .await
+    .map_err(|e| mastering_error_to_response(e.into()))
+}
+
+/// Render the waveforms of an original and its master, stacked or overlaid,
+/// to a PNG image next to the master unless `output_path` is given.
+#[tauri::command]
+pub async fn export_waveform_image(
+    original_path: String,
+    master_path: Option<String>,
+    output_path: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    overlay: Option<bool>,
+) -> Result<String, String> {
+    let inputs: Vec<PathBuf> = std::iter::once(original_path)
+        .chain(master_path)
+        .map(PathBuf::from)
+        .collect();
+    if let Some(missing) = inputs.iter().find(|p| !p.exists()) {
+        return Err(mastering_error_to_response(MasteringError::FileIo {
+            message: "File not found".to_string(),
+            path: Some(missing.clone()),
+        }));
+    }
+    let output = match output_path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let last = inputs.last().expect("at least the original");
+            let stem = last
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("output");
+            last.with_file_name(format!("{stem}_waveform.png"))
         }
+    };
+    let defaults = WaveformImageOptions::default();
+    let opts = WaveformImageOptions {
+        width: width.unwrap_or(defaults.width).max(1),
+        height: height.unwrap_or(defaults.height).max(1),
+        overlay: overlay.unwrap_or(defaults.overlay),
+    };
 
-        Ok(peaks)
+    tokio::task::spawn_blocking(move || {
+        let paths: Vec<_> = inputs.iter().map(PathBuf::as_path).collect();
+        render::waveform_png(&paths, &output, &opts, Some(peaks::global_peak_cache()))
+            .map(|()| output.display().to_string())
+            .map_err(|e| {
+                mastering_error_to_response(MasteringError::Generic {
+                    message: format!("Rendering the waveform failed: {e}"),
+                    source: None,
+                })
+            })
     })
     .await
-    .map_err(|e| mastering_error_to_response(MasteringError::Generic {
-        message: format!("Task failed: {e}"),
-        source: None,
-    }))?
+    .map_err(|e| {
+        mastering_error_to_response(MasteringError::Generic {
+            message: format!("Task failed: {e}"),
+            source: None,
+        })
+    })?
+}
+
+#[tauri::command]
+pub async fn get_loudness_timeline(
+    path: String,
+    step_secs: Option<f64>,
+) -> Result<loudness::LoudnessTimeline, String> {
+    let path = PathBuf::from(&path);
+    let step_secs = step_secs.unwrap_or(loudness::STEP_SECS).max(0.01);
+
+    tokio::task::spawn_blocking(move || {
+        let decoded = decode_audio(&path).map_err(|e| {
+            mastering_error_to_response(MasteringError::audio_decode_failed(
+                path.display().to_string(),
+                e.to_string(),
+            ))
+        })?;
+        Ok(loudness::loudness_timeline(&decoded, step_secs))
+    })
+    .await
+    .map_err(|e| {
+        mastering_error_to_response(MasteringError::Generic {
+            message: format!("Task failed: {e}"),
+            source: None,
+        })
+    })?
+}
+
+/// Decimated left/right pairs and correlation over time for a vectorscope.
+#[tauri::command]
+pub async fn get_vectorscope_data(
+    path: String,
+    num_points: Option<usize>,
+    window_secs: Option<f64>,
+) -> Result<Vectorscope, String> {
+    let path = PathBuf::from(&path);
+    let num_points = num_points.unwrap_or(4000).max(1);
+    let window_secs = window_secs
+        .unwrap_or(vectorscope::DEFAULT_WINDOW_SECS)
+        .max(0.01);
+
+    tokio::task::spawn_blocking(move || {
+        let decoded = decode_audio(&path).map_err(|e| {
+            mastering_error_to_response(MasteringError::audio_decode_failed(
+                path.display().to_string(),
+                e.to_string(),
+            ))
+        })?;
+        Ok(vectorscope::vectorscope(&decoded, num_points, window_secs))
+    })
+    .await
+    .map_err(|e| {
+        mastering_error_to_response(MasteringError::Generic {
+            message: format!("Task failed: {e}"),
+            source: None,
+        })
+    })?
 }
 
 fn build_job(request: &MasterRequest) -> Result<(MasteringJob, Config), String> {
@@ -264,6 +959,25 @@ fn build_job(request: &MasterRequest) -> Result<(MasteringJob, Config), String>
             config_key: Some("ai_provider".to_string()),
         }))?;
 
+    let reference_path = match request.reference_tag {
+        Some(ref tag) => Some(
+            ReferenceLibrary::open_default()
+                .and_then(|library| library.pick(tag))
+                .map_err(anyhow_error_to_response)?,
+        ),
+        None => request.reference_path.as_ref().map(PathBuf::from),
+    };
+    let (reference_path, match_eq) = if request.match_eq {
+        (None, reference_path)
+    } else if let Some(ref name) = request.target_curve {
+        let path = CurveStore::open_default()
+            .and_then(|store| store.path(name))
+            .map_err(anyhow_error_to_response)?;
+        (reference_path, Some(path))
+    } else {
+        (reference_path, None)
+    };
+
     let format: Option<AudioFormat> = request
         .format
         .as_deref()
@@ -274,39 +988,178 @@ fn build_job(request: &MasterRequest) -> Result<(MasteringJob, Config), String>
             config_key: Some("format".to_string()),
         }))?;
 
-    let preset: Option<Preset> = request
-        .preset
+    let dither: Option<Dither> = request
+        .dither
         .as_deref()
         .map(|s| s.parse())
         .transpose()
-        .map_err(|e| mastering_error_to_response(MasteringError::InvalidConfig {
-            message: format!("Invalid preset: {}", e),
-            config_key: Some("preset".to_string()),
-        }))?;
+        .map_err(|e| {
+            mastering_error_to_response(MasteringError::InvalidConfig {
+                message: format!("Invalid dither: {}", e),
+                config_key: Some("dither".to_string()),
+            })
+        })?;
+
+    let surround_mode: Option<SurroundMode> = request
+        .surround_mode
+        .as_deref()
+        .map(|s| s.parse())
+        .transpose()
+        .map_err(|e| {
+            mastering_error_to_response(MasteringError::InvalidConfig {
+                message: format!("Invalid surround mode: {}", e),
+                config_key: Some("surround_mode".to_string()),
+            })
+        })?;
+
+    let overwrite: Option<OverwritePolicy> = request
+        .overwrite
+        .as_deref()
+        .map(|s| s.parse())
+        .transpose()
+        .map_err(|e| {
+            mastering_error_to_response(MasteringError::InvalidConfig {
+                message: format!("Invalid overwrite policy: {}", e),
+                config_key: Some("overwrite".to_string()),
+            })
+        })?;
 
-    let job = MasteringJob {
+    let mut job = MasteringJob {
         input_path: PathBuf::from(&request.input_path),
+        stem_inputs: request.stem_inputs.clone(),
         output_path: request.output_path.as_ref().map(PathBuf::from),
-        reference_path: request.reference_path.as_ref().map(PathBuf::from),
+        overwrite,
+        reference_path,
+        match_eq,
+        match_eq_strength: request.match_eq_strength,
         backend,
         ai_provider,
         lmstudio_model: request.lmstudio_model.clone(),
+        device: request.device,
         bit_depth: request.bit_depth,
+        sample_format: request.sample_format,
         format,
+        bitrate_kbps: request.bitrate_kbps,
+        targets: request.targets.clone(),
+        sample_rate: request.sample_rate,
+        dither,
+        surround_mode,
         target_lufs: request.target_lufs,
+        ceiling_db: None,
         no_limiter: request.no_limiter,
-        preset,
+        preset: None,
+        params: None,
+        fade_in_secs: request.fade_in_secs,
+        fade_out_secs: request.fade_out_secs,
+        fade_curve: request.fade_curve,
+        brief: request
+            .brief
+            .as_deref()
+            .map(str::trim)
+            .filter(|b| !b.is_empty())
+            .map(String::from),
+        refinement: None,
+        explain: request.explain,
+        review: ParamReview::disabled(),
+        speech: request.speech,
+        restoration: request.restoration,
+        fix_balance: request.fix_balance,
+        fix_polarity: request.fix_polarity,
+        trim_silence: request.trim_silence,
+        no_sections: request.no_sections,
+        range: request.range,
+        preview: false,
+        stems: request.stems,
+        stem_adjustments: request.stem_adjustments.clone(),
         dry_run: false,
+        no_cache: false,
+        offline: request.offline,
+        strict: request.strict,
+        replaygain: request.replaygain,
+        tags: request.tags.clone(),
+        cancel_token: CancellationToken::new(),
     };
+    if let Some(ref name) = request.preset {
+        job.apply_preset(name, &config)
+            .map_err(mastering_error_to_response)?;
+    }
+    if let Some(ref name) = request.recipe {
+        let recipe = recipe_store()?
+            .load(name)
+            .map_err(anyhow_error_to_response)?;
+        job.apply_recipe(&recipe, &config);
+    }
 
     Ok((job, config))
 }
 
 #[tauri::command]
-pub async fn master_file(request: MasterRequest) -> Result<MasterResult, String> {
+pub async fn master_file(
+    app: AppHandle,
+    jobs: State<'_, RunningJobs>,
+    usage: State<'_, UsageTotals>,
+    request: MasterRequest,
+) -> Result<MasterResult, String> {
     let (job, config) = build_job(&request)?;
 
-    // Validate input file exists
+    // Validate input files exist
+    let inputs: Vec<&PathBuf> = if job.stem_inputs.is_empty() {
+        vec![&job.input_path]
+    } else {
+        job.stem_inputs.iter().map(|s| &s.path).collect()
+    };
+    if let Some(missing) = inputs.into_iter().find(|p| !p.exists()) {
+        return Err(mastering_error_to_response(MasteringError::FileIo {
+            message: "Input file not found".to_string(),
+            path: Some(missing.clone()),
+        }));
+    }
+
+    let job_id = request.job_id();
+    jobs.register(&job_id, job.cancel_token.clone());
+    let progress = progress_forwarder(&app, &request.input_path);
+    let result = pipeline::run_with_progress(&job, &config, &progress).await;
+    jobs.unregister(&job_id);
+    history::remember(&job, &result);
+    let result = result.map_err(|e| mastering_error_to_response(e.into()))?;
+    usage.record(&result);
+
+    Ok(result.into())
+}
+
+/// Master only the loudest 30 seconds of a track to audition the settings.
+#[tauri::command]
+pub async fn master_preview(
+    app: AppHandle,
+    jobs: State<'_, RunningJobs>,
+    usage: State<'_, UsageTotals>,
+    request: MasterRequest,
+) -> Result<MasterResult, String> {
+    let (mut job, config) = build_job(&request)?;
+    job.preview = true;
+
+    let job_id = request.job_id();
+    jobs.register(&job_id, job.cancel_token.clone());
+    let progress = progress_forwarder(&app, &request.input_path);
+    let result = pipeline::run_with_progress(&job, &config, &progress).await;
+    jobs.unregister(&job_id);
+    let result = result.map_err(|e| mastering_error_to_response(e.into()))?;
+    usage.record(&result);
+
+    Ok(result.into())
+}
+
+/// Master a track with parameters set by hand in the GUI, rendered through
+/// the same DSP chain the backends use. The request's preset, format and
+/// other options apply; its backend is ignored.
+#[tauri::command]
+pub async fn master_with_params(
+    app: AppHandle,
+    jobs: State<'_, RunningJobs>,
+    request: MasterRequest,
+    params: MasteringParams,
+) -> Result<MasterResult, String> {
+    let (mut job, config) = build_job(&request)?;
     if !job.input_path.exists() {
         return Err(mastering_error_to_response(MasteringError::FileIo {
             message: "Input file not found".to_string(),
@@ -314,51 +1167,79 @@ pub async fn master_file(request: MasterRequest) -> Result<MasterResult, String>
         }));
     }
 
-    let result = pipeline::run(&job, &config)
-        .await
-        .map_err(|e| mastering_error_to_response(e.into()))?;
+    let job_id = request.job_id();
+    jobs.register(&job_id, job.cancel_token.clone());
+    let progress = progress_forwarder(&app, &request.input_path);
+    let result = pipeline::master_with_params(&job, params.clone(), &config, &progress).await;
+    jobs.unregister(&job_id);
+    // Stored with its parameters fixed, so it can be run again as it was
+    job.params = Some(params);
+    job.backend = Backend::Basic;
+    history::remember(&job, &result);
+    let result = result.map_err(|e| mastering_error_to_response(e.into()))?;
 
-    Ok(MasterResult {
-        output_path: result.output_path.to_string_lossy().to_string(),
-        backend_used: result.backend_used,
-        pre_analysis: result.pre_analysis.map(|a| a.into()),
-        post_analysis: result.post_analysis.map(|a| a.into()),
-    })
+    Ok(result.into())
 }
 
+/// Re-master a track with the AI adjusting its previous parameters to feedback.
 #[tauri::command]
-pub async fn master_batch(requests: Vec<MasterRequest>) -> Vec<BatchResult> {
-    let mut results = Vec::with_capacity(requests.len());
+pub async fn refine_master(
+    app: AppHandle,
+    jobs: State<'_, RunningJobs>,
+    usage: State<'_, UsageTotals>,
+    request: RefineRequest,
+) -> Result<MasterResult, String> {
+    let (mut job, config) = build_job(&request.master)?;
+    job.refinement = Some(Refinement {
+        feedback: request.feedback.clone(),
+        previous_params: request.previous_params.clone(),
+        previous_output: PathBuf::from(&request.previous_output),
+    });
 
-    for request in &requests {
-        let path = request.input_path.clone();
+    let job_id = request.master.job_id();
+    jobs.register(&job_id, job.cancel_token.clone());
+    let progress = progress_forwarder(&app, &request.master.input_path);
+    let result = pipeline::run_with_progress(&job, &config, &progress).await;
+    jobs.unregister(&job_id);
+    history::remember(&job, &result);
+    let result = result.map_err(|e| mastering_error_to_response(e.into()))?;
+    usage.record(&result);
+
+    Ok(result.into())
+}
+
+#[tauri::command]
+pub async fn master_batch(
+    app: AppHandle,
+    jobs: State<'_, RunningJobs>,
+    usage: State<'_, UsageTotals>,
+    requests: Vec<MasterRequest>,
+    concurrency: Option<usize>,
+    album_gain: Option<bool>,
+    force: Option<bool>,
+) -> Result<Vec<BatchResult>, String> {
+    let config = Config::load().map_err(|e| mastering_error_to_response(e.into()))?;
+    let concurrency = concurrency.unwrap_or(config.general.batch_concurrency);
+
+    let mut results: Vec<Option<BatchResult>> = requests.iter().map(|_| None).collect();
+    let mut recorded = Vec::new();
+    let mut runnable = Vec::new();
+    let mut positions = Vec::new();
+    for (i, request) in requests.iter().enumerate() {
         match build_job(request) {
-            Ok((job, config)) => match pipeline::run(&job, &config).await {
-                Ok(r) => {
-                    results.push(BatchResult {
-                        path,
-                        success: true,
-                        result: Some(MasterResult {
-                            output_path: r.output_path.to_string_lossy().to_string(),
-                            backend_used: r.backend_used,
-                            pre_analysis: r.pre_analysis.map(|a| a.into()),
-                            post_analysis: r.post_analysis.map(|a| a.into()),
-                        }),
-                        error: None,
-                    });
-                }
-                Err(e) => {
-                    results.push(BatchResult {
-                        path,
-                        success: false,
-                        result: None,
-                        error: Some(format!("{e}")),
-                    });
-                }
-            },
+            Ok((job, job_config)) => {
+                jobs.register(&request.job_id(), job.cancel_token.clone());
+                recorded.push(job.clone());
+                runnable.push((
+                    job,
+                    job_config,
+                    progress_forwarder(&app, &request.input_path),
+                ));
+                positions.push(i);
+            }
             Err(e) => {
-                results.push(BatchResult {
-                    path,
+                results[i] = Some(BatchResult {
+                    path: request.input_path.clone(),
                     success: false,
                     result: None,
                     error: Some(e),
@@ -367,7 +1248,441 @@ pub async fn master_batch(requests: Vec<MasterRequest>) -> Vec<BatchResult> {
         }
     }
 
-    results
+    // Forward per-job status changes to the frontend, noting the jobs that
+    // were already mastered by an earlier run of the batch
+    let job_ids: Vec<String> = positions.iter().map(|&i| requests[i].job_id()).collect();
+    let (status_tx, mut status_rx) = tokio::sync::mpsc::unbounded_channel();
+    let forward_app = app.clone();
+    let forward_ids = job_ids.clone();
+    let forwarder = tauri::async_runtime::spawn(async move {
+        let mut skipped = Vec::new();
+        while let Some(update) = status_rx.recv().await {
+            if update.status == BatchJobStatus::Skipped {
+                skipped.push(update.index);
+            }
+            let event = BatchStatusEvent {
+                job_id: forward_ids[update.index].clone(),
+                update,
+            };
+            if let Err(e) = forward_app.emit(BATCH_STATUS_EVENT, event) {
+                tracing::warn!("Failed to emit batch status event: {e}");
+            }
+        }
+        skipped
+    });
+
+    let mut outcomes = pipeline::run_batch(
+        runnable,
+        concurrency,
+        force.unwrap_or(false),
+        Some(status_tx),
+    )
+    .await;
+    let skipped = forwarder.await.unwrap_or_default();
+    for job_id in &job_ids {
+        jobs.unregister(job_id);
+    }
+
+    // Album gain spans every track that was tagged, so it can only be
+    // computed once the whole batch has finished
+    if album_gain.unwrap_or(false) {
+        let tagged: Vec<&mut MasteringResult> = outcomes
+            .iter_mut()
+            .filter_map(|o| o.as_mut().ok())
+            .filter(|r| r.replaygain.is_some())
+            .collect();
+        let paths: Vec<PathBuf> = tagged.iter().map(|r| r.output_path.clone()).collect();
+        match tokio::task::spawn_blocking(move || replaygain::tag_album(&paths)).await {
+            Ok(Ok(gains)) => {
+                for (result, gain) in tagged.into_iter().zip(gains) {
+                    result.replaygain = Some(gain);
+                }
+            }
+            Ok(Err(e)) => tracing::warn!("Failed to write album gain: {e:#}"),
+            Err(e) => tracing::warn!("Album gain task failed: {e}"),
+        }
+    }
+
+    for (index, ((pos, outcome), job)) in positions
+        .into_iter()
+        .zip(outcomes)
+        .zip(&recorded)
+        .enumerate()
+    {
+        let skipped = skipped.contains(&index);
+        if !skipped {
+            history::remember(job, &outcome);
+        }
+        let path = requests[pos].input_path.clone();
+        results[pos] = Some(match outcome {
+            Ok(r) => {
+                if !skipped {
+                    usage.record(&r);
+                }
+                BatchResult {
+                    path,
+                    success: true,
+                    result: Some(r.into()),
+                    error: None,
+                }
+            }
+            Err(e) => BatchResult {
+                path,
+                success: false,
+                result: None,
+                error: Some(format!("{e}")),
+            },
+        });
+    }
+
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Language model tokens and estimated cost spent since the app started.
+#[tauri::command]
+pub fn get_usage_stats(usage: State<'_, UsageTotals>) -> UsageStats {
+    usage.0.lock().unwrap().clone()
+}
+
+/// Cancel a running job. Returns `false` if no job with that id is running.
+#[tauri::command]
+pub fn cancel_job(jobs: State<'_, RunningJobs>, job_id: String) -> bool {
+    let cancelled = jobs.cancel(&job_id);
+    if cancelled {
+        tracing::info!("Cancellation requested for job {job_id}");
+    }
+    cancelled
+}
+
+/// Whether the watch folder is running and what it has mastered.
+#[tauri::command]
+pub fn get_watch_status(watch: State<'_, WatchService>) -> WatchStatus {
+    watch.status()
+}
+
+/// Start mastering new files in a watch folder in the background, replacing
+/// any watch already running. The settings are saved so the watch resumes
+/// when the app starts again.
+#[tauri::command]
+pub fn start_watch(app: AppHandle, settings: WatchConfig) -> Result<WatchStatus, String> {
+    let mut config = Config::load().map_err(|e| mastering_error_to_response(e.into()))?;
+    spawn_watch(&app, settings.clone(), &config)?;
+    config.watch = WatchConfig {
+        enabled: true,
+        ..settings
+    };
+    config
+        .save()
+        .map_err(|e| mastering_error_to_response(e.into()))?;
+    Ok(app.state::<WatchService>().status())
+}
+
+/// Stop the watch folder. Returns `false` if it was not running.
+#[tauri::command]
+pub fn stop_watch(watch: State<'_, WatchService>) -> Result<bool, String> {
+    let stopped = watch.stop();
+    let mut config = Config::load().map_err(|e| mastering_error_to_response(e.into()))?;
+    if config.watch.enabled {
+        config.watch.enabled = false;
+        config
+            .save()
+            .map_err(|e| mastering_error_to_response(e.into()))?;
+    }
+    if stopped {
+        tracing::info!("Watch folder stopped");
+    }
+    Ok(stopped)
+}
+
+/// Restart the watch folder that was running when the app last quit.
+pub fn resume_watch(app: &AppHandle) {
+    let config = match Config::load() {
+        Ok(config) if config.watch.enabled => config,
+        Ok(_) => return,
+        Err(e) => {
+            tracing::warn!("Not resuming the watch folder: {e}");
+            return;
+        }
+    };
+    if let Err(e) = spawn_watch(app, config.watch.clone(), &config) {
+        tracing::warn!("Failed to resume the watch folder: {e}");
+        *app.state::<WatchService>().error.lock().unwrap() = Some(e);
+    }
+}
+
+fn spawn_watch(app: &AppHandle, settings: WatchConfig, config: &Config) -> Result<(), String> {
+    let invalid = |message: String| {
+        mastering_error_to_response(MasteringError::InvalidConfig {
+            message,
+            config_key: Some("watch".to_string()),
+        })
+    };
+    let Some(folder) = settings.folder.clone() else {
+        return Err(invalid("Choose a folder to watch".to_string()));
+    };
+    if !folder.input_dir.is_dir() {
+        return Err(invalid(format!(
+            "Watch folder not found: {}",
+            folder.input_dir.display()
+        )));
+    }
+    // Fail on an unknown preset now rather than for every new file
+    watch_job(&settings, Path::new("input.wav"), config).map_err(anyhow_error_to_response)?;
+
+    let service = app.state::<WatchService>();
+    service.stop();
+    *service.error.lock().unwrap() = None;
+    let cancel = CancellationToken::new();
+    let progress = Arc::new(Mutex::new(WatchProgress::default()));
+    *service.active.lock().unwrap() = Some(ActiveWatch {
+        settings: settings.clone(),
+        started_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        cancel: cancel.clone(),
+        progress: progress.clone(),
+    });
+    tracing::info!(
+        "Watching {} for new audio files",
+        folder.input_dir.display()
+    );
+
+    // Forward file events to the frontend and the notification centre
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let forward_app = app.clone();
+    let forward_progress = progress.clone();
+    let notify = settings.notify;
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            forward_progress.lock().unwrap().record(&event);
+            if let WatchEvent::Mastered { ref result, .. } = event {
+                forward_app.state::<UsageTotals>().record(result);
+            }
+            if notify {
+                notify_watch_event(&forward_app, &event);
+            }
+            if let Err(e) = forward_app.emit(WATCH_EVENT, &event) {
+                tracing::warn!("Failed to emit watch event: {e}");
+            }
+        }
+    });
+
+    let app = app.clone();
+    let config = config.clone();
+    tauri::async_runtime::spawn(async move {
+        let make_job = |path: &Path| watch_job(&settings, path, &config);
+        let outcome = pipeline::watch_folder(&folder, &config, make_job, Some(tx), cancel).await;
+        let service = app.state::<WatchService>();
+        let mut active = service.active.lock().unwrap();
+        // A newer watch may have replaced this one in the meantime
+        if active
+            .as_ref()
+            .is_some_and(|a| Arc::ptr_eq(&a.progress, &progress))
+        {
+            *active = None;
+        }
+        if let Err(e) = outcome {
+            tracing::warn!("Watch folder stopped: {e:#}");
+            *service.error.lock().unwrap() = Some(format!("{e:#}"));
+        }
+    });
+    Ok(())
+}
+
+fn watch_job(
+    settings: &WatchConfig,
+    input: &Path,
+    config: &Config,
+) -> anyhow::Result<MasteringJob> {
+    let mut job = MasteringJob {
+        input_path: input.to_path_buf(),
+        backend: settings.backend,
+        format: settings.format,
+        ..Default::default()
+    };
+    if let Some(ref name) = settings.preset {
+        job.apply_preset(name, config)?;
+    }
+    Ok(job)
+}
+
+fn notify_watch_event(app: &AppHandle, event: &WatchEvent) {
+    let (title, body) = match event {
+        WatchEvent::Mastered { result, .. } => {
+            let name = result
+                .output_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let levels = result
+                .post_analysis
+                .as_ref()
+                .map(|post| {
+                    format!(
+                        " ({:.1} LUFS, {:.1} dBTP)",
+                        post.lufs_integrated, post.true_peak_db
+                    )
+                })
+                .unwrap_or_default();
+            ("Mastered", format!("{name}{levels}"))
+        }
+        WatchEvent::Failed { input_path, error } => {
+            let name = input_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            ("Mastering failed", format!("{name}: {error}"))
+        }
+        WatchEvent::Detected { .. } | WatchEvent::Started { .. } => return,
+    };
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        tracing::warn!("Failed to show notification: {e}");
+    }
+}
+
+/// Add jobs to the persistent queue and start running it. Returns the ids
+/// of the new jobs.
+#[tauri::command]
+pub fn queue_jobs(app: AppHandle, requests: Vec<MasterRequest>) -> Result<Vec<i64>, String> {
+    let store = JobStore::open_default().map_err(anyhow_error_to_response)?;
+    let mut ids = Vec::with_capacity(requests.len());
+    for request in &requests {
+        let (job, _) = build_job(request)?;
+        ids.push(store.enqueue(&job).map_err(anyhow_error_to_response)?);
+    }
+    run_job_queue(&app);
+    Ok(ids)
+}
+
+/// Queued and finished jobs, newest first.
+#[tauri::command]
+pub fn list_jobs(status: Option<String>, limit: Option<usize>) -> Result<Vec<JobRecord>, String> {
+    let filter = JobFilter {
+        status: status
+            .map(|s| s.parse())
+            .transpose()
+            .map_err(anyhow_error_to_response)?,
+        limit,
+    };
+    JobStore::open_default()
+        .and_then(|store| store.list(&filter))
+        .map_err(anyhow_error_to_response)
+}
+
+/// Queue a failed or cancelled job again.
+#[tauri::command]
+pub fn retry_job(app: AppHandle, id: i64) -> Result<(), String> {
+    JobStore::open_default()
+        .and_then(|store| store.retry(id))
+        .map_err(anyhow_error_to_response)?;
+    run_job_queue(&app);
+    Ok(())
+}
+
+/// Queue an earlier job again with its settings and parameters, on its
+/// own input or on `input_path`. Returns the id of the new job.
+#[tauri::command]
+pub fn remaster_job(
+    app: AppHandle,
+    id: i64,
+    input_path: Option<String>,
+    output_path: Option<String>,
+) -> Result<i64, String> {
+    let store = JobStore::open_default().map_err(anyhow_error_to_response)?;
+    let record = store.get(id).map_err(anyhow_error_to_response)?;
+    let input = input_path.map(PathBuf::from);
+    let source = input.as_deref().unwrap_or(record.input_path());
+    if !source.exists() {
+        return Err(mastering_error_to_response(MasteringError::FileIo {
+            message: "Input file not found".to_string(),
+            path: Some(source.to_path_buf()),
+        }));
+    }
+
+    let mut job = record.remaster(input.as_deref());
+    if let Some(output) = output_path {
+        job.output_path = Some(PathBuf::from(output));
+    }
+    let new_id = store.enqueue(&job).map_err(anyhow_error_to_response)?;
+    run_job_queue(&app);
+    Ok(new_id)
+}
+
+/// Remove a job that is not running from the queue and history.
+#[tauri::command]
+pub fn delete_job(id: i64) -> Result<(), String> {
+    JobStore::open_default()
+        .and_then(|store| store.delete(id))
+        .map_err(anyhow_error_to_response)
+}
+
+/// Queue the jobs the app was running when it last quit again, and run
+/// whatever is queued.
+pub fn resume_job_queue(app: &AppHandle) {
+    match JobStore::open_default().and_then(|store| store.requeue_interrupted()) {
+        Ok(_) => run_job_queue(app),
+        Err(e) => tracing::warn!("Failed to open the job queue: {e:#}"),
+    }
+}
+
+/// Start running queued jobs in the background unless the queue is
+/// running already.
+fn run_job_queue(app: &AppHandle) {
+    let cancel = {
+        let mut running = app.state::<JobQueue>().running.lock().unwrap();
+        if running.is_some() {
+            return;
+        }
+        running.insert(CancellationToken::new()).clone()
+    };
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<JobRecord>();
+    let forward_app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(record) = rx.recv().await {
+            if let Some(ref result) = record.result {
+                forward_app.state::<UsageTotals>().record(result);
+            }
+            if let Err(e) = forward_app.emit(JOB_EVENT, &record) {
+                tracing::warn!("Failed to emit job event: {e}");
+            }
+        }
+    });
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let outcome = match (JobStore::open_default(), Config::load()) {
+                (Ok(store), Ok(config)) => {
+                    history::run_queue(&store, &config, Some(tx.clone()), cancel.clone()).await
+                }
+                (Err(e), _) | (_, Err(e)) => Err(e),
+            };
+            let queue = app.state::<JobQueue>();
+            let mut running = queue.running.lock().unwrap();
+            if let Err(e) = outcome {
+                tracing::warn!("Job queue stopped: {e:#}");
+                *running = None;
+                break;
+            }
+
+            // Jobs queued while the last one finished would otherwise wait
+            // for the next call to start the queue
+            let queued = JobStore::open_default()
+                .and_then(|store| {
+                    store.list(&JobFilter {
+                        status: Some(JobStatus::Queued),
+                        limit: Some(1),
+                    })
+                })
+                .is_ok_and(|jobs| !jobs.is_empty());
+            if !queued || cancel.is_cancelled() {
+                *running = None;
+                break;
+            }
+        }
+    });
 }
 
 #[tauri::command]
@@ -380,9 +1695,27 @@ pub fn get_config() -> Result<serde_json::Value, String> {
 pub fn save_config(config_json: serde_json::Value) -> Result<(), String> {
     let config: Config =
         serde_json::from_value(config_json).map_err(|e| format!("Invalid config: {e}"))?;
+    config
+        .ai
+        .limits
+        .validate()
+        .map_err(mastering_error_to_response)?;
     config.save().map_err(|e| format!("Save error: {e}"))
 }
 
+/// Store an AI provider's API key in the OS keyring, where it takes
+/// precedence over the config file.
+#[tauri::command]
+pub fn store_api_key(provider: String, key: String) -> Result<(), String> {
+    let provider: AiProvider = provider.parse().map_err(|e| {
+        mastering_error_to_response(MasteringError::InvalidConfig {
+            message: format!("Invalid AI provider: {e}"),
+            config_key: Some("ai_provider".to_string()),
+        })
+    })?;
+    mastering_core::secrets::store(provider, &key).map_err(|e| format!("{e:#}"))
+}
+
 #[tauri::command]
 pub async fn check_backends() -> Result<Vec<BackendStatus>, String> {
     let config = Config::load().map_err(|e| format!("Config error: {e}"))?;
@@ -391,6 +1724,8 @@ pub async fn check_backends() -> Result<Vec<BackendStatus>, String> {
         (Backend::Matchering, "Reference-based mastering"),
         (Backend::Ai, "AI-assisted mastering"),
         (Backend::LocalMl, "Local ML models"),
+        (Backend::Basic, "Built-in DSP mastering"),
+        (Backend::Recipe, "Saved recipes"),
     ];
 
     let mut results = Vec::new();
@@ -413,19 +1748,51 @@ pub async fn diagnose_backends() -> Result<Vec<BackendDiagnostic>, String> {
     let scripts_dir = Config::python_scripts_dir();
     let scripts_dir_str = scripts_dir.display().to_string();
 
+    let no_python = String::new();
     let backends = vec![
-        (Backend::Matchering, "Reference-based mastering (Matchering)", &config.backends.matchering.python_path),
-        (Backend::Ai, "AI-assisted mastering (LLM + DSP)", &config.backends.matchering.python_path),
-        (Backend::LocalMl, "Local ML models (DeepAFx-ST)", &config.backends.local_ml.python_path),
+        (
+            Backend::Matchering,
+            "Reference-based mastering (Matchering)",
+            &config.backends.matchering.python_path,
+            Some(config.backends.matchering.timeout_secs),
+        ),
+        (
+            Backend::Ai,
+            "AI-assisted mastering (LLM + DSP)",
+            &config.backends.matchering.python_path,
+            Some(config.backends.dsp_timeout_secs),
+        ),
+        (
+            Backend::LocalMl,
+            "Local ML models (DeepAFx-ST)",
+            &config.backends.local_ml.python_path,
+            Some(config.backends.local_ml.timeout_secs),
+        ),
+        (
+            Backend::Basic,
+            "Built-in DSP mastering (no Python)",
+            &no_python,
+            None,
+        ),
+        (
+            Backend::Recipe,
+            "Saved recipes rendered as they are",
+            &no_python,
+            None,
+        ),
     ];
 
     let mut results = Vec::new();
-    for (backend, description, python_path) in backends {
+    for (backend, description, python_path, timeout_secs) in backends {
         let engine = MasteringEngine::from_config(backend, &config);
         let (available, error) = match engine.check_available().await {
             Ok(true) => (true, None),
             Ok(false) => (false, Some("Backend check returned false. Python dependencies may be missing.".to_string())),
-            Err(e) => (false, Some(format!("{e}"))),
+            // Timeouts carry the setting to change, so explain them in full
+            Err(e) => match e.downcast_ref::<MasteringError>() {
+                Some(err) => (false, Some(err.user_message())),
+                None => (false, Some(format!("{e}"))),
+            },
         };
         results.push(BackendDiagnostic {
             name: backend.to_string(),
@@ -434,36 +1801,41 @@ pub async fn diagnose_backends() -> Result<Vec<BackendDiagnostic>, String> {
             error,
             python_path: python_path.clone(),
             scripts_dir: scripts_dir_str.clone(),
+            timeout_secs,
         });
     }
 
     Ok(results)
 }
 
+/// Built-in presets followed by the custom ones from the config.
 #[tauri::command]
 pub fn get_presets() -> Vec<PresetInfo> {
-    vec![
-        PresetInfo {
-            name: "streaming".into(),
-            target_lufs: Preset::Streaming.target_lufs(),
-            description: Preset::Streaming.description().into(),
-        },
-        PresetInfo {
-            name: "cd".into(),
-            target_lufs: Preset::Cd.target_lufs(),
-            description: Preset::Cd.description().into(),
-        },
-        PresetInfo {
-            name: "vinyl".into(),
-            target_lufs: Preset::Vinyl.target_lufs(),
-            description: Preset::Vinyl.description().into(),
-        },
-        PresetInfo {
-            name: "loud".into(),
-            target_lufs: Preset::Loud.target_lufs(),
-            description: Preset::Loud.description().into(),
-        },
-    ]
+    let mut presets: Vec<PresetInfo> = Preset::ALL
+        .iter()
+        .map(|p| PresetInfo {
+            name: p.to_string(),
+            target_lufs: p.target_lufs(),
+            description: p.description().into(),
+            fades: p.fades(),
+            speech: p.is_speech(),
+            custom: false,
+        })
+        .collect();
+
+    let config = Config::load().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load config for custom presets: {e:#}");
+        Config::default()
+    });
+    presets.extend(config.presets.iter().map(|(name, p)| PresetInfo {
+        name: name.clone(),
+        target_lufs: p.target_lufs.unwrap_or(config.general.target_lufs),
+        description: p.description.clone().unwrap_or_else(|| p.summary()),
+        fades: Fades::default(),
+        speech: false,
+        custom: true,
+    }));
+    presets
 }
 
 // ---------------------------------------------------------------------------
@@ -479,7 +1851,8 @@ pub struct LmStudioStatus {
 #[tauri::command]
 pub async fn lmstudio_status(endpoint: Option<String>) -> Result<LmStudioStatus, String> {
     let endpoint = endpoint.unwrap_or_else(|| "http://localhost:1234/v1".to_string());
-    let running = mastering_core::backends::ai::AiBackend::lmstudio_status(&endpoint)
+    let network = Config::load().unwrap_or_default().ai.network;
+    let running = mastering_core::backends::ai::AiBackend::lmstudio_status(&endpoint, &network)
         .await
         .unwrap_or(false);
     Ok(LmStudioStatus { running, endpoint })
@@ -493,7 +1866,8 @@ pub struct LmStudioModelInfo {
 #[tauri::command]
 pub async fn lmstudio_models(endpoint: Option<String>) -> Result<Vec<LmStudioModelInfo>, String> {
     let endpoint = endpoint.unwrap_or_else(|| "http://localhost:1234/v1".to_string());
-    let models = mastering_core::backends::ai::AiBackend::lmstudio_models(&endpoint)
+    let network = Config::load().unwrap_or_default().ai.network;
+    let models = mastering_core::backends::ai::AiBackend::lmstudio_models(&endpoint, &network)
         .await
         .map_err(|e| format!("Failed to list LM Studio models: {e}"))?;
     Ok(models
@@ -538,3 +1912,162 @@ pub fn detect_vram() -> Result<VramInfo, String> {
         recommendations,
     })
 }
+
+fn model_store() -> Result<ModelStore, String> {
+    ModelStore::default_dir()
+        .map(ModelStore::new)
+        .map_err(anyhow_error_to_response)
+}
+
+#[tauri::command]
+pub fn list_models() -> Result<Vec<ModelStatus>, String> {
+    let config = Config::load().map_err(|e| format!("Config error: {e}"))?;
+    model_store()?
+        .list(&config.backends.local_ml.models)
+        .map_err(anyhow_error_to_response)
+}
+
+/// Download a configured model, emitting [`MODEL_DOWNLOAD_EVENT`] as it goes.
+#[tauri::command]
+pub async fn download_model(app: AppHandle, name: String) -> Result<String, String> {
+    let config = Config::load().map_err(|e| format!("Config error: {e}"))?;
+    let path = model_store()?
+        .download(&name, &config, |file, bytes_done, bytes_total| {
+            let event = ModelDownloadEvent {
+                name: name.clone(),
+                file: file.to_string(),
+                bytes_done,
+                bytes_total,
+            };
+            if let Err(e) = app.emit(MODEL_DOWNLOAD_EVENT, event) {
+                tracing::warn!("Failed to emit model download event: {e}");
+            }
+        })
+        .await
+        .map_err(anyhow_error_to_response)?;
+    Ok(path.display().to_string())
+}
+
+#[tauri::command]
+pub fn remove_model(name: String) -> Result<(), String> {
+    model_store()?
+        .remove(&name)
+        .map_err(anyhow_error_to_response)
+}
+
+fn recipe_store() -> Result<RecipeStore, String> {
+    RecipeStore::open_default().map_err(anyhow_error_to_response)
+}
+
+#[tauri::command]
+pub fn list_recipes() -> Result<Vec<Recipe>, String> {
+    recipe_store()?.list().map_err(anyhow_error_to_response)
+}
+
+/// Save the parameters a finished master applied as a recipe. Returns the
+/// path of the recipe file.
+#[tauri::command]
+pub fn save_recipe(
+    name: String,
+    description: Option<String>,
+    input_path: String,
+    backend_used: String,
+    params: MasteringParams,
+) -> Result<String, String> {
+    let recipe = Recipe {
+        name,
+        description: description.filter(|d| !d.trim().is_empty()),
+        source_backend: Some(backend_used),
+        source_file: std::path::Path::new(&input_path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned()),
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        params,
+    };
+    let path = recipe_store()?
+        .save(&recipe)
+        .map_err(anyhow_error_to_response)?;
+    Ok(path.display().to_string())
+}
+
+#[tauri::command]
+pub fn delete_recipe(name: String) -> Result<(), String> {
+    recipe_store()?
+        .remove(&name)
+        .map_err(anyhow_error_to_response)
+}
+
+/// A library reference with the full path of its file.
+#[derive(Serialize)]
+pub struct ReferenceInfo {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub path: String,
+}
+
+fn reference_library() -> Result<ReferenceLibrary, String> {
+    ReferenceLibrary::open_default().map_err(anyhow_error_to_response)
+}
+
+#[tauri::command]
+pub fn list_references(tag: Option<String>) -> Result<Vec<ReferenceInfo>, String> {
+    let library = reference_library()?;
+    let entries = match tag {
+        Some(ref tag) => library.with_tag(tag),
+        None => library.list(),
+    }
+    .map_err(anyhow_error_to_response)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| ReferenceInfo {
+            path: library.path(&entry).display().to_string(),
+            name: entry.name,
+            tags: entry.tags,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub fn add_reference(
+    path: String,
+    name: Option<String>,
+    tags: Vec<String>,
+) -> Result<ReferenceInfo, String> {
+    let library = reference_library()?;
+    let entry = library
+        .add(std::path::Path::new(&path), name.as_deref(), &tags)
+        .map_err(anyhow_error_to_response)?;
+    Ok(ReferenceInfo {
+        path: library.path(&entry).display().to_string(),
+        name: entry.name,
+        tags: entry.tags,
+    })
+}
+
+#[tauri::command]
+pub fn remove_reference(name: String) -> Result<(), String> {
+    reference_library()?
+        .remove(&name)
+        .map_err(anyhow_error_to_response)
+}
+
+#[tauri::command]
+pub fn list_curves() -> Result<Vec<String>, String> {
+    CurveStore::open_default()
+        .and_then(|store| store.list())
+        .map_err(anyhow_error_to_response)
+}
+
+/// Compare a file's tonal balance with a target curve from the curves folder.
+#[tauri::command]
+pub async fn compare_curve(path: String, curve: String) -> Result<CurveComparison, String> {
+    let target = CurveStore::open_default()
+        .and_then(|store| store.load(&curve))
+        .map_err(anyhow_error_to_response)?;
+    analysis::compare_to_curve(&PathBuf::from(&path), &target)
+        .await
+        .map_err(anyhow_error_to_response)
+}
@@ -13,19 +13,56 @@ pub fn run() {
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_store::Builder::default().build())
+        .plugin(tauri_plugin_notification::init())
+        .manage(commands::RunningJobs::default())
+        .manage(commands::UsageTotals::default())
+        .manage(commands::WatchService::default())
+        .manage(commands::JobQueue::default())
         .invoke_handler(tauri::generate_handler![
             commands::analyze_file,
+            commands::diagnose_audio,
             commands::master_file,
+            commands::master_preview,
+            commands::master_with_params,
+            commands::refine_master,
             commands::master_batch,
+            commands::cancel_job,
+            commands::get_usage_stats,
+            commands::queue_jobs,
+            commands::list_jobs,
+            commands::retry_job,
+            commands::remaster_job,
+            commands::delete_job,
+            commands::get_watch_status,
+            commands::start_watch,
+            commands::stop_watch,
             commands::get_config,
             commands::save_config,
+            commands::store_api_key,
             commands::check_backends,
             commands::diagnose_backends,
             commands::get_presets,
             commands::get_waveform_data,
+            commands::get_spectrum_data,
+            commands::get_loudness_timeline,
+            commands::get_vectorscope_data,
+            commands::export_ab,
+            commands::export_waveform_image,
+            commands::predict_platforms,
             commands::lmstudio_status,
             commands::lmstudio_models,
             commands::detect_vram,
+            commands::list_models,
+            commands::download_model,
+            commands::remove_model,
+            commands::list_recipes,
+            commands::save_recipe,
+            commands::delete_recipe,
+            commands::list_references,
+            commands::add_reference,
+            commands::remove_reference,
+            commands::list_curves,
+            commands::compare_curve,
         ])
         .setup(|app| {
             // Set project dir env var so mastering-core can find python scripts
@@ -34,6 +71,8 @@ pub fn run() {
             }
 
             telemetry::add_breadcrumb("Application started", "lifecycle");
+            commands::resume_job_queue(app.handle());
+            commands::resume_watch(app.handle());
 
             #[cfg(debug_assertions)]
             if let Some(window) = app.get_webview_window("main") {
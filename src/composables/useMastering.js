@@ -1,5 +1,6 @@
 import { reactive, computed, ref } from "vue";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { trackProcessing, trackError, trackFeature } from "./useAnalytics.js";
 
 let trackIdCounter = 0;
@@ -8,22 +9,65 @@ const state = reactive({
   tracks: [],
   selectedTrackId: null,
   referenceFile: null,
+  // Library tag used instead of a reference file, e.g. "techno"
+  referenceTag: "",
   processing: false,
   processingMessage: "",
   processingProgress: 0,
+  // Latest status of the running job, e.g. tokens streamed by the AI model
+  processingDetail: "",
   backends: [],
   presets: [],
+  // Saved parameter sets, from list_recipes
+  recipes: [],
+  // Tagged reference tracks, from list_references
+  references: [],
+  // Names of target tonal-balance curves, from list_curves
+  curves: [],
   config: null,
   error: null,
+  // Language model usage of this session, from get_usage_stats
+  usage: null,
+  // Background watch folder, from get_watch_status
+  watch: null,
+  // Queued and finished jobs, newest first, from list_jobs
+  jobs: [],
 
   // Master options
   selectedBackend: "auto",
   selectedPreset: "streaming",
+  selectedRecipe: "",
   selectedProvider: "ollama",
   bitDepth: 24,
   outputFormat: "wav",
+  sampleRate: null,
+  dither: "tpdf",
+  surroundMode: "downmix",
   targetLufs: -14.0,
+  // Extra deliverables, e.g. { target_lufs: -9, format: "wav", bit_depth: 16 }
+  targets: [],
+  fadeInSecs: 0,
+  fadeOutSecs: 0,
+  fadeCurve: "linear",
   noLimiter: false,
+  strict: false,
+  replaygain: false,
+  // Refuse cloud AI providers and fall back to the rules provider
+  offline: false,
+  fixBalance: false,
+  // Match the reference's tonal balance with EQ only, instead of Matchering
+  matchEq: false,
+  // Target curve the EQ is steered toward, and how far (0 to 1)
+  targetCurve: "",
+  matchStrength: 1.0,
+  fixPolarity: false,
+  trimSilence: false,
+  // Even out quiet and loud sections instead of one static chain
+  sectionAware: true,
+  stems: false,
+  brief: "",
+  explain: false,
+  speech: false,
 
   // LM Studio state
   selectedLmStudioModel: "",
@@ -64,6 +108,90 @@ async function loadBackends() {
   }
 }
 
+async function loadUsageStats() {
+  try {
+    state.usage = await invoke("get_usage_stats");
+  } catch (e) {
+    console.error("Failed to load usage stats:", e);
+  }
+}
+
+let watchListener = null;
+
+async function loadWatchStatus() {
+  try {
+    state.watch = await invoke("get_watch_status");
+  } catch (e) {
+    console.error("Failed to load watch status:", e);
+  }
+  // Refresh on every file the watch folder picks up or finishes
+  if (!watchListener) {
+    watchListener = listen("mastering://watch", async (event) => {
+      if (event.payload.kind === "mastered" || event.payload.kind === "failed") {
+        loadUsageStats();
+      }
+      state.watch = await invoke("get_watch_status");
+    });
+  }
+}
+
+async function startWatch(settings) {
+  state.watch = await invoke("start_watch", { settings });
+  trackFeature("watch_folder");
+  await loadConfig();
+}
+
+async function stopWatch() {
+  await invoke("stop_watch");
+  await loadWatchStatus();
+  await loadConfig();
+}
+
+let jobListener = null;
+
+async function loadJobs() {
+  try {
+    state.jobs = await invoke("list_jobs", { limit: 200 });
+  } catch (e) {
+    console.error("Failed to load jobs:", e);
+  }
+  // Refresh as queued jobs start and finish
+  if (!jobListener) {
+    jobListener = listen("mastering://job", (event) => {
+      if (event.payload.result) loadUsageStats();
+      loadJobs();
+    });
+  }
+}
+
+// Queue the analyzed tracks to be mastered in the background
+async function queueAll() {
+  const requests = analyzedTracks.value.map((track) => buildRequest(track));
+  if (requests.length === 0) return [];
+  const ids = await invoke("queue_jobs", { requests });
+  trackFeature("jobs_queued", String(ids.length));
+  await loadJobs();
+  return ids;
+}
+
+async function retryJob(id) {
+  await invoke("retry_job", { id });
+  await loadJobs();
+}
+
+// Master again with an earlier job's settings, on its input or a new one
+async function remasterJob(id, inputPath = null) {
+  const newId = await invoke("remaster_job", { id, inputPath, outputPath: null });
+  trackFeature("remaster", inputPath ? "new_input" : "same_input");
+  await loadJobs();
+  return newId;
+}
+
+async function deleteJob(id) {
+  await invoke("delete_job", { id });
+  await loadJobs();
+}
+
 async function loadPresets() {
   try {
     state.presets = await invoke("get_presets");
@@ -72,6 +200,61 @@ async function loadPresets() {
   }
 }
 
+async function loadRecipes() {
+  try {
+    state.recipes = await invoke("list_recipes");
+  } catch (e) {
+    console.error("Failed to load recipes:", e);
+  }
+}
+
+// Keep the parameters of a finished master for the recipe backend
+async function saveRecipe(track, name) {
+  if (!track?.result?.params_applied) return null;
+  const path = await invoke("save_recipe", {
+    name,
+    description: null,
+    inputPath: track.path,
+    backendUsed: track.result.backend_used,
+    params: track.result.params_applied,
+  });
+  trackFeature("recipe_saved", track.result.backend_used);
+  await loadRecipes();
+  return path;
+}
+
+async function loadReferences() {
+  try {
+    state.references = await invoke("list_references", { tag: null });
+  } catch (e) {
+    console.error("Failed to load references:", e);
+  }
+}
+
+async function addReference(path, tags, name = null) {
+  await invoke("add_reference", { path, name, tags });
+  trackFeature("reference_added", tags.join(","));
+  await loadReferences();
+}
+
+async function removeReference(name) {
+  await invoke("remove_reference", { name });
+  await loadReferences();
+}
+
+async function loadCurves() {
+  try {
+    state.curves = await invoke("list_curves");
+  } catch (e) {
+    console.error("Failed to load curves:", e);
+  }
+}
+
+// Deviation of a track's tonal balance from a target curve, per band
+async function compareCurve(track, curve) {
+  return await invoke("compare_curve", { path: track.path, curve });
+}
+
 function addTracks(paths) {
   const newPaths = Array.isArray(paths) ? paths : [paths];
   trackFeature("tracks_imported", `${newPaths.length} tracks`);
@@ -84,9 +267,13 @@ function addTracks(paths) {
       name,
       status: "idle",
       analysis: null,
+      diagnosis: [],
       waveform: null,
       result: null,
+      preview: null,
       error: null,
+      progress: 0,
+      progressMessage: "",
     });
   }
   if (!state.selectedTrackId && state.tracks.length > 0) {
@@ -111,6 +298,13 @@ function setReferenceFile(path) {
   if (path) trackFeature("reference_set");
 }
 
+// Waveform of a time range, as { start_secs, end_secs, point_secs, channels:
+// [{ name, points: [[min, max, rms], ...] }] }. `channels` is "mix", "all" or
+// { channel: n }; zooming in only fetches the visible range, from cached peaks.
+async function fetchWaveform(path, { numPoints = 2000, startSecs = null, endSecs = null, channels = "mix" } = {}) {
+  return await invoke("get_waveform_data", { path, numPoints, startSecs, endSecs, channels });
+}
+
 async function analyzeTrack(track) {
   track.status = "analyzing";
   track.error = null;
@@ -118,10 +312,12 @@ async function analyzeTrack(track) {
   try {
     const [analysis, waveform] = await Promise.all([
       invoke("analyze_file", { path: track.path }),
-      invoke("get_waveform_data", { path: track.path, numPoints: 2000 }),
+      fetchWaveform(track.path),
     ]);
     track.analysis = analysis;
-    track.waveform = waveform;
+    track.waveform = waveform.channels[0].points;
+    // Uses the analysis just cached, so it costs no second decode
+    track.diagnosis = await invoke("diagnose_audio", { path: track.path });
     track.status = "analyzed";
     trackProcessing("analysis", "native", Date.now() - start, true);
   } catch (e) {
@@ -157,25 +353,60 @@ async function analyzeSelected() {
 }
 
 function buildRequest(track, outputPath) {
+  const useRecipe = state.selectedBackend === "recipe" && !!state.selectedRecipe;
   return {
     input_path: track.path,
     output_path: outputPath || null,
-    reference_path: state.referenceFile || null,
+    reference_path: state.referenceTag ? null : state.referenceFile || null,
+    reference_tag: state.referenceTag || null,
+    match_eq: state.matchEq && ["auto", "basic"].includes(state.selectedBackend),
+    target_curve: ["auto", "basic"].includes(state.selectedBackend) ? state.targetCurve || null : null,
+    match_eq_strength: state.matchEq || state.targetCurve ? state.matchStrength : null,
     backend: state.selectedBackend,
     ai_provider: state.selectedBackend === "ai" ? state.selectedProvider : null,
     lmstudio_model: state.selectedProvider === "lmstudio" ? state.selectedLmStudioModel || null : null,
     bit_depth: state.bitDepth,
+    // The dialog offers 32-bit as float, matching common WAV deliverables
+    sample_format: state.bitDepth === 32 ? "float" : "int",
     format: state.outputFormat,
-    target_lufs: state.targetLufs,
+    sample_rate: state.sampleRate,
+    dither: state.dither,
+    surround_mode: state.surroundMode,
+    // A recipe keeps the loudness it was saved with
+    target_lufs: useRecipe ? null : state.targetLufs,
+    targets: state.targets,
     preset: state.selectedPreset,
+    recipe: useRecipe ? state.selectedRecipe : null,
+    fade_in_secs: state.fadeInSecs,
+    fade_out_secs: state.fadeOutSecs,
+    fade_curve: state.fadeCurve,
     no_limiter: state.noLimiter,
+    brief: state.selectedBackend === "ai" ? state.brief.trim() || null : null,
+    explain: state.selectedBackend === "ai" && state.explain,
+    speech: state.speech,
+    strict: state.strict,
+    replaygain: state.replaygain,
+    offline: state.offline,
+    fix_balance: state.fixBalance,
+    fix_polarity: state.fixPolarity,
+    // An empty object trims with the default threshold and padding
+    trim_silence: state.trimSilence ? {} : null,
+    no_sections: !state.sectionAware,
+    stems: state.stems,
   };
 }
 
 async function masterTrack(track, outputPath) {
   track.status = "mastering";
   track.error = null;
+  track.progress = 0;
   const start = Date.now();
+  const unlisten = await listen("mastering://progress", (event) => {
+    if (event.payload.input_path !== track.path) return;
+    track.progress = event.payload.percent;
+    track.progressMessage = event.payload.message;
+    state.processingDetail = event.payload.message;
+  });
   try {
     const request = buildRequest(track, outputPath);
     const result = await invoke("master_file", { request });
@@ -183,14 +414,12 @@ async function masterTrack(track, outputPath) {
     track.status = "done";
     trackProcessing("mastering", state.selectedBackend, Date.now() - start, true);
     trackFeature("mastering_complete", result.backend_used);
+    if (result.usage) await loadUsageStats();
     // Update analysis with post if available
     if (result.post_analysis) {
       track.postAnalysis = result.post_analysis;
       try {
-        track.postWaveform = await invoke("get_waveform_data", {
-          path: result.output_path,
-          numPoints: 2000,
-        });
+        track.postWaveform = (await fetchWaveform(result.output_path)).channels[0].points;
       } catch (_) {}
     }
   } catch (e) {
@@ -198,9 +427,148 @@ async function masterTrack(track, outputPath) {
     track.error = `Mastering failed: ${e}`;
     trackProcessing("mastering", state.selectedBackend, Date.now() - start, false);
     trackError("MASTERING_FAILED", e, { backend: state.selectedBackend });
+  } finally {
+    unlisten();
+    state.processingDetail = "";
+  }
+}
+
+// Render hand-set EQ, compressor and limiter settings, skipping the backends
+async function masterWithParams(track, params, outputPath) {
+  track.status = "mastering";
+  track.error = null;
+  track.progress = 0;
+  const unlisten = await listen("mastering://progress", (event) => {
+    if (event.payload.input_path !== track.path) return;
+    track.progress = event.payload.percent;
+    track.progressMessage = event.payload.message;
+  });
+  try {
+    const request = buildRequest(track, outputPath);
+    const result = await invoke("master_with_params", { request, params });
+    track.result = result;
+    track.status = "done";
+    trackFeature("manual_params");
+    if (result.post_analysis) {
+      track.postAnalysis = result.post_analysis;
+      try {
+        track.postWaveform = (await fetchWaveform(result.output_path)).channels[0].points;
+      } catch (_) {}
+    }
+  } catch (e) {
+    track.status = "error";
+    track.error = `Mastering failed: ${e}`;
+    trackError("MANUAL_MASTERING_FAILED", e);
+  } finally {
+    unlisten();
   }
 }
 
+async function previewSelected() {
+  const track = selectedTrack.value;
+  if (!track) return;
+  state.processing = true;
+  state.processingMessage = `Rendering a preview of ${track.name}...`;
+  track.error = null;
+  const start = Date.now();
+  const unlisten = await listen("mastering://progress", (event) => {
+    if (event.payload.input_path !== track.path) return;
+    state.processingDetail = event.payload.message;
+  });
+  try {
+    track.preview = await invoke("master_preview", { request: buildRequest(track) });
+    trackProcessing("preview", state.selectedBackend, Date.now() - start, true);
+    if (track.preview.usage) await loadUsageStats();
+  } catch (e) {
+    track.error = `Preview failed: ${e}`;
+    trackProcessing("preview", state.selectedBackend, Date.now() - start, false);
+    trackError("PREVIEW_FAILED", e, { backend: state.selectedBackend });
+  } finally {
+    unlisten();
+    state.processing = false;
+    state.processingMessage = "";
+    state.processingDetail = "";
+  }
+}
+
+async function exportAb(track) {
+  if (!track?.result) return null;
+  trackFeature("ab_export");
+  return await invoke("export_ab", {
+    originalPath: track.path,
+    masterPath: track.result.output_path,
+  });
+}
+
+// Input and master waveforms as a PNG next to the master, for delivery notes
+async function exportWaveformImage(track, { overlay = false } = {}) {
+  if (!track?.result) return null;
+  trackFeature("waveform_export");
+  return await invoke("export_waveform_image", {
+    originalPath: track.path,
+    masterPath: track.result.output_path,
+    overlay,
+  });
+}
+
+async function refineTrack(track, feedback) {
+  const previous = track.result;
+  if (!previous?.params_applied) return;
+  track.status = "mastering";
+  track.error = null;
+  track.progress = 0;
+  const start = Date.now();
+  const unlisten = await listen("mastering://progress", (event) => {
+    if (event.payload.input_path !== track.path) return;
+    track.progress = event.payload.percent;
+    track.progressMessage = event.payload.message;
+    state.processingDetail = event.payload.message;
+  });
+  try {
+    const request = {
+      // Render next to the previous master instead of overwriting it
+      ...buildRequest(track, previous.output_path.replace(/(\.[^./\\]+)$/, "_refined$1")),
+      backend: "ai",
+      ai_provider: state.selectedProvider,
+      target_lufs: previous.params_applied.target_lufs,
+      feedback,
+      previous_params: previous.params_applied,
+      previous_output: previous.output_path,
+    };
+    const result = await invoke("refine_master", { request });
+    track.result = result;
+    track.status = "done";
+    trackProcessing("refinement", "ai", Date.now() - start, true);
+    trackFeature("refinement_complete", result.backend_used);
+    if (result.usage) await loadUsageStats();
+    if (result.post_analysis) {
+      track.postAnalysis = result.post_analysis;
+      try {
+        track.postWaveform = (await fetchWaveform(result.output_path)).channels[0].points;
+      } catch (_) {}
+    }
+  } catch (e) {
+    // The previous master is still valid; keep it selectable for another try
+    track.status = "done";
+    track.error = `Refinement failed: ${e}`;
+    trackProcessing("refinement", "ai", Date.now() - start, false);
+    trackError("REFINEMENT_FAILED", e);
+  } finally {
+    unlisten();
+    state.processingDetail = "";
+  }
+}
+
+async function refineSelected(feedback) {
+  const track = selectedTrack.value;
+  if (!track || !feedback.trim()) return;
+  state.processing = true;
+  state.processingMessage = `Refining ${track.name}: "${feedback}"`;
+  await refineTrack(track, feedback.trim());
+  state.processing = false;
+  state.processingMessage = "";
+}
+
 async function masterAll() {
   state.processing = true;
   state.error = null;
@@ -261,6 +629,22 @@ export function useMastering() {
     loadConfig,
     loadBackends,
     loadPresets,
+    loadRecipes,
+    saveRecipe,
+    loadReferences,
+    addReference,
+    removeReference,
+    loadCurves,
+    compareCurve,
+    loadUsageStats,
+    loadWatchStatus,
+    loadJobs,
+    queueAll,
+    retryJob,
+    remasterJob,
+    deleteJob,
+    startWatch,
+    stopWatch,
     addTracks,
     removeTrack,
     selectTrack,
@@ -268,9 +652,16 @@ export function useMastering() {
     analyzeTrack,
     analyzeAll,
     analyzeSelected,
+    fetchWaveform,
     masterTrack,
     masterAll,
     masterSelected,
+    masterWithParams,
+    previewSelected,
+    exportAb,
+    exportWaveformImage,
+    refineTrack,
+    refineSelected,
     checkLmStudio,
     clearAll,
   };